@@ -0,0 +1,141 @@
+//! Criterion benches for the library hot paths: JSONL tails, redaction and the research
+//! index scan. Run with `make bench`; the budgets these back are listed in the README.
+//!
+//! `ONE_ENGINE_BENCH_TAIL_GB` sets the size of the sparse thread file used by the tail bench
+//! (default 2). The file is created with `set_len`, so it costs no disk space.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use one_engine::jsonl;
+use one_engine::redact::{redact, redact_value};
+use one_engine::research::{self, IndexOptions};
+use one_engine::tail::split_tail;
+use serde_json::json;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Per-bench scratch dir under `<tmp>/one-engine-bench/`.
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join("one-engine-bench").join(format!(
+        "{}-{}",
+        name,
+        uuid::Uuid::new_v4()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn thread_line(i: usize) -> String {
+    json!({
+        "ts": "2026-01-01T00:00:00Z",
+        "role": if i.is_multiple_of(2) { "user" } else { "assistant" },
+        "run_id": format!("r-{i}"),
+        "content": format!("event {i}: build the wiki for repo-{} and summarise", i % 97),
+    })
+    .to_string()
+}
+
+fn thread_bytes(events: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    for i in 0..events {
+        out.extend_from_slice(thread_line(i).as_bytes());
+        out.push(b'\n');
+    }
+    out
+}
+
+/// A sparse file of `gb` GiB whose last 10k lines are real thread events.
+fn sparse_thread(dir: &Path, gb: u64) -> PathBuf {
+    let path = dir.join("big.jsonl");
+    let tail = thread_bytes(10_000);
+    let mut f = std::fs::File::create(&path).unwrap();
+    f.set_len(gb << 30).unwrap();
+    std::io::Seek::seek(&mut f, std::io::SeekFrom::End(0)).unwrap();
+    f.write_all(b"\n").unwrap();
+    f.write_all(&tail).unwrap();
+    path
+}
+
+fn bench_tail(c: &mut Criterion) {
+    let gb: u64 = std::env::var("ONE_ENGINE_BENCH_TAIL_GB")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(2);
+    let dir = scratch("tail");
+    let path = sparse_thread(&dir, gb);
+
+    let mut g = c.benchmark_group("tail");
+    // The window callers use: graphs (1.2 MB), thread reports and the API (512 KB).
+    for max_bytes in [512 * 1024u64, 1_200_000] {
+        g.throughput(Throughput::Bytes(max_bytes));
+        g.bench_with_input(
+            BenchmarkId::new(format!("jsonl_tail_{gb}gb"), max_bytes),
+            &max_bytes,
+            |b, &max| b.iter(|| jsonl::tail(&path, 800, max).unwrap()),
+        );
+    }
+    let buf = thread_bytes(10_000);
+    g.throughput(Throughput::Bytes(buf.len() as u64));
+    g.bench_function("split_tail_10k_events", |b| {
+        b.iter(|| split_tail(black_box(&buf), true, 800))
+    });
+    g.finish();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn bench_redact(c: &mut Criterion) {
+    let clean: String = (0..2_000).map(thread_line).collect::<Vec<_>>().join("\n");
+    let dirty: String = (0..2_000)
+        .map(|i| {
+            format!(
+                "{} x-api-key: k{i}abcdef sk-{i:0>24} {{\"db_password\":\"p{i}\"}}",
+                thread_line(i)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut g = c.benchmark_group("redact");
+    for (name, text) in [("clean", &clean), ("secrets", &dirty)] {
+        g.throughput(Throughput::Bytes(text.len() as u64));
+        g.bench_function(name, |b| b.iter(|| redact(black_box(text))));
+    }
+    let receipt = json!({
+        "request": {"goal_id": "meta3.chat", "inputs": {"message": dirty.clone(), "token": "t"}},
+        "response": {"reply": clean.clone(), "stdout": dirty.clone()},
+    });
+    g.bench_function("value", |b| {
+        b.iter(|| {
+            let mut v = receipt.clone();
+            redact_value(&mut v)
+        })
+    });
+    g.finish();
+}
+
+fn bench_index_scan(c: &mut Criterion) {
+    let dir = scratch("index");
+    for i in 0..2_000 {
+        let sub = dir.join(format!("d{}", i % 40));
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(
+            sub.join(format!("note-{i}.md")),
+            format!("# Note {i}\n\n{}\n", thread_line(i)),
+        )
+        .unwrap();
+    }
+    let opts = IndexOptions::default();
+    let (prev, _) = research::update_index(&dir, &[], &opts).unwrap();
+
+    let mut g = c.benchmark_group("index_scan");
+    g.sample_size(20);
+    g.bench_function("cold_2k_files", |b| {
+        b.iter(|| research::update_index(&dir, &[], &opts).unwrap())
+    });
+    g.bench_function("warm_2k_files", |b| {
+        b.iter(|| research::update_index(&dir, &prev, &opts).unwrap())
+    });
+    g.finish();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, bench_tail, bench_redact, bench_index_scan);
+criterion_main!(benches);
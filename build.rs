@@ -0,0 +1,9 @@
+// The gRPC surface is optional: with `--features grpc` (tonic, prost and tonic-build) the
+// service stubs are generated from proto/engine.proto; without it there is nothing to do.
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/engine.proto");
+        tonic_build::compile_protos("proto/engine.proto").expect("compile proto/engine.proto");
+    }
+}
@@ -0,0 +1,42 @@
+//! `cargo fuzz run tail_jsonl`: arbitrary bytes through the JSONL tail parser, the way
+//! thread and trace readers use it (split, then parse each line as JSON). The streaming
+//! reverse reader must agree with the in-memory split.
+//!
+//! The two modules are std-only, so they are compiled straight from `src/` rather than
+//! through a dependency on the engine package.
+#![no_main]
+
+#[allow(dead_code)]
+#[path = "../../src/tail.rs"]
+mod tail;
+
+#[allow(dead_code)]
+#[path = "../../src/jsonl.rs"]
+mod jsonl;
+
+use jsonl::RevLines;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+use tail::{split_tail, window_start};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&head, file)) = data.split_first() else {
+        return;
+    };
+    let limit = (head & 0x3f) as usize;
+    let max_bytes = (head as u64) * 7;
+    let (start, truncated) = window_start(file.len() as u64, max_bytes);
+    let lines = split_tail(&file[start as usize..], truncated, limit);
+    assert!(lines.len() <= limit);
+    for line in &lines {
+        assert!(!line.contains('\n'));
+        let _ = serde_json::from_str::<serde_json::Value>(line);
+    }
+    let mut streamed: Vec<String> = RevLines::new(Cursor::new(file), max_bytes)
+        .unwrap()
+        .take(limit)
+        .map(|l| l.unwrap())
+        .collect();
+    streamed.reverse();
+    assert_eq!(streamed, lines);
+});
@@ -1,10 +1,21 @@
 use crate::engine::{
     self,
-    types::{Bits, Manifest, Policy},
+    catalog,
+    correlation,
+    evidence::{ChatEvidence, Evidence},
+    flags,
+    live_log,
+    prompts,
+    secrets,
+    snapshot,
+    types::{Bits, Manifest, Policy, MANIFEST_SCHEMA_VERSION},
+    urls::{self, url_for},
     validate,
 };
-use crate::integrations::{self, AgentGoal, UIState};
-use crate::{meta, nstar};
+pub use crate::engine::validate::{ValidateResp, ValidationResult};
+use crate::{artifacts, bundle};
+use crate::integrations::{self, progress::Phase, run_queue::Priority, AgentGoal, UIState};
+use crate::{context, meta, nstar, nstar_policy};
 use axum::{
     extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
@@ -16,6 +27,9 @@ use axum::{
     Json,
 };
 use once_cell::sync::Lazy;
+use one_engine::atomic;
+use one_engine::jsonl;
+use one_engine::redact::{is_redacted, redact, redact_counted, redact_value};
 use one_engine::research::{self, ResearchArtifact};
 use regex::Regex;
 use schemars::JsonSchema;
@@ -24,10 +38,11 @@ use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
 use std::path::{Path as StdPath, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 use tokio::fs;
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
-use tokio::sync::{broadcast, Mutex};
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt;
 use utoipa::{OpenApi, ToSchema};
@@ -43,14 +58,28 @@ pub struct UserContext {
     pub api_key: String,
     pub quota_remaining: u32,
     pub policy_overrides: Option<Policy>,
+    /// e.g. `policy:write` (may update its own stored policy override).
+    pub permissions: Vec<String>,
+    /// Audit entry of the stored override in effect, if any.
+    pub policy_audit_id: Option<String>,
+    /// Priority of this user's runs when the request names none.
+    pub default_priority: Option<Priority>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct MpayloadCtx {
-    kind: String, // run|chat|dsl
+    kind: String, // run|chat|dsl|tool|mcp
     user_id: Option<String>,
     thread: Option<String>,
     run_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<String>,
+    /// Queue priority the run was accepted with (kept when it is requeued).
+    #[serde(default)]
+    priority: Priority,
+    /// Runs this one waits for (`/run.async` with `depends_on`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dependencies: Option<crate::integrations::run_deps::Dependencies>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,6 +88,8 @@ struct Mpayload {
     inputs: Value,
     policy_effective: Policy,
     policy_request: Option<Policy>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    policy_chain: Vec<PolicyLayer>,
     ctx: MpayloadCtx,
 }
 
@@ -74,17 +105,87 @@ fn default_policy_chat() -> Policy {
     }
 }
 
-fn resolve_policy(kind: &str, user: Option<&UserContext>, req_policy: Option<Policy>) -> Policy {
-    if let Some(p) = req_policy {
-        return p;
-    }
-    if let Some(u) = user.and_then(|u| u.policy_overrides.clone()) {
-        return u;
-    }
-    match kind {
+/// One step of policy resolution, recorded in receipt `request.json` as `policy_chain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PolicyLayer {
+    /// "kind" | "goal" | "user" | "request"
+    source: String,
+    /// Kind name, goal pattern or user id.
+    name: String,
+    /// Full policy (kind/user/request) or the partial override (goal).
+    policy: Value,
+    /// Layers are merged, so this is always true; older receipts have false where a request
+    /// or user policy replaced everything below it.
+    used: bool,
+    /// Policy audit entry behind a stored user override.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    audit_id: Option<String>,
+}
+
+/// Precedence request > user > goal (`goal_policies:`) > kind default. Goal entries patch the
+/// kind default; user and request policies are laid over field by field and may only tighten
+/// `max_risk`, `executor` and `limits` (see `engine::policy::layer_caller`).
+fn resolve_policy_chain(
+    kind: &str,
+    goal_id: &str,
+    user: Option<&UserContext>,
+    req_policy: Option<Policy>,
+) -> (Policy, Vec<PolicyLayer>) {
+    let mut policy = match kind {
         "chat" => default_policy_chat(),
         _ => default_policy_run(),
+    };
+    let mut chain = vec![PolicyLayer {
+        source: "kind".to_string(),
+        name: kind.to_string(),
+        policy: serde_json::to_value(&policy).unwrap_or(Value::Null),
+        used: true,
+        audit_id: None,
+    }];
+    for g in engine::policy::goal_policies_for(goal_id, &engine::policy::load_goal_policies()) {
+        g.policy.apply(&mut policy);
+        chain.push(PolicyLayer {
+            source: "goal".to_string(),
+            name: g.goal.clone(),
+            policy: serde_json::to_value(&g.policy).unwrap_or(Value::Null),
+            used: true,
+            audit_id: None,
+        });
+    }
+    let user_policy = user.and_then(|u| {
+        u.policy_overrides
+            .clone()
+            .map(|p| ("user", u.user_id.clone(), p, u.policy_audit_id.clone()))
+    });
+    for (source, name, p, audit_id) in user_policy
+        .into_iter()
+        .chain(req_policy.map(|p| ("request", "request".to_string(), p, None)))
+    {
+        chain.push(PolicyLayer {
+            source: source.to_string(),
+            name,
+            policy: serde_json::to_value(&p).unwrap_or(Value::Null),
+            used: true,
+            audit_id,
+        });
+        policy = engine::policy::layer_caller(&policy, p);
     }
+    (policy, chain)
+}
+
+fn resolve_policy(kind: &str, goal_id: &str, user: Option<&UserContext>, req_policy: Option<Policy>) -> Policy {
+    resolve_policy_chain(kind, goal_id, user, req_policy).0
+}
+
+/// Precedence request > user default > kind (runs started from chat are `high`, the rest
+/// `normal`).
+fn resolve_priority(kind: &str, user: Option<&UserContext>, requested: Option<Priority>) -> Priority {
+    requested
+        .or_else(|| user.and_then(|u| u.default_priority))
+        .unwrap_or(match kind {
+            "chat" => Priority::High,
+            _ => Priority::Normal,
+        })
 }
 
 impl Default for AppState {
@@ -98,6 +199,9 @@ impl Default for AppState {
                 api_key: "demo-key-123".to_string(),
                 quota_remaining: 1000,
                 policy_overrides: None,
+                permissions: Vec::new(),
+                policy_audit_id: None,
+                default_priority: None,
             },
         );
         users.insert(
@@ -111,32 +215,27 @@ impl Default for AppState {
                     time_ms: 60000,  // Longer timeout
                     max_risk: 0.5,   // Higher risk tolerance
                     tiny_diff_loc: 500,
+                    snapshot: false,
+                    idempotent: false,
+                    max_tool_iterations: 3,
+                    executor: Default::default(),
+                    limits: Default::default(),
                 }),
+                permissions: vec!["policy:write".to_string()],
+                policy_audit_id: None,
+                default_priority: Some(Priority::High),
             },
         );
         Self { users }
     }
 }
 
-// Simple progress bus
-static mut PROGRESS_TX: Option<broadcast::Sender<String>> = None;
-fn progress_tx() -> broadcast::Sender<String> {
-    unsafe {
-        if let Some(tx) = &PROGRESS_TX {
-            tx.clone()
-        } else {
-            let (tx, _rx) = broadcast::channel(100);
-            PROGRESS_TX = Some(tx.clone());
-            tx
-        }
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct ActiveRun {
     pub run_id: String,
     pub goal_id: String,
-    pub status: String, // queued|running
+    pub status: String, // waiting|queued|running
+    pub priority: Priority,
     pub ts: String,
     pub receipt_url: String,
     pub sse_url: String,
@@ -144,7 +243,22 @@ pub struct ActiveRun {
 
 static ACTIVE_RUNS: Lazy<Mutex<HashMap<String, ActiveRun>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
-async fn set_active_run(run_id: &str, goal_id: &str, status: &str) {
+/// Record an in-flight run at `phase`: phases before `start` (and a preemption) list it as
+/// `queued`, later ones as `running`.
+async fn set_active_run(run_id: &str, goal_id: &str, phase: Phase, priority: Priority) {
+    let status = match phase {
+        Phase::Queued | Phase::Init | Phase::Preempted => "queued",
+        _ => "running",
+    };
+    insert_active_run(run_id, goal_id, status, priority).await;
+}
+
+/// A run held back until its parents are terminal; it has not entered the queue yet.
+async fn set_waiting_run(run_id: &str, goal_id: &str, priority: Priority) {
+    insert_active_run(run_id, goal_id, "waiting", priority).await;
+}
+
+async fn insert_active_run(run_id: &str, goal_id: &str, status: &str, priority: Priority) {
     if !is_safe_segment(run_id) {
         return;
     }
@@ -156,9 +270,10 @@ async fn set_active_run(run_id: &str, goal_id: &str, status: &str) {
             run_id: run_id.to_string(),
             goal_id: goal_id.to_string(),
             status: status.to_string(),
+            priority,
             ts,
-            receipt_url: format!("/runs/receipts/{}/RECEIPT.md", run_id),
-            sse_url: format!("/progress.sse?run_id={}", run_id),
+            receipt_url: url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id)),
+            sse_url: url_for(&format!("/progress.sse?run_id={}", run_id)),
         },
     );
 }
@@ -166,17 +281,36 @@ async fn set_active_run(run_id: &str, goal_id: &str, status: &str) {
 async fn clear_active_run(run_id: &str) {
     let mut m = ACTIVE_RUNS.lock().await;
     m.remove(run_id);
+    integrations::progress::close(run_id);
 }
 
-fn emit_progress(run_id: &str, goal_id: &str, phase: &str, extra: serde_json::Value) {
+fn emit_progress(run_id: &str, goal_id: &str, phase: Phase, extra: serde_json::Value) {
     let payload = json!({
         "run_id": run_id,
         "goal_id": goal_id,
         "phase": phase,
         "ts": chrono::Utc::now().to_rfc3339(),
+        "correlation_id": correlation::current(),
+        "eta_s": integrations::progress::eta_s(run_id),
         "extra": extra
     });
-    let _ = progress_tx().send(payload.to_string());
+    integrations::progress::publish_phase(run_id, phase, payload.to_string());
+}
+
+/// Start a run's ETA from its goal's duration history (before its first progress event).
+async fn track_run_eta(run_id: &str, goal_id: &str) {
+    let stats = integrations::run_index::duration_stats(goal_id).await;
+    integrations::progress::track_eta(run_id, stats);
+}
+
+/// Feed a successful run's duration back into its goal's history (before `progress::close`).
+async fn record_run_duration(manifest: &Manifest) {
+    if manifest.evidence.get("actual_success").and_then(|v| v.as_bool()) != Some(true) {
+        return;
+    }
+    if let Some(ms) = integrations::progress::elapsed_ms(&manifest.run_id) {
+        integrations::run_index::record_duration(&manifest.goal_id, ms).await;
+    }
 }
 
 fn extract_api_key(headers: &HeaderMap) -> Option<String> {
@@ -186,12 +320,67 @@ fn extract_api_key(headers: &HeaderMap) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-fn authenticate_user(state: &AppState, api_key: &str) -> Option<UserContext> {
-    state
+/// Runs charged to each user since startup, on every surface (HTTP, gRPC, MCP, chat).
+/// `AppState` is cloned per request, so the count can't live in its `users`.
+static QUOTA_SPENT: Lazy<std::sync::Mutex<HashMap<String, u32>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Looks up the user by key and applies its stored policy override (`PUT /users/{id}/policy`)
+/// and the runs already charged to its quota.
+pub(crate) fn authenticate_user(state: &AppState, api_key: &str) -> Option<UserContext> {
+    let mut user = state
         .users
         .values()
         .find(|user| user.api_key == api_key)
-        .cloned()
+        .cloned()?;
+    if let Some(stored) = integrations::user_policy::load(&user.user_id) {
+        user.policy_overrides = Some(stored.policy);
+        user.policy_audit_id = Some(stored.audit_id);
+    }
+    let spent = QUOTA_SPENT.lock().unwrap_or_else(|e| e.into_inner());
+    let spent = spent.get(&user.user_id).copied().unwrap_or(0);
+    user.quota_remaining = user.quota_remaining.saturating_sub(spent);
+    Some(user)
+}
+
+/// Charge one run to `user`'s quota.
+pub(crate) fn spend_quota(user: &mut UserContext) {
+    let mut spent = QUOTA_SPENT.lock().unwrap_or_else(|e| e.into_inner());
+    *spent.entry(user.user_id.clone()).or_default() += 1;
+    user.quota_remaining = user.quota_remaining.saturating_sub(1);
+}
+
+/// `ONE_ENGINE_ADMIN_KEY` as `x-api-key` (unset = no admin access).
+pub(crate) fn is_admin_key(api_key: &str) -> bool {
+    std::env::var("ONE_ENGINE_ADMIN_KEY")
+        .map(|k| !k.is_empty() && k == api_key)
+        .unwrap_or(false)
+}
+
+/// The admin or a known user; 401 for a missing or unknown key.
+pub(crate) fn require_caller(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<integrations::receipt_acl::Caller, (StatusCode, String)> {
+    use integrations::receipt_acl::Caller;
+    match receipt_caller(state, headers) {
+        Caller::Anonymous if extract_api_key(headers).is_none() => Err((
+            StatusCode::UNAUTHORIZED,
+            "Missing x-api-key header".to_string(),
+        )),
+        Caller::Anonymous => Err((StatusCode::UNAUTHORIZED, "Invalid API key".to_string())),
+        caller => Ok(caller),
+    }
+}
+
+/// Who is asking, for receipt access checks.
+fn receipt_caller(state: &AppState, headers: &HeaderMap) -> integrations::receipt_acl::Caller {
+    use integrations::receipt_acl::Caller;
+    match extract_api_key(headers) {
+        Some(k) if is_admin_key(&k) => Caller::Admin,
+        Some(k) => authenticate_user(state, &k).map_or(Caller::Anonymous, |u| Caller::User(u.user_id)),
+        None => Caller::Anonymous,
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -205,6 +394,8 @@ pub struct UserRunReq {
     #[serde(default)]
     pub inputs: serde_json::Value,
     pub policy: Option<Policy>, // User can override default policy
+    #[serde(default)]
+    pub priority: Option<Priority>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -267,6 +458,21 @@ pub struct AttachRunResp {
     pub summary: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[schema(example = json!({"run_id":"r-demo-123","rating":"down","comment":"missed the second question"}))]
+pub struct FeedbackReq {
+    pub run_id: String,
+    pub rating: integrations::feedback::Rating,
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct FeedbackResp {
+    pub ok: bool,
+    pub feedback: integrations::feedback::Feedback,
+}
+
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct ThreadSummaryResp {
     pub user_id: String,
@@ -289,11 +495,12 @@ pub struct ThreadSummaryResp {
     responses(
         (status = 200, description = "Run completed", body = UserRunResp),
         (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Goal switched off by a flag"),
         (status = 429, description = "Quota exceeded")
     )
 )]
 pub async fn user_run_handler(
-    State(mut state): State<AppState>,
+    State(state): State<AppState>,
     Path(user_id): Path<String>,
     headers: HeaderMap,
     Json(req): Json<UserRunReq>,
@@ -330,18 +537,19 @@ pub async fn user_run_handler(
             .into_response();
     }
 
-    let policy = resolve_policy("run", Some(&user), req.policy.clone());
+    let policy = resolve_policy("run", &req.goal_id, Some(&user), req.policy.clone());
 
     // Namespace goal with user ID to prevent conflicts
     let namespaced_goal = format!("user:{}.{}", user_id, req.goal_id);
     let run_id = format!("r-{}", uuid::Uuid::new_v4());
+    let priority = resolve_priority("run", Some(&user), req.priority);
+    let _slot = integrations::run_queue::Ticket::new(priority, false).acquire().await;
 
-    match run_with_integrations(&namespaced_goal, req.inputs, &policy, &run_id).await {
+    match run_with_quota(Some(user_id.as_str()), &namespaced_goal, req.inputs, &policy, &run_id).await {
         Ok((mut manifest, bits, pr_id, meta2_proposal)) => {
+            emit_progress(&run_id, &namespaced_goal, Phase::Done, json!({}));
             manifest.run_id = run_id;
-            // Decrement quota
-            user.quota_remaining -= 1;
-            state.users.insert(user_id.clone(), user.clone());
+            spend_quota(&mut user);
 
             Json(UserRunResp {
                 user_id: user.user_id,
@@ -353,6 +561,9 @@ pub async fn user_run_handler(
             })
             .into_response()
         }
+        Err(e) if e.is::<flags::GoalDisabled>() => {
+            (axum::http::StatusCode::FORBIDDEN, e.to_string()).into_response()
+        }
         Err(e) => (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response(),
     }
 }
@@ -399,6 +610,34 @@ pub async fn user_status_handler(
     .into_response()
 }
 
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/usage",
+    responses(
+        (status = 200, description = "Disk bytes written by the user's runs in the quota window", body = integrations::disk_quota::UserUsage),
+        (status = 401, description = "Missing or invalid x-api-key")
+    )
+)]
+pub async fn user_usage_handler(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let api_key = match extract_api_key(&headers) {
+        Some(k) => k,
+        None => return unauthorized("Missing x-api-key"),
+    };
+    match authenticate_user(&state, &api_key) {
+        Some(u) if u.user_id == user_id => {}
+        _ => return unauthorized("Invalid user"),
+    };
+    // The first call (and one every few minutes) re-reads the usage ledger.
+    match tokio::task::spawn_blocking(move || integrations::disk_quota::user_usage(&user_id)).await {
+        Ok(usage) => Json(usage).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct UserStatus {
     pub user_id: String,
@@ -406,6 +645,126 @@ pub struct UserStatus {
     pub has_premium_policy: bool,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserPolicyResp {
+    pub user_id: String,
+    /// Override applied to this user's runs (stored, else built-in), if any.
+    pub effective: Option<Policy>,
+    /// "stored" | "builtin" | "none"
+    pub source: String,
+    pub stored: Option<integrations::user_policy::StoredPolicy>,
+    /// Recent changes, newest first.
+    pub history: Vec<integrations::user_policy::PolicyAuditEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserPolicyPutReq {
+    /// New override; `null` clears the stored one (falls back to the built-in override).
+    pub policy: Option<Policy>,
+}
+
+/// Admin key, or the user's own key (`need_write`: plus `policy:write`). Returns the actor.
+fn authorize_user_policy(
+    state: &AppState,
+    headers: &HeaderMap,
+    user_id: &str,
+    need_write: bool,
+) -> Result<String, (StatusCode, String)> {
+    let api_key = extract_api_key(headers)
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Missing x-api-key header".to_string()))?;
+    if is_admin_key(&api_key) {
+        return Ok("admin".to_string());
+    }
+    let user = authenticate_user(state, &api_key)
+        .filter(|u| u.user_id == user_id)
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Invalid API key or user ID".to_string()))?;
+    if need_write && !user.permissions.iter().any(|p| p == "policy:write") {
+        return Err((StatusCode::FORBIDDEN, "policy:write permission required".to_string()));
+    }
+    Ok(user.user_id)
+}
+
+async fn user_policy_view(state: &AppState, user_id: &str) -> UserPolicyResp {
+    let builtin = state.users.get(user_id).and_then(|u| u.policy_overrides.clone());
+    let stored = integrations::user_policy::load(user_id);
+    let (effective, source) = integrations::user_policy::effective(stored.as_ref(), builtin);
+    UserPolicyResp {
+        user_id: user_id.to_string(),
+        effective,
+        source: source.to_string(),
+        stored,
+        history: integrations::user_policy::history(user_id, 20).await,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/policy",
+    params(("user_id" = String, Path, description = "User id")),
+    responses(
+        (status = 200, description = "Effective and stored policy override with recent changes", body = UserPolicyResp),
+        (status = 401, description = "Missing or invalid key"),
+        (status = 404, description = "Unknown user")
+    )
+)]
+pub async fn user_policy_get_handler(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = authorize_user_policy(&state, &headers, &user_id, false) {
+        return e.into_response();
+    }
+    if !state.users.contains_key(&user_id) {
+        return (StatusCode::NOT_FOUND, "Unknown user".to_string()).into_response();
+    }
+    Json(user_policy_view(&state, &user_id).await).into_response()
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/{user_id}/policy",
+    params(("user_id" = String, Path, description = "User id")),
+    request_body = UserPolicyPutReq,
+    responses(
+        (status = 200, description = "Override stored (or cleared) and audited", body = UserPolicyResp),
+        (status = 400, description = "Policy failed validation"),
+        (status = 401, description = "Missing or invalid key"),
+        (status = 403, description = "Self-service without policy:write"),
+        (status = 404, description = "Unknown user")
+    )
+)]
+pub async fn user_policy_put_handler(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<UserPolicyPutReq>,
+) -> impl IntoResponse {
+    let actor = match authorize_user_policy(&state, &headers, &user_id, true) {
+        Ok(a) => a,
+        Err(e) => return e.into_response(),
+    };
+    if !state.users.contains_key(&user_id) {
+        return (StatusCode::NOT_FOUND, "Unknown user".to_string()).into_response();
+    }
+    let before = user_policy_view(&state, &user_id).await.effective;
+    let request_digest = integrations::audit::digest(&req);
+    match integrations::user_policy::set(&user_id, req.policy, before, &actor).await {
+        Ok(entry) => {
+            integrations::audit::record(
+                &actor,
+                "user.policy.set",
+                &user_id,
+                Some(request_digest),
+                json!({ "policy_audit_id": entry.id, "cleared": entry.after.is_none() }),
+            )
+            .await;
+            Json(user_policy_view(&state, &user_id).await).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
 #[schema(example = json!({
     "goal_id": "wiki.generate",
@@ -421,6 +780,15 @@ pub struct RunReq {
     pub policy: Option<Policy>,
     #[serde(default)]
     pub run_id: Option<String>,
+    /// high|normal|low; defaults to the user's default priority, else `normal`.
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    /// Run ids this run waits for (`/run.async` only); it starts once they have all finished.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// What to do when a parent did not succeed: `skip` (default) or `run_anyway`.
+    #[serde(default)]
+    pub on_parent_failure: Option<crate::integrations::run_deps::OnParentFailure>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -477,19 +845,17 @@ static START_TS: Lazy<SystemTime> = Lazy::new(SystemTime::now);
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
 #[schema(example = json!({"suite": "easy"}))]
 pub struct ValidateReq {
+    /// Built-in (easy/hard/adaptive/impossible) or a name from `GET /validate/suites`.
     pub suite: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
-pub struct ValidateResp {
-    pub metacognitive_score: f32,
-    pub results: Vec<ValidationResult>,
-    pub summary: String,
-}
-
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct GoldenReq {
     pub name: String,
+    /// Max cases run concurrently (default `ONE_ENGINE_GOLDEN_PARALLELISM`, else CPU count;
+    /// at most 64).
+    #[serde(default)]
+    pub parallelism: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -500,6 +866,8 @@ pub struct GoldenResp {
     pub failed: usize,
     pub details: Vec<engine::golden::GoldenCase>,
     pub bits: Bits,
+    pub parallelism: usize,
+    pub wall_ms: u64,
 }
 
 // -------- Ruliad kernel artifact serving --------
@@ -509,6 +877,7 @@ fn is_safe_segment(seg: &str) -> bool {
         && !seg.contains('/')
         && !seg.contains('\\')
         && !seg.contains("..")
+        && seg != "."
         && seg
             .chars()
             .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
@@ -532,17 +901,17 @@ fn meta3_root() -> PathBuf {
         .unwrap_or_else(|_| PathBuf::from("."))
 }
 
-fn thread_path(user_id: &str, thread: &str) -> Option<PathBuf> {
+pub(crate) fn thread_path(user_id: &str, thread: &str) -> Option<PathBuf> {
     if !is_safe_segment(user_id) || !is_safe_segment(thread) {
         return None;
     }
-    Some(
-        meta3_root()
-            .join("users")
-            .join(user_id)
-            .join("threads")
-            .join(format!("{thread}.jsonl")),
-    )
+    let path = meta3_root()
+        .join("users")
+        .join(user_id)
+        .join("threads")
+        .join(format!("{thread}.jsonl"));
+    // Readers and appenders share the scratch copy when META3_ROOT is read-only.
+    Some(one_engine::storage::writable_path(&path))
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -553,6 +922,37 @@ struct ThreadEvent {
     run_id: String,
 }
 
+/// One lock per thread file, so concurrent chat requests append (and rotate) one at a time.
+static THREAD_LOCKS: Lazy<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Size at which a thread file is rotated into a `<thread>.jsonl.<n>` segment.
+fn thread_segment_bytes() -> u64 {
+    std::env::var("ONE_ENGINE_THREAD_SEGMENT_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(8 * 1024 * 1024)
+}
+
+async fn thread_lock(path: &StdPath) -> Arc<Mutex<()>> {
+    let mut locks = THREAD_LOCKS.lock().await;
+    if locks.len() > 1024 {
+        // Drop locks nobody holds; they are recreated on the next append.
+        locks.retain(|_, l| Arc::strong_count(l) > 1);
+    }
+    locks.entry(path.to_path_buf()).or_default().clone()
+}
+
+/// Last `limit` events of a thread, across its rotated segments.
+async fn tail_thread_lines(path: &StdPath, limit: usize, max_bytes: u64) -> Result<Vec<String>, String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || jsonl::tail_segments(&path, limit, max_bytes))
+        .await
+        .map_err(|e| format!("tail: {e}"))?
+        .map_err(|e| format!("read: {e}"))
+}
+
 async fn append_thread_event(path: &PathBuf, role: &str, content: &str, run_id: &str) {
     let ts = chrono::Utc::now().to_rfc3339();
     let ev = ThreadEvent {
@@ -569,23 +969,17 @@ async fn append_thread_event(path: &PathBuf, role: &str, content: &str, run_id:
         let _ = fs::create_dir_all(parent).await;
     }
 
-    let mut f = match fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(path)
-        .await
-    {
-        Ok(f) => f,
-        Err(_) => return,
-    };
-    let _ = f.write_all(line.as_bytes()).await;
-    let _ = f.write_all(b"\n").await;
+    let lock = thread_lock(path).await;
+    let _guard = lock.lock().await;
+    let (p, max) = (path.clone(), thread_segment_bytes());
+    let _ = tokio::task::spawn_blocking(move || jsonl::rotate_if_larger(&p, max)).await;
+    let _ = atomic::append_async(path, &line).await;
 }
 
 async fn load_thread_history(path: &PathBuf, max_messages: usize) -> Vec<Value> {
     let mut out = Vec::new();
     let std_path = StdPath::new(path);
-    let lines = tail_lines(std_path, max_messages, 200_000).await.unwrap_or_default();
+    let lines = tail_thread_lines(std_path, max_messages, 2_000_000).await.unwrap_or_default();
     for line in lines {
         if let Ok(v) = serde_json::from_str::<Value>(&line) {
             let role = v.get("role").and_then(|x| x.as_str()).unwrap_or("");
@@ -604,13 +998,16 @@ async fn load_thread_history(path: &PathBuf, max_messages: usize) -> Vec<Value>
     out
 }
 
-async fn thread_summary(path: &PathBuf, user_id: &str, thread: &str) -> ThreadSummaryResp {
+pub(crate) async fn thread_summary(path: &PathBuf, user_id: &str, thread: &str) -> ThreadSummaryResp {
     let std_path = StdPath::new(path);
     let meta = tokio::fs::metadata(std_path).await.ok();
-    let bytes_total = meta.as_ref().map(|m| m.len()).unwrap_or(0);
+    let mut bytes_total = 0;
+    for seg in jsonl::segments(std_path) {
+        bytes_total += tokio::fs::metadata(&seg).await.map(|m| m.len()).unwrap_or(0);
+    }
     let last_updated = meta.as_ref().and_then(fmt_mtime);
 
-    let lines = tail_lines(std_path, 200, 500_000).await.unwrap_or_default();
+    let lines = tail_thread_lines(std_path, 200, 500_000).await.unwrap_or_default();
     let mut messages_total = 0usize;
     let mut messages_user = 0usize;
     let mut messages_assistant = 0usize;
@@ -651,8 +1048,26 @@ async fn thread_summary(path: &PathBuf, user_id: &str, thread: &str) -> ThreadSu
         last_run_ids.truncate(8);
     }
 
-    // Rough heuristic (not “token accurate”): 4 chars ≈ 1 token.
-    let approx_tokens = (bytes_total / 4).max(1);
+    // Tokens of the recent messages for the active model, scaled to the whole thread. BPE
+    // over up to the whole tail is CPU-bound, so it runs on the blocking pool.
+    let model = crate::engine::router::model_name();
+    let (sample_bytes, sample_tokens) = tokio::task::spawn_blocking(move || {
+        let (mut bytes, mut tokens) = (0u64, 0u64);
+        for line in &lines {
+            if let Ok(v) = serde_json::from_str::<Value>(line) {
+                let content = v.get("content").and_then(|x| x.as_str()).unwrap_or("");
+                bytes += line.len() as u64;
+                tokens += crate::engine::tokens::count(&model, content) as u64;
+            }
+        }
+        (bytes, tokens)
+    })
+    .await
+    .unwrap_or((0, 0));
+    let approx_tokens = (bytes_total.max(sample_bytes) * sample_tokens)
+        .checked_div(sample_bytes)
+        .unwrap_or(1)
+        .max(1);
 
     ThreadSummaryResp {
         user_id: user_id.to_string(),
@@ -669,7 +1084,7 @@ async fn thread_summary(path: &PathBuf, user_id: &str, thread: &str) -> ThreadSu
     }
 }
 
-async fn read_receipt_response_json(run_id: &str) -> Result<Value, String> {
+pub(crate) async fn read_receipt_response_json(run_id: &str) -> Result<Value, String> {
     if !is_safe_segment(run_id) {
         return Err("Invalid run_id".to_string());
     }
@@ -682,10 +1097,25 @@ async fn read_receipt_response_json(run_id: &str) -> Result<Value, String> {
     let txt = fs::read_to_string(&p)
         .await
         .map_err(|_| "Missing receipt response.json".to_string())?;
-    serde_json::from_str::<Value>(&txt).map_err(|e| format!("Bad receipt JSON: {e}"))
+    let mut resp =
+        serde_json::from_str::<Value>(&txt).map_err(|e| format!("Bad receipt JSON: {e}"))?;
+    engine::migrate::upgrade_response(&mut resp);
+    Ok(resp)
+}
+
+/// Thread summary of an attached run: LM bullets when `attach_summary.mode: lm` and the router
+/// answers, otherwise the evidence snippet heuristic.
+async fn summarize_run_for_thread(run_id: &str, resp: &Value, note: Option<&str>) -> (String, Option<String>) {
+    let bullets = integrations::run_summary::bullets(resp).await;
+    summarize_receipt_for_context(run_id, resp, note, bullets.as_deref())
 }
 
-fn summarize_receipt_for_context(run_id: &str, resp: &Value, note: Option<&str>) -> (String, Option<String>) {
+fn summarize_receipt_for_context(
+    run_id: &str,
+    resp: &Value,
+    note: Option<&str>,
+    bullets: Option<&[String]>,
+) -> (String, Option<String>) {
     let mut lines: Vec<String> = Vec::new();
     lines.push(format!("Tool: attached run output `{}`.", run_id));
 
@@ -712,14 +1142,30 @@ fn summarize_receipt_for_context(run_id: &str, resp: &Value, note: Option<&str>)
                 lines.push(format!("- view: `{}`", u));
             }
 
-            let mut strs: Vec<String> = Vec::new();
-            let mut budget = 8usize;
-            extract_strings_limited(ev, &mut strs, 4, &mut budget);
-            strs.retain(|s| s.len() <= 240);
-            if !strs.is_empty() {
-                lines.push("- evidence_snippets:".to_string());
-                for s in strs.into_iter().take(6) {
-                    lines.push(format!("  - {}", s));
+            if let Some(bullets) = bullets {
+                lines.push("- summary:".to_string());
+                for b in bullets {
+                    lines.push(format!("  - {}", b));
+                }
+            } else {
+                let mut strs: Vec<String> = Vec::new();
+                let mut budget = 8usize;
+                extract_strings_limited(ev, &mut strs, 4, &mut budget);
+                strs.retain(|s| s.len() <= 240);
+                if !strs.is_empty() {
+                    lines.push("- evidence_snippets:".to_string());
+                    for s in strs.into_iter().take(6) {
+                        lines.push(format!("  - {}", s));
+                    }
+                }
+            }
+            // Too large to inline: point at the full text instead.
+            if let Some(off) = ev.get("offloaded").and_then(|v| v.as_object()) {
+                lines.push("- full_outputs:".to_string());
+                for (field, rec) in off.iter().take(6) {
+                    let url = rec.get("url").and_then(|v| v.as_str()).unwrap_or("");
+                    let bytes = rec.get("bytes").and_then(|v| v.as_u64()).unwrap_or(0);
+                    lines.push(format!("  - {} ({} bytes): `{}`", field, bytes, url));
                 }
             }
         }
@@ -731,7 +1177,10 @@ fn summarize_receipt_for_context(run_id: &str, resp: &Value, note: Option<&str>)
         }
     }
 
-    lines.push(format!("- receipt: `/runs/receipts/{}/RECEIPT.md`", run_id));
+    lines.push(format!(
+        "- receipt: `{}`",
+        url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id))
+    ));
     if let Some(n) = note {
         if !n.trim().is_empty() {
             lines.push(format!("- note: {}", redact(n)));
@@ -741,6 +1190,80 @@ fn summarize_receipt_for_context(run_id: &str, resp: &Value, note: Option<&str>)
     (lines.join("\n"), goal_id)
 }
 
+/// Registered with [`engine::hooks`] so `meta.omni` tool calls get receipts.
+pub struct ToolCallReceipts;
+
+impl engine::hooks::ReceiptSink for ToolCallReceipts {
+    fn tool_call<'a>(
+        &'a self,
+        run_id: &'a str,
+        inputs: &'a Value,
+        policy: &'a Policy,
+        manifest: &'a Manifest,
+        bits: &'a Bits,
+    ) -> engine::harness::BoxFuture<'a, ()> {
+        Box::pin(write_tool_call_receipt(run_id, inputs, policy, manifest, bits))
+    }
+}
+
+/// Registered with [`engine::hooks`] so `experiment.sweep` children run in-process, exactly
+/// like an anonymous `POST /run`.
+pub struct ChildRuns;
+
+impl engine::hooks::ChildRunner for ChildRuns {
+    fn run(&self, request: Value) -> engine::harness::BoxFuture<'_, Result<Value, String>> {
+        Box::pin(async move {
+            let req: RunReq = serde_json::from_value(request).map_err(|e| e.to_string())?;
+            let resp = execute_run(req, None, "run").await?;
+            serde_json::to_value(resp).map_err(|e| e.to_string())
+        })
+    }
+}
+
+/// Receipt for a goal a meta.omni turn ran as a tool call (`ctx.kind = "tool"`). The
+/// manifest's evidence already names the parent run.
+async fn write_tool_call_receipt(
+    run_id: &str,
+    inputs: &Value,
+    policy: &Policy,
+    manifest: &Manifest,
+    bits: &Bits,
+) {
+    let mpayload = Mpayload {
+        goal_id: manifest.goal_id.clone(),
+        inputs: inputs.clone(),
+        policy_effective: policy.clone(),
+        policy_request: None,
+        policy_chain: Vec::new(),
+        ctx: MpayloadCtx {
+            kind: "tool".to_string(),
+            user_id: None,
+            thread: None,
+            run_id: run_id.to_string(),
+            correlation_id: correlation::current(),
+            priority: Priority::default(),
+            dependencies: None,
+        },
+    };
+    let resp = RunResp {
+        manifest: manifest.clone(),
+        bits: bits.clone(),
+        pr_created: None,
+        meta2_proposal: None,
+    };
+    write_receipt_bundle(
+        run_id,
+        &manifest.goal_id,
+        bits,
+        &manifest.deliverables,
+        &manifest.evidence,
+        false,
+        &mpayload,
+        &resp,
+    )
+    .await;
+}
+
 async fn write_receipt_bundle<Req: Serialize, Resp: Serialize>(
     run_id: &str,
     goal_id: &str,
@@ -759,27 +1282,60 @@ async fn write_receipt_bundle<Req: Serialize, Resp: Serialize>(
     let receipt_dir = root.join("runs/receipts").join(run_id);
     let _ = fs::create_dir_all(&receipt_dir).await;
 
-    let _ = fs::write(
+    // Redaction pass: nothing is persisted before it has been through `redact`.
+    let mut request_v = serde_json::to_value(request).unwrap_or(Value::Null);
+    let mut response_v = serde_json::to_value(response).unwrap_or(Value::Null);
+    if let Some(obj) = response_v.as_object_mut() {
+        obj.insert("schema_version".to_string(), json!(MANIFEST_SCHEMA_VERSION));
+    }
+    let mut redactions: Vec<(&str, usize)> = vec![
+        ("request", redact_value(&mut request_v)),
+        ("response", redact_value(&mut response_v)),
+    ];
+    let _ = atomic::write_async(
         receipt_dir.join("request.json"),
-        serde_json::to_string_pretty(request).unwrap_or_default(),
+        serde_json::to_string_pretty(&request_v).unwrap_or_default(),
     )
     .await;
-    let _ = fs::write(
+    let owner = integrations::receipt_acl::owner_of(&request_v);
+    integrations::receipt_acl::record_owner(&receipt_dir, owner.clone()).await;
+    let _ = atomic::write_async(
         receipt_dir.join("response.json"),
-        serde_json::to_string_pretty(response).unwrap_or_default(),
+        serde_json::to_string_pretty(&response_v).unwrap_or_default(),
+    )
+    .await;
+    integrations::run_deps::notify_finished();
+
+    // timing.json: keep the first write (queued stub) as started_at for run latency.
+    let now = chrono::Utc::now().to_rfc3339();
+    let started_at = fs::read_to_string(receipt_dir.join("timing.json"))
+        .await
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+        .and_then(|v| v.get("started_at").and_then(|x| x.as_str()).map(|s| s.to_string()))
+        .unwrap_or_else(|| now.clone());
+    let _ = atomic::write_async(
+        receipt_dir.join("timing.json"),
+        serde_json::to_string_pretty(&json!({ "started_at": started_at, "finished_at": now }))
+            .unwrap_or_default(),
     )
     .await;
 
-    let wrote_stdout = if let Some(s) = evidence.get("stdout").and_then(|v| v.as_str()) {
-        let _ = fs::write(receipt_dir.join("stdout.txt"), s).await;
+    // Offloaded fields are inline only as a head; the receipt files get the whole text.
+    let wrote_stdout = if let Some(s) = engine::offload::full_text(evidence, "/stdout") {
+        let (s, n) = redact_counted(&s);
+        redactions.push(("stdout", n));
+        let _ = atomic::write_async(receipt_dir.join("stdout.txt"), s).await;
         true
     } else {
         false
     };
 
-    let wrote_reply = if let Some(s) = evidence.get("reply").and_then(|v| v.as_str()) {
+    let wrote_reply = if let Some(s) = engine::offload::full_text(evidence, "/reply") {
         if !s.trim().is_empty() {
-            let _ = fs::write(receipt_dir.join("reply.txt"), s).await;
+            let (s, n) = redact_counted(&s);
+            redactions.push(("reply", n));
+            let _ = atomic::write_async(receipt_dir.join("reply.txt"), s).await;
             true
         } else {
             false
@@ -802,6 +1358,9 @@ async fn write_receipt_bundle<Req: Serialize, Resp: Serialize>(
     md.push_str("# RECEIPT\n\n");
     md.push_str(&format!("- run_id: `{}`\n", run_id));
     md.push_str(&format!("- goal_id: `{}`\n", goal_id));
+    if let Some(owner) = &owner {
+        md.push_str(&format!("- owner: `{}`\n", owner));
+    }
     md.push_str(&format!("- success: `{}`\n", actual_success));
     md.push_str(&format!(
         "- bits: a={} u={} p={} e={} d={} t={}\n",
@@ -810,16 +1369,63 @@ async fn write_receipt_bundle<Req: Serialize, Resp: Serialize>(
     if let Some(u) = view {
         md.push_str(&format!("- view: `{}`\n", u));
     }
+    let total_redactions: usize = redactions.iter().map(|(_, n)| n).sum();
+    if total_redactions > 0 {
+        let parts: Vec<String> = redactions
+            .iter()
+            .filter(|(_, n)| *n > 0)
+            .map(|(f, n)| format!("{} {}", f, n))
+            .collect();
+        md.push_str(&format!(
+            "- redactions: {} ({})\n",
+            total_redactions,
+            parts.join(", ")
+        ));
+    } else {
+        md.push_str("- redactions: 0\n");
+    }
+    let correlation_id = correlation::current();
+    if let Some(c) = &correlation_id {
+        md.push_str(&format!(
+            "- correlation_id: `{}` ({})\n",
+            c,
+            url_for(&format!("/correlations/{}", c))
+        ));
+    }
 
     md.push_str("\n## Files\n");
-    md.push_str(&format!("- request: `/runs/receipts/{}/request.json`\n", run_id));
-    md.push_str(&format!("- response: `/runs/receipts/{}/response.json`\n", run_id));
-    md.push_str(&format!("- receipt: `/runs/receipts/{}/RECEIPT.md`\n", run_id));
+    md.push_str(&format!(
+        "- request: `{}`\n",
+        url_for(&format!("/runs/receipts/{}/request.json", run_id))
+    ));
+    md.push_str(&format!(
+        "- response: `{}`\n",
+        url_for(&format!("/runs/receipts/{}/response.json", run_id))
+    ));
+    md.push_str(&format!(
+        "- receipt: `{}`\n",
+        url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id))
+    ));
     if wrote_stdout {
-        md.push_str(&format!("- stdout: `/runs/receipts/{}/stdout.txt`\n", run_id));
+        md.push_str(&format!(
+            "- stdout: `{}`\n",
+            url_for(&format!("/runs/receipts/{}/stdout.txt", run_id))
+        ));
     }
     if wrote_reply {
-        md.push_str(&format!("- reply: `/runs/receipts/{}/reply.txt`\n", run_id));
+        md.push_str(&format!(
+            "- reply: `{}`\n",
+            url_for(&format!("/runs/receipts/{}/reply.txt", run_id))
+        ));
+    }
+    if fs::try_exists(receipt_dir.join("environment.json"))
+        .await
+        .unwrap_or(false)
+    {
+        md.push_str(&format!(
+            "- environment: `{}`\n",
+            url_for(&format!("/runs/receipts/{}/environment.json", run_id))
+        ));
     }
 
     if !deliverables.is_empty() {
@@ -831,7 +1437,47 @@ async fn write_receipt_bundle<Req: Serialize, Resp: Serialize>(
         }
     }
 
-    let _ = fs::write(receipt_dir.join("RECEIPT.md"), md).await;
+    let _ = atomic::write_async(receipt_dir.join("RECEIPT.md"), md).await;
+
+    if correlation_id.is_some() {
+        let ctx = serde_json::to_value(request)
+            .ok()
+            .and_then(|v| v.get("ctx").cloned())
+            .unwrap_or(Value::Null);
+        let field = |k: &str| ctx.get(k).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let mut link = correlation::Link::new(
+            field("kind").as_deref().unwrap_or("run"),
+            run_id,
+        );
+        link.goal_id = Some(goal_id.to_string());
+        link.user_id = field("user_id");
+        link.thread = field("thread");
+        correlation::record(&link).await;
+    }
+
+    // Policy-gated goals (filesystem/shell mutations, rollbacks) go to the audit log.
+    if snapshot::is_mutating(goal_id) || goal_id == "runs.rollback" {
+        let actor = request_v
+            .get("ctx")
+            .and_then(|c| c.get("user_id"))
+            .or_else(|| request_v.get("actor"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("anonymous")
+            .to_string();
+        integrations::audit::record(
+            &actor,
+            "goal.run",
+            goal_id,
+            Some(integrations::audit::digest(&request_v)),
+            json!({
+                "run_id": run_id,
+                "success": actual_success,
+                "policy_blocked": evidence.get("policy_blocked").and_then(|v| v.as_bool()).unwrap_or(false),
+                "receipt_url": url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id))
+            }),
+        )
+        .await;
+    }
 }
 
 static RE_URL: Lazy<Regex> = Lazy::new(|| Regex::new(r#"https?://[^\s"'<>]+"#).unwrap());
@@ -841,42 +1487,12 @@ static RE_PATH_HINT: Lazy<Regex> = Lazy::new(|| {
     )
     .unwrap()
 });
-static RE_X_API_KEY: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r#"(?i)(x-api-key\s*[:=]\s*)([^\s"'\\]+)"#).unwrap());
-static RE_AUTH_BEARER: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r#"(?i)(authorization\s*:\s*bearer\s+)([^\s"'\\]+)"#).unwrap());
-static RE_SK: Lazy<Regex> = Lazy::new(|| Regex::new(r"sk-[A-Za-z0-9_-]{10,}").unwrap());
-
 fn fmt_mtime(meta: &std::fs::Metadata) -> Option<String> {
     meta.modified()
         .ok()
         .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
 }
 
-fn redact(s: &str) -> String {
-    let mut out = s.to_string();
-    out = RE_X_API_KEY.replace_all(&out, "${1}[REDACTED]").to_string();
-    out = RE_AUTH_BEARER
-        .replace_all(&out, "${1}[REDACTED]")
-        .to_string();
-    out = RE_SK.replace_all(&out, "sk-[REDACTED]").to_string();
-    out
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ApiTraceEvent {
-    ts: String,
-    method: String,
-    path: String,
-    query: Option<String>,
-    status: u16,
-    ms: u64,
-    mutation: bool,
-    run_id: Option<String>,
-    user_id: Option<String>,
-    thread: Option<String>,
-}
-
 fn parse_run_id_from_query(q: &str) -> Option<String> {
     for part in q.split('&') {
         let mut it = part.splitn(2, '=');
@@ -914,19 +1530,6 @@ fn parse_user_id_from_path(path: &str) -> Option<String> {
     None
 }
 
-async fn append_api_trace(ev: ApiTraceEvent) {
-    let root = meta3_root();
-    let p = root.join("runs").join("api_trace.jsonl");
-    let _ = fs::create_dir_all(root.join("runs")).await;
-    let line = serde_json::to_string(&ev).unwrap_or_default();
-    let mut f = match fs::OpenOptions::new().create(true).append(true).open(&p).await {
-        Ok(f) => f,
-        Err(_) => return,
-    };
-    let _ = f.write_all(line.as_bytes()).await;
-    let _ = f.write_all(b"\n").await;
-}
-
 pub async fn api_trace_middleware(
     req: axum::http::Request<axum::body::Body>,
     next: axum::middleware::Next,
@@ -961,11 +1564,60 @@ pub async fn api_trace_middleware(
         .map(|s| s.to_string())
         .or_else(|| parse_user_id_from_path(&path));
 
-    let resp = next.run(req).await;
+    // Honour a caller-supplied id; otherwise every mutation starts a new correlation.
+    let correlation_id = headers
+        .get(correlation::HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|s| correlation::is_valid(s))
+        .map(|s| s.to_string())
+        .or_else(|| mutation.then(correlation::new_id));
+
+    // Only bodies with a known, bounded length are buffered, so streaming uploads pass through.
+    let mut req = req;
+    let mut body = None;
+    let mut body_bytes = None;
+    if mutation
+        && integrations::api_trace::body_capture_enabled()
+        && !integrations::api_trace::body_opted_out(&path)
+    {
+        let len = headers
+            .get(axum::http::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        body_bytes = len;
+        if let Some(n) = len.filter(|n| *n > 0 && *n <= integrations::api_trace::BODY_BUFFER_LIMIT) {
+            let (parts, raw) = req.into_parts();
+            // A body that can't be read is rejected rather than forwarded empty.
+            let bytes = match axum::body::to_bytes(raw, n as usize).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return (
+                        axum::http::StatusCode::BAD_REQUEST,
+                        format!("failed to read request body: {}", e),
+                    )
+                        .into_response()
+                }
+            };
+            let text = String::from_utf8_lossy(&bytes);
+            body = Some(integrations::api_trace::cap_body(
+                &redact(&text),
+                integrations::api_trace::body_max_bytes(),
+            ));
+            req = axum::http::Request::from_parts(parts, axum::body::Body::from(bytes));
+        }
+    }
+
+    let mut resp = correlation::scope(correlation_id.clone(), next.run(req)).await;
+    if let Some(v) = correlation_id
+        .as_deref()
+        .and_then(|c| axum::http::HeaderValue::from_str(c).ok())
+    {
+        resp.headers_mut().insert(correlation::HEADER, v);
+    }
     let status = resp.status().as_u16();
     let ms = start.elapsed().as_millis() as u64;
 
-    let ev = ApiTraceEvent {
+    let ev = integrations::api_trace::ApiTraceEvent {
         ts: chrono::Utc::now().to_rfc3339(),
         method,
         path: redact(&path),
@@ -976,12 +1628,29 @@ pub async fn api_trace_middleware(
         run_id,
         user_id,
         thread,
+        correlation_id,
+        body,
+        body_bytes,
     };
-    tokio::spawn(async move { append_api_trace(ev).await });
+    tokio::spawn(async move { integrations::api_trace::append(&ev).await });
 
     resp
 }
 
+/// Scope the request's `X-Forwarded-Prefix` (sanitized) so `url_for()` emits links that
+/// survive being mounted under a path prefix behind a reverse proxy.
+pub async fn forwarded_prefix_middleware(
+    req: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let prefix = req
+        .headers()
+        .get("x-forwarded-prefix")
+        .and_then(|v| v.to_str().ok())
+        .and_then(urls::normalize_prefix);
+    urls::with_prefix(prefix, next.run(req)).await
+}
+
 fn split_url(url: &str) -> Option<(String, String)> {
     let rest = url
         .strip_prefix("http://")
@@ -1059,39 +1728,13 @@ fn extract_strings_limited(v: &Value, out: &mut Vec<String>, depth: usize, budge
     }
 }
 
+/// Streamed backwards from EOF on the blocking pool, so a large log never sits in memory whole.
 async fn tail_lines(path: &StdPath, limit: usize, max_bytes: u64) -> Result<Vec<String>, String> {
-    let meta = tokio::fs::metadata(path)
-        .await
-        .map_err(|e| format!("metadata: {e}"))?;
-    let len = meta.len();
-    let start = if len > max_bytes { len - max_bytes } else { 0 };
-
-    let mut f = tokio::fs::File::open(path)
-        .await
-        .map_err(|e| format!("open: {e}"))?;
-    if start > 0 {
-        f.seek(std::io::SeekFrom::Start(start))
-            .await
-            .map_err(|e| format!("seek: {e}"))?;
-    }
-
-    let mut buf = Vec::new();
-    f.read_to_end(&mut buf)
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || jsonl::tail(&path, limit, max_bytes))
         .await
-        .map_err(|e| format!("read: {e}"))?;
-
-    let mut s = String::from_utf8(buf).map_err(|e| format!("utf8: {e}"))?;
-    if start > 0 {
-        if let Some(idx) = s.find('\n') {
-            s = s[idx + 1..].to_string();
-        }
-    }
-
-    let mut lines: Vec<&str> = s.lines().filter(|l| !l.trim().is_empty()).collect();
-    if lines.len() > limit {
-        lines = lines[lines.len() - limit..].to_vec();
-    }
-    Ok(lines.into_iter().map(|l| l.to_string()).collect())
+        .map_err(|e| format!("tail: {e}"))?
+        .map_err(|e| format!("read: {e}"))
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -1103,6 +1746,10 @@ pub struct CodexSourceInfo {
     pub size_bytes: Option<u64>,
     pub file_count: Option<u64>,
     pub mtime: Option<String>,
+    /// Configured kind: archive | rollouts | utir.
+    pub source_kind: String,
+    pub path: String,
+    pub glob: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -1135,7 +1782,7 @@ pub struct CodexSearchQuery {
     pub limit_files: Option<usize>,
     pub limit_lines: Option<usize>,
     pub max_bytes: Option<u64>,
-    /// Comma-separated: archive,rollouts,utir (default: all)
+    /// Comma-separated kinds (index,archive,rollouts,utir) or source ids (default: index once imported, else all kinds)
     pub sources: Option<String>,
     pub case_sensitive: Option<bool>,
     pub regex: Option<bool>,
@@ -1183,125 +1830,30 @@ pub async fn codex_sources_handler(
         return unauthorized("Invalid x-api-key");
     }
 
-    let root = meta3_root();
-
-    let archive_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("orchestrator")
-        .join("runs")
-        .join("archives");
-    let rollouts_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("meta3")
-        .join("logs");
-    let utir_codex = root
-        .join("runs")
-        .join("utir")
-        .join("normalized_codex.jsonl");
-    let utir_history = root
-        .join("runs")
-        .join("utir")
-        .join("normalized_history.jsonl");
-
     let mut sources = Vec::new();
-
-    // Archive
-    let mut archive_available = false;
-    let mut archive_mtime = None;
-    let mut archive_size = None;
-    if let Ok(mut rd) = tokio::fs::read_dir(&archive_dir).await {
-        while let Ok(Some(entry)) = rd.next_entry().await {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if name.starts_with("codex_history_") && name.ends_with(".jsonl") {
-                if let Ok(m) = entry.metadata().await {
-                    archive_available = true;
-                    archive_size = Some(archive_size.unwrap_or(0) + m.len());
-                    if let Some(ts) = fmt_mtime(&m) {
-                        if archive_mtime.as_deref() < Some(ts.as_str()) {
-                            archive_mtime = Some(ts);
-                        }
-                    }
-                }
-            }
-        }
-    }
-    sources.push(CodexSourceInfo {
-        id: "orchestrator_archives".to_string(),
-        kind: "dir".to_string(),
-        description: "Codex Time Machine era archives (codex_history_*.jsonl)".to_string(),
-        available: archive_available,
-        size_bytes: archive_size,
-        file_count: None,
-        mtime: archive_mtime,
-    });
-
-    // Rollouts
-    let mut rollout_count: u64 = 0;
-    let mut rollouts_size: u64 = 0;
-    let mut rollouts_mtime = None;
-    if let Ok(mut rd) = tokio::fs::read_dir(&rollouts_dir).await {
-        while let Ok(Some(entry)) = rd.next_entry().await {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if name.ends_with(".jsonl") {
-                if let Ok(m) = entry.metadata().await {
-                    if m.is_file() {
-                        rollout_count += 1;
-                        rollouts_size += m.len();
-                        if let Some(ts) = fmt_mtime(&m) {
-                            if rollouts_mtime.as_deref() < Some(ts.as_str()) {
-                                rollouts_mtime = Some(ts);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    sources.push(CodexSourceInfo {
-        id: "meta3_rollouts".to_string(),
-        kind: "dir".to_string(),
-        description: "Meta3 rollout logs (*.jsonl)".to_string(),
-        available: rollout_count > 0,
-        size_bytes: Some(rollouts_size),
-        file_count: Some(rollout_count),
-        mtime: rollouts_mtime,
-    });
-
-    // UTIR normalized files
-    for (id, desc, path) in [
-        (
-            "utir_normalized_codex",
-            "UTIR normalized Codex JSONL",
-            utir_codex,
-        ),
-        (
-            "utir_normalized_history",
-            "UTIR normalized history/event ledger JSONL",
-            utir_history,
-        ),
-    ] {
-        match tokio::fs::metadata(&path).await {
-            Ok(m) => sources.push(CodexSourceInfo {
-                id: id.to_string(),
-                kind: "file".to_string(),
-                description: desc.to_string(),
-                available: m.is_file(),
-                size_bytes: Some(m.len()),
-                file_count: None,
-                mtime: fmt_mtime(&m),
-            }),
-            Err(_) => sources.push(CodexSourceInfo {
-                id: id.to_string(),
-                kind: "file".to_string(),
-                description: desc.to_string(),
-                available: false,
-                size_bytes: None,
-                file_count: None,
-                mtime: None,
-            }),
-        }
+    for src in integrations::codex::sources() {
+        let files = src.files();
+        let single = src.root().is_file();
+        sources.push(CodexSourceInfo {
+            id: src.id.clone(),
+            kind: if single { "file" } else { "dir" }.to_string(),
+            description: if src.description.is_empty() {
+                format!("{} ({}/{})", src.kind, src.path, src.glob)
+            } else {
+                src.description.clone()
+            },
+            available: !files.is_empty(),
+            size_bytes: (!files.is_empty()).then(|| files.iter().map(|f| f.len).sum()),
+            file_count: (!single).then_some(files.len() as u64),
+            mtime: files
+                .iter()
+                .map(|f| f.modified)
+                .max()
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()),
+            source_kind: src.kind,
+            path: src.path,
+            glob: src.glob,
+        });
     }
 
     Json(CodexSourcesResp { sources }).into_response()
@@ -1314,7 +1866,7 @@ pub async fn codex_sources_handler(
         ("limit" = Option<usize>, Query, description = "Number of events to return from the end (max 2000)")
     ),
     responses(
-        (status = 200, description = "Tail of latest codex_history_*.jsonl as parsed JSON", body = Value),
+        (status = 200, description = "Tail of the newest archive file as parsed JSON", body = Value),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Not found (disabled/missing)")
     )
@@ -1337,35 +1889,19 @@ pub async fn codex_archive_handler(
     }
 
     let limit = clamp_limit(q.limit, 200, 2000);
-    let root = meta3_root();
-    let archive_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("orchestrator")
-        .join("runs")
-        .join("archives");
-
-    let mut best: Option<(String, PathBuf)> = None;
-    if let Ok(mut rd) = tokio::fs::read_dir(&archive_dir).await {
-        while let Ok(Some(entry)) = rd.next_entry().await {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if name.starts_with("codex_history_") && name.ends_with(".jsonl") {
-                let path = entry.path();
-                match best.as_ref() {
-                    Some((best_name, _)) if best_name >= &name => {}
-                    _ => best = Some((name, path)),
-                }
-            }
-        }
-    }
+    let best = integrations::codex::sources_of("archive")
+        .into_iter()
+        .filter_map(|src| src.latest_by_name().map(|f| (src.id, f)))
+        .max_by(|a, b| a.1.name.cmp(&b.1.name));
 
-    let Some((file, path)) = best else {
+    let Some((source, latest)) = best else {
         return (
             axum::http::StatusCode::NOT_FOUND,
-            "no codex_history_*.jsonl found".to_string(),
+            "no archive files found".to_string(),
         )
             .into_response();
     };
+    let (file, path) = (latest.name, latest.path);
 
     let lines = match tail_lines(&path, limit, 10 * 1024 * 1024).await {
         Ok(v) => v,
@@ -1380,7 +1916,7 @@ pub async fn codex_archive_handler(
     }
 
     Json(json!({
-        "source": "orchestrator_archives",
+        "source": source,
         "file": file,
         "events": events
     }))
@@ -1417,13 +1953,8 @@ pub async fn codex_rollouts_list_handler(
     }
 
     let limit = clamp_limit(q.limit, 200, 1000);
-    let root = meta3_root();
-    let rollouts_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("meta3")
-        .join("logs");
-    if !rollouts_dir.exists() {
+    let rollouts = integrations::codex::sources_of("rollouts");
+    if !rollouts.iter().any(|src| src.root().exists()) {
         return (
             axum::http::StatusCode::NOT_FOUND,
             "rollouts dir missing".to_string(),
@@ -1432,30 +1963,19 @@ pub async fn codex_rollouts_list_handler(
     }
 
     let mut items = Vec::new();
-    match tokio::fs::read_dir(&rollouts_dir).await {
-        Ok(mut rd) => {
-            while let Ok(Some(entry)) = rd.next_entry().await {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if !name.ends_with(".jsonl") {
-                    continue;
-                }
-                if !is_safe_segment(&name) {
-                    continue;
-                }
-                if let Ok(m) = entry.metadata().await {
-                    if !m.is_file() {
-                        continue;
-                    }
-                    items.push(json!({
-                        "name": name,
-                        "size_bytes": m.len(),
-                        "mtime": fmt_mtime(&m),
-                    }));
-                }
+    for src in &rollouts {
+        for f in src.files() {
+            let base = f.name.rsplit('/').next().unwrap_or(&f.name).to_string();
+            if !is_safe_segment(&base) {
+                continue;
             }
-        }
-        Err(e) => {
-            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+            items.push(json!({
+                "name": base,
+                "path": f.name,
+                "source": src.id,
+                "size_bytes": f.len,
+                "mtime": chrono::DateTime::<chrono::Utc>::from(f.modified).to_rfc3339(),
+            }));
         }
     }
 
@@ -1470,7 +1990,7 @@ pub async fn codex_rollouts_list_handler(
     }
 
     Json(json!({
-        "source": "meta3_rollouts",
+        "sources": rollouts.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(),
         "count": items.len(),
         "files": items
     }))
@@ -1517,20 +2037,20 @@ pub async fn codex_rollout_file_handler(
     }
 
     let limit = clamp_limit(q.limit, 200, 2000);
-    let root = meta3_root();
-    let rollouts_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("meta3")
-        .join("logs");
-    let path = rollouts_dir.join(&file);
-    if !path.starts_with(&rollouts_dir) || !path.exists() {
+    // Looked up by base name among the configured rollout files, never joined onto a root.
+    let found = integrations::codex::sources_of("rollouts").into_iter().find_map(|src| {
+        src.files()
+            .into_iter()
+            .find(|f| f.name.rsplit('/').next() == Some(file.as_str()))
+            .map(|f| (src.id, f.path))
+    });
+    let Some((source, path)) = found else {
         return (
             axum::http::StatusCode::NOT_FOUND,
             "file not found".to_string(),
         )
             .into_response();
-    }
+    };
 
     let lines = match tail_lines(&path, limit, 10 * 1024 * 1024).await {
         Ok(v) => v,
@@ -1545,7 +2065,7 @@ pub async fn codex_rollout_file_handler(
     }
 
     Json(json!({
-        "source": "meta3_rollouts",
+        "source": source,
         "file": file,
         "events": events
     }))
@@ -1613,6 +2133,9 @@ pub struct CodexSearchResult {
     pub ts: Option<String>,
     pub kind: Option<String>,
     pub snippet: String,
+    /// Session id (index results only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -1622,6 +2145,9 @@ pub struct CodexSearchResp {
     pub scanned_files: u64,
     pub results: Vec<CodexSearchResult>,
     pub truncated: bool,
+    /// Token index freshness when the `index` source was searched through it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<integrations::codex_index::IndexStatus>,
 }
 
 fn parse_sources(s: Option<&str>) -> HashSet<String> {
@@ -1635,9 +2161,14 @@ fn parse_sources(s: Option<&str>) -> HashSet<String> {
         }
     }
     if out.is_empty() {
-        out.insert("archive".to_string());
-        out.insert("rollouts".to_string());
-        out.insert("utir".to_string());
+        // Prefer the imported index (codex.import); tail raw files until it exists.
+        if integrations::codex::has_index() {
+            out.insert("index".to_string());
+        } else {
+            out.insert("archive".to_string());
+            out.insert("rollouts".to_string());
+            out.insert("utir".to_string());
+        }
     }
     out
 }
@@ -1747,6 +2278,7 @@ async fn search_jsonl_file_tail(
             ts,
             kind,
             snippet: excerpt_around(&red, match_range),
+            session: None,
         });
     }
 
@@ -1871,7 +2403,7 @@ async fn scan_jsonl_file(
         ("limit_files" = Option<usize>, Query, description = "Max rollout files to scan (max 200)"),
         ("limit_lines" = Option<usize>, Query, description = "Max tailed lines per file (max 10000)"),
         ("max_bytes" = Option<u64>, Query, description = "Max tailed bytes per file (max 20MB)"),
-        ("include_archive" = Option<bool>, Query, description = "Include codex_history_*.jsonl archive tail")
+        ("include_archive" = Option<bool>, Query, description = "Include the newest archive file's tail")
     ),
     responses(
         (status = 200, description = "Auto capabilities report over Codex history", body = CodexCapabilitiesResp),
@@ -1901,19 +2433,6 @@ pub async fn codex_capabilities_handler(
     let limit_lines = clamp_limit(q.limit_lines, 2000, 10_000);
     let max_bytes = clamp_u64(q.max_bytes, 5 * 1024 * 1024, 20 * 1024 * 1024);
 
-    let root = meta3_root();
-    let archive_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("orchestrator")
-        .join("runs")
-        .join("archives");
-    let rollouts_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("meta3")
-        .join("logs");
-
     let mut files_scanned: Vec<CapScanFile> = Vec::new();
     let mut events_parsed: u64 = 0;
     let mut strings_extracted: u64 = 0;
@@ -1930,20 +2449,12 @@ pub async fn codex_capabilities_handler(
 
     // 1) Latest archive file (by lexicographic name)
     if include_archive {
-        let mut best: Option<(String, PathBuf)> = None;
-        if let Ok(mut rd) = tokio::fs::read_dir(&archive_dir).await {
-            while let Ok(Some(entry)) = rd.next_entry().await {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if name.starts_with("codex_history_") && name.ends_with(".jsonl") {
-                    let path = entry.path();
-                    match best.as_ref() {
-                        Some((best_name, _)) if best_name >= &name => {}
-                        _ => best = Some((name, path)),
-                    }
-                }
-            }
-        }
-        if let Some((name, path)) = best {
+        let best = integrations::codex::sources_of("archive")
+            .into_iter()
+            .filter_map(|src| src.latest_by_name().map(|f| (src.id, f)))
+            .max_by(|a, b| a.1.name.cmp(&b.1.name));
+        if let Some((source, f)) = best {
+            let (name, path) = (f.name, f.path);
             match scan_jsonl_file(&path, limit_lines, max_bytes, &tools).await {
                 Ok(acc) => {
                     events_parsed += acc.events_parsed;
@@ -1958,7 +2469,7 @@ pub async fn codex_capabilities_handler(
                         samples.push(s);
                     }
                     files_scanned.push(CapScanFile {
-                        source: "orchestrator_archives".to_string(),
+                        source,
                         file: name,
                         size_bytes: acc.size_bytes,
                         tailed_lines: acc.tailed_lines,
@@ -1976,26 +2487,19 @@ pub async fn codex_capabilities_handler(
     }
 
     // 2) Recent rollout files (by mtime desc)
-    let mut rollout_files: Vec<(String, PathBuf, std::time::SystemTime)> = Vec::new();
-    if let Ok(mut rd) = tokio::fs::read_dir(&rollouts_dir).await {
-        while let Ok(Some(entry)) = rd.next_entry().await {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if !name.ends_with(".jsonl") || !is_safe_segment(&name) {
-                continue;
-            }
-            if let Ok(m) = entry.metadata().await {
-                if !m.is_file() {
-                    continue;
-                }
-                let mt = m.modified().unwrap_or(std::time::UNIX_EPOCH);
-                rollout_files.push((name, entry.path(), mt));
-            }
-        }
-    }
-    rollout_files.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| b.0.cmp(&a.0)));
+    let mut rollout_files: Vec<(String, String, PathBuf, std::time::SystemTime)> =
+        integrations::codex::sources_of("rollouts")
+            .into_iter()
+            .flat_map(|src| {
+                src.files()
+                    .into_iter()
+                    .map(move |f| (src.id.clone(), f.name, f.path, f.modified))
+            })
+            .collect();
+    rollout_files.sort_by(|a, b| b.3.cmp(&a.3).then_with(|| b.1.cmp(&a.1)));
     rollout_files.truncate(limit_files);
 
-    for (name, path, _mt) in rollout_files {
+    for (source, name, path, _mt) in rollout_files {
         match scan_jsonl_file(&path, limit_lines, max_bytes, &tools).await {
             Ok(acc) => {
                 events_parsed += acc.events_parsed;
@@ -2010,7 +2514,7 @@ pub async fn codex_capabilities_handler(
                     samples.push(s);
                 }
                 files_scanned.push(CapScanFile {
-                    source: "meta3_rollouts".to_string(),
+                    source,
                     file: name,
                     size_bytes: acc.size_bytes,
                     tailed_lines: acc.tailed_lines,
@@ -2059,12 +2563,12 @@ pub async fn codex_capabilities_handler(
         ("limit_files" = Option<usize>, Query, description = "Max rollout files to scan (max 500)"),
         ("limit_lines" = Option<usize>, Query, description = "Max tailed lines per file (max 20000)"),
         ("max_bytes" = Option<u64>, Query, description = "Max tailed bytes per file (max 50MB)"),
-        ("sources" = Option<String>, Query, description = "Comma-separated: archive,rollouts,utir (default all)"),
+        ("sources" = Option<String>, Query, description = "Comma-separated kinds (index,archive,rollouts,utir) or configured source ids (default index once codex.import has run, else all kinds)"),
         ("case_sensitive" = Option<bool>, Query, description = "Case-sensitive substring match (default false)"),
         ("regex" = Option<bool>, Query, description = "Interpret q as regex (default false)")
     ),
     responses(
-        (status = 200, description = "Search Codex history sources (tailed)", body = CodexSearchResp),
+        (status = 200, description = "Search the normalized Codex index (or tail raw sources)", body = CodexSearchResp),
         (status = 400, description = "Invalid query"),
         (status = 401, description = "Unauthorized"),
         (status = 404, description = "Not found (disabled)")
@@ -2120,51 +2624,72 @@ pub async fn codex_search_handler(
         None
     };
 
-    let root = meta3_root();
-    let archive_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("orchestrator")
-        .join("runs")
-        .join("archives");
-    let rollouts_dir = root
-        .join("agents")
-        .join("NIX.codecli")
-        .join("meta3")
-        .join("logs");
-    let utir_history = root
-        .join("runs")
-        .join("utir")
-        .join("normalized_history.jsonl");
-    let utir_codex = root
-        .join("runs")
-        .join("utir")
-        .join("normalized_codex.jsonl");
+    // `sources` tokens select configured sources by kind or by id.
+    let configured: Vec<integrations::codex::CodexSource> = integrations::codex::sources()
+        .into_iter()
+        .filter(|src| sources.contains(&src.kind) || sources.contains(&src.id.to_ascii_lowercase()))
+        .collect();
 
     let mut results: Vec<CodexSearchResult> = Vec::new();
     let mut scanned_files: u64 = 0;
-
-    // Archive (single best file)
-    if sources.contains("archive") {
-        let mut best: Option<(String, PathBuf)> = None;
-        if let Ok(mut rd) = tokio::fs::read_dir(&archive_dir).await {
-            while let Ok(Some(entry)) = rd.next_entry().await {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if name.starts_with("codex_history_") && name.ends_with(".jsonl") {
-                    let path = entry.path();
-                    match best.as_ref() {
-                        Some((best_name, _)) if best_name >= &name => {}
-                        _ => best = Some((name, path)),
-                    }
-                }
+    let mut index_status = None;
+
+    // Normalized index (already redacted and deduplicated at import time); looked up through
+    // the token index unless the query is a regex or has no token to look up. Either may load
+    // the whole index or events file from disk, so both run on the blocking pool.
+    if sources.contains("index") {
+        let (q, re) = (query.clone(), compiled.clone());
+        let (hits, status) = tokio::task::spawn_blocking(move || {
+            let matches = |s: &str| line_matches(s, &q, case_sensitive, re.as_ref());
+            let indexed = if re.is_none() {
+                integrations::codex_index::search(&q, matches, limit)
+            } else {
+                None
+            };
+            match indexed {
+                Some((hits, status)) => (hits, Some(status)),
+                None => (integrations::codex::search(matches, limit).0, None),
+            }
+        })
+        .await
+        .unwrap_or_default();
+        if status.as_ref().is_some_and(|s| s.lag_bytes > 0) {
+            integrations::codex_index::refresh_in_background();
+        }
+        index_status = status;
+        scanned_files += 1;
+        results.extend(hits.into_iter().map(|e| {
+            let body = match e.command.as_deref() {
+                Some(c) if !line_matches(&e.text, &query, case_sensitive, compiled.as_ref()) => c.to_string(),
+                _ => e.text.clone(),
+            };
+            let mut snippet: String = body.chars().take(500).collect();
+            if body.chars().count() > 500 {
+                snippet.push('…');
             }
+            CodexSearchResult {
+                source: e.source,
+                file: e.file,
+                line: e.line,
+                ts: e.ts,
+                kind: e.kind,
+                snippet,
+                session: Some(e.session),
+            }
+        }));
+    }
+
+    // Archives (newest file per source)
+    for src in configured.iter().filter(|src| src.kind == "archive") {
+        if results.len() >= limit {
+            break;
         }
-        if let Some((name, path)) = best {
+        if let Some(f) = src.latest_by_name() {
             scanned_files += 1;
             if let Err(e) = search_jsonl_file_tail(
-                "orchestrator_archives",
-                &name,
-                &path,
+                &src.id,
+                &f.name,
+                &f.path,
                 &query,
                 case_sensitive,
                 compiled.as_ref(),
@@ -2180,36 +2705,25 @@ pub async fn codex_search_handler(
         }
     }
 
-    // Rollouts (most recent N by mtime)
-    if sources.contains("rollouts") && results.len() < limit {
-        let mut rollout_files: Vec<(String, PathBuf, std::time::SystemTime)> = Vec::new();
-        if let Ok(mut rd) = tokio::fs::read_dir(&rollouts_dir).await {
-            while let Ok(Some(entry)) = rd.next_entry().await {
-                let name = entry.file_name().to_string_lossy().to_string();
-                if !name.ends_with(".jsonl") || !is_safe_segment(&name) {
-                    continue;
-                }
-                if let Ok(m) = entry.metadata().await {
-                    if !m.is_file() {
-                        continue;
-                    }
-                    let mt = m.modified().unwrap_or(std::time::UNIX_EPOCH);
-                    rollout_files.push((name, entry.path(), mt));
-                }
-            }
-        }
-        rollout_files.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| b.0.cmp(&a.0)));
+    // Rollouts (most recent N by mtime across rollout sources)
+    if results.len() < limit {
+        let mut rollout_files: Vec<(&str, integrations::codex::SourceFile)> = configured
+            .iter()
+            .filter(|src| src.kind == "rollouts")
+            .flat_map(|src| src.files().into_iter().map(move |f| (src.id.as_str(), f)))
+            .collect();
+        rollout_files.sort_by(|a, b| b.1.modified.cmp(&a.1.modified).then_with(|| b.1.name.cmp(&a.1.name)));
         rollout_files.truncate(limit_files);
 
-        for (name, path, _mt) in rollout_files {
+        for (source, f) in rollout_files {
             if results.len() >= limit {
                 break;
             }
             scanned_files += 1;
             if let Err(e) = search_jsonl_file_tail(
-                "meta3_rollouts",
-                &name,
-                &path,
+                source,
+                &f.name,
+                &f.path,
                 &query,
                 case_sensitive,
                 compiled.as_ref(),
@@ -2225,35 +2739,28 @@ pub async fn codex_search_handler(
         }
     }
 
-    // UTIR normalized (option b)
-    if sources.contains("utir") && results.len() < limit {
-        for (label, path) in [
-            ("normalized_history.jsonl", utir_history),
-            ("normalized_codex.jsonl", utir_codex),
-        ] {
-            if results.len() >= limit {
-                break;
+    // UTIR normalized files
+    for src in configured.iter().filter(|src| src.kind == "utir") {
+        for f in src.files() {
+            if results.len() >= limit || f.len == 0 {
+                continue;
             }
-            if let Ok(m) = tokio::fs::metadata(&path).await {
-                if m.is_file() && m.len() > 0 {
-                    scanned_files += 1;
-                    if let Err(e) = search_jsonl_file_tail(
-                        "utir",
-                        label,
-                        &path,
-                        &query,
-                        case_sensitive,
-                        compiled.as_ref(),
-                        limit_lines,
-                        max_bytes,
-                        limit,
-                        &mut results,
-                    )
-                    .await
-                    {
-                        return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
-                    }
-                }
+            scanned_files += 1;
+            if let Err(e) = search_jsonl_file_tail(
+                &src.id,
+                &f.name,
+                &f.path,
+                &query,
+                case_sensitive,
+                compiled.as_ref(),
+                limit_lines,
+                max_bytes,
+                limit,
+                &mut results,
+            )
+            .await
+            {
+                return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
             }
         }
     }
@@ -2265,10 +2772,101 @@ pub async fn codex_search_handler(
         scanned_files,
         results,
         truncated,
+        index: index_status,
+    })
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CodexSessionsQuery {
+    pub limit: Option<usize>,
+    /// Only sessions that ran a command or touched a file containing this substring.
+    pub contains: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CodexSessionsResp {
+    pub generated_at: String,
+    pub total: usize,
+    pub sessions: Vec<integrations::codex::SessionSummary>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/codex/sessions",
+    params(
+        ("limit" = Option<usize>, Query, description = "Max sessions, newest first (default 50, max 1000)"),
+        ("contains" = Option<String>, Query, description = "Filter by command/file substring")
+    ),
+    responses(
+        (status = 200, description = "Per-session index built by codex.import", body = CodexSessionsResp),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not found (disabled)")
+    )
+)]
+pub async fn codex_sessions_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<CodexSessionsQuery>,
+) -> impl IntoResponse {
+    if !codex_history_enabled() {
+        return disabled();
+    }
+    let api_key = match extract_api_key(&headers) {
+        Some(k) => k,
+        None => return unauthorized("Missing x-api-key"),
+    };
+    if authenticate_user(&state, &api_key).is_none() {
+        return unauthorized("Invalid x-api-key");
+    }
+
+    let limit = clamp_limit(q.limit, 50, 1000);
+    let needle = q.contains.as_deref().map(|s| s.trim().to_ascii_lowercase()).filter(|s| !s.is_empty());
+    let mut sessions = integrations::codex::load_sessions();
+    if let Some(n) = needle.as_deref() {
+        sessions.retain(|s| {
+            s.commands.iter().chain(s.files_touched.iter()).any(|x| x.to_ascii_lowercase().contains(n))
+        });
+    }
+    let total = sessions.len();
+    sessions.truncate(limit);
+    Json(CodexSessionsResp {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        total,
+        sessions,
     })
     .into_response()
 }
 
+#[utoipa::path(
+    get,
+    path = "/codex/index",
+    responses(
+        (status = 200, description = "Freshness of the token index behind /codex/search", body = integrations::codex_index::IndexStatus),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not found (disabled)")
+    )
+)]
+pub async fn codex_index_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !codex_history_enabled() {
+        return disabled();
+    }
+    let api_key = match extract_api_key(&headers) {
+        Some(k) => k,
+        None => return unauthorized("Missing x-api-key"),
+    };
+    if authenticate_user(&state, &api_key).is_none() {
+        return unauthorized("Invalid x-api-key");
+    }
+    let status = tokio::task::spawn_blocking(integrations::codex_index::status)
+        .await
+        .unwrap_or_default();
+    Json(status).into_response()
+}
+
 #[utoipa::path(
     get,
     path = "/ruliad/{run_id}",
@@ -2327,6 +2925,7 @@ pub async fn ruliad_list_handler(Path(run_id): Path<String>) -> impl IntoRespons
     responses((status = 200, description = "Fetch ruliad.kernel artifact"))
 )]
 pub async fn ruliad_file_handler(
+    headers: HeaderMap,
     Path((run_id, file)): Path<(String, String)>,
 ) -> impl IntoResponse {
     if !is_safe_segment(&run_id) || !is_safe_segment(&file) {
@@ -2347,13 +2946,6 @@ pub async fn ruliad_file_handler(
         )
             .into_response();
     }
-    let body = match tokio::fs::read(&path).await {
-        Ok(b) => b,
-        Err(e) => {
-            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-        }
-    };
-
     let ctype = if file.ends_with(".dot") {
         "text/vnd.graphviz; charset=utf-8"
     } else if file.ends_with(".json") || file.ends_with(".jsonl") {
@@ -2364,51 +2956,855 @@ pub async fn ruliad_file_handler(
         "application/octet-stream"
     };
 
-    (
-        axum::http::StatusCode::OK,
-        [(axum::http::header::CONTENT_TYPE, ctype)],
-        body,
-    )
-        .into_response()
-}
-
-#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
-pub struct ValidationResult {
-    pub task: String,
-    pub expected_difficulty: f32,
-    pub actual_bits: Bits,
-    pub score: f32,
-}
-
-#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
-pub struct VersionInfo {
-    pub engine: &'static str,
-    pub build_token: Option<&'static str>,
-    pub git_ref: Option<&'static str>,
-    pub ts: String,
+    artifacts::serve_file(&headers, &path, ctype).await
 }
 
-impl VersionInfo {
-    pub fn current() -> Self {
-        let ts = chrono::Utc::now().to_rfc3339();
-        Self {
-            engine: env!("CARGO_PKG_VERSION"),
-            build_token: option_env!("BUILD_TOKEN"),
-            git_ref: option_env!("GIT_REF"),
-            ts,
+#[utoipa::path(
+    get,
+    path = "/runs/{path}",
+    params(("path" = String, Path, description = "Artifact path under META3_ROOT/runs")),
+    responses(
+        (status = 200, description = "Artifact body (ETag/Last-Modified set)"),
+        (status = 206, description = "Partial content for `Range: bytes=...`"),
+        (status = 304, description = "Not modified (If-None-Match / If-Modified-Since)"),
+        (status = 401, description = "The run belongs to a user; send their x-api-key (or the admin key)"),
+        (status = 404, description = "Not found (or not readable with this key)")
+    )
+)]
+pub async fn runs_artifact_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(tail): Path<String>,
+    Query(q): Query<LogTailQuery>,
+) -> impl IntoResponse {
+    {
+        use integrations::receipt_acl::{self, Caller, Scope};
+        let caller = receipt_caller(&state, &headers);
+        let allowed = match receipt_acl::scope(&tail) {
+            Scope::Run(run_id) => receipt_acl::caller_can_read(&run_id, &caller).await,
+            Scope::Private => caller == Caller::Admin,
+            Scope::Open => true,
+        };
+        if !allowed {
+            return if caller == Caller::Anonymous && extract_api_key(&headers).is_none() {
+                unauthorized("Missing x-api-key")
+            } else {
+                (StatusCode::NOT_FOUND, "not found".to_string()).into_response()
+            };
+        }
+    }
+    if let Some((run_id, "log/tail")) = tail.trim_matches('/').split_once('/') {
+        if is_safe_segment(run_id) {
+            return run_log_tail_handler(Path(run_id.to_string()), Query(q)).await.into_response();
+        }
+    }
+    // `/runs/{run_id}/receipt` shares the `/runs/*path` catch-all (the router can't hold both).
+    if let Some((run_id, "receipt")) = tail.trim_matches('/').split_once('/') {
+        if is_safe_segment(run_id) {
+            return receipt_html_handler(Path(run_id.to_string())).await.into_response();
+        }
+    }
+    if let Some((run_id, "artifacts")) = tail.trim_matches('/').split_once('/') {
+        if is_safe_segment(run_id) {
+            return run_artifacts_handler(Path(run_id.to_string())).await.into_response();
+        }
+    }
+    if let Some((run_id, "bundle.zip")) = tail.trim_matches('/').split_once('/') {
+        if is_safe_segment(run_id) {
+            return run_bundle_handler(Path(run_id.to_string())).await.into_response();
+        }
+    }
+    let root = meta3_root().join("runs");
+    // `/runs/{run_id}`: single segments that aren't files under runs/ (e.g. `api_trace.jsonl`).
+    let single = tail.trim_matches('/');
+    if is_safe_segment(single) && !root.join(single).is_file() {
+        return run_status_handler(Path(single.to_string())).await.into_response();
+    }
+    let Some(path) = artifacts::resolve_under(&root, &tail) else {
+        return (StatusCode::BAD_REQUEST, "invalid path".to_string()).into_response();
+    };
+    let ctype = artifacts::content_type_for(&path);
+    // Receipts kept in memory (read-only META3_ROOT) have no file to serve.
+    if one_engine::storage::mode() == one_engine::storage::Mode::Memory && !path.is_file() {
+        if let Ok(body) = one_engine::storage::read(&path) {
+            return ([(axum::http::header::CONTENT_TYPE, ctype)], body).into_response();
         }
     }
+    let mut path = one_engine::storage::resolve(&path);
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    artifacts::serve_file(&headers, &path, ctype).await
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LogTailQuery {
+    /// Byte offset to read from (the previous call's `next_offset`).
+    pub offset: Option<u64>,
+    /// Milliseconds to wait for new output before answering (long-poll; default 0, max 30000).
+    pub wait_ms: Option<u64>,
 }
 
 #[utoipa::path(
     get,
-    path = "/version",
+    path = "/runs/{run_id}/log/tail",
+    params(
+        ("run_id" = String, Path, description = "Run id of a meta3.build run"),
+        ("offset" = Option<u64>, Query, description = "Byte offset to read from (default 0)"),
+        ("wait_ms" = Option<u64>, Query, description = "Long-poll: wait up to this long for new output (max 30000)")
+    ),
     responses(
-        (status = 200, description = "Engine version", body = VersionInfo)
+        (status = 200, description = "Build log output since `offset`; poll again from `next_offset` until `complete` and caught up", body = live_log::LogTail),
+        (status = 404, description = "No build log for this run (yet)")
     )
 )]
-pub async fn version_handler() -> impl IntoResponse {
-    Json(VersionInfo::current())
+pub async fn run_log_tail_handler(
+    Path(run_id): Path<String>,
+    Query(q): Query<LogTailQuery>,
+) -> impl IntoResponse {
+    if !is_safe_segment(&run_id) {
+        return (StatusCode::BAD_REQUEST, "invalid run_id".to_string()).into_response();
+    }
+    let wait = std::time::Duration::from_millis(q.wait_ms.unwrap_or(0));
+    match live_log::tail(&run_id, q.offset.unwrap_or(0), wait).await {
+        Some(t) => Json(t).into_response(),
+        None => (StatusCode::NOT_FOUND, "no build log for this run".to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunArtifact {
+    /// Path relative to META3_ROOT (absolute when the file lives outside it).
+    pub path: String,
+    /// "receipt" (receipt dir) or "deliverable" (manifest deliverables).
+    pub source: String,
+    /// Download link, for files under `runs/`.
+    pub url: Option<String>,
+    pub exists: bool,
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    pub sha256: Option<String>,
+    pub modified: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunArtifactsResp {
+    pub run_id: String,
+    pub count: usize,
+    pub total_bytes: u64,
+    pub artifacts: Vec<RunArtifact>,
+}
+
+const RUN_ARTIFACTS_MAX: usize = 2_000;
+
+async fn run_artifact_entry(root: &StdPath, path: &StdPath, source: &str) -> RunArtifact {
+    let rel = path.strip_prefix(root).ok().map(|p| p.to_string_lossy().replace('\\', "/"));
+    let url = rel
+        .as_deref()
+        .filter(|r| r.starts_with("runs/"))
+        .map(|r| url_for(&format!("/{}", r)));
+    let info = artifacts::file_info(path).await;
+    RunArtifact {
+        path: rel.unwrap_or_else(|| path.display().to_string()),
+        source: source.to_string(),
+        url,
+        exists: info.is_some(),
+        size: info.as_ref().map(|i| i.size),
+        content_type: info.as_ref().map(|i| i.content_type.clone()),
+        sha256: info.as_ref().and_then(|i| i.sha256.clone()),
+        modified: info.and_then(|i| i.modified),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunTiming {
+    /// When the run was accepted (first receipt write, or the active-run entry).
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<u64>,
+    /// Seconds left for an active run, from its goal's p50/p90 duration history.
+    pub eta_s: Option<u64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunLinks {
+    pub receipt_url: String,
+    pub receipt_html_url: String,
+    pub artifacts_url: String,
+    pub bundle_url: String,
+    pub sse_url: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunStatusResp {
+    pub run_id: String,
+    pub goal_id: String,
+    /// queued|running|done|error|cancelled|interrupted
+    pub status: String,
+    /// `true` once no further updates are expected.
+    pub terminal: bool,
+    pub success: Option<bool>,
+    #[schema(value_type = Option<Object>)]
+    pub bits: Option<Value>,
+    /// Present once the run has finished.
+    #[schema(value_type = Option<Object>)]
+    pub manifest: Option<Value>,
+    pub error: Option<String>,
+    pub timing: RunTiming,
+    pub links: RunLinks,
+}
+
+fn run_links(run_id: &str) -> RunLinks {
+    RunLinks {
+        receipt_url: url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id)),
+        receipt_html_url: url_for(&format!("/runs/{}/receipt", run_id)),
+        artifacts_url: url_for(&format!("/runs/{}/artifacts", run_id)),
+        bundle_url: url_for(&format!("/runs/{}/bundle.zip", run_id)),
+        sse_url: url_for(&format!("/progress.sse?run_id={}", run_id)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}",
+    params(("run_id" = String, Path, description = "Run id")),
+    responses(
+        (status = 200, description = "Run status, bits and (when finished) manifest, for polling async runs", body = RunStatusResp),
+        (status = 404, description = "Unknown run")
+    )
+)]
+pub async fn run_status_handler(Path(run_id): Path<String>) -> impl IntoResponse {
+    if !is_safe_segment(&run_id) {
+        return (StatusCode::BAD_REQUEST, "invalid run_id".to_string()).into_response();
+    }
+    match run_status(&run_id).await {
+        Some(resp) => Json(resp).into_response(),
+        None => (StatusCode::NOT_FOUND, "unknown run".to_string()).into_response(),
+    }
+}
+
+/// Status of an active or indexed run; `None` when `run_id` is unknown.
+pub(crate) async fn run_status(run_id: &str) -> Option<RunStatusResp> {
+    // In flight: the receipt (if any) is only the queued stub.
+    if let Some(a) = ACTIVE_RUNS.lock().await.get(run_id).cloned() {
+        let since = chrono::DateTime::parse_from_rfc3339(&a.ts).ok();
+        return Some(RunStatusResp {
+            run_id: run_id.to_string(),
+            goal_id: a.goal_id,
+            status: a.status,
+            terminal: false,
+            success: None,
+            bits: None,
+            manifest: None,
+            error: None,
+            timing: RunTiming {
+                started_at: Some(a.ts),
+                finished_at: None,
+                duration_ms: since.map(|t| {
+                    (chrono::Utc::now() - t.with_timezone(&chrono::Utc))
+                        .num_milliseconds()
+                        .max(0) as u64
+                }),
+                eta_s: integrations::progress::eta_s(run_id),
+            },
+            links: run_links(run_id),
+        });
+    }
+    let d = integrations::run_index::get(run_id).await?;
+
+    let timing_field = |k: &str| {
+        d.timing
+            .get(k)
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+    let manifest = d.response.get("manifest").filter(|m| m.is_object()).cloned();
+    let status = match (d.status.as_deref(), d.record.success) {
+        (Some(s), _) => s.to_string(),
+        (None, Some(false)) => "error".to_string(),
+        (None, _) => "done".to_string(),
+    };
+    let error = manifest
+        .as_ref()
+        .and_then(|m| m.pointer("/evidence/error"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    Some(RunStatusResp {
+        run_id: run_id.to_string(),
+        goal_id: d.record.goal_id.clone(),
+        terminal: !matches!(status.as_str(), "queued" | "running"),
+        status,
+        success: d.record.success,
+        bits: d.record.bits.clone(),
+        // Stub receipts (queued/interrupted/...) carry no manifest worth returning.
+        manifest: if d.status.is_none() { manifest } else { None },
+        error,
+        timing: RunTiming {
+            started_at: timing_field("started_at"),
+            finished_at: timing_field("finished_at"),
+            duration_ms: d.record.latency_ms,
+            eta_s: None,
+        },
+        links: run_links(run_id),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/artifacts",
+    params(("run_id" = String, Path, description = "Run id")),
+    responses(
+        (status = 200, description = "Files in the receipt dir plus manifest deliverables, with sizes, content types and sha256", body = RunArtifactsResp),
+        (status = 404, description = "Receipt not found")
+    )
+)]
+pub async fn run_artifacts_handler(Path(run_id): Path<String>) -> impl IntoResponse {
+    if !is_safe_segment(&run_id) {
+        return (StatusCode::BAD_REQUEST, "invalid run_id".to_string()).into_response();
+    }
+    let Some((_, _, found)) = collect_run_artifacts(&run_id).await else {
+        return (StatusCode::NOT_FOUND, "receipt not found".to_string()).into_response();
+    };
+    let artifacts: Vec<RunArtifact> = found.into_iter().map(|(_, a)| a).collect();
+    Json(RunArtifactsResp {
+        run_id,
+        count: artifacts.len(),
+        total_bytes: artifacts.iter().filter_map(|a| a.size).sum(),
+        artifacts,
+    })
+    .into_response()
+}
+
+/// Receipt-dir files (recursive, symlinks not followed) then manifest deliverables under
+/// META3_ROOT, as `(canonical root, receipt dir, [(absolute path, entry)])`. `None` when the
+/// receipt dir is missing.
+async fn collect_run_artifacts(
+    run_id: &str,
+) -> Option<(PathBuf, PathBuf, Vec<(PathBuf, RunArtifact)>)> {
+    let root = meta3_root();
+    let root = std::fs::canonicalize(&root).unwrap_or(root);
+    let receipt_dir = root.join("runs/receipts").join(run_id);
+    if !receipt_dir.is_dir() {
+        return None;
+    }
+
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut out: Vec<(PathBuf, RunArtifact)> = Vec::new();
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut stack = vec![receipt_dir.clone()];
+    while let Some(dir) = stack.pop() {
+        let Ok(mut rd) = fs::read_dir(&dir).await else {
+            continue;
+        };
+        while let Ok(Some(ent)) = rd.next_entry().await {
+            let Ok(ft) = ent.file_type().await else {
+                continue;
+            };
+            if ft.is_dir() {
+                stack.push(ent.path());
+            } else if ft.is_file() && files.len() < RUN_ARTIFACTS_MAX {
+                files.push(ent.path());
+            }
+        }
+    }
+    files.sort();
+    for f in files {
+        seen.insert(f.clone());
+        let entry = run_artifact_entry(&root, &f, "receipt").await;
+        out.push((f, entry));
+    }
+
+    let resp = read_receipt_response_json(run_id).await.unwrap_or(Value::Null);
+    let deliverables = resp
+        .get("manifest")
+        .and_then(|m| m.get("deliverables"))
+        .or_else(|| resp.get("deliverables"))
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    for d in deliverables.iter().filter_map(|v| v.as_str()) {
+        if d.starts_with("http://") || d.starts_with("https://") || d.contains("..") {
+            continue;
+        }
+        let p = StdPath::new(d);
+        let p = if p.is_absolute() { p.to_path_buf() } else { root.join(p) };
+        let p = std::fs::canonicalize(&p).unwrap_or(p);
+        if !p.starts_with(&root) || p.is_dir() || !seen.insert(p.clone()) {
+            continue;
+        }
+        let entry = run_artifact_entry(&root, &p, "deliverable").await;
+        out.push((p, entry));
+    }
+    Some((root, receipt_dir, out))
+}
+
+#[derive(Debug, Serialize)]
+struct BundleIndexEntry<'a> {
+    /// Name inside the zip; `None` when the file was left out.
+    zip_path: Option<String>,
+    skipped: Option<&'static str>,
+    #[serde(flatten)]
+    artifact: &'a RunArtifact,
+}
+
+fn bundle_limit(key: &str, default: u64) -> u64 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(default)
+}
+
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/bundle.zip",
+    params(("run_id" = String, Path, description = "Run id")),
+    responses(
+        (status = 200, description = "Zip of the receipt dir and size-capped deliverables, with index.json", content_type = "application/zip"),
+        (status = 404, description = "Receipt not found")
+    )
+)]
+pub async fn run_bundle_handler(Path(run_id): Path<String>) -> impl IntoResponse {
+    if !is_safe_segment(&run_id) {
+        return (StatusCode::BAD_REQUEST, "invalid run_id".to_string()).into_response();
+    }
+    let Some((root, receipt_dir, found)) = collect_run_artifacts(&run_id).await else {
+        return (StatusCode::NOT_FOUND, "receipt not found".to_string()).into_response();
+    };
+    let max_file = bundle_limit("ONE_ENGINE_BUNDLE_MAX_FILE_BYTES", 25 * 1024 * 1024);
+    let max_total = bundle_limit("ONE_ENGINE_BUNDLE_MAX_BYTES", 200 * 1024 * 1024);
+
+    // Decide membership up front so index.json (written first) matches the zip contents.
+    let mut total = 0u64;
+    let mut members: Vec<(PathBuf, String)> = Vec::new();
+    let mut index: Vec<BundleIndexEntry> = Vec::new();
+    for (path, a) in &found {
+        let zip_path = match a.source.as_str() {
+            "receipt" => path
+                .strip_prefix(&receipt_dir)
+                .ok()
+                .map(|p| format!("receipt/{}", p.to_string_lossy().replace('\\', "/"))),
+            _ => path
+                .strip_prefix(&root)
+                .ok()
+                .map(|p| format!("deliverables/{}", p.to_string_lossy().replace('\\', "/"))),
+        };
+        let size = a.size.unwrap_or(0);
+        // Receipt files are always small; the per-file cap only applies to deliverables.
+        let skipped = if !a.exists {
+            Some("missing")
+        } else if a.source == "deliverable" && size > max_file {
+            Some("file_too_large")
+        } else if total + size > max_total {
+            Some("bundle_size_cap")
+        } else {
+            None
+        };
+        let zip_path = zip_path.filter(|_| skipped.is_none());
+        if let Some(z) = &zip_path {
+            total += size;
+            members.push((path.clone(), z.clone()));
+        }
+        index.push(BundleIndexEntry {
+            zip_path,
+            skipped,
+            artifact: a,
+        });
+    }
+    let index_json = serde_json::to_vec_pretty(&json!({
+        "run_id": run_id,
+        "generated_at": chrono::Utc::now().to_rfc3339(),
+        "receipt_url": url_for(&format!("/runs/{}/receipt", run_id)),
+        "max_file_bytes": max_file,
+        "max_bytes": max_total,
+        "entries": index,
+    }))
+    .unwrap_or_default();
+
+    let body = bundle::stream_zip(index_json, members);
+    let mut headers = HeaderMap::new();
+    headers.insert(axum::http::header::CONTENT_TYPE, axum::http::HeaderValue::from_static("application/zip"));
+    if let Ok(v) = axum::http::HeaderValue::from_str(&format!(
+        "attachment; filename=\"run-{}.zip\"",
+        run_id
+    )) {
+        headers.insert(axum::http::header::CONTENT_DISPOSITION, v);
+    }
+    (StatusCode::OK, headers, body).into_response()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RollbackResp {
+    /// Run id of the rollback itself (its own receipt).
+    pub run_id: String,
+    pub rolled_back_run_id: String,
+    pub restored: Vec<String>,
+    pub removed: Vec<String>,
+    pub receipt_url: String,
+}
+
+/// POST counterpart of `/runs/*path` (dispatches `<run_id>/rollback` and `<run_id>/share`).
+pub async fn runs_action_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(tail): Path<String>,
+    body: Option<Json<ShareReq>>,
+) -> impl IntoResponse {
+    match tail.trim_matches('/').split_once('/') {
+        Some((run_id, "rollback")) if is_safe_segment(run_id) => {
+            rollback_handler(State(state), headers, Path(run_id.to_string())).await.into_response()
+        }
+        Some((run_id, "share")) if is_safe_segment(run_id) => {
+            share_handler(State(state), headers, Path(run_id.to_string()), body).await.into_response()
+        }
+        _ => (StatusCode::NOT_FOUND, "not found".to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ShareReq {
+    /// `false` makes the run private to its owner again (default `true`).
+    #[serde(default)]
+    pub public: Option<bool>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/runs/{run_id}/share",
+    params(("run_id" = String, Path, description = "Run to share")),
+    request_body = ShareReq,
+    responses(
+        (status = 200, description = "The run's access list", body = integrations::receipt_acl::Acl),
+        (status = 401, description = "Missing x-api-key"),
+        (status = 404, description = "No such run, or not its owner")
+    )
+)]
+pub async fn share_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(run_id): Path<String>,
+    body: Option<Json<ShareReq>>,
+) -> impl IntoResponse {
+    use integrations::receipt_acl::{self, Caller};
+    let caller = receipt_caller(&state, &headers);
+    if caller == Caller::Anonymous {
+        return unauthorized("Missing or invalid x-api-key");
+    }
+    if !is_safe_segment(&run_id) {
+        return (StatusCode::BAD_REQUEST, "invalid run_id".to_string()).into_response();
+    }
+    let public = body.and_then(|Json(b)| b.public).unwrap_or(true);
+    match receipt_acl::set_public(&run_id, public, &caller).await {
+        Ok(acl) => Json(acl).into_response(),
+        // Someone else's run looks the same as a missing one.
+        Err(_) => (StatusCode::NOT_FOUND, "not found".to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/runs/{run_id}/rollback",
+    params(("run_id" = String, Path, description = "Run whose pre-run snapshot to restore")),
+    responses(
+        (status = 200, description = "Snapshot restored; rollback recorded as a new receipt", body = RollbackResp),
+        (status = 401, description = "Missing x-api-key"),
+        (status = 404, description = "No snapshot for this run, or not its owner"),
+        (status = 500, description = "Restore failed")
+    )
+)]
+pub async fn rollback_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(run_id): Path<String>,
+) -> impl IntoResponse {
+    use integrations::receipt_acl::{self, Caller};
+    // Rolling back rewrites the workspace: the admin, or the user who owns the run.
+    let caller = match require_caller(&state, &headers) {
+        Ok(c) => c,
+        Err(e) => return e.into_response(),
+    };
+    if !is_safe_segment(&run_id) {
+        return (StatusCode::NOT_FOUND, "snapshot not found".to_string()).into_response();
+    }
+    let allowed = match &caller {
+        Caller::Admin => true,
+        Caller::User(u) => receipt_acl::load(&run_id).await.owner.as_deref() == Some(u.as_str()),
+        Caller::Anonymous => false,
+    };
+    if !allowed || !snapshot::exists(&run_id) {
+        return (StatusCode::NOT_FOUND, "snapshot not found".to_string()).into_response();
+    }
+    let rid = run_id.clone();
+    let restored = tokio::task::spawn_blocking(move || snapshot::restore(&rid)).await;
+    let report = match restored {
+        Ok(Ok(r)) => r,
+        Ok(Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+
+    let rollback_id = format!("r-{}", uuid::Uuid::new_v4());
+    let mut bits = Bits::init();
+    bits.t = 1.0;
+    let evidence = json!({
+        "rolled_back_run_id": run_id,
+        "restored": report.restored,
+        "removed": report.removed,
+        "snapshot_url": url_for(&format!("/runs/snapshots/{}/snapshot.json", run_id)),
+        "expected_success": true,
+        "actual_success": true
+    });
+    let resp = RollbackResp {
+        run_id: rollback_id.clone(),
+        rolled_back_run_id: run_id.clone(),
+        restored: report.restored.clone(),
+        removed: report.removed.clone(),
+        receipt_url: url_for(&format!("/runs/receipts/{}/RECEIPT.md", rollback_id)),
+    };
+    write_receipt_bundle(
+        &rollback_id,
+        "runs.rollback",
+        &bits,
+        &[],
+        &evidence,
+        true,
+        &json!({
+            "goal_id": "runs.rollback",
+            "rollback_of": run_id,
+            "actor": integrations::audit::actor_for_key(
+                headers.get("x-api-key").and_then(|v| v.to_str().ok())
+            )
+        }),
+        &resp,
+    )
+    .await;
+    emit_progress(&rollback_id, "runs.rollback", Phase::Done, json!({ "rolled_back_run_id": run_id }));
+    integrations::progress::close(&rollback_id);
+
+    Json(resp).into_response()
+}
+
+/// Receipt run_ids from the run index, oldest → newest. Ties on the index timestamp are
+/// broken by run_id so the order doesn't depend on directory listing order.
+async fn receipt_run_ids_in_order() -> Vec<String> {
+    let mut records = integrations::run_index::scan(None).await;
+    records.sort_by(|a, b| a.ts.cmp(&b.ts).then_with(|| a.run_id.cmp(&b.run_id)));
+    records.into_iter().map(|r| r.run_id).collect()
+}
+
+/// Whether a link target from receipt markdown may be rendered as-is: relative links and
+/// fragments, or an `http`, `https` or `mailto` scheme. Anything else (`javascript:`,
+/// `data:`, `vbscript:`, ...) is replaced with `#`.
+fn is_safe_link(dest: &str) -> bool {
+    // Browsers ignore tabs and newlines inside a scheme (`java\tscript:`), so do the same.
+    let cleaned: String = dest
+        .trim()
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect();
+    match cleaned.find([':', '/', '?', '#']) {
+        Some(i) if cleaned[i..].starts_with(':') => {
+            matches!(
+                cleaned[..i].to_ascii_lowercase().as_str(),
+                "http" | "https" | "mailto"
+            )
+        }
+        _ => true,
+    }
+}
+
+fn bits_badge_html(resp: &Value) -> String {
+    let bits = resp
+        .get("bits")
+        .or_else(|| resp.get("manifest").and_then(|m| m.get("bits")));
+    let Some(b) = bits.and_then(|v| v.as_object()) else {
+        return String::new();
+    };
+    let mut out = String::from("<span class=\"badge\">");
+    for k in ["a", "u", "p", "e", "d", "t"] {
+        if let Some(v) = b.get(k).and_then(|v| v.as_f64()) {
+            let cls = match k {
+                "e" | "d" if v > 0.0 => "bad",
+                "t" if v >= 0.7 => "good",
+                "u" if v >= 0.5 => "warn",
+                _ => "",
+            };
+            out.push_str(&format!("<span class=\"bit {}\">{}={:.2}</span>", cls, k, v));
+        }
+    }
+    out.push_str("</span>");
+    out
+}
+
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/receipt",
+    params(("run_id" = String, Path, description = "Run id")),
+    responses(
+        (status = 200, description = "RECEIPT.md rendered as HTML with navigation"),
+        (status = 404, description = "Receipt not found")
+    )
+)]
+pub async fn receipt_html_handler(Path(run_id): Path<String>) -> impl IntoResponse {
+    if !is_safe_segment(&run_id) {
+        return (StatusCode::BAD_REQUEST, "invalid run_id".to_string()).into_response();
+    }
+    let dir = meta3_root().join("runs/receipts").join(&run_id);
+    let md = match fs::read_to_string(dir.join("RECEIPT.md")).await {
+        Ok(s) => s,
+        Err(_) => return (StatusCode::NOT_FOUND, "receipt not found".to_string()).into_response(),
+    };
+    let resp = read_receipt_response_json(&run_id).await.unwrap_or(Value::Null);
+
+    // Render markdown; raw HTML from receipts (replies, notes) is escaped, not trusted.
+    let mut body = String::new();
+    {
+        use pulldown_cmark::{html, Event, Options, Parser, Tag};
+        let parser = Parser::new_ext(&md, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH)
+            .map(|ev| match ev {
+                Event::Html(h) | Event::InlineHtml(h) => Event::Text(h),
+                Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) if !is_safe_link(&dest_url) => Event::Start(Tag::Link {
+                    link_type,
+                    dest_url: "#".into(),
+                    title,
+                    id,
+                }),
+                Event::Start(Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                }) if !is_safe_link(&dest_url) => Event::Start(Tag::Image {
+                    link_type,
+                    dest_url: "#".into(),
+                    title,
+                    id,
+                }),
+                other => other,
+            });
+        html::push_html(&mut body, parser);
+    }
+
+    let mut files = String::new();
+    for f in ["stdout.txt", "reply.txt", "request.json", "response.json", "RECEIPT.md"] {
+        if fs::metadata(dir.join(f)).await.is_ok() {
+            files.push_str(&format!(
+                "<a href=\"{}\">{}</a> ",
+                url_for(&format!("/runs/receipts/{}/{}", run_id, f)),
+                f
+            ));
+        }
+    }
+
+    let ids = receipt_run_ids_in_order().await;
+    let pos = ids.iter().position(|r| r == &run_id);
+    let prev = pos.and_then(|i| i.checked_sub(1)).and_then(|i| ids.get(i));
+    let next = pos.and_then(|i| ids.get(i + 1));
+    let nav_link = |label: &str, target: Option<&String>| match target {
+        Some(r) => format!(
+            "<a href=\"{}\">{}</a>",
+            url_for(&format!("/runs/{}/receipt", r)),
+            label
+        ),
+        None => format!("<span class=\"muted\">{}</span>", label),
+    };
+
+    let mut html = String::new();
+    html.push_str("<!doctype html><html><head><meta charset=\"utf-8\">");
+    html.push_str(&format!("<title>Receipt {}</title>", escape_html(&run_id)));
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width,initial-scale=1\">");
+    html.push_str("<style>body{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px;max-width:960px} a{color:#1f6feb;text-decoration:none} a:hover{text-decoration:underline} code,pre{background:#f6f8fa;border-radius:6px} code{padding:2px 6px} pre{padding:12px;overflow:auto} .muted{color:#57606a} .nav{display:flex;gap:14px;flex-wrap:wrap;align-items:center;margin-bottom:12px} .bit{display:inline-block;padding:1px 8px;margin-right:4px;border-radius:999px;border:1px solid #d0d7de;background:#f8f9fa;font-size:12px} .bit.good{background:#dafbe1} .bit.warn{background:#fff8c5} .bit.bad{background:#ffebe9}</style>");
+    html.push_str("</head><body>");
+    html.push_str(&format!(
+        "<div class=\"nav\">{} · {} · <a href=\"{}\">Browse</a> {}</div>",
+        nav_link("← prev", prev),
+        nav_link("next →", next),
+        url_for("/browse"),
+        bits_badge_html(&resp)
+    ));
+    html.push_str(&format!("<div class=\"nav muted\">Files: {}</div>", files));
+    html.push_str(&body);
+    html.push_str("</body></html>");
+
+    Html(html).into_response()
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct VersionInfo {
+    pub engine: &'static str,
+    pub build_token: Option<&'static str>,
+    pub git_ref: Option<&'static str>,
+    pub ts: String,
+    /// Where writes go: writable, scratch, memory or read-only (`META3_ROOT` not writable).
+    pub storage: String,
+    /// Present while simulation mode is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simulation: Option<crate::engine::simulation::SimulationStatus>,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        let ts = chrono::Utc::now().to_rfc3339();
+        Self {
+            engine: env!("CARGO_PKG_VERSION"),
+            build_token: option_env!("BUILD_TOKEN"),
+            git_ref: option_env!("GIT_REF"),
+            ts,
+            storage: one_engine::storage::mode().as_str().to_string(),
+            simulation: crate::engine::simulation::status(),
+        }
+    }
+}
+
+/// Built-in goals and the alias map (`GET /goals`).
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalCatalogResp {
+    pub goals: Vec<catalog::GoalEntry>,
+    /// Old ids that still run as their `to` goal.
+    pub aliases: Vec<catalog::GoalAlias>,
+    /// Goal families routed to a variant by the current bits (`goal_variants:`).
+    pub variants: Vec<crate::engine::routing::GoalFamily>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/goals",
+    responses(
+        (status = 200, description = "Goal catalog and alias/deprecation map", body = GoalCatalogResp)
+    )
+)]
+pub async fn goals_catalog_handler() -> impl IntoResponse {
+    Json(GoalCatalogResp {
+        goals: catalog::goals(),
+        aliases: catalog::aliases(),
+        variants: crate::engine::routing::families(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/progress/phases",
+    responses(
+        (status = 200, description = "Progress phases in lifecycle order, with which ones end a run", body = Vec<integrations::progress::PhaseInfo>)
+    )
+)]
+pub async fn progress_phases_handler() -> impl IntoResponse {
+    Json(integrations::progress::phases())
+}
+
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses(
+        (status = 200, description = "Engine version", body = VersionInfo)
+    )
+)]
+pub async fn version_handler() -> impl IntoResponse {
+    Json(VersionInfo::current())
 }
 
 #[utoipa::path(
@@ -2416,26 +3812,50 @@ pub async fn version_handler() -> impl IntoResponse {
     path = "/run",
     request_body = RunReq,
     responses(
-        (status = 200, description = "Run completed", body = RunResp)
+        (status = 200, description = "Run completed", body = RunResp),
+        (status = 403, description = "Goal switched off by a flag")
     )
 )]
 pub async fn run_handler(
     State(_state): State<AppState>,
     Json(req): Json<RunReq>,
 ) -> impl IntoResponse {
+    match execute_run(req, None, "run").await {
+        Ok(resp) => Json(resp).into_response(),
+        Err(e) if flags::is_disabled(&e) => (axum::http::StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) => (axum::http::StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// `POST /run` minus HTTP: policy chain (with `user`'s layer when given), progress events,
+/// receipt. The MCP server calls this too, so its tools are gated exactly like HTTP runs.
+pub(crate) async fn execute_run(
+    req: RunReq,
+    user: Option<&UserContext>,
+    kind: &str,
+) -> Result<RunResp, String> {
+    if !req.depends_on.is_empty() {
+        return Err("depends_on needs /run.async (a synchronous run can't wait)".to_string());
+    }
+    let (policy_effective, policy_chain) =
+        resolve_policy_chain("run", &req.goal_id, user, req.policy.clone());
     let mpayload = Mpayload {
         goal_id: req.goal_id.clone(),
         inputs: req.inputs.clone(),
-        policy_effective: resolve_policy("run", None, req.policy.clone()),
+        policy_effective,
         policy_request: req.policy.clone(),
+        policy_chain,
         ctx: MpayloadCtx {
-            kind: "run".to_string(),
-            user_id: None,
+            kind: kind.to_string(),
+            user_id: user.map(|u| u.user_id.clone()),
             thread: None,
             run_id: req
                 .run_id
                 .clone()
                 .unwrap_or_else(|| "auto".to_string()),
+            correlation_id: correlation::current(),
+            priority: resolve_priority(kind, user, req.priority),
+            dependencies: None,
         },
     };
     let policy = mpayload.policy_effective.clone();
@@ -2445,14 +3865,21 @@ pub async fn run_handler(
         .filter(|s| is_safe_segment(s))
         .map(|s| s.to_string())
         .unwrap_or_else(|| format!("r-{}", uuid::Uuid::new_v4()));
-    emit_progress(&run_id, &req.goal_id, "init", json!({}));
-    match run_with_integrations(&req.goal_id, req.inputs, &policy, &run_id).await {
+    track_run_eta(&run_id, &req.goal_id).await;
+    emit_progress(&run_id, &req.goal_id, Phase::Init, json!({}));
+    // The caller is waiting on the response, so a sync run is never preempted.
+    let _slot = integrations::run_queue::Ticket::new(mpayload.ctx.priority, false)
+        .acquire()
+        .await;
+    let user_id = user.map(|u| u.user_id.as_str());
+    match run_with_quota(user_id, &req.goal_id, req.inputs, &policy, &run_id).await {
         Ok((mut manifest, bits, pr_id, meta2_proposal)) => {
             manifest.run_id = run_id;
+            record_run_duration(&manifest).await;
             emit_progress(
                 &manifest.run_id,
                 &manifest.goal_id,
-                "done",
+                Phase::Done,
                 json!({
                     "pr": pr_id,
                     "bits": bits,
@@ -2460,6 +3887,7 @@ pub async fn run_handler(
                     "meta2_proposal": meta2_proposal
                 }),
             );
+            integrations::progress::close(&manifest.run_id);
 
             let resp = RunResp {
                 manifest: manifest.clone(),
@@ -2482,9 +3910,12 @@ pub async fn run_handler(
             )
             .await;
 
-            Json(resp).into_response()
+            Ok(resp)
+        }
+        Err(e) => {
+            integrations::progress::close(&run_id);
+            Err(e.to_string())
         }
-        Err(e) => (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response(),
     }
 }
 
@@ -2502,10 +3933,11 @@ pub async fn tau_handler(
         "context": req.context,
         "options": req.options
     });
-    let policy = resolve_policy("dsl", None, None);
+    let policy = resolve_policy("dsl", &goal_id, None, None);
     let run_id = format!("r-{}", uuid::Uuid::new_v4());
-    match run_with_integrations(&goal_id, inputs, &policy, &run_id).await {
+    match run_with_quota(None, &goal_id, inputs, &policy, &run_id).await {
         Ok((mut manifest, bits, pr_id, meta2_proposal)) => {
+            emit_progress(&run_id, &goal_id, Phase::Done, json!({}));
             manifest.run_id = run_id;
             Json(RunResp {
                 manifest,
@@ -2532,10 +3964,11 @@ pub async fn execute_handler(
         "task_type": req.task_type,
         "parameters": req.parameters
     });
-    let policy = resolve_policy("dsl", None, None);
+    let policy = resolve_policy("dsl", &req.goal, None, None);
     let run_id = format!("r-{}", uuid::Uuid::new_v4());
-    match run_with_integrations(&req.goal, inputs, &policy, &run_id).await {
+    match run_with_quota(None, &req.goal, inputs, &policy, &run_id).await {
         Ok((mut manifest, bits, pr_id, meta2_proposal)) => {
+            emit_progress(&run_id, &req.goal, Phase::Done, json!({}));
             manifest.run_id = run_id;
             Json(RunResp {
                 manifest,
@@ -2553,9 +3986,51 @@ pub async fn execute_handler(
     }
 }
 
-// Health alias for DSL
-pub async fn healthz_handler() -> impl IntoResponse {
-    "ok"
+#[derive(Debug, Deserialize)]
+pub struct HealthzQuery {
+    /// `1` / `true` to probe dependencies.
+    pub deep: Option<String>,
+}
+
+// Health alias for DSL; `?deep=1` probes dependencies.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    params(("deep" = Option<String>, Query, description = "1 to probe disk, router, queue and error rate")),
+    responses(
+        (status = 200, description = "`ok`, or a deep report whose status is ok or degraded", body = integrations::health::HealthReport),
+        (status = 503, description = "Deep report with status down")
+    )
+)]
+pub async fn healthz_handler(Query(q): Query<HealthzQuery>) -> impl IntoResponse {
+    let storage = one_engine::storage::mode();
+    if !matches!(q.deep.as_deref(), Some("1") | Some("true")) {
+        let body = match storage {
+            one_engine::storage::Mode::Writable => "ok".to_string(),
+            m => format!("ok (storage: {})", m.as_str()),
+        };
+        return ([("x-storage-mode", storage.as_str())], body).into_response();
+    }
+    let active: Vec<integrations::health::ActiveRunAge> = ACTIVE_RUNS
+        .lock()
+        .await
+        .values()
+        .filter_map(|r| {
+            let since = chrono::DateTime::parse_from_rfc3339(&r.ts).ok()?;
+            Some(integrations::health::ActiveRunAge {
+                run_id: r.run_id.clone(),
+                status: r.status.clone(),
+                since: since.with_timezone(&chrono::Utc),
+            })
+        })
+        .collect();
+    let report = integrations::health::deep(active).await;
+    let code = if report.status == integrations::health::Level::Down {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (code, [("x-storage-mode", storage.as_str())], Json(report)).into_response()
 }
 
 // Minimal metrics stub to avoid 404s
@@ -2628,6 +4103,38 @@ pub async fn validate_handler(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/validate/suites",
+    responses(
+        (status = 200, description = "Built-in and config/validation_suites.yaml suites usable with POST /validate", body = [validate::SuiteDef]),
+        (status = 500, description = "Suite file failed to parse")
+    )
+)]
+pub async fn validate_suites_handler() -> impl IntoResponse {
+    match validate::load_suites() {
+        Ok(suites) => Json(suites).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/secrets",
+    responses((status = 200, description = "Secret names usable as {\"$secret\": name} in inputs (values are never returned)", body = [secrets::SecretInfo]))
+)]
+pub async fn secrets_handler(headers: HeaderMap) -> impl IntoResponse {
+    let key = headers.get("x-api-key").and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !is_admin_key(key) {
+        return (StatusCode::FORBIDDEN, "admin key required".to_string()).into_response();
+    }
+    // Keychain lookups shell out; keep them off the async workers.
+    match tokio::task::spawn_blocking(secrets::list).await {
+        Ok(list) => Json(list).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/validate_golden",
@@ -2635,7 +4142,11 @@ pub async fn validate_handler(
     responses((status = 200, description = "Golden validation", body = GoldenResp))
 )]
 pub async fn validate_golden_handler(Json(req): Json<GoldenReq>) -> impl IntoResponse {
-    match engine::golden::validate_golden(&req.name).await {
+    let summary = match req.parallelism {
+        Some(n) => engine::golden::validate_golden_with(&req.name, n).await,
+        None => engine::golden::validate_golden(&req.name).await,
+    };
+    match summary {
         Ok(sum) => {
             let bits: Bits = sum.bits.into();
             Json(GoldenResp {
@@ -2645,6 +4156,8 @@ pub async fn validate_golden_handler(Json(req): Json<GoldenReq>) -> impl IntoRes
                 failed: sum.failed,
                 details: sum.details,
                 bits,
+                parallelism: sum.parallelism,
+                wall_ms: sum.wall_ms,
             })
             .into_response()
         }
@@ -2652,31 +4165,561 @@ pub async fn validate_golden_handler(Json(req): Json<GoldenReq>) -> impl IntoRes
     }
 }
 
-#[utoipa::path(
-    get,
-    path = "/dashboard",
-    responses(
-        (status = 200, description = "Unified dashboard state", body = UIState)
-    )
-)]
-pub async fn dashboard_handler() -> impl IntoResponse {
-    match integrations::ui::render_unified_state().await {
-        Ok(state) => Json(state).into_response(),
-        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TelemetryIngestReq {
+    pub events: Vec<integrations::TelemetryEvent>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TelemetryIngestResp {
+    pub accepted: usize,
+}
+
+const TELEMETRY_MAX_BATCH: usize = 1000;
+
+#[utoipa::path(
+    post,
+    path = "/telemetry",
+    request_body = TelemetryIngestReq,
+    responses(
+        (status = 200, description = "Events appended to runs/telemetry/<date>.jsonl", body = TelemetryIngestResp),
+        (status = 413, description = "Batch too large")
+    )
+)]
+pub async fn telemetry_ingest_handler(Json(req): Json<TelemetryIngestReq>) -> impl IntoResponse {
+    if req.events.len() > TELEMETRY_MAX_BATCH {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("batch too large (max {} events)", TELEMETRY_MAX_BATCH),
+        )
+            .into_response();
+    }
+    let mut events = req.events;
+    for ev in &mut events {
+        if ev.ts.trim().is_empty() {
+            ev.ts = chrono::Utc::now().to_rfc3339();
+        }
+    }
+    match integrations::telemetry::append_events(&events).await {
+        Ok(n) => Json(TelemetryIngestResp { accepted: n }).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TelemetryQuery {
+    pub component: Option<String>,
+    pub event_type: Option<String>,
+    pub run_id: Option<String>,
+    /// RFC3339 timestamp or a window like `24h` / `7d`.
+    pub since: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/telemetry/query",
+    params(
+        ("component" = Option<String>, Query, description = "Exact component match"),
+        ("event_type" = Option<String>, Query, description = "Exact event_type match"),
+        ("run_id" = Option<String>, Query, description = "Exact run_id match"),
+        ("correlation_id" = Option<String>, Query, description = "Exact correlation_id match"),
+        ("since" = Option<String>, Query, description = "RFC3339 timestamp or window (24h, 7d)"),
+        ("limit" = Option<usize>, Query, description = "Max events (newest), default 500")
+    ),
+    responses(
+        (status = 200, description = "Matching telemetry events", body = [integrations::TelemetryEvent]),
+        (status = 400, description = "Invalid since")
+    )
+)]
+pub async fn telemetry_query_handler(Query(q): Query<TelemetryQuery>) -> impl IntoResponse {
+    let since = match q.since.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s) => match chrono::DateTime::parse_from_rfc3339(s) {
+            Ok(dt) => Some(dt.with_timezone(&chrono::Utc)),
+            Err(_) => match integrations::run_index::parse_window(s) {
+                Some(w) => Some(chrono::Utc::now() - w),
+                None => {
+                    return (StatusCode::BAD_REQUEST, format!("invalid since: {}", s)).into_response();
+                }
+            },
+        },
+    };
+    let filter = integrations::telemetry::TelemetryFilter {
+        component: q.component.filter(|s| !s.is_empty()),
+        event_type: q.event_type.filter(|s| !s.is_empty()),
+        run_id: q.run_id.filter(|s| !s.is_empty()),
+        since,
+        limit: q.limit.unwrap_or(500).min(5000),
+    };
+    Json(integrations::telemetry::query(&filter).await).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiTraceQuery {
+    /// Path prefix, e.g. `/users/`.
+    pub path: Option<String>,
+    /// Exact status (`404`) or class (`5xx`).
+    pub status: Option<String>,
+    pub method: Option<String>,
+    pub run_id: Option<String>,
+    pub correlation_id: Option<String>,
+    /// RFC3339 timestamp or a window like `24h` / `7d`.
+    pub since: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api_trace/query",
+    params(
+        ("path" = Option<String>, Query, description = "Path prefix"),
+        ("status" = Option<String>, Query, description = "Exact status (404) or class (5xx)"),
+        ("method" = Option<String>, Query, description = "HTTP method"),
+        ("run_id" = Option<String>, Query, description = "Exact run_id match"),
+        ("since" = Option<String>, Query, description = "RFC3339 timestamp or window (24h, 7d)"),
+        ("offset" = Option<usize>, Query, description = "Matches to skip (use next_offset from the previous page)"),
+        ("limit" = Option<usize>, Query, description = "Page size, default 200")
+    ),
+    responses(
+        (status = 200, description = "Matching trace events, newest first, across rotated segments", body = integrations::api_trace::ApiTracePage),
+        (status = 400, description = "Invalid since"),
+        (status = 403, description = "Admin key required")
+    )
+)]
+pub async fn api_trace_query_handler(
+    headers: HeaderMap,
+    Query(q): Query<ApiTraceQuery>,
+) -> impl IntoResponse {
+    // Trace events carry every caller's paths, user ids and (optionally) request bodies.
+    let key = headers.get("x-api-key").and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !is_admin_key(key) {
+        return (StatusCode::FORBIDDEN, "admin key required".to_string()).into_response();
+    }
+    let since = match q.since.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s) => match chrono::DateTime::parse_from_rfc3339(s) {
+            Ok(dt) => Some(dt.with_timezone(&chrono::Utc)),
+            Err(_) => match integrations::run_index::parse_window(s) {
+                Some(w) => Some(chrono::Utc::now() - w),
+                None => {
+                    return (StatusCode::BAD_REQUEST, format!("invalid since: {}", s)).into_response();
+                }
+            },
+        },
+    };
+    let filter = integrations::api_trace::ApiTraceFilter {
+        path: q.path.filter(|s| !s.is_empty()),
+        status: q.status.filter(|s| !s.is_empty()),
+        method: q.method.filter(|s| !s.is_empty()),
+        run_id: q.run_id.filter(|s| !s.is_empty()),
+        correlation_id: q.correlation_id.filter(|s| !s.is_empty()),
+        since,
+        offset: q.offset.unwrap_or(0),
+        limit: q.limit.unwrap_or(200).clamp(1, 2000),
+    };
+    Json(integrations::api_trace::query(filter).await).into_response()
+}
+
+/// One run (chat turn, goal run, op, nstar loop) in a correlation tree.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CorrelationNode {
+    pub run_id: String,
+    /// Every kind recorded for this run id (a chat turn that runs a goal shows `chat`).
+    pub kinds: Vec<String>,
+    pub goal_id: Option<String>,
+    pub user_id: Option<String>,
+    pub thread: Option<String>,
+    pub first_ts: String,
+    pub receipt_url: String,
+    pub children: Vec<CorrelationNode>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CorrelationResp {
+    pub correlation_id: String,
+    /// Root runs (no recorded parent) with their ops nested under `children`.
+    pub tree: Vec<CorrelationNode>,
+    pub links: Vec<correlation::Link>,
+    /// API calls carrying this id, newest first.
+    pub api_calls: Vec<integrations::api_trace::ApiTraceEvent>,
+}
+
+fn correlation_node(
+    run_id: &str,
+    links: &[correlation::Link],
+    visited: &mut HashSet<String>,
+) -> CorrelationNode {
+    visited.insert(run_id.to_string());
+    let own: Vec<&correlation::Link> = links.iter().filter(|l| l.run_id == run_id).collect();
+    let mut kinds: Vec<String> = Vec::new();
+    for l in &own {
+        if !kinds.contains(&l.kind) {
+            kinds.push(l.kind.clone());
+        }
+    }
+    let pick = |f: fn(&correlation::Link) -> Option<String>| own.iter().find_map(|l| f(l));
+    let mut child_ids: Vec<&str> = Vec::new();
+    for l in links {
+        if l.parent_run_id.as_deref() == Some(run_id)
+            && !visited.contains(&l.run_id)
+            && !child_ids.contains(&l.run_id.as_str())
+        {
+            child_ids.push(&l.run_id);
+        }
+    }
+    let children = child_ids
+        .into_iter()
+        .filter_map(|c| {
+            if visited.contains(c) {
+                None
+            } else {
+                Some(correlation_node(c, links, visited))
+            }
+        })
+        .collect();
+    CorrelationNode {
+        run_id: run_id.to_string(),
+        kinds,
+        goal_id: pick(|l| l.goal_id.clone()),
+        user_id: pick(|l| l.user_id.clone()),
+        thread: pick(|l| l.thread.clone()),
+        first_ts: own.first().map(|l| l.ts.clone()).unwrap_or_default(),
+        receipt_url: url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id)),
+        children,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/correlations/{id}",
+    params(("id" = String, Path, description = "Correlation id (X-Correlation-Id)")),
+    responses(
+        (status = 200, description = "Runs, ops and API calls linked by the correlation id", body = CorrelationResp),
+        (status = 400, description = "Invalid id"),
+        (status = 404, description = "Nothing recorded under this id that this key may read")
+    )
+)]
+pub async fn correlations_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    use integrations::receipt_acl::{self, Caller};
+    if !correlation::is_valid(&id) {
+        return (StatusCode::BAD_REQUEST, "invalid correlation id").into_response();
+    }
+    // Only runs the key may read; the raw API calls are every tenant's, so admin only.
+    let caller = receipt_caller(&state, &headers);
+    let mut links = Vec::new();
+    for l in correlation::links(&id).await {
+        if receipt_acl::caller_can_read(&l.run_id, &caller).await {
+            links.push(l);
+        }
+    }
+    links.sort_by(|a, b| a.ts.cmp(&b.ts));
+    let api_calls = if caller == Caller::Admin {
+        integrations::api_trace::query(integrations::api_trace::ApiTraceFilter {
+            correlation_id: Some(id.clone()),
+            limit: 500,
+            ..Default::default()
+        })
+        .await
+        .events
+    } else {
+        Vec::new()
+    };
+    if links.is_empty() && api_calls.is_empty() {
+        return (StatusCode::NOT_FOUND, "unknown correlation id").into_response();
+    }
+
+    // Parents that were never recorded themselves (e.g. runs from before the id existed)
+    // leave their children as roots.
+    let known: HashSet<&str> = links.iter().map(|l| l.run_id.as_str()).collect();
+    let mut visited: HashSet<String> = HashSet::new();
+    let mut tree = Vec::new();
+    for l in &links {
+        let is_root = l
+            .parent_run_id
+            .as_deref()
+            .map(|p| !known.contains(p))
+            .unwrap_or(true);
+        if is_root && !visited.contains(&l.run_id) {
+            tree.push(correlation_node(&l.run_id, &links, &mut visited));
+        }
+    }
+    Json(CorrelationResp {
+        correlation_id: id,
+        tree,
+        links,
+        api_calls,
+    })
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DashboardQuery {
+    /// Aggregation window, e.g. `24h` (default), `7d`, `2w`.
+    pub window: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/dashboard",
+    params(("window" = Option<String>, Query, description = "Aggregation window: 24h (default), 7d, 2w, 90m")),
+    responses(
+        (status = 200, description = "Unified dashboard state", body = UIState),
+        (status = 400, description = "Invalid window")
+    )
+)]
+pub async fn dashboard_handler(Query(q): Query<DashboardQuery>) -> impl IntoResponse {
+    if let Some(w) = q.window.as_deref() {
+        if integrations::run_index::parse_window(w).is_none() {
+            return (axum::http::StatusCode::BAD_REQUEST, format!("invalid window: {}", w)).into_response();
+        }
+    }
+    match integrations::ui::render_unified_state(q.window.as_deref()).await {
+        Ok(state) => Json(state).into_response(),
+        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/planning",
+    responses(
+        (status = 200, description = "Weekly planning goals", body = Vec<AgentGoal>)
+    )
+)]
+pub async fn planning_handler() -> impl IntoResponse {
+    match integrations::kpi::weekly_planning().await {
+        Ok(goals) => Json(goals).into_response(),
+        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Slash commands accepted by `POST /users/{user_id}/chat` instead of a meta.omni turn.
+enum ChatCommand {
+    Help,
+    Run { goal_id: String, inputs: Value },
+    Attach { run_id: String, note: Option<String> },
+    Report { window: String },
+    Graph { kind: String },
+}
+
+const CHAT_GRAPH_KINDS: &[&str] = &["thread", "receipts", "api", "system"];
+
+const CHAT_HELP: &str = "Commands (results are added to the thread as tool messages):
+- /run <goal_id> [json inputs] - run a goal, e.g. /run demo.ping {\"message\":\"hi\"}
+- /attach <run_id> [note] - attach a finished run's output to this thread
+- /report [window] - daily digest over the window (default 24h, e.g. 7d)
+- /graph [thread|receipts|api|system] - build a graph (default: this thread)
+- /help - this list
+Messages starting with // are sent as ordinary chat.";
+
+/// `None` for ordinary messages (including `//...`); `Some(Err(usage))` for malformed commands.
+fn parse_chat_command(message: &str) -> Option<Result<ChatCommand, String>> {
+    let msg = message.trim();
+    let body = msg.strip_prefix('/').filter(|b| !b.starts_with('/'))?;
+    let (cmd, rest) = match body.split_once(char::is_whitespace) {
+        Some((c, r)) => (c, r.trim()),
+        None => (body, ""),
+    };
+    Some(match cmd {
+        "help" => Ok(ChatCommand::Help),
+        "run" => {
+            let (goal_id, raw) = match rest.split_once(char::is_whitespace) {
+                Some((g, r)) => (g, r.trim()),
+                None => (rest, ""),
+            };
+            if goal_id.is_empty() {
+                return Some(Err("usage: /run <goal_id> [json inputs]".to_string()));
+            }
+            let inputs = if raw.is_empty() {
+                Ok(json!({}))
+            } else {
+                match serde_json::from_str::<Value>(raw) {
+                    Ok(v) if v.is_object() => Ok(v),
+                    Ok(_) => Err("/run inputs must be a JSON object".to_string()),
+                    Err(e) => Err(format!("/run inputs are not valid JSON: {}", e)),
+                }
+            };
+            inputs.map(|inputs| ChatCommand::Run {
+                goal_id: goal_id.to_string(),
+                inputs,
+            })
+        }
+        "attach" => {
+            let (run_id, note) = match rest.split_once(char::is_whitespace) {
+                Some((r, n)) => (r, Some(n.trim().to_string())),
+                None => (rest, None),
+            };
+            if is_safe_segment(run_id) {
+                Ok(ChatCommand::Attach {
+                    run_id: run_id.to_string(),
+                    note,
+                })
+            } else {
+                Err("usage: /attach <run_id> [note]".to_string())
+            }
+        }
+        "report" => {
+            let window = if rest.is_empty() { "24h" } else { rest };
+            match integrations::run_index::parse_window(window) {
+                Some(_) => Ok(ChatCommand::Report {
+                    window: window.to_string(),
+                }),
+                None => Err(format!("invalid window {:?} (e.g. 24h, 7d)", window)),
+            }
+        }
+        "graph" => {
+            let kind = if rest.is_empty() { "thread" } else { rest };
+            if CHAT_GRAPH_KINDS.contains(&kind) {
+                Ok(ChatCommand::Graph {
+                    kind: kind.to_string(),
+                })
+            } else {
+                Err(format!("usage: /graph [{}]", CHAT_GRAPH_KINDS.join("|")))
+            }
+        }
+        other => Err(format!("unknown command /{}; try /help", other)),
+    })
+}
+
+/// Stand-in manifest for commands that don't run a goal (`/help`, `/attach`).
+fn chat_command_manifest(run_id: &str, command: &str, evidence: Value) -> Manifest {
+    Manifest {
+        run_id: run_id.to_string(),
+        goal_id: format!("chat.{}", command),
+        deliverables: vec![],
+        evidence,
+        bits: Bits::init(),
+        schema_version: MANIFEST_SCHEMA_VERSION,
     }
 }
 
-#[utoipa::path(
-    get,
-    path = "/planning",
-    responses(
-        (status = 200, description = "Weekly planning goals", body = Vec<AgentGoal>)
-    )
-)]
-pub async fn planning_handler() -> impl IntoResponse {
-    match integrations::kpi::weekly_planning().await {
-        Ok(goals) => Json(goals).into_response(),
-        Err(e) => (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+/// Execute a parsed chat command; the user message is already in the thread.
+async fn run_chat_command(
+    user: &UserContext,
+    thread: &str,
+    thread_file: &PathBuf,
+    req: &ChatReq,
+    run_id: &str,
+    cmd: ChatCommand,
+) -> axum::response::Response {
+    let chat_resp = |reply: String, manifest: Manifest, bits: Bits| ChatResp {
+        run_id: run_id.to_string(),
+        user_id: user.user_id.clone(),
+        thread: Some(thread.to_string()),
+        reply,
+        run_payload: None,
+        manifest,
+        bits,
+    };
+    let (goal_id, inputs) = match cmd {
+        ChatCommand::Help => {
+            integrations::progress::close(run_id);
+            let manifest = chat_command_manifest(run_id, "help", json!({ "actual_success": true }));
+            let bits = manifest.bits.clone();
+            return Json(chat_resp(CHAT_HELP.to_string(), manifest, bits)).into_response();
+        }
+        ChatCommand::Attach {
+            run_id: target,
+            note,
+        } => {
+            integrations::progress::close(run_id);
+            let resp = match read_receipt_response_json(&target).await {
+                Ok(v) => v,
+                Err(_) => {
+                    return (StatusCode::NOT_FOUND, "Receipt not found".to_string()).into_response()
+                }
+            };
+            let Some(manifest) = resp
+                .get("manifest")
+                .and_then(|m| serde_json::from_value::<Manifest>(m.clone()).ok())
+            else {
+                return (
+                    StatusCode::CONFLICT,
+                    "Run not ready (no manifest yet)".to_string(),
+                )
+                    .into_response();
+            };
+            let (summary, _) = summarize_run_for_thread(&target, &resp, note.as_deref()).await;
+            append_thread_event(thread_file, "tool", &summary, &target).await;
+            let bits = manifest.bits.clone();
+            return Json(chat_resp(summary, manifest, bits)).into_response();
+        }
+        // Namespaced like `POST /users/{user_id}/run`.
+        ChatCommand::Run { goal_id, inputs } => {
+            (format!("user:{}.{}", user.user_id, goal_id), inputs)
+        }
+        ChatCommand::Report { window } => ("reports.daily".to_string(), json!({ "window": window })),
+        ChatCommand::Graph { kind } => (
+            format!("graphs.{}", kind),
+            json!({ "user_id": user.user_id, "thread": thread }),
+        ),
+    };
+
+    // Commands that run a goal spend the user's quota like any other run.
+    let mut user = user.clone();
+    if user.quota_remaining == 0 {
+        integrations::progress::close(run_id);
+        return (StatusCode::TOO_MANY_REQUESTS, "Quota exceeded".to_string()).into_response();
+    }
+    let (policy, policy_chain) =
+        resolve_policy_chain("run", &goal_id, Some(&user), req.policy.clone());
+    let mpayload = Mpayload {
+        goal_id: goal_id.clone(),
+        inputs: inputs.clone(),
+        policy_effective: policy.clone(),
+        policy_request: req.policy.clone(),
+        policy_chain,
+        ctx: MpayloadCtx {
+            kind: "chat".to_string(),
+            user_id: Some(user.user_id.clone()),
+            thread: Some(thread.to_string()),
+            run_id: run_id.to_string(),
+            correlation_id: correlation::current(),
+            priority: resolve_priority("chat", Some(&user), None),
+            dependencies: None,
+        },
+    };
+    let slot = integrations::run_queue::Ticket::new(mpayload.ctx.priority, false)
+        .acquire()
+        .await;
+    let result = run_with_quota(Some(user.user_id.as_str()), &goal_id, inputs, &policy, run_id).await;
+    drop(slot);
+    if result.is_ok() {
+        emit_progress(run_id, &goal_id, Phase::Done, json!({}));
+    }
+    integrations::progress::close(run_id);
+    match result {
+        Ok((mut manifest, bits, _pr, _m2)) => {
+            spend_quota(&mut user);
+            manifest.run_id = run_id.to_string();
+            let (summary, _) =
+                summarize_run_for_thread(run_id, &json!({ "manifest": manifest }), None).await;
+            append_thread_event(thread_file, "tool", &summary, run_id).await;
+            let resp = chat_resp(summary, manifest, bits);
+            write_receipt_bundle(
+                run_id,
+                &goal_id,
+                &resp.bits,
+                &resp.manifest.deliverables,
+                &resp.manifest.evidence,
+                false,
+                &mpayload,
+                &resp,
+            )
+            .await;
+            Json(resp).into_response()
+        }
+        Err(e) => {
+            let msg = format!("Tool: `{}` failed: {}", goal_id, e);
+            append_thread_event(thread_file, "tool", &msg, run_id).await;
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
     }
 }
 
@@ -2701,7 +4744,8 @@ pub async fn user_chat_handler(
         Some(u) if u.user_id == user_id => u,
         _ => return (axum::http::StatusCode::UNAUTHORIZED, "Invalid user").into_response(),
     };
-    let policy = resolve_policy("chat", Some(&user), req.policy.clone());
+    let (policy, policy_chain) =
+        resolve_policy_chain("chat", "meta.omni", Some(&user), req.policy.clone());
 
     let requested = req.run_id.clone();
     let run_id = requested
@@ -2709,8 +4753,7 @@ pub async fn user_chat_handler(
         .filter(|s| is_safe_segment(s))
         .map(|s| s.to_string())
         .unwrap_or_else(|| format!("r-{}", uuid::Uuid::new_v4()));
-    let tx = progress_tx();
-    let _ = tx.send(format!("{{\"run_id\":\"{}\",\"phase\":\"start\"}}", run_id));
+    emit_progress(&run_id, "meta.omni", Phase::Start, json!({}));
 
     let thread = req
         .thread
@@ -2734,41 +4777,57 @@ pub async fn user_chat_handler(
                 .into_response()
         }
     };
-    let history = load_thread_history(&thread_file, 24).await;
+    if let Some(parsed) = parse_chat_command(&req.message) {
+        let cmd = match parsed {
+            Ok(c) => c,
+            Err(usage) => {
+                emit_progress(&run_id, "meta.omni", Phase::Error, json!({ "error": &usage }));
+                integrations::progress::close(&run_id);
+                return (axum::http::StatusCode::BAD_REQUEST, usage).into_response();
+            }
+        };
+        append_thread_event(&thread_file, "user", &req.message, &run_id).await;
+        return run_chat_command(&user, &thread, &thread_file, &req, &run_id, cmd).await;
+    }
+    // Load generously; meta.omni trims the history to the model's context window.
+    let history_messages = crate::engine::tokens::load_config().history_messages;
+    let history = load_thread_history(&thread_file, history_messages).await;
     append_thread_event(&thread_file, "user", &req.message, &run_id).await;
 
     // Use goal meta.omni
     let thread_id_for_resp = thread.clone();
     let loop_mode = req.loop_mode.unwrap_or(false);
     let inputs =
-        serde_json::json!({"message": req.message, "thread": thread, "history": history, "loop_mode": loop_mode});
+        serde_json::json!({"message": req.message, "thread": thread, "user_id": user.user_id, "history": history, "loop_mode": loop_mode});
     let mpayload = Mpayload {
         goal_id: "meta.omni".to_string(),
         inputs: inputs.clone(),
         policy_effective: policy.clone(),
         policy_request: req.policy.clone(),
+        policy_chain,
         ctx: MpayloadCtx {
             kind: "chat".to_string(),
             user_id: Some(user.user_id.clone()),
             thread: Some(thread.clone()),
             run_id: run_id.clone(),
+            correlation_id: correlation::current(),
+            priority: resolve_priority("chat", Some(&user), None),
+            dependencies: None,
         },
     };
-    match run_with_integrations("meta.omni", inputs, &policy, &run_id).await {
+    match run_with_quota(Some(user.user_id.as_str()), "meta.omni", inputs, &policy, &run_id).await {
         Ok((mut manifest, bits, _pr, _m2)) => {
             // Align manifest.run_id with the externally-visible run_id (for receipts + UI).
             manifest.run_id = run_id.clone();
-            let reply = manifest
-                .evidence
-                .get("reply")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            let _ = tx.send(format!("{{\"run_id\":\"{}\",\"phase\":\"done\"}}", run_id));
+            // Evidence that doesn't fit the chat shape is shown as an empty reply rather than failing the turn.
+            let chat = ChatEvidence::from_value(&manifest.evidence).unwrap_or_default();
+            let reply = chat.reply.clone();
+            emit_progress(&run_id, "meta.omni", Phase::Done, json!({}));
+            integrations::progress::close(&run_id);
 
             append_thread_event(&thread_file, "assistant", &reply, &run_id).await;
 
-            let run_payload = manifest.evidence.get("run_payload").cloned();
+            let run_payload = chat.run_payload();
             let resp = ChatResp {
                 run_id: run_id.clone(),
                 user_id: user.user_id,
@@ -2795,7 +4854,11 @@ pub async fn user_chat_handler(
             Json(resp)
             .into_response()
         }
-        Err(e) => (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) => {
+            emit_progress(&run_id, "meta.omni", Phase::Error, json!({ "error": e.to_string() }));
+            integrations::progress::close(&run_id);
+            (axum::http::StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
     }
 }
 
@@ -2860,7 +4923,7 @@ pub async fn user_thread_attach_run_handler(
             .into_response();
     }
 
-    let (summary, goal_id) = summarize_receipt_for_context(&run_id, &resp, req.note.as_deref());
+    let (summary, goal_id) = summarize_run_for_thread(&run_id, &resp, req.note.as_deref()).await;
     append_thread_event(&thread_file, "tool", &summary, &run_id).await;
 
     Json(AttachRunResp {
@@ -2874,6 +4937,69 @@ pub async fn user_thread_attach_run_handler(
     .into_response()
 }
 
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/threads/{thread}/feedback",
+    request_body = FeedbackReq,
+    responses(
+        (status = 200, description = "Rating stored next to the run's receipt", body = FeedbackResp),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "No receipt for this run in this user's thread")
+    )
+)]
+pub async fn user_thread_feedback_handler(
+    State(state): State<AppState>,
+    Path((user_id, thread)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(req): Json<FeedbackReq>,
+) -> impl IntoResponse {
+    // Auth
+    let api_key = match extract_api_key(&headers) {
+        Some(k) => k,
+        None => return unauthorized("Missing x-api-key"),
+    };
+    let user = match authenticate_user(&state, &api_key) {
+        Some(u) if u.user_id == user_id => u,
+        _ => return unauthorized("Invalid user"),
+    };
+
+    if !is_safe_segment(&thread) {
+        return (axum::http::StatusCode::BAD_REQUEST, "Invalid thread id".to_string())
+            .into_response();
+    }
+    let run_id = req.run_id.trim().to_string();
+    if !is_safe_segment(&run_id) {
+        return (axum::http::StatusCode::BAD_REQUEST, "Invalid run_id".to_string()).into_response();
+    }
+
+    // Only the user whose thread the run belongs to may rate it (runs without a thread, e.g.
+    // from /users/{id}/run, can be rated from any of the user's threads).
+    let owner = integrations::feedback::owner(&run_id).await;
+    let Some(owner) = owner.filter(|o| {
+        o.user_id.as_deref() == Some(user.user_id.as_str())
+            && o.thread.as_deref().is_none_or(|t| t == thread)
+    }) else {
+        return (axum::http::StatusCode::NOT_FOUND, "Receipt not found".to_string())
+            .into_response();
+    };
+
+    let feedback = integrations::feedback::Feedback {
+        ts: chrono::Utc::now().to_rfc3339(),
+        run_id,
+        user_id: user.user_id,
+        thread,
+        goal_id: owner.goal_id,
+        rating: req.rating,
+        comment: req.comment,
+    };
+    match integrations::feedback::record(feedback).await {
+        Ok(feedback) => Json(FeedbackResp { ok: true, feedback }).into_response(),
+        Err(e) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/users/{user_id}/threads/{thread}/summary",
@@ -2922,13 +5048,29 @@ pub async fn user_thread_summary_handler(
     path = "/run.async",
     request_body = RunReq,
     responses(
-        (status = 202, description = "Run queued", body = RunAsyncResp)
+        (status = 202, description = "Run queued", body = RunAsyncResp),
+        (status = 400, description = "Invalid depends_on")
     )
 )]
 pub async fn run_async_handler(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
+    headers: HeaderMap,
     Json(req): Json<RunReq>,
 ) -> impl IntoResponse {
+    let run_id = req.run_id.as_deref().unwrap_or_default();
+    let caller = receipt_caller(&state, &headers);
+    if let Err(e) = integrations::run_deps::validate(run_id, &req.depends_on, &caller).await {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    (StatusCode::ACCEPTED, Json(enqueue_run(req, None, "run").await)).into_response()
+}
+
+/// `POST /run.async` minus HTTP: stub receipt now, the run in the background.
+pub(crate) async fn enqueue_run(
+    req: RunReq,
+    user: Option<&UserContext>,
+    kind: &str,
+) -> RunAsyncResp {
     let requested = req.run_id.clone();
     let run_id = requested
         .as_deref()
@@ -2937,23 +5079,35 @@ pub async fn run_async_handler(
         .unwrap_or_else(|| format!("r-{}", uuid::Uuid::new_v4()));
 
     let goal_id = req.goal_id.clone();
+    let (policy_effective, policy_chain) =
+        resolve_policy_chain("run", &req.goal_id, user, req.policy.clone());
     let mpayload = Mpayload {
         goal_id: req.goal_id.clone(),
         inputs: req.inputs.clone(),
-        policy_effective: resolve_policy("run", None, req.policy.clone()),
+        policy_effective,
         policy_request: req.policy.clone(),
+        policy_chain,
         ctx: MpayloadCtx {
-            kind: "run".to_string(),
-            user_id: None,
+            kind: kind.to_string(),
+            user_id: user.map(|u| u.user_id.clone()),
             thread: None,
             run_id: run_id.clone(),
+            correlation_id: correlation::current(),
+            priority: resolve_priority(kind, user, req.priority),
+            dependencies: (!req.depends_on.is_empty()).then(|| {
+                integrations::run_deps::Dependencies {
+                    depends_on: req.depends_on.clone(),
+                    on_parent_failure: req.on_parent_failure.unwrap_or_default(),
+                }
+            }),
         },
     };
     let policy = mpayload.policy_effective.clone();
     let inputs = req.inputs.clone();
 
-    emit_progress(&run_id, &goal_id, "queued", json!({}));
-    set_active_run(&run_id, &goal_id, "queued").await;
+    track_run_eta(&run_id, &goal_id).await;
+    emit_progress(&run_id, &goal_id, Phase::Queued, json!({ "priority": mpayload.ctx.priority }));
+    set_active_run(&run_id, &goal_id, Phase::Queued, mpayload.ctx.priority).await;
 
     // Write an immediate placeholder receipt so links don't 404.
     let mut stub_bits = Bits::init();
@@ -2969,8 +5123,8 @@ pub async fn run_async_handler(
         run_id: run_id.clone(),
         goal_id: goal_id.clone(),
         status: "queued".to_string(),
-        receipt_url: format!("/runs/receipts/{}/RECEIPT.md", run_id),
-        sse_url: format!("/progress.sse?run_id={}", run_id),
+        receipt_url: url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id)),
+        sse_url: url_for(&format!("/progress.sse?run_id={}", run_id)),
     };
     write_receipt_bundle(
         &run_id,
@@ -2984,19 +5138,104 @@ pub async fn run_async_handler(
     )
     .await;
 
-    // Run in background.
-    let run_id_bg = run_id.clone();
-    let goal_id_bg = goal_id.clone();
-    tokio::spawn(async move {
-        set_active_run(&run_id_bg, &goal_id_bg, "running").await;
-        emit_progress(&run_id_bg, &goal_id_bg, "start", json!({}));
-        match run_with_integrations(&goal_id_bg, inputs, &policy, &run_id_bg).await {
+    spawn_background_run(run_id.clone(), goal_id.clone(), inputs, policy, mpayload);
+
+    stub_resp
+}
+
+/// Run a queued goal in the background and replace its stub receipt when it finishes.
+fn spawn_background_run(
+    run_id: String,
+    goal_id: String,
+    inputs: Value,
+    policy: Policy,
+    mpayload: Mpayload,
+) {
+    // Run in background (carry the request's forwarded prefix and correlation id so generated
+    // links and receipts match).
+    let run_id_bg = run_id;
+    let goal_id_bg = goal_id;
+    let prefix_bg = urls::forwarded_prefix();
+    let correlation_bg = correlation::current();
+    tokio::spawn(urls::with_prefix(prefix_bg, correlation::scope(correlation_bg, async move {
+        let priority = mpayload.ctx.priority;
+        // Wait for the runs this one depends on, then start it or skip it.
+        let parents = match &mpayload.ctx.dependencies {
+            Some(deps) => {
+                set_waiting_run(&run_id_bg, &goal_id_bg, priority).await;
+                emit_progress(&run_id_bg, &goal_id_bg, Phase::Queued, json!({ "waiting_on": deps.depends_on }));
+                let outcomes = integrations::run_deps::await_parents(&deps.depends_on).await;
+                if let Some(reason) = integrations::run_deps::skip_reason(&outcomes, deps.on_parent_failure) {
+                    emit_progress(&run_id_bg, &goal_id_bg, Phase::Error, json!({ "error": reason, "skipped": true }));
+                    let bits = Bits::init();
+                    let manifest = Manifest {
+                        run_id: run_id_bg.clone(),
+                        goal_id: goal_id_bg.clone(),
+                        deliverables: vec![],
+                        evidence: json!({
+                            "expected_success": true,
+                            "actual_success": false,
+                            "status": "skipped",
+                            "error": reason,
+                            "depends_on": outcomes
+                        }),
+                        bits: bits.clone(),
+                        schema_version: MANIFEST_SCHEMA_VERSION,
+                    };
+                    let resp = RunResp {
+                        manifest: manifest.clone(),
+                        bits: bits.clone(),
+                        pr_created: None,
+                        meta2_proposal: None,
+                    };
+                    write_receipt_bundle(
+                        &run_id_bg,
+                        &goal_id_bg,
+                        &bits,
+                        &[],
+                        &manifest.evidence,
+                        false,
+                        &mpayload,
+                        &resp,
+                    )
+                    .await;
+                    clear_active_run(&run_id_bg).await;
+                    return;
+                }
+                Some(outcomes)
+            }
+            None => None,
+        };
+        // Only idempotent runs are safe to stop and start over (as in startup recovery).
+        let ticket = integrations::run_queue::Ticket::new(priority, policy.idempotent);
+        let result = loop {
+            let slot = ticket.acquire().await;
+            set_active_run(&run_id_bg, &goal_id_bg, Phase::Start, priority).await;
+            emit_progress(&run_id_bg, &goal_id_bg, Phase::Start, json!({}));
+            let finished = tokio::select! {
+                res = run_with_quota(mpayload.ctx.user_id.as_deref(), &goal_id_bg, inputs.clone(), &policy, &run_id_bg) => Some(res),
+                _ = slot.preempted() => None,
+            };
+            drop(slot);
+            match finished {
+                Some(res) => break res,
+                None => {
+                    emit_progress(&run_id_bg, &goal_id_bg, Phase::Preempted, json!({ "priority": priority }));
+                    set_active_run(&run_id_bg, &goal_id_bg, Phase::Preempted, priority).await;
+                }
+            }
+        };
+        match result {
             Ok((mut manifest, bits, pr_id, meta2_proposal)) => {
                 manifest.run_id = run_id_bg.clone();
+                if let (Some(p), Some(ev)) = (&parents, manifest.evidence.as_object_mut()) {
+                    ev.insert("depends_on".to_string(), json!(p));
+                }
+                record_run_duration(&manifest).await;
                 emit_progress(
                     &manifest.run_id,
                     &manifest.goal_id,
-                    "done",
+                    Phase::Done,
                     json!({
                         "pr": pr_id,
                         "bits": bits,
@@ -3030,7 +5269,7 @@ pub async fn run_async_handler(
                 bits.e = 1.0;
                 bits.u = 1.0;
                 bits.t = 0.0;
-                let manifest = Manifest {
+                let mut manifest = Manifest {
                     run_id: run_id_bg.clone(),
                     goal_id: goal_id_bg.clone(),
                     deliverables: vec![],
@@ -3040,7 +5279,11 @@ pub async fn run_async_handler(
                         "error": e.to_string()
                     }),
                     bits: bits.clone(),
+                    schema_version: MANIFEST_SCHEMA_VERSION,
                 };
+                if let (Some(p), Some(ev)) = (&parents, manifest.evidence.as_object_mut()) {
+                    ev.insert("depends_on".to_string(), json!(p));
+                }
                 let resp = RunResp {
                     manifest: manifest.clone(),
                     bits: bits.clone(),
@@ -3058,13 +5301,121 @@ pub async fn run_async_handler(
                     &resp,
                 )
                 .await;
-                emit_progress(&run_id_bg, &goal_id_bg, "error", json!({ "error": e.to_string() }));
                 clear_active_run(&run_id_bg).await;
             }
         }
-    });
+    })));
+}
+
+/// Startup recovery: receipts still `queued` were left by a process that died mid-run (nothing
+/// in ACTIVE_RUNS survives a restart). Each is marked `interrupted` with a recovery note, or
+/// requeued under the same run id when its effective policy is `idempotent`.
+/// `ONE_ENGINE_RECOVER_RUNS=0` skips the scan.
+pub async fn recover_orphaned_runs() {
+    if std::env::var("ONE_ENGINE_RECOVER_RUNS").ok().as_deref() == Some("0") {
+        return;
+    }
+    let dir = meta3_root().join("runs/receipts");
+    let Ok(mut rd) = fs::read_dir(&dir).await else {
+        return;
+    };
+    let mut interrupted = 0usize;
+    let mut requeued = 0usize;
+    while let Ok(Some(ent)) = rd.next_entry().await {
+        let run_id = ent.file_name().to_string_lossy().to_string();
+        if !is_safe_segment(&run_id) {
+            continue;
+        }
+        let Ok(resp) = read_receipt_response_json(&run_id).await else {
+            continue;
+        };
+        let status = resp.get("status").and_then(|v| v.as_str()).unwrap_or("");
+        if status != "queued" && status != "running" {
+            continue;
+        }
+        if ACTIVE_RUNS.lock().await.contains_key(&run_id) {
+            continue;
+        }
+        let Some(request) = fs::read_to_string(ent.path().join("request.json"))
+            .await
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+        else {
+            continue;
+        };
+        let Ok(mpayload) = serde_json::from_value::<Mpayload>(request.clone()) else {
+            continue;
+        };
+        let goal_id = mpayload.goal_id.clone();
+        let detected_at = chrono::Utc::now().to_rfc3339();
+        // The receipt keeps only the redacted request; replaying it would run the goal with
+        // `[REDACTED]` in place of its secrets.
+        let redacted = is_redacted(&request);
+        let requeue = mpayload.policy_effective.idempotent && !redacted;
+        let recovery = json!({
+            "previous_status": status,
+            "detected_at": detected_at,
+            "action": if requeue { "requeued" } else { "interrupted" },
+            "note": if requeue {
+                "Server restarted while this run was in flight; requeued because its policy is idempotent."
+            } else if redacted && mpayload.policy_effective.idempotent {
+                "Server restarted while this run was in flight; not requeued because its stored request was redacted. Re-submit to run it again."
+            } else {
+                "Server restarted while this run was in flight; it did not finish. Re-submit to run it again."
+            }
+        });
+        integrations::telemetry::emit(
+            "api",
+            "run_interrupted",
+            Some(&run_id),
+            json!({ "goal_id": goal_id, "previous_status": status, "requeued": requeue }),
+        );
 
-    (StatusCode::ACCEPTED, Json(stub_resp)).into_response()
+        let status_now = if requeue { "queued" } else { "interrupted" };
+        let mut bits = Bits::init();
+        if !requeue {
+            bits.e = 1.0;
+            bits.t = 0.0;
+        }
+        let evidence = json!({
+            "expected_success": true,
+            "actual_success": false,
+            "status": status_now,
+            "run_id": run_id,
+            "goal_id": goal_id,
+            "recovery": recovery
+        });
+        let stub_resp = json!({
+            "run_id": run_id,
+            "goal_id": goal_id,
+            "status": status_now,
+            "recovery": recovery,
+            "receipt_url": url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id)),
+            "sse_url": url_for(&format!("/progress.sse?run_id={}", run_id))
+        });
+        write_receipt_bundle(&run_id, &goal_id, &bits, &[], &evidence, false, &mpayload, &stub_resp).await;
+        let phase = if requeue { Phase::Queued } else { Phase::Interrupted };
+        emit_progress(&run_id, &goal_id, phase, json!({ "recovery": recovery }));
+
+        if requeue {
+            track_run_eta(&run_id, &goal_id).await;
+            set_active_run(&run_id, &goal_id, Phase::Queued, mpayload.ctx.priority).await;
+            let inputs = mpayload.inputs.clone();
+            let policy = mpayload.policy_effective.clone();
+            spawn_background_run(run_id.clone(), goal_id, inputs, policy, mpayload);
+            requeued += 1;
+        } else {
+            integrations::progress::close(&run_id);
+            interrupted += 1;
+        }
+    }
+    if interrupted + requeued > 0 {
+        tracing::info!(
+            "recovered orphaned runs: {} interrupted, {} requeued",
+            interrupted,
+            requeued
+        );
+    }
 }
 
 #[utoipa::path(
@@ -3093,24 +5444,46 @@ pub async fn progress_sse_handler(
     Query(q): Query<ProgressQuery>,
 ) -> Sse<impl futures_core::Stream<Item = Result<Event, Infallible>>> {
     let target = q.run_id.clone();
-    let rx = progress_tx().subscribe();
-    let stream = BroadcastStream::new(rx)
-        .filter_map(move |evt| match evt {
-            Ok(s) => {
-                if let Some(ref rid) = target {
-                    if let Ok(v) = serde_json::from_str::<Value>(&s) {
-                        if v.get("run_id").and_then(|r| r.as_str()) == Some(rid.as_str()) {
-                            return Some(s);
-                        } else {
-                            return None;
-                        }
-                    }
-                }
-                Some(s)
+    // A run that already has a receipt and isn't active won't publish again: end right away
+    // instead of holding the connection (and a channel) open.
+    let finished = match &target {
+        Some(rid) => {
+            is_safe_segment(rid)
+                && !ACTIVE_RUNS.lock().await.contains_key(rid)
+                && fs::metadata(meta3_root().join("runs/receipts").join(rid).join("response.json"))
+                    .await
+                    .is_ok()
+        }
+        None => false,
+    };
+    let rx = match &target {
+        Some(rid) => {
+            let rx = integrations::progress::subscribe(rid);
+            if finished {
+                integrations::progress::close(rid);
             }
-            Err(_) => None,
+            rx
+        }
+        None => integrations::progress::subscribe_all(),
+    };
+    let lag_run = target.clone();
+    let end_run = target.clone();
+    // Items are `None` once the run's channel is closed; everything after that is dropped.
+    let events = BroadcastStream::new(rx)
+        .map(move |evt| match evt {
+            Ok(s) => Some(Event::default().data(s)),
+            Err(BroadcastStreamRecvError::Lagged(missed)) => Some(
+                Event::default()
+                    .event("lagged")
+                    .data(json!({ "run_id": lag_run, "missed": missed }).to_string()),
+            ),
         })
-        .map(|s| Ok(Event::default().data(s)));
+        .chain(tokio_stream::once(target.as_ref().map(|_| {
+            Event::default()
+                .event("end")
+                .data(json!({ "run_id": end_run }).to_string())
+        })))
+        .chain(tokio_stream::once(None));
 
     // Send real events (not just ":" comments) so reverse proxies (e.g. Cloudflare) keep the
     // connection alive and flush bytes regularly.
@@ -3120,12 +5493,19 @@ pub async fn progress_sse_handler(
         Duration::from_secs(15),
     ))
     .map(move |_| {
-        Ok(Event::default()
-            .event("keepalive")
-            .data(format!("{{\"keepalive\":true,\"pad\":\"{}\"}}", pad)))
+        Some(
+            Event::default()
+                .event("keepalive")
+                .data(format!("{{\"keepalive\":true,\"pad\":\"{}\"}}", pad)),
+        )
     });
 
-    Sse::new(stream.merge(keepalive))
+    Sse::new(
+        events
+            .merge(keepalive)
+            .take_while(|e| e.is_some())
+            .filter_map(|e| e.map(Ok)),
+    )
 }
 
 #[utoipa::path(get, path = "/browse", responses((status = 200, description = "Simple HTML browse page")))]
@@ -3194,19 +5574,23 @@ pub async fn browse_handler() -> impl IntoResponse {
     html.push_str("</head><body>");
     html.push_str("<h1>One Engine</h1>");
     html.push_str("<p class=\"muted\">Quick links: ");
-    html.push_str("<a href=\"/terminal\">Terminal</a> · <a href=\"/docs/openapi_request_examples.md\">API examples</a>");
+    html.push_str(&format!(
+        "<a href=\"{}\">Terminal</a> · <a href=\"{}\">API examples</a>",
+        url_for("/terminal"),
+        url_for("/docs/openapi_request_examples.md")
+    ));
     html.push_str("</p>");
 
     html.push_str("<h2>Latest receipts</h2><ul>");
     for r in receipts {
-        let href = format!("/runs/receipts/{}/RECEIPT.md", r);
+        let href = url_for(&format!("/runs/{}/receipt", r));
         html.push_str(&format!("<li><a href=\"{}\">{}</a></li>", href, r));
     }
     html.push_str("</ul>");
 
     html.push_str("<h2>meta3.build logs</h2><ul>");
     for f in meta3_logs {
-        let href = format!("/runs/meta3-build/{}", f);
+        let href = url_for(&format!("/runs/meta3-build/{}", f));
         html.push_str(&format!("<li><a href=\"{}\">{}</a></li>", href, f));
     }
     html.push_str("</ul>");
@@ -3272,294 +5656,407 @@ pub async fn browse_json_handler() -> impl IntoResponse {
         out.truncate(limit);
         out.into_iter().map(|(_, n)| n).collect()
     }
-
-    let receipts = list_dirs(&root, "runs/receipts", 50).await;
-    let meta3_logs = list_files(&root, "runs/meta3-build", 100).await;
-
-    Json(json!({
-        "meta3_root": root.display().to_string(),
-        "receipts": receipts,
-        "meta3_build_logs": meta3_logs,
-        "links": {
-            "ui": "/ui/",
-            "browse": "/browse",
-            "swagger": "/swagger-ui",
-            "staleness": "/docs/staleness_matrix.md",
-            "impact": "/docs/financial_impact.md"
-        }
-    }))
+
+    let receipts = list_dirs(&root, "runs/receipts", 50).await;
+    let meta3_logs = list_files(&root, "runs/meta3-build", 100).await;
+
+    Json(json!({
+        "meta3_root": root.display().to_string(),
+        "receipts": receipts,
+        "meta3_build_logs": meta3_logs,
+        "links": {
+            "ui": url_for("/ui/"),
+            "browse": url_for("/browse"),
+            "swagger": url_for("/swagger-ui"),
+            "staleness": url_for("/docs/staleness_matrix.html"),
+            "impact": url_for("/docs/financial_impact.md")
+        }
+    }))
+    .into_response()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalibrationQuery {
+    /// Only receipts from this window (`7d`, `30d`); default all.
+    pub window: Option<String>,
+    /// Reliability bins (default 10).
+    pub bins: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/bits/calibration",
+    params(
+        ("window" = Option<String>, Query, description = "Window like 7d / 30d (default: all receipts)"),
+        ("bins" = Option<usize>, Query, description = "Reliability bins, default 10")
+    ),
+    responses(
+        (status = 200, description = "Trust vs actual_success: reliability curves, Brier and ECE per goal family (read-only)", body = integrations::calibration::CalibrationReport),
+        (status = 400, description = "Invalid window")
+    )
+)]
+pub async fn bits_calibration_handler(Query(q): Query<CalibrationQuery>) -> impl IntoResponse {
+    match calibration_report(q).await {
+        Ok(report) => Json(report).into_response(),
+        Err(resp) => resp,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/bits/calibration",
+    params(
+        ("window" = Option<String>, Query, description = "Window like 7d / 30d (default: all receipts)"),
+        ("bins" = Option<usize>, Query, description = "Reliability bins, default 10")
+    ),
+    responses(
+        (status = 200, description = "The calibration report, also written to runs/calibration/index.html and calibration.json", body = integrations::calibration::CalibrationReport),
+        (status = 400, description = "Invalid window"),
+        (status = 500, description = "Report could not be written")
+    )
+)]
+pub async fn bits_calibration_write_handler(Query(q): Query<CalibrationQuery>) -> impl IntoResponse {
+    let mut report = match calibration_report(q).await {
+        Ok(report) => report,
+        Err(resp) => return resp,
+    };
+    if let Err(e) = integrations::calibration::write_report(&mut report).await {
+        return (StatusCode::INTERNAL_SERVER_ERROR, format!("write calibration report: {}", e)).into_response();
+    }
+    Json(report).into_response()
+}
+
+async fn calibration_report(
+    q: CalibrationQuery,
+) -> Result<integrations::calibration::CalibrationReport, axum::response::Response> {
+    let window = q.window.map(|w| w.trim().to_string()).filter(|w| !w.is_empty());
+    let since = match window.as_deref() {
+        None => None,
+        Some(w) => match integrations::run_index::parse_window(w) {
+            Some(d) => Some(chrono::Utc::now() - d),
+            None => return Err((StatusCode::BAD_REQUEST, format!("invalid window: {}", w)).into_response()),
+        },
+    };
+    Ok(integrations::calibration::compute(window, since, q.bins.unwrap_or(10)).await)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    /// RFC 3339 timestamp or a window like `24h` / `7d`; default all.
+    pub since: Option<String>,
+    /// Newest entries to return (default 200, max 5000).
+    pub limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/experiments/{id}/report",
+    params(("id" = String, Path, description = "Experiment id from `experiments:` in policies.yaml")),
+    responses(
+        (status = 200, description = "Control vs treatment: success, trust, latency and cost per arm", body = integrations::experiments::ExperimentReport),
+        (status = 404, description = "Experiment not configured")
+    )
+)]
+pub async fn experiment_report_handler(Path(id): Path<String>) -> impl IntoResponse {
+    if !is_safe_segment(&id) {
+        return (StatusCode::BAD_REQUEST, "invalid experiment id".to_string()).into_response();
+    }
+    match integrations::experiments::report(&id).await {
+        Some(r) => Json(r).into_response(),
+        None => (StatusCode::NOT_FOUND, "experiment not configured".to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditResp {
+    /// Verification of the whole chain, not just the returned page.
+    pub chain: integrations::audit::ChainStatus,
+    pub entries: Vec<integrations::audit::AuditEntry>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/audit",
+    params(
+        ("since" = Option<String>, Query, description = "RFC 3339 timestamp or window like 24h / 7d"),
+        ("limit" = Option<usize>, Query, description = "Newest entries to return, default 200")
+    ),
+    responses(
+        (status = 200, description = "Audit entries plus hash-chain verification", body = AuditResp),
+        (status = 400, description = "Invalid since"),
+        (status = 403, description = "Admin key required")
+    )
+)]
+pub async fn audit_handler(headers: HeaderMap, Query(q): Query<AuditQuery>) -> impl IntoResponse {
+    let key = headers.get("x-api-key").and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !is_admin_key(key) {
+        return (StatusCode::FORBIDDEN, "admin key required".to_string()).into_response();
+    }
+    let since = match q.since.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        None => None,
+        Some(s) => match chrono::DateTime::parse_from_rfc3339(s) {
+            Ok(t) => Some(t.with_timezone(&chrono::Utc)),
+            Err(_) => match integrations::run_index::parse_window(s) {
+                Some(d) => Some(chrono::Utc::now() - d),
+                None => return (StatusCode::BAD_REQUEST, format!("invalid since: {}", s)).into_response(),
+            },
+        },
+    };
+    let limit = q.limit.unwrap_or(200).clamp(1, 5000);
+    Json(AuditResp {
+        chain: integrations::audit::verify().await,
+        entries: integrations::audit::query(since, limit).await,
+    })
     .into_response()
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StalenessEntry {
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FlagsResp {
+    /// Runtime overrides first, then the policies file's `goal_flags` they don't replace.
+    pub flags: Vec<flags::FlagEntry>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetFlagReq {
+    /// Goal id or `*` glob.
+    pub goal: String,
     #[serde(default)]
-    feature: String,
+    pub enabled: Option<bool>,
     #[serde(default)]
-    status: String,
+    pub message: Option<String>,
     #[serde(default)]
-    ts: String,
+    pub rollout: Option<flags::Rollout>,
+    /// Drop the runtime override for `goal` (the file entry, if any, applies again).
     #[serde(default)]
-    detail: String,
+    pub clear: bool,
 }
 
-#[derive(Debug, Serialize)]
-struct Nudge {
-    id: String,
-    title: String,
-    severity: String, // info | warn | error
-    action: String,
-    link: Option<String>,
-    command: Option<String>,
-    run_payload: Option<serde_json::Value>,
+#[utoipa::path(
+    get,
+    path = "/admin/flags",
+    responses(
+        (status = 200, description = "Effective goal flags", body = FlagsResp),
+        (status = 403, description = "Admin key required")
+    )
+)]
+pub async fn flags_handler(headers: HeaderMap) -> impl IntoResponse {
+    let key = headers.get("x-api-key").and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !is_admin_key(key) {
+        return (StatusCode::FORBIDDEN, "admin key required".to_string()).into_response();
+    }
+    Json(FlagsResp { flags: flags::list() }).into_response()
 }
 
-fn escape_html(s: &str) -> String {
-    s.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
+#[utoipa::path(
+    post,
+    path = "/admin/flags",
+    request_body = SetFlagReq,
+    responses(
+        (status = 200, description = "Override stored or cleared; effective flags", body = FlagsResp),
+        (status = 400, description = "Invalid flag"),
+        (status = 403, description = "Admin key required"),
+        (status = 404, description = "No override to clear")
+    )
+)]
+pub async fn set_flag_handler(headers: HeaderMap, Json(req): Json<SetFlagReq>) -> impl IntoResponse {
+    let key = headers.get("x-api-key").and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !is_admin_key(key) {
+        return (StatusCode::FORBIDDEN, "admin key required".to_string()).into_response();
+    }
+    let actor = integrations::audit::actor_for_key(Some(key));
+    let detail = json!({
+        "enabled": req.enabled,
+        "rollout": req.rollout,
+        "clear": req.clear,
+    });
+    let changed = if req.clear {
+        match flags::clear(&req.goal) {
+            Ok(true) => Ok(()),
+            Ok(false) => return (StatusCode::NOT_FOUND, format!("no override for {}", req.goal)).into_response(),
+            Err(e) => Err(e),
+        }
+    } else {
+        let flag = flags::GoalFlag {
+            goal: req.goal.clone(),
+            enabled: req.enabled.unwrap_or(true),
+            message: req.message,
+            rollout: req.rollout,
+        };
+        flags::set(flag, &actor).map(|_| ())
+    };
+    if let Err(e) = changed {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    let action = if req.clear { "goal.flag.clear" } else { "goal.flag.set" };
+    integrations::audit::record(&actor, action, &req.goal, None, detail).await;
+    Json(FlagsResp { flags: flags::list() }).into_response()
 }
 
-fn nudge_for_feature(entry: &StalenessEntry) -> Option<Nudge> {
-    let feature = entry.feature.trim();
-    let status = entry.status.trim().to_ascii_lowercase();
-    if status == "pass" {
-        return None;
-    }
+#[derive(Debug, Deserialize)]
+pub struct PromptsQuery {
+    /// Include this template's text.
+    pub name: Option<String>,
+    /// Version of `name` to include (default: the active one).
+    pub version: Option<u32>,
+}
 
-    let (title, action, link, command, run_payload) = match feature {
-        "health" => (
-            "Health check failing".to_string(),
-            "Restart the engine and re-check /health".to_string(),
-            Some("/health".to_string()),
-            Some("curl -s http://127.0.0.1:8080/health".to_string()),
-            None,
-        ),
-        "version" => (
-            "Version endpoint failing".to_string(),
-            "Check /version output and logs".to_string(),
-            Some("/version".to_string()),
-            Some("curl -s http://127.0.0.1:8080/version | jq".to_string()),
-            None,
-        ),
-        f if f.starts_with("run.demo.ping") => (
-            "demo.ping failing".to_string(),
-            "Run demo.ping and confirm a receipt is written".to_string(),
-            Some("/browse".to_string()),
-            None,
-            Some(json!({
-                "goal_id": "demo.ping",
-                "inputs": {"message": "staleness nudge ping"},
-                "policy": {"gamma_gate": 0.5, "time_ms": 8000, "max_risk": 0.3, "tiny_diff_loc": 120}
-            })),
-        ),
-        f if f.contains("research.read") => (
-            "research.read failing".to_string(),
-            "Re-run the read and verify stale=false".to_string(),
-            Some("/browse".to_string()),
-            None,
-            Some(json!({
-                "goal_id": "research.read",
-                "inputs": {"path": "/Users/jobs/Desktop/tmp-meta3-engine-test/research/sources/history_miner_folder/memory/policy_ucb.json"},
-                "policy": {"gamma_gate": 0.5, "time_ms": 12000, "max_risk": 0.3, "tiny_diff_loc": 120}
-            })),
-        ),
-        f if f.contains("meta3.build") => (
-            "meta3.build failing".to_string(),
-            "Re-run meta3.build (policy-driven default_cmd) and inspect log".to_string(),
-            Some("/browse".to_string()),
-            None,
-            Some(json!({
-                "goal_id": "meta3.build",
-                "inputs": {"repo_path": "/Users/jobs/Desktop/meta3-monorepo"},
-                "policy": {"gamma_gate": 0.5, "time_ms": 300000, "max_risk": 0.3, "tiny_diff_loc": 120}
-            })),
-        ),
-        "progress.sse" => (
-            "progress.sse missing/unknown".to_string(),
-            "Connect SSE and verify events are emitted".to_string(),
-            Some("/progress.sse".to_string()),
-            Some("curl -N 'http://127.0.0.1:8080/progress.sse?run_id=r-sseprobe' | head -n 30".to_string()),
-            None,
-        ),
-        _ => (
-            format!("{} is {}", feature, entry.status),
-            "Inspect staleness detail and logs".to_string(),
-            Some("/docs/staleness_matrix.json".to_string()),
-            None,
-            None,
-        ),
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PromptsResp {
+    pub prompts: Vec<prompts::PromptInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/prompts",
+    params(
+        ("name" = Option<String>, Query, description = "Also return this template's text"),
+        ("version" = Option<u32>, Query, description = "Version of `name` (default: the active one)")
+    ),
+    responses(
+        (status = 200, description = "Prompt templates with versions, hashes and variables", body = PromptsResp),
+        (status = 403, description = "Admin key required"),
+        (status = 404, description = "No such template version")
+    )
+)]
+pub async fn prompts_handler(headers: HeaderMap, Query(q): Query<PromptsQuery>) -> impl IntoResponse {
+    let key = headers.get("x-api-key").and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !is_admin_key(key) {
+        return (StatusCode::FORBIDDEN, "admin key required".to_string()).into_response();
+    }
+    let text = match q.name.as_deref() {
+        Some(name) => match prompts::text(name, q.version) {
+            Some(t) => Some(t),
+            None => return (StatusCode::NOT_FOUND, format!("no prompt template {}", name)).into_response(),
+        },
+        None => None,
     };
+    Json(PromptsResp { prompts: prompts::list(), text }).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NudgesQuery {
+    /// Whose dismissals to apply (default `demo`).
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub include_dismissed: bool,
+}
 
-    let severity = match status.as_str() {
-        "fail" => "error",
-        "unknown" => "warn",
-        _ => "warn",
+#[utoipa::path(
+    get,
+    path = "/nudges.json",
+    params(
+        ("user_id" = Option<String>, Query, description = "Apply this user's dismissals (default demo)"),
+        ("include_dismissed" = Option<bool>, Query, description = "Also list dismissed nudges")
+    ),
+    responses((status = 200, description = "Actionable next steps"))
+)]
+pub async fn nudges_json_handler(Query(q): Query<NudgesQuery>) -> impl IntoResponse {
+    let user_id = q.user_id.unwrap_or_else(|| "demo".to_string());
+    if !is_safe_segment(&user_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid user_id".to_string()).into_response();
     }
-    .to_string();
+    let report = integrations::nudges::compute(&user_id, q.include_dismissed).await;
 
-    Some(Nudge {
-        id: format!("staleness:{}", feature),
-        title,
-        severity,
-        action,
-        link,
-        command,
-        run_payload,
-    })
+    Json(json!({
+        "meta3_root": meta3_root().display().to_string(),
+        "user_id": user_id,
+        "features": report.features,
+        "dismissed": report.dismissed,
+        "nudges": report.nudges
+    }))
+    .into_response()
 }
 
-fn evergreen_nudges() -> Vec<Nudge> {
-    let root = meta3_root();
-    vec![
-        Nudge {
-            id: "evergreen:wiki_local".to_string(),
-            title: "Generate a local DeepWiki snapshot".to_string(),
-            severity: "info".to_string(),
-            action: "Generate wiki under /runs/wiki/<run_id>/index.html".to_string(),
-            link: Some("/browse".to_string()),
-            command: None,
-            run_payload: Some(json!({
-                "goal_id": "wiki.generate",
-                "inputs": {},
-                "policy": {"gamma_gate": 0.5, "time_ms": 300000, "max_risk": 0.2, "tiny_diff_loc": 120}
-            })),
-        },
-        Nudge {
-            id: "evergreen:green_build".to_string(),
-            title: "Produce a fresh green receipt (fast)".to_string(),
-            severity: "info".to_string(),
-            action: "Run a real build of the engine repo and write a receipt".to_string(),
-            link: Some("/browse".to_string()),
-            command: None,
-            run_payload: Some(json!({
-                "goal_id": "meta3.build",
-                "inputs": {"repo_path": root.display().to_string(), "build_cmd": "cargo build --profile release-fast --bin one-engine"},
-                "policy": {"gamma_gate": 0.5, "time_ms": 300000, "max_risk": 0.3, "tiny_diff_loc": 120}
-            })),
-        },
-        Nudge {
-            id: "evergreen:threads_report".to_string(),
-            title: "Summarize this chat thread (auto)".to_string(),
-            severity: "info".to_string(),
-            action: "Generate an HTML report from recent chat turns + receipts".to_string(),
-            link: Some("/terminal".to_string()),
-            command: None,
-            run_payload: Some(json!({
-                "goal_id": "threads.report",
-                "inputs": {"user_id": "demo", "thread": "auto", "max_events": 600, "content_chars": 240},
-                "policy": {"gamma_gate": 0.5, "time_ms": 120000, "max_risk": 0.2, "tiny_diff_loc": 120}
-            })),
-        },
-        Nudge {
-            id: "evergreen:graphs_thread".to_string(),
-            title: "Generate a thread graph (auto)".to_string(),
-            severity: "info".to_string(),
-            action: "Generate a recursive, bits-native graph from recent chat turns".to_string(),
-            link: Some("/terminal".to_string()),
-            command: None,
-            run_payload: Some(json!({
-                "goal_id": "graphs.thread",
-                "inputs": {"user_id": "demo", "thread": "auto", "recursive": true, "depth": 2, "max_nodes": 400, "include_bits": true},
-                "policy": {"gamma_gate": 0.5, "time_ms": 120000, "max_risk": 0.2, "tiny_diff_loc": 120}
-            })),
-        },
-    ]
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct DismissNudgeReq {
+    /// Only for the admin key, which may dismiss for any user (default `demo`); a user's key
+    /// always dismisses for that user.
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Snooze for this many hours instead of until the goal runs again.
+    #[serde(default)]
+    pub hours: Option<u64>,
 }
 
-async fn compute_nudges(root: &PathBuf) -> (usize, Vec<Nudge>) {
-    let staleness_path = root.join("docs/staleness_matrix.json");
-    let mut nudges: Vec<Nudge> = Vec::new();
-    let mut staleness: Vec<StalenessEntry> = Vec::new();
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DismissNudgeResp {
+    pub id: String,
+    pub user_id: String,
+    pub dismissed_at: String,
+    pub until: Option<String>,
+}
 
-    if let Ok(raw) = fs::read_to_string(&staleness_path).await {
-        if let Ok(parsed) = serde_json::from_str::<Vec<StalenessEntry>>(&raw) {
-            staleness = parsed;
-            for e in &staleness {
-                if let Some(n) = nudge_for_feature(e) {
-                    nudges.push(n);
-                }
-            }
-        } else {
-            nudges.push(Nudge {
-                id: "staleness:parse_error".to_string(),
-                title: "Staleness report parse error".to_string(),
-                severity: "warn".to_string(),
-                action: "Fix docs/staleness_matrix.json or regenerate it".to_string(),
-                link: Some("/docs/staleness_matrix.json".to_string()),
-                command: Some("cd /Users/jobs/Desktop && ./scripts/workflows/run_all.sh".to_string()),
-                run_payload: None,
-            });
+#[utoipa::path(
+    post,
+    path = "/nudges/{id}/dismiss",
+    params(("id" = String, Path, description = "Nudge id, e.g. stale:meta3.build")),
+    request_body = DismissNudgeReq,
+    responses(
+        (status = 200, description = "Nudge dismissed for the user", body = DismissNudgeResp),
+        (status = 400, description = "Invalid id or user_id"),
+        (status = 401, description = "Missing or invalid x-api-key"),
+        (status = 403, description = "user_id names another user"),
+        (status = 404, description = "Unknown nudge")
+    )
+)]
+pub async fn nudge_dismiss_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Option<Json<DismissNudgeReq>>,
+) -> impl IntoResponse {
+    use integrations::receipt_acl::Caller;
+    let caller = match require_caller(&state, &headers) {
+        Ok(c) => c,
+        Err(e) => return e.into_response(),
+    };
+    let req = body.map(|Json(r)| r).unwrap_or_default();
+    let user_id = match (caller, req.user_id) {
+        (Caller::User(me), Some(other)) if other != me => {
+            return (
+                StatusCode::FORBIDDEN,
+                "A user key can only dismiss its own nudges".to_string(),
+            )
+                .into_response();
         }
-    } else {
-        nudges.push(Nudge {
-            id: "staleness:missing".to_string(),
-            title: "Staleness report missing".to_string(),
-            severity: "warn".to_string(),
-            action: "Generate docs/staleness_matrix.json to populate nudges".to_string(),
-            link: Some("/docs/".to_string()),
-            command: Some("cd /Users/jobs/Desktop && ./scripts/workflows/run_all.sh".to_string()),
-            run_payload: None,
-        });
+        (Caller::User(me), _) => me,
+        (_, requested) => requested.unwrap_or_else(|| "demo".to_string()),
+    };
+    if !is_safe_segment(&user_id) {
+        return (StatusCode::BAD_REQUEST, "Invalid user_id".to_string()).into_response();
     }
-
-    // If no wiki snapshots exist, surface a higher-priority nudge (even if staleness has other failures).
-    let wiki_dir = root.join("runs/wiki");
-    let mut has_any_wiki = false;
-    if let Ok(mut rd) = fs::read_dir(&wiki_dir).await {
-        while let Ok(Some(ent)) = rd.next_entry().await {
-            if ent.file_type().await.ok().map(|ft| ft.is_dir()).unwrap_or(false) {
-                has_any_wiki = true;
-                break;
-            }
-        }
+    if !integrations::nudges::is_valid_id(&id) {
+        return (StatusCode::BAD_REQUEST, "Invalid nudge id".to_string()).into_response();
     }
-    if !has_any_wiki {
-        nudges.push(Nudge {
-            id: "wiki:missing".to_string(),
-            title: "No wiki snapshots yet".to_string(),
-            severity: "warn".to_string(),
-            action: "Run wiki.generate to create the first /runs/wiki/<run_id>/index.html".to_string(),
-            link: Some("/browse".to_string()),
-            command: None,
-            run_payload: Some(json!({
-                "goal_id": "wiki.generate",
-                "inputs": {},
-                "policy": {"gamma_gate": 0.5, "time_ms": 300000, "max_risk": 0.2, "tiny_diff_loc": 120}
-            })),
-        });
+    let known = integrations::nudges::compute(&user_id, true).await;
+    if !known.nudges.iter().any(|n| n.id == id) {
+        return (StatusCode::NOT_FOUND, format!("Unknown nudge: {}", id)).into_response();
     }
-
-    // Always append evergreen nudges (dedup by id) so the UI always has “Run this” actions.
-    let mut seen: HashSet<String> = nudges.iter().map(|n| n.id.clone()).collect();
-    for n in evergreen_nudges() {
-        if seen.insert(n.id.clone()) {
-            nudges.push(n);
-        }
+    match integrations::nudges::dismiss(&user_id, &id, req.hours).await {
+        Ok(d) => Json(DismissNudgeResp {
+            id,
+            user_id,
+            dismissed_at: d.dismissed_at,
+            until: d.until,
+        })
+        .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
     }
-
-    (staleness.len(), nudges)
-}
-
-#[utoipa::path(get, path = "/nudges.json", responses((status = 200, description = "Actionable next steps")))]
-pub async fn nudges_json_handler() -> impl IntoResponse {
-    let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
-    let (staleness_entries, nudges) = compute_nudges(&root).await;
-
-    Json(json!({
-        "meta3_root": root.display().to_string(),
-        "staleness_entries": staleness_entries,
-        "nudges": nudges
-    }))
-    .into_response()
 }
 
 #[utoipa::path(get, path = "/nudges", responses((status = 200, description = "Simple HTML nudges page")))]
 pub async fn nudges_handler() -> impl IntoResponse {
-    let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
-    let (_staleness_entries, nudges) = compute_nudges(&root).await;
+    let nudges = integrations::nudges::compute("demo", false).await.nudges;
 
     let mut html = String::new();
     html.push_str("<!doctype html><html><head><meta charset=\"utf-8\"><title>One Engine Nudges</title>");
@@ -3567,8 +6064,16 @@ pub async fn nudges_handler() -> impl IntoResponse {
     html.push_str("<style>body{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px} a{color:#1f6feb;text-decoration:none} a:hover{text-decoration:underline} code{background:#f6f8fa;padding:2px 6px;border-radius:6px} .muted{color:#57606a} .pill{display:inline-block;padding:2px 8px;border-radius:999px;font-size:12px;background:#eef2ff;margin-right:8px} .pill.warn{background:#fff7ed} .pill.error{background:#fee2e2}</style>");
     html.push_str("</head><body>");
     html.push_str("<h1>Nudges</h1>");
-    html.push_str("<p class=\"muted\">Next steps computed from <a href=\"/docs/staleness_matrix.json\">staleness_matrix.json</a>. ");
-    html.push_str("Quick links: <a href=\"/ui/\">UI</a> · <a href=\"/browse\">Browse</a> · <a href=\"/swagger-ui\">Swagger</a></p>");
+    html.push_str(&format!(
+        "<p class=\"muted\">Next steps computed from the run index (<a href=\"{}\">nudges.json</a>). ",
+        url_for("/nudges.json")
+    ));
+    html.push_str(&format!(
+        "Quick links: <a href=\"{}\">UI</a> · <a href=\"{}\">Browse</a> · <a href=\"{}\">Swagger</a></p>",
+        url_for("/ui/"),
+        url_for("/browse"),
+        url_for("/swagger-ui")
+    ));
     html.push_str("<ul>");
     for n in nudges {
         let title = n.title;
@@ -3636,14 +6141,63 @@ pub async fn golden_handler(Path(name): Path<String>) -> impl IntoResponse {
     }
 }
 
+/// [`run_with_integrations`] under the disk quotas of `user_id` and the goal's family: refused
+/// when either is used up, and failed when the run had a write refused (even if the goal went on).
+async fn run_with_quota(
+    user_id: Option<&str>,
+    goal_id: &str,
+    inputs: serde_json::Value,
+    policy: &Policy,
+    run_id: &str,
+) -> anyhow::Result<(Manifest, Bits, Option<String>, Option<String>)> {
+    let res = run_metered(user_id, goal_id, inputs, policy, run_id).await;
+    // Success is reported by the caller, which knows the response; failure ends the run here.
+    if let Err(e) = &res {
+        emit_progress(run_id, goal_id, Phase::Error, json!({ "error": e.to_string() }));
+    }
+    res
+}
+
+async fn run_metered(
+    user_id: Option<&str>,
+    goal_id: &str,
+    inputs: serde_json::Value,
+    policy: &Policy,
+    run_id: &str,
+) -> anyhow::Result<(Manifest, Bits, Option<String>, Option<String>)> {
+    integrations::disk_quota::check(user_id, goal_id).map_err(anyhow::Error::msg)?;
+    let meter = integrations::disk_quota::RunMeter::new(run_id, user_id, goal_id);
+    let res = atomic::metered(meter.clone(), run_with_integrations(goal_id, inputs, policy, run_id)).await;
+    integrations::disk_quota::finish(&meter);
+    match meter.refused() {
+        Some(msg) => Err(anyhow::Error::msg(msg)),
+        None => res,
+    }
+}
+
+/// Inject the external run_id so goals can name artifacts deterministically. `__kernel` is the
+/// experiment arm's alone: one the client sent is dropped, or it could lower the evidence gate.
+fn engine_inputs(inputs: Value, run_id: &str, kernel: Option<Value>) -> Value {
+    match inputs {
+        Value::Object(mut map) => {
+            map.insert("__run_id".to_string(), json!(run_id));
+            map.remove("__kernel");
+            if let Some(kernel) = kernel {
+                map.insert("__kernel".to_string(), kernel);
+            }
+            Value::Object(map)
+        }
+        other => other,
+    }
+}
+
 async fn run_with_integrations(
     goal_id: &str,
     inputs: serde_json::Value,
     policy: &Policy,
     run_id: &str,
 ) -> anyhow::Result<(Manifest, Bits, Option<String>, Option<String>)> {
-    let tx = progress_tx();
-    let _ = tx.send(json!({"run_id": run_id, "goal_id": goal_id, "phase": "plan"}).to_string());
+    emit_progress(run_id, goal_id, Phase::Plan, json!({}));
 
     // Demo long-running goal with incremental progress updates.
     if goal_id == "demo.wait" {
@@ -3665,32 +6219,29 @@ async fn run_with_integrations(
         let total_ms = seconds.saturating_mul(1000);
         let total_ticks = ((total_ms + tick_ms - 1) / tick_ms).max(1);
 
-        let _ = tx.send(json!({"run_id": run_id, "goal_id": goal_id, "phase": "act"}).to_string());
+        emit_progress(run_id, goal_id, Phase::Act, json!({}));
         for i in 0..=total_ticks {
             let pct = ((i as f64) / (total_ticks as f64)).min(1.0);
             let eta_s = ((total_ticks.saturating_sub(i)) * tick_ms + 999) / 1000;
-            let _ = tx.send(
+            emit_progress(
+                run_id,
+                goal_id,
+                Phase::Tick,
                 json!({
-                    "run_id": run_id,
-                    "goal_id": goal_id,
-                    "phase": "tick",
-                    "extra": {
-                        "label": label,
-                        "i": i,
-                        "total": total_ticks,
-                        "pct": pct,
-                        "eta_s": eta_s,
-                        "tick_ms": tick_ms
-                    }
-                })
-                .to_string(),
+                    "label": label,
+                    "i": i,
+                    "total": total_ticks,
+                    "pct": pct,
+                    "eta_s": eta_s,
+                    "tick_ms": tick_ms
+                }),
             );
             if i < total_ticks {
                 tokio::time::sleep(Duration::from_millis(tick_ms)).await;
             }
         }
 
-        let _ = tx.send(json!({"run_id": run_id, "goal_id": goal_id, "phase": "verify"}).to_string());
+        emit_progress(run_id, goal_id, Phase::Verify, json!({}));
 
         let mut bits = Bits::init();
         bits.u = 0.2;
@@ -3708,30 +6259,32 @@ async fn run_with_integrations(
                 "label": label
             }),
             bits: bits.clone(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
-        let _ = tx.send(json!({"run_id": run_id, "goal_id": goal_id, "phase": "done"}).to_string());
         return Ok((manifest, bits, None, None));
     }
 
     // 1. Search flywheel for context
     let _context = integrations::flywheel::search(goal_id).await?;
 
-    let _ = tx.send(json!({"run_id": run_id, "goal_id": goal_id, "phase": "act"}).to_string());
+    emit_progress(run_id, goal_id, Phase::Act, json!({}));
+
+    // A/B experiments: the treatment arm runs with the experiment's policy/kernel overrides.
+    let assignment = integrations::experiments::assign(goal_id, run_id).await;
+    let arm_policy = assignment.as_ref().map(|a| a.policy(policy));
+    let policy = arm_policy.as_ref().unwrap_or(policy);
 
     // 2. Run engine with meta² layer
-    // Inject the external run_id so goals can name artifacts deterministically.
-    let inputs = match inputs {
-        Value::Object(mut map) => {
-            map.insert("__run_id".to_string(), json!(run_id));
-            Value::Object(map)
-        }
-        other => other,
-    };
-    let (manifest, ext_bits, meta2_proposal) = engine::run(goal_id, inputs, policy).await?;
+    let kernel = assignment.as_ref().and_then(|a| a.kernel());
+    let inputs = engine_inputs(inputs, run_id, kernel);
+    let (mut manifest, ext_bits, meta2_proposal) = engine::run(goal_id, inputs, policy).await?;
+    if let (Some(a), Some(obj)) = (&assignment, manifest.evidence.as_object_mut()) {
+        obj.insert("experiment".to_string(), a.tag());
+    }
     let bits: Bits = ext_bits.into(); // Convert to legacy format
 
-    let _ = tx.send(json!({"run_id": run_id, "goal_id": goal_id, "phase": "verify"}).to_string());
+    emit_progress(run_id, goal_id, Phase::Verify, json!({}));
 
     // 3. Update flywheel metadata
     integrations::flywheel::update_metadata(goal_id, &manifest, bits.t).await?;
@@ -3743,8 +6296,6 @@ async fn run_with_integrations(
     // 5. Serialize meta² proposal if present
     let meta2_json = meta2_proposal.map(|p| serde_json::to_string(&p).unwrap_or_default());
 
-    let _ = tx.send(json!({"run_id": run_id, "goal_id": goal_id, "phase": "done"}).to_string());
-
     Ok((manifest, bits, pr_id, meta2_json))
 }
 
@@ -3756,63 +6307,392 @@ async fn run_with_integrations(
     ),
     paths(
         version_handler,
+        goals_catalog_handler,
+        progress_phases_handler,
         run_handler,
         run_async_handler,
         runs_active_json_handler,
         validate_handler,
+        validate_suites_handler,
+        secrets_handler,
         validate_golden_handler,
         dashboard_handler,
         planning_handler,
         user_run_handler,
         user_status_handler,
+        user_usage_handler,
         user_chat_handler,
         user_thread_attach_run_handler,
+        user_thread_feedback_handler,
         user_thread_summary_handler,
         progress_sse_handler,
         golden_handler,
         research_index_handler,
+        files_handler,
+        files_preview_handler,
         codex_sources_handler,
         codex_archive_handler,
         codex_rollouts_list_handler,
         codex_rollout_file_handler,
         codex_capabilities_handler,
         codex_search_handler,
+        codex_sessions_handler,
+        codex_index_handler,
+        share_handler,
         ruliad_list_handler,
         ruliad_file_handler,
+        runs_artifact_handler,
+        receipt_html_handler,
+        run_log_tail_handler,
+        run_artifacts_handler,
+        run_status_handler,
+        run_bundle_handler,
+        rollback_handler,
+        telemetry_ingest_handler,
+        telemetry_query_handler,
+        api_trace_query_handler,
+        correlations_handler,
+        bits_calibration_handler,
+        bits_calibration_write_handler,
+        audit_handler,
+        flags_handler,
+        set_flag_handler,
+        prompts_handler,
+        experiment_report_handler,
+        healthz_handler,
+        nudges_json_handler,
+        nudge_dismiss_handler,
+        user_policy_get_handler,
+        user_policy_put_handler,
         meta::meta_run_handler,
         meta::meta_state_handler,
         meta::meta_reset_handler,
+        meta::meta_history_handler,
         nstar::nstar_run_handler,
-        nstar::nstar_hud_handler
+        nstar::nstar_hud_handler,
+        nstar::resolve_context_handler,
+        context::staleness_handler,
+        nstar_policy::nstar_policy_handler
     ),
-    components(schemas(Bits, Policy, Manifest, RunReq, RunResp, RunAsyncResp, ActiveRun, VersionInfo, ValidateReq, ValidateResp, GoldenReq, GoldenResp, ValidationResult, UIState, AgentGoal, UserRunReq, UserRunResp, UserStatus, ChatReq, ChatResp, AttachRunReq, AttachRunResp, ThreadSummaryResp, CodexSourceInfo, CodexSourcesResp, CountedItem, CapScanFile, CodexCapabilitiesResp, CodexSearchResult, CodexSearchResp, nstar::NStarRunReq, nstar::NStarRunResp, meta::MetaRunReq, meta::MetaRunResp, meta::MetaState)),
+    components(schemas(Bits, Policy, Manifest, RunReq, RunResp, RunAsyncResp, ActiveRun, VersionInfo, ValidateReq, ValidateResp, validate::SuiteDef, validate::SuiteTask, validate::ScoringWeights, GoldenReq, GoldenResp, ValidationResult, UIState, integrations::TelemetryEvent, TelemetryIngestReq, TelemetryIngestResp,
+            RollbackResp, live_log::LogTail, RunArtifact, RunArtifactsResp, RunStatusResp, RunTiming, RunLinks, ResearchIndexResp, integrations::workspace_files::FilesPage, integrations::workspace_files::FileEntry, integrations::workspace_files::EntryKind, integrations::workspace_files::FilePreview, integrations::RunTimeline, integrations::TimelineBucket, integrations::GoalFailures, integrations::Meta2ProposalRef, AgentGoal, UserRunReq, UserRunResp, UserStatus, integrations::run_queue::Priority, integrations::run_deps::OnParentFailure, integrations::run_deps::Dependencies, integrations::run_deps::ParentOutcome, GoalCatalogResp, catalog::GoalEntry, catalog::GoalAlias, crate::engine::routing::GoalFamily, crate::engine::routing::GoalVariant, crate::engine::routing::RouteDecision, crate::engine::routing::Alternative, crate::engine::routing::BitsSource, crate::engine::simulation::SimulationStatus, integrations::progress::Phase, integrations::progress::PhaseInfo, integrations::disk_quota::UserUsage, integrations::disk_quota::DiskUsage, integrations::disk_quota::UsageRow, ChatReq, ChatResp, AttachRunReq, AttachRunResp, FeedbackReq, FeedbackResp, integrations::feedback::Feedback, integrations::feedback::Rating, integrations::feedback::GoalSatisfaction, ThreadSummaryResp, CodexSourceInfo, CodexSourcesResp, CountedItem, CapScanFile, CodexCapabilitiesResp, CodexSearchResult, CodexSearchResp, CodexSessionsResp, integrations::codex::SessionSummary, integrations::codex_index::IndexStatus, ShareReq, integrations::receipt_acl::Acl, integrations::codex::ImportReport, crate::engine::graph_doc::GraphDoc, crate::engine::graph_doc::GraphNode, crate::engine::graph_doc::GraphEdge, crate::engine::graph_doc::GraphLink, DismissNudgeReq, DismissNudgeResp, UserPolicyResp, UserPolicyPutReq, integrations::user_policy::StoredPolicy, integrations::user_policy::PolicyAuditEntry, integrations::api_trace::ApiTraceEvent, integrations::api_trace::ApiTracePage, CorrelationResp, CorrelationNode, correlation::Link, integrations::calibration::CalibrationReport, integrations::calibration::FamilyCalibration, integrations::calibration::CalibrationBin, secrets::SecretInfo, AuditResp, integrations::audit::AuditEntry, FlagsResp, SetFlagReq, flags::FlagEntry, flags::GoalFlag, flags::FlagSource, flags::Rollout, PromptsResp, prompts::PromptInfo, prompts::PromptRecord, integrations::audit::ChainStatus, integrations::experiments::ExperimentReport, integrations::experiments::ExperimentArm, integrations::experiments::ArmDelta, integrations::health::HealthReport, integrations::health::Component, integrations::health::Level, integrations::nudges::FeatureStaleness, nstar::NStarRunReq, nstar::NStarRunResp, nstar::ResolveReq, nstar::ResolveResp, nstar::ContextMatch, context::ContextBundle, context::ContextItem, context::Provenance, context::SourceStat, context::ContextWeights, context::FreshnessReport, context::ItemFreshness, context::StalenessResp, nstar_policy::NStarPolicyResp, nstar_policy::NStarPolicyState, nstar_policy::FamilyPolicy, nstar_policy::ArmStats, meta::MetaRunReq, meta::MetaRunResp, meta::MetaState, meta::PersistedMetaState, meta::StrategyStats, meta::MetaEvent, meta::MetaEventKind, meta::MetaHistoryResp)),
     tags((name="one-engine", description="Multi-tenant metacognitive system"))
 )]
 pub struct ApiDoc;
 
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ResearchIndexResp {
+    /// When the index was last built (`research/index.meta.json`, else the file's mtime).
+    pub updated_at: Option<String>,
+    /// "disk" (research/index.jsonl) | "ephemeral" (built in-process; run `research.index` to persist)
+    pub source: String,
+    pub count: usize,
+    #[schema(value_type = Vec<Object>)]
+    pub items: Vec<ResearchArtifact>,
+}
+
+type CachedResearchIndex = (Option<SystemTime>, ResearchIndexResp);
+
+/// Parsed index keyed by the file's mtime, so requests don't re-read an unchanged index.
+static RESEARCH_INDEX_CACHE: Lazy<Mutex<Option<CachedResearchIndex>>> =
+    Lazy::new(|| Mutex::new(None));
+
 #[utoipa::path(
     get,
     path = "/research/index",
-    responses((status = 200, description = "Research artifact index", body = [ResearchArtifact]))
+    responses((status = 200, description = "Research artifact index (cached)", body = ResearchIndexResp))
 )]
 pub async fn research_index_handler() -> impl IntoResponse {
-    // Prefer on-disk index if present; else build from current workspace.
-    let disk = tokio::fs::read_to_string("research/index.jsonl").await;
-    let mut items: Vec<ResearchArtifact> = Vec::new();
-    if let Ok(s) = disk {
-        for line in s.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            if let Ok(a) = serde_json::from_str::<ResearchArtifact>(line) {
-                items.push(a);
-            }
+    let index_path = StdPath::new("research/index.jsonl");
+    let mtime = fs::metadata(index_path).await.ok().and_then(|m| m.modified().ok());
+    let mut cache = RESEARCH_INDEX_CACHE.lock().await;
+    if let Some((cached_mtime, resp)) = cache.as_ref() {
+        // An ephemeral index stays cached until a disk index appears.
+        if *cached_mtime == mtime {
+            return Json(resp.clone());
+        }
+    }
+
+    let resp = if let Some(m) = mtime {
+        let items = research::read_index(index_path);
+        let updated_at = fs::read_to_string("research/index.meta.json")
+            .await
+            .ok()
+            .and_then(|s| serde_json::from_str::<Value>(&s).ok())
+            .and_then(|v| v.get("updated_at").and_then(|x| x.as_str()).map(|x| x.to_string()))
+            .or_else(|| Some(chrono::DateTime::<chrono::Utc>::from(m).to_rfc3339()));
+        ResearchIndexResp {
+            updated_at,
+            source: "disk".to_string(),
+            count: items.len(),
+            items,
         }
     } else {
         // Fallback: build ephemeral index from '.' (no network)
-        if let Ok(v) = research::build_index(std::path::Path::new(".")) {
-            items = v;
+        let items = tokio::task::spawn_blocking(|| research::build_index(StdPath::new(".")))
+            .await
+            .ok()
+            .and_then(|r| r.ok())
+            .unwrap_or_default();
+        ResearchIndexResp {
+            updated_at: Some(chrono::Utc::now().to_rfc3339()),
+            source: "ephemeral".to_string(),
+            count: items.len(),
+            items,
+        }
+    };
+    *cache = Some((mtime, resp.clone()));
+    Json(resp)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FilesQuery {
+    /// Directory relative to `META3_ROOT/workspaces` (default: the root).
+    pub path: Option<String>,
+    /// List matching entries below `path` instead of its children, e.g. `**/*.md`.
+    pub glob: Option<String>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+fn files_error(e: integrations::workspace_files::FilesError) -> axum::response::Response {
+    use integrations::workspace_files::FilesError;
+    let status = match &e {
+        FilesError::Invalid(_) => StatusCode::BAD_REQUEST,
+        FilesError::Denied(_) => StatusCode::FORBIDDEN,
+        FilesError::NotFound(_) => StatusCode::NOT_FOUND,
+        FilesError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string()).into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/files",
+    params(
+        ("path" = Option<String>, Query, description = "Directory, relative to META3_ROOT, under workspaces/ (default workspaces)"),
+        ("glob" = Option<String>, Query, description = "Match entries below path (e.g. **/*.md) instead of listing its children"),
+        ("offset" = Option<usize>, Query, description = "Entries to skip (use next_offset from the previous page)"),
+        ("limit" = Option<usize>, Query, description = "Page size, default 200")
+    ),
+    responses(
+        (status = 200, description = "Workspace entries with size, mtime and type; sensitive files are left out", body = integrations::workspace_files::FilesPage),
+        (status = 400, description = "Path outside the workspaces root"),
+        (status = 401, description = "Missing or unknown x-api-key"),
+        (status = 403, description = "Path matches a deny pattern"),
+        (status = 404, description = "No such directory")
+    )
+)]
+pub async fn files_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<FilesQuery>,
+) -> impl IntoResponse {
+    if let Err(e) = require_caller(&state, &headers) {
+        return e.into_response();
+    }
+    let path = q.path.unwrap_or_default();
+    let glob = q.glob.filter(|g| !g.trim().is_empty());
+    let offset = q.offset.unwrap_or(0);
+    let limit = q.limit.unwrap_or(200).clamp(1, 2000);
+    let res = tokio::task::spawn_blocking(move || {
+        integrations::workspace_files::list(&path, glob.as_deref(), offset, limit)
+    })
+    .await;
+    match res {
+        Ok(Ok(page)) => Json(page).into_response(),
+        Ok(Err(e)) => files_error(e),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FilePreviewQuery {
+    pub path: String,
+}
+
+#[utoipa::path(
+    get,
+    path = "/files/preview",
+    params(("path" = String, Query, description = "File, relative to META3_ROOT, under workspaces/")),
+    responses(
+        (status = 200, description = "Redacted text of the file, capped at workspace_files.preview_max_bytes", body = integrations::workspace_files::FilePreview),
+        (status = 400, description = "Path outside the workspaces root, or not a file"),
+        (status = 401, description = "Missing or unknown x-api-key"),
+        (status = 403, description = "Path matches a deny pattern, directly or through a symlink"),
+        (status = 404, description = "No such file")
+    )
+)]
+pub async fn files_preview_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(q): Query<FilePreviewQuery>,
+) -> impl IntoResponse {
+    if let Err(e) = require_caller(&state, &headers) {
+        return e.into_response();
+    }
+    let res =
+        tokio::task::spawn_blocking(move || integrations::workspace_files::preview(&q.path)).await;
+    match res {
+        Ok(Ok(preview)) => Json(preview).into_response(),
+        Ok(Err(e)) => files_error(e),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::path::Component;
+
+    #[test]
+    fn receipt_links_keep_only_safe_schemes() {
+        for dest in [
+            "https://x.dev/a",
+            "http://x",
+            "mailto:a@b.c",
+            "/runs/r-1/receipt",
+            "#top",
+            "a/b:c",
+            "?q=1",
+        ] {
+            assert!(is_safe_link(dest), "{:?} rejected", dest);
+        }
+        for dest in [
+            "javascript:alert(1)",
+            " JavaScript:x",
+            "java\tscript:x",
+            "data:text/html,x",
+            "vbscript:x",
+        ] {
+            assert!(!is_safe_link(dest), "{:?} accepted", dest);
+        }
+    }
+
+    #[test]
+    fn hostile_segments_are_rejected() {
+        for seg in [
+            "", ".", "..", "...", "a/b", "a\\b", "../x", "x/..", "/etc", "a\0b", "\u{2215}etc",
+            "\u{ff0e}\u{ff0e}", "run\u{200b}id", "r\u{0301}", "C:", "~root", "a b",
+        ] {
+            assert!(!is_safe_segment(seg), "{:?} accepted", seg);
+        }
+        for seg in ["r-1", "run_2.json", "a.b", "v1.2.3"] {
+            assert!(is_safe_segment(seg), "{:?} rejected", seg);
+        }
+    }
+
+    #[test]
+    fn client_kernel_overrides_are_dropped() {
+        let sent = json!({"q": 1, "__run_id": "r-x", "__kernel": {"confidence_gate_tau": 1.0}});
+        let inputs = engine_inputs(sent.clone(), "r-1", None);
+        assert_eq!(inputs, json!({"q": 1, "__run_id": "r-1"}));
+        let arm = json!({"confidence_gate_tau": 0.5});
+        let inputs = engine_inputs(sent, "r-1", Some(arm.clone()));
+        assert_eq!(inputs["__kernel"], arm);
+    }
+
+    #[test]
+    fn chat_commands_parse_and_ordinary_messages_pass_through() {
+        assert!(parse_chat_command("hello /run x").is_none());
+        assert!(parse_chat_command("//run is just chat").is_none());
+        assert!(matches!(parse_chat_command(" /help "), Some(Ok(ChatCommand::Help))));
+        match parse_chat_command(r#"/run demo.ping {"message": "hi"}"#) {
+            Some(Ok(ChatCommand::Run { goal_id, inputs })) => {
+                assert_eq!(goal_id, "demo.ping");
+                assert_eq!(inputs, json!({"message": "hi"}));
+            }
+            _ => panic!("/run not parsed"),
+        }
+        assert!(matches!(
+            parse_chat_command("/attach r-1 see the diff"),
+            Some(Ok(ChatCommand::Attach { run_id, note }))
+                if run_id == "r-1" && note.as_deref() == Some("see the diff")
+        ));
+        assert!(matches!(
+            parse_chat_command("/report"),
+            Some(Ok(ChatCommand::Report { window })) if window == "24h"
+        ));
+        assert!(matches!(
+            parse_chat_command("/graph"),
+            Some(Ok(ChatCommand::Graph { kind })) if kind == "thread"
+        ));
+        for bad in [
+            "/run",
+            "/run demo.ping [1]",
+            "/run demo.ping {",
+            "/attach ../x",
+            "/report soon",
+            "/graph everything",
+            "/frobnicate",
+        ] {
+            assert!(matches!(parse_chat_command(bad), Some(Err(_))), "{}", bad);
+        }
+    }
+
+    proptest! {
+        /// An accepted segment is exactly one normal path component: joining it never
+        /// leaves (or names) the parent directory.
+        #[test]
+        fn safe_segment_is_one_normal_component(seg in "\\PC{0,64}|[a-z.]{1,6}|[./\\\\a-z]{1,8}") {
+            if is_safe_segment(&seg) {
+                let parts: Vec<_> = StdPath::new(&seg).components().collect();
+                prop_assert_eq!(parts.len(), 1);
+                prop_assert!(matches!(parts[0], Component::Normal(_)));
+            }
+        }
+    }
+
+    /// Budgets are for `--release` on a laptop; slower CI boxes set `ONE_ENGINE_BUDGET_SCALE`.
+    fn budget(ms: u64) -> std::time::Duration {
+        let scale: f64 = std::env::var("ONE_ENGINE_BUDGET_SCALE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1.0);
+        std::time::Duration::from_millis((ms as f64 * scale) as u64)
+    }
+
+    /// The capabilities scan at its largest per-file settings: a 20 MB window and 10k lines
+    /// of a rollout full of commands and URLs.
+    #[tokio::test]
+    #[ignore = "performance budget; run with `make bench`"]
+    async fn budget_codex_capability_scan() {
+        let dir = std::env::temp_dir()
+            .join("one-engine-budget-codex")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rollout.jsonl");
+        let mut rollout = String::new();
+        for i in 0..40_000 {
+            let cmd = format!(
+                "curl -s https://api.example.com/items/{i} | jq; cargo test -p c{}",
+                i % 13
+            );
+            let out = format!(
+                "git clean, see https://docs.example.com/{} and ./src/m{}.rs",
+                i % 17,
+                i % 53
+            );
+            let ev = json!({"type": "function_call", "payload": {
+                "name": "shell",
+                "arguments": {"command": ["bash", "-lc", cmd], "workdir": format!("/w/{}", i % 31)},
+                "output": out
+            }});
+            rollout.push_str(&ev.to_string());
+            rollout.push('\n');
         }
+        std::fs::write(&path, rollout).unwrap();
+        let tools = ["curl", "cargo", "git", "jq", "rg", "ast-grep"];
+
+        let t = std::time::Instant::now();
+        let acc = scan_jsonl_file(&path, 10_000, 20 * 1024 * 1024, &tools)
+            .await
+            .unwrap();
+        let took = t.elapsed();
+        assert_eq!(acc.tailed_lines, 10_000);
+        assert!(acc.tool_counts.get("curl").copied().unwrap_or(0) > 0);
+        assert!(took < budget(1_000), "capability scan took {:?}", took);
+        let _ = std::fs::remove_dir_all(&dir);
     }
-    Json(items)
 }
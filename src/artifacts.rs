@@ -0,0 +1,473 @@
+//! Conditional + ranged artifact serving for `/runs` and ruliad files.
+//!
+//! - `ETag`: strong sha256 content hash, truncated (memoized per path/len/mtime, so polling a
+//!   multi-MB log doesn't re-hash unless it changed)
+//! - `Last-Modified` / `If-Modified-Since`, `If-None-Match` → `304 Not Modified`
+//! - `Range: bytes=a-b | a- | -n` → `206 Partial Content` (single range; UI log tailing)
+//! - Pre-compressed storage: when `<file>` is missing but `<file>.gz` exists, the gzip bytes
+//!   are sent as-is with `Content-Encoding: gzip`, or inflated for clients without gzip.
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::{mpsc, Mutex};
+use tokio_stream::wrappers::ReceiverStream;
+
+#[derive(Clone, PartialEq, Eq)]
+struct EtagKey {
+    len: u64,
+    mtime_ns: u128,
+}
+
+static ETAG_CACHE: Lazy<Mutex<HashMap<PathBuf, (EtagKey, String)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+const ETAG_CACHE_MAX: usize = 4096;
+
+/// Content type by extension (the artifact set is small and known).
+pub fn content_type_for(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "txt" | "log" => "text/plain; charset=utf-8",
+        "json" => "application/json",
+        "jsonl" => "application/x-ndjson",
+        "dot" => "text/vnd.graphviz; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "xml" => "application/xml; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "yaml" | "yml" => "application/yaml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gz" => "application/gzip",
+        "zip" => "application/zip",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Full sha256 hex of `path`, memoized on len + mtime.
+async fn sha256_cached(path: &Path, len: u64, modified: Option<SystemTime>) -> Option<String> {
+    let mtime_ns = modified
+        .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let key = EtagKey { len, mtime_ns };
+    {
+        let cache = ETAG_CACHE.lock().await;
+        if let Some((k, hex)) = cache.get(path) {
+            if *k == key {
+                return Some(hex.clone());
+            }
+        }
+    }
+
+    let mut f = tokio::fs::File::open(path).await.ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = f.read(&mut buf).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let digest = hasher.finalize();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+
+    let mut cache = ETAG_CACHE.lock().await;
+    if cache.len() >= ETAG_CACHE_MAX {
+        cache.clear();
+    }
+    cache.insert(path.to_path_buf(), (key, hex.clone()));
+    Some(hex)
+}
+
+async fn etag_for(path: &Path, len: u64, modified: Option<SystemTime>) -> Option<String> {
+    let hex = sha256_cached(path, len, modified).await?;
+    Some(format!("\"{}\"", &hex[..32]))
+}
+
+/// Size, type and content hash of one file (for artifact listings).
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    pub size: u64,
+    pub content_type: String,
+    pub sha256: Option<String>,
+    pub modified: Option<String>,
+}
+
+pub async fn file_info(path: &Path) -> Option<FileInfo> {
+    let meta = tokio::fs::metadata(path).await.ok()?;
+    if !meta.is_file() {
+        return None;
+    }
+    let modified = meta.modified().ok();
+    Some(FileInfo {
+        size: meta.len(),
+        content_type: content_type_for(path).to_string(),
+        sha256: sha256_cached(path, meta.len(), modified).await,
+        modified: modified.map(|m| chrono::DateTime::<chrono::Utc>::from(m).to_rfc3339()),
+    })
+}
+
+fn etag_matches(header_val: &str, etag: &str) -> bool {
+    let bare = etag.trim_matches('"');
+    header_val
+        .split(',')
+        .map(|s| s.trim())
+        .any(|cand| cand == "*" || cand.trim_start_matches("W/").trim_matches('"') == bare)
+}
+
+/// Parse a single `bytes=` range against `len`. `Err(())` means unsatisfiable.
+fn parse_range(raw: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = raw.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        // Multi-range: serve the full body instead (allowed by RFC 9110).
+        return None;
+    }
+    let (start_s, end_s) = spec.split_once('-')?;
+    let (start_s, end_s) = (start_s.trim(), end_s.trim());
+    if start_s.is_empty() {
+        let suffix: u64 = end_s.parse().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        let start = len.saturating_sub(suffix);
+        return Some(Ok((start, len - 1)));
+    }
+    let start: u64 = start_s.parse().ok()?;
+    if start >= len {
+        return Some(Err(()));
+    }
+    let end = if end_s.is_empty() {
+        len - 1
+    } else {
+        let e: u64 = end_s.parse().ok()?;
+        if e < start {
+            return None;
+        }
+        e.min(len - 1)
+    };
+    Some(Ok((start, end)))
+}
+
+/// Serve `path` with ETag/Last-Modified validation and byte-range support.
+pub async fn serve_file(headers: &HeaderMap, path: &Path, ctype: &str) -> Response {
+    let meta = match tokio::fs::metadata(path).await {
+        Ok(m) if m.is_file() => m,
+        _ => {
+            let gz = gz_sibling(path);
+            if tokio::fs::metadata(&gz)
+                .await
+                .map(|m| m.is_file())
+                .unwrap_or(false)
+            {
+                return serve_precompressed(headers, &gz, ctype).await;
+            }
+            return (StatusCode::NOT_FOUND, "file not found".to_string()).into_response();
+        }
+    };
+    let len = meta.len();
+    let modified = meta.modified().ok();
+    let last_modified = modified.map(httpdate);
+    let etag = etag_for(path, len, modified).await;
+
+    // Conditional GET: If-None-Match wins over If-Modified-Since.
+    let inm = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok());
+    let not_modified = match (inm, etag.as_deref()) {
+        (Some(h), Some(tag)) => etag_matches(h, tag),
+        (Some(_), None) => false,
+        (None, _) => match (
+            headers
+                .get(header::IF_MODIFIED_SINCE)
+                .and_then(|v| v.to_str().ok()),
+            last_modified.as_deref(),
+        ) {
+            (Some(ims), Some(lm)) => ims.trim() == lm,
+            _ => false,
+        },
+    };
+
+    let mut base = HeaderMap::new();
+    base.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Some(tag) = etag.as_deref().and_then(|t| HeaderValue::from_str(t).ok()) {
+        base.insert(header::ETAG, tag);
+    }
+    if let Some(lm) = last_modified
+        .as_deref()
+        .and_then(|t| HeaderValue::from_str(t).ok())
+    {
+        base.insert(header::LAST_MODIFIED, lm);
+    }
+
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, base).into_response();
+    }
+    if let Ok(ct) = HeaderValue::from_str(ctype) {
+        base.insert(header::CONTENT_TYPE, ct);
+    }
+
+    // If-Range: only honor the range when the validator still matches.
+    let range_allowed = match headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        Some(ir) => {
+            etag.as_deref()
+                .map(|t| etag_matches(ir, t))
+                .unwrap_or(false)
+                || last_modified.as_deref() == Some(ir.trim())
+        }
+        None => true,
+    };
+    let range = if range_allowed {
+        headers
+            .get(header::RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|r| parse_range(r, len))
+    } else {
+        None
+    };
+
+    match range {
+        Some(Err(())) => {
+            if let Ok(v) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                base.insert(header::CONTENT_RANGE, v);
+            }
+            (StatusCode::RANGE_NOT_SATISFIABLE, base).into_response()
+        }
+        Some(Ok((start, end))) => {
+            let want = end - start + 1;
+            let mut f = match tokio::fs::File::open(path).await {
+                Ok(f) => f,
+                Err(e) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+                }
+            };
+            if let Err(e) = f.seek(std::io::SeekFrom::Start(start)).await {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            if let Ok(v) = HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, len)) {
+                base.insert(header::CONTENT_RANGE, v);
+            }
+            base.insert(header::CONTENT_LENGTH, HeaderValue::from(want));
+            let body = stream_reader(f.into_std().await.take(want));
+            (StatusCode::PARTIAL_CONTENT, base, body).into_response()
+        }
+        None => match tokio::fs::File::open(path).await {
+            Ok(f) => {
+                base.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+                (StatusCode::OK, base, stream_file(f.into_std().await, false)).into_response()
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+        },
+    }
+}
+
+fn gz_sibling(path: &Path) -> PathBuf {
+    let mut os = path.as_os_str().to_os_string();
+    os.push(".gz");
+    PathBuf::from(os)
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    let Some(raw) = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    raw.split(',').any(|part| {
+        let mut it = part.trim().split(';');
+        let coding = it.next().unwrap_or("").trim().to_ascii_lowercase();
+        let q_zero = it.any(|p| {
+            let p = p.trim();
+            p == "q=0" || p == "q=0.0" || p == "q=0.00" || p == "q=0.000"
+        });
+        (coding == "gzip" || coding == "x-gzip" || coding == "*") && !q_zero
+    })
+}
+
+/// Stream `file` in chunks, gunzipping it on the way when `inflate` is set.
+fn stream_file(file: std::fs::File, inflate: bool) -> Body {
+    if inflate {
+        stream_reader(flate2::read::GzDecoder::new(std::io::BufReader::new(file)))
+    } else {
+        stream_reader(file)
+    }
+}
+
+/// Stream `reader` in 64 KiB chunks from a blocking thread, so no body is held in memory.
+fn stream_reader(mut reader: impl Read + Send + 'static) -> Body {
+    let (tx, rx) = mpsc::channel::<std::io::Result<Vec<u8>>>(4);
+    tokio::task::spawn_blocking(move || {
+        loop {
+            let mut buf = vec![0u8; 64 * 1024];
+            match reader.read(&mut buf) {
+                Ok(0) => return,
+                Ok(n) => {
+                    buf.truncate(n);
+                    if tx.blocking_send(Ok(buf)).is_err() {
+                        return; // client went away
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.blocking_send(Err(e));
+                    return;
+                }
+            }
+        }
+    });
+    Body::from_stream(ReceiverStream::new(rx))
+}
+
+/// Serve a stored `<file>.gz` for a request of `<file>`, streamed as stored or inflated on the
+/// fly. No `Accept-Ranges`: offsets into either body don't map onto the stored file.
+async fn serve_precompressed(headers: &HeaderMap, gz: &Path, ctype: &str) -> Response {
+    let meta = match tokio::fs::metadata(gz).await {
+        Ok(m) => m,
+        Err(_) => return (StatusCode::NOT_FOUND, "file not found".to_string()).into_response(),
+    };
+    let modified = meta.modified().ok();
+    let last_modified = modified.map(httpdate);
+    let gzip_ok = accepts_gzip(headers);
+    // Distinct validators per representation (encoded vs inflated).
+    let etag = etag_for(gz, meta.len(), modified).await.map(|t| {
+        let bare = t.trim_matches('"').to_string();
+        if gzip_ok {
+            format!("\"{}-gz\"", bare)
+        } else {
+            format!("\"{}-id\"", bare)
+        }
+    });
+
+    let mut base = HeaderMap::new();
+    base.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if let Some(tag) = etag.as_deref().and_then(|t| HeaderValue::from_str(t).ok()) {
+        base.insert(header::ETAG, tag);
+    }
+    if let Some(lm) = last_modified
+        .as_deref()
+        .and_then(|t| HeaderValue::from_str(t).ok())
+    {
+        base.insert(header::LAST_MODIFIED, lm);
+    }
+    let inm = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok());
+    if let (Some(h), Some(tag)) = (inm, etag.as_deref()) {
+        if etag_matches(h, tag) {
+            return (StatusCode::NOT_MODIFIED, base).into_response();
+        }
+    }
+    if let Ok(ct) = HeaderValue::from_str(ctype) {
+        base.insert(header::CONTENT_TYPE, ct);
+    }
+
+    let file = match tokio::fs::File::open(gz).await {
+        Ok(f) => f.into_std().await,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    if gzip_ok {
+        base.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    }
+    (StatusCode::OK, base, stream_file(file, !gzip_ok)).into_response()
+}
+
+/// Resolve a `/runs/<tail>` request path under `root`, rejecting traversal.
+/// Directories resolve to their `index.html` (matches the previous ServeDir behavior).
+pub fn resolve_under(root: &Path, tail: &str) -> Option<PathBuf> {
+    let mut out = root.to_path_buf();
+    for seg in tail.split('/') {
+        if seg.is_empty() || seg == "." {
+            continue;
+        }
+        if seg == ".." || seg.contains('\\') || seg.contains('\0') {
+            return None;
+        }
+        out.push(seg);
+    }
+    if out.is_dir() {
+        out.push("index.html");
+    }
+    Some(out)
+}
+
+/// RFC 7231 IMF-fixdate, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn httpdate(t: SystemTime) -> String {
+    let dt: chrono::DateTime<chrono::Utc> = t.into();
+    dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range_handles_bounded_open_and_suffix_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 100), Some(Ok((0, 9))));
+        assert_eq!(parse_range("bytes=90-", 100), Some(Ok((90, 99))));
+        assert_eq!(parse_range("bytes=-10", 100), Some(Ok((90, 99))));
+        // End past the file is clamped; a suffix longer than the file is the whole file.
+        assert_eq!(parse_range("bytes=50-500", 100), Some(Ok((50, 99))));
+        assert_eq!(parse_range("bytes=-500", 100), Some(Ok((0, 99))));
+    }
+
+    #[test]
+    fn parse_range_rejects_unsatisfiable_and_ignores_the_rest() {
+        assert_eq!(parse_range("bytes=100-", 100), Some(Err(())));
+        assert_eq!(parse_range("bytes=-0", 100), Some(Err(())));
+        assert_eq!(parse_range("bytes=-5", 0), Some(Err(())));
+        // Served as a full 200 instead.
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), None);
+        assert_eq!(parse_range("bytes=9-3", 100), None);
+        assert_eq!(parse_range("items=0-9", 100), None);
+        assert_eq!(parse_range("bytes=a-b", 100), None);
+    }
+
+    #[tokio::test]
+    async fn serve_file_streams_ranges_and_full_bodies() {
+        let dir = std::env::temp_dir()
+            .join("one-engine-artifacts")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("run.log");
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_static("bytes=100000-"));
+        let res = serve_file(&headers, &path, "text/plain").await;
+        assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            res.headers()[header::CONTENT_RANGE],
+            "bytes 100000-199999/200000"
+        );
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], &data[100_000..]);
+
+        let res = serve_file(&HeaderMap::new(), &path, "text/plain").await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_LENGTH], "200000");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], &data[..]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
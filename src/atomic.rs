@@ -0,0 +1,326 @@
+//! Crash-safe artifact writes: receipts, wiki pages, graphs, ruliad output and thread appends.
+//!
+//! [`write`] puts the bytes in a temp file next to the target and renames it over, so readers
+//! (and a process that crashed mid-write) see the old file or the new one, never half of
+//! either. [`append`] writes a whole line with one `write` on an `O_APPEND` handle.
+//!
+//! Whether data is also flushed to disk is the `artifacts.fsync` policy:
+//!
+//! ```yaml
+//! artifacts:
+//!   fsync: none   # none (default) | file | full
+//! ```
+//!
+//! `file` fsyncs the data before the rename; `full` also fsyncs the directory so the rename
+//! itself survives a power cut. `ONE_ENGINE_FSYNC` overrides the policy.
+//!
+//! Writes made inside [`metered`] are charged to its [`Meter`] first, which may refuse them
+//! (disk quotas).
+//!
+//! When `META3_ROOT` is read-only, writes under it land wherever [`crate::storage`] redirects
+//! them (a scratch dir or memory), or fail with a "read-only" error.
+
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::storage::{self, Target};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    /// Atomic rename only: safe against crashes, not against power loss.
+    #[default]
+    None,
+    /// fsync the file before it is renamed into place (and after each append).
+    File,
+    /// `File`, plus fsync the parent directory after the rename.
+    Full,
+}
+
+impl Durability {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "off" => Some(Durability::None),
+            "file" => Some(Durability::File),
+            "full" => Some(Durability::Full),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ArtifactsSection {
+    #[serde(default)]
+    fsync: Durability,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesArtifacts {
+    #[serde(default)]
+    artifacts: Option<ArtifactsSection>,
+}
+
+static DURABILITY: Lazy<Durability> = Lazy::new(|| {
+    if let Some(d) = std::env::var("ONE_ENGINE_FSYNC")
+        .ok()
+        .and_then(|s| Durability::parse(&s))
+    {
+        return d;
+    }
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesArtifacts>(&raw).ok())
+        .and_then(|p| p.artifacts)
+        .map(|a| a.fsync)
+        .unwrap_or_default()
+});
+
+/// The configured fsync policy (read once).
+pub fn durability() -> Durability {
+    *DURABILITY
+}
+
+/// Accounts for the bytes written by one unit of work (a run).
+pub trait Meter: Send + Sync {
+    /// Called before `bytes` are written to `path`; an error refuses the write.
+    fn charge(&self, path: &Path, bytes: u64) -> io::Result<()>;
+}
+
+tokio::task_local! {
+    static METER: Arc<dyn Meter>;
+}
+
+/// Run `fut` with every [`write`] and [`append`] it makes charged to `meter`. Tasks it spawns
+/// are not metered, except blocking work moved with [`spawn_blocking`].
+pub async fn metered<F: Future>(meter: Arc<dyn Meter>, fut: F) -> F::Output {
+    METER.scope(meter, fut).await
+}
+
+fn current_meter() -> Option<Arc<dyn Meter>> {
+    METER.try_with(|m| m.clone()).ok()
+}
+
+fn charge(path: &Path, bytes: u64) -> io::Result<()> {
+    METER.try_with(|m| m.charge(path, bytes)).unwrap_or(Ok(()))
+}
+
+/// Keep the caller's meter for work moved to the blocking pool.
+fn with_meter<T>(meter: Option<Arc<dyn Meter>>, f: impl FnOnce() -> T) -> T {
+    match meter {
+        Some(m) => METER.sync_scope(m, f),
+        None => f(),
+    }
+}
+
+static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A temp path in the target's directory (rename must not cross filesystems), unique per
+/// process and call so concurrent writers of the same file never share one.
+fn tmp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), seq))
+}
+
+fn sync_dir(path: &Path) -> io::Result<()> {
+    match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        // Directories can't be opened for fsync on Windows; the rename is already durable there.
+        Some(dir) if cfg!(unix) => File::open(dir)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+/// Atomically replace `path` with `contents`; a drop-in for `fs::write`.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_with(path.as_ref(), contents.as_ref(), durability())
+}
+
+/// [`write`] with an explicit durability instead of the configured one.
+pub fn write_with(path: &Path, contents: &[u8], durability: Durability) -> io::Result<()> {
+    charge(path, contents.len() as u64)?;
+    let path = match storage::target(path, false)? {
+        Target::Disk(p) => p,
+        Target::Memory(rel) => {
+            storage::memory_write(rel, contents, false);
+            return Ok(());
+        }
+    };
+    let path = path.as_path();
+    let tmp = tmp_path(path);
+    let res: io::Result<()> = (|| {
+        let mut f = File::create(&tmp)?;
+        f.write_all(contents)?;
+        if durability != Durability::None {
+            f.sync_all()?;
+        }
+        drop(f);
+        fs::rename(&tmp, path)?;
+        if durability == Durability::Full {
+            sync_dir(path)?;
+        }
+        Ok(())
+    })();
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    res
+}
+
+/// [`write`] from async code, on the blocking pool; a drop-in for `tokio::fs::write`.
+pub async fn write_async(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let contents = contents.as_ref().to_vec();
+    let meter = current_meter();
+    tokio::task::spawn_blocking(move || with_meter(meter, || write(path, contents)))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// `tokio::task::spawn_blocking` that keeps the caller's meter for the writes `f` makes.
+pub async fn spawn_blocking<T, F>(f: F) -> Result<T, tokio::task::JoinError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let meter = current_meter();
+    tokio::task::spawn_blocking(move || with_meter(meter, f)).await
+}
+
+/// Append `line` plus `\n` to `path` (created if missing) in a single write, so concurrent
+/// appenders never split each other's lines.
+pub fn append(path: impl AsRef<Path>, line: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut buf = Vec::with_capacity(line.len() + 1);
+    buf.extend_from_slice(line.as_bytes());
+    buf.push(b'\n');
+    charge(path, buf.len() as u64)?;
+    let path = match storage::target(path, true)? {
+        Target::Disk(p) => p,
+        Target::Memory(rel) => {
+            storage::memory_write(rel, &buf, true);
+            return Ok(());
+        }
+    };
+    let mut f = OpenOptions::new().create(true).append(true).open(path)?;
+    f.write_all(&buf)?;
+    if durability() != Durability::None {
+        f.sync_data()?;
+    }
+    Ok(())
+}
+
+/// [`append`] from async code, on the blocking pool.
+pub async fn append_async(path: impl AsRef<Path>, line: &str) -> io::Result<()> {
+    let path = path.as_ref().to_path_buf();
+    let line = line.to_string();
+    let meter = current_meter();
+    tokio::task::spawn_blocking(move || with_meter(meter, || append(path, &line)))
+        .await
+        .map_err(io::Error::other)?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch() -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join("one-engine-atomic")
+            .join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn write_replaces_and_leaves_no_temp_files() {
+        let dir = scratch();
+        let path = dir.join("response.json");
+        for d in [Durability::None, Durability::File, Durability::Full] {
+            write_with(&path, b"{\"v\":1}", d).unwrap();
+            write_with(&path, b"{\"v\":2}", d).unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), "{\"v\":2}");
+        }
+        let names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name())
+            .collect();
+        assert_eq!(names, vec![std::ffi::OsString::from("response.json")]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn failed_write_cleans_up_its_temp_file() {
+        let dir = scratch();
+        let path = dir.join("index.html");
+        write(&path, "old").unwrap();
+        // Renaming a file over a non-empty directory fails after the temp file is written.
+        let blocked = dir.join("blocked");
+        fs::create_dir_all(blocked.join("x")).unwrap();
+        assert!(write(&blocked, "new").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn metered_writes_are_charged_and_can_be_refused() {
+        struct Limit(std::sync::Mutex<u64>);
+        impl Meter for Limit {
+            fn charge(&self, _path: &Path, bytes: u64) -> io::Result<()> {
+                let mut left = self.0.lock().unwrap();
+                if bytes > *left {
+                    return Err(io::Error::other("quota exceeded"));
+                }
+                *left -= bytes;
+                Ok(())
+            }
+        }
+        let dir = scratch();
+        let limit = Arc::new(Limit(std::sync::Mutex::new(10)));
+        let res = metered(limit.clone(), async {
+            write_async(dir.join("a"), "12345").await?;
+            append_async(dir.join("b"), "123").await?;
+            write(dir.join("c"), "123")
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(*limit.0.lock().unwrap(), 1);
+        assert!(!dir.join("c").exists());
+        // Outside the scope nothing is charged.
+        write(dir.join("c"), "123").unwrap();
+        assert_eq!(*limit.0.lock().unwrap(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn concurrent_appends_keep_whole_lines() {
+        let dir = scratch();
+        let path = dir.join("t.jsonl");
+        let line = "x".repeat(3000);
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..50 {
+                        append(&path, &line).unwrap();
+                    }
+                });
+            }
+        });
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 400);
+        assert!(text.lines().all(|l| l == line));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,273 @@
+//! Streaming zip writer for run bundles (`/runs/{run_id}/bundle.zip`).
+//!
+//! Members are deflated one at a time and sent as soon as each is ready, so memory stays
+//! bounded by the largest member and the client starts receiving bytes immediately. Sizes
+//! are known before each local header is written (no data descriptors), and there is no
+//! zip64: bundles are size-capped far below 4 GiB.
+
+use axum::body::Body;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// General-purpose flag bit 11: names are UTF-8.
+const FLAG_UTF8: u16 = 0x0800;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+struct Entry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed: u32,
+    uncompressed: u32,
+    offset: u32,
+    time: u16,
+    date: u16,
+}
+
+/// MS-DOS time/date (local fields, 2-second resolution; UTC here).
+fn dos_datetime(t: SystemTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let dt: chrono::DateTime<chrono::Utc> = t.into();
+    let year = dt.year().clamp(1980, 2107) as u16;
+    let time = ((dt.hour() as u16) << 11) | ((dt.minute() as u16) << 5) | (dt.second() as u16 / 2);
+    let date = ((year - 1980) << 9) | ((dt.month() as u16) << 5) | dt.day() as u16;
+    (time, date)
+}
+
+/// Deflate `data`, falling back to stored when compression doesn't help.
+fn compress(data: &[u8]) -> (u16, Vec<u8>) {
+    let mut enc = DeflateEncoder::new(Vec::new(), Compression::default());
+    match enc.write_all(data).and_then(|_| enc.finish()) {
+        Ok(out) if out.len() < data.len() => (METHOD_DEFLATE, out),
+        _ => (METHOD_STORED, data.to_vec()),
+    }
+}
+
+fn local_header(e: &Entry) -> Vec<u8> {
+    let mut h = Vec::with_capacity(30 + e.name.len());
+    h.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+    h.extend_from_slice(&20u16.to_le_bytes()); // version needed
+    h.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+    h.extend_from_slice(&e.method.to_le_bytes());
+    h.extend_from_slice(&e.time.to_le_bytes());
+    h.extend_from_slice(&e.date.to_le_bytes());
+    h.extend_from_slice(&e.crc.to_le_bytes());
+    h.extend_from_slice(&e.compressed.to_le_bytes());
+    h.extend_from_slice(&e.uncompressed.to_le_bytes());
+    h.extend_from_slice(&(e.name.len() as u16).to_le_bytes());
+    h.extend_from_slice(&0u16.to_le_bytes()); // extra len
+    h.extend_from_slice(e.name.as_bytes());
+    h
+}
+
+fn central_header(e: &Entry) -> Vec<u8> {
+    let mut h = Vec::with_capacity(46 + e.name.len());
+    h.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+    h.extend_from_slice(&20u16.to_le_bytes()); // version made by
+    h.extend_from_slice(&20u16.to_le_bytes()); // version needed
+    h.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+    h.extend_from_slice(&e.method.to_le_bytes());
+    h.extend_from_slice(&e.time.to_le_bytes());
+    h.extend_from_slice(&e.date.to_le_bytes());
+    h.extend_from_slice(&e.crc.to_le_bytes());
+    h.extend_from_slice(&e.compressed.to_le_bytes());
+    h.extend_from_slice(&e.uncompressed.to_le_bytes());
+    h.extend_from_slice(&(e.name.len() as u16).to_le_bytes());
+    h.extend_from_slice(&0u16.to_le_bytes()); // extra len
+    h.extend_from_slice(&0u16.to_le_bytes()); // comment len
+    h.extend_from_slice(&0u16.to_le_bytes()); // disk number
+    h.extend_from_slice(&0u16.to_le_bytes()); // internal attrs
+    h.extend_from_slice(&0u32.to_le_bytes()); // external attrs
+    h.extend_from_slice(&e.offset.to_le_bytes());
+    h.extend_from_slice(e.name.as_bytes());
+    h
+}
+
+fn end_of_central_dir(count: usize, size: u32, offset: u32) -> Vec<u8> {
+    let mut h = Vec::with_capacity(22);
+    h.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    h.extend_from_slice(&0u16.to_le_bytes()); // this disk
+    h.extend_from_slice(&0u16.to_le_bytes()); // central dir disk
+    h.extend_from_slice(&(count as u16).to_le_bytes());
+    h.extend_from_slice(&(count as u16).to_le_bytes());
+    h.extend_from_slice(&size.to_le_bytes());
+    h.extend_from_slice(&offset.to_le_bytes());
+    h.extend_from_slice(&0u16.to_le_bytes()); // comment len
+    h
+}
+
+fn too_large() -> std::io::Error {
+    std::io::Error::other("bundle exceeds 4 GiB (no zip64)")
+}
+
+/// Stream a zip holding `index.json` followed by `members` (`(file, name in zip)`).
+/// Members that can't be read by the time they are reached are left out silently;
+/// `index.json` is the caller's record of what was meant to be included.
+pub fn stream_zip(index_json: Vec<u8>, members: Vec<(PathBuf, String)>) -> Body {
+    let (tx, rx) = mpsc::channel::<std::io::Result<Vec<u8>>>(4);
+    tokio::spawn(async move {
+        let mut offset: u64 = 0;
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut pending: Vec<(Option<PathBuf>, String)> = vec![(None, "index.json".to_string())];
+        pending.extend(members.into_iter().map(|(p, n)| (Some(p), n)));
+        let mut index_json = Some(index_json);
+
+        for (path, name) in pending {
+            let (data, mtime) = match path {
+                None => (index_json.take().unwrap_or_default(), SystemTime::now()),
+                Some(p) => {
+                    let mtime = tokio::fs::metadata(&p)
+                        .await
+                        .and_then(|m| m.modified())
+                        .unwrap_or_else(|_| SystemTime::now());
+                    match tokio::fs::read(&p).await {
+                        Ok(d) => (d, mtime),
+                        Err(_) => continue,
+                    }
+                }
+            };
+            if data.len() > u32::MAX as usize || offset > u32::MAX as u64 {
+                let _ = tx.send(Err(too_large())).await;
+                return;
+            }
+            let packed = tokio::task::spawn_blocking(move || {
+                let mut crc = Crc::new();
+                crc.update(&data);
+                let (method, out) = compress(&data);
+                (crc.sum(), data.len() as u32, method, out)
+            })
+            .await;
+            let Ok((crc, uncompressed, method, out)) = packed else {
+                continue;
+            };
+            let (time, date) = dos_datetime(mtime);
+            let entry = Entry {
+                name,
+                method,
+                crc,
+                compressed: out.len() as u32,
+                uncompressed,
+                offset: offset as u32,
+                time,
+                date,
+            };
+            let mut chunk = local_header(&entry);
+            chunk.extend_from_slice(&out);
+            offset += chunk.len() as u64;
+            entries.push(entry);
+            if tx.send(Ok(chunk)).await.is_err() {
+                return; // client went away
+            }
+        }
+
+        if offset > u32::MAX as u64 || entries.len() > u16::MAX as usize {
+            let _ = tx.send(Err(too_large())).await;
+            return;
+        }
+        let mut central: Vec<u8> = Vec::new();
+        for e in &entries {
+            central.extend_from_slice(&central_header(e));
+        }
+        let eocd = end_of_central_dir(entries.len(), central.len() as u32, offset as u32);
+        central.extend_from_slice(&eocd);
+        let _ = tx.send(Ok(central)).await;
+    });
+    Body::from_stream(ReceiverStream::new(rx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::io::Read;
+
+    fn u16_at(b: &[u8], at: usize) -> usize {
+        u16::from_le_bytes([b[at], b[at + 1]]) as usize
+    }
+
+    fn u32_at(b: &[u8], at: usize) -> usize {
+        u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]]) as usize
+    }
+
+    /// Read every member back through the central directory, checking each CRC.
+    fn unzip(zip: &[u8]) -> BTreeMap<String, Vec<u8>> {
+        let eocd = zip.len() - 22;
+        assert_eq!(u32_at(zip, eocd), 0x0605_4b50);
+        let (count, mut at) = (u16_at(zip, eocd + 10), u32_at(zip, eocd + 16));
+        let mut out = BTreeMap::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(zip, at), 0x0201_4b50);
+            let (method, crc) = (u16_at(zip, at + 10), u32_at(zip, at + 16));
+            let (compressed, uncompressed) = (u32_at(zip, at + 20), u32_at(zip, at + 24));
+            let name_len = u16_at(zip, at + 28);
+            let local = u32_at(zip, at + 42);
+            let name = String::from_utf8(zip[at + 46..at + 46 + name_len].to_vec()).unwrap();
+            assert_eq!(u32_at(zip, local), 0x0403_4b50);
+            let start = local + 30 + u16_at(zip, local + 26) + u16_at(zip, local + 28);
+            let raw = &zip[start..start + compressed];
+            let data = match method as u16 {
+                METHOD_STORED => raw.to_vec(),
+                METHOD_DEFLATE => {
+                    let mut d = Vec::new();
+                    flate2::read::DeflateDecoder::new(raw)
+                        .read_to_end(&mut d)
+                        .unwrap();
+                    d
+                }
+                m => panic!("unexpected method {}", m),
+            };
+            let mut sum = Crc::new();
+            sum.update(&data);
+            assert_eq!(
+                (data.len(), sum.sum() as usize),
+                (uncompressed, crc),
+                "{}",
+                name
+            );
+            out.insert(name, data);
+            at += 46 + name_len + u16_at(zip, at + 30) + u16_at(zip, at + 32);
+        }
+        out
+    }
+
+    #[tokio::test]
+    async fn bundle_round_trips_through_unzip() {
+        let dir = std::env::temp_dir()
+            .join("one-engine-bundle")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = "step ok\n".repeat(2_000).into_bytes();
+        let noise: Vec<u8> = (0..64u8).map(|i| i.wrapping_mul(151) ^ 0x5a).collect();
+        std::fs::write(dir.join("run.log"), &log).unwrap();
+        std::fs::write(dir.join("noise.bin"), &noise).unwrap();
+        let index = br#"{"files":["run.log","noise.bin","gone.json"]}"#.to_vec();
+
+        let body = stream_zip(
+            index.clone(),
+            vec![
+                (dir.join("run.log"), "deliverables/run.log".to_string()),
+                (dir.join("noise.bin"), "receipt/noise.bin".to_string()),
+                (dir.join("gone.json"), "receipt/gone.json".to_string()),
+            ],
+        );
+        let zip = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let files = unzip(&zip);
+
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            ["deliverables/run.log", "index.json", "receipt/noise.bin"]
+        );
+        assert_eq!(files["index.json"], index);
+        assert_eq!(files["deliverables/run.log"], log);
+        assert_eq!(files["receipt/noise.bin"], noise);
+        // The repetitive log was deflated, not stored.
+        assert!(zip.len() < log.len());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1039 @@
+//! Context resolver: pluggable `ContextSource`s, ranked and assembled under a token budget.
+//!
+//! Each source turns a query into candidate items (with provenance); candidates are scored
+//! as `relevance * w_relevance + recency * w_recency` and packed greedily, highest score
+//! first, until the token budget is spent. Chat, N* and meta loops call [`resolve`] and
+//! either use the structured bundle or [`ContextBundle::render`] it into a prompt block.
+//!
+//! Every item is stamped with its source mtime, a sha256 and a TTL class. Items passed back
+//! as `inputs.context` are re-checked by [`assess`] (TTL since `checked_at`, and sha drift for
+//! whole-file items); any stale item sets Δ in `engine::run`.
+
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use one_engine::{atomic, jsonl};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+use walkdir::WalkDir;
+
+use crate::integrations::receipt_acl::{self, Caller};
+use crate::integrations::workspace_files;
+
+pub const DEFAULT_BUDGET_TOKENS: usize = 2000;
+/// Per-item cap so one large file or receipt cannot take the whole budget.
+const MAX_ITEM_CHARS: usize = 2000;
+/// Only the tail of large JSONL files is scanned.
+const TAIL_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_RECEIPT_DIRS: usize = 500;
+const MAX_GLOB_FILES: usize = 2000;
+const MAX_GLOB_FILE_BYTES: u64 = 256 * 1024;
+/// Recency decays by half every this many hours.
+const RECENCY_HALF_LIFE_HOURS: f64 = 72.0;
+const SKIP_DIRS: &[&str] = &[".git", "target", "node_modules"];
+
+/// TTL class → seconds a resolved item may be reused before it must be re-resolved.
+pub const TTL_CLASSES: &[(&str, i64)] = &[
+    ("live", 15 * 60),
+    ("session", 60 * 60),
+    ("run", 24 * 60 * 60),
+    ("reference", 7 * 24 * 60 * 60),
+    ("archive", 30 * 24 * 60 * 60),
+];
+
+fn ttl_class_for(source: &str) -> &'static str {
+    match source {
+        "fs" => "live",
+        "threads" => "session",
+        "receipts" => "run",
+        "research" => "reference",
+        _ => "archive",
+    }
+}
+
+fn ttl_secs(class: &str) -> i64 {
+    TTL_CLASSES
+        .iter()
+        .find(|(c, _)| *c == class)
+        .map(|(_, s)| *s)
+        .unwrap_or(60 * 60)
+}
+
+pub const ALL_SOURCES: &[&str] = &["threads", "receipts", "research", "codex", "fs"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ContextWeights {
+    pub relevance: f32,
+    pub recency: f32,
+}
+
+impl Default for ContextWeights {
+    fn default() -> Self {
+        Self {
+            relevance: 0.7,
+            recency: 0.3,
+        }
+    }
+}
+
+/// What to resolve and from where.
+#[derive(Debug, Clone, Default)]
+pub struct ContextQuery {
+    pub query: String,
+    /// Source names to consult; empty means all of [`ALL_SOURCES`].
+    pub sources: Vec<String>,
+    pub budget_tokens: usize,
+    pub weights: ContextWeights,
+    /// Required for the `threads` source.
+    pub user_id: Option<String>,
+    /// Restricts `threads` to one thread; otherwise all of the user's threads are scanned.
+    pub thread: Option<String>,
+    /// Globs for the `fs` source (e.g. `src/**/*.rs`), relative to `META3_PATH` or cwd.
+    pub globs: Vec<String>,
+    /// Who the bundle is for. `None` (in-process callers) reads every receipt; otherwise the
+    /// `receipts` source only returns runs this caller may read, and the shared N* log only
+    /// to the admin.
+    pub reader: Option<Caller>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema, ToSchema)]
+pub struct Provenance {
+    pub path: String,
+    pub line: Option<usize>,
+    pub run_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, ToSchema)]
+pub struct ContextItem {
+    pub source: String,
+    pub id: String,
+    pub content: String,
+    pub ts: Option<String>,
+    pub relevance: f32,
+    pub recency: f32,
+    pub score: f32,
+    pub tokens: usize,
+    pub provenance: Provenance,
+    /// Unix mtime of `provenance.path` when resolved.
+    pub mtime: Option<i64>,
+    pub sha256: String,
+    /// "file": `sha256` covers the whole file at `provenance.path` (re-checked for drift);
+    /// "record": it covers only this item's record (line, receipt).
+    pub sha_scope: String,
+    pub ttl_class: String,
+    /// Seconds after `checked_at` at which the item is stale.
+    pub ttl: i64,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, ToSchema)]
+pub struct SourceStat {
+    pub name: String,
+    pub candidates: usize,
+    pub included: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema, ToSchema)]
+pub struct ContextBundle {
+    pub query: String,
+    pub budget_tokens: usize,
+    pub used_tokens: usize,
+    pub resolved_at: String,
+    pub items: Vec<ContextItem>,
+    /// Relevant candidates left out because the budget ran out.
+    pub dropped: usize,
+    pub sources: Vec<SourceStat>,
+}
+
+impl ContextBundle {
+    /// Plain-text block for prompts, one item per section with its provenance.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for it in &self.items {
+            let loc = match it.provenance.line {
+                Some(l) => format!("{}:{}", it.provenance.path, l),
+                None => it.provenance.path.clone(),
+            };
+            out.push_str(&format!(
+                "[{} {}]\n{}\n\n",
+                it.source,
+                loc,
+                it.content.trim()
+            ));
+        }
+        out
+    }
+}
+
+/// A raw candidate before scoring.
+pub struct Candidate {
+    pub id: String,
+    pub content: String,
+    pub ts: Option<chrono::DateTime<chrono::Utc>>,
+    pub provenance: Provenance,
+    /// Set when `content` is the whole file at `provenance.path`; otherwise the record is hashed.
+    pub file_sha256: Option<String>,
+    /// Overrides the source's TTL class (e.g. a research artifact's own `ttl`).
+    pub ttl: Option<i64>,
+}
+
+pub trait ContextSource: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Candidates that match at least one query term; the resolver scores and packs them.
+    fn collect(&self, q: &ContextQuery, terms: &[String]) -> Result<Vec<Candidate>, String>;
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn is_safe_segment(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 128
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && !s.contains("..")
+        && s != "."
+}
+
+fn codex_history_enabled() -> bool {
+    match std::env::var("ONE_ENGINE_ENABLE_CODEX_HISTORY") {
+        Ok(v) => matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        ),
+        Err(_) => false,
+    }
+}
+
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = query
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '.')
+        .filter(|t| t.len() >= 3)
+        .map(|t| t.to_string())
+        .collect();
+    terms.sort();
+    terms.dedup();
+    if terms.is_empty() && !query.trim().is_empty() {
+        terms.push(query.trim().to_lowercase());
+    }
+    terms
+}
+
+fn matches_any(text: &str, terms: &[String]) -> bool {
+    let t = text.to_lowercase();
+    terms.iter().any(|term| t.contains(term.as_str()))
+}
+
+/// Fraction of query terms present, plus a bonus when the whole query appears verbatim.
+fn relevance(text: &str, query: &str, terms: &[String]) -> f32 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let t = text.to_lowercase();
+    let hits = terms
+        .iter()
+        .filter(|term| t.contains(term.as_str()))
+        .count();
+    let mut r = hits as f32 / terms.len() as f32;
+    let q = query.trim().to_lowercase();
+    if terms.len() > 1 && !q.is_empty() && t.contains(&q) {
+        r += 0.25;
+    }
+    r.min(1.0)
+}
+
+fn recency(ts: Option<chrono::DateTime<chrono::Utc>>) -> f32 {
+    let Some(ts) = ts else {
+        return 0.0;
+    };
+    let hours = (chrono::Utc::now() - ts).num_minutes().max(0) as f64 / 60.0;
+    0.5f64.powf(hours / RECENCY_HALF_LIFE_HOURS) as f32
+}
+
+fn estimate_tokens(s: &str) -> usize {
+    crate::engine::tokens::count(&crate::engine::router::model_name(), s).max(1)
+}
+
+fn parse_ts(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|d| d.with_timezone(&chrono::Utc))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// sha256 of the file at `path`, hashed as it streams; `None` when it is over
+/// `MAX_GLOB_FILE_BYTES`, which no whole-file item is stamped from, so it has changed.
+fn file_sha256(path: &Path) -> std::io::Result<Option<String>> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+    let file = fs::File::open(path)?;
+    if file.metadata()?.len() > MAX_GLOB_FILE_BYTES {
+        return Ok(None);
+    }
+    let mut hasher = Sha256::new();
+    // The file may grow after the size check.
+    let n = std::io::copy(&mut file.take(MAX_GLOB_FILE_BYTES + 1), &mut hasher)?;
+    if n > MAX_GLOB_FILE_BYTES {
+        return Ok(None);
+    }
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+fn mtime_secs(path: &Path) -> Option<i64> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+fn mtime(path: &Path) -> Option<chrono::DateTime<chrono::Utc>> {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .map(chrono::DateTime::<chrono::Utc>::from)
+}
+
+/// Window of `MAX_ITEM_CHARS` around the first term hit (or the head of the text).
+fn excerpt(text: &str, terms: &[String]) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= MAX_ITEM_CHARS {
+        return text.to_string();
+    }
+    let lower = text.to_lowercase();
+    let first_hit = terms
+        .iter()
+        .filter_map(|t| lower.find(t.as_str()))
+        .min()
+        .map(|byte| lower[..byte].chars().count())
+        .unwrap_or(0);
+    let start = first_hit.saturating_sub(MAX_ITEM_CHARS / 4);
+    let end = (start + MAX_ITEM_CHARS).min(chars.len());
+    chars[start..end].iter().collect()
+}
+
+/// Lines from the last `TAIL_BYTES` of a file (partial first line dropped), with line numbers
+/// when the whole file was read.
+fn tail_lines(path: &Path) -> std::io::Result<(Vec<String>, bool)> {
+    let whole = fs::metadata(path)?.len() <= TAIL_BYTES;
+    let lines = if whole {
+        jsonl::lines(path)?.collect::<std::io::Result<Vec<_>>>()?
+    } else {
+        jsonl::tail(path, usize::MAX, TAIL_BYTES)?
+    };
+    Ok((lines, whole))
+}
+
+fn json_text(v: &Value) -> Option<String> {
+    for key in ["content", "text", "message", "task", "prompt"] {
+        if let Some(s) = v.get(key).and_then(|x| x.as_str()) {
+            if !s.trim().is_empty() {
+                return Some(s.to_string());
+            }
+        }
+    }
+    None
+}
+
+fn json_ts(v: &Value) -> Option<chrono::DateTime<chrono::Utc>> {
+    ["ts", "timestamp", "created_at"]
+        .iter()
+        .find_map(|k| v.get(*k).and_then(|x| x.as_str()).and_then(parse_ts))
+}
+
+/// `users/<user>/threads/<thread>.jsonl` events.
+pub struct ThreadHistorySource;
+
+impl ContextSource for ThreadHistorySource {
+    fn name(&self) -> &'static str {
+        "threads"
+    }
+
+    fn collect(&self, q: &ContextQuery, terms: &[String]) -> Result<Vec<Candidate>, String> {
+        let Some(user) = q.user_id.as_deref().filter(|u| is_safe_segment(u)) else {
+            return Ok(Vec::new());
+        };
+        let dir = meta3_root().join("users").join(user).join("threads");
+        let files: Vec<PathBuf> = match q.thread.as_deref() {
+            Some(t) if is_safe_segment(t) => vec![dir.join(format!("{t}.jsonl"))],
+            Some(_) => return Err("invalid thread id".to_string()),
+            None => fs::read_dir(&dir)
+                .map(|rd| {
+                    rd.flatten()
+                        .map(|e| e.path())
+                        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("jsonl"))
+                        .filter(|p| !jsonl::is_rotated_segment(p))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        let mut out = Vec::new();
+        for path in files {
+            let Ok((lines, whole)) = tail_lines(&path) else {
+                continue;
+            };
+            let thread = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string();
+            for (i, line) in lines.iter().enumerate() {
+                let Ok(v) = serde_json::from_str::<Value>(line) else {
+                    continue;
+                };
+                let content = v.get("content").and_then(|x| x.as_str()).unwrap_or("");
+                if content.is_empty() || !matches_any(content, terms) {
+                    continue;
+                }
+                let role = v.get("role").and_then(|x| x.as_str()).unwrap_or("?");
+                let run_id = v
+                    .get("run_id")
+                    .and_then(|x| x.as_str())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string());
+                out.push(Candidate {
+                    id: format!("{}#{}", thread, i + 1),
+                    content: format!("{}: {}", role, content),
+                    ts: json_ts(&v),
+                    provenance: Provenance {
+                        path: path.display().to_string(),
+                        line: whole.then_some(i + 1),
+                        run_id,
+                    },
+                    file_sha256: None,
+                    ttl: None,
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Run receipts (`runs/receipts/<id>/`) plus the N* receipt log (`NSTAR_RECEIPTS`).
+pub struct ReceiptsSource;
+
+impl ContextSource for ReceiptsSource {
+    fn name(&self) -> &'static str {
+        "receipts"
+    }
+
+    fn collect(&self, q: &ContextQuery, terms: &[String]) -> Result<Vec<Candidate>, String> {
+        let mut out = Vec::new();
+        let readable = |rdir: &Path| {
+            q.reader
+                .as_ref()
+                .is_none_or(|c| receipt_acl::readable_in(rdir, c))
+        };
+
+        let dir = meta3_root().join("runs").join("receipts");
+        let mut dirs: Vec<(Option<chrono::DateTime<chrono::Utc>>, PathBuf)> = fs::read_dir(&dir)
+            .map(|rd| {
+                rd.flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir())
+                    .map(|p| (mtime(&p), p))
+                    .collect()
+            })
+            .unwrap_or_default();
+        dirs.sort_by_key(|d| std::cmp::Reverse(d.0));
+        dirs.truncate(MAX_RECEIPT_DIRS);
+        for (ts, path) in dirs {
+            let run_id = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string();
+            if !readable(&path) {
+                continue;
+            }
+            let md_path = path.join("RECEIPT.md");
+            let body = fs::read_to_string(&md_path)
+                .or_else(|_| fs::read_to_string(path.join("request.json")))
+                .unwrap_or_default();
+            if body.is_empty() || !matches_any(&body, terms) {
+                continue;
+            }
+            out.push(Candidate {
+                id: run_id.clone(),
+                content: body,
+                ts,
+                provenance: Provenance {
+                    path: path.display().to_string(),
+                    line: None,
+                    run_id: Some(run_id),
+                },
+                file_sha256: None,
+                ttl: None,
+            });
+        }
+
+        if q.reader.as_ref().is_some_and(|c| *c != Caller::Admin) {
+            return Ok(out);
+        }
+        let log = PathBuf::from(
+            std::env::var("NSTAR_RECEIPTS").unwrap_or_else(|_| "trace/receipts.jsonl".to_string()),
+        );
+        if let Ok((lines, whole)) = tail_lines(&log) {
+            for (i, line) in lines.iter().enumerate() {
+                let Ok(v) = serde_json::from_str::<Value>(line) else {
+                    continue;
+                };
+                let Some(task) = json_text(&v) else {
+                    continue;
+                };
+                if !matches_any(&task, terms) {
+                    continue;
+                }
+                let run_id = v
+                    .get("run_id")
+                    .and_then(|x| x.as_str())
+                    .map(|s| s.to_string());
+                out.push(Candidate {
+                    id: run_id.clone().unwrap_or_else(|| format!("nstar#{}", i + 1)),
+                    content: task,
+                    ts: json_ts(&v),
+                    provenance: Provenance {
+                        path: log.display().to_string(),
+                        line: whole.then_some(i + 1),
+                        run_id,
+                    },
+                    file_sha256: None,
+                    ttl: None,
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// `research/index.jsonl` artifacts (falls back to building the index in-process).
+pub struct ResearchIndexSource;
+
+impl ContextSource for ResearchIndexSource {
+    fn name(&self) -> &'static str {
+        "research"
+    }
+
+    fn collect(&self, _q: &ContextQuery, terms: &[String]) -> Result<Vec<Candidate>, String> {
+        let artifacts: Vec<one_engine::research::ResearchArtifact> =
+            match fs::read_to_string("research/index.jsonl") {
+                Ok(s) => s
+                    .lines()
+                    .filter_map(|l| serde_json::from_str(l).ok())
+                    .collect(),
+                Err(_) => {
+                    one_engine::research::build_index(Path::new(".")).map_err(|e| e.to_string())?
+                }
+            };
+        let mut out = Vec::new();
+        for a in artifacts {
+            let header = format!("{} ({}) tags: {}", a.path, a.kind, a.tags.join(", "));
+            let raw = fs::metadata(&a.path)
+                .ok()
+                .filter(|m| m.is_file() && m.len() <= MAX_GLOB_FILE_BYTES)
+                .and_then(|_| fs::read(&a.path).ok());
+            let body = raw
+                .as_deref()
+                .map(|b| String::from_utf8_lossy(b).into_owned())
+                .unwrap_or_default();
+            if !matches_any(&header, terms) && !matches_any(&body, terms) {
+                continue;
+            }
+            out.push(Candidate {
+                id: a.id.clone(),
+                content: format!("{}\n{}", header, body),
+                ts: parse_ts(&a.ts),
+                provenance: Provenance {
+                    path: a.path.clone(),
+                    line: None,
+                    run_id: None,
+                },
+                file_sha256: raw.as_deref().map(sha256_hex),
+                ttl: (a.ttl > 0).then_some(a.ttl as i64),
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// Normalized Codex history (`codex.import` events plus configured `utir` sources); only when codex history is enabled.
+pub struct CodexHistorySource;
+
+impl ContextSource for CodexHistorySource {
+    fn name(&self) -> &'static str {
+        "codex"
+    }
+
+    fn collect(&self, _q: &ContextQuery, terms: &[String]) -> Result<Vec<Candidate>, String> {
+        if !codex_history_enabled() {
+            return Ok(Vec::new());
+        }
+        let mut files = vec![crate::integrations::codex::events_path()];
+        for src in crate::integrations::codex::sources_of("utir") {
+            files.extend(src.files().into_iter().map(|f| f.path));
+        }
+        let mut out = Vec::new();
+        for path in files {
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_string();
+            let Ok((lines, whole)) = tail_lines(&path) else {
+                continue;
+            };
+            for (i, line) in lines.iter().enumerate() {
+                if !matches_any(line, terms) {
+                    continue;
+                }
+                let v = serde_json::from_str::<Value>(line).unwrap_or(Value::Null);
+                out.push(Candidate {
+                    id: format!("{}#{}", name, i + 1),
+                    content: json_text(&v).unwrap_or_else(|| line.clone()),
+                    ts: json_ts(&v),
+                    provenance: Provenance {
+                        path: path.display().to_string(),
+                        line: whole.then_some(i + 1),
+                        run_id: None,
+                    },
+                    file_sha256: None,
+                    ttl: None,
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// `*` within a segment, `**` across segments, `?` for one char.
+fn glob_regex(glob: &str) -> Option<regex::Regex> {
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    regex::Regex::new(&re).ok()
+}
+
+/// Files matching `q.globs` under `META3_PATH` (or cwd). Names on the workspace deny list
+/// (`.env*`, keys, `.ssh`, `.git`, ...) are never read.
+pub struct FsGlobSource;
+
+impl ContextSource for FsGlobSource {
+    fn name(&self) -> &'static str {
+        "fs"
+    }
+
+    fn collect(&self, q: &ContextQuery, terms: &[String]) -> Result<Vec<Candidate>, String> {
+        if q.globs.is_empty() {
+            return Ok(Vec::new());
+        }
+        if let Some(bad) = q
+            .globs
+            .iter()
+            .find(|g| g.starts_with('/') || g.contains(".."))
+        {
+            return Err(format!("glob must be relative: {}", bad));
+        }
+        let patterns: Vec<regex::Regex> = q.globs.iter().filter_map(|g| glob_regex(g)).collect();
+        let root = std::env::var("META3_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("."));
+        let mut out = Vec::new();
+        let mut seen = 0;
+        let walker = WalkDir::new(&root).into_iter().filter_entry(|e| {
+            let name = e.file_name().to_str().unwrap_or("");
+            e.depth() == 0
+                || !((e.file_type().is_dir() && SKIP_DIRS.contains(&name))
+                    || workspace_files::is_denied(name, &[]))
+        });
+        for ent in walker.flatten() {
+            if !ent.file_type().is_file() {
+                continue;
+            }
+            let Ok(rel) = ent.path().strip_prefix(&root) else {
+                continue;
+            };
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            if !patterns.iter().any(|re| re.is_match(&rel)) {
+                continue;
+            }
+            seen += 1;
+            if seen > MAX_GLOB_FILES {
+                break;
+            }
+            if ent
+                .metadata()
+                .map(|m| m.len() > MAX_GLOB_FILE_BYTES)
+                .unwrap_or(true)
+            {
+                continue;
+            }
+            let Ok(body) = fs::read_to_string(ent.path()) else {
+                continue;
+            };
+            if !matches_any(&body, terms) && !matches_any(&rel, terms) {
+                continue;
+            }
+            let lower = body.to_lowercase();
+            let line = terms
+                .iter()
+                .filter_map(|t| lower.find(t.as_str()))
+                .min()
+                .map(|byte| lower[..byte].matches('\n').count() + 1);
+            out.push(Candidate {
+                id: rel,
+                file_sha256: Some(sha256_hex(body.as_bytes())),
+                content: body,
+                ts: mtime(ent.path()),
+                provenance: Provenance {
+                    path: ent.path().display().to_string(),
+                    line,
+                    run_id: None,
+                },
+                ttl: None,
+            });
+        }
+        Ok(out)
+    }
+}
+
+pub fn default_sources() -> Vec<Box<dyn ContextSource>> {
+    vec![
+        Box::new(ThreadHistorySource),
+        Box::new(ReceiptsSource),
+        Box::new(ResearchIndexSource),
+        Box::new(CodexHistorySource),
+        Box::new(FsGlobSource),
+    ]
+}
+
+/// Score every candidate from the selected sources and pack the best into the budget.
+pub fn resolve_with(q: &ContextQuery, sources: &[Box<dyn ContextSource>]) -> ContextBundle {
+    let terms = query_terms(&q.query);
+    let budget = if q.budget_tokens == 0 {
+        DEFAULT_BUDGET_TOKENS
+    } else {
+        q.budget_tokens
+    };
+    let resolved_at = chrono::Utc::now().to_rfc3339();
+    let mut stats = Vec::new();
+    let mut scored: Vec<(usize, ContextItem)> = Vec::new();
+
+    for src in sources {
+        if !q.sources.is_empty() && !q.sources.iter().any(|s| s == src.name()) {
+            continue;
+        }
+        let (cands, error) = match src.collect(q, &terms) {
+            Ok(c) => (c, None),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        stats.push(SourceStat {
+            name: src.name().to_string(),
+            candidates: cands.len(),
+            included: 0,
+            error,
+        });
+        let stat_idx = stats.len() - 1;
+        for c in cands {
+            let rel = relevance(&c.content, &q.query, &terms);
+            if rel <= 0.0 {
+                continue;
+            }
+            let rec = recency(c.ts);
+            let content = excerpt(&c.content, &terms);
+            let (sha256, sha_scope) = match c.file_sha256 {
+                Some(sha) => (sha, "file"),
+                None => (sha256_hex(c.content.as_bytes()), "record"),
+            };
+            let ttl_class = if c.ttl.is_some() {
+                "artifact"
+            } else {
+                ttl_class_for(src.name())
+            };
+            scored.push((
+                stat_idx,
+                ContextItem {
+                    source: src.name().to_string(),
+                    id: c.id,
+                    tokens: estimate_tokens(&content),
+                    content,
+                    ts: c.ts.map(|t| t.to_rfc3339()),
+                    relevance: rel,
+                    recency: rec,
+                    score: rel * q.weights.relevance + rec * q.weights.recency,
+                    mtime: mtime_secs(Path::new(&c.provenance.path)),
+                    provenance: c.provenance,
+                    sha256,
+                    sha_scope: sha_scope.to_string(),
+                    ttl_class: ttl_class.to_string(),
+                    ttl: c.ttl.unwrap_or_else(|| ttl_secs(ttl_class)),
+                    checked_at: resolved_at.clone(),
+                },
+            ));
+        }
+    }
+
+    scored.sort_by(|a, b| {
+        b.1.score
+            .partial_cmp(&a.1.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut used = 0;
+    let mut dropped = 0;
+    let mut items = Vec::new();
+    for (stat_idx, item) in scored {
+        if used + item.tokens > budget {
+            dropped += 1;
+            continue;
+        }
+        used += item.tokens;
+        stats[stat_idx].included += 1;
+        items.push(item);
+    }
+
+    ContextBundle {
+        query: q.query.clone(),
+        budget_tokens: budget,
+        used_tokens: used,
+        resolved_at,
+        items,
+        dropped,
+        sources: stats,
+    }
+}
+
+/// Resolve against the default sources (file scans run on the blocking pool).
+pub async fn resolve(q: ContextQuery) -> ContextBundle {
+    let query = q.query.clone();
+    let budget = q.budget_tokens;
+    tokio::task::spawn_blocking(move || resolve_with(&q, &default_sources()))
+        .await
+        .unwrap_or_else(|e| ContextBundle {
+            query,
+            budget_tokens: budget,
+            used_tokens: 0,
+            resolved_at: chrono::Utc::now().to_rfc3339(),
+            items: Vec::new(),
+            dropped: 0,
+            sources: vec![SourceStat {
+                name: "resolver".to_string(),
+                candidates: 0,
+                included: 0,
+                error: Some(e.to_string()),
+            }],
+        })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ItemFreshness {
+    pub index: usize,
+    pub id: String,
+    pub source: Option<String>,
+    pub ttl_class: Option<String>,
+    pub ttl: Option<i64>,
+    pub age_s: Option<i64>,
+    /// "expired" | "sha256_mismatch" | "mtime_mismatch" | "missing"
+    pub reasons: Vec<String>,
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct FreshnessReport {
+    pub checked_at: String,
+    pub total: usize,
+    pub stale: usize,
+    pub items: Vec<ItemFreshness>,
+}
+
+/// Re-check context items (bundle items or caller-supplied `{ts, ttl}`) for staleness.
+/// Age counts from `checked_at` when present, else `ts`; whole-file items are re-hashed,
+/// other items with an `mtime` stamp are compared by mtime.
+pub fn assess(items: &[Value]) -> FreshnessReport {
+    let now = chrono::Utc::now();
+    let mut out = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        let str_field = |k: &str| item.get(k).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let ttl = item.get("ttl").and_then(|v| v.as_i64());
+        let age_s = str_field("checked_at")
+            .or_else(|| str_field("ts"))
+            .and_then(|t| parse_ts(&t))
+            .map(|t| (now - t).num_seconds());
+        let mut reasons = Vec::new();
+        if let (Some(ttl), Some(age)) = (ttl, age_s) {
+            if age > ttl {
+                reasons.push("expired".to_string());
+            }
+        }
+        let path = item
+            .get("provenance")
+            .and_then(|p| p.get("path"))
+            .and_then(|v| v.as_str());
+        match (path, str_field("sha_scope").as_deref()) {
+            (Some(path), Some("file")) => match file_sha256(Path::new(path)) {
+                Ok(now) => {
+                    if str_field("sha256").is_some_and(|sha| now.as_deref() != Some(sha.as_str())) {
+                        reasons.push("sha256_mismatch".to_string());
+                    }
+                }
+                Err(_) => reasons.push("missing".to_string()),
+            },
+            // Append-only logs and receipt dirs change mtime without invalidating old records.
+            (Some(_), Some("record")) => {}
+            (Some(path), _) => {
+                if let Some(expected) = item.get("mtime").and_then(|v| v.as_i64()) {
+                    if mtime_secs(Path::new(path)).is_some_and(|m| m != expected) {
+                        reasons.push("mtime_mismatch".to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+        out.push(ItemFreshness {
+            index,
+            id: str_field("id").unwrap_or_else(|| format!("#{}", index)),
+            source: str_field("source"),
+            ttl_class: str_field("ttl_class"),
+            ttl,
+            age_s,
+            stale: !reasons.is_empty(),
+            reasons,
+        });
+    }
+    FreshnessReport {
+        checked_at: now.to_rfc3339(),
+        total: out.len(),
+        stale: out.iter().filter(|i| i.stale).count(),
+        items: out,
+    }
+}
+
+pub fn staleness_dir() -> PathBuf {
+    meta3_root().join("runs").join("staleness")
+}
+
+/// Persist a report under `runs/staleness/<run_id>.json` and, for thread runs, append it to
+/// `runs/staleness/threads/<thread>.jsonl` so `GET /context/staleness?thread=` can explain Δ.
+pub fn record_staleness(
+    run_id: &str,
+    goal_id: &str,
+    thread: Option<&str>,
+    report: &FreshnessReport,
+) {
+    let dir = staleness_dir();
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let rec = serde_json::json!({
+        "run_id": run_id,
+        "goal_id": goal_id,
+        "thread": thread,
+        "report": report
+    });
+    if is_safe_segment(run_id) {
+        let _ = atomic::write(
+            dir.join(format!("{}.json", run_id)),
+            serde_json::to_string_pretty(&rec).unwrap_or_default(),
+        );
+    }
+    if let Some(t) = thread.filter(|t| is_safe_segment(t)) {
+        let tdir = dir.join("threads");
+        let _ = fs::create_dir_all(&tdir);
+        let _ = atomic::append(tdir.join(format!("{}.jsonl", t)), &rec.to_string());
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema, ToSchema)]
+pub struct StalenessQuery {
+    pub thread: Option<String>,
+    pub run_id: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, JsonSchema, ToSchema)]
+pub struct StalenessResp {
+    pub thread: Option<String>,
+    /// `{run_id, goal_id, thread, report}` records, newest first.
+    pub records: Vec<Value>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/context/staleness",
+    params(
+        ("thread" = Option<String>, Query, description = "Thread whose stale-context runs to list"),
+        ("run_id" = Option<String>, Query, description = "A single run's staleness report"),
+        ("limit" = Option<usize>, Query, description = "Max records (default 20)")
+    ),
+    responses((status = 200, description = "Why Δ was set: per-item staleness reports", body = StalenessResp))
+)]
+pub async fn staleness_handler(Query(q): Query<StalenessQuery>) -> impl IntoResponse {
+    let dir = staleness_dir();
+    if let Some(run_id) = q.run_id.as_deref() {
+        if !is_safe_segment(run_id) {
+            return (StatusCode::BAD_REQUEST, "invalid run_id".to_string()).into_response();
+        }
+        return match tokio::fs::read_to_string(dir.join(format!("{}.json", run_id))).await {
+            Ok(raw) => Json(StalenessResp {
+                thread: None,
+                records: serde_json::from_str::<Value>(&raw).into_iter().collect(),
+            })
+            .into_response(),
+            Err(_) => (
+                StatusCode::NOT_FOUND,
+                "no staleness report for run".to_string(),
+            )
+                .into_response(),
+        };
+    }
+    let Some(thread) = q.thread.clone() else {
+        return (
+            StatusCode::BAD_REQUEST,
+            "thread or run_id is required".to_string(),
+        )
+            .into_response();
+    };
+    if !is_safe_segment(&thread) {
+        return (StatusCode::BAD_REQUEST, "invalid thread".to_string()).into_response();
+    }
+    let limit = q.limit.unwrap_or(20).clamp(1, 200);
+    let raw = tokio::fs::read_to_string(dir.join("threads").join(format!("{}.jsonl", thread)))
+        .await
+        .unwrap_or_default();
+    let records: Vec<Value> = raw
+        .lines()
+        .rev()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .take(limit)
+        .collect();
+    Json(StalenessResp {
+        thread: Some(thread),
+        records,
+    })
+    .into_response()
+}
@@ -0,0 +1,135 @@
+//! CORS / preflight configuration (tower-http).
+//!
+//! Disabled unless origins are configured. Global settings:
+//! - `ONE_ENGINE_CORS_ORIGINS`      `*` or comma list (`https://a.example,https://b.example`)
+//! - `ONE_ENGINE_CORS_HEADERS`      allowed request headers (default includes `x-api-key`)
+//! - `ONE_ENGINE_CORS_METHODS`      allowed methods (default `GET,POST,PUT,OPTIONS`)
+//! - `ONE_ENGINE_CORS_MAX_AGE`      preflight cache seconds (default 600)
+//! - `ONE_ENGINE_CORS_CREDENTIALS`  `1` to allow credentials (explicit origins only)
+//!
+//! Per-route overrides use the same keys with a scope infix, e.g.
+//! `ONE_ENGINE_CORS_SSE_ORIGINS` or `ONE_ENGINE_CORS_USERS_HEADERS`; unset keys fall back
+//! to the global value.
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+const DEFAULT_HEADERS: &str =
+    "content-type,accept,authorization,x-api-key,x-run-id,x-thread,x-user-id,last-event-id,cache-control";
+const DEFAULT_METHODS: &str = "GET,POST,PUT,OPTIONS";
+const DEFAULT_MAX_AGE_SECS: u64 = 600;
+
+#[derive(Clone, Copy, Debug)]
+pub enum CorsScope {
+    /// Everything not covered by a narrower scope.
+    Default,
+    /// `GET /progress.sse` (EventSource from SPA dashboards).
+    Sse,
+    /// `/users/:user_id/...` endpoints (need `x-api-key`).
+    Users,
+}
+
+impl CorsScope {
+    fn env_infix(self) -> Option<&'static str> {
+        match self {
+            CorsScope::Default => None,
+            CorsScope::Sse => Some("SSE"),
+            CorsScope::Users => Some("USERS"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CorsConfig {
+    pub origins: Vec<String>,
+    pub headers: Vec<String>,
+    pub methods: Vec<String>,
+    pub max_age_secs: u64,
+    pub allow_credentials: bool,
+}
+
+fn scoped_env(scope: CorsScope, key: &str) -> Option<String> {
+    let scoped = scope
+        .env_infix()
+        .and_then(|infix| std::env::var(format!("ONE_ENGINE_CORS_{}_{}", infix, key)).ok());
+    scoped
+        .or_else(|| std::env::var(format!("ONE_ENGINE_CORS_{}", key)).ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn split_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+impl CorsConfig {
+    /// Load the config for a scope; `None` when no origins are configured (CORS off).
+    pub fn from_env(scope: CorsScope) -> Option<Self> {
+        let origins = split_list(&scoped_env(scope, "ORIGINS")?);
+        if origins.is_empty() {
+            return None;
+        }
+        let headers = split_list(
+            &scoped_env(scope, "HEADERS").unwrap_or_else(|| DEFAULT_HEADERS.to_string()),
+        );
+        let methods = split_list(
+            &scoped_env(scope, "METHODS").unwrap_or_else(|| DEFAULT_METHODS.to_string()),
+        );
+        let max_age_secs = scoped_env(scope, "MAX_AGE")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MAX_AGE_SECS);
+        let allow_credentials = scoped_env(scope, "CREDENTIALS").as_deref() == Some("1");
+        Some(Self {
+            origins,
+            headers,
+            methods,
+            max_age_secs,
+            allow_credentials,
+        })
+    }
+
+    pub fn layer(&self) -> CorsLayer {
+        let wildcard = self.origins.iter().any(|o| o == "*");
+        let allow_origin = if wildcard {
+            AllowOrigin::any()
+        } else {
+            AllowOrigin::list(
+                self.origins
+                    .iter()
+                    .filter_map(|o| HeaderValue::from_str(o.trim_end_matches('/')).ok()),
+            )
+        };
+        let headers: Vec<HeaderName> = self
+            .headers
+            .iter()
+            .filter_map(|h| HeaderName::from_bytes(h.to_ascii_lowercase().as_bytes()).ok())
+            .collect();
+        let methods: Vec<Method> = self
+            .methods
+            .iter()
+            .filter_map(|m| Method::from_bytes(m.to_ascii_uppercase().as_bytes()).ok())
+            .collect();
+
+        let mut layer = CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_headers(headers)
+            .allow_methods(methods)
+            .expose_headers([HeaderName::from_static("x-run-id")])
+            .max_age(Duration::from_secs(self.max_age_secs));
+        // tower-http rejects credentials with wildcard origins; only honor it for explicit lists.
+        if self.allow_credentials && !wildcard {
+            layer = layer.allow_credentials(true);
+        }
+        layer
+    }
+}
+
+/// Convenience: the configured layer for `scope`, if CORS is enabled for it.
+pub fn layer_for(scope: CorsScope) -> Option<CorsLayer> {
+    CorsConfig::from_env(scope).map(|c| c.layer())
+}
@@ -0,0 +1,265 @@
+//! Structured parsing of build output (cargo / turbo / npm+jest) for `meta3.build`.
+//!
+//! The parser is line-based and tolerant: unknown lines are ignored, so mixed output
+//! (turbo running cargo, npm scripts inside turbo) still yields useful counts.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PackageStatus {
+    pub name: String,
+    /// "ok" | "failed" | "cached"
+    pub status: String,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildReport {
+    /// "cargo" | "turbo" | "npm" | "mixed" | "unknown"
+    pub tool: String,
+    pub exit_ok: bool,
+    pub success: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    pub packages: Vec<PackageStatus>,
+    pub failing_tests: Vec<String>,
+    pub tests_passed: usize,
+    pub tests_failed: usize,
+    pub duration_ms: Option<u64>,
+    /// First few error lines, for receipts / summaries.
+    pub error_samples: Vec<String>,
+}
+
+/// Parse `12.3s`, `1m 2.5s`, `450ms`, `2.01 secs` into milliseconds.
+pub fn parse_duration_ms(raw: &str) -> Option<u64> {
+    let s = raw.trim().trim_end_matches('.').to_ascii_lowercase();
+    let mut total = 0f64;
+    let mut matched = false;
+    for part in s.split_whitespace() {
+        let (num, unit): (String, String) = {
+            let idx = part
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(part.len());
+            (part[..idx].to_string(), part[idx..].to_string())
+        };
+        let Ok(n) = num.parse::<f64>() else {
+            continue;
+        };
+        let factor = match unit.as_str() {
+            "ms" => 1.0,
+            "s" | "sec" | "secs" | "seconds" => 1000.0,
+            "m" | "min" | "mins" => 60_000.0,
+            "h" => 3_600_000.0,
+            _ => continue,
+        };
+        total += n * factor;
+        matched = true;
+    }
+    if matched {
+        Some(total.round() as u64)
+    } else {
+        None
+    }
+}
+
+fn push_error(r: &mut BuildReport, line: &str) {
+    r.errors += 1;
+    if r.error_samples.len() < 10 {
+        r.error_samples
+            .push(line.trim().chars().take(300).collect());
+    }
+}
+
+/// `@scope/pkg:build` style turbo task prefix (not `src/x.rs:10`).
+fn is_task_prefix(pre: &str) -> bool {
+    let Some((pkg, task)) = pre.split_once(':') else {
+        return false;
+    };
+    !pkg.is_empty()
+        && !task.is_empty()
+        && !pre.contains(' ')
+        && !task.contains(':')
+        && task
+            .chars()
+            .all(|c| c.is_ascii_alphabetic() || c == '-' || c == '_')
+}
+
+fn after<'a>(line: &'a str, needle: &str) -> Option<&'a str> {
+    line.find(needle).map(|i| &line[i + needle.len()..])
+}
+
+/// Numbers preceding a keyword, e.g. `count_before("5 passed; 1 failed", "failed") == Some(1)`.
+fn count_before(s: &str, keyword: &str) -> Option<usize> {
+    let idx = s.find(keyword)?;
+    s[..idx]
+        .trim_end()
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .next()
+        .and_then(|n| n.parse().ok())
+}
+
+pub fn parse(output: &str, exit_ok: bool) -> BuildReport {
+    let mut r = BuildReport {
+        exit_ok,
+        ..Default::default()
+    };
+    let mut packages: BTreeMap<String, PackageStatus> = BTreeMap::new();
+    let (mut saw_cargo, mut saw_turbo, mut saw_npm) = (false, false, false);
+
+    for raw_line in output.lines() {
+        let line = raw_line.trim_end();
+        let t = line.trim_start();
+
+        // turbo prefixes task output with `<pkg>:<task>: `
+        let (task_prefix, body) = match t.split_once(": ") {
+            Some((pre, rest)) if is_task_prefix(pre) => (Some(pre.to_string()), rest.trim_start()),
+            _ => (None, t),
+        };
+        if let Some(pre) = task_prefix.as_deref() {
+            saw_turbo = true;
+            let name = pre.to_string();
+            let entry = packages.entry(name.clone()).or_insert(PackageStatus {
+                name,
+                status: "ok".to_string(),
+                duration_ms: None,
+            });
+            if body.contains("cache hit") {
+                entry.status = "cached".to_string();
+            }
+            if body.contains("ERROR") || body.contains("command finished with error") {
+                entry.status = "failed".to_string();
+            }
+        }
+
+        // ---- cargo ----
+        if let Some(rest) = body.strip_prefix("Compiling ") {
+            saw_cargo = true;
+            let name = rest.split_whitespace().next().unwrap_or("").to_string();
+            if !name.is_empty() {
+                packages.entry(name.clone()).or_insert(PackageStatus {
+                    name,
+                    status: "ok".to_string(),
+                    duration_ms: None,
+                });
+            }
+            continue;
+        }
+        if let Some(rest) = body.strip_prefix("error: could not compile `") {
+            saw_cargo = true;
+            let name = rest.split('`').next().unwrap_or("").to_string();
+            packages
+                .entry(name.clone())
+                .or_insert(PackageStatus {
+                    name,
+                    status: "failed".to_string(),
+                    duration_ms: None,
+                })
+                .status = "failed".to_string();
+            continue;
+        }
+        if body.starts_with("Finished ") {
+            saw_cargo = true;
+            if let Some(d) = after(body, " in ") {
+                r.duration_ms = parse_duration_ms(d).or(r.duration_ms);
+            }
+            continue;
+        }
+        if body.starts_with("error: aborting due to") {
+            continue;
+        }
+        if body.starts_with("error[") || body.starts_with("error:") {
+            push_error(&mut r, body);
+            continue;
+        }
+        if body.starts_with("warning:") {
+            // Skip cargo's per-crate summary ("warning: `x` (lib) generated 3 warnings").
+            if !body.contains(" generated ") {
+                r.warnings += 1;
+            }
+            continue;
+        }
+        if body.starts_with("test ") && body.ends_with("... FAILED") {
+            let name = body
+                .trim_start_matches("test ")
+                .trim_end_matches("... FAILED")
+                .trim()
+                .to_string();
+            if !r.failing_tests.contains(&name) {
+                r.failing_tests.push(name);
+            }
+            continue;
+        }
+        if let Some(rest) = body.strip_prefix("test result: ") {
+            r.tests_passed += count_before(rest, " passed").unwrap_or(0);
+            r.tests_failed += count_before(rest, " failed").unwrap_or(0);
+            continue;
+        }
+
+        // ---- turbo summary ----
+        if body.starts_with("Tasks:") {
+            saw_turbo = true;
+            continue;
+        }
+        if let Some(rest) = body.strip_prefix("Failed:") {
+            saw_turbo = true;
+            for task in rest.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                let name = task.replace('#', ":");
+                packages
+                    .entry(name.clone())
+                    .or_insert(PackageStatus {
+                        name,
+                        status: "failed".to_string(),
+                        duration_ms: None,
+                    })
+                    .status = "failed".to_string();
+            }
+            continue;
+        }
+        if let Some(rest) = body.strip_prefix("Time:") {
+            r.duration_ms =
+                parse_duration_ms(rest.split('>').next().unwrap_or(rest)).or(r.duration_ms);
+            continue;
+        }
+
+        // ---- npm / jest / tsc ----
+        if body.starts_with("npm ERR!") || body.starts_with("npm error") {
+            saw_npm = true;
+            push_error(&mut r, body);
+            continue;
+        }
+        if body.contains("ERR_PNPM") || (body.contains("error TS") && body.contains(':')) {
+            push_error(&mut r, body);
+            continue;
+        }
+        if let Some(rest) = body.strip_prefix("FAIL ") {
+            saw_npm = true;
+            let name = rest.split_whitespace().next().unwrap_or("").to_string();
+            if !name.is_empty() && !r.failing_tests.contains(&name) {
+                r.failing_tests.push(name);
+            }
+            continue;
+        }
+        if let Some(rest) = body.strip_prefix("Tests:") {
+            saw_npm = true;
+            r.tests_passed += count_before(rest, " passed").unwrap_or(0);
+            r.tests_failed += count_before(rest, " failed").unwrap_or(0);
+            continue;
+        }
+    }
+
+    r.tool = match (saw_cargo, saw_turbo, saw_npm) {
+        (true, false, false) => "cargo",
+        (false, true, false) => "turbo",
+        (false, false, true) => "npm",
+        (false, false, false) => "unknown",
+        _ => "mixed",
+    }
+    .to_string();
+    r.packages = packages.into_values().collect();
+    r.tests_failed = r.tests_failed.max(r.failing_tests.len());
+
+    let failed_packages = r.packages.iter().filter(|p| p.status == "failed").count();
+    r.success = exit_ok && r.errors == 0 && failed_packages == 0 && r.tests_failed == 0;
+    r
+}
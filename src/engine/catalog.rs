@@ -0,0 +1,250 @@
+//! Goal catalog: the built-in goal ids and the alias table for renamed ones.
+//!
+//! Goal handlers still match ids by substring, so renames go through [`resolve`] first: an id
+//! whose bare goal (after any `user:<id>.` prefix) is an alias runs as its target, and a
+//! deprecated alias adds a `deprecation` notice to the evidence and a warning to the log.
+//! Built-in aliases cover the old singular graph and report ids; the policies file adds more:
+//!
+//! ```yaml
+//! goal_aliases:
+//!   - from: wiki.build
+//!     to: wiki.generate
+//!     deprecated: true
+//!     message: "wiki.build was renamed; it will stop working in 0.9"
+//! ```
+//!
+//! File entries replace a built-in alias with the same `from`. `GET /goals` lists both tables,
+//! with the goals declared in `config/goals.d` (see [`declared`]) after the built-ins.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+
+use super::goals::declared;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalEntry {
+    pub id: String,
+    pub description: String,
+    /// The `config/goals.d` file of a declared goal; absent for built-ins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalAlias {
+    pub from: String,
+    pub to: String,
+    /// Still runs, but with a notice in evidence and logs.
+    #[serde(default)]
+    pub deprecated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+const BUILTIN_GOALS: &[(&str, &str)] = &[
+    (
+        "align.sota",
+        "Alignment notes against the current research index",
+    ),
+    ("research.index", "Rebuild research/index.jsonl"),
+    ("research.fetch", "Fetch and archive a research source"),
+    ("research.read", "Read and summarize an archived source"),
+    (
+        "research.manifest",
+        "Pin sha256/mtime/bytes of files for research.read",
+    ),
+    (
+        "staleness.check",
+        "Re-run feature probes and report stale ones",
+    ),
+    ("reports.daily", "Daily report over recent receipts"),
+    ("experiment.sweep", "Parameter sweep over a goal"),
+    ("codex.import", "Import Codex sessions"),
+    ("wiki.generate", "Static wiki from receipts and research"),
+    ("graphs.thread", "Graph of a chat thread and its runs"),
+    ("graphs.receipts", "Graph of recent receipts"),
+    ("graphs.api", "Graph of API calls and mutations"),
+    ("graphs.system", "Graph of the engine's components"),
+    (
+        "meta3.build",
+        "Build the meta3 monorepo (locally or on a remote host)",
+    ),
+    ("cargo.build", "cargo build with structured diagnostics"),
+    ("cargo.test", "cargo test with structured results"),
+    ("cargo.clippy", "cargo clippy with structured diagnostics"),
+    ("ruliad.hypergraph", "Hypergraph rewriting run"),
+    ("ruliad", "Ruliad exploration run"),
+    ("threads.report", "Report over a user's threads"),
+    ("shell.exec", "Run a shell command"),
+    ("file.write", "Write a file inside the write scope"),
+    (
+        "file.patch",
+        "Apply edits or a unified diff inside the write scope",
+    ),
+    ("meta.omni", "Chat turn with tool calls"),
+];
+
+const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("graph.thread", "graphs.thread"),
+    ("graph.receipts", "graphs.receipts"),
+    ("graph.api", "graphs.api"),
+    ("graph.system", "graphs.system"),
+    ("thread.report", "threads.report"),
+];
+
+/// Built-in goals, then those declared in `config/goals.d`, then WASM plugin goals.
+pub fn goals() -> Vec<GoalEntry> {
+    let builtin = BUILTIN_GOALS.iter().map(|(id, description)| GoalEntry {
+        id: id.to_string(),
+        description: description.to_string(),
+        source: None,
+    });
+    let declared = declared::specs().iter().map(|s| GoalEntry {
+        id: s.id.clone(),
+        description: s.description.clone(),
+        source: Some(s.source.display().to_string()),
+    });
+    #[allow(unused_mut)]
+    let mut all: Vec<GoalEntry> = builtin.chain(declared).collect();
+    #[cfg(feature = "wasm")]
+    for m in super::plugins::manifests() {
+        all.extend(m.goals.iter().map(|id| GoalEntry {
+            id: id.clone(),
+            description: m.description.clone(),
+            source: Some(m.wasm.display().to_string()),
+        }));
+    }
+    all
+}
+
+pub fn is_builtin(goal_id: &str) -> bool {
+    BUILTIN_GOALS.iter().any(|(id, _)| *id == goal_id)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesGoalAliases {
+    #[serde(default)]
+    goal_aliases: Vec<GoalAlias>,
+}
+
+/// Built-in aliases, then the policies file's (which win on the same `from`).
+pub fn aliases() -> Vec<GoalAlias> {
+    let mut all: Vec<GoalAlias> = BUILTIN_ALIASES
+        .iter()
+        .map(|(from, to)| GoalAlias {
+            from: from.to_string(),
+            to: to.to_string(),
+            deprecated: true,
+            message: None,
+        })
+        .collect();
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let file = match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesGoalAliases>(&raw) {
+            Ok(p) => p.goal_aliases,
+            Err(e) => {
+                tracing::warn!("invalid goal_aliases in {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    };
+    for a in file {
+        all.retain(|b| b.from != a.from);
+        all.push(a);
+    }
+    all
+}
+
+/// A goal id after alias resolution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub goal_id: String,
+    /// The last alias applied, when any was.
+    pub alias: Option<GoalAlias>,
+}
+
+impl Resolved {
+    /// Evidence notice for a deprecated alias.
+    pub fn deprecation(&self, requested: &str) -> Option<Value> {
+        let a = self.alias.as_ref().filter(|a| a.deprecated)?;
+        let message = a
+            .message
+            .clone()
+            .unwrap_or_else(|| format!("goal {} is deprecated; use {}", a.from, a.to));
+        Some(json!({
+            "requested": requested,
+            "goal_id": self.goal_id,
+            "message": message,
+        }))
+    }
+}
+
+/// `goal_id` with aliases followed (chains too, stopping at a cycle).
+pub fn resolve(goal_id: &str) -> Resolved {
+    resolve_with(goal_id, &aliases())
+}
+
+fn resolve_with(goal_id: &str, table: &[GoalAlias]) -> Resolved {
+    let (prefix, mut bare) = match goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.'))
+    {
+        Some((user, g)) => (format!("user:{}.", user), g.to_string()),
+        None => (String::new(), goal_id.to_string()),
+    };
+    let mut alias = None;
+    for _ in 0..table.len() {
+        let Some(a) = table.iter().find(|a| a.from == bare) else {
+            break;
+        };
+        bare = a.to.clone();
+        alias = Some(a.clone());
+    }
+    Resolved {
+        goal_id: format!("{}{}", prefix, bare),
+        alias,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alias(from: &str, to: &str, deprecated: bool) -> GoalAlias {
+        GoalAlias {
+            from: from.into(),
+            to: to.into(),
+            deprecated,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn aliases_resolve_under_user_prefixes_and_through_chains() {
+        let table = vec![
+            alias("graph.thread", "graphs.thread", true),
+            alias("a.old", "a.mid", false),
+            alias("a.mid", "a.new", false),
+            alias("loop.x", "loop.y", false),
+            alias("loop.y", "loop.x", false),
+        ];
+        let r = resolve_with("user:demo.graph.thread", &table);
+        assert_eq!(r.goal_id, "user:demo.graphs.thread");
+        assert_eq!(
+            r.deprecation("user:demo.graph.thread").unwrap()["message"],
+            "goal graph.thread is deprecated; use graphs.thread"
+        );
+
+        let r = resolve_with("a.old", &table);
+        assert_eq!(r.goal_id, "a.new");
+        assert!(r.deprecation("a.old").is_none());
+
+        assert_eq!(resolve_with("graphs.thread", &table).alias, None);
+        // A cycle stops after one pass over the table instead of spinning.
+        assert!(resolve_with("loop.x", &table).goal_id.starts_with("loop."));
+    }
+}
@@ -0,0 +1,259 @@
+//! Container backend for `Action::Cli`: with `policy.executor: container` (set per request or
+//! per goal through `goal_policies:`), each command runs in an ephemeral Docker or Podman
+//! container instead of the host shell. Output, exit status and the time limit go through the
+//! same path as host commands ([`executor::run_command`]).
+//!
+//! Image, mounts and network are configured per goal family (`meta3` for `meta3.build` and
+//! `user:<id>.meta3.build`), falling back to the `default` entry:
+//!
+//! ```yaml
+//! containers:
+//!   runtime: podman          # default: docker, else podman, whichever is on PATH
+//!   fallback_to_host: false  # no runtime installed: run on the host instead of failing
+//!   families:
+//!     default:
+//!       image: debian:stable-slim
+//!       network: none        # none | bridge | host
+//!     meta3:
+//!       image: rust:1
+//!       network: bridge
+//!       mounts: ["/srv/meta3", "/srv/cache:/cache:ro"]
+//!       workdir: /srv/meta3
+//! ```
+//!
+//! A mount is `host[:container][:ro]`; the container path defaults to the host path, so
+//! commands that name host paths work unchanged. Nothing else from the host is visible, and
+//! the only variable passed in is the correlation id. Commands run under the image's `sh -c`,
+//! so goals spell them for `sh` ([`platform::shell`]). `ONE_ENGINE_CONTAINER_RUNTIME`
+//! overrides `runtime`.
+
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::future::Future;
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::executor::{self, ExecResult};
+use super::platform;
+use super::types::{ExecutorBackend, Policy, ResourceLimits};
+use crate::integrations::{calibration, telemetry};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct FamilyConfig {
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    mounts: Vec<String>,
+    #[serde(default)]
+    workdir: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ContainersSection {
+    #[serde(default)]
+    runtime: Option<String>,
+    #[serde(default)]
+    fallback_to_host: bool,
+    #[serde(default)]
+    families: HashMap<String, FamilyConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesContainers {
+    #[serde(default)]
+    containers: Option<ContainersSection>,
+}
+
+const DEFAULT_IMAGE: &str = "debian:stable-slim";
+
+static SECTION: Lazy<ContainersSection> = Lazy::new(|| {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesContainers>(&raw).ok())
+        .and_then(|p| p.containers)
+        .unwrap_or_default()
+});
+
+/// `docker` or `podman`, whichever is configured or installed.
+static RUNTIME: Lazy<Option<String>> = Lazy::new(|| {
+    if let Some(r) = std::env::var("ONE_ENGINE_CONTAINER_RUNTIME")
+        .ok()
+        .or_else(|| SECTION.runtime.clone())
+        .filter(|r| !r.trim().is_empty())
+    {
+        return Some(r.trim().to_string());
+    }
+    ["docker", "podman"]
+        .into_iter()
+        .find(|p| platform::on_path(p))
+        .map(|p| p.to_string())
+});
+
+/// The container runtime commands would use, if any.
+pub fn runtime() -> Option<&'static str> {
+    RUNTIME.as_deref()
+}
+
+tokio::task_local! {
+    static TARGET: Target;
+}
+
+#[derive(Debug, Clone)]
+struct Target {
+    goal_id: String,
+    backend: ExecutorBackend,
+}
+
+/// Run `fut` (a goal run) with `goal_id` and `policy.executor` as the current executor target.
+pub async fn scope<F: Future>(goal_id: &str, policy: &Policy, fut: F) -> F::Output {
+    let target = Target {
+        goal_id: goal_id.to_string(),
+        backend: policy.executor,
+    };
+    TARGET.scope(target, fut).await
+}
+
+/// Whether commands of the current run go to a container (so goals should spell them for `sh`).
+pub fn active() -> bool {
+    let selected = TARGET
+        .try_with(|t| t.backend == ExecutorBackend::Container)
+        .unwrap_or(false);
+    selected && (RUNTIME.is_some() || !SECTION.fallback_to_host)
+}
+
+fn family_config(goal_id: Option<&str>) -> FamilyConfig {
+    goal_id
+        .and_then(|g| SECTION.families.get(&calibration::family(g)))
+        .or_else(|| SECTION.families.get("default"))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// `host[:container][:ro]` as a `-v` argument, the container path defaulting to the host one.
+fn volume(spec: &str) -> String {
+    let (rest, ro) = match spec.strip_suffix(":ro") {
+        Some(rest) => (rest, true),
+        None => (spec.strip_suffix(":rw").unwrap_or(spec), false),
+    };
+    // Split on the last `:/` so Windows host paths (`C:\repo`) keep their drive colon.
+    let (host, ctr) = match rest.rfind(":/") {
+        Some(i) if i > 0 => (&rest[..i], &rest[i + 1..]),
+        _ => (rest, rest),
+    };
+    format!("{}:{}{}", host, ctr, if ro { ":ro" } else { "" })
+}
+
+/// `runtime run` arguments for one command under `cfg`.
+fn run_args(cfg: &FamilyConfig, limits: &ResourceLimits, name: &str, cmd: &str) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        "run".into(),
+        "--rm".into(),
+        "-i".into(),
+        "--name".into(),
+        name.into(),
+        "--network".into(),
+        cfg.network.clone().unwrap_or_else(|| "none".into()),
+    ];
+    for m in &cfg.mounts {
+        args.push("-v".into());
+        args.push(volume(m));
+    }
+    // The runtime's cgroup enforces memory and CPU; the executor can't see inside.
+    if let Some(mb) = limits.max_rss_mb {
+        args.push("--memory".into());
+        args.push(format!("{}m", mb));
+    }
+    if let Some(s) = limits.cpu_s {
+        args.push("--ulimit".into());
+        args.push(format!("cpu={}:{}", s, s));
+    }
+    if let Some(dir) = &cfg.workdir {
+        args.push("-w".into());
+        args.push(dir.clone());
+    }
+    if let Some(id) = crate::engine::correlation::current() {
+        args.push("-e".into());
+        args.push(format!("{}={}", crate::engine::correlation::ENV, id));
+    }
+    args.push(
+        cfg.image
+            .clone()
+            .unwrap_or_else(|| DEFAULT_IMAGE.to_string()),
+    );
+    args.extend(["sh".into(), "-c".into(), cmd.into()]);
+    args
+}
+
+/// Run `cmd` in a fresh container for the current goal's family; the capability gate has
+/// already run.
+pub async fn run(cmd: &str, policy: &Policy) -> anyhow::Result<ExecResult> {
+    let Some(runtime) = runtime() else {
+        if SECTION.fallback_to_host {
+            tracing::warn!(
+                "no container runtime installed; running on the host: {}",
+                cmd
+            );
+            telemetry::emit(
+                "executor",
+                "container_fallback",
+                None,
+                json!({"reason": "no_runtime"}),
+            );
+            return executor::run_shell(cmd, policy).await;
+        }
+        return Err(anyhow!(
+            "policy.executor is container, but neither docker nor podman is installed (set containers.runtime or containers.fallback_to_host)"
+        ));
+    };
+    let goal_id = TARGET.try_with(|t| t.goal_id.clone()).ok();
+    let cfg = family_config(goal_id.as_deref());
+    let name = format!("one-engine-{}", uuid::Uuid::new_v4());
+    let mut command = Command::new(runtime);
+    command.args(run_args(&cfg, &policy.limits, &name, cmd));
+    let res = executor::run_command(command, cmd, policy).await?;
+    if !res.ok {
+        // Killing the client on a timeout can leave the container running.
+        let _ = Command::new(runtime)
+            .args(["rm", "-f", &name])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volumes_default_the_container_path_and_keep_drive_letters() {
+        assert_eq!(volume("/srv/meta3"), "/srv/meta3:/srv/meta3");
+        assert_eq!(volume("/srv/cache:/cache:ro"), "/srv/cache:/cache:ro");
+        assert_eq!(volume("C:\\repo:/work"), "C:\\repo:/work");
+        assert_eq!(volume("/data:rw"), "/data:/data");
+    }
+
+    #[test]
+    fn containers_are_offline_unless_configured() {
+        let cfg = FamilyConfig {
+            image: Some("rust:1".into()),
+            mounts: vec!["/srv/meta3".into()],
+            workdir: Some("/srv/meta3".into()),
+            ..Default::default()
+        };
+        let args = run_args(&cfg, &ResourceLimits::default(), "c1", "cargo build");
+        let line = args.join(" ");
+        assert!(line.starts_with("run --rm -i --name c1 --network none"));
+        assert!(line.contains("-v /srv/meta3:/srv/meta3 -w /srv/meta3"));
+        assert!(line.ends_with("rust:1 sh -c cargo build"));
+    }
+}
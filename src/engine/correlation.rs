@@ -0,0 +1,137 @@
+//! Correlation ids: stitch a chat turn, the runs it spawned, their ops and the API calls in
+//! between.
+//!
+//! An id is taken from the `x-correlation-id` request header or minted for mutating requests
+//! (`api_trace_middleware`), scoped as a task-local for the rest of the request (and re-scoped
+//! into background runs), and propagated into Mpayload ctx, progress events, api_trace events,
+//! the executor environment (`ONE_ENGINE_CORRELATION_ID`) and receipts. Every receipt written
+//! under an id appends a link to `runs/correlations/<id>.jsonl`, which `GET /correlations/{id}`
+//! turns into a tree.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use utoipa::ToSchema;
+
+pub const HEADER: &str = "x-correlation-id";
+pub const ENV: &str = "ONE_ENGINE_CORRELATION_ID";
+
+tokio::task_local! {
+    static CORRELATION_ID: Option<String>;
+}
+
+pub fn new_id() -> String {
+    format!("c-{}", uuid::Uuid::new_v4())
+}
+
+/// Ids double as file names under `runs/correlations/`.
+pub fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && !id.contains("..")
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Correlation id of the current task, if any.
+pub fn current() -> Option<String> {
+    CORRELATION_ID.try_with(|c| c.clone()).ok().flatten()
+}
+
+/// Run `fut` with `id` as the current correlation id.
+pub async fn scope<F: Future>(id: Option<String>, fut: F) -> F::Output {
+    CORRELATION_ID.scope(id, fut).await
+}
+
+/// One receipt (or chat turn / op) recorded under a correlation id.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Link {
+    pub ts: String,
+    /// "run" | "chat" | "dsl" | "op" | "nstar"
+    pub kind: String,
+    pub run_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_run_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub goal_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+}
+
+impl Link {
+    pub fn new(kind: &str, run_id: &str) -> Self {
+        Self {
+            ts: chrono::Utc::now().to_rfc3339(),
+            kind: kind.to_string(),
+            run_id: run_id.to_string(),
+            parent_run_id: None,
+            goal_id: None,
+            user_id: None,
+            thread: None,
+        }
+    }
+}
+
+fn dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs")
+        .join("correlations")
+}
+
+/// Append `link` under the current correlation id (no-op outside a correlated task).
+pub async fn record(link: &Link) {
+    let Some(id) = current().filter(|id| is_valid(id)) else {
+        return;
+    };
+    let dir = dir();
+    if tokio::fs::create_dir_all(&dir).await.is_err() {
+        return;
+    }
+    let Ok(mut f) = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{id}.jsonl")))
+        .await
+    else {
+        return;
+    };
+    let line = serde_json::to_string(link).unwrap_or_default();
+    let _ = f.write_all(format!("{line}\n").as_bytes()).await;
+}
+
+/// Links recorded under `id`, oldest first; repeated writes for the same run keep the last.
+pub async fn links(id: &str) -> Vec<Link> {
+    if !is_valid(id) {
+        return Vec::new();
+    }
+    let Ok(f) = tokio::fs::File::open(dir().join(format!("{id}.jsonl"))).await else {
+        return Vec::new();
+    };
+    let mut out: Vec<Link> = Vec::new();
+    let mut lines = tokio::io::BufReader::new(f).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(link) = serde_json::from_str::<Link>(&line) else {
+            continue;
+        };
+        match out
+            .iter_mut()
+            .find(|l| l.run_id == link.run_id && l.kind == link.kind)
+        {
+            Some(prev) => {
+                *prev = Link {
+                    ts: prev.ts.clone(),
+                    ..link
+                }
+            }
+            None => out.push(link),
+        }
+    }
+    out
+}
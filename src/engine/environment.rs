@@ -0,0 +1,213 @@
+//! Environment snapshots: what machine and toolchain a run started on.
+//!
+//! At the start of every run that has a receipt, `runs/receipts/<run_id>/environment.json`
+//! records the OS and architecture, the engine version and executor backend, the git commit
+//! of `META3_ROOT` (and whether the tree was dirty), the versions of the tools the goal uses
+//! (`rustc`/`cargo` for cargo and meta3 builds, `node`/`npm` for meta3 builds) and a whitelist
+//! of environment variables. Values go through the same redaction as receipts, and the
+//! snapshot is also copied into the evidence as `environment`.
+//!
+//! ```yaml
+//! environment:
+//!   capture: true                  # default
+//!   env_vars: [CI, RUSTUP_TOOLCHAIN, NODE_ENV]
+//! ```
+//!
+//! `ONE_ENGINE_CAPTURE_ENVIRONMENT=0` turns capture off. Versions are those of the engine's
+//! host, also for runs on the container or a remote backend.
+
+use once_cell::sync::Lazy;
+use one_engine::{atomic, redact::redact_value};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use super::types::{ExecutorBackend, Policy};
+
+const DEFAULT_ENV_VARS: &[&str] = &[
+    "CI",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_BUILD_TARGET",
+    "RUSTFLAGS",
+    "NODE_ENV",
+    "NODE_OPTIONS",
+    "META3_PATH",
+    "LANG",
+    "TZ",
+];
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Deserialize)]
+struct EnvironmentSection {
+    #[serde(default = "default_capture")]
+    capture: bool,
+    #[serde(default)]
+    env_vars: Option<Vec<String>>,
+}
+
+fn default_capture() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesEnvironment {
+    #[serde(default)]
+    environment: Option<EnvironmentSection>,
+}
+
+struct Config {
+    capture: bool,
+    env_vars: Vec<String>,
+}
+
+static CONFIG: Lazy<Config> = Lazy::new(|| {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let section = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesEnvironment>(&raw).ok())
+        .and_then(|p| p.environment);
+    let capture = match std::env::var("ONE_ENGINE_CAPTURE_ENVIRONMENT") {
+        Ok(v) => !matches!(v.trim(), "0" | "false" | "off"),
+        Err(_) => section.as_ref().is_none_or(|s| s.capture),
+    };
+    Config {
+        capture,
+        env_vars: section
+            .and_then(|s| s.env_vars)
+            .unwrap_or_else(|| DEFAULT_ENV_VARS.iter().map(|v| v.to_string()).collect()),
+    }
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Environment {
+    pub captured_at: String,
+    pub os: String,
+    pub arch: String,
+    pub engine_version: String,
+    pub executor: ExecutorBackend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_sha: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_dirty: Option<bool>,
+    /// Tool name → first line of its `--version`.
+    pub tools: BTreeMap<String, String>,
+    /// Whitelisted variables that are set.
+    pub env: BTreeMap<String, String>,
+}
+
+/// Tools whose versions matter for `goal_id`.
+fn tools_for(goal_id: &str) -> &'static [&'static str] {
+    if goal_id.contains("meta3.build") {
+        &["rustc", "cargo", "node", "npm"]
+    } else if goal_id.contains("cargo.") {
+        &["rustc", "cargo"]
+    } else {
+        &[]
+    }
+}
+
+/// Trimmed stdout of `program args`, when it exits 0 within the probe timeout. Simulation
+/// runs no host programs, so there it is always `None`.
+async fn probe(program: &str, args: &[&str]) -> Option<String> {
+    if crate::engine::simulation::active() {
+        return None;
+    }
+    let out = tokio::time::timeout(
+        PROBE_TIMEOUT,
+        tokio::process::Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+fn meta3_root() -> PathBuf {
+    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()))
+}
+
+/// Snapshot the environment for a `goal_id` run under `policy`.
+pub async fn capture(goal_id: &str, policy: &Policy) -> Environment {
+    let root = meta3_root().display().to_string();
+    let git_sha = probe("git", &["-C", &root, "rev-parse", "HEAD"])
+        .await
+        .filter(|s| !s.is_empty());
+    let git_dirty = match git_sha {
+        Some(_) => probe("git", &["-C", &root, "status", "--porcelain"])
+            .await
+            .map(|s| !s.is_empty()),
+        None => None,
+    };
+    let mut tools = BTreeMap::new();
+    for tool in tools_for(goal_id) {
+        if let Some(v) = probe(tool, &["--version"]).await {
+            let first = v.lines().next().unwrap_or_default().to_string();
+            tools.insert(tool.to_string(), first);
+        }
+    }
+    let env = CONFIG
+        .env_vars
+        .iter()
+        .filter_map(|k| std::env::var(k).ok().map(|v| (k.clone(), v)))
+        .collect();
+    Environment {
+        captured_at: chrono::Utc::now().to_rfc3339(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        engine_version: env!("CARGO_PKG_VERSION").to_string(),
+        executor: policy.executor,
+        git_sha,
+        git_dirty,
+        tools,
+        env,
+    }
+}
+
+/// Capture (when enabled), redact and write `environment.json` into receipt `run_id`'s
+/// directory; returns the redacted snapshot for the evidence.
+pub async fn record(run_id: &str, goal_id: &str, policy: &Policy) -> Option<Value> {
+    if !CONFIG.capture || run_id.is_empty() || run_id.contains(['/', '\\']) || run_id.contains("..")
+    {
+        return None;
+    }
+    let mut snapshot = serde_json::to_value(capture(goal_id, policy).await).ok()?;
+    redact_value(&mut snapshot);
+    let dir = meta3_root().join("runs/receipts").join(run_id);
+    let path = dir.join("environment.json");
+    let written = std::fs::create_dir_all(&dir).and_then(|_| {
+        atomic::write(
+            &path,
+            serde_json::to_string_pretty(&snapshot).unwrap_or_default(),
+        )
+    });
+    if let Err(e) = written {
+        tracing::warn!("cannot write {}: {}", path.display(), e);
+    }
+    Some(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn snapshot_names_the_platform_and_only_relevant_tools() {
+        assert!(tools_for("wiki.generate").is_empty());
+        assert_eq!(tools_for("user:demo.cargo.test"), &["rustc", "cargo"]);
+        let env = capture("wiki.generate", &Policy::default()).await;
+        assert_eq!(env.os, std::env::consts::OS);
+        assert!(env.tools.is_empty());
+        assert!(env.env.keys().all(|k| CONFIG.env_vars.contains(k)));
+    }
+}
@@ -0,0 +1,201 @@
+//! Typed `Manifest.evidence` per goal family.
+//!
+//! The wire format stays plain JSON; these structs name the fields each family writes so
+//! producers and consumers agree on them. Fields a struct doesn't know are kept in `extra`,
+//! so reading evidence into a struct and writing it back never drops anything.
+
+use anyhow::anyhow;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::build_log::BuildReport;
+use super::graph_doc;
+use super::urls::url_for;
+
+pub trait Evidence: Serialize + DeserializeOwned {
+    /// Goal family this evidence belongs to, for error messages.
+    const FAMILY: &'static str;
+
+    fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+
+    fn from_value(v: &Value) -> anyhow::Result<Self> {
+        serde_json::from_value(v.clone()).map_err(|e| anyhow!("{} evidence: {}", Self::FAMILY, e))
+    }
+}
+
+/// Fields every deterministic goal reports.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Outcome {
+    pub actual_success: bool,
+    #[serde(default)]
+    pub expected_success: bool,
+    #[serde(default)]
+    pub meta2_triggered: bool,
+}
+
+impl Outcome {
+    pub fn ok(meta2_triggered: bool) -> Self {
+        Self {
+            actual_success: true,
+            expected_success: true,
+            meta2_triggered,
+        }
+    }
+}
+
+/// `wiki.generate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WikiEvidence {
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub wiki_dir: String,
+    pub index_html_url: String,
+    pub static_html_url: String,
+    pub files_count: usize,
+    pub topfiles_count: usize,
+    pub readme_copied: bool,
+    pub modules_count: usize,
+    pub search_docs: usize,
+    pub search_html_url: String,
+    pub stdout: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Evidence for WikiEvidence {
+    const FAMILY: &'static str = "wiki.generate";
+}
+
+/// `graphs.*`: counts plus links to the rendered files under `runs/graphs/<run_id>/`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEvidence {
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub nodes: usize,
+    pub edges: usize,
+    pub index_html_url: String,
+    pub dot_url: String,
+    pub events_url: String,
+    /// Engine that rendered `graph.svg`, if any.
+    pub layout: Option<String>,
+    pub svg_url: Option<String>,
+    pub graph_json_url: String,
+    pub viewer_url: String,
+    pub stdout: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl GraphEvidence {
+    pub fn new(
+        run_id: &str,
+        nodes: usize,
+        edges: usize,
+        layout: Option<&str>,
+        stdout: String,
+        meta2_triggered: bool,
+    ) -> Self {
+        let url = |file: &str| url_for(&format!("/runs/graphs/{}/{}", run_id, file));
+        Self {
+            outcome: Outcome::ok(meta2_triggered),
+            nodes,
+            edges,
+            index_html_url: url("index.html"),
+            dot_url: url("graph.dot"),
+            events_url: url("events.json"),
+            layout: layout.map(|s| s.to_string()),
+            svg_url: layout.map(|_| url("graph.svg")),
+            graph_json_url: url("graph.json"),
+            viewer_url: graph_doc::viewer_url(&format!("/runs/graphs/{}/graph.json", run_id)),
+            stdout,
+            extra: Map::new(),
+        }
+    }
+
+    /// Family-specific field (`thread`, `since_hours`, ...).
+    pub fn with(mut self, key: &str, value: impl Serialize) -> Self {
+        self.extra.insert(
+            key.to_string(),
+            serde_json::to_value(value).unwrap_or(Value::Null),
+        );
+        self
+    }
+}
+
+impl Evidence for GraphEvidence {
+    const FAMILY: &'static str = "graphs";
+}
+
+/// `meta3.build`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildEvidence {
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    pub stdout: String,
+    pub repo_path: String,
+    pub build_cmd: String,
+    pub log_path: String,
+    pub log_url: String,
+    pub report_url: String,
+    pub build_report: BuildReport,
+    pub exit_ok: bool,
+    pub run_id: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Evidence for BuildEvidence {
+    const FAMILY: &'static str = "meta3.build";
+}
+
+/// `meta.omni`: the model's JSON reply. It reports no outcome fields, so none are added.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatEvidence {
+    #[serde(default, deserialize_with = "string_or_empty")]
+    pub reply: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub intent: Value,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub bits: Value,
+    /// Goal the model proposes to run next (`{goal_id, inputs}`), if any.
+    #[serde(default)]
+    pub run_payload: Value,
+    /// Goals run through tool calls during this turn.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Model output isn't schema-checked; a non-string `reply` reads as empty.
+fn string_or_empty<'de, D: serde::Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    Ok(Value::deserialize(d)?.as_str().unwrap_or("").to_string())
+}
+
+impl ChatEvidence {
+    pub fn run_payload(&self) -> Option<Value> {
+        Some(self.run_payload.clone()).filter(|v| !v.is_null())
+    }
+}
+
+impl Evidence for ChatEvidence {
+    const FAMILY: &'static str = "meta.omni";
+}
+
+/// Check `evidence` against its family's struct; `Ok` for families without one.
+pub fn check(goal_id: &str, evidence: &Value) -> anyhow::Result<()> {
+    if goal_id.contains("wiki.generate") {
+        WikiEvidence::from_value(evidence).map(|_| ())
+    } else if goal_id.contains("graphs.") || goal_id.contains("graph.") {
+        GraphEvidence::from_value(evidence).map(|_| ())
+    } else if goal_id.contains("meta3.build") {
+        BuildEvidence::from_value(evidence).map(|_| ())
+    } else if goal_id.contains("meta.omni") {
+        ChatEvidence::from_value(evidence).map(|_| ())
+    } else {
+        Ok(())
+    }
+}
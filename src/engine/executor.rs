@@ -1,15 +1,31 @@
-use super::types::Policy;
+use super::container;
+use super::harness;
+use super::limits;
+use super::live_log;
+use super::platform;
+use super::types::{ExecutorBackend, Policy};
+use super::write_scope;
+use crate::integrations::telemetry;
 use anyhow::{anyhow, Context};
+use serde_json::json;
 use std::process::Stdio;
-use tokio::io::AsyncReadExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::process::Command;
-use tokio::time::{timeout, Duration};
+use tokio::time::Duration;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Action {
     Cli(String),
+    /// Write `content` to `path`, creating parent dirs.
+    WriteFile {
+        path: String,
+        content: String,
+    },
 }
 
+#[derive(Debug, Clone)]
 pub struct ExecResult {
     pub ok: bool,
     pub drift: bool,
@@ -23,73 +39,305 @@ pub async fn execute(action: Action, policy: &Policy) -> anyhow::Result<ExecResu
             // Capability gate (simple heuristic). If STRICT_CAPS=1, block risky ops.
             if let Some(cap) = detect_capability(&cmd) {
                 if std::env::var("STRICT_CAPS").ok().as_deref() == Some("1") {
+                    telemetry::emit(
+                        "executor",
+                        "gate_trip",
+                        None,
+                        json!({"gate": "capability", "capability": cap}),
+                    );
                     return Err(anyhow!("capability gate blocked: {}", cap));
                 }
             }
-            let mut child = Command::new("bash")
-                .arg("-lc")
-                .arg(&cmd)
-                .stdout(Stdio::piped())
-                .stderr(Stdio::piped())
-                .spawn()
-                .with_context(|| format!("failed to spawn: {}", cmd))?;
+            match harness::current() {
+                Some(h) => h.runner.run(&cmd, policy).await,
+                None if policy.executor == ExecutorBackend::Container => {
+                    container::run(&cmd, policy).await
+                }
+                None => {
+                    // Commands write relative to the working directory, so it must be in scope.
+                    let cwd = std::env::current_dir().context("cwd")?;
+                    if let Err(v) = write_scope::check(&cwd) {
+                        scope_trip(&v);
+                        return Err(anyhow!("write scope blocked shell cwd: {}", v.reason));
+                    }
+                    run_shell(&cmd, policy).await
+                }
+            }
+        }
+        Action::WriteFile { path, content } => {
+            let harness = harness::current();
+            let mut native = platform::native_path(&path);
+            // A harness writes to its own filesystem; only real writes are scoped.
+            if harness.is_none() {
+                match write_scope::check(&native) {
+                    Ok(real) => native = real,
+                    Err(v) => {
+                        scope_trip(&v);
+                        return Ok(ExecResult {
+                            ok: false,
+                            drift: false,
+                            stdout: String::new(),
+                            stderr: format!("write scope blocked {}: {}", path, v.reason),
+                        });
+                    }
+                }
+            }
+            let p = native.as_path();
+            if let (None, Some(parent)) =
+                (&harness, p.parent().filter(|d| !d.as_os_str().is_empty()))
+            {
+                one_engine::storage::create_dir_all(parent)
+                    .with_context(|| format!("failed to create dir {}", parent.display()))?;
+            }
+            // Through `atomic` so the write counts against the run's disk quota.
+            let res = match harness {
+                Some(h) => h.fs.write(p, content.as_bytes()),
+                None => one_engine::atomic::write_async(p, content.as_bytes()).await,
+            };
+            match res {
+                Ok(()) => Ok(ExecResult {
+                    ok: true,
+                    drift: false,
+                    stdout: format!("wrote {} bytes to {}", content.len(), path),
+                    stderr: String::new(),
+                }),
+                Err(e) => Ok(ExecResult {
+                    ok: false,
+                    drift: false,
+                    stdout: String::new(),
+                    stderr: format!("failed to write {}: {}", path, e),
+                }),
+            }
+        }
+    }
+}
 
-            let time_limit = Duration::from_millis(policy.time_ms as u64);
+fn scope_trip(v: &write_scope::Violation) {
+    telemetry::emit(
+        "executor",
+        "gate_trip",
+        None,
+        json!({"gate": "write_scope", "path": v.path, "reason": v.reason}),
+    );
+}
 
-            let mut stdout_pipe = child
-                .stdout
-                .take()
-                .ok_or_else(|| anyhow!("missing stdout pipe"))?;
-            let mut stderr_pipe = child
-                .stderr
-                .take()
-                .ok_or_else(|| anyhow!("missing stderr pipe"))?;
+/// `cmd` in the host shell (`bash -lc` on Unix, see [`platform`]) under the policy's time
+/// limit; the capability gate has already run.
+pub async fn run_shell(cmd: &str, policy: &Policy) -> anyhow::Result<ExecResult> {
+    run_command(platform::shell().command(cmd), cmd, policy).await
+}
 
-            let stdout_task = tokio::spawn(async move {
-                let mut buf = Vec::new();
-                let _ = stdout_pipe.read_to_end(&mut buf).await;
-                buf
-            });
-            let stderr_task = tokio::spawn(async move {
-                let mut buf = Vec::new();
-                let _ = stderr_pipe.read_to_end(&mut buf).await;
-                buf
-            });
+/// Spawn `command` (which runs `cmd`), collect its output and enforce the policy's time limit.
+/// Shared by the host shell and the container backend so both report the same way.
+pub(crate) async fn run_command(
+    mut command: Command,
+    cmd: &str,
+    policy: &Policy,
+) -> anyhow::Result<ExecResult> {
+    // A preempted or cancelled run drops this future; the process must not outlive it.
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(id) = crate::engine::correlation::current() {
+        command.env(crate::engine::correlation::ENV, id);
+    }
+    // Secrets reach commands only through resolved inputs, never the inherited env.
+    for (k, _) in std::env::vars_os() {
+        if k.to_string_lossy()
+            .starts_with(crate::engine::secrets::ENV_PREFIX)
+        {
+            command.env_remove(k);
+        }
+    }
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("failed to spawn: {}", cmd))?;
 
-            let mut timed_out = false;
-            let status_success = match timeout(time_limit, child.wait()).await {
-                Ok(res) => res
-                    .with_context(|| format!("failed to wait: {}", cmd))?
-                    .success(),
-                Err(_) => {
-                    timed_out = true;
-                    let _ = child.kill().await;
-                    let _ = child.wait().await;
-                    false
-                }
-            };
+    let time_limit = Duration::from_millis(policy.time_ms);
+    let limits = &policy.limits;
+    let cap = limits.max_output_bytes.unwrap_or(u64::MAX);
+    let output_bytes = Arc::new(AtomicU64::new(0));
+
+    let stdout_pipe = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("missing stdout pipe"))?;
+    let stderr_pipe = child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow!("missing stderr pipe"))?;
+
+    // Spawned readers lose the task-local, so the live log goes in explicitly.
+    let live = live_log::current();
+    let stdout_task = tokio::spawn(read_capped(
+        stdout_pipe,
+        cap,
+        output_bytes.clone(),
+        live.clone(),
+    ));
+    let stderr_task = tokio::spawn(read_capped(stderr_pipe, cap, output_bytes.clone(), live));
 
-            let stdout_bytes = stdout_task.await.unwrap_or_default();
-            let stderr_bytes = stderr_task.await.unwrap_or_default();
-            let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
-            let mut stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
-            if timed_out {
-                if !stderr.is_empty() {
-                    stderr.push('\n');
+    let mut timed_out = false;
+    let mut exceeded = None;
+    let pid = child.id();
+    let deadline = tokio::time::sleep(time_limit);
+    tokio::pin!(deadline);
+    let mut sample = tokio::time::interval(Duration::from_millis(250));
+    let status_success = loop {
+        tokio::select! {
+            res = child.wait() => {
+                break res.with_context(|| format!("failed to wait: {}", cmd))?.success();
+            }
+            _ = &mut deadline => {
+                timed_out = true;
+                break false;
+            }
+            _ = sample.tick(), if !limits.is_unlimited() => {
+                let usage = match pid {
+                    Some(pid) => limits::sample(pid).await.unwrap_or_default(),
+                    None => Default::default(),
+                };
+                let output = output_bytes.load(Ordering::Relaxed);
+                exceeded = limits::check(limits, usage, output, cmd);
+                if exceeded.is_some() {
+                    break false;
                 }
-                stderr.push_str(&format!("timeout after {}ms", policy.time_ms));
             }
-            Ok(ExecResult {
-                ok: status_success && !timed_out,
+        }
+    };
+    if timed_out || exceeded.is_some() {
+        if let Some(pid) = pid {
+            limits::kill_tree(pid).await;
+        }
+        let _ = child.kill().await;
+        let _ = child.wait().await;
+    }
+
+    let stdout_bytes = stdout_task.await.unwrap_or_default();
+    let stderr_bytes = stderr_task.await.unwrap_or_default();
+    let stdout = String::from_utf8_lossy(&stdout_bytes).to_string();
+    let mut stderr = String::from_utf8_lossy(&stderr_bytes).to_string();
+    if timed_out {
+        if !stderr.is_empty() {
+            stderr.push('\n');
+        }
+        stderr.push_str(&format!("timeout after {}ms", policy.time_ms));
+    }
+    if let Some(e) = &exceeded {
+        if !stderr.is_empty() {
+            stderr.push('\n');
+        }
+        stderr.push_str(&e.to_string());
+    }
+    if !status_success || timed_out {
+        telemetry::emit(
+            "executor",
+            "exec_failure",
+            None,
+            json!({
+                "timed_out": timed_out,
+                "time_ms": policy.time_ms,
+                "resource_limit": exceeded.as_ref().map(|e| &e.limit),
+                "stderr_tail": stderr
+                    .chars()
+                    .skip(stderr.chars().count().saturating_sub(400))
+                    .collect::<String>()
+            }),
+        );
+    }
+    if let Some(e) = exceeded {
+        limits::record(e);
+    }
+    Ok(ExecResult {
+        ok: status_success && !timed_out,
+        drift: false,
+        stdout,
+        stderr,
+    })
+}
+
+/// Read `pipe` to the end, keeping at most `cap` bytes and adding everything read to `total`
+/// (reading continues past the cap so the child never blocks on a full pipe). Every chunk also
+/// goes to `live`, uncapped, when the run has a live log.
+async fn read_capped<R: AsyncRead + Unpin>(
+    mut pipe: R,
+    cap: u64,
+    total: Arc<AtomicU64>,
+    live: Option<Arc<std::fs::File>>,
+) -> Vec<u8> {
+    use std::io::Write;
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    while let Ok(n) = pipe.read(&mut chunk).await {
+        if n == 0 {
+            break;
+        }
+        total.fetch_add(n as u64, Ordering::Relaxed);
+        if let Some(f) = &live {
+            let _ = (&**f).write_all(&chunk[..n]);
+        }
+        let room = (cap as usize).saturating_sub(buf.len());
+        buf.extend_from_slice(&chunk[..n.min(room)]);
+    }
+    buf
+}
+
+/// Dry-run: parse the command without executing it (`bash -n`; shells without a parse-only
+/// flag are not checked); writes only report their size.
+pub async fn dry_run(action: &Action) -> ExecResult {
+    if harness::current().is_some() {
+        // Fakes have no parser to consult; the replay still goes through the runner.
+        return ExecResult {
+            ok: true,
+            drift: false,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+    }
+    match action {
+        Action::Cli(cmd) => {
+            let sh = platform::shell();
+            let Some(mut check) = sh.syntax_check(cmd) else {
+                return ExecResult {
+                    ok: true,
+                    drift: false,
+                    stdout: String::new(),
+                    stderr: format!("{} has no parse-only mode; syntax not checked", sh.name()),
+                };
+            };
+            match check.output().await {
+                Ok(out) => ExecResult {
+                    ok: out.status.success(),
+                    drift: false,
+                    stdout: String::from_utf8_lossy(&out.stdout).to_string(),
+                    stderr: String::from_utf8_lossy(&out.stderr).to_string(),
+                },
+                Err(e) => ExecResult {
+                    ok: false,
+                    drift: false,
+                    stdout: String::new(),
+                    stderr: e.to_string(),
+                },
+            }
+        }
+        Action::WriteFile { path, content } => {
+            let blocked = platform::native_path(path).is_dir();
+            ExecResult {
+                ok: !blocked,
                 drift: false,
-                stdout,
-                stderr,
-            })
+                stdout: format!("would write {} bytes to {}", content.len(), path),
+                stderr: if blocked {
+                    format!("{} is a directory", path)
+                } else {
+                    String::new()
+                },
+            }
         }
     }
 }
 
-fn detect_capability(cmd: &str) -> Option<&'static str> {
+pub fn detect_capability(cmd: &str) -> Option<&'static str> {
     let s = cmd.to_lowercase();
     if s.contains("curl ") || s.contains("wget ") {
         return Some("network");
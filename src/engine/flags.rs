@@ -0,0 +1,359 @@
+//! Goal flags: kill switches and percentage rollouts that change without a redeploy.
+//!
+//! ```yaml
+//! goal_flags:
+//!   - goal: "graphs.*"              # goal id or `*` glob, matched after alias resolution
+//!     enabled: false
+//!     message: "graph rendering is down, see #412"
+//!   - goal: wiki.generate
+//!     rollout: {to: wiki.generate.v2, percent: 10}
+//! ```
+//!
+//! A disabled goal fails before it runs with a [`GoalDisabled`] error (HTTP 403). A rollout
+//! sends `percent` of the goal's runs to `to` instead, picked by a hash of the flag and run id
+//! so a retried run lands on the same version; the evidence says so under `rollout`. Patterns
+//! match the full goal id or the id after a `user:<id>.` prefix, which a routed run keeps.
+//!
+//! `POST /admin/flags` stores runtime overrides in `runs/flags/overrides.json`; they replace a
+//! file entry with the same `goal` and are read on every run. For a goal, an exact `goal`
+//! beats a glob, then overrides beat the file and earlier entries beat later ones.
+
+use anyhow::{bail, Context, Result};
+use once_cell::sync::Lazy;
+use one_engine::atomic;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use utoipa::ToSchema;
+
+use super::policy::glob_match;
+
+/// Start of every [`GoalDisabled`] message, for callers that only see the error text.
+pub const DISABLED: &str = "goal disabled";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Rollout {
+    /// Goal id the selected runs go to (a `user:<id>.` prefix is carried over).
+    pub to: String,
+    /// Share of runs sent to `to`, 0–100.
+    pub percent: f64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalFlag {
+    /// Goal id or `*` glob.
+    pub goal: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Shown to callers of a disabled goal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollout: Option<Rollout>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagSource {
+    Config,
+    Override,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct FlagEntry {
+    pub flag: GoalFlag,
+    pub source: FlagSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+    /// Audit actor of the override (`admin`, `key:<digest prefix>`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_by: Option<String>,
+}
+
+/// The error of a run whose goal is switched off.
+#[derive(Debug, Clone)]
+pub struct GoalDisabled {
+    pub goal_id: String,
+    /// The `goal` pattern of the flag that matched.
+    pub flag: String,
+    pub message: Option<String>,
+}
+
+impl std::fmt::Display for GoalDisabled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} is switched off by flag {}",
+            DISABLED, self.goal_id, self.flag
+        )?;
+        if let Some(m) = &self.message {
+            write!(f, " ({})", m)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for GoalDisabled {}
+
+/// Whether an error message is a [`GoalDisabled`] one.
+pub fn is_disabled(message: &str) -> bool {
+    message.starts_with(DISABLED)
+}
+
+/// Where a rollout sent a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Routed {
+    pub requested: String,
+    pub goal_id: String,
+    pub flag: String,
+    pub percent: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesFlags {
+    #[serde(default)]
+    goal_flags: Vec<GoalFlag>,
+}
+
+fn config_flags() -> Vec<GoalFlag> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesFlags>(&raw) {
+            Ok(p) => p.goal_flags,
+            Err(e) => {
+                tracing::warn!("invalid goal_flags in {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+fn overrides_path() -> PathBuf {
+    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()))
+        .join("runs/flags/overrides.json")
+}
+
+fn read_overrides() -> Vec<FlagEntry> {
+    std::fs::read_to_string(overrides_path())
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Overrides first, then the file entries they don't replace.
+pub fn list() -> Vec<FlagEntry> {
+    let mut all = read_overrides();
+    for flag in config_flags() {
+        if !all.iter().any(|e| e.flag.goal == flag.goal) {
+            all.push(FlagEntry {
+                flag,
+                source: FlagSource::Config,
+                updated_at: None,
+                updated_by: None,
+            });
+        }
+    }
+    all
+}
+
+/// `(prefix, bare)`: `("user:demo.", "wiki.generate")` for `user:demo.wiki.generate`.
+fn split_user(goal_id: &str) -> (&str, &str) {
+    match goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.'))
+    {
+        Some((_, bare)) => (&goal_id[..goal_id.len() - bare.len()], bare),
+        None => ("", goal_id),
+    }
+}
+
+fn find<'a>(flags: &'a [FlagEntry], goal_id: &str) -> Option<&'a GoalFlag> {
+    let bare = split_user(goal_id).1;
+    flags
+        .iter()
+        .find(|e| e.flag.goal == goal_id || e.flag.goal == bare)
+        .or_else(|| {
+            flags
+                .iter()
+                .find(|e| glob_match(&e.flag.goal, goal_id) || glob_match(&e.flag.goal, bare))
+        })
+        .map(|e| &e.flag)
+}
+
+/// Uniform in [0, 100) from the flag pattern and run id.
+fn bucket(flag: &str, run_id: &str) -> f64 {
+    let digest = Sha256::digest(format!("{}:{}", flag, run_id).as_bytes());
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(b) >> 11) as f64 / (1u64 << 53) as f64 * 100.0
+}
+
+fn disabled(goal_id: &str, flag: &GoalFlag) -> GoalDisabled {
+    GoalDisabled {
+        goal_id: goal_id.to_string(),
+        flag: flag.goal.clone(),
+        message: flag.message.clone(),
+    }
+}
+
+fn check_with(
+    flags: &[FlagEntry],
+    goal_id: &str,
+    run_id: &str,
+) -> Result<Option<Routed>, GoalDisabled> {
+    let Some(flag) = find(flags, goal_id) else {
+        return Ok(None);
+    };
+    if !flag.enabled {
+        return Err(disabled(goal_id, flag));
+    }
+    let Some(r) = flag
+        .rollout
+        .as_ref()
+        .filter(|r| bucket(&flag.goal, run_id) < r.percent)
+    else {
+        return Ok(None);
+    };
+    let to = format!("{}{}", split_user(goal_id).0, r.to);
+    // The new version has its own kill switch, but is not routed again.
+    if let Some(target) = find(flags, &to).filter(|t| !t.enabled) {
+        return Err(disabled(&to, target));
+    }
+    Ok(Some(Routed {
+        requested: goal_id.to_string(),
+        goal_id: to,
+        flag: flag.goal.clone(),
+        percent: r.percent,
+    }))
+}
+
+/// Fail when `goal_id` is switched off; otherwise where a rollout sends run `run_id`, if
+/// anywhere.
+pub fn check(goal_id: &str, run_id: &str) -> Result<Option<Routed>, GoalDisabled> {
+    check_with(&list(), goal_id, run_id)
+}
+
+fn validate(flag: &GoalFlag) -> Result<()> {
+    if flag.goal.trim().is_empty() || flag.goal.contains(char::is_whitespace) {
+        bail!("goal must be a goal id or glob without whitespace");
+    }
+    if let Some(r) = &flag.rollout {
+        if !(0.0..=100.0).contains(&r.percent) {
+            bail!("rollout.percent must be between 0 and 100");
+        }
+        if r.to.trim().is_empty() || r.to == flag.goal || r.to.contains('*') {
+            bail!("rollout.to must be another goal id");
+        }
+    }
+    Ok(())
+}
+
+static WRITE: Lazy<std::sync::Mutex<()>> = Lazy::new(|| std::sync::Mutex::new(()));
+
+fn write_overrides(entries: &[FlagEntry]) -> Result<()> {
+    let path = overrides_path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    atomic::write(&path, serde_json::to_string_pretty(entries)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Store `flag` as a runtime override, replacing one with the same `goal`.
+pub fn set(flag: GoalFlag, actor: &str) -> Result<FlagEntry> {
+    validate(&flag)?;
+    let _guard = WRITE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = read_overrides();
+    entries.retain(|e| e.flag.goal != flag.goal);
+    let entry = FlagEntry {
+        flag,
+        source: FlagSource::Override,
+        updated_at: Some(chrono::Utc::now().to_rfc3339()),
+        updated_by: Some(actor.to_string()),
+    };
+    entries.insert(0, entry.clone());
+    write_overrides(&entries)?;
+    Ok(entry)
+}
+
+/// Drop the runtime override for `goal`; `false` when there was none.
+pub fn clear(goal: &str) -> Result<bool> {
+    let _guard = WRITE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = read_overrides();
+    let before = entries.len();
+    entries.retain(|e| e.flag.goal != goal);
+    if entries.len() == before {
+        return Ok(false);
+    }
+    write_overrides(&entries)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(flag: GoalFlag) -> FlagEntry {
+        FlagEntry {
+            flag,
+            source: FlagSource::Config,
+            updated_at: None,
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn kill_switches_and_rollouts_follow_the_most_specific_flag() {
+        let flags = vec![
+            entry(GoalFlag {
+                goal: "graphs.*".into(),
+                enabled: false,
+                message: Some("maintenance".into()),
+                rollout: None,
+            }),
+            entry(GoalFlag {
+                goal: "graphs.thread".into(),
+                enabled: true,
+                message: None,
+                rollout: None,
+            }),
+            entry(GoalFlag {
+                goal: "wiki.generate".into(),
+                enabled: true,
+                message: None,
+                rollout: Some(Rollout {
+                    to: "wiki.generate.v2".into(),
+                    percent: 30.0,
+                }),
+            }),
+        ];
+        let err = check_with(&flags, "user:demo.graphs.api", "r-1").unwrap_err();
+        assert!(is_disabled(&err.to_string()));
+        assert!(err.to_string().ends_with("(maintenance)"));
+        assert!(check_with(&flags, "graphs.thread", "r-1")
+            .unwrap()
+            .is_none());
+
+        let routed: Vec<Routed> = (0..1000)
+            .filter_map(|i| {
+                check_with(&flags, "user:demo.wiki.generate", &format!("r-{}", i)).unwrap()
+            })
+            .collect();
+        assert!((200..400).contains(&routed.len()), "{}", routed.len());
+        assert!(routed
+            .iter()
+            .all(|r| r.goal_id == "user:demo.wiki.generate.v2"));
+        // The same run always lands on the same side.
+        let again = check_with(&flags, "user:demo.wiki.generate", "r-7").unwrap();
+        let first = check_with(&flags, "user:demo.wiki.generate", "r-7").unwrap();
+        assert_eq!(again.is_some(), first.is_some());
+    }
+}
@@ -0,0 +1,279 @@
+//! cargo.build / cargo.test / cargo.clippy: cargo-native goals parsed from
+//! `--message-format=json` (compiler messages) plus libtest's text output (test results).
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::engine::executor::{self, ExecResult};
+use crate::engine::platform;
+use crate::engine::types::Policy;
+
+const MAX_ITEMS: usize = 200;
+
+/// Defaults from `config/policies.yaml` (`cargo:` section); per-run inputs override.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CargoPolicy {
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub all_features: bool,
+    #[serde(default)]
+    pub no_default_features: bool,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub package: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompileDiagnostic {
+    pub level: String,
+    pub message: String,
+    pub code: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CargoReport {
+    pub subcommand: String,
+    pub cmd: String,
+    pub exit_ok: bool,
+    pub build_finished: Option<bool>,
+    pub success: bool,
+    pub artifacts: usize,
+    pub errors: Vec<CompileDiagnostic>,
+    pub warnings: usize,
+    /// clippy lints grouped by level, then lint name → count.
+    pub lints_by_level: BTreeMap<String, BTreeMap<String, usize>>,
+    pub tests_passed: Vec<String>,
+    pub tests_failed: Vec<String>,
+    pub tests_ignored: usize,
+    pub stderr_tail: String,
+}
+
+fn subcommand_for(goal_id: &str) -> Option<&'static str> {
+    if goal_id.contains("cargo.build") {
+        Some("build")
+    } else if goal_id.contains("cargo.test") {
+        Some("test")
+    } else if goal_id.contains("cargo.clippy") {
+        Some("clippy")
+    } else {
+        None
+    }
+}
+
+fn str_list(v: Option<&Value>) -> Option<Vec<String>> {
+    match v? {
+        Value::String(s) => Some(
+            s.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|x| !x.is_empty())
+                .map(|x| x.to_string())
+                .collect(),
+        ),
+        Value::Array(arr) => Some(
+            arr.iter()
+                .filter_map(|x| x.as_str().map(|s| s.to_string()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn safe_arg(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '/' | '.' | ':' | '@'))
+}
+
+/// Merge policy defaults with inputs and build the cargo command line.
+pub fn build_command(subcommand: &str, inputs: &Value, defaults: &CargoPolicy) -> Result<String> {
+    let repo = inputs
+        .get("repo_path")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .or_else(|| std::env::var("META3_PATH").ok())
+        .unwrap_or_else(|| ".".to_string());
+    let features = str_list(inputs.get("features")).unwrap_or_else(|| defaults.features.clone());
+    let all_features = inputs
+        .get("all_features")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(defaults.all_features);
+    let no_default = inputs
+        .get("no_default_features")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(defaults.no_default_features);
+    let profile = inputs
+        .get("profile")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .or_else(|| defaults.profile.clone());
+    let package = inputs
+        .get("package")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .or_else(|| defaults.package.clone());
+
+    let mut args: Vec<String> = vec![subcommand.to_string(), "--message-format=json".to_string()];
+    if let Some(p) = profile.as_deref() {
+        if !safe_arg(p) {
+            return Err(anyhow!("invalid profile: {}", p));
+        }
+        args.push(format!("--profile={}", p));
+    }
+    if let Some(p) = package.as_deref() {
+        if !safe_arg(p) {
+            return Err(anyhow!("invalid package: {}", p));
+        }
+        args.push(format!("--package={}", p));
+    }
+    if all_features {
+        args.push("--all-features".to_string());
+    } else if !features.is_empty() {
+        if let Some(bad) = features.iter().find(|f| !safe_arg(f)) {
+            return Err(anyhow!("invalid feature: {}", bad));
+        }
+        args.push(format!("--features={}", features.join(",")));
+    }
+    if no_default {
+        args.push("--no-default-features".to_string());
+    }
+    if subcommand == "test" {
+        args.push("--no-fail-fast".to_string());
+    }
+
+    let sh = platform::shell();
+    Ok(sh.in_dir(&repo, &sh.argv("cargo", &args)))
+}
+
+fn primary_span(msg: &Value) -> (Option<String>, Option<u64>, Option<u64>) {
+    let span = msg.get("spans").and_then(|s| s.as_array()).and_then(|arr| {
+        arr.iter()
+            .find(|s| s.get("is_primary").and_then(|v| v.as_bool()) == Some(true))
+            .or_else(|| arr.first())
+    });
+    match span {
+        Some(s) => (
+            s.get("file_name")
+                .and_then(|v| v.as_str())
+                .map(|x| x.to_string()),
+            s.get("line_start").and_then(|v| v.as_u64()),
+            s.get("column_start").and_then(|v| v.as_u64()),
+        ),
+        None => (None, None, None),
+    }
+}
+
+pub fn parse_output(subcommand: &str, cmd: &str, res: &ExecResult) -> CargoReport {
+    let mut r = CargoReport {
+        subcommand: subcommand.to_string(),
+        cmd: cmd.to_string(),
+        exit_ok: res.ok,
+        ..Default::default()
+    };
+
+    for line in res.stdout.lines() {
+        let t = line.trim();
+        if t.starts_with('{') {
+            let Ok(v) = serde_json::from_str::<Value>(t) else {
+                continue;
+            };
+            match v.get("reason").and_then(|x| x.as_str()) {
+                Some("compiler-artifact") => r.artifacts += 1,
+                Some("build-finished") => {
+                    r.build_finished = v.get("success").and_then(|x| x.as_bool());
+                }
+                Some("compiler-message") => {
+                    let Some(msg) = v.get("message") else {
+                        continue;
+                    };
+                    let level = msg
+                        .get("level")
+                        .and_then(|x| x.as_str())
+                        .unwrap_or("")
+                        .to_string();
+                    let code = msg
+                        .get("code")
+                        .and_then(|c| c.get("code"))
+                        .and_then(|x| x.as_str())
+                        .map(|x| x.to_string());
+                    if let Some(c) = code.as_deref().filter(|c| c.starts_with("clippy::")) {
+                        *r.lints_by_level
+                            .entry(level.clone())
+                            .or_default()
+                            .entry(c.to_string())
+                            .or_insert(0) += 1;
+                    }
+                    match level.as_str() {
+                        "error" | "error: internal compiler error"
+                            if r.errors.len() < MAX_ITEMS =>
+                        {
+                            let (file, line, column) = primary_span(msg);
+                            r.errors.push(CompileDiagnostic {
+                                level,
+                                message: msg
+                                    .get("message")
+                                    .and_then(|x| x.as_str())
+                                    .unwrap_or("")
+                                    .to_string(),
+                                code,
+                                file,
+                                line,
+                                column,
+                            });
+                        }
+                        "warning" => r.warnings += 1,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        // libtest text output: `test path::name ... ok|FAILED|ignored`
+        if let Some(rest) = t.strip_prefix("test ") {
+            if let Some((name, outcome)) = rest.rsplit_once(" ... ") {
+                let name = name.trim().to_string();
+                match outcome.trim() {
+                    "ok" if r.tests_passed.len() < MAX_ITEMS => r.tests_passed.push(name),
+                    "FAILED" if r.tests_failed.len() < MAX_ITEMS => r.tests_failed.push(name),
+                    o if o.starts_with("ignored") => r.tests_ignored += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    r.stderr_tail = res
+        .stderr
+        .chars()
+        .skip(res.stderr.chars().count().saturating_sub(2000))
+        .collect();
+
+    let compiled = r.build_finished.unwrap_or(res.ok) && r.errors.is_empty();
+    r.success = match subcommand {
+        "test" => compiled && res.ok && r.tests_failed.is_empty(),
+        "clippy" => compiled && !r.lints_by_level.contains_key("error"),
+        _ => compiled && res.ok,
+    };
+    r
+}
+
+/// Run a cargo.* goal; returns `None` for goal ids outside the family.
+pub async fn handle(
+    goal_id: &str,
+    inputs: &Value,
+    policy: &Policy,
+    defaults: &CargoPolicy,
+) -> Result<Option<CargoReport>> {
+    let Some(sub) = subcommand_for(goal_id) else {
+        return Ok(None);
+    };
+    let cmd = build_command(sub, inputs, defaults)?;
+    let res = executor::execute(executor::Action::Cli(cmd.clone()), policy).await?;
+    Ok(Some(parse_output(sub, &cmd, &res)))
+}
@@ -0,0 +1,486 @@
+//! reports.daily: digest of the last `window` (default 24h) of receipts for people who don't
+//! want to click through run URLs — success rates per goal, failures with receipt links, cost,
+//! and notable meta2 proposals — written as `digest.md` / `digest.html` / `digest.json` under
+//! `runs/reports/daily/<run_id>/`, optionally pushed through the notify webhook.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use one_engine::{atomic, storage};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncBufReadExt;
+
+use crate::engine::urls::url_for;
+use crate::integrations::{notify, run_index, telemetry};
+
+const MAX_FAILURES: usize = 50;
+const MAX_PROPOSALS: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GoalStats {
+    pub goal_id: String,
+    pub runs: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub success_rate: Option<f32>,
+    pub latency_p50_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedRun {
+    pub run_id: String,
+    pub goal_id: String,
+    pub ts: String,
+    pub error: Option<String>,
+    pub receipt_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CostStats {
+    /// Sum of `cost` on telemetry events in the window.
+    pub telemetry_cost: f64,
+    /// Sum of `cost` / `tokens` on nstar receipts in the window.
+    pub nstar_cost: f64,
+    pub nstar_tokens: u64,
+    /// Total wall-clock across finished runs.
+    pub run_seconds: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Proposal {
+    pub run_id: String,
+    pub goal_id: String,
+    pub ts: String,
+    pub proposal: Value,
+    pub receipt_url: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DailyDigest {
+    pub generated_at: String,
+    pub window: String,
+    pub since: String,
+    pub total_runs: usize,
+    pub finished: usize,
+    pub succeeded: usize,
+    pub success_rate: Option<f32>,
+    pub goals: Vec<GoalStats>,
+    pub failures: Vec<FailedRun>,
+    /// Failures beyond `MAX_FAILURES` that were counted but not listed.
+    pub failures_omitted: usize,
+    pub cost: CostStats,
+    pub meta2_proposals: Vec<Proposal>,
+}
+
+pub struct DailyReportResult {
+    pub digest: DailyDigest,
+    pub out_dir: PathBuf,
+    /// `Some(Ok(status))` / `Some(Err(reason))` when a notification was attempted.
+    pub notified: Option<std::result::Result<u16, String>>,
+}
+
+fn rate(ok: usize, total: usize) -> Option<f32> {
+    (total > 0).then(|| ok as f32 / total as f32)
+}
+
+fn receipt_url(run_id: &str) -> String {
+    url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id))
+}
+
+fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn pct(r: Option<f32>) -> String {
+    r.map(|r| format!("{:.0}%", r * 100.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+async fn failure_reason(root: &Path, run_id: &str) -> Option<String> {
+    let raw = tokio::fs::read_to_string(
+        root.join("runs/receipts")
+            .join(run_id)
+            .join("response.json"),
+    )
+    .await
+    .ok()?;
+    let v: Value = serde_json::from_str(&raw).ok()?;
+    let e = v.get("manifest")?.get("evidence")?;
+    let msg = e
+        .get("error")
+        .or_else(|| e.get("stderr"))
+        .and_then(|x| x.as_str())?
+        .trim();
+    let first = msg.lines().next().unwrap_or("").trim();
+    (!first.is_empty()).then(|| first.chars().take(200).collect())
+}
+
+async fn nstar_costs(since: DateTime<Utc>) -> (f64, u64) {
+    let path =
+        std::env::var("NSTAR_RECEIPTS").unwrap_or_else(|_| "trace/receipts.jsonl".to_string());
+    let Ok(f) = tokio::fs::File::open(&path).await else {
+        return (0.0, 0);
+    };
+    let (mut cost, mut tokens) = (0.0, 0);
+    let mut lines = tokio::io::BufReader::new(f).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(v) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let ts = v
+            .get("ts")
+            .and_then(|x| x.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok());
+        if ts.map(|t| t.with_timezone(&Utc) < since).unwrap_or(true) {
+            continue;
+        }
+        cost += v.get("cost").and_then(|x| x.as_f64()).unwrap_or(0.0);
+        tokens += v.get("tokens").and_then(|x| x.as_u64()).unwrap_or(0);
+    }
+    (cost, tokens)
+}
+
+async fn build(window: &str, root: &Path) -> Result<DailyDigest> {
+    let span =
+        run_index::parse_window(window).ok_or_else(|| anyhow!("invalid window {:?}", window))?;
+    let now = Utc::now();
+    let since = now - span;
+    // Skip this digest's own earlier runs so they don't pad the numbers.
+    let records: Vec<run_index::RunRecord> = run_index::scan(Some(since))
+        .await
+        .into_iter()
+        .filter(|r| r.goal_id != "reports.daily")
+        .collect();
+
+    let mut per_goal: BTreeMap<String, (usize, usize, usize, Vec<u64>)> = BTreeMap::new();
+    let mut failures = Vec::new();
+    let mut failures_omitted = 0;
+    let mut proposals = Vec::new();
+    let mut run_ms: u64 = 0;
+    for r in &records {
+        let g = per_goal.entry(r.goal_id.clone()).or_default();
+        g.0 += 1;
+        match r.success {
+            Some(true) => g.1 += 1,
+            Some(false) => {
+                g.2 += 1;
+                if failures.len() < MAX_FAILURES {
+                    failures.push(FailedRun {
+                        run_id: r.run_id.clone(),
+                        goal_id: r.goal_id.clone(),
+                        ts: r.ts.to_rfc3339(),
+                        error: failure_reason(root, &r.run_id).await,
+                        receipt_url: receipt_url(&r.run_id),
+                    });
+                } else {
+                    failures_omitted += 1;
+                }
+            }
+            None => {}
+        }
+        if let Some(ms) = r.latency_ms {
+            g.3.push(ms);
+            if r.success.is_some() {
+                run_ms += ms;
+            }
+        }
+        if let Some(p) = r.meta2_proposal.as_ref() {
+            if proposals.len() < MAX_PROPOSALS {
+                proposals.push(Proposal {
+                    run_id: r.run_id.clone(),
+                    goal_id: r.goal_id.clone(),
+                    ts: r.ts.to_rfc3339(),
+                    proposal: p.clone(),
+                    receipt_url: receipt_url(&r.run_id),
+                });
+            }
+        }
+    }
+
+    let mut goals: Vec<GoalStats> = per_goal
+        .into_iter()
+        .map(|(goal_id, (runs, ok, failed, mut lat))| {
+            lat.sort_unstable();
+            GoalStats {
+                goal_id,
+                runs,
+                succeeded: ok,
+                failed,
+                success_rate: rate(ok, ok + failed),
+                latency_p50_ms: lat.get(lat.len() / 2).copied(),
+            }
+        })
+        .collect();
+    goals.sort_by(|a, b| b.failed.cmp(&a.failed).then_with(|| b.runs.cmp(&a.runs)));
+
+    let telemetry_cost: f64 = telemetry::query(&telemetry::TelemetryFilter {
+        since: Some(since),
+        limit: 100_000,
+        ..Default::default()
+    })
+    .await
+    .iter()
+    .filter_map(|e| e.cost)
+    .map(f64::from)
+    .sum();
+    let (nstar_cost, nstar_tokens) = nstar_costs(since).await;
+
+    let finished: usize = goals.iter().map(|g| g.succeeded + g.failed).sum();
+    let succeeded: usize = goals.iter().map(|g| g.succeeded).sum();
+    Ok(DailyDigest {
+        generated_at: now.to_rfc3339(),
+        window: window.to_string(),
+        since: since.to_rfc3339(),
+        total_runs: records.len(),
+        finished,
+        succeeded,
+        success_rate: rate(succeeded, finished),
+        goals,
+        failures,
+        failures_omitted,
+        cost: CostStats {
+            telemetry_cost,
+            nstar_cost,
+            nstar_tokens,
+            run_seconds: run_ms as f64 / 1000.0,
+        },
+        meta2_proposals: proposals,
+    })
+}
+
+fn proposal_summary(p: &Value) -> String {
+    let s = p
+        .get("summary")
+        .or_else(|| p.get("title"))
+        .or_else(|| p.get("reason"))
+        .and_then(|x| x.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| p.to_string());
+    s.chars().take(160).collect()
+}
+
+fn render_markdown(d: &DailyDigest) -> String {
+    let mut md = String::new();
+    md.push_str(&format!("# Daily digest ({})\n\n", d.window));
+    md.push_str(&format!(
+        "Generated {} · since {}\n\n",
+        d.generated_at, d.since
+    ));
+    md.push_str(&format!(
+        "- runs: {} ({} finished, {} succeeded, success rate {})\n",
+        d.total_runs,
+        d.finished,
+        d.succeeded,
+        pct(d.success_rate)
+    ));
+    md.push_str(&format!(
+        "- failures: {}\n",
+        d.failures.len() + d.failures_omitted
+    ));
+    md.push_str(&format!(
+        "- cost: telemetry {:.2} · nstar {:.2} ({} tokens) · {:.0}s of run time\n",
+        d.cost.telemetry_cost, d.cost.nstar_cost, d.cost.nstar_tokens, d.cost.run_seconds
+    ));
+
+    md.push_str("\n## Success rate by goal\n\n");
+    if d.goals.is_empty() {
+        md.push_str("_No runs in this window._\n");
+    } else {
+        md.push_str("| goal | runs | ok | failed | success | p50 |\n|---|---|---|---|---|---|\n");
+        for g in &d.goals {
+            md.push_str(&format!(
+                "| `{}` | {} | {} | {} | {} | {} |\n",
+                g.goal_id,
+                g.runs,
+                g.succeeded,
+                g.failed,
+                pct(g.success_rate),
+                g.latency_p50_ms
+                    .map(|ms| format!("{ms} ms"))
+                    .unwrap_or_else(|| "-".to_string())
+            ));
+        }
+    }
+
+    md.push_str("\n## Failures\n\n");
+    if d.failures.is_empty() {
+        md.push_str("_None._\n");
+    }
+    for f in &d.failures {
+        md.push_str(&format!(
+            "- `{}` [{}]({}) at {}{}\n",
+            f.goal_id,
+            f.run_id,
+            f.receipt_url,
+            f.ts,
+            f.error
+                .as_deref()
+                .map(|e| format!(": {}", e))
+                .unwrap_or_default()
+        ));
+    }
+    if d.failures_omitted > 0 {
+        md.push_str(&format!("- … and {} more\n", d.failures_omitted));
+    }
+
+    md.push_str("\n## Meta2 proposals\n\n");
+    if d.meta2_proposals.is_empty() {
+        md.push_str("_None._\n");
+    }
+    for p in &d.meta2_proposals {
+        md.push_str(&format!(
+            "- `{}` [{}]({}): {}\n",
+            p.goal_id,
+            p.run_id,
+            p.receipt_url,
+            proposal_summary(&p.proposal)
+        ));
+    }
+    md
+}
+
+fn render_html(d: &DailyDigest) -> String {
+    let mut goals = String::new();
+    for g in &d.goals {
+        goals.push_str(&format!(
+            "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            html_escape(&g.goal_id),
+            g.runs,
+            g.succeeded,
+            g.failed,
+            pct(g.success_rate),
+            g.latency_p50_ms.map(|ms| format!("{ms} ms")).unwrap_or_else(|| "-".to_string())
+        ));
+    }
+    let mut failures = String::new();
+    for f in &d.failures {
+        failures.push_str(&format!(
+            "<li><code>{}</code> <a href=\"{}\">{}</a> <span class=\"muted\">{}</span> {}</li>",
+            html_escape(&f.goal_id),
+            html_escape(&f.receipt_url),
+            html_escape(&f.run_id),
+            html_escape(&f.ts),
+            html_escape(f.error.as_deref().unwrap_or(""))
+        ));
+    }
+    if d.failures_omitted > 0 {
+        failures.push_str(&format!(
+            "<li class=\"muted\">… and {} more</li>",
+            d.failures_omitted
+        ));
+    }
+    let mut proposals = String::new();
+    for p in &d.meta2_proposals {
+        proposals.push_str(&format!(
+            "<li><code>{}</code> <a href=\"{}\">{}</a> {}</li>",
+            html_escape(&p.goal_id),
+            html_escape(&p.receipt_url),
+            html_escape(&p.run_id),
+            html_escape(&proposal_summary(&p.proposal))
+        ));
+    }
+    let none = "<li class=\"muted\">None.</li>";
+    format!(
+        r#"<!doctype html><html><head><meta charset="utf-8"><title>Daily digest</title>
+<meta name="viewport" content="width=device-width,initial-scale=1">
+<style>body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px;max-width:960px}} a{{color:#1f6feb;text-decoration:none}} code{{background:#f6f8fa;padding:2px 6px;border-radius:6px}} .muted{{color:#57606a;font-size:12px}} table{{border-collapse:collapse}} td,th{{border-bottom:1px solid #eee;padding:6px 10px;text-align:left}} .kpi{{display:inline-block;margin-right:24px}} .kpi b{{font-size:22px;display:block}}</style>
+</head><body><h1>Daily digest ({window})</h1>
+<p class="muted">Generated {generated} · since {since} · <a href="digest.md">digest.md</a> · <a href="digest.json">digest.json</a></p>
+<p><span class="kpi"><b>{total}</b>runs</span><span class="kpi"><b>{rate}</b>success</span><span class="kpi"><b>{failed}</b>failures</span><span class="kpi"><b>{cost:.2}</b>cost (telemetry + nstar)</span><span class="kpi"><b>{secs:.0}s</b>run time</span></p>
+<h2>Success rate by goal</h2>
+<table><tr><th>goal</th><th>runs</th><th>ok</th><th>failed</th><th>success</th><th>p50</th></tr>{goals}</table>
+<h2>Failures</h2><ul>{failures}</ul>
+<h2>Meta2 proposals</h2><ul>{proposals}</ul>
+</body></html>"#,
+        window = html_escape(&d.window),
+        generated = html_escape(&d.generated_at),
+        since = html_escape(&d.since),
+        total = d.total_runs,
+        rate = pct(d.success_rate),
+        failed = d.failures.len() + d.failures_omitted,
+        cost = d.cost.telemetry_cost + d.cost.nstar_cost,
+        secs = d.cost.run_seconds,
+        goals = goals,
+        failures = if failures.is_empty() {
+            none.to_string()
+        } else {
+            failures
+        },
+        proposals = if proposals.is_empty() {
+            none.to_string()
+        } else {
+            proposals
+        },
+    )
+}
+
+/// Build the digest for `window`, write it under `runs/reports/daily/<run_id>/`, and notify
+/// when `notify` is set.
+pub async fn generate(
+    run_id: &str,
+    window: &str,
+    notify_webhook: bool,
+    root: &Path,
+) -> Result<DailyReportResult> {
+    if !is_safe_segment(run_id) {
+        return Err(anyhow!("invalid run id {:?}", run_id));
+    }
+    let digest = build(window, root).await?;
+    let out_dir = root.join("runs").join("reports").join("daily").join(run_id);
+    storage::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
+    let md = render_markdown(&digest);
+    atomic::write_async(out_dir.join("digest.md"), &md)
+        .await
+        .context("write digest.md")?;
+    atomic::write_async(out_dir.join("digest.html"), render_html(&digest))
+        .await
+        .context("write digest.html")?;
+    atomic::write_async(
+        out_dir.join("digest.json"),
+        serde_json::to_string_pretty(&digest).unwrap_or_default(),
+    )
+    .await
+    .context("write digest.json")?;
+
+    let notified = if notify_webhook {
+        let n = notify::Notification {
+            subject: format!(
+                "Daily digest: {} runs, {} success, {} failures",
+                digest.total_runs,
+                pct(digest.success_rate),
+                digest.failures.len() + digest.failures_omitted
+            ),
+            text: md,
+            // Webhook readers are outside the server, so prefer an absolute link.
+            url: Some(format!(
+                "{}{}",
+                std::env::var("ONE_ENGINE_BASE_URL")
+                    .unwrap_or_default()
+                    .trim_end_matches('/'),
+                url_for(&format!("/runs/reports/daily/{}/digest.html", run_id))
+            )),
+        };
+        Some(notify::send(&n).await.map_err(|e| e.to_string()))
+    } else {
+        None
+    };
+    Ok(DailyReportResult {
+        digest,
+        out_dir,
+        notified,
+    })
+}
@@ -0,0 +1,454 @@
+//! Goals declared in YAML: each `config/goals.d/*.yaml` file (directory overridable with
+//! `ONE_ENGINE_GOALS_DIR`) adds one goal without rebuilding the engine.
+//!
+//! ```yaml
+//! id: lint.shellcheck
+//! description: shellcheck every script under a directory
+//! inputs:
+//!   dir: {type: path, required: true}
+//!   severity: {type: string, default: warning, one_of: [error, warning, info, style]}
+//! workdir: "{{dir}}"                 # relative to META3_ROOT; default META3_ROOT itself
+//! command: "shellcheck --severity={{severity}} --format=gcc *.sh > shellcheck.txt"
+//! artifacts: ["shellcheck.txt"]      # must exist afterwards (relative to workdir)
+//! verify:
+//!   stdout: []                       # regexes the output must match
+//!   not_stdout: ["error:"]           # regexes it must not match
+//! ```
+//!
+//! `{{name}}` in `command` becomes the input's value quoted as one shell argument, so inputs
+//! cannot add commands or split into extra words. Inputs are checked against their `type`
+//! (`string`, `path`, `integer`, `number`, `boolean`) first: `path` values must be relative
+//! without `..`, `pattern` must match a whole string and `one_of` lists the allowed values.
+//! `workdir` and `artifacts` interpolate the checked values unquoted, and must resolve
+//! (following symlinks) inside `META3_ROOT` and the workdir respectively; an artifact outside
+//! the workdir counts as missing.
+//!
+//! Files are read once at startup. A file that does not parse, uses an undeclared
+//! placeholder, has a bad regex or reuses a built-in goal id is skipped with a warning.
+
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::engine::catalog;
+use crate::engine::executor::{self, Action};
+use crate::engine::platform::{self, Shell};
+use crate::engine::types::Policy;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputType {
+    #[default]
+    String,
+    Path,
+    Integer,
+    Number,
+    Boolean,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct InputSpec {
+    #[serde(default, rename = "type")]
+    pub kind: InputType,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub one_of: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VerifySpec {
+    #[serde(default)]
+    pub stdout: Vec<String>,
+    #[serde(default)]
+    pub not_stdout: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoalSpec {
+    pub id: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub inputs: BTreeMap<String, InputSpec>,
+    pub command: String,
+    #[serde(default)]
+    pub workdir: Option<String>,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    #[serde(default)]
+    pub verify: VerifySpec,
+    /// The file the goal came from.
+    #[serde(skip)]
+    pub source: PathBuf,
+    /// `verify` compiled by `check`, each with whether the output must match it.
+    #[serde(skip)]
+    verify_res: Vec<(Regex, bool)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArtifactCheck {
+    pub path: String,
+    pub exists: bool,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternCheck {
+    pub pattern: String,
+    /// `true` for `verify.stdout`, `false` for `verify.not_stdout`.
+    pub expect_match: bool,
+    pub passed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeclaredRun {
+    pub source: String,
+    pub command: String,
+    pub inputs: BTreeMap<String, String>,
+    pub exit_ok: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub artifacts: Vec<ArtifactCheck>,
+    pub checks: Vec<PatternCheck>,
+    pub success: bool,
+}
+
+fn placeholder() -> &'static Regex {
+    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z0-9_]+)\s*\}\}").unwrap());
+    &RE
+}
+
+/// `template` with each `{{name}}` replaced by `render(value)`.
+fn interpolate(
+    template: &str,
+    values: &BTreeMap<String, String>,
+    render: impl Fn(&str) -> String,
+) -> Result<String> {
+    let mut missing = None;
+    let out = placeholder().replace_all(template, |c: &regex::Captures| match values.get(&c[1]) {
+        Some(v) => render(v),
+        None => {
+            missing.get_or_insert_with(|| c[1].to_string());
+            String::new()
+        }
+    });
+    match missing {
+        Some(name) => bail!("template uses undeclared input {:?}", name),
+        None => Ok(out.into_owned()),
+    }
+}
+
+impl GoalSpec {
+    /// Validate the spec and compile its `verify` regexes.
+    fn check(&mut self) -> Result<()> {
+        if self.id.trim().is_empty() || self.id.contains(char::is_whitespace) {
+            bail!("goal id {:?} is empty or contains whitespace", self.id);
+        }
+        if catalog::is_builtin(&catalog::resolve(&self.id).goal_id) {
+            bail!("goal id {:?} is a built-in goal", self.id);
+        }
+        let declared: BTreeMap<String, String> = self
+            .inputs
+            .keys()
+            .map(|k| (k.clone(), String::new()))
+            .collect();
+        interpolate(&self.command, &declared, |v| v.to_string())?;
+        for t in self.workdir.iter().chain(&self.artifacts) {
+            interpolate(t, &declared, |v| v.to_string())?;
+        }
+        for (name, input) in &self.inputs {
+            if let Some(p) = &input.pattern {
+                Regex::new(p).with_context(|| format!("input {} pattern", name))?;
+            }
+        }
+        let must = self.verify.stdout.iter().map(|p| (p, true));
+        let must_not = self.verify.not_stdout.iter().map(|p| (p, false));
+        self.verify_res = must
+            .chain(must_not)
+            .map(|(p, expect_match)| {
+                Regex::new(p)
+                    .map(|re| (re, expect_match))
+                    .with_context(|| format!("verify regex {:?}", p))
+            })
+            .collect::<Result<_>>()?;
+        Ok(())
+    }
+
+    /// The run's input values as strings, after type and constraint checks.
+    pub fn bind(&self, inputs: &Value) -> Result<BTreeMap<String, String>> {
+        let mut out = BTreeMap::new();
+        for (name, spec) in &self.inputs {
+            let v = match inputs.get(name).filter(|v| !v.is_null()) {
+                Some(v) => v.clone(),
+                None => match &spec.default {
+                    Some(d) => d.clone(),
+                    None if spec.required => bail!("{} is required", name),
+                    None => continue,
+                },
+            };
+            let s = match (spec.kind, &v) {
+                (InputType::String | InputType::Path, Value::String(s)) => s.clone(),
+                (InputType::Integer, Value::Number(n)) if n.is_i64() || n.is_u64() => n.to_string(),
+                (InputType::Integer, Value::String(s)) if s.parse::<i64>().is_ok() => s.clone(),
+                (InputType::Number, Value::Number(n)) => n.to_string(),
+                (InputType::Number, Value::String(s)) if s.parse::<f64>().is_ok() => s.clone(),
+                (InputType::Boolean, Value::Bool(b)) => b.to_string(),
+                (InputType::Boolean, Value::String(s)) if s == "true" || s == "false" => s.clone(),
+                (kind, _) => bail!("{} must be of type {:?}", name, kind),
+            };
+            if spec.kind == InputType::Path {
+                let p = Path::new(&s);
+                if s.is_empty()
+                    || p.components()
+                        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+                {
+                    bail!("{} must be a relative path without '..'", name);
+                }
+            }
+            if let Some(pattern) = &spec.pattern {
+                let re = Regex::new(&format!("^(?:{})$", pattern))?;
+                if !re.is_match(&s) {
+                    bail!("{} does not match {}", name, pattern);
+                }
+            }
+            if !spec.one_of.is_empty() && !spec.one_of.contains(&s) {
+                bail!("{} must be one of {}", name, spec.one_of.join(", "));
+            }
+            out.insert(name.clone(), s);
+        }
+        Ok(out)
+    }
+
+    /// The shell command for `values`, each placeholder quoted for `sh`.
+    pub fn render(&self, sh: Shell, values: &BTreeMap<String, String>) -> Result<String> {
+        interpolate(&self.command, values, |v| sh.quote(v))
+    }
+}
+
+fn goals_dir() -> PathBuf {
+    PathBuf::from(
+        std::env::var("ONE_ENGINE_GOALS_DIR").unwrap_or_else(|_| "config/goals.d".to_string()),
+    )
+}
+
+fn load_dir(dir: &Path) -> Vec<GoalSpec> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yaml" | "yml")))
+        .collect();
+    files.sort();
+    let mut specs: Vec<GoalSpec> = Vec::new();
+    for path in files {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|raw| Ok(serde_yaml::from_str::<GoalSpec>(&raw)?))
+            .and_then(|mut spec| spec.check().map(|_| spec));
+        match parsed {
+            Ok(spec) if specs.iter().any(|s| s.id == spec.id) => {
+                tracing::warn!(
+                    "skipping {}: goal {} declared twice",
+                    path.display(),
+                    spec.id
+                );
+            }
+            Ok(mut spec) => {
+                spec.source = path;
+                specs.push(spec);
+            }
+            Err(e) => tracing::warn!("skipping goal file {}: {:#}", path.display(), e),
+        }
+    }
+    specs
+}
+
+static SPECS: Lazy<Vec<GoalSpec>> = Lazy::new(|| load_dir(&goals_dir()));
+
+/// Read the goal files now (at startup) rather than on the first run; returns the count.
+pub fn load() -> usize {
+    SPECS.len()
+}
+
+pub fn specs() -> &'static [GoalSpec] {
+    &SPECS
+}
+
+/// The declared goal `goal_id` names, if any (a `user:<id>.` prefix is ignored).
+pub fn find(goal_id: &str) -> Option<&'static GoalSpec> {
+    let bare = goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.'))
+        .map_or(goal_id, |(_, g)| g);
+    SPECS.iter().find(|s| s.id == bare)
+}
+
+fn meta3_root() -> PathBuf {
+    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()))
+}
+
+/// `base.join(rel)` with symlinks resolved, if it exists and stays under `base` (canonical).
+fn resolve_within(base: &Path, rel: &str) -> Option<PathBuf> {
+    let p = base.join(rel).canonicalize().ok()?;
+    p.starts_with(base).then_some(p)
+}
+
+/// Run `spec` with `inputs`: bind, render, execute, then check artifacts and output.
+pub async fn run(spec: &GoalSpec, inputs: &Value, policy: &Policy) -> Result<DeclaredRun> {
+    let values = spec
+        .bind(inputs)
+        .map_err(|e| anyhow!("{}: {}", spec.id, e))?;
+    let sh = platform::shell();
+    let command = spec.render(sh, &values)?;
+    let root = meta3_root()
+        .canonicalize()
+        .context("META3_ROOT is not readable")?;
+    let dir = match &spec.workdir {
+        Some(w) => {
+            let rel = interpolate(w, &values, |v| v.to_string())?;
+            resolve_within(&root, &rel).ok_or_else(|| {
+                anyhow!(
+                    "{}: workdir {:?} is missing or outside META3_ROOT",
+                    spec.id,
+                    rel
+                )
+            })?
+        }
+        None => root,
+    };
+    let res = executor::execute(
+        Action::Cli(sh.in_dir(&dir.display().to_string(), &command)),
+        policy,
+    )
+    .await?;
+
+    let mut artifacts = Vec::new();
+    for a in &spec.artifacts {
+        let rel = interpolate(a, &values, |v| v.to_string())?;
+        let meta = resolve_within(&dir, &rel).and_then(|p| std::fs::metadata(p).ok());
+        artifacts.push(ArtifactCheck {
+            path: dir.join(&rel).display().to_string(),
+            exists: meta.as_ref().is_some_and(|m| m.is_file()),
+            bytes: meta.map(|m| m.len()).unwrap_or(0),
+        });
+    }
+    let checks: Vec<PatternCheck> = spec
+        .verify_res
+        .iter()
+        .map(|(re, expect_match)| PatternCheck {
+            pattern: re.as_str().to_string(),
+            expect_match: *expect_match,
+            passed: re.is_match(&res.stdout) == *expect_match,
+        })
+        .collect();
+    let success = res.ok && artifacts.iter().all(|a| a.exists) && checks.iter().all(|c| c.passed);
+    Ok(DeclaredRun {
+        source: spec.source.display().to_string(),
+        command,
+        inputs: values,
+        exit_ok: res.ok,
+        stdout: res.stdout,
+        stderr: res.stderr,
+        artifacts,
+        checks,
+        success,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inputs_are_checked_and_quoted_as_single_arguments() {
+        let mut spec: GoalSpec = serde_yaml::from_str(
+            r#"
+id: lint.sh
+inputs:
+  dir: {type: path, required: true}
+  level: {default: warning, one_of: [error, warning]}
+  jobs: {type: integer, default: 2}
+command: "lint -j {{jobs}} --level={{ level }} {{dir}}"
+"#,
+        )
+        .unwrap();
+        spec.check().unwrap();
+
+        let values = spec
+            .bind(&serde_json::json!({"dir": "src/my dir; rm -rf ~"}))
+            .unwrap();
+        assert_eq!(
+            spec.render(Shell::Bash, &values).unwrap(),
+            "lint -j 2 --level=warning 'src/my dir; rm -rf ~'"
+        );
+        assert!(spec.bind(&serde_json::json!({"dir": "../etc"})).is_err());
+        assert!(spec
+            .bind(&serde_json::json!({"dir": "src", "level": "info"}))
+            .is_err());
+        assert!(spec
+            .bind(&serde_json::json!({"dir": "src", "jobs": "two"}))
+            .is_err());
+
+        let mut bad = GoalSpec {
+            command: "lint {{missing}}".into(),
+            ..spec
+        };
+        assert!(bad.check().is_err());
+    }
+
+    #[test]
+    fn check_compiles_verify_regexes_once() {
+        let mut spec: GoalSpec = serde_yaml::from_str(
+            r#"
+id: lint.verify
+command: "lint"
+verify: {stdout: ["^ok"], not_stdout: ["error:"]}
+"#,
+        )
+        .unwrap();
+        spec.check().unwrap();
+        let compiled: Vec<(&str, bool)> = spec
+            .verify_res
+            .iter()
+            .map(|(re, m)| (re.as_str(), *m))
+            .collect();
+        assert_eq!(compiled, [("^ok", true), ("error:", false)]);
+
+        spec.verify.not_stdout = vec!["(".into()];
+        assert!(spec.check().is_err());
+    }
+
+    #[test]
+    fn workdir_and_artifacts_stay_under_their_base() {
+        let base = std::env::temp_dir()
+            .join("one-engine-declared")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(base.join("work")).unwrap();
+        std::fs::write(base.join("outside.txt"), "x").unwrap();
+        std::fs::write(base.join("work/out.txt"), "x").unwrap();
+        let base = base.canonicalize().unwrap();
+        let work = base.join("work");
+
+        assert_eq!(resolve_within(&base, "work"), Some(work.clone()));
+        assert_eq!(resolve_within(&work, "out.txt"), Some(work.join("out.txt")));
+        assert_eq!(resolve_within(&work, "../outside.txt"), None);
+        assert_eq!(resolve_within(&work, "missing.txt"), None);
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(base.join("outside.txt"), work.join("link.txt")).unwrap();
+            assert_eq!(resolve_within(&work, "link.txt"), None);
+        }
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -1,9 +1,317 @@
 use anyhow::Result;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use std::future::Future;
+use std::pin::Pin;
 
+use crate::engine::kernel::{ExtendedBits, Meta2Proposal};
+use crate::engine::prompts;
 use crate::engine::router;
+use crate::engine::tokens::{self, BudgetReport, Keep};
+use crate::engine::types::{Manifest, Policy, MANIFEST_SCHEMA_VERSION};
 
-pub async fn handle(inputs: &Value) -> Result<Value> {
+/// Tool calls run per model turn; the rest of a turn's calls are answered with an error.
+pub const MAX_TOOL_CALLS_PER_ROUND: usize = 8;
+
+/// A goal offered to the model as a function. The function name is the goal id with `.`
+/// replaced by `_` (function names may not contain dots).
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolGoal {
+    pub goal_id: String,
+    pub description: String,
+    /// JSON Schema for the goal inputs; any object when unset.
+    #[serde(default)]
+    pub parameters: Option<Value>,
+}
+
+impl ToolGoal {
+    pub fn name(&self) -> String {
+        self.goal_id.replace('.', "_")
+    }
+
+    pub fn input_schema(&self) -> Value {
+        self.parameters.clone().unwrap_or_else(|| {
+            json!({
+                "type": "object",
+                "additionalProperties": true
+            })
+        })
+    }
+
+    fn definition(&self) -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name(),
+                "description": self.description,
+                "parameters": self.input_schema()
+            }
+        })
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesChatTools {
+    #[serde(default)]
+    chat_tools: Option<Vec<ToolGoal>>,
+}
+
+fn default_tools() -> Vec<ToolGoal> {
+    let tool = |goal_id: &str, description: &str, parameters: Option<Value>| ToolGoal {
+        goal_id: goal_id.to_string(),
+        description: description.to_string(),
+        parameters,
+    };
+    vec![
+        tool(
+            "wiki.generate",
+            "Regenerate the wiki from threads and receipts.",
+            None,
+        ),
+        tool(
+            "ruliad.kernel",
+            "Expand rewrite rules from a seed into a causal graph and render it.",
+            Some(json!({
+                "type": "object",
+                "properties": {
+                    "seed": {"type": "string"},
+                    "rules": {"type": "array", "items": {"type": "array", "items": {"type": "string"}}},
+                    "depth": {"type": "integer"},
+                    "mode": {"type": "string", "enum": ["simulated", "real"]}
+                }
+            })),
+        ),
+        tool(
+            "threads.report",
+            "Summarize a chat thread into a report.",
+            Some(json!({
+                "type": "object",
+                "properties": {
+                    "user_id": {"type": "string"},
+                    "thread": {"type": "string"}
+                }
+            })),
+        ),
+        tool(
+            "graphs.receipts",
+            "Render the graph of recent runs and their receipts.",
+            None,
+        ),
+        tool(
+            "graphs.system",
+            "Render the system architecture graph.",
+            None,
+        ),
+    ]
+}
+
+/// Goals exposed as tools: `chat_tools:` in policies.yaml, else a read-mostly default set.
+/// meta.omni itself is never offered.
+pub fn tool_catalog() -> Vec<ToolGoal> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let configured = match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesChatTools>(&raw) {
+            Ok(p) => p.chat_tools,
+            Err(e) => {
+                tracing::warn!("invalid {}: {}", path, e);
+                None
+            }
+        },
+        Err(_) => None,
+    };
+    configured
+        .unwrap_or_else(default_tools)
+        .into_iter()
+        .filter(|t| !t.goal_id.contains("meta.omni"))
+        .collect()
+}
+
+type RunOutcome = Result<(Manifest, ExtendedBits, Option<Meta2Proposal>)>;
+
+/// `engine::run` behind a box: a tool call runs a goal from inside a goal.
+fn run_boxed(
+    goal_id: String,
+    inputs: Value,
+    policy: Policy,
+) -> Pin<Box<dyn Future<Output = RunOutcome> + Send>> {
+    Box::pin(async move { crate::engine::run(&goal_id, inputs, &policy).await })
+}
+
+fn excerpt(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        format!("{}…", s.chars().take(max).collect::<String>())
+    }
+}
+
+/// Inputs of a tool call: the model's arguments, with `user_id` always the session's (dropped
+/// when there is none), so a call can't read another user's threads.
+fn tool_inputs(args: Value, user_id: Option<&str>, run_id: &str) -> Value {
+    let mut inputs = if args.is_object() { args } else { json!({}) };
+    let obj = inputs.as_object_mut().expect("object");
+    match user_id {
+        Some(u) => obj.insert("user_id".to_string(), json!(u)),
+        None => obj.remove("user_id"),
+    };
+    obj.insert("__run_id".to_string(), json!(run_id));
+    inputs
+}
+
+/// The answer to the `n`th call of a round once the round is over its cap.
+fn over_cap(call_id: &str, n: usize) -> Option<Value> {
+    (n >= MAX_TOOL_CALLS_PER_ROUND).then(|| {
+        json!({
+            "call_id": call_id,
+            "error": format!("not run: at most {} tool calls per turn", MAX_TOOL_CALLS_PER_ROUND)
+        })
+    })
+}
+
+/// Run one tool call as child run `run_id` and write its receipt. Returns what is fed back
+/// to the model (also kept in the parent's `tool_calls` evidence).
+async fn run_tool(
+    tool: &ToolGoal,
+    call_id: &str,
+    inputs: Value,
+    policy: &Policy,
+    parent: &str,
+    run_id: &str,
+) -> Value {
+    let (manifest, bits) =
+        match run_boxed(tool.goal_id.clone(), inputs.clone(), policy.clone()).await {
+            Ok((mut manifest, bits, _)) => {
+                manifest.run_id = run_id.to_string();
+                (manifest, bits)
+            }
+            Err(e) => {
+                let mut bits = ExtendedBits::init();
+                bits.e = 1.0;
+                let manifest = Manifest {
+                    run_id: run_id.to_string(),
+                    goal_id: tool.goal_id.clone(),
+                    deliverables: vec![],
+                    evidence: json!({"error": format!("{:#}", e), "actual_success": false}),
+                    bits: bits.clone().into(),
+                    schema_version: MANIFEST_SCHEMA_VERSION,
+                };
+                (manifest, bits)
+            }
+        };
+    let mut manifest = manifest;
+    if let Some(obj) = manifest.evidence.as_object_mut() {
+        obj.insert("parent_run_id".to_string(), json!(parent));
+        obj.insert("tool_call_id".to_string(), json!(call_id));
+    }
+    crate::engine::hooks::write_tool_call_receipt(run_id, &inputs, policy, &manifest, &bits.into())
+        .await;
+
+    let ev = &manifest.evidence;
+    let text = ev
+        .get("error")
+        .or_else(|| ev.get("reply"))
+        .or_else(|| ev.get("stdout"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    json!({
+        "call_id": call_id,
+        "goal_id": tool.goal_id,
+        "run_id": run_id,
+        "success": ev.get("actual_success").and_then(|v| v.as_bool()).unwrap_or(false),
+        "deliverables": manifest.deliverables,
+        "output": excerpt(text, 2000)
+    })
+}
+
+/// Function-calling loop: offer the catalog, run whatever the model calls, feed the results
+/// back, and stop offering tools after `policy.max_tool_iterations` rounds (each running at
+/// most [`MAX_TOOL_CALLS_PER_ROUND`] calls, as `user_id`). Every round is
+/// fitted to the model's context window, tool results cut to `tool_output_tokens`; earlier
+/// rounds are [`Keep::Round`], so their outputs are elided before the user turn is cut. Returns
+/// the final parsed reply, one entry per tool call and the last round's budget.
+async fn tool_loop(
+    mut messages: Vec<(Keep, Value)>,
+    policy: &Policy,
+    parent: &str,
+    user_id: Option<&str>,
+) -> Result<(Value, Vec<Value>, BudgetReport)> {
+    let model = router::model_name();
+    let tool_output_tokens = tokens::load_config().tool_output_tokens;
+    let catalog = if policy.max_tool_iterations > 0 {
+        tool_catalog()
+    } else {
+        Vec::new()
+    };
+    let tools: Vec<Value> = catalog.iter().map(|t| t.definition()).collect();
+    let mut calls: Vec<Value> = Vec::new();
+    let mut round = 0u32;
+    let first_round = messages.len();
+    loop {
+        let offer = round < policy.max_tool_iterations && !tools.is_empty();
+        let offered: &[Value] = if offer { &tools } else { &[] };
+        // Counting is CPU-bound over up to the whole loaded thread.
+        let (fitted, budget) = {
+            let (model, items) = (model.clone(), messages.clone());
+            tokio::task::spawn_blocking(move || tokens::fit(&model, items)).await?
+        };
+        let msg = router::chat_turn(&fitted, offered).await?;
+        let tool_calls = msg
+            .get("tool_calls")
+            .and_then(|v| v.as_array())
+            .filter(|a| !a.is_empty())
+            .cloned();
+        let Some(tool_calls) = tool_calls.filter(|_| offer) else {
+            return Ok((router::parse_content(&msg), calls, budget));
+        };
+        round += 1;
+        for (keep, _) in &mut messages[first_round..] {
+            *keep = Keep::Round;
+        }
+        messages.push((Keep::Always, msg));
+        for (n, call) in tool_calls.into_iter().enumerate() {
+            let call_id = call
+                .get("id")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let name = call
+                .pointer("/function/name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let args = call
+                .pointer("/function/arguments")
+                .and_then(|v| v.as_str())
+                .and_then(|s| serde_json::from_str::<Value>(s).ok())
+                .unwrap_or_else(|| json!({}));
+            let result = match (
+                over_cap(&call_id, n),
+                catalog.iter().find(|t| t.name() == name),
+            ) {
+                (Some(refused), _) => refused,
+                (None, Some(tool)) => {
+                    let run_id = format!("{}-t{}", parent, calls.len() + 1);
+                    let inputs = tool_inputs(args, user_id, &run_id);
+                    run_tool(tool, &call_id, inputs, policy, parent, &run_id).await
+                }
+                (None, None) => {
+                    json!({"call_id": call_id, "error": format!("unknown tool: {}", name)})
+                }
+            };
+            messages.push((
+                Keep::Always,
+                json!({
+                    "role": "tool",
+                    "tool_call_id": call_id,
+                    "content": tokens::truncate(&model, &result.to_string(), tool_output_tokens)
+                }),
+            ));
+            calls.push(result);
+        }
+    }
+}
+
+pub async fn handle(inputs: &Value, policy: &Policy) -> Result<Value> {
     let user_msg = inputs.get("message").and_then(|v| v.as_str()).unwrap_or("");
     let loop_mode = inputs
         .get("loop_mode")
@@ -31,10 +339,10 @@ pub async fn handle(inputs: &Value) -> Result<Value> {
     if !profile_name.is_empty() {
         let mut impact_url = None;
         let url_res = if profile_name.contains("Real") {
-            crate::nstar::execute_system_matrix().await
+            crate::engine::hooks::system_matrix().await
         } else {
             let rules_vec: Vec<(String, String)> = rules.iter().map(|(p, r)| (p.to_string(), r.to_string())).collect();
-            crate::nstar::execute_divine_ruliad(&seed.to_string(), rules_vec, 8).await
+            crate::engine::hooks::ruliad(seed, rules_vec, 8).await
         };
 
         let url_msg = match url_res {
@@ -73,13 +381,19 @@ pub async fn handle(inputs: &Value) -> Result<Value> {
     }
 
     // 2. Standard LLM Route
-    let persona = std::fs::read_to_string("prompts/META_OMNI.md").unwrap_or_else(|_| {
-        "You are One Engine, designed to reason about goals and build autonomy loops.".to_string()
-    });
+    let user_id = inputs.get("user_id").and_then(|v| v.as_str()).unwrap_or("");
+    let thread = inputs.get("thread").and_then(|v| v.as_str()).unwrap_or("");
+    let date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let prompt = prompts::render(
+        "meta_omni",
+        "You are One Engine, designed to reason about goals and build autonomy loops.",
+        &[("user", user_id), ("thread", thread), ("goal", "meta.omni"), ("date", date.as_str())],
+    );
+    let persona = prompt.text;
 
-    let mut messages = vec![json!({"role": "system", "content": persona})];
+    let mut messages = vec![(Keep::Always, json!({"role": "system", "content": persona}))];
     if loop_mode {
-        messages.push(json!({"role":"system","content":"LOOP MODE: Always include a runnable run_payload. If uncertain, default to {\"goal_id\":\"wiki.generate\",\"inputs\":{}}. Keep reply short and include what will run."}));
+        messages.push((Keep::Always, json!({"role":"system","content":"LOOP MODE: Always include a runnable run_payload. If uncertain, default to {\"goal_id\":\"wiki.generate\",\"inputs\":{}}. Keep reply short and include what will run."})));
     }
     if let Some(arr) = inputs.get("history").and_then(|v| v.as_array()) {
         for m in arr {
@@ -89,17 +403,29 @@ pub async fn handle(inputs: &Value) -> Result<Value> {
                 continue;
             }
             if role == "user" || role == "assistant" {
-                messages.push(json!({"role": role, "content": content}));
+                messages.push((Keep::History, json!({"role": role, "content": content})));
             } else if role == "system" || role == "tool" {
                 // Treat tool outputs / injected context as system messages so the LM can use them.
-                messages.push(json!({"role": "system", "content": content}));
+                // Run summaries and attached receipts outlast plain turns when trimming.
+                messages.push((Keep::Pinned, json!({"role": "system", "content": content})));
             }
         }
     }
-    messages.push(json!({"role": "user", "content": user_msg}));
+    messages.push((Keep::Always, json!({"role": "user", "content": user_msg})));
 
-    match router::chat_messages(messages).await {
-        Ok(mut response) => {
+    let parent = inputs
+        .get("__run_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("r-{}", uuid::Uuid::new_v4()));
+    let session_user = Some(user_id).filter(|u| !u.is_empty());
+    match tool_loop(messages, policy, &parent, session_user).await {
+        Ok((mut response, tool_calls, budget)) => {
+            if !tool_calls.is_empty() {
+                if let Some(obj) = response.as_object_mut() {
+                    obj.insert("tool_calls".to_string(), json!(tool_calls));
+                }
+            }
             if response.get("intent").is_none() {
                 response
                     .as_object_mut()
@@ -171,6 +497,10 @@ pub async fn handle(inputs: &Value) -> Result<Value> {
                     }
                 }
             }
+            if let Some(obj) = response.as_object_mut() {
+                obj.insert("prompt".to_string(), json!(prompt.record));
+                obj.insert("context_budget".to_string(), json!(budget));
+            }
             Ok(response)
         }
         Err(err) => {
@@ -193,10 +523,41 @@ pub async fn handle(inputs: &Value) -> Result<Value> {
                 "reply": reply,
                 "run_payload": run_payload,
                 "patch": Value::Null,
-                "explanation": {"assumptions": ["router unavailable"], "evidence": [err.to_string()]}
+                "explanation": {"assumptions": ["router unavailable"], "evidence": [err.to_string()]},
+                "prompt": prompt.record
             });
             
             Ok(resp)
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tool_calls_run_as_the_session_user_and_stop_at_the_cap() {
+        let args = json!({"user_id": "someone-else", "thread": "t1"});
+        let inputs = tool_inputs(args.clone(), Some("demo"), "r-1-t1");
+        assert_eq!(
+            inputs,
+            json!({"user_id": "demo", "thread": "t1", "__run_id": "r-1-t1"})
+        );
+        let anonymous = tool_inputs(args, None, "r-1-t2");
+        assert!(anonymous.get("user_id").is_none());
+        assert_eq!(tool_inputs(json!("x"), None, "r")["__run_id"], "r");
+
+        assert!(over_cap("c1", MAX_TOOL_CALLS_PER_ROUND - 1).is_none());
+        let refused = over_cap("c9", MAX_TOOL_CALLS_PER_ROUND).unwrap();
+        assert_eq!(refused["call_id"], "c9");
+        assert!(refused["error"].as_str().unwrap().starts_with("not run"));
+    }
+
+    #[test]
+    fn meta_omni_is_never_a_tool() {
+        assert!(default_tools().iter().all(|t| t.name() != "meta_omni"));
+        assert!(tool_catalog().iter().all(|t| !t.goal_id.contains("meta.omni")));
+        assert_eq!(default_tools()[0].name(), "wiki_generate");
+    }
+}
@@ -1 +1,9 @@
+pub mod cargo;
+pub mod daily_report;
+pub mod declared;
 pub mod meta_omni;
+pub mod patch;
+pub mod research_fetch;
+pub mod research_manifest;
+pub mod staleness;
+pub mod sweep;
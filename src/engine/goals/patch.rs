@@ -0,0 +1,534 @@
+//! file.patch: apply a unified diff or a structured edit list atomically.
+//!
+//! Every target is read and hashed up front; all hunks/edits are resolved in memory before
+//! anything is written, and each file's pre-image hash is re-checked right before the swap,
+//! so a target that changed underneath us rejects the whole patch. A reverse patch (new → old)
+//! is returned for rollback.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::engine::platform;
+
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub rel: String,
+    pub path: PathBuf,
+    /// `None` = file did not exist.
+    pub old: Option<String>,
+    /// `None` = delete the file.
+    pub new: Option<String>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchedFile {
+    pub path: String,
+    pub pre_sha256: Option<String>,
+    pub post_sha256: Option<String>,
+    pub added: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PatchOutcome {
+    /// "unified_diff" | "edits"
+    pub mode: String,
+    pub files: Vec<PatchedFile>,
+    pub added: usize,
+    pub removed: usize,
+    #[serde(skip)]
+    pub reverse_patch: String,
+}
+
+pub fn sha256_hex(s: &str) -> String {
+    let mut h = Sha256::new();
+    h.update(s.as_bytes());
+    format!("{:x}", h.finalize())
+}
+
+fn resolve(root: &Path, rel: &str) -> Result<PathBuf> {
+    let p = platform::native_path(rel);
+    if rel.is_empty()
+        || p.is_absolute()
+        || p.components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        bail!("unsafe patch path: {}", rel);
+    }
+    Ok(root.join(p))
+}
+
+fn read_existing(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(s) => Ok(Some(s)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+}
+
+// ---- unified diff ----
+
+struct Hunk {
+    old_start: usize,
+    old: Vec<String>,
+    new: Vec<String>,
+    added: usize,
+    removed: usize,
+}
+
+struct FileDiff {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+}
+
+fn strip_diff_path(raw: &str) -> Option<String> {
+    let p = raw.split('\t').next().unwrap_or("").trim();
+    if p == "/dev/null" {
+        return None;
+    }
+    Some(
+        p.strip_prefix("a/")
+            .or_else(|| p.strip_prefix("b/"))
+            .unwrap_or(p)
+            .to_string(),
+    )
+}
+
+/// `-12,3` / `+5` → (start, len); start is 1-based (0 for empty ranges), len defaults to 1.
+fn parse_range(s: &str) -> Option<(usize, usize)> {
+    let mut it = s.get(1..)?.split(',');
+    let start = it.next()?.parse().ok()?;
+    let len = match it.next() {
+        Some(n) => n.parse().ok()?,
+        None => 1,
+    };
+    Some((start, len))
+}
+
+fn parse_unified(diff: &str) -> Result<Vec<FileDiff>> {
+    let mut files: Vec<FileDiff> = Vec::new();
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(old) = line.strip_prefix("--- ") {
+            let new = lines
+                .get(i + 1)
+                .and_then(|l| l.strip_prefix("+++ "))
+                .ok_or_else(|| anyhow!("missing +++ header after line {}", i + 1))?;
+            files.push(FileDiff {
+                old_path: strip_diff_path(old),
+                new_path: strip_diff_path(new),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+        if line.starts_with("@@") {
+            let file = files
+                .last_mut()
+                .ok_or_else(|| anyhow!("hunk before file header at line {}", i + 1))?;
+            let mut parts = line.split_whitespace().skip(1);
+            let ((old_start, old_len), (_, new_len)) = parts
+                .next()
+                .and_then(parse_range)
+                .zip(parts.next().and_then(parse_range))
+                .ok_or_else(|| anyhow!("bad hunk header: {}", line))?;
+            let mut hunk = Hunk {
+                old_start,
+                old: Vec::new(),
+                new: Vec::new(),
+                added: 0,
+                removed: 0,
+            };
+            i += 1;
+            // Consume by the header's line counts so `--- ` / `@@` inside content are safe.
+            while i < lines.len() {
+                let l = lines[i];
+                let body = l.get(1..).unwrap_or("");
+                let full = hunk.old.len() >= old_len && hunk.new.len() >= new_len;
+                match l.chars().next() {
+                    Some('\\') => {
+                        // "\ No newline at end of file" applies to the previous line.
+                        match lines[i - 1].chars().next() {
+                            Some('-') => trim_last(&mut hunk.old),
+                            Some('+') => trim_last(&mut hunk.new),
+                            _ => {
+                                trim_last(&mut hunk.old);
+                                trim_last(&mut hunk.new);
+                            }
+                        }
+                    }
+                    _ if full => break,
+                    Some(' ') | None => {
+                        hunk.old.push(format!("{}\n", body));
+                        hunk.new.push(format!("{}\n", body));
+                    }
+                    Some('-') => {
+                        hunk.old.push(format!("{}\n", body));
+                        hunk.removed += 1;
+                    }
+                    Some('+') => {
+                        hunk.new.push(format!("{}\n", body));
+                        hunk.added += 1;
+                    }
+                    _ => bail!("unexpected diff line {}: {}", i + 1, l),
+                }
+                i += 1;
+            }
+            if hunk.old.len() != old_len || hunk.new.len() != new_len {
+                bail!("truncated hunk: {}", line);
+            }
+            file.hunks.push(hunk);
+            continue;
+        }
+        i += 1;
+    }
+    if files.is_empty() {
+        bail!("no file headers found in diff");
+    }
+    Ok(files)
+}
+
+fn trim_last(v: &mut [String]) {
+    if let Some(last) = v.last_mut() {
+        if last.ends_with('\n') {
+            last.pop();
+        }
+    }
+}
+
+fn find_block(haystack: &[String], needle: &[String], from: usize, hint: usize) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(hint.clamp(from, haystack.len()));
+    }
+    let fits = |at: usize| {
+        at + needle.len() <= haystack.len() && haystack[at..at + needle.len()] == *needle
+    };
+    if hint >= from && fits(hint) {
+        return Some(hint);
+    }
+    // Hunk moved: accept only a unique match after the previous hunk.
+    let mut found = (from..haystack.len()).filter(|&at| fits(at));
+    match (found.next(), found.next()) {
+        (Some(at), None) => Some(at),
+        _ => None,
+    }
+}
+
+fn apply_hunks(rel: &str, old: &str, hunks: &[Hunk]) -> Result<String> {
+    let src: Vec<String> = old.split_inclusive('\n').map(|s| s.to_string()).collect();
+    let mut out: Vec<String> = Vec::new();
+    let mut cursor = 0usize;
+    let mut delta: isize = 0;
+    for (n, h) in hunks.iter().enumerate() {
+        let hint = (h.old_start.saturating_sub(1) as isize + delta).max(0) as usize;
+        let at = find_block(&src, &h.old, cursor, hint)
+            .ok_or_else(|| anyhow!("hunk {} does not apply to {}", n + 1, rel))?;
+        out.extend_from_slice(&src[cursor..at]);
+        out.extend(h.new.iter().cloned());
+        cursor = at + h.old.len();
+        delta += at as isize - h.old_start.saturating_sub(1) as isize;
+    }
+    out.extend_from_slice(&src[cursor..]);
+    Ok(out.concat())
+}
+
+fn plan_unified(root: &Path, diff: &str) -> Result<Vec<FileChange>> {
+    let mut changes = Vec::new();
+    for fd in parse_unified(diff)? {
+        let rel = fd
+            .new_path
+            .clone()
+            .or_else(|| fd.old_path.clone())
+            .ok_or_else(|| anyhow!("diff entry without a path"))?;
+        let path = resolve(root, &rel)?;
+        let current = read_existing(&path)?;
+        let added = fd.hunks.iter().map(|h| h.added).sum();
+        let removed = fd.hunks.iter().map(|h| h.removed).sum();
+        let new = if fd.new_path.is_none() {
+            if current.is_none() {
+                bail!("cannot delete missing file {}", rel);
+            }
+            None
+        } else {
+            if fd.old_path.is_none() && current.is_some() {
+                bail!("refusing to create {}: file already exists", rel);
+            }
+            if fd.old_path.is_some() && current.is_none() {
+                bail!("target {} does not exist", rel);
+            }
+            Some(apply_hunks(
+                &rel,
+                current.as_deref().unwrap_or(""),
+                &fd.hunks,
+            )?)
+        };
+        changes.push(FileChange {
+            rel,
+            path,
+            old: current,
+            new,
+            added,
+            removed,
+        });
+    }
+    Ok(changes)
+}
+
+// ---- structured edits: [{path, old, new}] ----
+
+fn plan_edits(root: &Path, edits: &[Value]) -> Result<Vec<FileChange>> {
+    let mut by_file: BTreeMap<String, FileChange> = BTreeMap::new();
+    for (n, e) in edits.iter().enumerate() {
+        let rel = e
+            .get("path")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("edit {}: path required", n + 1))?
+            .to_string();
+        let old_s = e.get("old").and_then(|v| v.as_str()).unwrap_or("");
+        let new_s = e
+            .get("new")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("edit {}: new required", n + 1))?;
+        if !by_file.contains_key(&rel) {
+            let path = resolve(root, &rel)?;
+            let current = read_existing(&path)?;
+            by_file.insert(
+                rel.clone(),
+                FileChange {
+                    rel: rel.clone(),
+                    path,
+                    new: current.clone(),
+                    old: current,
+                    added: 0,
+                    removed: 0,
+                },
+            );
+        }
+        let fc = by_file.get_mut(&rel).expect("inserted above");
+        let next = match fc.new.as_deref() {
+            None if old_s.is_empty() => new_s.to_string(),
+            None => bail!("edit {}: {} does not exist", n + 1, rel),
+            Some(_) if old_s.is_empty() => {
+                bail!("edit {}: old text required for existing {}", n + 1, rel)
+            }
+            Some(cur) => match cur.matches(old_s).count() {
+                1 => cur.replacen(old_s, new_s, 1),
+                0 => bail!("edit {}: old text not found in {}", n + 1, rel),
+                k => bail!("edit {}: old text matches {} times in {}", n + 1, k, rel),
+            },
+        };
+        fc.removed += old_s.lines().count();
+        fc.added += new_s.lines().count();
+        fc.new = Some(next);
+    }
+    Ok(by_file.into_values().collect())
+}
+
+// ---- reverse patch (new → old) ----
+
+fn hunk_range(start: usize, len: usize) -> String {
+    // Empty ranges point at the line before the change, per unified diff convention.
+    if len == 0 {
+        format!("{},0", start.saturating_sub(1))
+    } else {
+        format!("{},{}", start, len)
+    }
+}
+
+fn push_side(out: &mut String, prefix: char, line: &str) {
+    out.push(prefix);
+    out.push_str(line);
+    if !line.ends_with('\n') {
+        out.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Single-hunk unified diff from `from` to `to` (common prefix/suffix trimmed).
+pub fn unified_diff(rel: &str, from: Option<&str>, to: Option<&str>) -> String {
+    let a: Vec<&str> = from.unwrap_or("").split_inclusive('\n').collect();
+    let b: Vec<&str> = to.unwrap_or("").split_inclusive('\n').collect();
+    if from == to {
+        return String::new();
+    }
+    let mut pre = 0;
+    while pre < a.len() && pre < b.len() && a[pre] == b[pre] {
+        pre += 1;
+    }
+    let mut suf = 0;
+    while suf < a.len() - pre && suf < b.len() - pre && a[a.len() - 1 - suf] == b[b.len() - 1 - suf]
+    {
+        suf += 1;
+    }
+    let ctx_start = pre.saturating_sub(CONTEXT_LINES);
+    let ctx_end_a = (a.len() - suf + CONTEXT_LINES).min(a.len());
+    let ctx_end_b = (b.len() - suf + CONTEXT_LINES).min(b.len());
+
+    let mut out = String::new();
+    out.push_str(&match from {
+        Some(_) => format!("--- a/{}\n", rel),
+        None => "--- /dev/null\n".to_string(),
+    });
+    out.push_str(&match to {
+        Some(_) => format!("+++ b/{}\n", rel),
+        None => "+++ /dev/null\n".to_string(),
+    });
+    out.push_str(&format!(
+        "@@ -{} +{} @@\n",
+        hunk_range(ctx_start + 1, ctx_end_a - ctx_start),
+        hunk_range(ctx_start + 1, ctx_end_b - ctx_start)
+    ));
+    for l in &a[ctx_start..pre] {
+        push_side(&mut out, ' ', l);
+    }
+    for l in &a[pre..a.len() - suf] {
+        push_side(&mut out, '-', l);
+    }
+    for l in &b[pre..b.len() - suf] {
+        push_side(&mut out, '+', l);
+    }
+    for l in &a[a.len() - suf..ctx_end_a] {
+        push_side(&mut out, ' ', l);
+    }
+    out
+}
+
+// ---- atomic apply ----
+
+fn tmp_path(path: &Path, tag: &str) -> PathBuf {
+    let mut os = path.as_os_str().to_os_string();
+    os.push(format!(".{}.{}", tag, std::process::id()));
+    PathBuf::from(os)
+}
+
+/// Stage every new file next to its target, re-verify pre-images, then rename into place.
+/// If a rename fails midway, already-swapped files are restored from their backups.
+fn commit(changes: &[FileChange]) -> Result<()> {
+    let mut staged: Vec<(usize, PathBuf)> = Vec::new();
+    let cleanup = |staged: &[(usize, PathBuf)]| {
+        for (_, tmp) in staged {
+            let _ = fs::remove_file(tmp);
+        }
+    };
+    for (i, c) in changes.iter().enumerate() {
+        if let Some(content) = c.new.as_deref() {
+            if let Some(parent) = c.path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("failed to create dir {}", parent.display()))?;
+            }
+            let tmp = tmp_path(&c.path, "patch");
+            if let Err(e) = fs::write(&tmp, content) {
+                cleanup(&staged);
+                return Err(e).with_context(|| format!("failed to stage {}", tmp.display()));
+            }
+            staged.push((i, tmp));
+        }
+    }
+
+    for c in changes {
+        let now = read_existing(&c.path)?;
+        if now.as_deref().map(sha256_hex) != c.old.as_deref().map(sha256_hex) {
+            cleanup(&staged);
+            bail!("pre-image mismatch: {} changed while patching", c.rel);
+        }
+    }
+
+    let mut done: Vec<usize> = Vec::new();
+    let mut result = Ok(());
+    for (i, c) in changes.iter().enumerate() {
+        let r = match staged.iter().find(|(j, _)| *j == i) {
+            Some((_, tmp)) => fs::rename(tmp, &c.path),
+            None => fs::remove_file(&c.path),
+        };
+        if let Err(e) = r {
+            result = Err(anyhow!("failed to apply {}: {}", c.rel, e));
+            break;
+        }
+        done.push(i);
+    }
+    if result.is_err() {
+        for i in done {
+            let c = &changes[i];
+            let _ = match c.old.as_deref() {
+                Some(old) => fs::write(&c.path, old),
+                None => fs::remove_file(&c.path),
+            };
+        }
+        cleanup(&staged);
+    }
+    result
+}
+
+/// Plan, verify and apply a patch from goal inputs:
+/// `diff` (unified diff) or `edits` ([{path, old, new}]), optional `root` (default ".")
+/// and `expected_sha256` ({path: hex}) pre-image hashes.
+pub fn apply(inputs: &Value, tiny_diff_loc: u32) -> Result<PatchOutcome> {
+    let root = PathBuf::from(inputs.get("root").and_then(|v| v.as_str()).unwrap_or("."));
+    let (mode, changes) = if let Some(diff) = inputs.get("diff").and_then(|v| v.as_str()) {
+        ("unified_diff", plan_unified(&root, diff)?)
+    } else if let Some(edits) = inputs.get("edits").and_then(|v| v.as_array()) {
+        ("edits", plan_edits(&root, edits)?)
+    } else {
+        bail!("diff or edits required");
+    };
+
+    if let Some(expected) = inputs.get("expected_sha256").and_then(|v| v.as_object()) {
+        for c in &changes {
+            if let Some(want) = expected.get(&c.rel).and_then(|v| v.as_str()) {
+                let have = c.old.as_deref().map(sha256_hex);
+                if have.as_deref() != Some(want) {
+                    bail!(
+                        "pre-image mismatch for {}: expected {}, found {}",
+                        c.rel,
+                        want,
+                        have.unwrap_or_else(|| "<missing>".to_string())
+                    );
+                }
+            }
+        }
+    }
+
+    let added: usize = changes.iter().map(|c| c.added).sum();
+    let removed: usize = changes.iter().map(|c| c.removed).sum();
+    if added + removed > tiny_diff_loc as usize {
+        bail!(
+            "patch touches {} lines, over policy tiny_diff_loc={}",
+            added + removed,
+            tiny_diff_loc
+        );
+    }
+
+    commit(&changes)?;
+
+    let reverse_patch = changes
+        .iter()
+        .map(|c| unified_diff(&c.rel, c.new.as_deref(), c.old.as_deref()))
+        .collect::<String>();
+    Ok(PatchOutcome {
+        mode: mode.to_string(),
+        files: changes
+            .iter()
+            .map(|c| PatchedFile {
+                path: c.rel.clone(),
+                pre_sha256: c.old.as_deref().map(sha256_hex),
+                post_sha256: c.new.as_deref().map(sha256_hex),
+                added: c.added,
+                removed: c.removed,
+            })
+            .collect(),
+        added,
+        removed,
+        reverse_patch,
+    })
+}
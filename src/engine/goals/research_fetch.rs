@@ -0,0 +1,439 @@
+//! research.fetch: download a URL from an allowlisted domain, normalize it to markdown and
+//! store it under `research/sources/web/` so the research index (and context resolver) see it.
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use one_engine::{atomic, research, storage};
+
+const DEFAULT_MAX_BYTES: u64 = 2 * 1024 * 1024;
+const DEFAULT_TIMEOUT_SECS: u64 = 20;
+const MAX_REDIRECTS: usize = 5;
+const SOURCES_DIR: &str = "research/sources/web";
+const INDEX_PATH: &str = "research/index.jsonl";
+
+/// `research_fetch:` section of `config/policies.yaml`. Nothing is fetched unless the host is
+/// allowlisted here or in `ONE_ENGINE_RESEARCH_ALLOWED_DOMAINS=a.com,b.org`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FetchPolicy {
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+impl FetchPolicy {
+    fn domains(&self) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .allowed_domains
+            .iter()
+            .map(|d| d.trim().trim_start_matches("*.").to_lowercase())
+            .collect();
+        if let Ok(extra) = std::env::var("ONE_ENGINE_RESEARCH_ALLOWED_DOMAINS") {
+            out.extend(
+                extra
+                    .split(',')
+                    .map(|d| d.trim().trim_start_matches("*.").to_lowercase())
+                    .filter(|d| !d.is_empty()),
+            );
+        }
+        out
+    }
+}
+
+/// `example.com` allows `example.com` and any subdomain of it.
+pub fn domain_allowed(host: &str, allowed: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    allowed
+        .iter()
+        .any(|d| !d.is_empty() && (host == *d || host.ends_with(&format!(".{}", d))))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchManifest {
+    pub url: String,
+    pub final_url: String,
+    pub fetched_at: String,
+    /// sha256 of the downloaded bytes.
+    pub sha256: String,
+    pub content_type: String,
+    pub bytes: usize,
+    pub title: Option<String>,
+    pub path: String,
+}
+
+fn decode_entities(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        let Some(end) = tail.find(';').filter(|&e| e <= 10) else {
+            out.push('&');
+            rest = &tail[1..];
+            continue;
+        };
+        let ent = &tail[1..end];
+        let decoded = match ent {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" | "#39" => Some('\''),
+            "nbsp" => Some(' '),
+            _ if ent.starts_with("#x") || ent.starts_with("#X") => {
+                u32::from_str_radix(&ent[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            _ if ent.starts_with('#') => ent[1..].parse::<u32>().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &tail[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &tail[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Remove `<tag ...>...</tag>` blocks (case-insensitive) whose content is never readable text.
+fn strip_blocks(html: &str, tags: &[&str]) -> String {
+    let mut s = html.to_string();
+    for tag in tags {
+        let re = regex::Regex::new(&format!(r"(?is)<{0}\b[^>]*>.*?</{0}\s*>", tag))
+            .expect("static pattern");
+        s = re.replace_all(&s, " ").into_owned();
+    }
+    regex::Regex::new(r"(?s)<!--.*?-->")
+        .expect("static pattern")
+        .replace_all(&s, " ")
+        .into_owned()
+}
+
+fn attr(tag_body: &str, name: &str) -> Option<String> {
+    let re = regex::Regex::new(&format!(
+        r#"(?i)\b{}\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s>]+))"#,
+        name
+    ))
+    .ok()?;
+    let c = re.captures(tag_body)?;
+    c.get(1)
+        .or_else(|| c.get(2))
+        .or_else(|| c.get(3))
+        .map(|m| decode_entities(m.as_str()))
+}
+
+/// Readable-text extraction: headings, paragraphs, lists, links, emphasis and code blocks
+/// become markdown; everything else is reduced to its text.
+pub fn html_to_markdown(html: &str) -> (Option<String>, String) {
+    let title = regex::Regex::new(r"(?is)<title[^>]*>(.*?)</title>")
+        .expect("static pattern")
+        .captures(html)
+        .map(|c| decode_entities(c[1].trim()))
+        .filter(|t| !t.is_empty());
+    let body = strip_blocks(
+        html,
+        &[
+            "head", "script", "style", "noscript", "svg", "nav", "footer", "form",
+        ],
+    );
+
+    let mut out = String::new();
+    let mut pre = 0usize;
+    let mut links: Vec<Option<String>> = Vec::new();
+    let mut rest = body.as_str();
+    while !rest.is_empty() {
+        let Some(lt) = rest.find('<') else {
+            push_text(&mut out, rest, pre > 0);
+            break;
+        };
+        push_text(&mut out, &rest[..lt], pre > 0);
+        let Some(gt) = rest[lt..].find('>') else {
+            push_text(&mut out, &rest[lt..], pre > 0);
+            break;
+        };
+        let inner = &rest[lt + 1..lt + gt];
+        rest = &rest[lt + gt + 1..];
+        let closing = inner.starts_with('/');
+        let name: String = inner
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+        match (name.as_str(), closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                out.push_str("\n\n");
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => out.push_str("\n\n"),
+            ("p" | "div" | "section" | "article" | "main" | "table" | "tr" | "dl", _) => {
+                out.push_str("\n\n")
+            }
+            ("blockquote", false) => out.push_str("\n\n> "),
+            ("blockquote", true) => out.push_str("\n\n"),
+            ("br", _) => out.push('\n'),
+            ("hr", _) => out.push_str("\n\n---\n\n"),
+            ("li", false) => out.push_str("\n- "),
+            ("ul" | "ol", true) => out.push('\n'),
+            ("dt", false) => out.push_str("\n\n**"),
+            ("dt", true) => out.push_str("**\n"),
+            ("td" | "th", false) => out.push(' '),
+            ("strong" | "b", _) if pre == 0 => out.push_str("**"),
+            ("em" | "i", _) if pre == 0 => out.push('_'),
+            ("code", _) if pre == 0 => out.push('`'),
+            ("pre", false) => {
+                pre += 1;
+                out.push_str("\n\n```\n");
+            }
+            ("pre", true) => {
+                pre = pre.saturating_sub(1);
+                out.push_str("\n```\n\n");
+            }
+            ("a", false) => {
+                links.push(attr(inner, "href").filter(|h| {
+                    !h.is_empty()
+                        && !h.starts_with('#')
+                        && !h.to_lowercase().starts_with("javascript:")
+                }));
+                out.push('[');
+            }
+            ("a", true) => match links.pop().flatten() {
+                Some(href) => out.push_str(&format!("]({})", href)),
+                None => out.push(']'),
+            },
+            ("img", _) => {
+                if let Some(alt) = attr(inner, "alt").filter(|a| !a.trim().is_empty()) {
+                    out.push_str(&format!("[image: {}]", alt.trim()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Tidy: drop empty link brackets, trailing spaces and runs of blank lines.
+    let out = out.replace("[]", "");
+    let mut md = String::new();
+    let mut blank = 0;
+    for line in out.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            blank += 1;
+            if blank > 1 {
+                continue;
+            }
+        } else {
+            blank = 0;
+        }
+        md.push_str(line);
+        md.push('\n');
+    }
+    (title, md.trim().to_string() + "\n")
+}
+
+fn push_text(out: &mut String, raw: &str, pre: bool) {
+    let text = decode_entities(raw);
+    if pre {
+        out.push_str(&text);
+        return;
+    }
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.is_empty() {
+        if !text.is_empty() && !out.ends_with(char::is_whitespace) {
+            out.push(' ');
+        }
+        return;
+    }
+    if text.starts_with(char::is_whitespace) && !out.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+    out.push_str(&collapsed);
+    if text.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+}
+
+fn slug(url: &reqwest::Url) -> String {
+    let raw = format!("{}{}", url.host_str().unwrap_or("url"), url.path());
+    let s: String = raw
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let s = s
+        .split('-')
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    s.chars().take(80).collect()
+}
+
+/// Fetch, normalize, store and index one URL. `root` is the workspace the index lives in.
+pub async fn fetch(inputs: &Value, policy: &FetchPolicy, root: &Path) -> Result<FetchManifest> {
+    let raw_url = inputs
+        .get("url")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("url is required"))?;
+    crate::engine::simulation::deny("research.fetch")?;
+    let url =
+        reqwest::Url::parse(raw_url).map_err(|e| anyhow!("invalid url {}: {}", raw_url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("unsupported scheme: {}", url.scheme());
+    }
+    let allowed = policy.domains();
+    let host = url.host_str().unwrap_or("").to_string();
+    if !domain_allowed(&host, &allowed) {
+        bail!(
+            "domain not allowed: {} (allowlist research_fetch.allowed_domains or ONE_ENGINE_RESEARCH_ALLOWED_DOMAINS)",
+            host
+        );
+    }
+    let max_bytes = inputs
+        .get("max_bytes")
+        .and_then(|v| v.as_u64())
+        .map(|n| n.min(policy.max_bytes.unwrap_or(DEFAULT_MAX_BYTES)))
+        .unwrap_or_else(|| policy.max_bytes.unwrap_or(DEFAULT_MAX_BYTES));
+
+    // Redirects must stay on allowlisted hosts too.
+    let redirect_allow = allowed.clone();
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(
+            policy.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS),
+        ))
+        .redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            let ok = attempt
+                .url()
+                .host_str()
+                .map(|h| domain_allowed(h, &redirect_allow))
+                .unwrap_or(false);
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if ok {
+                attempt.follow()
+            } else {
+                attempt.error("redirect to a domain outside the allowlist")
+            }
+        }))
+        .build()?;
+
+    let mut resp = client
+        .get(url.clone())
+        .header("user-agent", "one-engine research.fetch")
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        bail!("fetch failed: HTTP {}", resp.status());
+    }
+    if resp.content_length().is_some_and(|n| n > max_bytes) {
+        bail!(
+            "response too large: {} bytes > max {}",
+            resp.content_length().unwrap_or(0),
+            max_bytes
+        );
+    }
+    let final_url = resp.url().clone();
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+    let mut bytes: Vec<u8> = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if bytes.len() as u64 + chunk.len() as u64 > max_bytes {
+            bail!("response exceeded max {} bytes", max_bytes);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    let is_html = content_type.contains("html")
+        || (content_type.is_empty()
+            && text
+                .trim_start()
+                .to_lowercase()
+                .starts_with("<!doctype html"));
+    let (title, body) = if is_html {
+        html_to_markdown(&text)
+    } else if content_type.starts_with("text/")
+        || content_type.contains("json")
+        || content_type.contains("xml")
+        || content_type.contains("markdown")
+    {
+        (None, text)
+    } else {
+        bail!("unsupported content type: {}", content_type);
+    };
+
+    let sha256 = format!("{:x}", Sha256::digest(&bytes));
+    let fetched_at = chrono::Utc::now().to_rfc3339();
+    let rel = PathBuf::from(SOURCES_DIR).join(format!("{}-{}.md", slug(&url), &sha256[..8]));
+    let mut tags = vec!["web".to_string(), host.clone()];
+    if let Some(extra) = inputs.get("tags").and_then(|v| v.as_array()) {
+        tags.extend(
+            extra
+                .iter()
+                .filter_map(|t| t.as_str().map(|s| s.to_string())),
+        );
+    }
+    let doc = format!(
+        "---\ntitle: {}\nurl: {}\nfetched_at: {}\nsha256: {}\ntags:\n{}---\n\n{}",
+        serde_json::to_string(title.as_deref().unwrap_or("")).unwrap_or_default(),
+        final_url,
+        fetched_at,
+        sha256,
+        tags.iter()
+            .map(|t| format!("  - {}\n", t))
+            .collect::<String>(),
+        body
+    );
+    let abs = root.join(&rel);
+    if let Some(parent) = abs.parent() {
+        storage::create_dir_all(parent)?;
+    }
+    atomic::write_async(&abs, doc).await?;
+
+    let manifest = FetchManifest {
+        url: raw_url.to_string(),
+        final_url: final_url.to_string(),
+        fetched_at,
+        sha256,
+        content_type,
+        bytes: bytes.len(),
+        title,
+        path: rel.display().to_string(),
+    };
+    let log = root.join(SOURCES_DIR).join("manifest.jsonl");
+    atomic::append_async(&log, &serde_json::to_string(&manifest)?).await?;
+
+    let index = root.join(INDEX_PATH);
+    let root_owned = root.to_path_buf();
+    atomic::spawn_blocking(move || research::upsert_index(&index, &root_owned, &abs))
+        .await
+        .map_err(|e| anyhow!("index update failed: {}", e))??;
+    Ok(manifest)
+}
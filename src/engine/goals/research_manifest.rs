@@ -0,0 +1,290 @@
+//! research.manifest: walk the given paths and pin each file's sha256, mtime and size in a
+//! context manifest, `research/manifests/<id>.json` under META3_ROOT.
+//!
+//! `research.read` takes the manifest back as `context_manifest`, either inline or by
+//! reference (the manifest id or its path), and checks the file it reads, and every other
+//! file the manifest pins, against it. Staleness is then a property of a named, reusable
+//! artifact instead of values each caller copies around.
+//!
+//! Every path, given or pinned, must stay under META3_ROOT once symlinks are resolved. An
+//! existing manifest is only replaced when the caller asks for it (`overwrite: true`).
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+use one_engine::research::{glob_match, DEFAULT_EXCLUDES};
+
+pub const MANIFESTS_DIR: &str = "research/manifests";
+/// Files one manifest may pin.
+const MAX_FILES: usize = 5_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    /// As given (or below a given directory), relative to META3_ROOT.
+    pub path: String,
+    pub sha256: String,
+    /// Seconds since the epoch, as `research.read` reports it.
+    pub mtime: i64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextManifest {
+    pub id: String,
+    pub created_at: String,
+    pub files: Vec<ManifestFile>,
+}
+
+impl ContextManifest {
+    pub fn file(&self, path: &str) -> Option<&ManifestFile> {
+        let path = normalize(path);
+        self.files.iter().find(|f| normalize(&f.path) == path)
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// The real path of `path` under `root`: relative, no `..`, and still inside `root` after
+/// symlinks are resolved.
+fn confine(root: &Path, path: &str) -> Result<PathBuf> {
+    let rel = Path::new(path);
+    if rel.is_absolute()
+        || rel
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        bail!("path must be relative to META3_ROOT: {}", path);
+    }
+    let real_root =
+        std::fs::canonicalize(root).with_context(|| format!("root {}", root.display()))?;
+    let real = std::fs::canonicalize(root.join(rel)).with_context(|| format!("read {}", path))?;
+    if !real.starts_with(&real_root) {
+        bail!("{} resolves outside META3_ROOT", path);
+    }
+    Ok(real)
+}
+
+/// Current sha256, mtime and size of `path` (relative to `root`); the file is hashed as it
+/// streams.
+pub fn fingerprint(root: &Path, path: &str) -> Result<ManifestFile> {
+    let full = confine(root, path)?;
+    let mut file = std::fs::File::open(&full).with_context(|| format!("read {}", path))?;
+    let mut hasher = Sha256::new();
+    let bytes = std::io::copy(&mut file, &mut hasher).with_context(|| format!("read {}", path))?;
+    let mtime = file
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    Ok(ManifestFile {
+        path: normalize(path),
+        sha256: format!("{:x}", hasher.finalize()),
+        mtime,
+        bytes,
+    })
+}
+
+/// Pin every file in `paths` (directories are walked, minus [`DEFAULT_EXCLUDES`]).
+pub fn build(root: &Path, id: &str, paths: &[String]) -> Result<ContextManifest> {
+    if paths.is_empty() {
+        bail!("paths is required");
+    }
+    let root = &std::fs::canonicalize(root).with_context(|| format!("root {}", root.display()))?;
+    let mut files = Vec::new();
+    for p in paths {
+        let full = confine(root, p)?;
+        if full.is_dir() {
+            for e in WalkDir::new(&full)
+                .sort_by_file_name()
+                .into_iter()
+                .flatten()
+            {
+                if !e.file_type().is_file() {
+                    continue;
+                }
+                let (Ok(rel), Ok(below)) =
+                    (e.path().strip_prefix(root), e.path().strip_prefix(&full))
+                else {
+                    continue;
+                };
+                let below = below.to_string_lossy().replace('\\', "/");
+                if DEFAULT_EXCLUDES.iter().any(|x| glob_match(x, &below)) {
+                    continue;
+                }
+                let rel = rel.to_string_lossy().replace('\\', "/");
+                files.push(fingerprint(root, &rel)?);
+                if files.len() > MAX_FILES {
+                    bail!("more than {} files; narrow paths", MAX_FILES);
+                }
+            }
+        } else {
+            files.push(fingerprint(root, p)?);
+        }
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files.dedup_by(|a, b| a.path == b.path);
+    Ok(ContextManifest {
+        id: id.to_string(),
+        created_at: crate::engine::harness::now().to_rfc3339(),
+        files,
+    })
+}
+
+pub fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+pub fn manifest_path(root: &Path, id: &str) -> PathBuf {
+    root.join(MANIFESTS_DIR).join(format!("{}.json", id))
+}
+
+/// Store `manifest` under its id; an existing one is replaced only with `overwrite`.
+pub fn write(root: &Path, manifest: &ContextManifest, overwrite: bool) -> Result<PathBuf> {
+    let path = manifest_path(root, &manifest.id);
+    if !overwrite && path.exists() {
+        bail!(
+            "manifest {} already exists; pass overwrite: true to replace it",
+            manifest.id
+        );
+    }
+    if let Some(dir) = path.parent() {
+        one_engine::storage::create_dir_all(dir)
+            .with_context(|| format!("mkdir {}", dir.display()))?;
+    }
+    one_engine::atomic::write(&path, serde_json::to_string_pretty(manifest)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// The manifest `reference` names: a manifest id, or the path of a manifest file.
+pub fn load(root: &Path, reference: &str) -> Result<ContextManifest> {
+    let path = if reference.ends_with(".json") || reference.contains('/') {
+        confine(root, reference)
+            .map_err(|e| anyhow!("invalid manifest reference {}: {}", reference, e))?
+    } else if is_safe_segment(reference) {
+        manifest_path(root, reference)
+    } else {
+        bail!("invalid manifest reference: {}", reference);
+    };
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("manifest {} not readable: {}", path.display(), e))?;
+    serde_json::from_str(&raw).with_context(|| format!("invalid manifest {}", path.display()))
+}
+
+/// `context_manifest` of a `research.read` request: a reference string or an inline manifest
+/// (`{"files": [...]}`). `None` for the older single-file form (`{"sha256", "mtime"}`).
+pub fn from_input(root: &Path, input: &Value) -> Option<Result<ContextManifest>> {
+    match input {
+        Value::String(reference) => Some(load(root, reference)),
+        Value::Object(o) if o.contains_key("files") => Some(
+            serde_json::from_value::<ContextManifest>(input.clone())
+                .context("invalid inline context_manifest"),
+        ),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileCheck {
+    pub path: String,
+    /// "fresh" | "changed" | "missing"
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+}
+
+/// `pinned` against the file on disk now.
+pub fn check_file(root: &Path, pinned: &ManifestFile) -> FileCheck {
+    let (status, reason) = match fingerprint(root, &pinned.path) {
+        Err(_) => ("missing", None),
+        Ok(now) if now.sha256 != pinned.sha256 => ("changed", Some("sha256_mismatch")),
+        Ok(now) if now.mtime != pinned.mtime => ("changed", Some("mtime_mismatch")),
+        Ok(_) => ("fresh", None),
+    };
+    FileCheck {
+        path: pinned.path.clone(),
+        status,
+        reason,
+    }
+}
+
+pub fn check(root: &Path, manifest: &ContextManifest) -> Vec<FileCheck> {
+    manifest.files.iter().map(|f| check_file(root, f)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn manifests_pin_files_and_report_drift_by_reference() {
+        let root = std::env::temp_dir().join(format!("research-manifest-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("docs/sub")).unwrap();
+        std::fs::create_dir_all(root.join("docs/.git")).unwrap();
+        std::fs::write(root.join("docs/a.md"), "alpha").unwrap();
+        std::fs::write(root.join("docs/sub/b.md"), "beta").unwrap();
+        std::fs::write(root.join("docs/.git/HEAD"), "ref").unwrap();
+        std::fs::write(root.join("README.md"), "readme").unwrap();
+
+        let paths = vec!["docs".to_string(), "./README.md".to_string()];
+        let m = build(&root, "ctx-1", &paths).unwrap();
+        let pinned: Vec<&str> = m.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(pinned, ["README.md", "docs/a.md", "docs/sub/b.md"]);
+        assert_eq!(m.file("./docs/a.md").unwrap().bytes, 5);
+        write(&root, &m, false).unwrap();
+        assert!(write(&root, &m, false).is_err());
+        write(&root, &m, true).unwrap();
+
+        std::fs::write(root.join("docs/a.md"), "ALPHA").unwrap();
+        std::fs::remove_file(root.join("docs/sub/b.md")).unwrap();
+        for reference in ["ctx-1", "research/manifests/ctx-1.json"] {
+            let loaded = from_input(&root, &Value::String(reference.into()))
+                .unwrap()
+                .unwrap();
+            let statuses: Vec<(&str, &str)> = check(&root, &loaded)
+                .iter()
+                .map(|c| (c.status, c.reason.unwrap_or("")))
+                .collect();
+            assert_eq!(
+                statuses,
+                [
+                    ("fresh", ""),
+                    ("changed", "sha256_mismatch"),
+                    ("missing", "")
+                ]
+            );
+        }
+        assert!(load(&root, "../etc/passwd.json").is_err());
+        for outside in ["/etc/passwd", "../README.md", "docs/../../x"] {
+            assert!(
+                build(&root, "ctx-2", &[outside.to_string()]).is_err(),
+                "{}",
+                outside
+            );
+        }
+        let inline = json!({"id": "x", "created_at": "", "files": [
+            {"path": "/etc/hostname", "sha256": "", "mtime": 0, "bytes": 0}
+        ]});
+        let m = from_input(&root, &inline).unwrap().unwrap();
+        assert_eq!(check(&root, &m)[0].status, "missing");
+        assert!(from_input(&root, &serde_json::json!({"sha256": "x"})).is_none());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,502 @@
+//! staleness.check: probe each configured feature by actually invoking it against the running
+//! server (small per-probe budgets), then write `docs/staleness_matrix.json` and an HTML view.
+//!
+//! Endpoint features (`health`, `version`, `progress.sse`) are plain HTTP probes; goal features
+//! (`demo.ping`, `research.read`, `meta3.build`) go through `POST /run`, so each probe also
+//! leaves a normal receipt behind.
+//!
+//! The configured server (`ONE_ENGINE_BASE_URL`) is trusted. A caller's `base_url` is only
+//! probed when every address its host resolves to is public (not loopback, private,
+//! link-local, ...); the client is pinned to those addresses and follows no redirects, so
+//! neither a second lookup nor a `Location` header can move it inside the network.
+
+use anyhow::{anyhow, bail, Context, Result};
+use one_engine::{atomic, storage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::engine::urls::url_for;
+
+pub const FEATURES: &[&str] = &[
+    "health",
+    "version",
+    "demo.ping",
+    "research.read",
+    "meta3.build",
+    "progress.sse",
+];
+
+const MATRIX_JSON: &str = "docs/staleness_matrix.json";
+const MATRIX_HTML: &str = "docs/staleness_matrix.html";
+const DEFAULT_TIME_MS: u64 = 15_000;
+const DEFAULT_BUILD_TIME_MS: u64 = 120_000;
+
+/// One row of `docs/staleness_matrix.json` (`feature`/`status`/`ts`/`detail` keep the
+/// format the external script produced).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StalenessEntry {
+    pub feature: String,
+    /// "pass" | "fail"
+    pub status: String,
+    pub ts: String,
+    pub detail: String,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub bits: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StalenessReport {
+    pub base_url: String,
+    pub entries: Vec<StalenessEntry>,
+    pub passed: usize,
+    pub failed: usize,
+    pub json_path: PathBuf,
+    pub html_path: PathBuf,
+}
+
+fn probe_budget(inputs: &Value, feature: &str) -> u64 {
+    let (key, default) = if feature == "meta3.build" {
+        ("build_time_ms", DEFAULT_BUILD_TIME_MS)
+    } else {
+        ("time_ms", DEFAULT_TIME_MS)
+    };
+    inputs
+        .get(key)
+        .and_then(|v| v.as_u64())
+        .unwrap_or(default)
+        .clamp(1_000, 600_000)
+}
+
+fn configured_base_url() -> String {
+    std::env::var("ONE_ENGINE_BASE_URL")
+        .unwrap_or_else(|_| "http://127.0.0.1:8080".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Not an address inside this host or its network.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public(IpAddr::V4(v4)),
+            None => {
+                let first = v6.segments()[0];
+                !(v6.is_loopback()
+                    || v6.is_unspecified()
+                    // Unique local (fc00::/7) and link-local (fe80::/10).
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Where to probe, and for a caller's `base_url` the host and public addresses to pin it to.
+async fn target(inputs: &Value) -> Result<(String, Option<(String, Vec<SocketAddr>)>)> {
+    let configured = configured_base_url();
+    let Some(raw) = inputs.get("base_url").and_then(|v| v.as_str()) else {
+        return Ok((configured, None));
+    };
+    let base = raw.trim_end_matches('/').to_string();
+    if base == configured {
+        return Ok((base, None));
+    }
+    let url =
+        reqwest::Url::parse(&base).map_err(|e| anyhow!("invalid base_url {}: {}", base, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("base_url must be http or https: {}", base);
+    }
+    let port = url.port_or_known_default().unwrap_or(80);
+    // IPv6 literals keep their brackets in `host_str`.
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("base_url has no host: {}", base))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let addrs: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|e| anyhow!("base_url {}: {}", host, e))?
+            .collect(),
+    };
+    if addrs.is_empty() {
+        bail!("base_url {} resolves to no address", host);
+    }
+    if let Some(a) = addrs.iter().find(|a| !is_public(a.ip())) {
+        bail!(
+            "base_url {} resolves to {}, which is not a public address; only the configured server (ONE_ENGINE_BASE_URL) may be internal",
+            host,
+            a.ip()
+        );
+    }
+    Ok((base, Some((host, addrs))))
+}
+
+/// Run payload inputs for goal probes; `inputs.<feature>` (an object) overrides the defaults.
+fn goal_inputs(inputs: &Value, feature: &str) -> Value {
+    if let Some(custom) = inputs.get(feature).filter(|v| v.is_object()) {
+        return custom.clone();
+    }
+    match feature {
+        "demo.ping" => json!({"message": "staleness.check probe"}),
+        "research.read" => json!({"path": "README.md"}),
+        _ => json!({}),
+    }
+}
+
+/// (pass, detail, run_id, bits)
+async fn probe(
+    client: &reqwest::Client,
+    base: &str,
+    feature: &str,
+    inputs: &Value,
+    budget_ms: u64,
+) -> Result<(bool, String, Option<String>, Value)> {
+    let timeout = Duration::from_millis(budget_ms + 5_000);
+    match feature {
+        "health" => {
+            let resp = client
+                .get(format!("{base}/health"))
+                .timeout(timeout)
+                .send()
+                .await?;
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            Ok((
+                status.is_success() && body.trim() == "ok",
+                format!("HTTP {} {}", status.as_u16(), body.trim()),
+                None,
+                Value::Null,
+            ))
+        }
+        "version" => {
+            let resp = client
+                .get(format!("{base}/version"))
+                .timeout(timeout)
+                .send()
+                .await?;
+            let status = resp.status();
+            let v: Value = resp.json().await.unwrap_or(Value::Null);
+            let engine = v.get("engine").and_then(|x| x.as_str()).unwrap_or("");
+            Ok((
+                status.is_success() && !engine.is_empty(),
+                format!("HTTP {} engine={}", status.as_u16(), engine),
+                None,
+                Value::Null,
+            ))
+        }
+        "progress.sse" => {
+            // Only the response head matters: a live stream never ends on its own.
+            let resp = client
+                .get(format!("{base}/progress.sse?run_id=r-staleness-probe"))
+                .timeout(timeout)
+                .send()
+                .await?;
+            let status = resp.status();
+            let ctype = resp
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_string();
+            Ok((
+                status.is_success() && ctype.starts_with("text/event-stream"),
+                format!("HTTP {} {}", status.as_u16(), ctype),
+                None,
+                Value::Null,
+            ))
+        }
+        goal => {
+            let body = json!({
+                "goal_id": goal,
+                "inputs": goal_inputs(inputs, goal),
+                "policy": {"gamma_gate": 0.5, "time_ms": budget_ms, "max_risk": 0.3, "tiny_diff_loc": 120}
+            });
+            let resp = client
+                .post(format!("{base}/run"))
+                .json(&body)
+                .timeout(timeout)
+                .send()
+                .await?;
+            let status = resp.status();
+            let v: Value = resp.json().await.unwrap_or(Value::Null);
+            let manifest = v.get("manifest").cloned().unwrap_or(Value::Null);
+            let evidence = manifest.get("evidence").cloned().unwrap_or(Value::Null);
+            let ok = status.is_success()
+                && evidence
+                    .get("actual_success")
+                    .and_then(|x| x.as_bool())
+                    .unwrap_or(true);
+            let detail = evidence
+                .get("error")
+                .and_then(|x| x.as_str())
+                .map(|e| format!("HTTP {} error: {}", status.as_u16(), e))
+                .unwrap_or_else(|| format!("HTTP {} actual_success={}", status.as_u16(), ok));
+            Ok((
+                ok,
+                detail,
+                manifest
+                    .get("run_id")
+                    .and_then(|x| x.as_str())
+                    .map(|s| s.to_string()),
+                v.get("bits").cloned().unwrap_or(Value::Null),
+            ))
+        }
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_html(report: &StalenessReport, generated_at: &str) -> String {
+    let mut rows = String::new();
+    for e in &report.entries {
+        let run = e
+            .run_id
+            .as_deref()
+            .map(|r| {
+                format!(
+                    "<a href=\"{}\">{}</a>",
+                    url_for(&format!("/runs/receipts/{}/RECEIPT.md", r)),
+                    escape_html(r)
+                )
+            })
+            .unwrap_or_default();
+        let bits = if e.bits.is_null() {
+            String::new()
+        } else {
+            e.bits.to_string()
+        };
+        rows.push_str(&format!(
+            "<tr><td><code>{}</code></td><td><span class=\"pill {}\">{}</span></td><td>{} ms</td><td>{}</td><td>{}</td><td class=\"muted\">{}</td></tr>",
+            escape_html(&e.feature),
+            escape_html(&e.status),
+            escape_html(&e.status),
+            e.latency_ms,
+            escape_html(&e.detail),
+            run,
+            escape_html(&bits)
+        ));
+    }
+    format!(
+        r#"<!doctype html><html><head><meta charset="utf-8"><title>Staleness matrix</title>
+<meta name="viewport" content="width=device-width,initial-scale=1">
+<style>body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px}} a{{color:#1f6feb;text-decoration:none}} code{{background:#f6f8fa;padding:2px 6px;border-radius:6px}} .muted{{color:#57606a;font-size:12px}} table{{border-collapse:collapse}} td,th{{border-bottom:1px solid #eee;padding:6px 10px;text-align:left;vertical-align:top}} .pill{{display:inline-block;padding:2px 8px;border-radius:999px;font-size:12px}} .pill.pass{{background:#dcfce7}} .pill.fail{{background:#fee2e2}}</style>
+</head><body><h1>Staleness matrix</h1>
+<p class="muted">Generated {generated_at} by staleness.check against {base} · {passed} pass · {failed} fail · <a href="staleness_matrix.json">staleness_matrix.json</a></p>
+<table><tr><th>feature</th><th>status</th><th>latency</th><th>detail</th><th>receipt</th><th>bits</th></tr>{rows}</table>
+</body></html>"#,
+        generated_at = escape_html(generated_at),
+        base = escape_html(&report.base_url),
+        passed = report.passed,
+        failed = report.failed,
+        rows = rows
+    )
+}
+
+/// Probe the selected features (input `features`, default all) and write the matrix under `root`.
+pub async fn check(inputs: &Value, root: &Path) -> Result<StalenessReport> {
+    let selected: Vec<String> = match inputs.get("features").and_then(|v| v.as_array()) {
+        Some(list) => list
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        None => FEATURES.iter().map(|s| s.to_string()).collect(),
+    };
+    if let Some(bad) = selected.iter().find(|f| !FEATURES.contains(&f.as_str())) {
+        bail!(
+            "unknown feature {:?} (expected one of {})",
+            bad,
+            FEATURES.join(", ")
+        );
+    }
+    if selected.is_empty() {
+        bail!("features must not be empty");
+    }
+
+    crate::engine::simulation::deny("context.staleness probes")?;
+    let (base, pinned) = target(inputs).await?;
+    let mut builder = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none());
+    if let Some((host, addrs)) = &pinned {
+        builder = builder.resolve_to_addrs(host, addrs);
+    }
+    let client = builder.build().map_err(|e| anyhow!("http client: {}", e))?;
+    let mut entries = Vec::new();
+    for feature in &selected {
+        let budget = probe_budget(inputs, feature);
+        let started = Instant::now();
+        let (pass, detail, run_id, bits) =
+            match probe(&client, &base, feature, inputs, budget).await {
+                Ok(r) => r,
+                Err(e) => (false, format!("probe error: {}", e), None, Value::Null),
+            };
+        entries.push(StalenessEntry {
+            feature: feature.clone(),
+            status: if pass { "pass" } else { "fail" }.to_string(),
+            ts: chrono::Utc::now().to_rfc3339(),
+            detail,
+            latency_ms: started.elapsed().as_millis() as u64,
+            run_id,
+            bits,
+        });
+    }
+
+    let passed = entries.iter().filter(|e| e.status == "pass").count();
+    let report = StalenessReport {
+        base_url: base,
+        failed: entries.len() - passed,
+        passed,
+        entries,
+        json_path: root.join(MATRIX_JSON),
+        html_path: root.join(MATRIX_HTML),
+    };
+    if let Some(dir) = report.json_path.parent() {
+        storage::create_dir_all(dir).with_context(|| format!("mkdir {}", dir.display()))?;
+    }
+    atomic::write(
+        &report.json_path,
+        serde_json::to_string_pretty(&report.entries).unwrap_or_default(),
+    )
+    .with_context(|| format!("write {}", report.json_path.display()))?;
+    atomic::write(
+        &report.html_path,
+        render_html(&report, &chrono::Utc::now().to_rfc3339()),
+    )
+    .with_context(|| format!("write {}", report.html_path.display()))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn caller_base_urls_must_be_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "192.168.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "::1",
+            "fd00::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "2606:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for base in [
+            "http://169.254.169.254/latest",
+            "http://[::1]:8080",
+            "file:///etc/passwd",
+        ] {
+            assert!(
+                target(&json!({ "base_url": base })).await.is_err(),
+                "{}",
+                base
+            );
+        }
+        let (base, pinned) = target(&json!({ "base_url": "http://93.184.216.34/" }))
+            .await
+            .unwrap();
+        assert_eq!(base, "http://93.184.216.34");
+        assert_eq!(
+            pinned.unwrap().1,
+            ["93.184.216.34:80".parse::<SocketAddr>().unwrap()]
+        );
+        // Without base_url the configured server is probed, wherever it is.
+        assert!(target(&json!({})).await.unwrap().1.is_none());
+    }
+
+    #[test]
+    fn probe_budgets_and_inputs_have_per_feature_defaults() {
+        assert_eq!(probe_budget(&json!({}), "health"), DEFAULT_TIME_MS);
+        assert_eq!(
+            probe_budget(&json!({}), "meta3.build"),
+            DEFAULT_BUILD_TIME_MS
+        );
+        let inputs = json!({"time_ms": 10, "build_time_ms": 9_000_000});
+        assert_eq!(probe_budget(&inputs, "demo.ping"), 1_000);
+        assert_eq!(probe_budget(&inputs, "meta3.build"), 600_000);
+
+        assert_eq!(
+            goal_inputs(&json!({}), "research.read"),
+            json!({"path": "README.md"})
+        );
+        let custom = json!({"research.read": {"path": "docs/x.md"}, "demo.ping": "not an object"});
+        assert_eq!(
+            goal_inputs(&custom, "research.read"),
+            json!({"path": "docs/x.md"})
+        );
+        assert_eq!(
+            goal_inputs(&custom, "demo.ping"),
+            json!({"message": "staleness.check probe"})
+        );
+    }
+
+    #[tokio::test]
+    async fn check_rejects_unknown_or_empty_feature_lists() {
+        let root = std::env::temp_dir();
+        let err = check(&json!({"features": ["health", "nope"]}), &root)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("unknown feature \"nope\""),
+            "{}",
+            err
+        );
+        assert!(check(&json!({"features": [" "]}), &root).await.is_err());
+    }
+
+    #[test]
+    fn html_escapes_details_and_links_receipts() {
+        let report = StalenessReport {
+            base_url: "http://127.0.0.1:8080".into(),
+            entries: vec![StalenessEntry {
+                feature: "demo.ping".into(),
+                status: "fail".into(),
+                ts: String::new(),
+                detail: "HTTP 500 error: <boom>".into(),
+                latency_ms: 12,
+                run_id: Some("r-1".into()),
+                bits: Value::Null,
+            }],
+            passed: 0,
+            failed: 1,
+            json_path: PathBuf::new(),
+            html_path: PathBuf::new(),
+        };
+        let html = render_html(&report, "now");
+        assert!(html.contains("HTTP 500 error: &lt;boom&gt;"));
+        assert!(html.contains("/runs/receipts/r-1/RECEIPT.md"));
+        assert!(html.contains("0 pass · 1 fail"));
+    }
+}
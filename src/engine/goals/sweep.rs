@@ -0,0 +1,497 @@
+//! experiment.sweep: run one goal across a parameter grid and compare the outcomes.
+//!
+//! Each grid point becomes a child run (run id `<parent>-c<NNN>`), run in-process through
+//! [`hooks::run_child`] exactly as `POST /run` would, so every child leaves its own receipt.
+//! A grid larger than `max_runs` is refused before any point is generated. Results land under
+//! `runs/experiments/<parent_run_id>/` as `results.json`, `results.csv` and `index.html`
+//! (per-parameter success and trust bars), all linked from the parent receipt.
+//!
+//! Grid keys name an input (`depth`, or dotted `options.temperature` for nested inputs) or,
+//! with a `policy.` prefix, a policy field (`policy.time_ms`).
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use crate::engine::hooks;
+use crate::engine::urls::url_for;
+
+const DEFAULT_MAX_RUNS: usize = 64;
+const HARD_MAX_RUNS: usize = 256;
+const DEFAULT_TIME_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChildResult {
+    pub run_id: String,
+    /// Grid point, keyed by grid key.
+    pub params: BTreeMap<String, Value>,
+    /// "done" | "error" (goal failed) | "failed" (the run itself couldn't complete)
+    pub status: String,
+    pub success: bool,
+    pub trust: Option<f32>,
+    pub uncertainty: Option<f32>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub receipt_url: String,
+}
+
+/// Success rate and mean trust for one value of one parameter.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParamBucket {
+    pub param: String,
+    pub value: Value,
+    pub runs: usize,
+    pub succeeded: usize,
+    pub success_rate: f32,
+    pub mean_trust: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepReport {
+    pub parent_run_id: String,
+    pub goal_id: String,
+    pub grid: BTreeMap<String, Vec<Value>>,
+    pub total: usize,
+    pub succeeded: usize,
+    /// Children whose run didn't complete (transport or server error).
+    pub failed_runs: usize,
+    pub children: Vec<ChildResult>,
+    pub by_param: Vec<ParamBucket>,
+    #[serde(skip)]
+    pub out_dir: PathBuf,
+}
+
+fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn parse_grid(inputs: &Value) -> Result<BTreeMap<String, Vec<Value>>> {
+    let obj = inputs
+        .get("grid")
+        .and_then(|v| v.as_object())
+        .ok_or_else(|| anyhow!("grid must be an object of parameter -> [values]"))?;
+    if obj.is_empty() {
+        bail!("grid must not be empty");
+    }
+    let mut grid = BTreeMap::new();
+    for (k, v) in obj {
+        let values = match v {
+            Value::Array(a) if !a.is_empty() => a.clone(),
+            Value::Array(_) => bail!("grid.{} has no values", k),
+            other => vec![other.clone()],
+        };
+        if k.trim().is_empty() || k.split('.').any(|p| p.is_empty()) {
+            bail!("invalid grid key {:?}", k);
+        }
+        grid.insert(k.clone(), values);
+    }
+    Ok(grid)
+}
+
+/// Points in the grid; `None` if the count overflows.
+fn grid_size(grid: &BTreeMap<String, Vec<Value>>) -> Option<usize> {
+    grid.values()
+        .try_fold(1usize, |n, values| n.checked_mul(values.len()))
+}
+
+/// Cartesian product, in key order with the last key varying fastest, one point at a time.
+fn expand(
+    grid: &BTreeMap<String, Vec<Value>>,
+) -> impl Iterator<Item = BTreeMap<String, Value>> + '_ {
+    (0..grid_size(grid).unwrap_or(0)).map(move |mut i| {
+        let mut point = BTreeMap::new();
+        for (k, values) in grid.iter().rev() {
+            point.insert(k.clone(), values[i % values.len()].clone());
+            i /= values.len();
+        }
+        point
+    })
+}
+
+fn set_path(target: &mut Map<String, Value>, path: &str, value: Value) {
+    let mut parts = path.split('.').peekable();
+    let mut cur = target;
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            cur.insert(part.to_string(), value);
+            return;
+        }
+        let slot = cur
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !slot.is_object() {
+            *slot = Value::Object(Map::new());
+        }
+        let Value::Object(next) = slot else {
+            return;
+        };
+        cur = next;
+    }
+}
+
+/// `(inputs, policy)` for one grid point.
+fn child_payload(
+    base_inputs: &Value,
+    base_policy: &Value,
+    point: &BTreeMap<String, Value>,
+) -> (Value, Value) {
+    let mut inputs = base_inputs.as_object().cloned().unwrap_or_default();
+    let mut policy = base_policy.as_object().cloned().unwrap_or_default();
+    for (k, v) in point {
+        match k.strip_prefix("policy.") {
+            Some(field) => set_path(&mut policy, field, v.clone()),
+            None => set_path(&mut inputs, k, v.clone()),
+        }
+    }
+    (Value::Object(inputs), Value::Object(policy))
+}
+
+/// `body` is the `POST /run` payload for `run_id`.
+async fn run_child(
+    run_id: String,
+    params: BTreeMap<String, Value>,
+    body: Value,
+    timeout: Duration,
+) -> ChildResult {
+    let started = Instant::now();
+    let res = match tokio::time::timeout(timeout, hooks::run_child(body)).await {
+        Ok(r) => r,
+        Err(_) => Err(format!("timed out after {} ms", timeout.as_millis())),
+    };
+    let receipt_url = url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id));
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let v = match res {
+        Ok(v) => v,
+        Err(e) => {
+            return ChildResult {
+                run_id,
+                params,
+                status: "failed".to_string(),
+                success: false,
+                trust: None,
+                uncertainty: None,
+                error: Some(e.chars().take(200).collect()),
+                latency_ms,
+                receipt_url,
+            }
+        }
+    };
+    let evidence = v
+        .pointer("/manifest/evidence")
+        .cloned()
+        .unwrap_or(Value::Null);
+    let success = evidence
+        .get("actual_success")
+        .and_then(|x| x.as_bool())
+        .unwrap_or(true);
+    let bit = |k: &str| {
+        v.get("bits")
+            .and_then(|b| b.get(k))
+            .and_then(|x| x.as_f64())
+            .map(|x| x as f32)
+    };
+    ChildResult {
+        run_id,
+        params,
+        status: if success { "done" } else { "error" }.to_string(),
+        success,
+        trust: bit("t"),
+        uncertainty: bit("u"),
+        error: evidence
+            .get("error")
+            .and_then(|x| x.as_str())
+            .map(|s| s.to_string()),
+        latency_ms,
+        receipt_url,
+    }
+}
+
+fn aggregate(grid: &BTreeMap<String, Vec<Value>>, children: &[ChildResult]) -> Vec<ParamBucket> {
+    let mut out = Vec::new();
+    for (k, values) in grid {
+        for value in values {
+            let matching: Vec<&ChildResult> = children
+                .iter()
+                .filter(|c| c.params.get(k) == Some(value))
+                .collect();
+            let succeeded = matching.iter().filter(|c| c.success).count();
+            let trusts: Vec<f32> = matching.iter().filter_map(|c| c.trust).collect();
+            out.push(ParamBucket {
+                param: k.clone(),
+                value: value.clone(),
+                runs: matching.len(),
+                succeeded,
+                success_rate: if matching.is_empty() {
+                    0.0
+                } else {
+                    succeeded as f32 / matching.len() as f32
+                },
+                mean_trust: (!trusts.is_empty())
+                    .then(|| trusts.iter().sum::<f32>() / trusts.len() as f32),
+            });
+        }
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn value_label(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn render_csv(report: &SweepReport) -> String {
+    let keys: Vec<&String> = report.grid.keys().collect();
+    let mut out = String::new();
+    let mut header: Vec<String> = vec!["run_id".to_string()];
+    header.extend(keys.iter().map(|k| csv_field(k)));
+    header.extend(
+        [
+            "status",
+            "success",
+            "trust",
+            "uncertainty",
+            "latency_ms",
+            "error",
+        ]
+        .iter()
+        .map(|s| s.to_string()),
+    );
+    out.push_str(&header.join(","));
+    out.push('\n');
+    for c in &report.children {
+        let mut row: Vec<String> = vec![csv_field(&c.run_id)];
+        row.extend(
+            keys.iter()
+                .map(|k| csv_field(&c.params.get(*k).map(value_label).unwrap_or_default())),
+        );
+        row.push(c.status.clone());
+        row.push(c.success.to_string());
+        row.push(c.trust.map(|t| format!("{:.3}", t)).unwrap_or_default());
+        row.push(
+            c.uncertainty
+                .map(|u| format!("{:.3}", u))
+                .unwrap_or_default(),
+        );
+        row.push(c.latency_ms.to_string());
+        row.push(csv_field(c.error.as_deref().unwrap_or("")));
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn bar(frac: Option<f32>, class: &str) -> String {
+    match frac {
+        Some(f) => format!(
+            "<div class=\"bar\"><div class=\"fill {}\" style=\"width:{:.0}%\"></div><span>{:.2}</span></div>",
+            class,
+            f.clamp(0.0, 1.0) * 100.0,
+            f
+        ),
+        None => "<span class=\"muted\">-</span>".to_string(),
+    }
+}
+
+fn render_html(report: &SweepReport, generated_at: &str) -> String {
+    let mut charts = String::new();
+    for k in report.grid.keys() {
+        let mut rows = String::new();
+        for b in report.by_param.iter().filter(|b| &b.param == k) {
+            rows.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}/{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&value_label(&b.value)),
+                b.succeeded,
+                b.runs,
+                bar(Some(b.success_rate), "ok"),
+                bar(b.mean_trust, "trust")
+            ));
+        }
+        charts.push_str(&format!(
+            "<h2><code>{}</code></h2><table><tr><th>value</th><th>runs</th><th>success</th><th>mean trust</th></tr>{}</table>",
+            escape_html(k),
+            rows
+        ));
+    }
+    let mut runs = String::new();
+    for c in &report.children {
+        let params = c
+            .params
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, value_label(v)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        runs.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td><code>{}</code></td><td><span class=\"pill {}\">{}</span></td><td>{}</td><td>{} ms</td><td class=\"muted\">{}</td></tr>",
+            escape_html(&c.receipt_url),
+            escape_html(&c.run_id),
+            escape_html(&params),
+            escape_html(&c.status),
+            escape_html(&c.status),
+            c.trust.map(|t| format!("{:.2}", t)).unwrap_or_else(|| "-".to_string()),
+            c.latency_ms,
+            escape_html(c.error.as_deref().unwrap_or(""))
+        ));
+    }
+    format!(
+        r#"<!doctype html><html><head><meta charset="utf-8"><title>Sweep {parent}</title>
+<meta name="viewport" content="width=device-width,initial-scale=1">
+<style>body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px}} a{{color:#1f6feb;text-decoration:none}} code{{background:#f6f8fa;padding:2px 6px;border-radius:6px}} .muted{{color:#57606a;font-size:12px}} table{{border-collapse:collapse;margin-bottom:16px}} td,th{{border-bottom:1px solid #eee;padding:6px 10px;text-align:left;vertical-align:middle}} .bar{{position:relative;width:220px;height:16px;background:#f6f8fa;border-radius:4px}} .bar span{{position:absolute;left:6px;top:0;font-size:11px}} .fill{{height:16px;border-radius:4px}} .fill.ok{{background:#bbf7d0}} .fill.trust{{background:#bfdbfe}} .pill{{display:inline-block;padding:2px 8px;border-radius:999px;font-size:12px}} .pill.done{{background:#dcfce7}} .pill.error,.pill.failed{{background:#fee2e2}}</style>
+</head><body><h1>Sweep of <code>{goal}</code></h1>
+<p class="muted">Generated {generated_at} · parent run <a href="{parent_url}">{parent}</a> · {total} runs · {succeeded} succeeded · {failed_runs} did not complete · <a href="results.json">results.json</a> · <a href="results.csv">results.csv</a></p>
+{charts}
+<h2>Runs</h2>
+<table><tr><th>run</th><th>params</th><th>status</th><th>trust</th><th>latency</th><th>error</th></tr>{runs}</table>
+</body></html>"#,
+        parent = escape_html(&report.parent_run_id),
+        parent_url = escape_html(&url_for(&format!(
+            "/runs/receipts/{}/RECEIPT.md",
+            report.parent_run_id
+        ))),
+        goal = escape_html(&report.goal_id),
+        generated_at = escape_html(generated_at),
+        total = report.total,
+        succeeded = report.succeeded,
+        failed_runs = report.failed_runs,
+        charts = charts,
+        runs = runs
+    )
+}
+
+/// Expand the grid, run every point (at most `concurrency` at a time) and write the results
+/// under `root/runs/experiments/<parent_run_id>/`.
+pub async fn run(parent_run_id: &str, inputs: &Value, root: &Path) -> Result<SweepReport> {
+    if !is_safe_segment(parent_run_id) {
+        bail!("invalid run id {:?}", parent_run_id);
+    }
+    let goal_id = inputs
+        .get("goal")
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| anyhow!("inputs.goal is required"))?;
+    if goal_id.contains("experiment.") {
+        bail!("experiments can't sweep other experiments ({})", goal_id);
+    }
+    let grid = parse_grid(inputs)?;
+    let max_runs = inputs
+        .get("max_runs")
+        .and_then(|v| v.as_u64())
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_RUNS)
+        .min(HARD_MAX_RUNS);
+    match grid_size(&grid) {
+        Some(n) if n <= max_runs => {}
+        n => bail!(
+            "grid expands to {} runs, over max_runs {} (hard cap {})",
+            n.map_or_else(|| "too many".to_string(), |n| n.to_string()),
+            max_runs,
+            HARD_MAX_RUNS
+        ),
+    }
+    let concurrency = inputs
+        .get("concurrency")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(2)
+        .clamp(1, 8) as usize;
+    let time_ms = inputs
+        .get("time_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_TIME_MS)
+        .clamp(1_000, 600_000);
+
+    let base_inputs = inputs.get("inputs").cloned().unwrap_or_else(|| json!({}));
+    let mut base_policy =
+        json!({"gamma_gate": 0.5, "time_ms": time_ms, "max_risk": 0.3, "tiny_diff_loc": 120});
+    if let (Some(dst), Some(src)) = (
+        base_policy.as_object_mut(),
+        inputs.get("policy").and_then(|v| v.as_object()),
+    ) {
+        for (k, v) in src {
+            dst.insert(k.clone(), v.clone());
+        }
+    }
+
+    let timeout = Duration::from_millis(time_ms + 5_000);
+    let permits = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (i, point) in expand(&grid).enumerate() {
+        let (child_inputs, child_policy) = child_payload(&base_inputs, &base_policy, &point);
+        let run_id = format!("{}-c{:03}", parent_run_id, i);
+        let body = json!({
+            "goal_id": goal_id,
+            "inputs": child_inputs,
+            "policy": child_policy,
+            "run_id": run_id
+        });
+        let permits = permits.clone();
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let res = run_child(run_id, point, body, timeout).await;
+            (i, res)
+        });
+    }
+    let mut children: Vec<(usize, ChildResult)> = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        if let Ok(r) = joined {
+            children.push(r);
+        }
+    }
+    children.sort_by_key(|(i, _)| *i);
+    let children: Vec<ChildResult> = children.into_iter().map(|(_, c)| c).collect();
+
+    let report = SweepReport {
+        parent_run_id: parent_run_id.to_string(),
+        goal_id,
+        total: children.len(),
+        succeeded: children.iter().filter(|c| c.success).count(),
+        failed_runs: children.iter().filter(|c| c.status == "failed").count(),
+        by_param: aggregate(&grid, &children),
+        grid,
+        children,
+        out_dir: root.join("runs/experiments").join(parent_run_id),
+    };
+
+    std::fs::create_dir_all(&report.out_dir)
+        .with_context(|| format!("mkdir {}", report.out_dir.display()))?;
+    let write = |name: &str, body: String| {
+        let p = report.out_dir.join(name);
+        std::fs::write(&p, body).with_context(|| format!("write {}", p.display()))
+    };
+    write(
+        "results.json",
+        serde_json::to_string_pretty(&report).unwrap_or_default(),
+    )?;
+    write("results.csv", render_csv(&report))?;
+    write(
+        "index.html",
+        render_html(&report, &chrono::Utc::now().to_rfc3339()),
+    )?;
+    Ok(report)
+}
@@ -1,8 +1,13 @@
 use crate::engine::bits::Bits as RuntimeBits;
+use crate::engine::evidence;
 use anyhow::Result;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
 use utoipa::ToSchema;
 
 #[derive(Debug, Deserialize, Serialize, Clone, JsonSchema, ToSchema)]
@@ -18,6 +23,12 @@ pub struct GoldenCase {
     pub test: String,
     pub ok: bool,
     pub reason: Option<String>,
+    /// Wall time for this case, to spot slow ones.
+    #[serde(default)]
+    pub wall_ms: u64,
+    /// Isolated output root, kept only for failed cases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_dir: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, JsonSchema, ToSchema)]
@@ -28,6 +39,26 @@ pub struct GoldenSummary {
     pub failed: usize,
     pub details: Vec<GoldenCase>,
     pub bits: RuntimeBits,
+    #[serde(default)]
+    pub parallelism: usize,
+    #[serde(default)]
+    pub wall_ms: u64,
+}
+
+/// Upper bound on `parallelism`, whatever the request or environment asks for.
+pub const MAX_PARALLELISM: usize = 64;
+
+/// Concurrency limit: `ONE_ENGINE_GOLDEN_PARALLELISM`, else the number of CPUs.
+pub fn default_parallelism() -> usize {
+    std::env::var("ONE_ENGINE_GOLDEN_PARALLELISM")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+        })
 }
 
 fn parse_bits(v: &Value) -> Option<RuntimeBits> {
@@ -86,32 +117,137 @@ fn bits_valid(b: &RuntimeBits) -> bool {
     vals.iter().all(|v| *v >= 0.0 && *v <= 1.0 && !v.is_nan())
 }
 
+/// Per-case temp roots live under `<tmp>/one-engine-golden/<suite>-<uuid>/<index>/`. A case
+/// reads nothing but its own inputs and writes only under its root, never under META3_ROOT.
+fn suite_tmp_root(name: &str) -> PathBuf {
+    std::env::temp_dir().join("one-engine-golden").join(format!(
+        "{}-{}",
+        name.replace(['/', '\\'], "_"),
+        uuid::Uuid::new_v4()
+    ))
+}
+
+/// A case's root, removed on drop unless [`CaseRoot::keep`] was called, so a case that errors
+/// or whose task is cancelled doesn't leak it.
+struct CaseRoot {
+    dir: PathBuf,
+    keep: bool,
+}
+
+impl CaseRoot {
+    fn keep(&mut self) -> String {
+        self.keep = true;
+        self.dir.display().to_string()
+    }
+}
+
+impl Drop for CaseRoot {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+/// A `result` that records a manifest (`goal_id` + `evidence`) must match its family's
+/// evidence struct.
+fn check_result_evidence(result: &Value) -> Result<()> {
+    match (
+        result.get("goal_id").and_then(|v| v.as_str()),
+        result.get("evidence"),
+    ) {
+        (Some(goal_id), Some(evidence)) => evidence::check(goal_id, evidence),
+        _ => Ok(()),
+    }
+}
+
+/// Check one case inside its own output root; the evaluated case is written to `case.json`.
+async fn run_case(case: GoldenCaseRaw, dir: &Path) -> (bool, Option<String>) {
+    if let Err(e) = tokio::fs::create_dir_all(dir).await {
+        return (
+            false,
+            Some(format!("isolated root {}: {}", dir.display(), e)),
+        );
+    }
+    let ok_bits = match parse_bits(&case.bits) {
+        Some(b) => bits_valid(&b),
+        None => false,
+    };
+    let (ok, reason) = if !ok_bits {
+        (false, Some("invalid or out-of-range bits".to_string()))
+    } else if let Err(e) = check_result_evidence(&case.result) {
+        (false, Some(e.to_string()))
+    } else {
+        (true, None)
+    };
+    let _ = tokio::fs::write(
+        dir.join("case.json"),
+        serde_json::to_string_pretty(&json!({
+            "test": case.test,
+            "assertion": case.assertion,
+            "result": case.result,
+            "bits": case.bits,
+            "ok": ok,
+            "reason": reason
+        }))
+        .unwrap_or_default(),
+    )
+    .await;
+    (ok, reason)
+}
+
 pub async fn validate_golden(name: &str) -> Result<GoldenSummary> {
+    validate_golden_with(name, default_parallelism()).await
+}
+
+/// Run the suite's cases concurrently (at most `parallelism` at a time); `details` keep file
+/// order.
+pub async fn validate_golden_with(name: &str, parallelism: usize) -> Result<GoldenSummary> {
     let path = format!("trace/golden/{}.json", name);
     let s = tokio::fs::read_to_string(&path).await?;
     let raw: Vec<GoldenCaseRaw> = serde_json::from_str(&s)?;
 
-    let mut details = Vec::new();
-    let mut passed = 0usize;
-    for case in raw.into_iter() {
-        let ok_bits = match parse_bits(&case.bits) {
-            Some(b) => bits_valid(&b),
-            None => false,
-        };
-        let (ok, reason) = if ok_bits {
-            (true, None)
-        } else {
-            (false, Some("invalid or out-of-range bits".to_string()))
-        };
-        if ok {
-            passed += 1;
-        }
-        details.push(GoldenCase {
-            test: case.test,
-            ok,
-            reason,
+    let parallelism = parallelism.clamp(1, MAX_PARALLELISM);
+    let started = Instant::now();
+    let tmp_root = suite_tmp_root(name);
+    let sem = Arc::new(Semaphore::new(parallelism));
+    let mut handles = Vec::with_capacity(raw.len());
+    for (i, case) in raw.into_iter().enumerate() {
+        let sem = sem.clone();
+        let dir = tmp_root.join(i.to_string());
+        let test = case.test.clone();
+        let handle = tokio::spawn(async move {
+            let _permit = sem.acquire_owned().await;
+            let mut root = CaseRoot { dir, keep: false };
+            let t0 = Instant::now();
+            let test = case.test.clone();
+            let (ok, reason) = run_case(case, &root.dir).await;
+            let wall_ms = t0.elapsed().as_millis() as u64;
+            let out_dir = (!ok).then(|| root.keep());
+            GoldenCase {
+                test,
+                ok,
+                reason,
+                wall_ms,
+                out_dir,
+            }
         });
+        handles.push((test, handle));
+    }
+
+    let mut details = Vec::with_capacity(handles.len());
+    for (test, handle) in handles {
+        details.push(handle.await.unwrap_or_else(|e| GoldenCase {
+            test,
+            ok: false,
+            reason: Some(format!("case task failed: {}", e)),
+            wall_ms: 0,
+            out_dir: None,
+        }));
     }
+    // Drop the suite root when every case passed (failed cases keep theirs).
+    let _ = tokio::fs::remove_dir(&tmp_root).await;
+    let passed = details.iter().filter(|c| c.ok).count();
     let total = details.len();
     let failed = total - passed;
     let bits = if failed == 0 {
@@ -146,6 +282,8 @@ pub async fn validate_golden(name: &str) -> Result<GoldenSummary> {
         failed,
         details,
         bits,
+        parallelism,
+        wall_ms: started.elapsed().as_millis() as u64,
     })
 }
 
@@ -159,4 +297,22 @@ mod tests {
         assert_eq!(summary.failed, 0, "golden cases should have valid bits");
         assert!(summary.total >= 1);
     }
+
+    #[tokio::test]
+    async fn golden_parallel_matches_serial_order() {
+        let serial = validate_golden_with("wolfram_unity", 1)
+            .await
+            .expect("serial");
+        let parallel = validate_golden_with("wolfram_unity", 8)
+            .await
+            .expect("parallel");
+        let names =
+            |s: &GoldenSummary| s.details.iter().map(|c| c.test.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&serial), names(&parallel));
+        assert_eq!(serial.passed, parallel.passed);
+        let capped = validate_golden_with("wolfram_unity", usize::MAX)
+            .await
+            .expect("capped");
+        assert_eq!(capped.parallelism, MAX_PARALLELISM);
+    }
 }
@@ -0,0 +1,145 @@
+//! Shared `graph.json` document emitted by every graph family (thread, receipts, api, ruliad).
+//!
+//! The per-family `index.html` pages stay as they are; `graph.json` is the common interchange
+//! format read by the reusable viewer at `ui/graph.html` (`/ui/graph.html?src=<graph.json url>`).
+
+use anyhow::{Context, Result};
+use one_engine::atomic;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use utoipa::ToSchema;
+
+use super::urls::url_for;
+
+/// Schema identifier written into every document; bump on breaking changes.
+pub const SCHEMA: &str = "one-engine.graph/v1";
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GraphDoc {
+    pub schema: String,
+    /// Graph family: "thread" | "receipts" | "api" | "system" | "ruliad".
+    pub kind: String,
+    pub title: String,
+    pub generated_at: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// Family-specific parameters (thread, filters, rules, ...).
+    #[serde(default)]
+    pub meta: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GraphNode {
+    pub id: String,
+    pub label: String,
+    /// Node kind used for filtering/colouring (e.g. role, HTTP method, "receipt", "state").
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ok: Option<bool>,
+    /// Relative node size (1.0 = default), e.g. scaled by cost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<f32>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub bits: BTreeMap<String, f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<GraphLink>,
+    /// Free-form details shown in the viewer's node panel.
+    #[serde(default)]
+    pub data: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GraphLink {
+    /// Link relation, e.g. "receipt" | "view".
+    pub rel: String,
+    pub href: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// Edge kind used for filtering (e.g. "seq", "ref", "hop", "rewrite").
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f32>,
+}
+
+impl GraphDoc {
+    pub fn new(kind: &str, title: impl Into<String>) -> Self {
+        Self {
+            schema: SCHEMA.to_string(),
+            kind: kind.to_string(),
+            title: title.into(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            meta: Value::Null,
+        }
+    }
+}
+
+impl GraphNode {
+    pub fn new(id: impl Into<String>, label: impl Into<String>, kind: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            label: label.into(),
+            kind: kind.into(),
+            group: None,
+            ok: None,
+            size: None,
+            bits: BTreeMap::new(),
+            links: Vec::new(),
+            data: Value::Null,
+        }
+    }
+
+    pub fn link(mut self, rel: &str, href: impl Into<String>) -> Self {
+        self.links.push(GraphLink {
+            rel: rel.to_string(),
+            href: href.into(),
+        });
+        self
+    }
+}
+
+impl GraphEdge {
+    pub fn new(source: impl Into<String>, target: impl Into<String>, kind: &str) -> Self {
+        Self {
+            source: source.into(),
+            target: target.into(),
+            kind: kind.to_string(),
+            label: None,
+            weight: None,
+        }
+    }
+}
+
+/// Write `graph.json` into `out_dir`.
+pub fn write(out_dir: &Path, doc: &GraphDoc) -> Result<()> {
+    atomic::write(
+        out_dir.join("graph.json"),
+        serde_json::to_string_pretty(doc).unwrap_or_default(),
+    )
+    .with_context(|| "write graph.json".to_string())
+}
+
+/// Viewer URL for a `graph.json` served at `json_path` (a server path such as `/runs/graphs/<id>/graph.json`).
+pub fn viewer_url(json_path: &str) -> String {
+    url_for(&format!("/ui/graph.html?src={}", url_for(json_path)))
+}
+
+/// Small HTML link block for the per-family index pages.
+pub fn viewer_link_html(json_path: &str) -> String {
+    format!(
+        "<div class=\"muted\" style=\"margin-top:8px\">Interactive: <a href=\"{}\" target=\"_blank\" rel=\"noreferrer\">graph viewer</a> · <a href=\"graph.json\">graph.json</a></div>",
+        viewer_url(json_path)
+    )
+}
@@ -0,0 +1,683 @@
+//! DOT → SVG for the graph goals, so `index.html` can show a picture instead of raw DOT.
+//!
+//! `ONE_ENGINE_GRAPH_LAYOUT` picks the engine: `auto` (default; graphviz `dot` when it is on
+//! PATH, else the built-in layout), `graphviz`, `builtin` or `off`. A `dot` that outlives
+//! `GRAPHVIZ_TIMEOUT` is killed and the built-in layout takes over. The built-in engine only
+//! understands the DOT subset our generators emit (node/edge statements with `label`,
+//! `fillcolor`, `color`; `rankdir`) and does a small layered (Sugiyama-style) layout.
+
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+const MAX_LAYOUT_NODES: usize = 1500;
+const MAX_LABEL_CHARS: usize = 48;
+const CHAR_W: f32 = 7.0;
+const LINE_H: f32 = 16.0;
+const NODE_GAP: f32 = 24.0;
+const RANK_GAP: f32 = 56.0;
+const MARGIN: f32 = 20.0;
+/// A `dot` still running after this is killed and the builtin layout is used instead.
+const GRAPHVIZ_TIMEOUT: Duration = Duration::from_secs(20);
+
+static GRAPHVIZ_AVAILABLE: Lazy<bool> = Lazy::new(|| {
+    Command::new("dot")
+        .arg("-V")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+});
+
+pub struct RenderedSvg {
+    pub svg: String,
+    /// "graphviz" | "builtin"
+    pub engine: &'static str,
+}
+
+fn layout_mode() -> String {
+    std::env::var("ONE_ENGINE_GRAPH_LAYOUT")
+        .unwrap_or_else(|_| "auto".to_string())
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Render DOT to SVG with the configured engine; `None` when disabled or the graph is too big.
+pub async fn render_svg(dot: &str) -> Option<RenderedSvg> {
+    let mode = layout_mode();
+    if mode == "off" {
+        return None;
+    }
+    // Simulation runs no host programs; the builtin layout stands in for `dot`.
+    if (mode == "auto" || mode == "graphviz" || mode == "dot")
+        && !crate::engine::simulation::active()
+        && *GRAPHVIZ_AVAILABLE
+    {
+        match graphviz_svg(dot).await {
+            Ok(svg) => {
+                return Some(RenderedSvg {
+                    svg,
+                    engine: "graphviz",
+                })
+            }
+            Err(e) => tracing::warn!("graphviz render failed, using builtin layout: {}", e),
+        }
+    }
+    let graph = parse_dot(dot);
+    if graph.nodes.is_empty() || graph.nodes.len() > MAX_LAYOUT_NODES {
+        return None;
+    }
+    Some(RenderedSvg {
+        svg: layered_svg(&graph),
+        engine: "builtin",
+    })
+}
+
+/// Render and write `<out_dir>/<stem>.svg`.
+pub async fn write_svg(out_dir: &Path, stem: &str, dot: &str) -> Option<RenderedSvg> {
+    let rendered = render_svg(dot).await?;
+    match one_engine::atomic::write(
+        out_dir.join(format!("{}.svg", stem)),
+        rendered.svg.as_bytes(),
+    ) {
+        Ok(()) => Some(rendered),
+        Err(e) => {
+            tracing::warn!("write {}.svg failed: {}", stem, e);
+            None
+        }
+    }
+}
+
+/// `write_svg` for the synchronous graph builders: runs it to completion on a scratch runtime
+/// on its own thread, so it works whether or not the caller is already inside one.
+pub fn write_svg_blocking(out_dir: &Path, stem: &str, dot: &str) -> Option<RenderedSvg> {
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .ok()?;
+            rt.block_on(write_svg(out_dir, stem, dot))
+        })
+        .join()
+        .ok()
+        .flatten()
+    })
+}
+
+/// Inline `<section>` for an index.html viewer (empty when nothing was rendered).
+pub fn embed_html(rendered: Option<&RenderedSvg>, file: &str) -> String {
+    match rendered {
+        Some(r) => format!(
+            "<section style=\"margin-top:12px\"><div class=\"muted\">Layout: {} · <a href=\"{}\">{}</a></div><div style=\"overflow:auto;border:1px solid #d0d7de;border-radius:12px;margin-top:6px;max-height:80vh\">{}</div></section>",
+            r.engine, file, file, r.svg
+        ),
+        None => String::new(),
+    }
+}
+
+async fn graphviz_svg(dot: &str) -> Result<String, String> {
+    let mut child = tokio::process::Command::new("dot")
+        .arg("-Tsvg")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let stdin = child.stdin.take();
+    // Feed stdin while collecting output so a large graph can't deadlock on full pipes;
+    // dropping `stdin` after the write closes it.
+    let feed = async move {
+        if let Some(mut stdin) = stdin {
+            stdin.write_all(dot.as_bytes()).await?;
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    let run = async move {
+        let (fed, out) = tokio::join!(feed, child.wait_with_output());
+        fed.and(out)
+    };
+    let out = tokio::time::timeout(GRAPHVIZ_TIMEOUT, run)
+        .await
+        .map_err(|_| format!("dot timed out after {}s", GRAPHVIZ_TIMEOUT.as_secs()))?
+        .map_err(|e| e.to_string())?;
+    if !out.status.success() {
+        return Err(String::from_utf8_lossy(&out.stderr)
+            .chars()
+            .take(300)
+            .collect());
+    }
+    let svg = String::from_utf8_lossy(&out.stdout);
+    // Drop the XML prolog/doctype so the SVG can be inlined into HTML.
+    let start = svg.find("<svg").ok_or("no <svg> in graphviz output")?;
+    Ok(svg[start..].to_string())
+}
+
+#[derive(Debug, Default)]
+struct DotNode {
+    id: String,
+    label: String,
+    fill: Option<String>,
+    stroke: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct DotGraph {
+    left_right: bool,
+    default_fill: Option<String>,
+    nodes: Vec<DotNode>,
+    edges: Vec<(usize, usize, Option<String>)>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Tok {
+    Id(String),
+    Arrow,
+    Open,
+    Close,
+    Eq,
+    Sep,
+    End,
+    Brace,
+}
+
+fn tokenize(dot: &str) -> Vec<Tok> {
+    let mut out = Vec::new();
+    let mut chars = dot.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' | '\n' => out.push(Tok::End),
+            c if c.is_whitespace() => {}
+            '"' => {
+                let mut s = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some('n') | Some('l') | Some('r') => s.push('\n'),
+                            Some(o) => s.push(o),
+                            None => {}
+                        },
+                        '"' => break,
+                        o => s.push(o),
+                    }
+                }
+                out.push(Tok::Id(s));
+            }
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                out.push(Tok::Arrow);
+            }
+            '[' => out.push(Tok::Open),
+            ']' => out.push(Tok::Close),
+            '=' => out.push(Tok::Eq),
+            ',' => out.push(Tok::Sep),
+            '{' | '}' => out.push(Tok::Brace),
+            c => {
+                let mut s = c.to_string();
+                while let Some(&n) = chars.peek() {
+                    if n.is_alphanumeric() || matches!(n, '_' | '.' | '#') {
+                        s.push(n);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                out.push(Tok::Id(s));
+            }
+        }
+    }
+    out
+}
+
+fn parse_dot(dot: &str) -> DotGraph {
+    let toks = tokenize(dot);
+    let mut g = DotGraph::default();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut i = 0;
+
+    fn node_idx(g: &mut DotGraph, index: &mut HashMap<String, usize>, id: &str) -> usize {
+        *index.entry(id.to_string()).or_insert_with(|| {
+            g.nodes.push(DotNode {
+                id: id.to_string(),
+                ..Default::default()
+            });
+            g.nodes.len() - 1
+        })
+    }
+
+    while i < toks.len() {
+        // Collect one statement: ids/arrows up to an optional attribute list and End.
+        let mut ids: Vec<String> = Vec::new();
+        let mut attrs: Vec<(String, String)> = Vec::new();
+        while i < toks.len() && !matches!(toks[i], Tok::End | Tok::Brace) {
+            match &toks[i] {
+                Tok::Id(s) => {
+                    if toks.get(i + 1) == Some(&Tok::Eq) {
+                        if let Some(Tok::Id(v)) = toks.get(i + 2) {
+                            attrs.push((s.clone(), v.clone()));
+                        }
+                        i += 2;
+                    } else {
+                        ids.push(s.clone());
+                    }
+                }
+                Tok::Open => {
+                    i += 1;
+                    while i < toks.len() && toks[i] != Tok::Close {
+                        if let (Tok::Id(k), Some(Tok::Eq), Some(Tok::Id(v))) =
+                            (&toks[i], toks.get(i + 1), toks.get(i + 2))
+                        {
+                            attrs.push((k.clone(), v.clone()));
+                            i += 2;
+                        }
+                        i += 1;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        i += 1;
+        let attr = |k: &str| attrs.iter().find(|(a, _)| a == k).map(|(_, v)| v.clone());
+        let is_edge = ids.len() >= 2;
+        match ids.first().map(|s| s.as_str()) {
+            None => {
+                if let Some(rd) = attr("rankdir") {
+                    g.left_right = rd.eq_ignore_ascii_case("LR") || rd.eq_ignore_ascii_case("RL");
+                }
+            }
+            Some("digraph") | Some("graph") | Some("strict") | Some("subgraph") => {}
+            Some("node") if !is_edge => {
+                if let Some(f) = attr("fillcolor") {
+                    g.default_fill = Some(f);
+                }
+            }
+            Some("edge") if !is_edge => {}
+            Some(first) if !is_edge => {
+                let idx = node_idx(&mut g, &mut index, first);
+                let n = &mut g.nodes[idx];
+                if let Some(l) = attr("label") {
+                    n.label = l;
+                }
+                if let Some(f) = attr("fillcolor") {
+                    n.fill = Some(f);
+                }
+                if let Some(c) = attr("color") {
+                    n.stroke = Some(c);
+                }
+            }
+            Some(_) => {
+                // a -> b -> c
+                let label = attr("label").filter(|l| !l.is_empty());
+                let idxs: Vec<usize> = ids
+                    .iter()
+                    .map(|id| node_idx(&mut g, &mut index, id))
+                    .collect();
+                for w in idxs.windows(2) {
+                    g.edges.push((w[0], w[1], label.clone()));
+                }
+            }
+        }
+    }
+    g
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn label_lines(n: &DotNode) -> Vec<String> {
+    let raw = if n.label.is_empty() {
+        n.id.as_str()
+    } else {
+        n.label.as_str()
+    };
+    raw.lines()
+        .take(6)
+        .map(|l| {
+            if l.chars().count() > MAX_LABEL_CHARS {
+                format!(
+                    "{}…",
+                    l.chars().take(MAX_LABEL_CHARS - 1).collect::<String>()
+                )
+            } else {
+                l.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Longest-path layering (back edges from a DFS are ignored), a few barycenter sweeps to
+/// reduce crossings, then straight packing of each layer.
+fn layered_svg(g: &DotGraph) -> String {
+    let n = g.nodes.len();
+    let mut succ: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (a, b, _) in &g.edges {
+        if a != b {
+            succ[*a].push(*b);
+        }
+    }
+
+    // Mark DFS back edges so cycles don't break layering.
+    let mut state = vec![0u8; n];
+    let mut back: std::collections::HashSet<(usize, usize)> = std::collections::HashSet::new();
+    for root in 0..n {
+        if state[root] != 0 {
+            continue;
+        }
+        let mut stack: Vec<(usize, usize)> = vec![(root, 0)];
+        state[root] = 1;
+        while let Some((v, k)) = stack.pop() {
+            if k < succ[v].len() {
+                stack.push((v, k + 1));
+                let w = succ[v][k];
+                match state[w] {
+                    0 => {
+                        state[w] = 1;
+                        stack.push((w, 0));
+                    }
+                    1 => {
+                        back.insert((v, w));
+                    }
+                    _ => {}
+                }
+            } else {
+                state[v] = 2;
+            }
+        }
+    }
+
+    let mut indeg = vec![0usize; n];
+    for (v, ws) in succ.iter().enumerate() {
+        for &w in ws {
+            if !back.contains(&(v, w)) {
+                indeg[w] += 1;
+            }
+        }
+    }
+    let mut rank = vec![0usize; n];
+    let mut queue: std::collections::VecDeque<usize> = (0..n).filter(|v| indeg[*v] == 0).collect();
+    while let Some(v) = queue.pop_front() {
+        for &w in &succ[v] {
+            if back.contains(&(v, w)) {
+                continue;
+            }
+            rank[w] = rank[w].max(rank[v] + 1);
+            indeg[w] -= 1;
+            if indeg[w] == 0 {
+                queue.push_back(w);
+            }
+        }
+    }
+
+    let ranks = rank.iter().copied().max().unwrap_or(0) + 1;
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); ranks];
+    for v in 0..n {
+        layers[rank[v]].push(v);
+    }
+    let mut pred: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (v, ws) in succ.iter().enumerate() {
+        for &w in ws {
+            pred[w].push(v);
+        }
+    }
+    let mut pos = vec![0f32; n];
+    let reindex = |layers: &Vec<Vec<usize>>, pos: &mut Vec<f32>| {
+        for layer in layers {
+            for (i, v) in layer.iter().enumerate() {
+                pos[*v] = i as f32;
+            }
+        }
+    };
+    reindex(&layers, &mut pos);
+    for sweep in 0..4 {
+        let down = sweep % 2 == 0;
+        let order: Vec<usize> = if down {
+            (1..ranks).collect()
+        } else {
+            (0..ranks.saturating_sub(1)).rev().collect()
+        };
+        for r in order {
+            let neigh = if down { &pred } else { &succ };
+            let mut keyed: Vec<(f32, usize)> = layers[r]
+                .iter()
+                .map(|&v| {
+                    let ns = &neigh[v];
+                    let key = if ns.is_empty() {
+                        pos[v]
+                    } else {
+                        ns.iter().map(|u| pos[*u]).sum::<f32>() / ns.len() as f32
+                    };
+                    (key, v)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            layers[r] = keyed.into_iter().map(|(_, v)| v).collect();
+            reindex(&layers, &mut pos);
+        }
+    }
+
+    let lines: Vec<Vec<String>> = g.nodes.iter().map(label_lines).collect();
+    let size: Vec<(f32, f32)> = lines
+        .iter()
+        .map(|ls| {
+            let w = ls.iter().map(|l| l.chars().count()).max().unwrap_or(1) as f32 * CHAR_W + 20.0;
+            (w.clamp(48.0, 360.0), ls.len().max(1) as f32 * LINE_H + 12.0)
+        })
+        .collect();
+
+    // Along-rank extent (x for TB, y for LR) and across-rank thickness per layer.
+    let along = |v: usize| if g.left_right { size[v].1 } else { size[v].0 };
+    let across = |v: usize| if g.left_right { size[v].0 } else { size[v].1 };
+    let layer_len: Vec<f32> = layers
+        .iter()
+        .map(|l| {
+            l.iter().map(|v| along(*v)).sum::<f32>() + NODE_GAP * l.len().saturating_sub(1) as f32
+        })
+        .collect();
+    let max_len = layer_len.iter().cloned().fold(0.0, f32::max);
+    let mut center = vec![(0f32, 0f32); n];
+    let mut offset = MARGIN;
+    for (r, layer) in layers.iter().enumerate() {
+        let thick = layer.iter().map(|v| across(*v)).fold(0.0, f32::max);
+        let mut cursor = MARGIN + (max_len - layer_len[r]) / 2.0;
+        for &v in layer {
+            let a = cursor + along(v) / 2.0;
+            let c = offset + thick / 2.0;
+            center[v] = if g.left_right { (c, a) } else { (a, c) };
+            cursor += along(v) + NODE_GAP;
+        }
+        offset += thick + RANK_GAP;
+    }
+    let (width, height) = if g.left_right {
+        (offset - RANK_GAP + MARGIN, max_len + 2.0 * MARGIN)
+    } else {
+        (max_len + 2.0 * MARGIN, offset - RANK_GAP + MARGIN)
+    };
+
+    let mut s = format!(
+        "<svg width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.0} {h:.0}\" xmlns=\"http://www.w3.org/2000/svg\">",
+        w = width.max(1.0),
+        h = height.max(1.0)
+    );
+    s.push_str("<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"7\" markerHeight=\"7\" orient=\"auto-start-reverse\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"#868e96\"/></marker></defs>");
+    s.push_str("<style>text{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;font-size:12px;fill:#212529}.el{font-size:10px;fill:#868e96}</style>");
+
+    for (a, b, label) in &g.edges {
+        let (a, b) = (*a, *b);
+        let ((ax, ay), (bx, by)) = (center[a], center[b]);
+        let forward = rank[b] > rank[a];
+        let path = if g.left_right {
+            let (sx, ex) = if forward {
+                (ax + size[a].0 / 2.0, bx - size[b].0 / 2.0)
+            } else {
+                (ax - size[a].0 / 2.0, bx + size[b].0 / 2.0)
+            };
+            let mid = (sx + ex) / 2.0;
+            let bend = if forward { 0.0 } else { -RANK_GAP };
+            format!(
+                "M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}",
+                sx,
+                ay,
+                mid,
+                ay + bend,
+                mid,
+                by + bend,
+                ex,
+                by
+            )
+        } else {
+            let (sy, ey) = if forward {
+                (ay + size[a].1 / 2.0, by - size[b].1 / 2.0)
+            } else {
+                (ay - size[a].1 / 2.0, by + size[b].1 / 2.0)
+            };
+            let mid = (sy + ey) / 2.0;
+            let bend = if forward { 0.0 } else { RANK_GAP };
+            format!(
+                "M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}",
+                ax,
+                sy,
+                ax + bend,
+                mid,
+                bx + bend,
+                mid,
+                bx,
+                ey
+            )
+        };
+        s.push_str(&format!(
+            "<path d=\"{}\" fill=\"none\" stroke=\"#adb5bd\" stroke-width=\"1.5\" marker-end=\"url(#arrow)\"/>",
+            path
+        ));
+        if let Some(l) = label {
+            s.push_str(&format!(
+                "<text class=\"el\" x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                (ax + bx) / 2.0,
+                (ay + by) / 2.0,
+                xml_escape(l)
+            ));
+        }
+    }
+
+    for (v, node) in g.nodes.iter().enumerate() {
+        let (cx, cy) = center[v];
+        let (w, h) = size[v];
+        let fill = node
+            .fill
+            .as_deref()
+            .or(g.default_fill.as_deref())
+            .unwrap_or("#f8f9fa");
+        let stroke = node.stroke.as_deref().unwrap_or("#868e96");
+        s.push_str(&format!(
+            "<g><title>{}</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"8\" fill=\"{}\" stroke=\"{}\"/>",
+            xml_escape(&node.id),
+            cx - w / 2.0,
+            cy - h / 2.0,
+            w,
+            h,
+            xml_escape(fill),
+            xml_escape(stroke)
+        ));
+        let top = cy - h / 2.0 + 6.0 + LINE_H * 0.8;
+        for (i, l) in lines[v].iter().enumerate() {
+            s.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+                cx,
+                top + i as f32 * LINE_H,
+                xml_escape(l)
+            ));
+        }
+        s.push_str("</g>");
+    }
+    s.push_str("</svg>");
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(x, y)` of the node box titled `id` in a `layered_svg` picture.
+    fn rect_at(svg: &str, id: &str) -> (f32, f32) {
+        let at = svg.find(&format!("<title>{}</title><rect", id)).unwrap();
+        let attr = |name: &str| {
+            let rest = &svg[at..];
+            let start = rest.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+            rest[start..start + rest[start..].find('"').unwrap()]
+                .parse::<f32>()
+                .unwrap()
+        };
+        (attr("x"), attr("y"))
+    }
+
+    #[test]
+    fn parse_dot_reads_nodes_edge_chains_and_defaults() {
+        let g = parse_dot(
+            r##"digraph G {
+  rankdir=LR;
+  node [shape=box, fillcolor="#eee"];
+  a [label="first\nline", color="red"];
+  a -> b -> c [label="next"];
+  "d e" -> a;
+}"##,
+        );
+        assert!(g.left_right);
+        assert_eq!(g.default_fill.as_deref(), Some("#eee"));
+        let ids: Vec<&str> = g.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c", "d e"]);
+        assert_eq!(g.nodes[0].label, "first\nline");
+        assert_eq!(g.nodes[0].stroke.as_deref(), Some("red"));
+        assert_eq!(
+            g.edges,
+            [
+                (0, 1, Some("next".to_string())),
+                (1, 2, Some("next".to_string())),
+                (3, 0, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn layered_svg_ranks_edges_downward_and_survives_cycles() {
+        let svg = layered_svg(&parse_dot(
+            "digraph { a -> b; b -> c; a -> c; c -> a; x [label=\"<x>\"]; }",
+        ));
+        let (a, b, c) = (
+            rect_at(&svg, "a").1,
+            rect_at(&svg, "b").1,
+            rect_at(&svg, "c").1,
+        );
+        assert!(a < b && b < c, "{} {} {}", a, b, c);
+        // Unconnected nodes sit in the first layer, side by side with its other nodes.
+        assert_eq!(rect_at(&svg, "x").1, a);
+        assert_ne!(rect_at(&svg, "x").0, rect_at(&svg, "a").0);
+        assert_eq!(svg.matches("marker-end").count(), 4);
+        assert!(svg.contains("&lt;x&gt;") && !svg.contains("<x>"));
+
+        let lr = layered_svg(&parse_dot("digraph { rankdir=LR; a -> b; }"));
+        assert!(rect_at(&lr, "a").0 < rect_at(&lr, "b").0);
+        assert_eq!(rect_at(&lr, "a").1, rect_at(&lr, "b").1);
+    }
+
+    #[test]
+    fn long_labels_are_cut_to_six_short_lines() {
+        let node = DotNode {
+            id: "n".into(),
+            label: format!("{}\n2\n3\n4\n5\n6\n7", "x".repeat(100)),
+            ..Default::default()
+        };
+        let lines = label_lines(&node);
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0].chars().count(), MAX_LABEL_CHARS);
+        assert!(lines[0].ends_with('…'));
+    }
+}
@@ -1,9 +1,18 @@
 use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use one_engine::{atomic, jsonl, storage};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::graph_doc::{self, GraphDoc, GraphEdge, GraphNode};
+use super::graph_layout;
+use super::urls::url_for;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BitsLite {
@@ -23,6 +32,8 @@ pub struct ThreadGraphResult {
     pub out_dir: PathBuf,
     pub nodes: usize,
     pub edges: usize,
+    /// Engine that rendered `graph.svg` ("graphviz" | "builtin"), if any.
+    pub layout: Option<&'static str>,
     pub thread: String,
 }
 
@@ -44,6 +55,9 @@ pub struct ReceiptsGraphResult {
     pub out_dir: PathBuf,
     pub nodes: usize,
     pub edges: usize,
+    /// Engine that rendered `graph.svg` ("graphviz" | "builtin"), if any.
+    pub layout: Option<&'static str>,
+    pub stats: ScanStats,
 }
 
 #[derive(Debug, Clone)]
@@ -51,6 +65,8 @@ pub struct ApiGraphResult {
     pub out_dir: PathBuf,
     pub nodes: usize,
     pub edges: usize,
+    /// Engine that rendered `graph.svg` ("graphviz" | "builtin"), if any.
+    pub layout: Option<&'static str>,
 }
 
 #[derive(Debug, Clone)]
@@ -63,6 +79,28 @@ pub struct ApiGraphOpts {
     pub collapse: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct SystemGraphResult {
+    pub out_dir: PathBuf,
+    pub nodes: usize,
+    pub edges: usize,
+    /// Engine that rendered `graph.svg` ("graphviz" | "builtin"), if any.
+    pub layout: Option<&'static str>,
+    pub users: usize,
+    pub threads: usize,
+    pub runs: usize,
+    /// Clusters folded into summary nodes to stay within `max_nodes`.
+    pub collapsed: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemGraphOpts {
+    pub since_hours: u64,
+    pub max_nodes: usize,
+    pub user_id: Option<String>,
+    pub include_api: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct ApiTraceEvent {
     ts: String,
@@ -90,6 +128,7 @@ fn is_safe_segment(seg: &str) -> bool {
         && !seg.contains('/')
         && !seg.contains('\\')
         && !seg.contains("..")
+        && seg != "."
         && seg
             .chars()
             .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
@@ -134,31 +173,14 @@ fn truncate_chars(s: &str, max_chars: usize) -> String {
 }
 
 fn tail_lines(path: &Path, limit: usize, max_bytes: u64) -> Result<Vec<String>> {
-    let meta = fs::metadata(path).with_context(|| format!("metadata {}", path.display()))?;
-    let len = meta.len();
-    let start = if len > max_bytes { len - max_bytes } else { 0 };
-
-    let mut f = fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
-    if start > 0 {
-        f.seek(SeekFrom::Start(start))
-            .with_context(|| format!("seek {}", path.display()))?;
-    }
-    let mut buf = Vec::new();
-    f.read_to_end(&mut buf)
-        .with_context(|| format!("read {}", path.display()))?;
-
-    let mut s = String::from_utf8_lossy(&buf).to_string();
-    if start > 0 {
-        if let Some(idx) = s.find('\n') {
-            s = s[idx + 1..].to_string();
-        }
-    }
+    one_engine::jsonl::tail(path, limit, max_bytes)
+        .with_context(|| format!("tail {}", path.display()))
+}
 
-    let mut lines: Vec<&str> = s.lines().filter(|l| !l.trim().is_empty()).collect();
-    if lines.len() > limit {
-        lines = lines[lines.len() - limit..].to_vec();
-    }
-    Ok(lines.into_iter().map(|l| l.to_string()).collect())
+/// [`tail_lines`] across a thread's rotated `<thread>.jsonl.<n>` segments.
+fn tail_thread_lines(path: &Path, limit: usize, max_bytes: u64) -> Result<Vec<String>> {
+    jsonl::tail_segments(&storage::resolve(path), limit, max_bytes)
+        .with_context(|| format!("tail {}", path.display()))
 }
 
 fn receipt_response_json(run_id: &str) -> Option<Value> {
@@ -171,8 +193,10 @@ fn receipt_response_json(run_id: &str) -> Option<Value> {
         .join("receipts")
         .join(run_id)
         .join("response.json");
-    let txt = fs::read_to_string(p).ok()?;
-    serde_json::from_str::<Value>(&txt).ok()
+    let txt = storage::read_to_string(&p).ok()?;
+    let mut resp = serde_json::from_str::<Value>(&txt).ok()?;
+    super::migrate::upgrade_response(&mut resp);
+    Some(resp)
 }
 
 fn get_goal_id(resp: &Value) -> Option<String> {
@@ -379,7 +403,7 @@ fn build_svg(
             format!("run_id: {}", ev.run_id)
         };
 
-        let href = format!("/runs/receipts/{}/RECEIPT.md", ev.run_id);
+        let href = url_for(&format!("/runs/receipts/{}/RECEIPT.md", ev.run_id));
         s.push_str(&format!("<a href=\"{}\" target=\"_blank\" rel=\"noreferrer\">", html_escape(&href)));
         s.push_str(&format!(
             "<rect x=\"{}\" y=\"{}\" rx=\"10\" ry=\"10\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"2\"/>",
@@ -435,15 +459,20 @@ fn build_svg(
     s
 }
 
-fn index_html(
-    run_id: &str,
-    user_id: &str,
-    thread: &str,
-    svg: &str,
+/// What `index.html` shows for one thread graph (`svg`, `table_html` and `layout_html` are
+/// already HTML).
+struct IndexPage<'a> {
+    run_id: &'a str,
+    user_id: &'a str,
+    thread: &'a str,
+    svg: &'a str,
     nodes: usize,
     edges: usize,
-    table_html: &str,
-) -> String {
+    table_html: &'a str,
+    layout_html: &'a str,
+}
+
+fn index_html(page: &IndexPage) -> String {
     format!(
         r#"<!doctype html>
 <html lang="en">
@@ -476,6 +505,7 @@ fn index_html(
   </div>
   <p class="muted">Click a node to open its receipt.</p>
   <div style="margin-top:12px">{svg}</div>
+  {layout_html}
   <h2 style="margin-top:18px">Nodes</h2>
   <div class="muted">This table is the “user relevant” view: natural language + bits + links.</div>
   <table>
@@ -494,16 +524,86 @@ fn index_html(
 </body>
 </html>
 "#,
-        run_id = html_escape(run_id),
-        user_id = html_escape(user_id),
-        thread = html_escape(thread),
-        nodes = nodes,
-        edges = edges,
-        svg = svg,
-        table_html = table_html
+        run_id = html_escape(page.run_id),
+        user_id = html_escape(page.user_id),
+        thread = html_escape(page.thread),
+        nodes = page.nodes,
+        edges = page.edges,
+        svg = page.svg,
+        table_html = page.table_html,
+        layout_html = page.layout_html
     )
 }
 
+fn bits_map(b: &BitsLite) -> std::collections::BTreeMap<String, f32> {
+    [
+        ("a", b.a),
+        ("u", b.u),
+        ("p", b.p),
+        ("e", b.e),
+        ("d", b.d),
+        ("i", b.i),
+        ("r", b.r),
+        ("t", b.t),
+        ("m", b.m),
+    ]
+    .into_iter()
+    .filter_map(|(k, v)| v.map(|v| (k.to_string(), v)))
+    .collect()
+}
+
+/// A thread graph's events, the per-event columns (same index as `events`) and its edges.
+struct ThreadDocParts<'a> {
+    events: &'a [ThreadEvent],
+    goal_ids: &'a [Option<String>],
+    view_urls: &'a [Option<String>],
+    oks: &'a [Option<bool>],
+    bits: &'a [BitsLite],
+    edges: &'a [(usize, usize, &'static str)],
+}
+
+fn thread_doc(user_id: &str, thread: &str, parts: ThreadDocParts) -> GraphDoc {
+    let ThreadDocParts {
+        events,
+        goal_ids,
+        view_urls,
+        oks,
+        bits,
+        edges,
+    } = parts;
+    let mut doc = GraphDoc::new("thread", format!("Thread {thread}"));
+    doc.meta = serde_json::json!({ "user_id": user_id, "thread": thread });
+    for (i, e) in events.iter().enumerate() {
+        let goal = goal_ids.get(i).and_then(|x| x.clone());
+        let label = match goal.as_deref() {
+            Some(g) => format!("{} · {}", g, truncate_chars(&e.content, 60)),
+            None => truncate_chars(&e.content, 60),
+        };
+        let mut node = GraphNode::new(format!("n{i}"), label, e.role.clone()).link(
+            "receipt",
+            url_for(&format!("/runs/receipts/{}/RECEIPT.md", e.run_id)),
+        );
+        if let Some(v) = view_urls.get(i).and_then(|x| x.clone()) {
+            node = node.link("view", v);
+        }
+        node.group = goal.clone();
+        node.ok = oks.get(i).and_then(|x| *x);
+        node.bits = bits.get(i).map(bits_map).unwrap_or_default();
+        node.data = serde_json::json!({
+            "ts": e.ts,
+            "run_id": e.run_id,
+            "goal_id": goal,
+            "content": e.content,
+        });
+        doc.nodes.push(node);
+    }
+    for (a, b, kind) in edges {
+        doc.edges
+            .push(GraphEdge::new(format!("n{a}"), format!("n{b}"), kind));
+    }
+    doc
+}
+
 fn extract_run_ids_limited(v: &Value, out: &mut Vec<String>, depth: usize, budget: &mut usize) {
     if *budget == 0 || depth == 0 {
         return;
@@ -586,11 +686,12 @@ pub fn thread_graph_with_opts(
     let thread = match thread.trim() {
         "" | "auto" => {
             let mut best: Option<(u64, String)> = None;
-            let rd = fs::read_dir(&threads_dir)
-                .with_context(|| format!("read_dir {}", threads_dir.display()))?;
-            for entry in rd.flatten() {
-                let p = entry.path();
-                if p.extension().and_then(|x| x.to_str()) != Some("jsonl") {
+            if !storage::exists(&threads_dir) {
+                return Err(anyhow!("read_dir {}: not found", threads_dir.display()));
+            }
+            for entry in storage::entries(&threads_dir) {
+                let p = threads_dir.join(entry);
+                if p.extension().and_then(|x| x.to_str()) != Some("jsonl") || jsonl::is_rotated_segment(&p) {
                     continue;
                 }
                 let name = p
@@ -601,7 +702,7 @@ pub fn thread_graph_with_opts(
                 if !is_safe_segment(&name) {
                     continue;
                 }
-                let sz = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                let sz = fs::metadata(storage::resolve(&p)).map(|m| m.len()).unwrap_or(0);
                 match best {
                     None => best = Some((sz, name)),
                     Some((bsz, _)) if sz > bsz => best = Some((sz, name)),
@@ -625,7 +726,7 @@ pub fn thread_graph_with_opts(
         .join("threads")
         .join(format!("{thread}.jsonl"));
 
-    if !thread_path.exists() {
+    if !storage::exists(&thread_path) {
         return Err(anyhow!("thread not found: {}", thread_path.display()));
     }
 
@@ -638,7 +739,7 @@ pub fn thread_graph_with_opts(
         opts.label_mode = "nl+goal".to_string();
     }
 
-    let lines = tail_lines(&thread_path, opts.max_events, 1_200_000)?;
+    let lines = tail_thread_lines(&thread_path, opts.max_events, 1_200_000)?;
     let mut events: Vec<ThreadEvent> = Vec::new();
     for line in lines {
         let v: Value = match serde_json::from_str(&line) {
@@ -702,7 +803,7 @@ pub fn thread_graph_with_opts(
     }
 
     let out_dir = root.join("runs").join("graphs").join(external_run_id);
-    fs::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
+    storage::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
 
     // Optional recursion: discover referenced run_ids from receipts and add them as "ref" nodes.
     if opts.recursive {
@@ -806,8 +907,23 @@ pub fn thread_graph_with_opts(
                 &filtered_edges,
                 &opts,
             );
-            fs::write(out_dir.join("graph.dot"), dot.as_bytes())
+            atomic::write(out_dir.join("graph.dot"), dot.as_bytes())
                 .with_context(|| "write graph.dot".to_string())?;
+            let rendered = graph_layout::write_svg_blocking(&out_dir, "graph", &dot);
+            let mut doc = thread_doc(
+                user_id,
+                &thread,
+                ThreadDocParts {
+                    events: &filtered,
+                    goal_ids: &filtered_goal_ids,
+                    view_urls: &filtered_view_urls,
+                    oks: &filtered_ok,
+                    bits: &filtered_bits,
+                    edges: &filtered_edges,
+                },
+            );
+            doc.meta["filter_goal"] = Value::String(fg.clone());
+            graph_doc::write(&out_dir, &doc)?;
             let events_json = serde_json::json!({
                 "user_id": user_id,
                 "thread": thread,
@@ -823,11 +939,11 @@ pub fn thread_graph_with_opts(
                         "view_url": filtered_view_urls.get(i).and_then(|x| x.clone()),
                         "actual_success": filtered_ok.get(i).and_then(|x| *x),
                         "bits": filtered_bits.get(i).cloned().unwrap_or_default(),
-                        "receipt_url": format!("/runs/receipts/{}/RECEIPT.md", e.run_id),
+                        "receipt_url": url_for(&format!("/runs/receipts/{}/RECEIPT.md", e.run_id)),
                     })
                 }).collect::<Vec<_>>()
             });
-            fs::write(
+            atomic::write(
                 out_dir.join("events.json"),
                 serde_json::to_string_pretty(&events_json).unwrap_or_default(),
             )
@@ -849,30 +965,46 @@ pub fn thread_graph_with_opts(
                 &filtered_ok,
                 &opts,
             );
-            let html = index_html(
-                external_run_id,
+            let html = index_html(&IndexPage {
+                run_id: external_run_id,
                 user_id,
-                &thread,
-                &svg,
-                filtered.len(),
-                filtered.len().saturating_sub(1),
-                &table_html,
-            );
-            fs::write(out_dir.join("index.html"), html.as_bytes())
+                thread: &thread,
+                svg: &svg,
+                nodes: filtered.len(),
+                edges: filtered.len().saturating_sub(1),
+                table_html: &table_html,
+                layout_html: &format!(
+                    "{}{}",
+                    graph_doc::viewer_link_html(&format!("/runs/graphs/{}/graph.json", external_run_id)),
+                    graph_layout::embed_html(rendered.as_ref(), "graph.svg")
+                ),
+            });
+            atomic::write(out_dir.join("index.html"), html.as_bytes())
                 .with_context(|| "write index.html".to_string())?;
 
             return Ok(ThreadGraphResult {
                 out_dir,
                 nodes: filtered.len(),
                 edges: filtered.len().saturating_sub(1),
+                layout: rendered.map(|r| r.engine),
                 thread,
             });
         }
     }
 
     let dot = build_dot(&events, &goal_ids, &bits, &oks, &edges, &opts);
-    fs::write(out_dir.join("graph.dot"), dot.as_bytes())
+    atomic::write(out_dir.join("graph.dot"), dot.as_bytes())
         .with_context(|| "write graph.dot".to_string())?;
+    let rendered = graph_layout::write_svg_blocking(&out_dir, "graph", &dot);
+    let parts = ThreadDocParts {
+        events: &events,
+        goal_ids: &goal_ids,
+        view_urls: &view_urls,
+        oks: &oks,
+        bits: &bits,
+        edges: &edges,
+    };
+    graph_doc::write(&out_dir, &thread_doc(user_id, &thread, parts))?;
 
     let events_json = serde_json::json!({
         "user_id": user_id,
@@ -888,11 +1020,11 @@ pub fn thread_graph_with_opts(
                 "view_url": view_urls.get(i).and_then(|x| x.clone()),
                 "actual_success": oks.get(i).and_then(|x| *x),
                 "bits": bits.get(i).cloned().unwrap_or_default(),
-                "receipt_url": format!("/runs/receipts/{}/RECEIPT.md", e.run_id),
+                "receipt_url": url_for(&format!("/runs/receipts/{}/RECEIPT.md", e.run_id)),
             })
         }).collect::<Vec<_>>()
     });
-    fs::write(
+    atomic::write(
         out_dir.join("events.json"),
         serde_json::to_string_pretty(&events_json).unwrap_or_default(),
     )
@@ -900,22 +1032,28 @@ pub fn thread_graph_with_opts(
 
     let svg = build_svg(&events, &goal_ids, &view_urls, &bits, &oks, &opts);
     let table_html = build_table_html(&events, &goal_ids, &view_urls, &bits, &oks, &opts);
-    let html = index_html(
-        external_run_id,
+    let html = index_html(&IndexPage {
+        run_id: external_run_id,
         user_id,
-        &thread,
-        &svg,
-        events.len(),
-        edges.len(),
-        &table_html,
-    );
-    fs::write(out_dir.join("index.html"), html.as_bytes())
+        thread: &thread,
+        svg: &svg,
+        nodes: events.len(),
+        edges: edges.len(),
+        table_html: &table_html,
+        layout_html: &format!(
+            "{}{}",
+            graph_doc::viewer_link_html(&format!("/runs/graphs/{}/graph.json", external_run_id)),
+            graph_layout::embed_html(rendered.as_ref(), "graph.svg")
+        ),
+    });
+    atomic::write(out_dir.join("index.html"), html.as_bytes())
         .with_context(|| "write index.html".to_string())?;
 
     Ok(ThreadGraphResult {
         out_dir,
         nodes: events.len(),
         edges: edges.len(),
+        layout: rendered.map(|r| r.engine),
         thread,
     })
 }
@@ -932,7 +1070,7 @@ fn build_table_html(
     for (i, ev) in events.iter().enumerate() {
         let goal = goal_ids.get(i).and_then(|x| x.as_deref()).unwrap_or("");
         let view = view_urls.get(i).and_then(|x| x.as_deref()).unwrap_or("");
-        let receipt = format!("/runs/receipts/{}/RECEIPT.md", ev.run_id);
+        let receipt = url_for(&format!("/runs/receipts/{}/RECEIPT.md", ev.run_id));
         let passed = ok.get(i).and_then(|x| *x);
 
         let t = bits.get(i).and_then(|b| b.t);
@@ -979,7 +1117,7 @@ fn build_table_html(
     out
 }
 
-fn index_html_receipts(run_id: &str, nodes: usize, edges: usize, items_html: &str) -> String {
+fn index_html_receipts(run_id: &str, nodes: usize, edges: usize, items_html: &str, layout_html: &str) -> String {
     format!(
         r#"<!doctype html>
 <html lang="en">
@@ -1008,6 +1146,7 @@ fn index_html_receipts(run_id: &str, nodes: usize, edges: usize, items_html: &st
   <div class="muted" style="margin-top:8px">
     Links: <a href="graph.dot">graph.dot</a> · <a href="events.json">events.json</a>
   </div>
+  {layout_html}
 
   <input id="q" placeholder="filter by goal_id / run_id..." />
   <div class="box">
@@ -1031,7 +1170,8 @@ fn index_html_receipts(run_id: &str, nodes: usize, edges: usize, items_html: &st
         run_id = html_escape(run_id),
         nodes = nodes,
         edges = edges,
-        items_html = items_html
+        items_html = items_html,
+        layout_html = layout_html
     )
 }
 
@@ -1052,7 +1192,7 @@ fn normalize_path(p: &str) -> String {
     p.to_string()
 }
 
-fn index_html_api(run_id: &str, nodes: usize, edges: usize, items_html: &str) -> String {
+fn index_html_api(run_id: &str, nodes: usize, edges: usize, items_html: &str, layout_html: &str) -> String {
     format!(
         r#"<!doctype html>
 <html lang="en">
@@ -1082,6 +1222,7 @@ fn index_html_api(run_id: &str, nodes: usize, edges: usize, items_html: &str) ->
   <div class="muted" style="margin-top:8px">
     Links: <a href=\"graph.dot\">graph.dot</a> · <a href=\"events.json\">events.json</a>
   </div>
+  {layout_html}
   <input id=\"q\" placeholder=\"filter by path/method/run_id...\" />
   <div class=\"box\">
     <table>
@@ -1106,7 +1247,8 @@ fn index_html_api(run_id: &str, nodes: usize, edges: usize, items_html: &str) ->
         run_id = html_escape(run_id),
         nodes = nodes,
         edges = edges,
-        items_html = items_html
+        items_html = items_html,
+        layout_html = layout_html
     )
 }
 
@@ -1217,7 +1359,7 @@ pub fn api_graph(external_run_id: &str, mut opts: ApiGraphOpts) -> Result<ApiGra
     }
 
     let out_dir = root.join("runs").join("graphs").join(external_run_id);
-    fs::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
+    storage::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
 
     // DOT
     let mut dot = String::from("digraph api {\nrankdir=LR;\nnode [shape=box, style=\"rounded,filled\", fontname=\"Helvetica\", fillcolor=\"#f8f9fa\"];\n");
@@ -1228,8 +1370,53 @@ pub fn api_graph(external_run_id: &str, mut opts: ApiGraphOpts) -> Result<ApiGra
         dot.push_str(&format!("  n{} -> n{};\n", a, b));
     }
     dot.push_str("}\n");
-    fs::write(out_dir.join("graph.dot"), dot.as_bytes())
+    atomic::write(out_dir.join("graph.dot"), dot.as_bytes())
         .with_context(|| "write graph.dot".to_string())?;
+    let rendered = graph_layout::write_svg_blocking(&out_dir, "graph", &dot);
+
+    // graph.json: one node per endpoint, transitions aggregated into weighted edges.
+    let mut doc = GraphDoc::new("api", "API trace");
+    doc.meta = serde_json::json!({
+        "only_mutations": opts.only_mutations,
+        "run_id": opts.run_id,
+        "thread": opts.thread,
+        "user_id": opts.user_id,
+    });
+    for (i, key) in nodes.iter().enumerate() {
+        let hits: Vec<&Row> = rows
+            .iter()
+            .filter(|r| format!("{} {}", r.method, r.path) == *key)
+            .collect();
+        let method = key.split(' ').next().unwrap_or("").to_string();
+        let mut node = GraphNode::new(format!("n{i}"), key.clone(), method);
+        node.group = Some(if hits.iter().any(|r| r.mutation) { "mutation" } else { "read" }.to_string());
+        node.ok = Some(hits.iter().all(|r| r.status < 400));
+        let mut run_ids: Vec<&str> = hits.iter().map(|r| r.run_id.as_str()).filter(|r| !r.is_empty()).collect();
+        run_ids.dedup();
+        if let Some(rid) = run_ids.last() {
+            node = node.link(
+                "receipt",
+                url_for(&format!("/runs/receipts/{}/RECEIPT.md", rid)),
+            );
+        }
+        node.data = serde_json::json!({
+            "hits": hits.len(),
+            "total_ms": hits.iter().map(|r| r.ms).sum::<u64>(),
+            "statuses": hits.iter().map(|r| r.status).collect::<std::collections::BTreeSet<_>>(),
+            "run_ids": run_ids,
+        });
+        doc.nodes.push(node);
+    }
+    let mut weights: std::collections::BTreeMap<(usize, usize), usize> = std::collections::BTreeMap::new();
+    for e in &edges {
+        *weights.entry(*e).or_insert(0) += 1;
+    }
+    for ((a, b), n) in weights {
+        let mut edge = GraphEdge::new(format!("n{a}"), format!("n{b}"), "hop");
+        edge.weight = Some(n as f32);
+        doc.edges.push(edge);
+    }
+    graph_doc::write(&out_dir, &doc)?;
 
     // events.json
     let events_json = serde_json::json!({
@@ -1258,7 +1445,7 @@ pub fn api_graph(external_run_id: &str, mut opts: ApiGraphOpts) -> Result<ApiGra
         "nodes": nodes,
         "edges": edges,
     });
-    fs::write(out_dir.join("events.json"), serde_json::to_string_pretty(&events_json).unwrap_or_default())
+    atomic::write(out_dir.join("events.json"), serde_json::to_string_pretty(&events_json).unwrap_or_default())
         .with_context(|| "write events.json".to_string())?;
 
     // index.html
@@ -1279,95 +1466,264 @@ pub fn api_graph(external_run_id: &str, mut opts: ApiGraphOpts) -> Result<ApiGra
             html_escape(&r.thread),
         ));
     }
-    let html = index_html_api(external_run_id, nodes.len(), edges.len(), &items_html);
-    fs::write(out_dir.join("index.html"), html.as_bytes())
+    let html = index_html_api(
+        external_run_id,
+        nodes.len(),
+        edges.len(),
+        &items_html,
+        &format!(
+            "{}{}",
+            graph_doc::viewer_link_html(&format!("/runs/graphs/{}/graph.json", external_run_id)),
+            graph_layout::embed_html(rendered.as_ref(), "graph.svg")
+        ),
+    );
+    atomic::write(out_dir.join("index.html"), html.as_bytes())
         .with_context(|| "write index.html".to_string())?;
 
     Ok(ApiGraphResult {
         out_dir,
         nodes: nodes.len(),
         edges: edges.len(),
+        layout: rendered.map(|r| r.engine),
     })
 }
 
-pub fn receipts_graph(external_run_id: &str, limit: usize) -> Result<ReceiptsGraphResult> {
-    if !is_safe_segment(external_run_id) {
-        return Err(anyhow!("invalid __run_id"));
-    }
-    let limit = limit.clamp(1, 2000);
+/// Default time budget for the receipts scan of `graphs.receipts`.
+pub const RECEIPTS_SCAN_BUDGET: Duration = Duration::from_secs(3);
+/// Upper bound on a caller's `budget_ms`.
+pub const RECEIPTS_SCAN_BUDGET_MAX_MS: u64 = 30_000;
 
-    let root = meta3_root();
-    let receipts_dir = root.join("runs").join("receipts");
-    let rd = fs::read_dir(&receipts_dir)
-        .with_context(|| format!("read_dir {}", receipts_dir.display()))?;
+/// What the receipts graph keeps of one receipt.
+#[derive(Clone)]
+struct ReceiptItem {
+    run_id: String,
+    goal_id: String,
+    ok: Option<bool>,
+    view: Option<String>,
+    mtime: u64,
+    /// Runs this one waited for (`ctx.dependencies` of its `request.json`).
+    depends_on: Vec<String>,
+}
 
-    #[derive(Clone)]
-    struct Item {
-        run_id: String,
-        goal_id: String,
-        ok: Option<bool>,
-        view: Option<String>,
-        mtime: u64,
+type CachedReceipt = (SystemTime, Option<ReceiptItem>);
+
+/// Parsed receipts by run id, with the `response.json` mtime they were parsed at; `None` for
+/// queued stubs (no manifest yet), which are parsed again once the response changes.
+static RECEIPT_CACHE: Lazy<Mutex<HashMap<String, CachedReceipt>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// `f` over `items` on a bounded pool of threads, in order. Items not started before
+/// `deadline` are `None`.
+fn par_map<T: Sync, R: Send>(
+    items: &[T],
+    deadline: Instant,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<Option<R>> {
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .clamp(1, 8)
+        .min(items.len().max(1));
+    let next = AtomicUsize::new(0);
+    let mut out: Vec<Option<R>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    let done: Vec<Vec<(usize, R)>> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut mine = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= items.len() || Instant::now() >= deadline {
+                            return mine;
+                        }
+                        mine.push((i, f(&items[i])));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_default())
+            .collect()
+    });
+    for (i, r) in done.into_iter().flatten() {
+        out[i] = Some(r);
     }
+    out
+}
 
-    let mut items: Vec<Item> = Vec::new();
-    for entry in rd.flatten() {
-        let p = entry.path();
-        if !p.is_dir() {
-            continue;
-        }
-        let run_id = p
-            .file_name()
-            .and_then(|x| x.to_str())
-            .unwrap_or("")
-            .to_string();
-        if !is_safe_segment(&run_id) {
-            continue;
-        }
-        let resp_path = p.join("response.json");
-        let meta = match fs::metadata(&resp_path) {
-            Ok(m) => m,
-            Err(_) => continue,
-        };
-        let mtime = meta
-            .modified()
-            .ok()
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
+fn parse_receipt(receipts_dir: &Path, run_id: &str, mtime: u64) -> Option<ReceiptItem> {
+    let txt = storage::read_to_string(&receipts_dir.join(run_id).join("response.json")).ok()?;
+    let mut resp = serde_json::from_str::<Value>(&txt).ok()?;
+    super::migrate::upgrade_response(&mut resp);
+    // Skip queued stubs (no manifest).
+    resp.get("manifest")?;
+    let depends_on = storage::read_to_string(&receipts_dir.join(run_id).join("request.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
+        .and_then(|req| {
+            req.pointer("/ctx/dependencies/depends_on")
+                .and_then(|v| serde_json::from_value::<Vec<String>>(v.clone()).ok())
+        })
+        .unwrap_or_default();
+    Some(ReceiptItem {
+        run_id: run_id.to_string(),
+        goal_id: get_goal_id(&resp).unwrap_or_else(|| "unknown".to_string()),
+        ok: get_actual_success(&resp),
+        view: get_view_url(&resp),
+        mtime,
+        depends_on,
+    })
+}
 
-        let resp = match receipt_response_json(&run_id) {
-            Some(v) => v,
-            None => continue,
+/// How far [`scan_receipts`] got.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanStats {
+    pub scanned: usize,
+    pub cached: usize,
+    /// The time budget ran out before every candidate was read.
+    pub truncated: bool,
+}
+
+/// The `limit` most recent finished receipts, newest first. Dirs are stat'ed and parsed on a
+/// bounded thread pool, unchanged receipts come from [`RECEIPT_CACHE`], and whatever is read
+/// by `deadline` is returned. Stat'ing gets half the budget, so what it found can be parsed.
+fn scan_receipts(
+    receipts_dir: &Path,
+    limit: usize,
+    deadline: Instant,
+) -> Result<(Vec<ReceiptItem>, ScanStats)> {
+    let now = Instant::now();
+    let stat_deadline = now + deadline.saturating_duration_since(now) / 2;
+    scan_receipts_until(receipts_dir, limit, stat_deadline, deadline)
+}
+
+fn scan_receipts_until(
+    receipts_dir: &Path,
+    limit: usize,
+    stat_deadline: Instant,
+    deadline: Instant,
+) -> Result<(Vec<ReceiptItem>, ScanStats)> {
+    if !storage::exists(receipts_dir) {
+        return Err(anyhow!("read_dir {}: not found", receipts_dir.display()));
+    }
+    let run_ids: Vec<String> = storage::entries(receipts_dir)
+        .into_iter()
+        .filter(|id| is_safe_segment(id))
+        .collect();
+    let mut stats = ScanStats::default();
+
+    let stat = par_map(&run_ids, stat_deadline, |id| {
+        storage::modified(&receipts_dir.join(id).join("response.json"))
+    });
+    stats.truncated = stat.iter().any(|m| m.is_none());
+    let mut candidates: Vec<(String, SystemTime)> = run_ids
+        .into_iter()
+        .zip(stat)
+        .filter_map(|(id, m)| m.flatten().map(|m| (id, m)))
+        .collect();
+    candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+
+    // Parse newest first, a window at a time, until `limit` finished receipts are found. Cache
+    // hits need no reading, so they are used even once `deadline` has passed.
+    let mut items = Vec::new();
+    let mut start = 0;
+    while items.len() < limit && start < candidates.len() {
+        let end = (start + limit - items.len()).min(candidates.len());
+        let window: Vec<(&str, SystemTime, Option<Option<ReceiptItem>>)> = {
+            let cache = RECEIPT_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+            candidates[start..end]
+                .iter()
+                .map(|(id, m)| {
+                    let hit = cache
+                        .get(id)
+                        .filter(|(at, _)| at == m)
+                        .map(|(_, it)| it.clone());
+                    (id.as_str(), *m, hit)
+                })
+                .collect()
         };
-        // Skip queued stubs (no manifest).
-        if resp.get("manifest").is_none() {
-            continue;
+        let misses: Vec<&(&str, SystemTime, _)> =
+            window.iter().filter(|(_, _, hit)| hit.is_none()).collect();
+        let mut parsed = par_map(&misses, deadline, |(id, m, _)| {
+            let secs = m
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            parse_receipt(receipts_dir, id, secs)
+        })
+        .into_iter();
+        let mut cache = RECEIPT_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        for (id, m, hit) in &window {
+            let item = match hit {
+                Some(item) => {
+                    stats.cached += 1;
+                    item.clone()
+                }
+                None => {
+                    let Some(item) = parsed.next().flatten() else {
+                        stats.truncated = true;
+                        continue;
+                    };
+                    cache.insert(id.to_string(), (*m, item.clone()));
+                    item
+                }
+            };
+            stats.scanned += 1;
+            items.extend(item);
         }
-        let goal_id = get_goal_id(&resp).unwrap_or_else(|| "unknown".to_string());
-        let ok = resp
-            .get("manifest")
-            .and_then(|m| m.get("evidence"))
-            .and_then(|e| e.get("actual_success"))
-            .and_then(|v| v.as_bool());
-        let view = get_view_url(&resp);
-        items.push(Item {
-            run_id,
-            goal_id,
-            ok,
-            view,
-            mtime,
-        });
+        start = end;
+    }
+    if !stats.truncated {
+        // Forget receipts that were deleted.
+        let present: HashSet<&str> = candidates.iter().map(|(id, _)| id.as_str()).collect();
+        RECEIPT_CACHE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|id, _| present.contains(id.as_str()));
     }
-
-    // Keep most recent.
-    items.sort_by(|a, b| b.mtime.cmp(&a.mtime).then_with(|| b.run_id.cmp(&a.run_id)));
     items.truncate(limit);
+    Ok((items, stats))
+}
+
+/// Dependency edges (parent -> dependent) between receipts that are both in `items`.
+fn dependency_edges(items: &[ReceiptItem]) -> Vec<(usize, usize)> {
+    let index: HashMap<&str, usize> =
+        items.iter().enumerate().map(|(i, it)| (it.run_id.as_str(), i)).collect();
+    items
+        .iter()
+        .enumerate()
+        .flat_map(|(i, it)| {
+            it.depends_on
+                .iter()
+                .filter_map(|p| index.get(p.as_str()).map(|&j| (j, i)))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Graph of the `limit` most recent receipts. The scan stops at `budget`; the graph then has
+/// what was read so far and `truncated` is set.
+pub fn receipts_graph(
+    external_run_id: &str,
+    limit: usize,
+    budget: Duration,
+) -> Result<ReceiptsGraphResult> {
+    if !is_safe_segment(external_run_id) {
+        return Err(anyhow!("invalid __run_id"));
+    }
+    let limit = limit.clamp(1, 2000);
+
+    let root = meta3_root();
+    let receipts_dir = root.join("runs").join("receipts");
+    let (mut items, stats) = scan_receipts(&receipts_dir, limit, Instant::now() + budget)?;
     // Chronological order for edges.
     items.sort_by(|a, b| a.mtime.cmp(&b.mtime).then_with(|| a.run_id.cmp(&b.run_id)));
+    let dep_edges = dependency_edges(&items);
+    let edge_count = items.len().saturating_sub(1) + dep_edges.len();
 
     let out_dir = root.join("runs").join("graphs").join(external_run_id);
-    fs::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
+    storage::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
 
     // DOT
     let mut dot = String::from(
@@ -1388,26 +1744,69 @@ pub fn receipts_graph(external_run_id: &str, limit: usize) -> Result<ReceiptsGra
     for i in 0..items.len().saturating_sub(1) {
         dot.push_str(&format!("  n{} -> n{};\n", i, i + 1));
     }
+    for (a, b) in &dep_edges {
+        dot.push_str(&format!(
+            "  n{} -> n{} [style=dashed, color=\"#1c7ed6\", label=\"depends_on\"];\n",
+            a, b
+        ));
+    }
     dot.push_str("}\n");
-    fs::write(out_dir.join("graph.dot"), dot.as_bytes())
+    atomic::write(out_dir.join("graph.dot"), dot.as_bytes())
         .with_context(|| "write graph.dot".to_string())?;
+    let rendered = graph_layout::write_svg_blocking(&out_dir, "graph", &dot);
+
+    let mut doc = GraphDoc::new("receipts", "Recent receipts");
+    doc.meta = serde_json::json!({ "limit": limit, "truncated": stats.truncated });
+    for (i, it) in items.iter().enumerate() {
+        let mut node = GraphNode::new(
+            format!("n{i}"),
+            format!("{} · {}", it.goal_id, it.run_id),
+            "receipt",
+        )
+        .link(
+            "receipt",
+            url_for(&format!("/runs/receipts/{}/RECEIPT.md", it.run_id)),
+        );
+        if let Some(v) = it.view.clone() {
+            node = node.link("view", v);
+        }
+        node.group = Some(it.goal_id.clone());
+        node.ok = it.ok;
+        node.data = serde_json::json!({ "run_id": it.run_id, "goal_id": it.goal_id, "mtime_s": it.mtime });
+        doc.nodes.push(node);
+    }
+    for i in 0..items.len().saturating_sub(1) {
+        doc.edges.push(GraphEdge::new(format!("n{i}"), format!("n{}", i + 1), "seq"));
+    }
+    for (a, b) in &dep_edges {
+        doc.edges.push(GraphEdge::new(
+            format!("n{a}"),
+            format!("n{b}"),
+            "depends_on",
+        ));
+    }
+    graph_doc::write(&out_dir, &doc)?;
 
     // events.json
     let events_json = serde_json::json!({
         "kind": "receipts",
         "limit": limit,
+        "truncated": stats.truncated,
+        "scanned": stats.scanned,
+        "cached": stats.cached,
         "items": items.iter().map(|it| {
             serde_json::json!({
                 "run_id": it.run_id,
                 "goal_id": it.goal_id,
                 "actual_success": it.ok,
                 "view_url": it.view,
-                "receipt_url": format!("/runs/receipts/{}/RECEIPT.md", it.run_id),
+                "receipt_url": url_for(&format!("/runs/receipts/{}/RECEIPT.md", it.run_id)),
                 "mtime_s": it.mtime,
+                "depends_on": it.depends_on,
             })
         }).collect::<Vec<_>>()
     });
-    fs::write(
+    atomic::write(
         out_dir.join("events.json"),
         serde_json::to_string_pretty(&events_json).unwrap_or_default(),
     )
@@ -1417,7 +1816,7 @@ pub fn receipts_graph(external_run_id: &str, limit: usize) -> Result<ReceiptsGra
     let mut items_html = String::new();
     for it in &items {
         let ok = it.ok.map(|b| if b { "ok" } else { "fail" }).unwrap_or("?");
-        let receipt = format!("/runs/receipts/{}/RECEIPT.md", it.run_id);
+        let receipt = url_for(&format!("/runs/receipts/{}/RECEIPT.md", it.run_id));
         let view = it.view.clone().unwrap_or_default();
         let text = format!("{} · {}", it.goal_id, it.run_id);
         let data_t = format!("{} {}", it.goal_id, it.run_id);
@@ -1438,15 +1837,728 @@ pub fn receipts_graph(external_run_id: &str, limit: usize) -> Result<ReceiptsGra
     let html = index_html_receipts(
         external_run_id,
         items.len(),
-        items.len().saturating_sub(1),
+        edge_count,
         &items_html,
+        &format!(
+            "{}{}",
+            graph_doc::viewer_link_html(&format!("/runs/graphs/{}/graph.json", external_run_id)),
+            graph_layout::embed_html(rendered.as_ref(), "graph.svg")
+        ),
     );
-    fs::write(out_dir.join("index.html"), html.as_bytes())
+    atomic::write(out_dir.join("index.html"), html.as_bytes())
         .with_context(|| "write index.html".to_string())?;
 
     Ok(ReceiptsGraphResult {
         out_dir,
         nodes: items.len(),
-        edges: items.len().saturating_sub(1),
+        edges: edge_count,
+        layout: rendered.map(|r| r.engine),
+        stats,
+    })
+}
+
+fn ts_secs(ts: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(ts).ok().map(|d| d.timestamp())
+}
+
+fn mtime_secs(p: &Path) -> i64 {
+    storage::modified(p)
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Run cost in ms: receipt timing.json (started_at → finished_at), else evidence.duration_ms.
+fn receipt_cost_ms(run_id: &str, resp: Option<&Value>) -> u64 {
+    let timing = storage::read_to_string(
+        &meta3_root()
+            .join("runs")
+            .join("receipts")
+            .join(run_id)
+            .join("timing.json"),
+    )
+    .ok()
+    .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+    let field = |k: &str| {
+        timing
+            .as_ref()
+            .and_then(|t| t.get(k))
+            .and_then(|v| v.as_str())
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+    };
+    if let (Some(a), Some(b)) = (field("started_at"), field("finished_at")) {
+        let ms = (b - a).num_milliseconds();
+        if ms > 0 {
+            return ms as u64;
+        }
+    }
+    resp.and_then(|r| r.get("manifest"))
+        .and_then(|m| m.get("evidence"))
+        .and_then(|e| e.get("duration_ms"))
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0)
+}
+
+#[derive(Default)]
+struct SysThread {
+    events: usize,
+    runs: std::collections::BTreeMap<String, usize>,
+}
+
+struct SysRun {
+    goal_id: String,
+    ok: Option<bool>,
+    cost_ms: u64,
+}
+
+/// Aggregate graph over the whole system for a time window: user → thread → run → goal.
+///
+/// Threads contribute events (and the runs they reference), receipts in the window without a
+/// thread are attached to their `user_id` (or `(system)`), and api_trace hits add frequency.
+/// When the node count exceeds `max_nodes`, the least important clusters are folded: first a
+/// thread's runs into one summary node, then a user's smallest threads into "other threads".
+pub fn system_graph(external_run_id: &str, mut opts: SystemGraphOpts) -> Result<SystemGraphResult> {
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    if !is_safe_segment(external_run_id) {
+        return Err(anyhow!("invalid __run_id"));
+    }
+    if let Some(uid) = opts.user_id.as_deref() {
+        if !is_safe_segment(uid) {
+            return Err(anyhow!("invalid user_id"));
+        }
+    }
+    opts.since_hours = opts.since_hours.clamp(1, 24 * 90);
+    opts.max_nodes = opts.max_nodes.clamp(20, 2000);
+    let cutoff = chrono::Utc::now().timestamp() - (opts.since_hours as i64) * 3600;
+    let root = meta3_root();
+    let user_ok = |u: &str| opts.user_id.as_deref().map(|f| f == u).unwrap_or(true);
+
+    // user -> thread -> aggregate
+    let mut users: BTreeMap<String, BTreeMap<String, SysThread>> = BTreeMap::new();
+    let mut owner: HashMap<String, (String, String)> = HashMap::new();
+
+    // 1) Threads.
+    for user in storage::entries(&root.join("users")) {
+        if !is_safe_segment(&user) || !user_ok(&user) {
+            continue;
+        }
+        let threads_dir = root.join("users").join(&user).join("threads");
+        for name in storage::entries(&threads_dir) {
+            let p = threads_dir.join(name);
+            if p.extension().and_then(|x| x.to_str()) != Some("jsonl")
+                || jsonl::is_rotated_segment(&p)
+                || mtime_secs(&p) < cutoff
+            {
+                continue;
+            }
+            let thread = p.file_stem().and_then(|x| x.to_str()).unwrap_or("").to_string();
+            if !is_safe_segment(&thread) {
+                continue;
+            }
+            let lines = tail_thread_lines(&p, 2000, 2_000_000).unwrap_or_default();
+            let agg = users.entry(user.clone()).or_default().entry(thread.clone()).or_default();
+            for line in lines {
+                let Ok(v) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                let ts = v.get("ts").and_then(|x| x.as_str()).and_then(ts_secs);
+                if ts.map(|t| t < cutoff).unwrap_or(false) {
+                    continue;
+                }
+                agg.events += 1;
+                let run_id = v.get("run_id").and_then(|x| x.as_str()).unwrap_or("");
+                if is_safe_segment(run_id) {
+                    *agg.runs.entry(run_id.to_string()).or_insert(0) += 1;
+                    owner
+                        .entry(run_id.to_string())
+                        .or_insert_with(|| (user.clone(), thread.clone()));
+                }
+            }
+            if agg.events == 0 {
+                if let Some(m) = users.get_mut(&user) {
+                    m.remove(&thread);
+                }
+            }
+        }
+    }
+
+    // 2) Receipts in the window that no thread referenced.
+    let receipts_dir = root.join("runs").join("receipts");
+    for run_id in storage::entries(&receipts_dir) {
+        if !is_safe_segment(&run_id) || owner.contains_key(&run_id) {
+            continue;
+        }
+        if mtime_secs(&receipts_dir.join(&run_id).join("response.json")) < cutoff {
+            continue;
+        }
+        let Some(resp) = receipt_response_json(&run_id) else {
+            continue;
+        };
+        if resp.get("manifest").is_none() {
+            continue;
+        }
+        let user = resp
+            .get("user_id")
+            .and_then(|v| v.as_str())
+            .filter(|u| is_safe_segment(u))
+            .unwrap_or("(system)")
+            .to_string();
+        if !user_ok(&user) {
+            continue;
+        }
+        let thread = "(no thread)".to_string();
+        let agg = users.entry(user.clone()).or_default().entry(thread.clone()).or_default();
+        *agg.runs.entry(run_id.clone()).or_insert(0) += 1;
+        owner.insert(run_id, (user, thread));
+    }
+
+    // 3) api_trace frequency.
+    let mut api_hits = 0usize;
+    if opts.include_api {
+        let trace_path = root.join("runs").join("api_trace.jsonl");
+        for line in tail_lines(&trace_path, 20_000, 8_000_000).unwrap_or_default() {
+            let Ok(ev) = serde_json::from_str::<ApiTraceEvent>(&line) else {
+                continue;
+            };
+            if ts_secs(&ev.ts).map(|t| t < cutoff).unwrap_or(true) {
+                continue;
+            }
+            let rid = ev.run_id.filter(|r| is_safe_segment(r));
+            let (user, thread) = match (ev.user_id, ev.thread, rid.as_ref().and_then(|r| owner.get(r))) {
+                (_, _, Some((u, t))) => (u.clone(), t.clone()),
+                (Some(u), Some(t), None) if is_safe_segment(&u) && is_safe_segment(&t) => (u, t),
+                _ => continue,
+            };
+            if !user_ok(&user) {
+                continue;
+            }
+            api_hits += 1;
+            let agg = users.entry(user.clone()).or_default().entry(thread.clone()).or_default();
+            agg.events += 1;
+            if let Some(r) = rid {
+                *agg.runs.entry(r.clone()).or_insert(0) += 1;
+                owner.entry(r).or_insert((user, thread));
+            }
+        }
+    }
+
+    users.retain(|_, threads| !threads.is_empty());
+    if users.is_empty() {
+        return Err(anyhow!("no activity in the last {}h", opts.since_hours));
+    }
+
+    // Receipt facts per run.
+    let mut runs: HashMap<String, SysRun> = HashMap::new();
+    for run_id in owner.keys() {
+        let resp = receipt_response_json(run_id);
+        runs.insert(
+            run_id.clone(),
+            SysRun {
+                goal_id: resp.as_ref().and_then(get_goal_id).unwrap_or_else(|| "unknown".to_string()),
+                ok: resp.as_ref().and_then(get_actual_success),
+                cost_ms: receipt_cost_ms(run_id, resp.as_ref()),
+            },
+        );
+    }
+
+    // Importance: cost in seconds plus activity.
+    let run_weight = |r: &str, refs: usize| runs.get(r).map(|x| x.cost_ms / 1000).unwrap_or(0) as usize + refs;
+    let thread_weight = |t: &SysThread| t.events + t.runs.iter().map(|(r, n)| run_weight(r, *n)).sum::<usize>();
+
+    // Budget: fold clusters until the node count fits.
+    let goals: BTreeSet<String> = runs.values().map(|r| r.goal_id.clone()).collect();
+    let n_threads: usize = users.values().map(|m| m.len()).sum();
+    let mut total = users.len() + n_threads + goals.len() + runs.len();
+    let mut runs_folded: BTreeSet<(String, String)> = BTreeSet::new();
+    let mut threads_folded: BTreeSet<(String, String)> = BTreeSet::new();
+    let mut order: Vec<(usize, String, String)> = users
+        .iter()
+        .flat_map(|(u, m)| m.iter().map(move |(t, agg)| (u, t, agg)))
+        .map(|(u, t, agg)| (thread_weight(agg), u.clone(), t.clone()))
+        .collect();
+    order.sort();
+    for (_, u, t) in &order {
+        if total <= opts.max_nodes {
+            break;
+        }
+        let n = users[u][t].runs.len();
+        if n > 1 {
+            runs_folded.insert((u.clone(), t.clone()));
+            total -= n - 1;
+        }
+    }
+    for (_, u, t) in &order {
+        if total <= opts.max_nodes {
+            break;
+        }
+        let user_threads = users[u].len();
+        let already = threads_folded.iter().filter(|(fu, _)| fu == u).count();
+        if user_threads - already < 2 {
+            continue;
+        }
+        // Folding a thread removes it and its run nodes; the first fold per user adds the bucket.
+        let n = users[u][t].runs.len();
+        let own = 1 + if runs_folded.contains(&(u.clone(), t.clone())) { n.min(1) } else { n };
+        threads_folded.insert((u.clone(), t.clone()));
+        total = total + usize::from(already == 0) - own;
+    }
+    let collapsed = runs_folded.len() + threads_folded.len();
+
+    let user_id = |u: &str| format!("user:{u}");
+    let thread_rep = |u: &str, t: &str| {
+        if threads_folded.contains(&(u.to_string(), t.to_string())) {
+            format!("threads:{u}")
+        } else {
+            format!("thread:{u}/{t}")
+        }
+    };
+    let run_rep = |u: &str, t: &str, r: &str| {
+        if threads_folded.contains(&(u.to_string(), t.to_string())) {
+            format!("threads:{u}")
+        } else if runs_folded.contains(&(u.to_string(), t.to_string())) {
+            format!("runs:{u}/{t}")
+        } else {
+            format!("run:{r}")
+        }
+    };
+
+    // Nodes keyed by representative id; folded clusters accumulate cost/count/failures.
+    struct Acc {
+        label: String,
+        kind: &'static str,
+        group: Option<String>,
+        cost_ms: u64,
+        count: usize,
+        fails: usize,
+        run_id: Option<String>,
+    }
+    fn merge(nodes: &mut BTreeMap<String, Acc>, id: String, acc: Acc) {
+        let a = nodes.entry(id).or_insert(Acc { cost_ms: 0, count: 0, fails: 0, ..acc });
+        a.cost_ms += acc.cost_ms;
+        a.count += 1;
+        a.fails += acc.fails;
+    }
+    let acc = |label: String, kind: &'static str, group: Option<String>, cost_ms: u64, fail: bool, run_id: Option<String>| Acc {
+        label,
+        kind,
+        group,
+        cost_ms,
+        count: 1,
+        fails: usize::from(fail),
+        run_id,
+    };
+    let mut nodes: BTreeMap<String, Acc> = BTreeMap::new();
+    let mut edges: BTreeMap<(String, String, &'static str), usize> = BTreeMap::new();
+    for (u, threads) in &users {
+        merge(&mut nodes, user_id(u), acc(u.clone(), "user", None, 0, false, None));
+        for (t, agg) in threads {
+            let tid = thread_rep(u, t);
+            let folded = tid.starts_with("threads:");
+            let tlabel = if folded { "other threads".to_string() } else { t.clone() };
+            let tkind = if folded { "collapsed" } else { "thread" };
+            merge(&mut nodes, tid.clone(), acc(tlabel, tkind, Some(user_id(u)), 0, false, None));
+            *edges.entry((user_id(u), tid.clone(), "contains")).or_insert(0) += agg.events.max(1);
+            for (r, refs) in &agg.runs {
+                let info = runs.get(r);
+                let cost = info.map(|x| x.cost_ms).unwrap_or(0);
+                let fail = info.and_then(|x| x.ok) == Some(false);
+                let rid = run_rep(u, t, r);
+                if rid != tid {
+                    let (label, kind, run_id) = if rid.starts_with("runs:") {
+                        ("runs".to_string(), "collapsed", None)
+                    } else {
+                        (r.clone(), "run", Some(r.clone()))
+                    };
+                    merge(&mut nodes, rid.clone(), acc(label, kind, Some(tid.clone()), cost, fail, run_id));
+                    *edges.entry((tid.clone(), rid.clone(), "ran")).or_insert(0) += *refs;
+                } else if let Some(a) = nodes.get_mut(&tid) {
+                    a.cost_ms += cost;
+                    a.fails += usize::from(fail);
+                }
+                if let Some(info) = info {
+                    let gid = format!("goal:{}", info.goal_id);
+                    merge(&mut nodes, gid.clone(), acc(info.goal_id.clone(), "goal", None, cost, fail, None));
+                    *edges.entry((rid.clone(), gid, "goal")).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    let max_cost = nodes.values().map(|a| a.cost_ms).max().unwrap_or(0).max(1) as f32;
+    let size_for = |cost: u64| 1.0 + 1.5 * ((cost as f32 + 1.0).ln() / (max_cost + 1.0).ln()).clamp(0.0, 1.0);
+
+    let out_dir = root.join("runs").join("graphs").join(external_run_id);
+    storage::create_dir_all(&out_dir).with_context(|| format!("mkdir {}", out_dir.display()))?;
+
+    // DOT: one cluster per user, goals outside.
+    let key: HashMap<&String, usize> = nodes.keys().enumerate().map(|(i, k)| (k, i)).collect();
+    let dot_label = |a: &Acc| {
+        let mut l = a.label.clone();
+        if a.kind == "collapsed" {
+            l = format!("{} ({})", l, a.count);
+        }
+        if a.cost_ms > 0 {
+            l = format!("{}\\n{:.1}s", l, a.cost_ms as f64 / 1000.0);
+        }
+        l.replace('"', "\\\"")
+    };
+    let dot_node = |id: &String, a: &Acc| {
+        let fill = match a.kind {
+            "user" => "#e7f5ff",
+            "thread" => "#f8f9fa",
+            "goal" => "#fff9db",
+            "collapsed" => "#f1f3f5",
+            _ if a.fails > 0 => "#fff5f5",
+            _ => "#ebfbee",
+        };
+        format!(
+            "    n{} [label=\"{}\", fillcolor=\"{}\", width={:.2}];\n",
+            key[id],
+            dot_label(a),
+            fill,
+            1.2 * size_for(a.cost_ms)
+        )
+    };
+    let mut dot = String::from(
+        "digraph system {\nrankdir=LR;\nnode [shape=box, style=\"rounded,filled\", fontname=\"Helvetica\"];\n",
+    );
+    for (ui, u) in users.keys().enumerate() {
+        let uid = user_id(u);
+        dot.push_str(&format!("  subgraph cluster_{} {{\n    label=\"{}\";\n", ui, u.replace('"', "\\\"")));
+        for (id, a) in &nodes {
+            let in_user = *id == uid
+                || a.group.as_deref() == Some(uid.as_str())
+                || a.group.as_ref().and_then(|g| nodes.get(g)).and_then(|p| p.group.as_deref()) == Some(uid.as_str());
+            if in_user {
+                dot.push_str(&dot_node(id, a));
+            }
+        }
+        dot.push_str("  }\n");
+    }
+    for (id, a) in nodes.iter().filter(|(_, a)| a.kind == "goal") {
+        dot.push_str(&dot_node(id, a).replacen("    ", "  ", 1));
+    }
+    for ((a, b, kind), w) in &edges {
+        let style = if *kind == "goal" { ", style=dashed" } else { "" };
+        let label = if *w > 1 { format!(", label=\"{}\"", w) } else { String::new() };
+        dot.push_str(&format!(
+            "  n{} -> n{} [penwidth={:.1}{}{}];\n",
+            key[a],
+            key[b],
+            1.0 + (*w as f32).ln().max(0.0),
+            label,
+            style
+        ));
+    }
+    dot.push_str("}\n");
+    atomic::write(out_dir.join("graph.dot"), dot.as_bytes())
+        .with_context(|| "write graph.dot".to_string())?;
+    let rendered = graph_layout::write_svg_blocking(&out_dir, "graph", &dot);
+
+    // graph.json
+    let mut doc = GraphDoc::new("system", format!("System map (last {}h)", opts.since_hours));
+    doc.meta = serde_json::json!({
+        "since_hours": opts.since_hours,
+        "max_nodes": opts.max_nodes,
+        "user_id": opts.user_id,
+        "include_api": opts.include_api,
+        "api_hits": api_hits,
+        "collapsed": collapsed,
+    });
+    for (id, a) in &nodes {
+        let mut node = GraphNode::new(id.clone(), a.label.clone(), a.kind);
+        node.group = a.group.clone();
+        node.size = Some(size_for(a.cost_ms));
+        if matches!(a.kind, "run" | "goal" | "collapsed") {
+            node.ok = Some(a.fails == 0);
+        }
+        if let Some(r) = a.run_id.as_deref() {
+            node = node.link(
+                "receipt",
+                url_for(&format!("/runs/receipts/{}/RECEIPT.md", r)),
+            );
+        }
+        node.data = serde_json::json!({ "cost_ms": a.cost_ms, "count": a.count, "fails": a.fails });
+        doc.nodes.push(node);
+    }
+    for ((a, b, kind), w) in &edges {
+        let mut e = GraphEdge::new(a.clone(), b.clone(), kind);
+        e.weight = Some(*w as f32);
+        doc.edges.push(e);
+    }
+    graph_doc::write(&out_dir, &doc)?;
+
+    // events.json: the per-thread aggregates the graph was built from.
+    let events_json = serde_json::json!({
+        "kind": "system",
+        "since_hours": opts.since_hours,
+        "users": users.iter().map(|(u, threads)| {
+            serde_json::json!({
+                "user_id": u,
+                "threads": threads.iter().map(|(t, agg)| {
+                    serde_json::json!({
+                        "thread": t,
+                        "events": agg.events,
+                        "runs": agg.runs.iter().map(|(r, n)| {
+                            let info = runs.get(r);
+                            serde_json::json!({
+                                "run_id": r,
+                                "refs": n,
+                                "goal_id": info.map(|x| x.goal_id.clone()),
+                                "actual_success": info.and_then(|x| x.ok),
+                                "cost_ms": info.map(|x| x.cost_ms),
+                            })
+                        }).collect::<Vec<_>>(),
+                    })
+                }).collect::<Vec<_>>(),
+            })
+        }).collect::<Vec<_>>(),
+    });
+    atomic::write(out_dir.join("events.json"), serde_json::to_string_pretty(&events_json).unwrap_or_default())
+        .with_context(|| "write events.json".to_string())?;
+
+    // index.html: per-thread table.
+    let mut rows_html = String::new();
+    for (u, threads) in &users {
+        for (t, agg) in threads {
+            let cost: u64 = agg.runs.keys().filter_map(|r| runs.get(r)).map(|x| x.cost_ms).sum();
+            let fails = agg.runs.keys().filter_map(|r| runs.get(r)).filter(|x| x.ok == Some(false)).count();
+            let folded = if threads_folded.contains(&(u.clone(), t.clone())) {
+                "threads folded"
+            } else if runs_folded.contains(&(u.clone(), t.clone())) {
+                "runs folded"
+            } else {
+                ""
+            };
+            rows_html.push_str(&format!(
+                "<tr data-t=\"{}\"><td><code>{}</code></td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}s</td><td class=\"muted\">{}</td></tr>\n",
+                html_escape(&format!("{} {}", u, t)),
+                html_escape(u),
+                html_escape(t),
+                agg.events,
+                agg.runs.len(),
+                fails,
+                cost as f64 / 1000.0,
+                folded,
+            ));
+        }
+    }
+    let html = index_html_system(
+        external_run_id,
+        opts.since_hours,
+        nodes.len(),
+        edges.len(),
+        collapsed,
+        &rows_html,
+        &format!(
+            "{}{}",
+            graph_doc::viewer_link_html(&format!("/runs/graphs/{}/graph.json", external_run_id)),
+            graph_layout::embed_html(rendered.as_ref(), "graph.svg")
+        ),
+    );
+    atomic::write(out_dir.join("index.html"), html.as_bytes())
+        .with_context(|| "write index.html".to_string())?;
+
+    Ok(SystemGraphResult {
+        out_dir,
+        nodes: nodes.len(),
+        edges: edges.len(),
+        layout: rendered.map(|r| r.engine),
+        users: users.len(),
+        threads: n_threads,
+        runs: runs.len(),
+        collapsed,
     })
 }
+
+fn index_html_system(
+    run_id: &str,
+    since_hours: u64,
+    nodes: usize,
+    edges: usize,
+    collapsed: usize,
+    rows_html: &str,
+    layout_html: &str,
+) -> String {
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width,initial-scale=1">
+  <title>System Map {run_id}</title>
+  <style>
+    body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px;max-width:1100px}}
+    .muted{{color:#57606a}}
+    code{{background:#f6f8fa;border:1px solid #d0d7de;border-radius:10px;padding:2px 6px}}
+    a{{color:#1f6feb;text-decoration:none}} a:hover{{text-decoration:underline}}
+    input{{width:100%;padding:10px 12px;border:1px solid #d0d7de;border-radius:10px;margin:12px 0}}
+    table{{border-collapse:collapse;width:100%}}
+    th,td{{border-bottom:1px solid #f1f3f5;padding:6px;text-align:left}}
+    th{{font-size:12px;color:#57606a}}
+  </style>
+</head>
+<body>
+  <h1>System Map</h1>
+  <div class="muted">
+    run_id: <code>{run_id}</code> · window: <code>{since_hours}h</code> · nodes: <code>{nodes}</code> · edges: <code>{edges}</code> · collapsed clusters: <code>{collapsed}</code>
+  </div>
+  <div class="muted" style="margin-top:8px">
+    Links: <a href="graph.dot">graph.dot</a> · <a href="events.json">events.json</a>
+  </div>
+  {layout_html}
+
+  <input id="q" placeholder="filter by user / thread..." />
+  <table>
+    <thead><tr><th>User</th><th>Thread</th><th>Events</th><th>Runs</th><th>Failed</th><th>Cost</th><th></th></tr></thead>
+    <tbody id="rows">{rows_html}</tbody>
+  </table>
+
+  <script>
+    const q = document.getElementById('q');
+    q.addEventListener('input', () => {{
+      const s = q.value.trim().toLowerCase();
+      for (const tr of document.querySelectorAll('#rows tr')) {{
+        tr.style.display = !s || (tr.dataset.t || '').toLowerCase().includes(s) ? '' : 'none';
+      }}
+    }});
+  </script>
+</body>
+</html>
+"#,
+        run_id = html_escape(run_id),
+        since_hours = since_hours,
+        nodes = nodes,
+        edges = edges,
+        collapsed = collapsed,
+        rows_html = rows_html,
+        layout_html = layout_html
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use once_cell::sync::Lazy;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    /// Budgets are for `--release` on a laptop; slower CI boxes set `ONE_ENGINE_BUDGET_SCALE`.
+    fn budget(ms: u64) -> Duration {
+        let scale: f64 = std::env::var("ONE_ENGINE_BUDGET_SCALE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1.0);
+        Duration::from_millis((ms as f64 * scale) as u64)
+    }
+
+    /// One META3_ROOT for every budget test (it is process-wide): a 10k-event thread and
+    /// 2000 receipts. Layout uses the builtin engine so timings don't depend on graphviz.
+    static ROOT: Lazy<PathBuf> = Lazy::new(|| {
+        let root = std::env::temp_dir()
+            .join("one-engine-budget")
+            .join(uuid::Uuid::new_v4().to_string());
+        let threads = root.join("users").join("u1").join("threads");
+        fs::create_dir_all(&threads).unwrap();
+        let mut thread = String::new();
+        for i in 0..10_000 {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            let ev = json!({"ts": "2026-01-01T00:00:00Z", "role": role, "run_id": format!("r-{}", i / 2),
+                "content": format!("event {i}: graph the receipts for repo-{}", i % 97)});
+            thread.push_str(&ev.to_string());
+            thread.push('\n');
+        }
+        atomic::write(threads.join("t1.jsonl"), thread).unwrap();
+        for i in 0..2_000 {
+            let dir = root.join("runs").join("receipts").join(format!("r-{i}"));
+            fs::create_dir_all(&dir).unwrap();
+            let resp = json!({"schema_version": 2, "manifest": {"goal_id": "meta3.chat",
+                "evidence": {"actual_success": i % 7 != 0, "reply": "ok"}}});
+            atomic::write(dir.join("response.json"), resp.to_string()).unwrap();
+        }
+        std::env::set_var("META3_ROOT", &root);
+        std::env::set_var("ONE_ENGINE_GRAPH_LAYOUT", "builtin");
+        root
+    });
+
+    #[test]
+    fn receipts_scan_keeps_newest_finished_and_reuses_parsed_receipts() {
+        let dir = std::env::temp_dir().join(format!("receipts-scan-{}", uuid::Uuid::new_v4()));
+        let tag = uuid::Uuid::new_v4().simple().to_string();
+        let base = std::time::SystemTime::now() - Duration::from_secs(3600);
+        for i in 0..5u64 {
+            let rdir = dir.join(format!("{tag}-{i}"));
+            fs::create_dir_all(&rdir).unwrap();
+            // The newest receipt is a queued stub.
+            let resp = if i == 4 {
+                json!({"status": "queued"})
+            } else {
+                json!({"manifest": {"goal_id": format!("g{i}"), "evidence": {"actual_success": true}}})
+            };
+            // Each run depends on the one before it, and on one that isn't in the graph.
+            if i > 0 {
+                let req = json!({"ctx": {"dependencies": {"depends_on": [format!("{tag}-{}", i - 1), "r-gone"]}}});
+                fs::write(rdir.join("request.json"), req.to_string()).unwrap();
+            }
+            let p = rdir.join("response.json");
+            fs::write(&p, resp.to_string()).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&p)
+                .unwrap()
+                .set_modified(base + Duration::from_secs(i))
+                .unwrap();
+        }
+        let later = Instant::now() + Duration::from_secs(30);
+
+        let (items, stats) = scan_receipts(&dir, 3, later).unwrap();
+        let goals: Vec<&str> = items.iter().map(|it| it.goal_id.as_str()).collect();
+        assert_eq!(goals, vec!["g3", "g2", "g1"]);
+        assert_eq!(items[0].depends_on, vec![format!("{tag}-2"), "r-gone".to_string()]);
+        // Newest first, so each edge runs from the parent (later index) to its dependent.
+        assert_eq!(dependency_edges(&items), vec![(1, 0), (2, 1)]);
+        assert_eq!(
+            (stats.scanned, stats.cached, stats.truncated),
+            (4, 0, false)
+        );
+
+        let (_, stats) = scan_receipts(&dir, 3, later).unwrap();
+        assert_eq!((stats.scanned, stats.cached), (4, 4));
+
+        // Past the parse deadline, what was stat'ed still comes back from the cache; a new
+        // receipt that would need reading is left out and the scan reports itself truncated.
+        let rdir = dir.join(format!("{tag}-5"));
+        fs::create_dir_all(&rdir).unwrap();
+        fs::write(rdir.join("response.json"), json!({"manifest": {"goal_id": "g5"}}).to_string()).unwrap();
+        let (items, stats) = scan_receipts_until(&dir, 3, later, Instant::now()).unwrap();
+        let goals: Vec<&str> = items.iter().map(|it| it.goal_id.as_str()).collect();
+        assert_eq!(goals, vec!["g3", "g2", "g1"]);
+        assert!(stats.truncated);
+
+        let (items, stats) = scan_receipts(&dir, 3, Instant::now()).unwrap();
+        assert!(items.is_empty() && stats.truncated);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[ignore = "performance budget; run with `make bench`"]
+    fn budget_thread_graph_10k_events() {
+        Lazy::force(&ROOT);
+        let t = Instant::now();
+        let g = thread_graph("budget-thread", "u1", "t1", 800).unwrap();
+        let took = t.elapsed();
+        assert!(g.nodes > 0);
+        assert!(took < budget(1_000), "thread_graph took {:?}", took);
+    }
+
+    #[test]
+    #[ignore = "performance budget; run with `make bench`"]
+    fn budget_receipts_graph_2k_receipts() {
+        Lazy::force(&ROOT);
+        let t = Instant::now();
+        let g = receipts_graph("budget-receipts", 2_000, Duration::from_secs(60)).unwrap();
+        let took = t.elapsed();
+        assert!(g.nodes > 0);
+        assert!(took < budget(2_000), "receipts_graph took {:?}", took);
+    }
+}
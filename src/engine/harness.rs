@@ -0,0 +1,474 @@
+//! Deterministic harness for goal handlers: a virtual clock, a fake command runner and an
+//! in-memory filesystem, injected as a task-local around a run.
+//!
+//! Outside [`scope`] nothing changes: the executor spawns `bash`, writes real files and the
+//! engine reads the system clock. Inside it, `executor::execute` routes `Action::Cli` to the
+//! [`CommandRunner`] and `Action::WriteFile` to the [`FsOps`], and [`now`] reads the [`Clock`].
+//! Goals that write their artifacts with `std::fs` directly (wiki, graphs, reports) still
+//! touch disk; point `META3_ROOT` at a temp dir for those.
+//!
+//! The fakes and [`TestEngine`], which wires them together, exist in test builds only:
+//!
+//! ```ignore
+//! let t = TestEngine::new().command("cargo build", true, "Finished");
+//! let run = t.run("demo.easy", json!({"message": "hi"})).await?;
+//! run.assert_success();
+//! assert!(run.ran("echo"));
+//! ```
+
+#[cfg(test)]
+use anyhow::anyhow;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+#[cfg(test)]
+use chrono::{Duration, TimeZone};
+#[cfg(test)]
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+
+use super::executor::{self, ExecResult};
+#[cfg(test)]
+use super::types::Manifest;
+use super::types::Policy;
+#[cfg(test)]
+use super::types::MANIFEST_SCHEMA_VERSION;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub trait CommandRunner: Send + Sync {
+    fn run<'a>(&'a self, cmd: &'a str, policy: &'a Policy) -> BoxFuture<'a, Result<ExecResult>>;
+}
+
+pub trait FsOps: Send + Sync {
+    /// Write `content` to `path`, creating parent dirs.
+    fn write(&self, path: &Path, content: &[u8]) -> std::io::Result<()>;
+    #[cfg(test)]
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Runs commands for real, in `bash` (the harness always speaks POSIX; see `platform::shell`).
+pub struct ShellRunner;
+
+impl CommandRunner for ShellRunner {
+    fn run<'a>(&'a self, cmd: &'a str, policy: &'a Policy) -> BoxFuture<'a, Result<ExecResult>> {
+        Box::pin(executor::run_shell(cmd, policy))
+    }
+}
+
+pub struct RealFs;
+
+impl FsOps for RealFs {
+    fn write(&self, path: &Path, content: &[u8]) -> std::io::Result<()> {
+        if let Some(parent) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)
+    }
+
+    #[cfg(test)]
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+}
+
+#[cfg(test)]
+/// Clock that only moves when told to.
+pub struct VirtualClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+impl VirtualClock {
+    pub fn at(t: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(t) }
+    }
+
+    pub fn set(&self, t: DateTime<Utc>) {
+        *self.now.lock().unwrap() = t;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += by;
+    }
+}
+
+#[cfg(test)]
+impl Clock for VirtualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+/// Canned results matched by substring, first rule wins. Unmatched `echo <arg>` prints its
+/// argument, unmatched `true`/`false` succeed/fail, anything else fails with `no fake for`.
+#[derive(Default)]
+pub struct FakeRunner {
+    rules: Mutex<Vec<(String, ExecResult)>>,
+    calls: Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl FakeRunner {
+    pub fn on(&self, pattern: &str, result: ExecResult) {
+        self.rules
+            .lock()
+            .unwrap()
+            .push((pattern.to_string(), result));
+    }
+
+    /// Commands run so far, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn answer(&self, cmd: &str) -> ExecResult {
+        self.calls.lock().unwrap().push(cmd.to_string());
+        if let Some((_, r)) = self
+            .rules
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(p, _)| cmd.contains(p.as_str()))
+        {
+            return r.clone();
+        }
+        match cmd.trim() {
+            "true" => exec_result(true, ""),
+            "false" => exec_result(false, ""),
+            c if c.starts_with("echo ") => {
+                let arg = c["echo ".len()..].trim();
+                let arg = arg
+                    .strip_prefix('\'')
+                    .and_then(|a| a.strip_suffix('\''))
+                    .unwrap_or(arg);
+                exec_result(true, &format!("{}\n", arg))
+            }
+            c => ExecResult {
+                ok: false,
+                drift: false,
+                stdout: String::new(),
+                stderr: format!("no fake for: {}", c),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+impl CommandRunner for FakeRunner {
+    fn run<'a>(&'a self, cmd: &'a str, _policy: &'a Policy) -> BoxFuture<'a, Result<ExecResult>> {
+        Box::pin(async move { Ok(self.answer(cmd)) })
+    }
+}
+
+pub fn exec_result(ok: bool, stdout: &str) -> ExecResult {
+    ExecResult {
+        ok,
+        drift: false,
+        stdout: stdout.to_string(),
+        stderr: String::new(),
+    }
+}
+
+#[derive(Default)]
+pub struct MemFs {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemFs {
+    #[cfg(test)]
+    pub fn files(&self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files.lock().unwrap().clone()
+    }
+}
+
+impl FsOps for MemFs {
+    fn write(&self, path: &Path, content: &[u8]) -> std::io::Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), content.to_vec());
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+    }
+}
+
+/// What the executor and clock use inside a [`scope`].
+#[derive(Clone)]
+pub struct Harness {
+    pub clock: Arc<dyn Clock>,
+    pub runner: Arc<dyn CommandRunner>,
+    pub fs: Arc<dyn FsOps>,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self {
+            clock: Arc::new(SystemClock),
+            runner: Arc::new(ShellRunner),
+            fs: Arc::new(RealFs),
+        }
+    }
+}
+
+tokio::task_local! {
+    static HARNESS: Harness;
+}
+
+/// Harness of the current task, if the task runs inside [`scope`].
+pub fn current() -> Option<Harness> {
+    HARNESS.try_with(|h| h.clone()).ok()
+}
+
+/// Run `fut` with `harness` in place of the shell, the filesystem and the system clock.
+pub async fn scope<F: Future>(harness: Harness, fut: F) -> F::Output {
+    HARNESS.scope(harness, fut).await
+}
+
+/// Engine time: the harness clock inside [`scope`], else the system clock.
+pub fn now() -> DateTime<Utc> {
+    HARNESS
+        .try_with(|h| h.clock.now())
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[cfg(test)]
+/// Builder that runs goals against the fakes and returns what they did.
+pub struct TestEngine {
+    policy: Policy,
+    clock: Arc<VirtualClock>,
+    runner: Arc<FakeRunner>,
+    fs: Arc<MemFs>,
+}
+
+#[cfg(test)]
+impl Default for TestEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+impl TestEngine {
+    /// Clock at 2024-01-01T00:00:00Z, no canned commands, empty filesystem.
+    pub fn new() -> Self {
+        Self {
+            policy: Policy::default(),
+            clock: Arc::new(VirtualClock::at(
+                Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            )),
+            runner: Arc::new(FakeRunner::default()),
+            fs: Arc::new(MemFs::default()),
+        }
+    }
+
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn at(self, t: DateTime<Utc>) -> Self {
+        self.clock.set(t);
+        self
+    }
+
+    /// Commands containing `pattern` succeed or fail with `stdout`.
+    pub fn command(self, pattern: &str, ok: bool, stdout: &str) -> Self {
+        self.runner.on(pattern, exec_result(ok, stdout));
+        self
+    }
+
+    pub fn file(self, path: impl AsRef<Path>, content: impl AsRef<[u8]>) -> Self {
+        let _ = self.fs.write(path.as_ref(), content.as_ref());
+        self
+    }
+
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// Goals that write under `runs/` use `inputs.__run_id`; a fresh `r-<uuid>` when absent.
+    pub async fn run(&self, goal_id: &str, mut inputs: Value) -> Result<TestRun> {
+        if let Some(obj) = inputs.as_object_mut() {
+            obj.entry("__run_id")
+                .or_insert_with(|| json!(format!("r-{}", uuid::Uuid::new_v4())));
+        }
+        let harness = Harness {
+            clock: self.clock.clone(),
+            runner: self.runner.clone(),
+            fs: self.fs.clone(),
+        };
+        let before = self.runner.calls().len();
+        // Boxed: the whole engine future is too large for a test thread's default stack.
+        let (manifest, _, _) =
+            scope(harness, Box::pin(super::run(goal_id, inputs, &self.policy))).await?;
+        let receipt = json!({
+            "manifest": manifest,
+            "bits": manifest.bits,
+            "schema_version": MANIFEST_SCHEMA_VERSION,
+        });
+        Ok(TestRun {
+            commands: self.runner.calls().split_off(before),
+            files: self.fs.files(),
+            receipt,
+            manifest,
+        })
+    }
+}
+
+#[cfg(test)]
+/// One goal run under [`TestEngine`].
+#[derive(Debug)]
+pub struct TestRun {
+    pub manifest: Manifest,
+    /// Commands this run sent to the runner.
+    pub commands: Vec<String>,
+    /// The in-memory filesystem after the run.
+    pub files: BTreeMap<PathBuf, Vec<u8>>,
+    /// The run as `response.json` would store it.
+    pub receipt: Value,
+}
+
+#[cfg(test)]
+impl TestRun {
+    pub fn evidence(&self, key: &str) -> &Value {
+        self.manifest.evidence.get(key).unwrap_or(&Value::Null)
+    }
+
+    pub fn success(&self) -> bool {
+        self.evidence("actual_success").as_bool().unwrap_or(false)
+    }
+
+    pub fn ran(&self, needle: &str) -> bool {
+        self.commands.iter().any(|c| c.contains(needle))
+    }
+
+    pub fn assert_success(&self) {
+        assert!(
+            self.success(),
+            "{} failed: {}",
+            self.manifest.goal_id,
+            self.manifest.evidence
+        );
+    }
+
+    pub fn assert_failure(&self) {
+        assert!(
+            !self.success(),
+            "{} unexpectedly succeeded",
+            self.manifest.goal_id
+        );
+    }
+
+    /// Receipt field at a JSON pointer (`/manifest/evidence/stdout`).
+    pub fn receipt_at(&self, pointer: &str) -> Result<&Value> {
+        self.receipt
+            .pointer(pointer)
+            .ok_or_else(|| anyhow!("receipt has no {}", pointer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn echo_goal_runs_without_a_shell() {
+        let run = TestEngine::new()
+            .run("demo.easy", json!({"message": "hello harness"}))
+            .await
+            .expect("run");
+        run.assert_success();
+        assert!(run.ran("echo"));
+        assert_eq!(
+            run.receipt_at("/manifest/goal_id").unwrap(),
+            &json!("demo.easy")
+        );
+    }
+
+    #[tokio::test]
+    async fn canned_failures_and_writes_stay_in_memory() {
+        let t = TestEngine::new().command("false", false, "");
+        let run = t.run("demo.impossible", json!({})).await.expect("run");
+        run.assert_failure();
+
+        let write = executor::Action::WriteFile {
+            path: "/nonexistent/harness.txt".to_string(),
+            content: "x".to_string(),
+        };
+        let harness = Harness {
+            fs: t.fs.clone(),
+            ..Harness::default()
+        };
+        let res = scope(harness, executor::execute(write, &Policy::default()))
+            .await
+            .expect("write");
+        assert!(res.ok);
+        assert_eq!(
+            t.fs.read(Path::new("/nonexistent/harness.txt")).unwrap(),
+            b"x"
+        );
+    }
+
+    #[tokio::test]
+    async fn virtual_clock_only_moves_when_advanced() {
+        let t = TestEngine::new();
+        let start = t.clock().now();
+        let inside = scope(
+            Harness {
+                clock: t.clock.clone(),
+                ..Harness::default()
+            },
+            async { now() },
+        )
+        .await;
+        assert_eq!(inside, start);
+        t.clock().advance(Duration::hours(2));
+        assert_eq!(t.clock().now() - start, Duration::hours(2));
+    }
+
+    #[tokio::test]
+    async fn builder_presets_clock_policy_and_files() {
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let run = TestEngine::new()
+            .policy(Policy::default())
+            .at(at)
+            .file("/seed/notes.txt", "seeded")
+            .run("demo.easy", json!({"message": "hi"}))
+            .await
+            .expect("run");
+        run.assert_success();
+        assert_eq!(
+            run.files
+                .get(Path::new("/seed/notes.txt"))
+                .map(Vec::as_slice),
+            Some(&b"seeded"[..])
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! What the engine needs from whoever embeds it, registered once at startup.
+//!
+//! Goals must not reach into the HTTP server (see `docs/WORKSPACE_SPLIT.md`), so the server
+//! hands in what they used to import from it: writing the child receipt of each `meta.omni`
+//! tool call, rendering CodeAct worlds, and running the children of `experiment.sweep`.
+//! Without a registered hook a tool call leaves no receipt, a CodeAct turn reports that world
+//! generation failed and a sweep child fails.
+
+use once_cell::sync::OnceCell;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::harness::BoxFuture;
+use super::types::{Bits, Manifest, Policy};
+
+pub trait ReceiptSink: Send + Sync {
+    /// Persist the receipt of child run `run_id`, which a tool call ran inside another goal.
+    fn tool_call<'a>(
+        &'a self,
+        run_id: &'a str,
+        inputs: &'a Value,
+        policy: &'a Policy,
+        manifest: &'a Manifest,
+        bits: &'a Bits,
+    ) -> BoxFuture<'a, ()>;
+}
+
+pub trait WorldRenderer: Send + Sync {
+    /// Render the system's own trace; returns the world's URL.
+    fn system_matrix(&self) -> BoxFuture<'_, Result<String, String>>;
+    /// Expand `seed` under `rules` for `depth` steps; returns the world's URL.
+    fn ruliad<'a>(
+        &'a self,
+        seed: &'a str,
+        rules: Vec<(String, String)>,
+        depth: usize,
+    ) -> BoxFuture<'a, Result<String, String>>;
+}
+
+pub trait ChildRunner: Send + Sync {
+    /// Run `request`, a `POST /run` body, in this process; the `POST /run` response.
+    fn run(&self, request: Value) -> BoxFuture<'_, Result<Value, String>>;
+}
+
+static RECEIPTS: OnceCell<Arc<dyn ReceiptSink>> = OnceCell::new();
+static WORLDS: OnceCell<Arc<dyn WorldRenderer>> = OnceCell::new();
+static CHILD_RUNS: OnceCell<Arc<dyn ChildRunner>> = OnceCell::new();
+
+/// First registration wins.
+pub fn set_receipt_sink(sink: Arc<dyn ReceiptSink>) {
+    let _ = RECEIPTS.set(sink);
+}
+
+pub fn set_world_renderer(renderer: Arc<dyn WorldRenderer>) {
+    let _ = WORLDS.set(renderer);
+}
+
+pub fn set_child_runner(runner: Arc<dyn ChildRunner>) {
+    let _ = CHILD_RUNS.set(runner);
+}
+
+pub async fn write_tool_call_receipt(
+    run_id: &str,
+    inputs: &Value,
+    policy: &Policy,
+    manifest: &Manifest,
+    bits: &Bits,
+) {
+    if let Some(sink) = RECEIPTS.get() {
+        sink.tool_call(run_id, inputs, policy, manifest, bits).await;
+    }
+}
+
+pub async fn system_matrix() -> Result<String, String> {
+    match WORLDS.get() {
+        Some(r) => r.system_matrix().await,
+        None => Err("no world renderer registered".to_string()),
+    }
+}
+
+pub async fn ruliad(
+    seed: &str,
+    rules: Vec<(String, String)>,
+    depth: usize,
+) -> Result<String, String> {
+    match WORLDS.get() {
+        Some(r) => r.ruliad(seed, rules, depth).await,
+        None => Err("no world renderer registered".to_string()),
+    }
+}
+
+pub async fn run_child(request: Value) -> Result<Value, String> {
+    match CHILD_RUNS.get() {
+        Some(r) => r.run(request).await,
+        None => Err("no child runner registered".to_string()),
+    }
+}
@@ -0,0 +1,350 @@
+//! Wolfram-model style hypergraph rewriting for `ruliad.hypergraph`.
+//!
+//! A state is an ordered list of relations (tuples of vertex ids). Each step finds a maximal set
+//! of non-overlapping rule matches (rules in order, relations oldest first), applies them all at
+//! once, appends the produced relations and mints fresh vertices for right-hand-side variables
+//! that are not bound by the left-hand side. Every applied match is an update event; an event
+//! causally depends on the events that created the relations it consumed.
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub type Relation = Vec<u64>;
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub lhs: Vec<Vec<String>>,
+    pub rhs: Vec<Vec<String>>,
+}
+
+/// Classic growth rule {{x,y},{x,z}} -> {{x,z},{x,w},{y,w},{z,w}}.
+pub fn default_rule() -> Rule {
+    let r = |xs: &[[&str; 2]]| -> Vec<Vec<String>> {
+        xs.iter()
+            .map(|r| r.iter().map(|v| v.to_string()).collect())
+            .collect()
+    };
+    Rule {
+        lhs: r(&[["x", "y"], ["x", "z"]]),
+        rhs: r(&[["x", "z"], ["x", "w"], ["y", "w"], ["z", "w"]]),
+    }
+}
+
+pub fn default_init() -> Vec<Relation> {
+    vec![vec![0, 0], vec![0, 0]]
+}
+
+fn var_key(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn pattern_list(v: &Value, what: &str) -> Result<Vec<Vec<String>>> {
+    let arr = v
+        .as_array()
+        .ok_or_else(|| anyhow!("{what} must be a list of relations"))?;
+    arr.iter()
+        .map(|rel| {
+            let items = rel
+                .as_array()
+                .filter(|a| !a.is_empty())
+                .ok_or_else(|| anyhow!("{what}: each relation must be a non-empty list"))?;
+            items
+                .iter()
+                .map(|x| {
+                    var_key(x)
+                        .ok_or_else(|| anyhow!("{what}: variables must be strings or numbers"))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl Rule {
+    /// Accepts `{"lhs": [[..],..], "rhs": [[..],..]}` or `[lhs, rhs]`.
+    pub fn parse(v: &Value) -> Result<Self> {
+        let (lhs, rhs) = match v {
+            Value::Object(o) => (
+                o.get("lhs").ok_or_else(|| anyhow!("rule is missing lhs"))?,
+                o.get("rhs").ok_or_else(|| anyhow!("rule is missing rhs"))?,
+            ),
+            Value::Array(a) if a.len() == 2 => (&a[0], &a[1]),
+            _ => bail!("rule must be {{lhs, rhs}} or [lhs, rhs]"),
+        };
+        let rule = Self {
+            lhs: pattern_list(lhs, "lhs")?,
+            rhs: pattern_list(rhs, "rhs")?,
+        };
+        if rule.lhs.is_empty() {
+            bail!("lhs must contain at least one relation");
+        }
+        Ok(rule)
+    }
+
+    pub fn describe(&self) -> String {
+        let side = |rels: &[Vec<String>]| {
+            format!(
+                "{{{}}}",
+                rels.iter()
+                    .map(|r| format!("{{{}}}", r.join(",")))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            )
+        };
+        format!("{}->{}", side(&self.lhs), side(&self.rhs))
+    }
+}
+
+/// Initial state: a list of relations over non-negative integer vertex ids.
+pub fn parse_init(v: &Value) -> Result<Vec<Relation>> {
+    let arr = v
+        .as_array()
+        .ok_or_else(|| anyhow!("init must be a list of relations"))?;
+    arr.iter()
+        .map(|rel| {
+            rel.as_array()
+                .filter(|a| !a.is_empty())
+                .ok_or_else(|| anyhow!("init: each relation must be a non-empty list"))?
+                .iter()
+                .map(|x| {
+                    x.as_u64()
+                        .ok_or_else(|| anyhow!("init: vertices must be non-negative integers"))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn render(rels: &[Relation]) -> String {
+    format!(
+        "{{{}}}",
+        rels.iter()
+            .map(|r| format!(
+                "{{{}}}",
+                r.iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ))
+            .collect::<Vec<_>>()
+            .join(",")
+    )
+}
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub step: usize,
+    pub rule: usize,
+    /// Events that created the consumed relations (initial relations have no creator).
+    pub causes: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Evolution {
+    /// State after each step (index 0 = initial state).
+    pub states: Vec<Vec<Relation>>,
+    pub events: Vec<Event>,
+    pub vertex_growth: Vec<usize>,
+    pub edge_growth: Vec<usize>,
+    pub events_per_step: Vec<usize>,
+    /// "steps" | "fixed_point" | "max_relations"
+    pub stop_reason: &'static str,
+}
+
+fn vertex_count(rels: &[Relation]) -> usize {
+    let mut vs: Vec<u64> = rels.iter().flatten().copied().collect();
+    vs.sort_unstable();
+    vs.dedup();
+    vs.len()
+}
+
+struct Matcher<'a> {
+    rule: &'a Rule,
+    live: &'a [(Option<usize>, Relation)],
+    used: &'a [bool],
+    by_vertex: &'a HashMap<u64, Vec<usize>>,
+}
+
+impl Matcher<'_> {
+    /// Backtracking match of `lhs[k..]`; the first pattern only considers relations from `from`.
+    fn extend(
+        &self,
+        k: usize,
+        from: usize,
+        chosen: &mut Vec<usize>,
+        bind: &mut HashMap<String, u64>,
+    ) -> bool {
+        let Some(pat) = self.rule.lhs.get(k) else {
+            return true;
+        };
+        // Narrow candidates through any already-bound variable.
+        let bound = pat.iter().find_map(|var| bind.get(var).copied());
+        let candidates: Box<dyn Iterator<Item = usize> + '_> = match bound {
+            Some(v) => match self.by_vertex.get(&v) {
+                Some(c) => Box::new(c.iter().copied()),
+                None => return false,
+            },
+            None => Box::new((if k == 0 { from } else { 0 })..self.live.len()),
+        };
+        for i in candidates {
+            let rel = &self.live[i].1;
+            if (k == 0 && i < from) || self.used[i] || chosen.contains(&i) || rel.len() != pat.len()
+            {
+                continue;
+            }
+            let mut added: Vec<&String> = Vec::new();
+            let mut ok = true;
+            for (var, &v) in pat.iter().zip(rel) {
+                match bind.get(var).copied() {
+                    Some(b) if b != v => {
+                        ok = false;
+                        break;
+                    }
+                    Some(_) => {}
+                    None => {
+                        bind.insert(var.clone(), v);
+                        added.push(var);
+                    }
+                }
+            }
+            if ok {
+                chosen.push(i);
+                if self.extend(k + 1, from, chosen, bind) {
+                    return true;
+                }
+                chosen.pop();
+            }
+            for var in added {
+                bind.remove(var);
+            }
+        }
+        false
+    }
+}
+
+pub fn evolve(
+    init: Vec<Relation>,
+    rules: &[Rule],
+    steps: usize,
+    max_relations: usize,
+) -> Evolution {
+    let mut next_vertex = init
+        .iter()
+        .flatten()
+        .copied()
+        .max()
+        .map(|m| m + 1)
+        .unwrap_or(0);
+    let mut live: Vec<(Option<usize>, Relation)> = init.into_iter().map(|r| (None, r)).collect();
+    let snapshot = |live: &[(Option<usize>, Relation)]| {
+        live.iter().map(|(_, r)| r.clone()).collect::<Vec<_>>()
+    };
+    let first = snapshot(&live);
+    let mut evo = Evolution {
+        vertex_growth: vec![vertex_count(&first)],
+        edge_growth: vec![first.len()],
+        states: vec![first],
+        events: Vec::new(),
+        events_per_step: vec![0],
+        stop_reason: "steps",
+    };
+
+    for step in 1..=steps {
+        let mut by_vertex: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, (_, rel)) in live.iter().enumerate() {
+            let mut vs = rel.clone();
+            vs.sort_unstable();
+            vs.dedup();
+            for v in vs {
+                by_vertex.entry(v).or_default().push(i);
+            }
+        }
+
+        // Collect a maximal non-overlapping match set, rules in order, oldest relations first.
+        let mut used = vec![false; live.len()];
+        let mut matches: Vec<(usize, Vec<usize>, HashMap<String, u64>)> = Vec::new();
+        for (ri, rule) in rules.iter().enumerate() {
+            let mut from = 0usize;
+            loop {
+                let mut chosen = Vec::new();
+                let mut bind = HashMap::new();
+                let found = Matcher {
+                    rule,
+                    live: &live,
+                    used: &used,
+                    by_vertex: &by_vertex,
+                }
+                .extend(0, from, &mut chosen, &mut bind);
+                if !found {
+                    break;
+                }
+                // A first relation that failed to start a match cannot start one later (fewer relations remain).
+                from = chosen[0];
+                for &i in &chosen {
+                    used[i] = true;
+                }
+                matches.push((ri, chosen, bind));
+            }
+        }
+        if matches.is_empty() {
+            evo.stop_reason = "fixed_point";
+            break;
+        }
+        let projected = live.len()
+            + matches
+                .iter()
+                .map(|(ri, _, _)| rules[*ri].rhs.len())
+                .sum::<usize>()
+            - matches
+                .iter()
+                .map(|(_, chosen, _)| chosen.len())
+                .sum::<usize>();
+        if projected > max_relations {
+            evo.stop_reason = "max_relations";
+            break;
+        }
+
+        let n_events = matches.len();
+        let mut produced: Vec<(Option<usize>, Relation)> = Vec::new();
+        for (ri, chosen, mut bind) in matches {
+            let ev = evo.events.len();
+            let mut causes: Vec<usize> = chosen.iter().filter_map(|&i| live[i].0).collect();
+            causes.sort_unstable();
+            causes.dedup();
+            for rel in &rules[ri].rhs {
+                let r: Relation = rel
+                    .iter()
+                    .map(|var| {
+                        *bind.entry(var.clone()).or_insert_with(|| {
+                            next_vertex += 1;
+                            next_vertex - 1
+                        })
+                    })
+                    .collect();
+                produced.push((Some(ev), r));
+            }
+            evo.events.push(Event {
+                step,
+                rule: ri,
+                causes,
+            });
+        }
+        live = live
+            .into_iter()
+            .zip(used)
+            .filter(|(_, u)| !u)
+            .map(|(r, _)| r)
+            .chain(produced)
+            .collect();
+        let state = snapshot(&live);
+        evo.vertex_growth.push(vertex_count(&state));
+        evo.edge_growth.push(state.len());
+        evo.events_per_step.push(n_events);
+        evo.states.push(state);
+    }
+    evo
+}
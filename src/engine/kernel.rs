@@ -102,8 +102,9 @@ impl KernelLoop {
         bits.a >= 1.0 && bits.p >= 1.0 && bits.d == 0.0
     }
 
-    pub fn evidence_gate(&self, bits: &ExtendedBits) -> bool {
-        if bits.u >= self.l2_params.confidence_gate_tau {
+    /// `tau` overrides `confidence_gate_tau` for one run (A/B experiment arms).
+    pub fn evidence_gate(&self, bits: &ExtendedBits, tau: Option<f32>) -> bool {
+        if bits.u >= tau.unwrap_or(self.l2_params.confidence_gate_tau) {
             // Require verification mode first
             false
         } else {
@@ -0,0 +1,234 @@
+//! Per-command resource limits (`policy.limits`) for executor children.
+//!
+//! Output bytes are counted as they are read, on every host. CPU seconds and resident memory
+//! are sampled from `/proc` for the command's whole process tree, so they are only enforced on
+//! Linux (and by the runtime's cgroup on the container backend). A command over a limit is
+//! killed together with everything it spawned, and the run that issued it records a
+//! [`LimitExceeded`] (see [`track`]): the goal fails with `bits.e = 1` and the evidence says
+//! which limit was hit.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use super::types::ResourceLimits;
+
+/// The error code commands and receipts use for a killed command.
+pub const CODE: &str = "resource_limit_exceeded";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LimitExceeded {
+    /// `cpu_s`, `max_rss_mb` or `max_output_bytes`.
+    pub limit: String,
+    pub max: u64,
+    pub used: u64,
+    pub command: String,
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {} over the limit of {}",
+            CODE, self.limit, self.used, self.max
+        )
+    }
+}
+
+/// Resources used so far by a process tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Usage {
+    pub cpu_s: u64,
+    pub rss_mb: u64,
+}
+
+/// The first limit `usage` and `output_bytes` go over, if any.
+pub fn check(
+    limits: &ResourceLimits,
+    usage: Usage,
+    output_bytes: u64,
+    command: &str,
+) -> Option<LimitExceeded> {
+    let over = |limit: &str, max: Option<u64>, used: u64| {
+        max.filter(|m| used > *m).map(|max| LimitExceeded {
+            limit: limit.to_string(),
+            max,
+            used,
+            command: command.to_string(),
+        })
+    };
+    over("max_output_bytes", limits.max_output_bytes, output_bytes)
+        .or_else(|| over("max_rss_mb", limits.max_rss_mb, usage.rss_mb))
+        .or_else(|| over("cpu_s", limits.cpu_s, usage.cpu_s))
+}
+
+/// `/proc/<pid>/stat` as `(ppid, cpu ticks)`, counting reaped children's time too.
+fn stat(pid: u32) -> Option<(u32, u64)> {
+    let raw = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // Fields after the parenthesised command name, which may itself contain spaces.
+    let rest = &raw[raw.rfind(')')? + 1..];
+    let f: Vec<&str> = rest.split_whitespace().collect();
+    let ppid = f.get(1)?.parse().ok()?;
+    let ticks = f
+        .get(11..15)?
+        .iter()
+        .filter_map(|v| v.parse::<u64>().ok())
+        .sum();
+    Some((ppid, ticks))
+}
+
+fn rss_kb(pid: u32) -> u64 {
+    std::fs::read_to_string(format!("/proc/{}/status", pid))
+        .ok()
+        .and_then(|s| {
+            s.lines()
+                .find_map(|l| l.strip_prefix("VmRSS:"))
+                .and_then(|v| v.split_whitespace().next()?.parse().ok())
+        })
+        .unwrap_or(0)
+}
+
+/// `root` and its live descendants, with each one's stat.
+fn tree(root: u32) -> Vec<(u32, u64)> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut procs: HashMap<u32, (u32, u64)> = HashMap::new();
+    for e in entries.flatten() {
+        if let Some(pid) = e.file_name().to_str().and_then(|n| n.parse::<u32>().ok()) {
+            if let Some(st) = stat(pid) {
+                procs.insert(pid, st);
+            }
+        }
+    }
+    let mut out = Vec::new();
+    let mut frontier = vec![root];
+    while let Some(pid) = frontier.pop() {
+        let Some(&(_, ticks)) = procs.get(&pid) else {
+            continue;
+        };
+        out.push((pid, ticks));
+        frontier.extend(
+            procs
+                .iter()
+                .filter(|(_, (ppid, _))| *ppid == pid)
+                .map(|(child, _)| *child),
+        );
+    }
+    out
+}
+
+/// Usage of `root`'s process tree; `None` where `/proc` is unavailable.
+pub fn tree_usage(root: u32) -> Option<Usage> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let procs = tree(root);
+    if procs.is_empty() {
+        return None;
+    }
+    // /proc reports CPU time in USER_HZ, which is 100 on Linux.
+    let ticks: u64 = procs.iter().map(|(_, t)| t).sum();
+    let rss: u64 = procs.iter().map(|(pid, _)| rss_kb(*pid)).sum();
+    Some(Usage {
+        cpu_s: ticks / 100,
+        rss_mb: rss / 1024,
+    })
+}
+
+/// [`tree_usage`] on the blocking pool: one sample reads every `/proc/<pid>`.
+pub async fn sample(root: u32) -> Option<Usage> {
+    tokio::task::spawn_blocking(move || tree_usage(root))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Kill `root`'s descendants; the caller kills `root` itself through its `Child`.
+pub async fn kill_tree(root: u32) {
+    if !cfg!(unix) {
+        return;
+    }
+    let pids: Vec<String> = tree(root)
+        .into_iter()
+        .filter(|(pid, _)| *pid != root)
+        .map(|(pid, _)| pid.to_string())
+        .collect();
+    if pids.is_empty() {
+        return;
+    }
+    let _ = tokio::process::Command::new("kill")
+        .arg("-KILL")
+        .args(&pids)
+        .status()
+        .await;
+}
+
+tokio::task_local! {
+    static EXCEEDED: Arc<Mutex<Vec<LimitExceeded>>>;
+}
+
+/// Note that a command of the current run was killed.
+pub fn record(e: LimitExceeded) {
+    let _ = EXCEEDED.try_with(|v| v.lock().unwrap_or_else(|p| p.into_inner()).push(e));
+}
+
+/// Run `fut` (a goal run), returning the limits its commands went over.
+pub async fn track<F: Future>(fut: F) -> (F::Output, Vec<LimitExceeded>) {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let out = EXCEEDED.scope(seen.clone(), fut).await;
+    let seen = seen.lock().unwrap_or_else(|p| p.into_inner()).clone();
+    (out, seen)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_limit_over_is_reported() {
+        let limits = ResourceLimits {
+            cpu_s: Some(10),
+            max_rss_mb: Some(512),
+            max_output_bytes: None,
+        };
+        let fine = Usage {
+            cpu_s: 10,
+            rss_mb: 100,
+        };
+        assert_eq!(check(&limits, fine, u64::MAX, "make"), None);
+        let hog = Usage {
+            cpu_s: 11,
+            rss_mb: 900,
+        };
+        let e = check(&limits, hog, 0, "make").unwrap();
+        assert_eq!(e.limit, "max_rss_mb");
+        assert_eq!(
+            e.to_string(),
+            "resource_limit_exceeded: max_rss_mb 900 over the limit of 512"
+        );
+    }
+
+    #[tokio::test]
+    async fn records_reach_the_tracking_run_only() {
+        record(LimitExceeded {
+            limit: "cpu_s".into(),
+            max: 1,
+            used: 2,
+            command: "outside".into(),
+        });
+        let ((), seen) = track(async {
+            record(LimitExceeded {
+                limit: "cpu_s".into(),
+                max: 1,
+                used: 2,
+                command: "inside".into(),
+            })
+        })
+        .await;
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].command, "inside");
+    }
+}
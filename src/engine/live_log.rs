@@ -0,0 +1,189 @@
+//! Live build logs: command output appended to a file while the command is still running.
+//!
+//! meta3.build runs its build command inside [`scope`], so every chunk the executor reads from
+//! the child also lands in `runs/meta3-build/<run_id>.log.partial` as it arrives. When the
+//! command finishes the goal writes the usual `<run_id>.log` (or `.log.gz`) and removes the
+//! partial file. [`tail`] reads from a byte offset of whichever exists and can wait for more
+//! output; `GET /runs/{run_id}/log/tail` serves it.
+//!
+//! The final log is the partial one unless the build wrote to stderr or hit the output cap, in
+//! which case offsets past its end are clamped to it.
+
+use flate2::read::GzDecoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::future::Future;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Most bytes one [`tail`] call returns.
+pub const MAX_CHUNK: u64 = 256 * 1024;
+/// Longest [`tail`] waits for new output.
+pub const MAX_WAIT: Duration = Duration::from_secs(30);
+const POLL: Duration = Duration::from_millis(250);
+
+tokio::task_local! {
+    static SINK: Option<Arc<File>>;
+}
+
+/// Run `fut` with its commands' output also appended to `path` (created or truncated).
+pub async fn scope<F: Future>(path: &Path, fut: F) -> F::Output {
+    let sink = match File::create(path) {
+        Ok(f) => Some(Arc::new(f)),
+        Err(e) => {
+            tracing::warn!("cannot create live log {}: {}", path.display(), e);
+            None
+        }
+    };
+    SINK.scope(sink, fut).await
+}
+
+/// The live log of the current task, if any.
+pub fn current() -> Option<Arc<File>> {
+    SINK.try_with(|s| s.clone()).ok().flatten()
+}
+
+/// `<log>.partial`: where output goes until `log` itself is written.
+pub fn partial_path(log: &Path) -> PathBuf {
+    let mut os = log.as_os_str().to_os_string();
+    os.push(".partial");
+    PathBuf::from(os)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct LogTail {
+    pub run_id: String,
+    /// Offset the bytes in `data` start at.
+    pub offset: u64,
+    /// Offset to ask for next.
+    pub next_offset: u64,
+    /// Bytes of log so far.
+    pub size: u64,
+    /// Output since `offset` (invalid UTF-8 replaced; a character split by a read stays for the
+    /// next call).
+    pub data: String,
+    /// The build finished: once `next_offset == size` there is nothing more to come.
+    pub complete: bool,
+}
+
+fn log_dir(root: &Path) -> PathBuf {
+    root.join("runs/meta3-build")
+}
+
+/// Up to `max` bytes from `offset`, the log size, and whether the log is final.
+fn read_at(dir: &Path, run_id: &str, offset: u64, max: u64) -> Option<(Vec<u8>, u64, bool)> {
+    let log = dir.join(format!("{}.log", run_id));
+    let gz = dir.join(format!("{}.log.gz", run_id));
+    if gz.is_file() && !log.is_file() {
+        let mut all = Vec::new();
+        GzDecoder::new(File::open(&gz).ok()?)
+            .read_to_end(&mut all)
+            .ok()?;
+        let size = all.len() as u64;
+        let start = offset.min(size) as usize;
+        let end = (offset.saturating_add(max)).min(size) as usize;
+        return Some((all[start..end].to_vec(), size, true));
+    }
+    let (path, complete) = if log.is_file() {
+        (log, true)
+    } else {
+        (partial_path(&log), false)
+    };
+    let mut f = File::open(&path).ok()?;
+    let size = f.metadata().ok()?.len();
+    f.seek(SeekFrom::Start(offset.min(size))).ok()?;
+    let mut buf = Vec::new();
+    f.take(max).read_to_end(&mut buf).ok()?;
+    Some((buf, size, complete))
+}
+
+/// Length of `buf` without a trailing, incomplete UTF-8 sequence.
+fn whole_chars(buf: &[u8]) -> usize {
+    for i in (buf.len().saturating_sub(3)..buf.len()).rev() {
+        let b = buf[i];
+        if b & 0xC0 == 0x80 {
+            continue;
+        }
+        let need = match b {
+            0xF0.. => 4,
+            0xE0.. => 3,
+            0xC0.. => 2,
+            _ => 1,
+        };
+        return if buf.len() - i < need { i } else { buf.len() };
+    }
+    buf.len()
+}
+
+/// Output of run `run_id`'s build log from `offset`, waiting up to `wait` for bytes to appear
+/// (or the log to complete). `None` when the run has no log (yet).
+pub async fn tail(run_id: &str, offset: u64, wait: Duration) -> Option<LogTail> {
+    let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+    tail_in(&log_dir(&root), run_id, offset, wait).await
+}
+
+async fn tail_in(dir: &Path, run_id: &str, offset: u64, wait: Duration) -> Option<LogTail> {
+    let deadline = tokio::time::Instant::now() + wait.min(MAX_WAIT);
+    loop {
+        let read = read_at(dir, run_id, offset, MAX_CHUNK);
+        let ready = read
+            .as_ref()
+            .is_some_and(|(buf, _, complete)| !buf.is_empty() || *complete);
+        if ready || tokio::time::Instant::now() >= deadline {
+            let (mut buf, size, complete) = read?;
+            if !complete {
+                buf.truncate(whole_chars(&buf));
+            }
+            let offset = offset.min(size);
+            return Some(LogTail {
+                run_id: run_id.to_string(),
+                offset,
+                next_offset: offset + buf.len() as u64,
+                size,
+                data: String::from_utf8_lossy(&buf).into_owned(),
+                complete,
+            });
+        }
+        tokio::time::sleep(POLL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tail_follows_the_partial_log_then_the_final_one() {
+        let dir = std::env::temp_dir().join(format!("live-log-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let log = dir.join("r-1.log");
+        assert!(tail_in(&dir, "r-1", 0, Duration::ZERO).await.is_none());
+
+        // "é" is split across two writes; the first tail stops before it.
+        std::fs::write(partial_path(&log), b"step 1\n\xC3").unwrap();
+        let t = tail_in(&dir, "r-1", 0, Duration::ZERO).await.unwrap();
+        assert_eq!(
+            (t.data.as_str(), t.next_offset, t.complete),
+            ("step 1\n", 7, false)
+        );
+
+        std::fs::write(partial_path(&log), "step 1\né\n".as_bytes()).unwrap();
+        let t = tail_in(&dir, "r-1", t.next_offset, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(t.data, "é\n");
+
+        std::fs::write(&log, "step 1\né\ndone\n").unwrap();
+        std::fs::remove_file(partial_path(&log)).unwrap();
+        let t = tail_in(&dir, "r-1", t.next_offset, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!((t.data.as_str(), t.complete), ("done\n", true));
+        assert_eq!(t.next_offset, t.size);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,20 +1,25 @@
 use anyhow::Result;
 use serde_json::{json, Value};
-use std::fs;
 
-use crate::engine::router;
+use crate::engine::{prompts, router};
 
 pub async fn handle(message: &str, bits: &crate::engine::bits::Bits) -> Result<Value> {
-    let persona = fs::read_to_string("prompts/meta_omni.md").unwrap_or_else(|_| {
-        "You are One Engine v0.2, a metacognitive AI system. Act with clarity.".to_string()
-    });
+    let prompt = prompts::render(
+        "meta_omni",
+        "You are One Engine v0.2, a metacognitive AI system. Act with clarity.",
+        &[("goal", "meta.prompt")],
+    );
+    let persona = prompt.text;
 
-    match router::chat(&persona, message).await {
-        Ok(mut response) => {
-            response
-                .as_object_mut()
-                .map(|obj| obj.insert("bits".to_string(), serde_json::json!(bits)))
-                .unwrap_or_default();
+    match router::chat_opts(&persona, message, &router::ChatOpts::default()).await {
+        Ok(router::ChatOutcome {
+            value: mut response,
+            ..
+        }) => {
+            if let Some(obj) = response.as_object_mut() {
+                obj.insert("bits".to_string(), serde_json::json!(bits));
+                obj.insert("prompt".to_string(), serde_json::json!(prompt.record));
+            }
             Ok(response)
         }
         Err(err) => {
@@ -0,0 +1,140 @@
+//! Receipt schema migrations.
+//!
+//! `response.json` files written by older builds are upgraded to [`MANIFEST_SCHEMA_VERSION`]
+//! whenever they are read ([`upgrade_response`]). `one-engine migrate-receipts` rewrites them
+//! on disk ([`migrate_receipts`]), keeping the original as `response.json.v<N>.bak`.
+
+use anyhow::{Context, Result};
+use one_engine::atomic;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+use super::types::MANIFEST_SCHEMA_VERSION;
+
+/// `STEPS[i]` upgrades a response from version `i + 1` to `i + 2`.
+const STEPS: &[fn(&mut Map<String, Value>)] = &[v1_to_v2];
+
+/// Version a stored response was written with; unversioned receipts are 1.
+pub fn response_version(resp: &Value) -> u32 {
+    resp.get("schema_version")
+        .or_else(|| resp.get("manifest").and_then(|m| m.get("schema_version")))
+        .and_then(|v| v.as_u64())
+        .map(|v| v as u32)
+        .unwrap_or(1)
+}
+
+/// v1 receipts may lack `deliverables`/`evidence`, `bits.m`, or one of the two `bits` copies.
+fn v1_to_v2(resp: &mut Map<String, Value>) {
+    if let Some(m) = resp.get_mut("manifest").and_then(|m| m.as_object_mut()) {
+        m.entry("deliverables").or_insert_with(|| json!([]));
+        m.entry("evidence").or_insert_with(|| json!({}));
+    }
+    let manifest_bits = resp.get("manifest").and_then(|m| m.get("bits")).cloned();
+    if !resp.contains_key("bits") {
+        if let Some(b) = manifest_bits {
+            resp.insert("bits".to_string(), b);
+        }
+    }
+    let top_bits = resp.get("bits").cloned();
+    if let (Some(m), Some(b)) = (
+        resp.get_mut("manifest").and_then(|m| m.as_object_mut()),
+        top_bits,
+    ) {
+        m.entry("bits").or_insert(b);
+    }
+    let manifest_bits = resp
+        .get_mut("manifest")
+        .and_then(|m| m.get_mut("bits"))
+        .and_then(|b| b.as_object_mut());
+    if let Some(b) = manifest_bits {
+        b.entry("m").or_insert(json!(0.0));
+    }
+    if let Some(b) = resp.get_mut("bits").and_then(|b| b.as_object_mut()) {
+        b.entry("m").or_insert(json!(0.0));
+    }
+}
+
+/// Upgrade a stored response in place; returns the version it had when it was older than
+/// the current one. Responses from a newer build are left alone.
+pub fn upgrade_response(resp: &mut Value) -> Option<u32> {
+    let from = response_version(resp);
+    if from >= MANIFEST_SCHEMA_VERSION {
+        return None;
+    }
+    let obj = resp.as_object_mut()?;
+    for step in STEPS.iter().skip(from.saturating_sub(1) as usize) {
+        step(obj);
+    }
+    obj.insert("schema_version".to_string(), json!(MANIFEST_SCHEMA_VERSION));
+    if let Some(m) = obj.get_mut("manifest").and_then(|m| m.as_object_mut()) {
+        m.insert("schema_version".to_string(), json!(MANIFEST_SCHEMA_VERSION));
+    }
+    Some(from)
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MigrateReport {
+    pub schema_version: u32,
+    pub scanned: usize,
+    pub migrated: usize,
+    pub up_to_date: usize,
+    pub dry_run: bool,
+    /// `(run_id, error)` for receipts that could not be read or written.
+    pub failed: Vec<(String, String)>,
+}
+
+fn migrate_one(dir: &Path, dry_run: bool) -> Result<bool> {
+    let path = dir.join("response.json");
+    let raw = fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    let mut resp: Value =
+        serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+    let Some(from) = upgrade_response(&mut resp) else {
+        return Ok(false);
+    };
+    if dry_run {
+        return Ok(true);
+    }
+    let backup = dir.join(format!("response.json.v{}.bak", from));
+    if !backup.exists() {
+        fs::write(&backup, &raw).with_context(|| format!("write {}", backup.display()))?;
+    }
+    atomic::write(&path, serde_json::to_string_pretty(&resp)?)
+        .with_context(|| format!("replace {}", path.display()))?;
+    Ok(true)
+}
+
+/// Rewrite every `runs/receipts/<run_id>/response.json` under `root` to the current version.
+pub fn migrate_receipts(root: &Path, dry_run: bool) -> Result<MigrateReport> {
+    let mut report = MigrateReport {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        dry_run,
+        ..Default::default()
+    };
+    let receipts = root.join("runs").join("receipts");
+    let entries = match fs::read_dir(&receipts) {
+        Ok(e) => e,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(e).with_context(|| format!("read {}", receipts.display())),
+    };
+    let mut dirs: Vec<_> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.join("response.json").is_file())
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        report.scanned += 1;
+        let run_id = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        match migrate_one(&dir, dry_run) {
+            Ok(true) => report.migrated += 1,
+            Ok(false) => report.up_to_date += 1,
+            Err(e) => report.failed.push((run_id, format!("{:#}", e))),
+        }
+    }
+    Ok(report)
+}
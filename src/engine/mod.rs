@@ -1,29 +1,63 @@
 pub mod bits;
+pub mod build_log;
+pub mod catalog;
+pub mod container;
+pub mod correlation;
+pub mod environment;
+pub mod evidence;
 pub mod executor;
+pub mod flags;
 pub mod goals;
 pub mod golden;
+pub mod hooks;
 pub mod kernel;
+pub mod limits;
+pub mod live_log;
 pub mod meta_prompt;
+pub mod migrate;
+pub mod offload;
+pub mod ops;
+pub mod pdf;
+pub mod platform;
+#[cfg(feature = "wasm")]
+pub mod plugins;
 pub mod policy;
+pub mod prompts;
+pub mod regression;
+pub mod remote;
 pub mod router;
+pub mod routing;
+pub mod ruliad;
+pub mod secrets;
+pub mod simulation;
+pub mod snapshot;
 pub mod types;
 pub mod validate;
 pub mod verify;
+pub mod graph_doc;
+pub mod graph_layout;
 pub mod graphs;
+pub mod harness;
+pub mod hypergraph;
 pub mod thread_report;
+pub mod tokens;
+pub mod urls;
 pub mod wiki;
+pub mod write_scope;
 
+use one_engine::{atomic, storage};
 use std::{fs, path::{Path, PathBuf}, time::UNIX_EPOCH};
 
 use crate::engine::validate::set_align_boost;
+use crate::integrations::telemetry;
 use anyhow::Context;
 use bits::Bits;
-use chrono::{DateTime, Utc};
+use evidence::{BuildEvidence, Evidence, GraphEvidence, Outcome, WikiEvidence};
 use kernel::{ExtendedBits, KernelLoop, Meta2Proposal};
 use serde::Deserialize;
 use serde_json::json;
 use sha2::{Digest, Sha256};
-use types::{Manifest, Policy};
+use types::{Manifest, Policy, MANIFEST_SCHEMA_VERSION};
 use uuid::Uuid;
 
 static mut KERNEL: Option<KernelLoop> = None;
@@ -34,6 +68,10 @@ static mut TRACE_HISTORY: Vec<ExtendedBits> = Vec::new();
 struct PoliciesFile {
     #[serde(default)]
     meta3_build: Option<Meta3BuildPolicy>,
+    #[serde(default)]
+    cargo: Option<goals::cargo::CargoPolicy>,
+    #[serde(default)]
+    research_fetch: Option<goals::research_fetch::FetchPolicy>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,6 +80,12 @@ struct Meta3BuildPolicy {
     default_cmd: Option<String>,
     #[serde(default)]
     forbid_global_installs: Option<bool>,
+    /// `remote_hosts:` entry to build on when the run names none.
+    #[serde(default)]
+    remote: Option<String>,
+    /// Bisect stored build logs in regression reports (the `bisect` input overrides).
+    #[serde(default)]
+    regression_bisect: Option<bool>,
 }
 
 fn contains_global_install(cmd: &str) -> bool {
@@ -52,6 +96,119 @@ fn contains_global_install(cmd: &str) -> bool {
         || s.contains("sudo ")
 }
 
+/// Logs at or above `ONE_ENGINE_GZIP_LOG_MIN_BYTES` (default 1 MiB, `0` disables) are stored
+/// as `<name>.gz`; `/runs/...` serves them transparently under the original name.
+fn write_log_maybe_gz(path: &Path, bytes: &[u8]) -> anyhow::Result<PathBuf> {
+    let min_bytes = std::env::var("ONE_ENGINE_GZIP_LOG_MIN_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(1024 * 1024);
+    if min_bytes == 0 || bytes.len() < min_bytes {
+        atomic::write(path, bytes).with_context(|| format!("failed to write log {}", path.display()))?;
+        return Ok(path.to_path_buf());
+    }
+    use std::io::Write;
+    let mut gz_os = path.as_os_str().to_os_string();
+    gz_os.push(".gz");
+    let gz_path = PathBuf::from(gz_os);
+    let mut enc = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let gz = enc
+        .write_all(bytes)
+        .and_then(|_| enc.finish())
+        .with_context(|| format!("failed to compress log {}", gz_path.display()))?;
+    atomic::write(&gz_path, gz).with_context(|| format!("failed to write log {}", gz_path.display()))?;
+    Ok(gz_path)
+}
+
+fn load_cargo_policy() -> goals::cargo::CargoPolicy {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .ok()
+        .unwrap_or_else(|| "config/policies.yaml".to_string());
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesFile>(&raw).ok())
+        .and_then(|p| p.cargo)
+        .unwrap_or_default()
+}
+
+fn load_research_fetch_policy() -> goals::research_fetch::FetchPolicy {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .ok()
+        .unwrap_or_else(|| "config/policies.yaml".to_string());
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesFile>(&raw).ok())
+        .and_then(|p| p.research_fetch)
+        .unwrap_or_default()
+}
+
+fn load_meta3_build_remote() -> Option<String> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .ok()
+        .unwrap_or_else(|| "config/policies.yaml".to_string());
+    let raw = fs::read_to_string(&path).ok()?;
+    let parsed: PoliciesFile = serde_yaml::from_str(&raw).ok()?;
+    parsed.meta3_build?.remote.filter(|r| !r.trim().is_empty())
+}
+
+fn load_meta3_build_regression_bisect() -> bool {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .ok()
+        .unwrap_or_else(|| "config/policies.yaml".to_string());
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesFile>(&raw).ok())
+        .and_then(|p| p.meta3_build?.regression_bisect)
+        .unwrap_or(false)
+}
+
+/// For a red `meta3.build`, compare it with the last green receipt and link the report. The
+/// receipts are read on the blocking pool.
+async fn record_regression(
+    goal_id: &str,
+    receipt_id: Option<&str>,
+    inputs: &serde_json::Value,
+    policy: &Policy,
+    manifest: &mut Manifest,
+) {
+    if !goal_id.contains("meta3.build") {
+        return;
+    }
+    let Some(failing) = serde_json::to_value(&*manifest)
+        .ok()
+        .and_then(|m| {
+            regression::view(receipt_id.unwrap_or_default(), inputs, &json!(policy), &m)
+        })
+        .filter(|r| !r.success)
+    else {
+        return;
+    };
+    let bisect = inputs
+        .get("bisect")
+        .and_then(|v| v.as_bool())
+        .unwrap_or_else(load_meta3_build_regression_bisect);
+    let goal = goal_id.to_string();
+    let written = atomic::spawn_blocking(move || {
+        let report = regression::analyze(&goal, &failing, bisect);
+        regression::write(&report)
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.into()));
+    match written {
+        Ok((path, link)) => {
+            manifest.deliverables.push(path.display().to_string());
+            if let Some(obj) = manifest.evidence.as_object_mut() {
+                obj.insert("regression".to_string(), link);
+            }
+        }
+        Err(e) => tracing::warn!(
+            "regression report for {} not written: {:#}",
+            manifest.run_id,
+            e
+        ),
+    }
+}
+
 fn load_meta3_build_cmd_from_policies() -> Option<String> {
     let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
         .ok()
@@ -71,59 +228,382 @@ fn load_meta3_build_cmd_from_policies() -> Option<String> {
     Some(cmd)
 }
 
+/// Relative input paths are resolved against META3_ROOT so run payloads stay portable.
+fn under_meta3_root(p: &str) -> PathBuf {
+    let path = PathBuf::from(p);
+    if path.is_absolute() {
+        return path;
+    }
+    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string())).join(path)
+}
+
 pub async fn run(
     goal_id: &str,
-    inputs: serde_json::Value,
+    mut inputs: serde_json::Value,
     policy: &Policy,
 ) -> anyhow::Result<(Manifest, ExtendedBits, Option<Meta2Proposal>)> {
-    let kernel = unsafe { KERNEL.get_or_insert_with(KernelLoop::new) };
-    let mut bits = ExtendedBits::init();
-    // Freshness filter: set Δ when any context item is expired
-    if let Some(ctx_items) = inputs.get("context").and_then(|v| v.as_array()) {
-        for item in ctx_items {
-            if let (Some(ts), Some(ttl)) = (
-                item.get("ts").and_then(|v| v.as_str()),
-                item.get("ttl").and_then(|v| v.as_i64()),
-            ) {
-                if let Ok(parsed) =
-                    chrono::DateTime::parse_from_rfc3339(ts).map(|dt| dt.with_timezone(&Utc))
-                {
-                    let age = (Utc::now() - parsed).num_seconds();
-                    if age > ttl {
-                        bits.d = 1.0;
-                    }
-                }
-            }
+    // Renamed goals run as their new id (the old singular graph ids included).
+    let resolved = catalog::resolve(goal_id);
+    let deprecation = resolved.deprecation(goal_id);
+    if let Some(d) = &deprecation {
+        tracing::warn!("{}", d["message"].as_str().unwrap_or_default());
+        telemetry::emit("engine", "goal_deprecated", None, d.clone());
+    }
+    let receipt_id = inputs
+        .get("__run_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+    // A goal family runs as the variant its current bits call for; the family's own kill
+    // switch counts as well as the variant's.
+    if routing::is_family(&resolved.goal_id) {
+        flags::check(&resolved.goal_id, receipt_id.as_deref().unwrap_or_default())?;
+    }
+    let routing = routing::route(&resolved.goal_id, &mut inputs, policy).await;
+    let routed = routing.as_ref().map_or(resolved.goal_id.as_str(), |r| r.goal_id.as_str());
+    // Kill switches fail the run here; a rollout may send it to a newer goal version.
+    let rollout = flags::check(routed, receipt_id.as_deref().unwrap_or_default())?;
+    let goal_id = rollout.as_ref().map_or(routed, |r| r.goal_id.as_str());
+    let snap = take_snapshot_if_requested(goal_id, &inputs, policy);
+    let freshness = assess_context(goal_id, &inputs);
+    let context_stale = freshness.as_ref().is_some_and(|f| f.stale > 0);
+    let environment = match &receipt_id {
+        Some(id) => environment::record(id, goal_id, policy).await,
+        None => None,
+    };
+    // The form receipts store, with secret references rather than values.
+    let request_inputs = inputs.clone();
+    let tau_override = tau_override(&inputs);
+    // Secret values exist only for the goal itself; whatever it echoes back is scrubbed.
+    let (inputs, secrets) = secrets::resolve(goal_id, inputs).await?;
+    let goal = container::scope(goal_id, policy, run_goal(goal_id, inputs, policy, context_stale));
+    let goal = simulation::scope(goal);
+    let (res, exceeded) = limits::track(goal).await;
+    let (mut manifest, mut bits, meta2) = res.map_err(|e| {
+        // Goals that bail on a failed command still name the limit that killed it.
+        let msg = match exceeded.first() {
+            Some(x) => format!("{}: {:#}", x, e),
+            None => format!("{:#}", e),
+        };
+        anyhow::anyhow!(secrets.scrub_str(&msg))
+    })?;
+    if !exceeded.is_empty() {
+        record_limits_exceeded(&mut manifest, &mut bits, &exceeded);
+    }
+    secrets.scrub(&mut manifest.evidence);
+    for d in manifest.deliverables.iter_mut() {
+        *d = secrets.scrub_str(d);
+    }
+    if let Some((snap_id, snap)) = snap {
+        record_snapshot(&mut manifest, &snap_id, snap);
+    }
+    if let (Some(report), Some(obj)) = (freshness, manifest.evidence.as_object_mut()) {
+        obj.insert("context_freshness".to_string(), json!(report));
+    }
+    if let (Some(d), Some(obj)) = (deprecation, manifest.evidence.as_object_mut()) {
+        obj.insert("deprecation".to_string(), d);
+    }
+    if let (Some(r), Some(obj)) = (routing.as_ref(), manifest.evidence.as_object_mut()) {
+        obj.insert("routing".to_string(), json!(r));
+    }
+    if let (Some(r), Some(obj)) = (rollout.as_ref(), manifest.evidence.as_object_mut()) {
+        obj.insert("rollout".to_string(), json!(r));
+    }
+    if let (Some(env), Some(obj)) = (environment, manifest.evidence.as_object_mut()) {
+        obj.insert("environment".to_string(), env);
+    }
+    apply_registered_verifier(&mut manifest, &mut bits);
+    enforce_evidence_gate(goal_id, tau_override, &mut manifest, &mut bits);
+    record_regression(
+        goal_id,
+        receipt_id.as_deref(),
+        &request_inputs,
+        policy,
+        &mut manifest,
+    )
+    .await;
+    // Last, so verifiers above still see whole fields.
+    let receipt_id = receipt_id.unwrap_or_else(|| manifest.run_id.clone());
+    offload::apply(&receipt_id, &mut manifest.evidence);
+    Ok((manifest, bits, meta2))
+}
+
+/// Per-item staleness of `inputs.context`; stale reports are persisted (the Ask-Act gate
+/// rejects the run before any evidence exists) so `GET /context/staleness` can explain Δ.
+fn assess_context(goal_id: &str, inputs: &serde_json::Value) -> Option<crate::context::FreshnessReport> {
+    let items = inputs.get("context").and_then(|v| v.as_array())?;
+    let report = crate::context::assess(items);
+    if report.stale > 0 {
+        let run_id = inputs
+            .get("__run_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("r-{}", Uuid::new_v4()));
+        let thread = inputs.get("thread").and_then(|v| v.as_str());
+        crate::context::record_staleness(&run_id, goal_id, thread, &report);
+    }
+    Some(report)
+}
+
+/// With `policy.snapshot`, copy the paths a mutating goal will touch before it runs.
+/// Keyed by the external `__run_id` when present (so `/runs/{run_id}/rollback` finds it).
+fn take_snapshot_if_requested(
+    goal_id: &str,
+    inputs: &serde_json::Value,
+    policy: &Policy,
+) -> Option<(String, snapshot::Snapshot)> {
+    if !policy.snapshot || !(snapshot::is_mutating(goal_id) || inputs.get("snapshot_paths").is_some()) {
+        return None;
+    }
+    let targets = snapshot::targets_for(goal_id, inputs);
+    if targets.is_empty() {
+        return None;
+    }
+    let snap_id = inputs
+        .get("__run_id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("snap-{}", Uuid::new_v4()));
+    match snapshot::take(&snap_id, goal_id, &targets) {
+        Ok(snap) => Some((snap_id, snap)),
+        Err(e) => {
+            tracing::warn!("snapshot before {} failed: {}", goal_id, e);
+            None
         }
     }
+}
 
-    // Set uncertainty based on goal difficulty
-    bits.u = match goal_id {
+fn record_snapshot(manifest: &mut Manifest, snap_id: &str, mut snap: snapshot::Snapshot) {
+    // Provisional ids follow the handler's run_id; external ids already match the receipt.
+    if snap_id.starts_with("snap-") && snapshot::rename(snap_id, &manifest.run_id).is_ok() {
+        snap.run_id = manifest.run_id.clone();
+    }
+    if let Some(obj) = manifest.evidence.as_object_mut() {
+        obj.insert(
+            "snapshot".to_string(),
+            json!({
+                "run_id": snap.run_id,
+                "targets": snap.targets,
+                "rollback_url": urls::url_for(&format!("/runs/{}/rollback", snap.run_id))
+            }),
+        );
+    }
+}
+
+/// A command killed over `policy.limits` fails the run, whatever the goal made of its output.
+fn record_limits_exceeded(
+    manifest: &mut Manifest,
+    bits: &mut ExtendedBits,
+    exceeded: &[limits::LimitExceeded],
+) {
+    bits.e = 1.0;
+    manifest.bits = bits.clone().into();
+    if let Some(obj) = manifest.evidence.as_object_mut() {
+        obj.insert("actual_success".to_string(), json!(false));
+        obj.insert(limits::CODE.to_string(), json!(exceeded));
+    }
+}
+
+/// τ of the evidence gate for this run, when an A/B experiment arm overrides it.
+fn tau_override(inputs: &serde_json::Value) -> Option<f32> {
+    inputs
+        .pointer("/__kernel/confidence_gate_tau")
+        .and_then(|v| v.as_f64())
+        .map(|v| v as f32)
+}
+
+/// Uncertainty a goal starts with, before its handler runs.
+fn prior_uncertainty(goal_id: &str) -> f32 {
+    match goal_id {
         id if id.contains("easy") => 0.1,
         id if id.contains("hard") => 0.7,
         id if id.contains("impossible") => 0.9,
         _ => 0.3,
+    }
+}
+
+/// The evidence gate, for every goal: a run whose prior uncertainty is at or above τ needs a
+/// passing verification before high trust. The builtin command path replays its command
+/// (its `verification` is already in evidence); any other goal counts as verified when its
+/// family's registered verifier passed, and has its trust capped otherwise.
+fn enforce_evidence_gate(
+    goal_id: &str,
+    tau_override: Option<f32>,
+    manifest: &mut Manifest,
+    bits: &mut ExtendedBits,
+) {
+    let kernel = unsafe { (*std::ptr::addr_of_mut!(KERNEL)).get_or_insert_with(KernelLoop::new) };
+    let mut prior = ExtendedBits::init();
+    prior.u = prior_uncertainty(goal_id);
+    if kernel.evidence_gate(&prior, tau_override) {
+        return;
+    }
+    let tau = tau_override.unwrap_or(kernel.l2_params.confidence_gate_tau);
+    let Some(obj) = manifest.evidence.as_object_mut() else {
+        return;
     };
+    if obj.get("verification").is_some_and(|v| !v.is_null()) {
+        return;
+    }
+    let checks = obj.get("checks").and_then(|c| c.get("passed"));
+    let passed = checks.and_then(|v| v.as_bool()).unwrap_or(false);
+    if !passed {
+        bits.t = bits.t.min(verify::UNVERIFIED_TRUST_CAP);
+        manifest.bits = bits.clone().into();
+    }
+    let detail = match checks {
+        Some(_) => "registered verifier",
+        None => "no verifier registered for this goal",
+    };
+    obj.insert(
+        "evidence_gate".to_string(),
+        json!({"triggered": true, "tau": tau}),
+    );
+    obj.insert(
+        "verification".to_string(),
+        json!({"method": "registered_verifier", "passed": passed, "detail": detail}),
+    );
+    telemetry::emit(
+        "kernel",
+        "verification",
+        Some(&manifest.run_id),
+        json!({"goal_id": goal_id, "method": "registered_verifier", "passed": passed}),
+    );
+}
+
+/// Run the goal family's registered verifier (verify::REGISTRY) and record its checks.
+/// A failing verifier overrides the handler's `actual_success` and caps trust.
+fn apply_registered_verifier(manifest: &mut Manifest, bits: &mut ExtendedBits) {
+    let report = {
+        let ctx = verify::VerifyCtx {
+            goal_id: &manifest.goal_id,
+            evidence: &manifest.evidence,
+            deliverables: &manifest.deliverables,
+        };
+        match verify::run_registered(&ctx) {
+            Some(r) => r,
+            None => return,
+        }
+    };
+    if !report.passed {
+        bits.e = 1.0;
+        bits.t = bits.t.min(verify::UNVERIFIED_TRUST_CAP);
+        manifest.bits = bits.clone().into();
+    }
+    if let Some(obj) = manifest.evidence.as_object_mut() {
+        if !report.passed {
+            obj.insert("actual_success".to_string(), json!(false));
+        }
+        obj.insert("checks".to_string(), json!(report));
+    }
+}
+
+async fn run_goal(
+    goal_id: &str,
+    inputs: serde_json::Value,
+    policy: &Policy,
+    context_stale: bool,
+) -> anyhow::Result<(Manifest, ExtendedBits, Option<Meta2Proposal>)> {
+    let kernel = unsafe { KERNEL.get_or_insert_with(KernelLoop::new) };
+    let mut bits = ExtendedBits::init();
+    // Freshness filter: set Δ when any context item is expired or has drifted (see assess_context)
+    if context_stale {
+        bits.d = 1.0;
+    }
+
+    // Set uncertainty based on goal difficulty
+    bits.u = prior_uncertainty(goal_id);
 
     // Ask-Act gate (inherent)
     if !kernel.ask_act_gate(&bits) {
+        telemetry::emit(
+            "kernel",
+            "gate_trip",
+            None,
+            json!({"gate": "ask_act", "goal_id": goal_id, "a": bits.a, "p": bits.p, "d": bits.d}),
+        );
         return Err(anyhow::anyhow!(
-            "Ask-Act gate failed: A={}, P={}, Δ={}",
+            "Ask-Act gate failed: A={}, P={}, Δ={}{}",
             bits.a,
             bits.p,
-            bits.d
+            bits.d,
+            if context_stale { " (stale context items; see /context/staleness)" } else { "" }
         ));
     }
 
-    // Evidence gate (inherent)
-    let needs_verification = !kernel.evidence_gate(&bits);
+    // Evidence gate (inherent); A/B experiment arms may override τ for this run.
+    let tau_override = tau_override(&inputs);
+    let tau = tau_override.unwrap_or(kernel.l2_params.confidence_gate_tau);
+    let needs_verification = !kernel.evidence_gate(&bits, tau_override);
     if needs_verification {
-        tracing::info!(
-            "Evidence gate triggered: U={:.2} >= τ={:.2}",
-            bits.u,
-            kernel.l2_params.confidence_gate_tau
+        tracing::info!("Evidence gate triggered: U={:.2} >= τ={:.2}", bits.u, tau);
+        telemetry::emit(
+            "kernel",
+            "gate_trip",
+            None,
+            json!({"gate": "evidence", "goal_id": goal_id, "u": bits.u, "tau": tau}),
         );
-        // In real system: run dry-run first
+        // Enforced below for the builtin command path (a verification sub-run), and by
+        // `enforce_evidence_gate` for every other goal.
+    }
+
+    // Handle goals declared in config/goals.d/*.yaml (checked first: exact ids only)
+    if let Some(spec) = goals::declared::find(goal_id) {
+        let out = goals::declared::run(spec, &inputs, policy).await?;
+        bits.u = 0.2;
+        bits.e = if out.success { 0.0 } else { 1.0 };
+        bits.t = if out.success { 0.95 } else { 0.4 };
+        let deliverables = out
+            .artifacts
+            .iter()
+            .filter(|a| a.exists)
+            .map(|a| a.path.clone())
+            .collect();
+        let mut evidence = serde_json::to_value(&out).unwrap_or_default();
+        if let Some(obj) = evidence.as_object_mut() {
+            obj.insert("actual_success".to_string(), json!(out.success));
+            obj.insert("meta2_triggered".to_string(), json!(bits.m > 0.0));
+        }
+        let manifest = Manifest {
+            run_id: format!("r-{}", uuid::Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables,
+            evidence,
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // Handle goals served by WASM plugins (plugins/*.wasm)
+    #[cfg(feature = "wasm")]
+    if let Some(plugin) = plugins::find(goal_id) {
+        let out = plugins::run(plugin, &inputs, policy).await?;
+        let ok = out.exit_code == 0;
+        bits.u = 0.2;
+        bits.e = if ok { 0.0 } else { 1.0 };
+        bits.t = if ok { 0.9 } else { 0.4 };
+        let mut evidence = serde_json::Value::Object(out.evidence.clone());
+        if let Some(obj) = evidence.as_object_mut() {
+            obj.insert(
+                "plugin".to_string(),
+                json!({
+                    "name": out.plugin,
+                    "exit_code": out.exit_code,
+                    "actions": out.actions,
+                    "fuel_used": out.fuel_used,
+                }),
+            );
+            obj.insert("actual_success".to_string(), json!(ok));
+        }
+        let manifest = Manifest {
+            run_id: format!("r-{}", uuid::Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables: out.deliverables,
+            evidence,
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
     }
 
     // Handle align.sota: apply alignment boost, echo message
@@ -133,8 +613,7 @@ pub async fn run(
             .get("message")
             .and_then(|v| v.as_str())
             .unwrap_or("align.sota");
-        let action =
-            executor::Action::Cli(format!("echo {}", shell_escape::escape(message.into())));
+        let action = executor::Action::Cli(platform::shell().echo(message));
         let res = executor::execute(action, policy).await?;
         let manifest = Manifest {
             run_id: format!("r-{}", uuid::Uuid::new_v4()),
@@ -146,11 +625,372 @@ pub async fn run(
                 "meta2_triggered": false
             }),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
         bits.t = (bits.t + 0.1).min(1.5);
         return Ok((manifest, bits, None));
     }
 
+    // Handle research.index: incremental rebuild of research/index.jsonl (+ index.meta.json)
+    if goal_id.contains("research.index") {
+        use one_engine::research;
+        let started = std::time::Instant::now();
+        let root = PathBuf::from(inputs.get("root").and_then(|v| v.as_str()).unwrap_or("."));
+        let out = PathBuf::from(
+            inputs
+                .get("out")
+                .and_then(|v| v.as_str())
+                .unwrap_or("research/index.jsonl"),
+        );
+        let globs = |key: &str| {
+            inputs.get(key).and_then(|v| v.as_array()).map(|arr| {
+                arr.iter()
+                    .filter_map(|g| g.as_str().map(|s| s.to_string()))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let opts = research::IndexOptions {
+            include: globs("include").unwrap_or_default(),
+            exclude: globs("exclude"),
+        };
+        let full = inputs.get("full").and_then(|v| v.as_bool()).unwrap_or(false);
+        let prev = if full { Vec::new() } else { research::read_index(&out) };
+        let (items, stats) = research::update_index(&root, &prev, &opts)?;
+        research::write_index(&out, &items)?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let updated_at = harness::now().to_rfc3339();
+        let meta = json!({
+            "updated_at": updated_at,
+            "root": root.display().to_string(),
+            "count": items.len(),
+            "stats": stats,
+            "duration_ms": duration_ms,
+            "include": opts.include,
+            "exclude": opts.exclude
+        });
+        let meta_path = out.with_file_name("index.meta.json");
+        atomic::write(&meta_path, serde_json::to_string_pretty(&meta).unwrap_or_default())
+            .with_context(|| format!("failed to write {}", meta_path.display()))?;
+
+        bits.u = 0.1;
+        bits.e = 0.0;
+        bits.t = 0.95;
+        let manifest = Manifest {
+            run_id: format!("r-{}", Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables: vec![out.display().to_string(), meta_path.display().to_string()],
+            evidence: json!({
+                "index_path": out.display().to_string(),
+                "updated_at": updated_at,
+                "count": items.len(),
+                "stats": stats,
+                "incremental": !full && !prev.is_empty(),
+                "duration_ms": duration_ms,
+                "actual_success": true,
+                "expected_success": true,
+                "meta2_triggered": false
+            }),
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // Handle research.fetch: allowlisted URL → research/sources/web/<slug>.md + index entry
+    if goal_id.contains("research.fetch") {
+        let fetch_policy = load_research_fetch_policy();
+        let (deliverables, evidence) =
+            match goals::research_fetch::fetch(&inputs, &fetch_policy, Path::new(".")).await {
+                Ok(m) => {
+                    bits.u = 0.2;
+                    bits.e = 0.0;
+                    bits.t = 0.9;
+                    (
+                        vec![m.path.clone()],
+                        json!({
+                            "source": m,
+                            "indexed": true,
+                            "actual_success": true,
+                            "expected_success": true,
+                            "meta2_triggered": false
+                        }),
+                    )
+                }
+                Err(e) => {
+                    bits.u = 0.4;
+                    bits.e = 1.0;
+                    bits.t = 0.3;
+                    (
+                        vec![],
+                        json!({
+                            "url": inputs.get("url"),
+                            "error": e.to_string(),
+                            "actual_success": false,
+                            "expected_success": true,
+                            "meta2_triggered": false
+                        }),
+                    )
+                }
+            };
+        let manifest = Manifest {
+            run_id: format!("r-{}", Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables,
+            evidence,
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // staleness.check: probe features end-to-end → docs/staleness_matrix.json + .html
+    if goal_id.contains("staleness.check") {
+        let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+        let report = goals::staleness::check(&inputs, &root).await?;
+        let total = report.entries.len().max(1) as f32;
+        bits.u = 0.1;
+        bits.e = report.failed as f32 / total;
+        bits.t = if report.failed == 0 { 0.95 } else { 0.5 };
+        let features: Vec<serde_json::Value> = report
+            .entries
+            .iter()
+            .map(|e| {
+                json!({
+                    "feature": e.feature,
+                    "status": e.status,
+                    "latency_ms": e.latency_ms,
+                    "run_id": e.run_id,
+                    "bits": e.bits,
+                })
+            })
+            .collect();
+        let manifest = Manifest {
+            run_id: format!("r-{}", Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables: vec![
+                report.json_path.display().to_string(),
+                report.html_path.display().to_string(),
+            ],
+            evidence: json!({
+                "base_url": report.base_url,
+                "features": features,
+                "passed": report.passed,
+                "failed": report.failed,
+                "matrix_json_url": urls::url_for("/docs/staleness_matrix.json"),
+                "matrix_html_url": urls::url_for("/docs/staleness_matrix.html"),
+                "actual_success": report.failed == 0,
+                "expected_success": true,
+                "meta2_triggered": false
+            }),
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // reports.daily: last-24h receipts digest (md/html/json), optionally sent via notify webhook
+    if goal_id.contains("reports.daily") {
+        let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+        let external_run_id = inputs
+            .get("__run_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("daily-{}", harness::now().format("%Y%m%d")));
+        let window = inputs.get("window").and_then(|v| v.as_str()).unwrap_or("24h");
+        let notify = inputs.get("notify").and_then(|v| v.as_bool()).unwrap_or(false);
+        let res = goals::daily_report::generate(&external_run_id, window, notify, &root).await?;
+        let d = &res.digest;
+        let notify_error = res.notified.as_ref().and_then(|r| r.as_ref().err().cloned());
+        bits.u = 0.1;
+        bits.e = if notify_error.is_some() { 0.5 } else { 0.0 };
+        bits.t = if notify_error.is_some() { 0.6 } else { 0.95 };
+        let base = format!("/runs/reports/daily/{}", external_run_id);
+        let manifest = Manifest {
+            run_id: format!("r-{}", Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables: vec![
+                res.out_dir.join("digest.html").display().to_string(),
+                res.out_dir.join("digest.md").display().to_string(),
+                res.out_dir.join("digest.json").display().to_string(),
+            ],
+            evidence: json!({
+                "window": d.window,
+                "total_runs": d.total_runs,
+                "success_rate": d.success_rate,
+                "failures": d.failures.len() + d.failures_omitted,
+                "meta2_proposals": d.meta2_proposals.len(),
+                "cost": d.cost,
+                "index_html_url": urls::url_for(&format!("{}/digest.html", base)),
+                "digest_md_url": urls::url_for(&format!("{}/digest.md", base)),
+                "digest_json_url": urls::url_for(&format!("{}/digest.json", base)),
+                "notified": res.notified.as_ref().map(|r| r.is_ok()),
+                "notify_error": notify_error,
+                "actual_success": notify_error.is_none(),
+                "expected_success": true,
+                "meta2_triggered": false
+            }),
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // experiment.sweep: one goal across a parameter grid → child runs + results table
+    if goal_id.contains("experiment.sweep") {
+        let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+        let parent_run_id = inputs
+            .get("__run_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("sweep-{}", harness::now().format("%Y%m%d%H%M%S")));
+        let report = goals::sweep::run(&parent_run_id, &inputs, &root).await?;
+        let total = report.total.max(1) as f32;
+        bits.u = 0.1;
+        bits.e = report.failed_runs as f32 / total;
+        bits.t = if report.failed_runs == 0 { 0.9 } else { 0.5 };
+        let base = format!("/runs/experiments/{}", parent_run_id);
+        let children: Vec<serde_json::Value> = report
+            .children
+            .iter()
+            .map(|c| json!({"run_id": c.run_id, "params": c.params, "status": c.status, "receipt_url": c.receipt_url}))
+            .collect();
+        let manifest = Manifest {
+            run_id: format!("r-{}", Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables: vec![
+                report.out_dir.join("index.html").display().to_string(),
+                report.out_dir.join("results.json").display().to_string(),
+                report.out_dir.join("results.csv").display().to_string(),
+            ],
+            evidence: json!({
+                "goal": report.goal_id,
+                "grid": report.grid,
+                "total": report.total,
+                "succeeded": report.succeeded,
+                "failed_runs": report.failed_runs,
+                "by_param": report.by_param,
+                "children": children,
+                "index_html_url": urls::url_for(&format!("{}/index.html", base)),
+                "results_json_url": urls::url_for(&format!("{}/results.json", base)),
+                "results_csv_url": urls::url_for(&format!("{}/results.csv", base)),
+                "actual_success": report.failed_runs == 0,
+                "expected_success": true,
+                "meta2_triggered": false
+            }),
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // codex.import: normalize raw Codex archives/rollouts into runs/utir/codex_events.jsonl
+    if goal_id.contains("codex.import") {
+        let full = inputs.get("full").and_then(|v| v.as_bool()).unwrap_or(false);
+        let (deliverables, evidence) = match crate::integrations::codex::import(full).await {
+            Ok(report) => {
+                bits.u = 0.1;
+                bits.e = 0.0;
+                bits.t = 0.9;
+                (
+                    vec![
+                        crate::integrations::codex::events_path().display().to_string(),
+                        crate::integrations::codex::sessions_path().display().to_string(),
+                        crate::integrations::codex_index::index_path().display().to_string(),
+                    ],
+                    json!({
+                        "report": report,
+                        "index": crate::integrations::codex_index::status(),
+                        "full": full,
+                        "actual_success": true,
+                        "expected_success": true,
+                        "meta2_triggered": false
+                    }),
+                )
+            }
+            Err(e) => {
+                bits.u = 0.4;
+                bits.e = 1.0;
+                bits.t = 0.3;
+                (
+                    vec![],
+                    json!({
+                        "error": e.to_string(),
+                        "actual_success": false,
+                        "expected_success": true,
+                        "meta2_triggered": false
+                    }),
+                )
+            }
+        };
+        let manifest = Manifest {
+            run_id: format!("r-{}", Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables,
+            evidence,
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // Handle research.manifest: pin sha256/mtime/bytes of files for research.read
+    if goal_id.contains("research.manifest") {
+        let paths: Vec<String> = match inputs.get("paths").and_then(|v| v.as_array()) {
+            Some(arr) => arr
+                .iter()
+                .filter_map(|p| p.as_str().map(|s| s.to_string()))
+                .collect(),
+            None => inputs
+                .get("path")
+                .and_then(|v| v.as_str())
+                .map(|s| vec![s.to_string()])
+                .unwrap_or_default(),
+        };
+        let id = inputs
+            .get("name")
+            .or_else(|| inputs.get("__run_id"))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("m-{}", Uuid::new_v4()));
+        if !goals::research_manifest::is_safe_segment(&id) {
+            anyhow::bail!("invalid manifest name {:?}", id);
+        }
+        let overwrite = inputs.get("overwrite").and_then(|v| v.as_bool()).unwrap_or(false);
+        let root = under_meta3_root(".");
+        let (pinned, out) = {
+            let (id, paths) = (id.clone(), paths.clone());
+            atomic::spawn_blocking(move || -> anyhow::Result<_> {
+                let pinned = goals::research_manifest::build(&root, &id, &paths)?;
+                let out = goals::research_manifest::write(&root, &pinned, overwrite)?;
+                Ok((pinned, out))
+            })
+            .await??
+        };
+        let bytes: u64 = pinned.files.iter().map(|f| f.bytes).sum();
+
+        bits.u = 0.1;
+        bits.e = 0.0;
+        bits.t = 0.95;
+        let manifest = Manifest {
+            run_id: format!("r-{}", Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables: vec![out.display().to_string()],
+            evidence: json!({
+                "manifest_id": id,
+                "manifest_path": out.display().to_string(),
+                "paths": paths,
+                "files": pinned.files.len(),
+                "bytes": bytes,
+                "actual_success": true,
+                "expected_success": true,
+                "meta2_triggered": false
+            }),
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
     // Handle research.read: read a file and return snippet + stats
     if goal_id.contains("research.read") {
         let path = inputs
@@ -159,8 +999,9 @@ pub async fn run(
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("path or context_path is required"))?;
 
-        let content = std::fs::read_to_string(path)
-            .map_err(|e| anyhow::anyhow!("read failed for {}: {}", path, e))?;
+        let resolved = under_meta3_root(path);
+        let content = std::fs::read_to_string(&resolved)
+            .map_err(|e| anyhow::anyhow!("read failed for {}: {}", resolved.display(), e))?;
 
         let lines = content.lines().count();
         let bytes = content.as_bytes().len();
@@ -171,17 +1012,55 @@ pub async fn run(
         };
 
         let sha = format!("{:x}", Sha256::digest(content.as_bytes()));
-        let meta = fs::metadata(path).ok();
+        let meta = fs::metadata(&resolved).ok();
         let mtime = meta
             .and_then(|m| m.modified().ok())
             .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
             .map(|d| d.as_secs() as i64)
             .unwrap_or(0);
 
+        // `context_manifest` is `{sha256, mtime}` for this file, or a research.manifest
+        // (inline or by reference) that should pin it.
         let expected = inputs.get("context_manifest");
         let mut stale = false;
         let mut stale_reason = None;
-        if let Some(exp) = expected {
+        let mut manifest_check = None;
+        let meta3_root = under_meta3_root(".");
+        let from_manifest = match expected.cloned() {
+            Some(exp) => tokio::task::spawn_blocking(move || {
+                goals::research_manifest::from_input(&meta3_root, &exp).map(|m| {
+                    let m = m?;
+                    let checks = goals::research_manifest::check(&meta3_root, &m);
+                    Ok::<_, anyhow::Error>((m, checks))
+                })
+            })
+            .await?,
+            None => None,
+        };
+        let pinned = match from_manifest {
+            Some(m) => {
+                let (m, checks) = m?;
+                let count = |status: &str| checks.iter().filter(|c| c.status == status).count();
+                manifest_check = Some(json!({
+                    "id": m.id,
+                    "files": checks.len(),
+                    "fresh": count("fresh"),
+                    "changed": count("changed"),
+                    "missing": count("missing"),
+                    "drift": checks.iter().filter(|c| c.status != "fresh").collect::<Vec<_>>()
+                }));
+                match m.file(path) {
+                    Some(f) => Some(json!({"sha256": f.sha256, "mtime": f.mtime})),
+                    None => {
+                        stale = true;
+                        stale_reason = Some("not_in_manifest");
+                        None
+                    }
+                }
+            }
+            None => expected.cloned(),
+        };
+        if let Some(exp) = &pinned {
             if let Some(exp_sha) = exp.get("sha256").and_then(|v| v.as_str()) {
                 if exp_sha != sha {
                     stale = true;
@@ -214,11 +1093,13 @@ pub async fn run(
                 "mtime": mtime,
                 "stale": stale,
                 "stale_reason": stale_reason,
+                "context_manifest": manifest_check,
                 "actual_success": !stale,
                 "expected_success": true,
                 "meta2_triggered": false
             }),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
@@ -231,7 +1112,8 @@ pub async fn run(
             .and_then(|v| v.as_str())
             .unwrap_or_else(|| "wiki-unknown");
 
-        let res = wiki::generate(external_run_id).await?;
+        let base_url = inputs.get("base_url").and_then(|v| v.as_str());
+        let res = wiki::generate(external_run_id, base_url).await?;
         bits.u = 0.2;
         bits.e = 0.0;
         bits.t = 0.95;
@@ -239,34 +1121,54 @@ pub async fn run(
         let manifest = Manifest {
             run_id: format!("r-{}", uuid::Uuid::new_v4()),
             goal_id: goal_id.to_string(),
-            deliverables: vec![
-                res.out_dir.join("index.html").display().to_string(),
-                res.out_dir.join("static.html").display().to_string(),
-                res.out_dir.join("index.md").display().to_string(),
-                res.out_dir.join("files.txt").display().to_string(),
-                res.out_dir.join("topfiles.txt").display().to_string(),
-                res.out_dir.join("folder_summary.md").display().to_string(),
-            ],
-            evidence: serde_json::json!({
-                "actual_success": true,
-                "expected_success": true,
-                "wiki_dir": res.out_dir.display().to_string(),
-                "index_html_url": format!("/runs/wiki/{}/index.html", external_run_id),
-                "static_html_url": format!("/runs/wiki/{}/static.html", external_run_id),
-                "files_count": res.files_count,
-                "topfiles_count": res.topfiles_count,
-                "readme_copied": res.readme_copied,
-                "stdout": format!("[wiki.generate] wrote {} ({} files, {} topfiles)", res.out_dir.display(), res.files_count, res.topfiles_count),
-                "meta2_triggered": bits.m > 0.0
-            }),
+            // Files only: the verifier reads each one.
+            deliverables: res
+                .pages
+                .iter()
+                .map(|p| p.as_str())
+                .chain([
+                    "index.md",
+                    "files.txt",
+                    "topfiles.txt",
+                    "folder_summary.md",
+                    "search_index.json",
+                    "sitemap.xml",
+                ])
+                .map(|p| res.out_dir.join(p).display().to_string())
+                .collect(),
+            evidence: WikiEvidence {
+                outcome: Outcome::ok(bits.m > 0.0),
+                wiki_dir: res.out_dir.display().to_string(),
+                index_html_url: urls::url_for(&format!(
+                    "/runs/wiki/{}/index.html",
+                    external_run_id
+                )),
+                static_html_url: urls::url_for(&format!(
+                    "/runs/wiki/{}/static.html",
+                    external_run_id
+                )),
+                files_count: res.files_count,
+                topfiles_count: res.topfiles_count,
+                readme_copied: res.readme_copied,
+                modules_count: res.modules_count,
+                search_docs: res.search_docs,
+                search_html_url: urls::url_for(&format!(
+                    "/runs/wiki/{}/search.html",
+                    external_run_id
+                )),
+                stdout: format!("[wiki.generate] wrote {} ({} files, {} topfiles, {} modules)", res.out_dir.display(), res.files_count, res.topfiles_count, res.modules_count),
+                extra: Default::default(),
+            }
+            .to_value(),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
     }
 
     // Handle graphs.thread: render a coherent graph of a chat thread (events -> receipts)
-    if goal_id.contains("graphs.thread") || goal_id.contains("graph.thread") {
+    if goal_id.contains("graphs.thread") {
         let external_run_id = inputs
             .get("__run_id")
             .and_then(|v| v.as_str())
@@ -340,32 +1242,38 @@ pub async fn run(
         let manifest = Manifest {
             run_id: format!("r-{}", uuid::Uuid::new_v4()),
             goal_id: goal_id.to_string(),
-            deliverables: vec![
-                res.out_dir.join("index.html").display().to_string(),
-                res.out_dir.join("graph.dot").display().to_string(),
-                res.out_dir.join("events.json").display().to_string(),
-            ],
-            evidence: serde_json::json!({
-                "actual_success": true,
-                "expected_success": true,
-                "user_id": user_id,
-                "thread": res.thread,
-                "nodes": res.nodes,
-                "edges": res.edges,
-                "index_html_url": format!("/runs/graphs/{}/index.html", external_run_id),
-                "dot_url": format!("/runs/graphs/{}/graph.dot", external_run_id),
-                "events_url": format!("/runs/graphs/{}/events.json", external_run_id),
-                "stdout": format!("[graphs.thread] wrote {} ({} nodes)", res.out_dir.display(), res.nodes),
-                "meta2_triggered": bits.m > 0.0
-            }),
+            deliverables: {
+                let mut d = vec![
+                    res.out_dir.join("index.html").display().to_string(),
+                    res.out_dir.join("graph.dot").display().to_string(),
+                    res.out_dir.join("events.json").display().to_string(),
+                    res.out_dir.join("graph.json").display().to_string(),
+                ];
+                if res.layout.is_some() {
+                    d.push(res.out_dir.join("graph.svg").display().to_string());
+                }
+                d
+            },
+            evidence: GraphEvidence::new(
+                external_run_id,
+                res.nodes,
+                res.edges,
+                res.layout,
+                format!("[graphs.thread] wrote {} ({} nodes)", res.out_dir.display(), res.nodes),
+                bits.m > 0.0,
+            )
+                .with("user_id", user_id)
+                .with("thread", &res.thread)
+            .to_value(),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
     }
 
     // Handle graphs.receipts: graph recent receipts into a coherent timeline
-    if goal_id.contains("graphs.receipts") || goal_id.contains("graph.receipts") {
+    if goal_id.contains("graphs.receipts") {
         let external_run_id = inputs
             .get("__run_id")
             .and_then(|v| v.as_str())
@@ -375,7 +1283,18 @@ pub async fn run(
             .and_then(|v| v.as_u64())
             .unwrap_or(200) as usize;
 
-        let res = graphs::receipts_graph(external_run_id, limit)?;
+        let budget = inputs
+            .get("budget_ms")
+            .and_then(|v| v.as_u64())
+            .map(|ms| ms.clamp(1, graphs::RECEIPTS_SCAN_BUDGET_MAX_MS))
+            .map(std::time::Duration::from_millis)
+            .unwrap_or(graphs::RECEIPTS_SCAN_BUDGET);
+
+        // The scan fans out on its own threads; keep it off the async workers (but metered).
+        let id = external_run_id.to_string();
+        let res = atomic::spawn_blocking(move || graphs::receipts_graph(&id, limit, budget))
+            .await
+            .map_err(|e| anyhow::anyhow!("graphs.receipts task failed: {}", e))??;
         bits.u = 0.2;
         bits.e = 0.0;
         bits.t = 0.95;
@@ -383,30 +1302,39 @@ pub async fn run(
         let manifest = Manifest {
             run_id: format!("r-{}", uuid::Uuid::new_v4()),
             goal_id: goal_id.to_string(),
-            deliverables: vec![
-                res.out_dir.join("index.html").display().to_string(),
-                res.out_dir.join("graph.dot").display().to_string(),
-                res.out_dir.join("events.json").display().to_string(),
-            ],
-            evidence: serde_json::json!({
-                "actual_success": true,
-                "expected_success": true,
-                "nodes": res.nodes,
-                "edges": res.edges,
-                "index_html_url": format!("/runs/graphs/{}/index.html", external_run_id),
-                "dot_url": format!("/runs/graphs/{}/graph.dot", external_run_id),
-                "events_url": format!("/runs/graphs/{}/events.json", external_run_id),
-                "stdout": format!("[graphs.receipts] wrote {} ({} nodes)", res.out_dir.display(), res.nodes),
-                "meta2_triggered": bits.m > 0.0
-            }),
+            deliverables: {
+                let mut d = vec![
+                    res.out_dir.join("index.html").display().to_string(),
+                    res.out_dir.join("graph.dot").display().to_string(),
+                    res.out_dir.join("events.json").display().to_string(),
+                    res.out_dir.join("graph.json").display().to_string(),
+                ];
+                if res.layout.is_some() {
+                    d.push(res.out_dir.join("graph.svg").display().to_string());
+                }
+                d
+            },
+            evidence: GraphEvidence::new(
+                external_run_id,
+                res.nodes,
+                res.edges,
+                res.layout,
+                format!("[graphs.receipts] wrote {} ({} nodes)", res.out_dir.display(), res.nodes),
+                bits.m > 0.0,
+            )
+            .with("truncated", res.stats.truncated)
+            .with("scanned", res.stats.scanned)
+            .with("cached", res.stats.cached)
+            .to_value(),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
     }
 
     // Handle graphs.api: show "API jumping" (endpoint hops + mutations) from runs/api_trace.jsonl
-    if goal_id.contains("graphs.api") || goal_id.contains("graph.api") {
+    if goal_id.contains("graphs.api") {
         let external_run_id = inputs
             .get("__run_id")
             .and_then(|v| v.as_str())
@@ -454,23 +1382,108 @@ pub async fn run(
         let manifest = Manifest {
             run_id: format!("r-{}", uuid::Uuid::new_v4()),
             goal_id: goal_id.to_string(),
-            deliverables: vec![
-                res.out_dir.join("index.html").display().to_string(),
-                res.out_dir.join("graph.dot").display().to_string(),
-                res.out_dir.join("events.json").display().to_string(),
-            ],
-            evidence: serde_json::json!({
-                "actual_success": true,
-                "expected_success": true,
-                "nodes": res.nodes,
-                "edges": res.edges,
-                "index_html_url": format!("/runs/graphs/{}/index.html", external_run_id),
-                "dot_url": format!("/runs/graphs/{}/graph.dot", external_run_id),
-                "events_url": format!("/runs/graphs/{}/events.json", external_run_id),
-                "stdout": format!("[graphs.api] wrote {} ({} nodes)", res.out_dir.display(), res.nodes),
-                "meta2_triggered": bits.m > 0.0
-            }),
+            deliverables: {
+                let mut d = vec![
+                    res.out_dir.join("index.html").display().to_string(),
+                    res.out_dir.join("graph.dot").display().to_string(),
+                    res.out_dir.join("events.json").display().to_string(),
+                    res.out_dir.join("graph.json").display().to_string(),
+                ];
+                if res.layout.is_some() {
+                    d.push(res.out_dir.join("graph.svg").display().to_string());
+                }
+                d
+            },
+            evidence: GraphEvidence::new(
+                external_run_id,
+                res.nodes,
+                res.edges,
+                res.layout,
+                format!("[graphs.api] wrote {} ({} nodes)", res.out_dir.display(), res.nodes),
+                bits.m > 0.0,
+            )
+            .to_value(),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+
+        return Ok((manifest, bits, None));
+    }
+
+    // Handle graphs.system: merge threads, receipts and api_trace into one clustered system map
+    if goal_id.contains("graphs.system") {
+        let external_run_id = inputs
+            .get("__run_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("graph-unknown");
+        let since_hours = inputs
+            .get("since_hours")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(24 * 7);
+        let max_nodes = inputs
+            .get("max_nodes")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(250) as usize;
+        let include_api = inputs
+            .get("include_api")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let user_id = inputs
+            .get("user_id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+
+        let res = graphs::system_graph(
+            external_run_id,
+            graphs::SystemGraphOpts {
+                since_hours,
+                max_nodes,
+                user_id,
+                include_api,
+            },
+        )?;
+        bits.u = 0.2;
+        bits.e = 0.0;
+        bits.t = 0.95;
+
+        let manifest = Manifest {
+            run_id: format!("r-{}", uuid::Uuid::new_v4()),
+            goal_id: goal_id.to_string(),
+            deliverables: {
+                let mut d = vec![
+                    res.out_dir.join("index.html").display().to_string(),
+                    res.out_dir.join("graph.dot").display().to_string(),
+                    res.out_dir.join("events.json").display().to_string(),
+                    res.out_dir.join("graph.json").display().to_string(),
+                ];
+                if res.layout.is_some() {
+                    d.push(res.out_dir.join("graph.svg").display().to_string());
+                }
+                d
+            },
+            evidence: GraphEvidence::new(
+                external_run_id,
+                res.nodes,
+                res.edges,
+                res.layout,
+                format!(
+                    "[graphs.system] wrote {} ({} nodes, {} users, {} threads, {} runs)",
+                    res.out_dir.display(),
+                    res.nodes,
+                    res.users,
+                    res.threads,
+                    res.runs
+                ),
+                bits.m > 0.0,
+            )
+                .with("since_hours", since_hours)
+                .with("users", res.users)
+                .with("threads", res.threads)
+                .with("runs", res.runs)
+                .with("collapsed", res.collapsed)
+            .to_value(),
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
@@ -485,9 +1498,20 @@ pub async fn run(
             .map(|s| s.to_string())
             .or_else(|| std::env::var("META3_PATH").ok())
             .unwrap_or_else(|| "meta3-monorepo".to_string());
-        let build_cmd = inputs
-            .get("build_cmd")
+        let repo = under_meta3_root(&repo).display().to_string();
+        let remote_name = inputs
+            .get("remote")
             .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .or_else(load_meta3_build_remote);
+        let target = remote_name.as_deref().map(remote::lookup).transpose()?;
+        let requested_cmd = inputs.get("build_cmd").and_then(|v| v.as_str());
+        // Remote hosts always run POSIX shell.
+        if requested_cmd.is_none() && target.is_none() {
+            // The configured and built-in commands are POSIX shell; a caller can pass its own.
+            platform::require_posix("meta3.build without a build_cmd input")?;
+        }
+        let build_cmd = requested_cmd
             .map(|s| s.to_string())
             .or_else(|| std::env::var("META3_BUILD_DEFAULT_CMD").ok())
             .or_else(load_meta3_build_cmd_from_policies)
@@ -496,21 +1520,42 @@ pub async fn run(
                 "echo \"[meta3.build] start\"; npx turbo run build --filter '!@meta3/cli' --filter '!@meta3/kernel' --no-cache; status=$?; echo \"[meta3.build] done\"; exit $status".to_string()
             });
 
-        let run_id = format!("r-{}", Uuid::new_v4());
+        // Named after the external run id when there is one, so `/runs/{run_id}/log/tail` finds it.
+        let run_id = inputs
+            .get("__run_id")
+            .and_then(|v| v.as_str())
+            .filter(|id| !id.is_empty() && !id.contains(['/', '\\']) && !id.contains(".."))
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("r-{}", Uuid::new_v4()));
         let meta_root =
             PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
         let log_dir = meta_root.join("runs/meta3-build");
-        fs::create_dir_all(&log_dir)
+        storage::create_dir_all(&log_dir)
             .with_context(|| format!("failed to create log directory {}", log_dir.display()))?;
         let log_path = log_dir.join(format!("{}.log", run_id));
 
-        let cmd = format!(
-            "cd {} && {} 2>&1",
-            shell_escape::escape(repo.clone().into()),
-            build_cmd
-        );
-        let action = executor::Action::Cli(cmd);
-        let res = executor::execute(action, policy).await?;
+        // Output streams into `<run_id>.log.partial` until the final log is written.
+        let partial_log = live_log::partial_path(&log_path);
+        let ran = live_log::scope(&partial_log, async {
+            match (&remote_name, &target) {
+                (Some(name), Some(t)) => {
+                    let mut remote_run = remote::sync(name, t, &repo, policy).await?;
+                    let res = remote::execute(t, &format!("{} 2>&1", build_cmd), policy).await?;
+                    remote::fetch_artifacts(t, &log_dir.join(&run_id), policy, &mut remote_run).await;
+                    Ok((res, Some(remote_run)))
+                }
+                _ => {
+                    let cmd = platform::shell().in_dir(&repo, &format!("{} 2>&1", build_cmd));
+                    let action = executor::Action::Cli(cmd);
+                    anyhow::Ok((executor::execute(action, policy).await?, None))
+                }
+            }
+        })
+        .await;
+        if ran.is_err() {
+            let _ = fs::remove_file(&partial_log);
+        }
+        let (res, remote_run) = ran?;
 
         let combined = if res.stderr.is_empty() {
             res.stdout.clone()
@@ -518,18 +1563,28 @@ pub async fn run(
             format!("STDOUT:\\n{}\\nSTDERR:\\n{}", res.stdout, res.stderr)
         };
 
-        fs::write(&log_path, combined.as_bytes())
-            .with_context(|| format!("failed to write log {}", log_path.display()))?;
+        let stored_log_path = write_log_maybe_gz(&log_path, combined.as_bytes());
+        let _ = fs::remove_file(&partial_log);
+        let stored_log_path = stored_log_path?;
+
+        // Structured report next to the log; success comes from the parsed result.
+        let build_report = build_log::parse(&combined, res.ok);
+        let report_path = log_dir.join(format!("{}.report.json", run_id));
+        atomic::write(
+            &report_path,
+            serde_json::to_string_pretty(&build_report).unwrap_or_default(),
+        )
+        .with_context(|| format!("failed to write report {}", report_path.display()))?;
 
         if res.drift {
             bits.d = 1.0;
         }
-        if !res.ok {
+        if !build_report.success {
             bits.e = 1.0;
             bits.u = (bits.u + 0.2).min(1.0);
         }
 
-        let passed = verify::check_minimal(&res);
+        let passed = build_report.success;
         let legacy_bits: types::Bits = bits.clone().into();
         bits.t = policy::trust_from(passed, &legacy_bits);
         if passed != true {
@@ -546,18 +1601,305 @@ pub async fn run(
         let manifest = Manifest {
             run_id: run_id.clone(),
             goal_id: goal_id.to_string(),
-            deliverables: vec![log_path.display().to_string()],
+            deliverables: [
+                stored_log_path.display().to_string(),
+                report_path.display().to_string(),
+            ]
+            .into_iter()
+            .chain(remote_run.iter().flat_map(|r| r.artifacts.clone()))
+            .collect(),
+            evidence: BuildEvidence {
+                outcome: Outcome {
+                    actual_success: passed,
+                    expected_success: true,
+                    meta2_triggered: bits.m > 0.0,
+                },
+                stdout: res.stdout,
+                repo_path: repo,
+                build_cmd,
+                log_path: stored_log_path.display().to_string(),
+                log_url: urls::url_for(&format!("/runs/meta3-build/{}.log", run_id)),
+                report_url: urls::url_for(&format!("/runs/meta3-build/{}.report.json", run_id)),
+                build_report,
+                exit_ok: res.ok,
+                run_id: run_id.clone(),
+                extra: remote_run
+                    .map(|r| [("remote".to_string(), json!(r))].into_iter().collect())
+                    .unwrap_or_default(),
+            }
+            .to_value(),
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+
+        return Ok((manifest, bits, None));
+    }
+
+    // Handle cargo.build / cargo.test / cargo.clippy: structured cargo JSON diagnostics
+    if goal_id.contains("cargo.") {
+        if let Some(report) =
+            goals::cargo::handle(goal_id, &inputs, policy, &load_cargo_policy()).await?
+        {
+            let run_id = format!("r-{}", Uuid::new_v4());
+            let meta_root =
+                PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+            let out_dir = meta_root.join("runs/cargo");
+            storage::create_dir_all(&out_dir)
+                .with_context(|| format!("failed to create directory {}", out_dir.display()))?;
+            let report_path = out_dir.join(format!("{}.json", run_id));
+            atomic::write(
+                &report_path,
+                serde_json::to_string_pretty(&report).unwrap_or_default(),
+            )
+            .with_context(|| format!("failed to write report {}", report_path.display()))?;
+
+            let passed = report.success;
+            bits.u = 0.2;
+            if !passed {
+                bits.e = 1.0;
+            }
+            let legacy_bits: types::Bits = bits.clone().into();
+            bits.t = policy::trust_from(passed, &legacy_bits);
+
+            let manifest = Manifest {
+                run_id: run_id.clone(),
+                goal_id: goal_id.to_string(),
+                deliverables: vec![report_path.display().to_string()],
+                evidence: serde_json::json!({
+                    "cmd": report.cmd,
+                    "report_url": urls::url_for(&format!("/runs/cargo/{}.json", run_id)),
+                    "cargo_report": report,
+                    "exit_ok": report.exit_ok,
+                    "expected_success": true,
+                    "actual_success": passed,
+                    "run_id": run_id,
+                    "meta2_triggered": bits.m > 0.0
+                }),
+                bits: bits.clone().into(),
+                schema_version: MANIFEST_SCHEMA_VERSION,
+            };
+            return Ok((manifest, bits, None));
+        }
+    }
+
+    // Handle ruliad.hypergraph: Wolfram-model hypergraph rewriting with growth curves
+    if goal_id.contains("ruliad.hypergraph") {
+        let rules: Vec<hypergraph::Rule> = match inputs.get("rules") {
+            Some(serde_json::Value::Array(arr)) if arr.first().map(|r| r.is_object()).unwrap_or(false) => arr
+                .iter()
+                .map(hypergraph::Rule::parse)
+                .collect::<anyhow::Result<_>>()?,
+            Some(v) if !v.is_null() => vec![hypergraph::Rule::parse(v)?],
+            _ => vec![hypergraph::default_rule()],
+        };
+        let init = match inputs.get("init") {
+            Some(v) if !v.is_null() => hypergraph::parse_init(v)?,
+            _ => hypergraph::default_init(),
+        };
+        let steps = inputs
+            .get("steps")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(8)
+            .clamp(1, 40) as usize;
+        let max_relations = inputs
+            .get("max_relations")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(20_000)
+            .clamp(1, 200_000) as usize;
+
+        let run_id = inputs
+            .get("run_id")
+            .and_then(|v| v.as_str())
+            .filter(|r| ruliad::is_valid_run_id(r))
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| format!("r-{}", Uuid::new_v4()));
+        let out_dir = Path::new("runs").join("ruliad_kernel").join(&run_id);
+        storage::create_dir_all(&out_dir)
+            .with_context(|| format!("failed to create {}", out_dir.display()))?;
+
+        let evo = hypergraph::evolve(init, &rules, steps, max_relations);
+        let rule_names: Vec<String> = rules.iter().map(|r| r.describe()).collect();
+        let final_state = evo.states.last().cloned().unwrap_or_default();
+
+        // states.jsonl / edges.jsonl: same shape as ruliad.kernel (one state per step).
+        let mut states_lines = Vec::new();
+        for (sid, st) in evo.states.iter().enumerate() {
+            let small = st.len() <= 2000;
+            states_lines.push(
+                json!({
+                    "id": sid,
+                    "string": if small { hypergraph::render(st) } else { String::new() },
+                    "depth": sid,
+                    "relations": st.len(),
+                    "vertices": evo.vertex_growth[sid],
+                    "truncated": !small,
+                })
+                .to_string(),
+            );
+        }
+        atomic::write(out_dir.join("states.jsonl"), states_lines.join("\n"))?;
+        let mut edge_lines = Vec::new();
+        for step in 1..evo.states.len() {
+            for (ri, name) in rule_names.iter().enumerate() {
+                let n = evo.events.iter().filter(|e| e.step == step && e.rule == ri).count();
+                if n > 0 {
+                    edge_lines.push(json!({ "src": step - 1, "dst": step, "depth": step, "rule": name, "events": n }).to_string());
+                }
+            }
+        }
+        atomic::write(out_dir.join("edges.jsonl"), edge_lines.join("\n"))?;
+
+        // multiway.dot: the (deterministic) evolution chain; causal.dot: update events.
+        let mut dot = String::from("digraph multiway {\nrankdir=LR;\n");
+        for (sid, st) in evo.states.iter().enumerate() {
+            dot.push_str(&format!(
+                "  n{} [label=\"step {}\\n{} rel · {} vtx\"];\n",
+                sid,
+                sid,
+                st.len(),
+                evo.vertex_growth[sid]
+            ));
+        }
+        for step in 1..evo.states.len() {
+            dot.push_str(&format!("  n{} -> n{} [label=\"{} ev\"];\n", step - 1, step, evo.events_per_step[step]));
+        }
+        dot.push_str("}\n");
+        atomic::write(out_dir.join("multiway.dot"), &dot)?;
+
+        let mut causal = String::from("digraph causal {\nrankdir=TB;\n");
+        for (i, ev) in evo.events.iter().enumerate() {
+            causal.push_str(&format!("  e{} [label=\"{}:{}\"];\n", i, ev.step, ev.rule));
+        }
+        let mut causal_edges = 0usize;
+        for (i, ev) in evo.events.iter().enumerate() {
+            for c in &ev.causes {
+                causal.push_str(&format!("  e{} -> e{};\n", c, i));
+                causal_edges += 1;
+            }
+        }
+        causal.push_str("}\n");
+        atomic::write(out_dir.join("causal.dot"), &causal)?;
+
+        // hypergraph.dot: final state; k-ary relations drawn as a chain of k-1 edges.
+        let mut hg = String::from("digraph hypergraph {\nrankdir=LR;\nnode [shape=circle, label=\"\", width=0.15];\n");
+        for (ri, rel) in final_state.iter().enumerate() {
+            match rel.as_slice() {
+                [v] => hg.push_str(&format!("  v{} [shape=doublecircle];\n", v)),
+                _ => {
+                    for w in rel.windows(2) {
+                        hg.push_str(&format!("  v{} -> v{} [label=\"{}\"];\n", w[0], w[1], if rel.len() > 2 { format!("r{ri}") } else { String::new() }));
+                    }
+                }
+            }
+        }
+        hg.push_str("}\n");
+        atomic::write(out_dir.join("hypergraph.dot"), &hg)?;
+
+        // SVG rendering is skipped for large graphs (DOT is still written).
+        let multiway_svg = graph_layout::write_svg(&out_dir, "multiway", &dot).await;
+        let causal_svg = if evo.events.len() <= 1500 { graph_layout::write_svg(&out_dir, "causal", &causal).await } else { None };
+        let hypergraph_svg = if final_state.len() <= 1500 { graph_layout::write_svg(&out_dir, "hypergraph", &hg).await } else { None };
+
+        // graph.json: the final hypergraph (vertices + relation edges).
+        let mut doc = graph_doc::GraphDoc::new("ruliad", format!("Hypergraph after {} steps", evo.states.len() - 1));
+        doc.meta = json!({ "rules": rule_names, "steps": steps, "vertex_growth": evo.vertex_growth, "edge_growth": evo.edge_growth });
+        let mut vertices: Vec<u64> = final_state.iter().flatten().copied().collect();
+        vertices.sort_unstable();
+        vertices.dedup();
+        for v in &vertices {
+            let degree = final_state.iter().filter(|r| r.contains(v)).count();
+            let mut node = graph_doc::GraphNode::new(format!("v{v}"), v.to_string(), "vertex");
+            node.data = json!({ "degree": degree });
+            doc.nodes.push(node);
+        }
+        for (ri, rel) in final_state.iter().enumerate() {
+            for w in rel.windows(2) {
+                let mut edge = graph_doc::GraphEdge::new(format!("v{}", w[0]), format!("v{}", w[1]), &format!("arity {}", rel.len()));
+                edge.label = (rel.len() > 2).then(|| format!("r{ri}"));
+                doc.edges.push(edge);
+            }
+        }
+        graph_doc::write(&out_dir, &doc)?;
+        let graph_json_path = format!("/runs/ruliad_kernel/{}/graph.json", run_id);
+
+        let mut growth_rows = String::new();
+        for (sid, st) in evo.states.iter().enumerate() {
+            growth_rows.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                sid,
+                evo.vertex_growth[sid],
+                st.len(),
+                evo.events_per_step[sid]
+            ));
+        }
+        let section = |title: &str, stem: &str, svg: Option<&graph_layout::RenderedSvg>| match svg {
+            Some(_) => format!("<h2>{}</h2>{}", title, graph_layout::embed_html(svg, &format!("{}.svg", stem))),
+            None => format!("<h2>{}</h2><p class='muted'>Not rendered; see <a href='{}.dot'>{}.dot</a>.</p>", title, stem, stem),
+        };
+        let html = format!(
+            "<!doctype html><html><head><meta charset='utf-8'><style>body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px}}.muted{{color:#57606a}}td,th{{padding:4px 10px;border-bottom:1px solid #f1f3f5;text-align:right}}</style></head><body><h1>Hypergraph evolution</h1><p>Rules <code>{}</code> · {} steps ({}) · <a href='states.jsonl'>states.jsonl</a> · <a href='hypergraph.dot'>hypergraph.dot</a> · <a href='causal.dot'>causal.dot</a></p>{}<table><tr><th>step</th><th>vertices</th><th>relations</th><th>events</th></tr>{}</table>{}{}{}</body></html>",
+            rule_names.join(" ; ").replace('<', "&lt;"),
+            evo.states.len() - 1,
+            evo.stop_reason,
+            graph_doc::viewer_link_html(&graph_json_path),
+            growth_rows,
+            section("Final hypergraph", "hypergraph", hypergraph_svg.as_ref()),
+            section("Causal graph", "causal", causal_svg.as_ref()),
+            section("Evolution", "multiway", multiway_svg.as_ref())
+        );
+        atomic::write(out_dir.join("index.html"), html)?;
+
+        bits.u = 0.1;
+        bits.e = 0.0;
+        bits.t = 0.95;
+
+        let manifest = Manifest {
+            run_id: run_id.clone(),
+            goal_id: goal_id.to_string(),
+            deliverables: [
+                "states.jsonl",
+                "edges.jsonl",
+                "multiway.dot",
+                "causal.dot",
+                "hypergraph.dot",
+                "graph.json",
+                "index.html",
+            ]
+            .into_iter()
+            .map(|f| out_dir.join(f).display().to_string())
+            .chain(
+                [
+                    ("multiway.svg", &multiway_svg),
+                    ("causal.svg", &causal_svg),
+                    ("hypergraph.svg", &hypergraph_svg),
+                ]
+                .into_iter()
+                .filter(|(_, r)| r.is_some())
+                .map(|(f, _)| out_dir.join(f).display().to_string()),
+            )
+            .collect(),
             evidence: serde_json::json!({
-                "stdout": res.stdout,
-                "repo_path": repo,
-                "build_cmd": build_cmd,
-                "log_path": log_path.display().to_string(),
+                "layout": multiway_svg.as_ref().map(|r| r.engine),
+                "graph_json_url": urls::url_for(&graph_json_path),
+                "viewer_url": graph_doc::viewer_url(&graph_json_path),
+                "rules": rule_names,
+                "steps_requested": steps,
+                "steps": evo.states.len() - 1,
+                "max_relations": max_relations,
+                "stop_reason": evo.stop_reason,
+                "vertex_growth": evo.vertex_growth,
+                "edge_growth": evo.edge_growth,
+                "events_per_step": evo.events_per_step,
+                "events": evo.events.len(),
+                "causal_edges": causal_edges,
+                "vertices": vertices.len(),
+                "relations": final_state.len(),
                 "expected_success": true,
-                "actual_success": passed,
-                "run_id": run_id,
-                "meta2_triggered": bits.m > 0.0
+                "actual_success": true,
+                "meta2_triggered": false
             }),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
@@ -565,8 +1907,6 @@ pub async fn run(
 
     // Handle ruliad.kernel: generate a multiway slice + causal graph and artifacts
     if goal_id.contains("ruliad") {
-        use std::collections::{HashMap, HashSet};
-
         let seed = inputs
             .get("seed")
             .and_then(|v| v.as_str())
@@ -594,76 +1934,74 @@ pub async fn run(
             })
             .unwrap_or_else(|| vec![("01".into(), "10".into()), ("10".into(), "011".into())]);
 
-        // BFS over string rewrites to build multiway graph
-        let mut states: HashMap<usize, HashSet<String>> = HashMap::new();
-        states.insert(0, [seed.clone()].into_iter().collect());
-        let mut id_for: HashMap<String, usize> = HashMap::new();
-        id_for.insert(seed.clone(), 0);
-        let mut next_id = 1usize;
-        let mut edges: Vec<(usize, usize, usize, String)> = Vec::new();
-
-        for d in 0..depth {
-            let layer = states.get(&d).cloned().unwrap_or_default();
-            for s in layer {
-                for (pat, rep) in &rules {
-                    let mut idx = 0usize;
-                    while let Some(pos) = s[idx..].find(pat) {
-                        let global = idx + pos;
-                        let ns = format!("{}{}{}", &s[..global], rep, &s[global + pat.len()..]);
-                        let dst_id = *id_for.entry(ns.clone()).or_insert_with(|| {
-                            let id = next_id;
-                            next_id += 1;
-                            id
-                        });
-                        edges.push((*id_for.get(&s).unwrap(), dst_id, d + 1, pat.clone()));
-                        states.entry(d + 1).or_default().insert(ns);
-                        idx = global + 1;
-                    }
-                }
-            }
-        }
+        // Exploration controls: budgets, dedup strategy, and resume via the same run_id.
+        let max_states = inputs
+            .get("max_states")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(5_000)
+            .clamp(1, 200_000) as usize;
+        let max_edges = inputs
+            .get("max_edges")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(20_000)
+            .clamp(1, 1_000_000) as usize;
+        let dedup_raw = inputs.get("dedup").and_then(|v| v.as_str()).unwrap_or("exact");
+        let dedup = ruliad::Dedup::parse(dedup_raw)
+            .ok_or_else(|| anyhow::anyhow!("unknown dedup {:?} (exact|rotation|reflection|dihedral)", dedup_raw))?;
 
         // Prepare output dir under runs/ruliad_kernel/<run_id>
-        let run_id = format!("r-{}", Uuid::new_v4());
+        let run_id = inputs
+            .get("run_id")
+            .and_then(|v| v.as_str())
+            .filter(|r| ruliad::is_valid_run_id(r))
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| format!("r-{}", Uuid::new_v4()));
         let out_dir = Path::new("runs").join("ruliad_kernel").join(&run_id);
-        fs::create_dir_all(&out_dir)
+        storage::create_dir_all(&out_dir)
             .with_context(|| format!("failed to create {}", out_dir.display()))?;
 
+        // BFS over string rewrites to build multiway graph
+        let resumed = ruliad::Exploration::resume(&out_dir, &seed, &rules, dedup)?;
+        let resumed_from = resumed.as_ref().map(|ex| ex.frontier_depth().unwrap_or(ex.max_depth()));
+        let mut ex = resumed.unwrap_or_else(|| ruliad::Exploration::new(&seed, rules.clone(), dedup));
+        let stop = ex.explore(
+            depth,
+            ruliad::Limits {
+                max_states,
+                max_edges,
+            },
+        );
+        ex.save(&out_dir)?;
+        let inv = &ex.states;
+        let edges = &ex.edges;
+
         // states.jsonl
         let mut states_lines = Vec::new();
-        let mut inv: Vec<String> = vec!["".into(); id_for.len()];
-        for (k, v) in id_for.iter() {
-            inv[*v] = k.clone();
-        }
         for (sid, s) in inv.iter().enumerate() {
-            let d = states
-                .iter()
-                .find_map(|(depth, set)| if set.contains(s) { Some(*depth) } else { None })
-                .unwrap_or(0);
-            states_lines.push(json!({ "id": sid, "string": s, "depth": d }).to_string());
+            states_lines.push(json!({ "id": sid, "string": s, "depth": ex.depth_of[sid] }).to_string());
         }
-        fs::write(out_dir.join("states.jsonl"), states_lines.join("\n"))?;
+        atomic::write(out_dir.join("states.jsonl"), states_lines.join("\n"))?;
 
         // edges.jsonl
         let mut edge_lines = Vec::new();
-        for (src, dst, d, pat) in &edges {
+        for (src, dst, d, pat) in edges {
             edge_lines.push(json!({ "src": src, "dst": dst, "depth": d, "rule": pat }).to_string());
         }
-        fs::write(out_dir.join("edges.jsonl"), edge_lines.join("\n"))?;
+        atomic::write(out_dir.join("edges.jsonl"), edge_lines.join("\n"))?;
 
         // multiway DOT
         let mut dot = String::from("digraph multiway {\nrankdir=LR;\n");
         for (sid, s) in inv.iter().enumerate() {
             dot.push_str(&format!("  n{} [label=\"{}\"];\n", sid, s));
         }
-        for (src, dst, d, pat) in &edges {
+        for (src, dst, d, pat) in edges.iter() {
             dot.push_str(&format!(
                 "  n{} -> n{} [label=\"{}@{}\"];\n",
                 src, dst, pat, d
             ));
         }
         dot.push_str("}\n");
-        fs::write(out_dir.join("multiway.dot"), dot)?;
+        atomic::write(out_dir.join("multiway.dot"), &dot)?;
 
         // causal DOT (approx: same edges without depth labels)
         let mut causal = String::from("digraph causal {\nrankdir=LR;\n");
@@ -674,14 +2012,48 @@ pub async fn run(
             causal.push_str(&format!("  n{} -> n{} [label=\"{}\"];\n", src, dst, pat));
         }
         causal.push_str("}\n");
-        fs::write(out_dir.join("causal.dot"), causal)?;
+        atomic::write(out_dir.join("causal.dot"), &causal)?;
+        let multiway_svg = graph_layout::write_svg(&out_dir, "multiway", &dot).await;
+        let causal_svg = graph_layout::write_svg(&out_dir, "causal", &causal).await;
 
-        // Minimal HTML viewer
+        // graph.json (shared viewer schema): states grouped by depth, rewrites labelled by rule.
+        let mut doc = graph_doc::GraphDoc::new("ruliad", format!("Ruliad slice from {seed}"));
+        doc.meta = json!({ "seed": seed, "depth": depth, "rules": rules });
+        for (sid, s) in inv.iter().enumerate() {
+            let d = ex.depth_of[sid];
+            let mut node = graph_doc::GraphNode::new(format!("n{sid}"), s.clone(), if sid == 0 { "seed" } else { "state" });
+            node.group = Some(format!("depth {d}"));
+            node.data = json!({ "string": s, "depth": d, "len": s.len() });
+            doc.nodes.push(node);
+        }
+        for (src, dst, d, pat) in edges.iter() {
+            let mut edge = graph_doc::GraphEdge::new(format!("n{src}"), format!("n{dst}"), "rewrite");
+            edge.label = Some(format!("{pat}@{d}"));
+            doc.edges.push(edge);
+        }
+        graph_doc::write(&out_dir, &doc)?;
+        let graph_json_path = format!("/runs/ruliad_kernel/{}/graph.json", run_id);
+
+        // HTML viewer: rendered SVGs when a layout engine is available, else the raw DOT.
+        let section = |title: &str, stem: &str, svg: Option<&graph_layout::RenderedSvg>| match svg {
+            Some(_) => format!("<h2>{}</h2>{}", title, graph_layout::embed_html(svg, &format!("{}.svg", stem))),
+            None => format!(
+                "<h2>{}</h2><pre id='{}'></pre><script>fetch('{}.dot').then(r=>r.text()).then(t=>document.getElementById('{}').textContent=t);</script>",
+                title, stem, stem, stem
+            ),
+        };
         let html = format!(
-            "<!doctype html><html><body><h1>Ruliad slice</h1><p>Rule {:?}, depth {}</p><pre id='multiway'></pre><pre id='causal'></pre><script>fetch('multiway.dot').then(r=>r.text()).then(t=>multiway.textContent=t);fetch('causal.dot').then(r=>r.text()).then(t=>causal.textContent=t);</script></body></html>",
-            rules, depth
+            "<!doctype html><html><head><meta charset='utf-8'><style>body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px}}.muted{{color:#57606a}}</style></head><body><h1>Ruliad slice</h1><p>Rule {:?}, depth {} · {} states, {} edges ({}) · <a href='multiway.dot'>multiway.dot</a> · <a href='causal.dot'>causal.dot</a></p>{}{}{}</body></html>",
+            rules,
+            depth,
+            inv.len(),
+            edges.len(),
+            stop.as_str(),
+            graph_doc::viewer_link_html(&graph_json_path),
+            section("Multiway", "multiway", multiway_svg.as_ref()),
+            section("Causal", "causal", causal_svg.as_ref())
         );
-        fs::write(out_dir.join("index.html"), html)?;
+        atomic::write(out_dir.join("index.html"), html)?;
 
         bits.u = 0.1;
         bits.e = 0.0;
@@ -696,25 +2068,49 @@ pub async fn run(
                 out_dir.join("multiway.dot").display().to_string(),
                 out_dir.join("causal.dot").display().to_string(),
                 out_dir.join("index.html").display().to_string(),
-            ],
+                out_dir.join("graph.json").display().to_string(),
+                out_dir.join(ruliad::FRONTIER_FILE).display().to_string(),
+            ]
+            .into_iter()
+            .chain(
+                [("multiway.svg", &multiway_svg), ("causal.svg", &causal_svg)]
+                    .into_iter()
+                    .filter(|(_, r)| r.is_some())
+                    .map(|(f, _)| out_dir.join(f).display().to_string()),
+            )
+            .collect(),
             evidence: serde_json::json!({
+                "layout": multiway_svg.as_ref().map(|r| r.engine),
+                "graph_json_url": urls::url_for(&graph_json_path),
+                "viewer_url": graph_doc::viewer_url(&graph_json_path),
                 "rule": rules,
                 "seed": seed,
                 "depth": depth,
                 "states": inv.len(),
                 "edges": edges.len(),
+                "max_states": max_states,
+                "max_edges": max_edges,
+                "dedup": dedup.as_str(),
+                "resumed": resumed_from.is_some(),
+                "resumed_from_depth": resumed_from,
+                "frontier": ex.pending.len(),
+                "frontier_depth": ex.frontier_depth(),
+                "stop_reason": stop.as_str(),
+                "truncated": stop.truncated(),
+                "frontier_url": urls::url_for(&format!("/runs/ruliad_kernel/{}/{}", run_id, ruliad::FRONTIER_FILE)),
                 "expected_success": true,
                 "actual_success": true,
                 "meta2_triggered": false
             }),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
     }
 
     // Handle threads.report: render a human-friendly report of a chat thread (events -> receipts)
-    if goal_id.contains("threads.report") || goal_id.contains("thread.report") {
+    if goal_id.contains("threads.report") {
         let external_run_id = inputs
             .get("__run_id")
             .and_then(|v| v.as_str())
@@ -737,6 +2133,30 @@ pub async fn run(
             .get("content_chars")
             .and_then(|v| v.as_u64())
             .unwrap_or(220) as usize;
+        let opt_str = |k: &str| {
+            inputs
+                .get(k)
+                .and_then(|v| v.as_str())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        // Accept either ["a","b"] or "a,b".
+        let str_list = |k: &str| -> Vec<String> {
+            match inputs.get(k) {
+                Some(serde_json::Value::Array(a)) => a
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+                Some(serde_json::Value::String(s)) => s
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
 
         let res = thread_report::generate(
             external_run_id,
@@ -745,8 +2165,21 @@ pub async fn run(
                 thread: thread.clone(),
                 max_events,
                 content_chars,
+                since: opt_str("since"),
+                goal_filter: opt_str("goal_filter"),
+                roles: str_list("roles"),
+                sections: str_list("sections"),
+                pdf: inputs.get("pdf").and_then(|v| v.as_bool()).unwrap_or(false),
             },
         )?;
+        let mut deliverables = vec![
+            res.out_dir.join("index.html").display().to_string(),
+            res.out_dir.join("report.json").display().to_string(),
+            res.out_dir.join("report.md").display().to_string(),
+        ];
+        if res.pdf {
+            deliverables.push(res.out_dir.join("report.pdf").display().to_string());
+        }
 
         bits.u = 0.2;
         bits.e = 0.0;
@@ -755,23 +2188,24 @@ pub async fn run(
         let manifest = Manifest {
             run_id: format!("r-{}", uuid::Uuid::new_v4()),
             goal_id: goal_id.to_string(),
-            deliverables: vec![
-                res.out_dir.join("index.html").display().to_string(),
-                res.out_dir.join("report.json").display().to_string(),
-            ],
+            deliverables,
             evidence: serde_json::json!({
                 "expected_success": true,
                 "actual_success": true,
                 "user_id": user_id,
                 "thread": res.thread,
+                "sections": res.sections,
                 "nodes": res.nodes,
                 "threads_dir": res.out_dir.display().to_string(),
-                "index_html_url": format!("/runs/threads/{}/index.html", external_run_id),
-                "report_json_url": format!("/runs/threads/{}/report.json", external_run_id),
+                "index_html_url": urls::url_for(&format!("/runs/threads/{}/index.html", external_run_id)),
+                "report_json_url": urls::url_for(&format!("/runs/threads/{}/report.json", external_run_id)),
+                "report_md_url": urls::url_for(&format!("/runs/threads/{}/report.md", external_run_id)),
+                "report_pdf_url": res.pdf.then(|| urls::url_for(&format!("/runs/threads/{}/report.pdf", external_run_id))),
                 "stdout": format!("[threads.report] wrote {} ({} nodes)", res.out_dir.display(), res.nodes),
                 "meta2_triggered": bits.m > 0.0
             }),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
@@ -803,6 +2237,7 @@ pub async fn run(
                 "meta2_triggered": bits.m > 0.0
             }),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
         return Ok((manifest, bits, None));
     }
@@ -818,13 +2253,45 @@ pub async fn run(
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("content required"))?;
 
-        let path = Path::new(path_str);
+        let path = match write_scope::check(&platform::native_path(path_str)) {
+            Ok(p) => p,
+            Err(v) => {
+                tracing::warn!("write scope blocked file.write: {}", v.reason);
+                telemetry::emit(
+                    "kernel",
+                    "gate_trip",
+                    None,
+                    json!({"gate": "write_scope", "goal_id": goal_id, "path": v.path, "reason": v.reason}),
+                );
+                bits.u = 0.1;
+                bits.e = 1.0;
+                bits.t = 0.2;
+                let manifest = Manifest {
+                    run_id: format!("r-{}", uuid::Uuid::new_v4()),
+                    goal_id: goal_id.to_string(),
+                    deliverables: vec![],
+                    evidence: serde_json::json!({
+                        "path": v.path,
+                        "policy_blocked": true,
+                        "blocked_by": "write_scope",
+                        "reason": v.reason,
+                        "allowed_roots": v.allowed_roots,
+                        "actual_success": false,
+                        "meta2_triggered": bits.m > 0.0
+                    }),
+                    bits: bits.clone().into(),
+                    schema_version: MANIFEST_SCHEMA_VERSION,
+                };
+                return Ok((manifest, bits, None));
+            }
+        };
+        let path = path.as_path();
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)
+            storage::create_dir_all(parent)
                 .with_context(|| format!("failed to create dir {}", parent.display()))?;
         }
 
-        fs::write(path, content).with_context(|| format!("failed to write {}", path.display()))?;
+        atomic::write(path, content).with_context(|| format!("failed to write {}", path.display()))?;
 
         bits.u = 0.1;
         bits.e = 0.0;
@@ -841,13 +2308,73 @@ pub async fn run(
                 "meta2_triggered": bits.m > 0.0
             }),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
+        };
+        return Ok((manifest, bits, None));
+    }
+
+    // Handle file.patch: unified diff / edit list, applied atomically with a reverse patch
+    if goal_id.contains("file.patch") {
+        let run_id = format!("r-{}", Uuid::new_v4());
+        let (deliverables, evidence) = match goals::patch::apply(&inputs, policy.tiny_diff_loc) {
+            Ok(outcome) => {
+                let meta_root =
+                    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+                let patch_dir = meta_root.join("runs/patches");
+                storage::create_dir_all(&patch_dir)
+                    .with_context(|| format!("failed to create dir {}", patch_dir.display()))?;
+                let reverse_path = patch_dir.join(format!("{}.reverse.patch", run_id));
+                atomic::write(&reverse_path, &outcome.reverse_patch)
+                    .with_context(|| format!("failed to write {}", reverse_path.display()))?;
+
+                bits.u = 0.1;
+                bits.e = 0.0;
+                bits.t = 1.0;
+                (
+                    vec![reverse_path.display().to_string()],
+                    serde_json::json!({
+                        "mode": outcome.mode,
+                        "files": outcome.files,
+                        "lines_added": outcome.added,
+                        "lines_removed": outcome.removed,
+                        "tiny_diff_loc": policy.tiny_diff_loc,
+                        "reverse_patch_url": urls::url_for(&format!("/runs/patches/{}.reverse.patch", run_id)),
+                        "actual_success": true,
+                        "meta2_triggered": bits.m > 0.0
+                    }),
+                )
+            }
+            Err(e) => {
+                // Rejected patches leave every target untouched.
+                bits.u = 0.3;
+                bits.e = 1.0;
+                bits.t = 0.3;
+                (
+                    vec![],
+                    serde_json::json!({
+                        "error": e.to_string(),
+                        "tiny_diff_loc": policy.tiny_diff_loc,
+                        "actual_success": false,
+                        "meta2_triggered": bits.m > 0.0
+                    }),
+                )
+            }
+        };
+
+        let manifest = Manifest {
+            run_id,
+            goal_id: goal_id.to_string(),
+            deliverables,
+            evidence,
+            bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
         return Ok((manifest, bits, None));
     }
 
     // Handle meta.omni through LM persona
     if goal_id.contains("meta.omni") {
-        let lm_result = goals::meta_omni::handle(&inputs).await?;
+        let lm_result = goals::meta_omni::handle(&inputs, policy).await?;
 
         // Extract reply from LM response
         let reply = lm_result
@@ -873,7 +2400,7 @@ pub async fn run(
             bits.e = e as f32;
         }
 
-        let action = executor::Action::Cli(format!("echo {}", shell_escape::escape(reply.into())));
+        let action = executor::Action::Cli(platform::shell().echo(reply));
         let res = executor::execute(action, policy).await?;
 
         let manifest = Manifest {
@@ -886,6 +2413,7 @@ pub async fn run(
                 .cloned()
                 .unwrap_or(lm_result.clone()),
             bits: bits.clone().into(),
+            schema_version: MANIFEST_SCHEMA_VERSION,
         };
 
         return Ok((manifest, bits, None));
@@ -903,25 +2431,18 @@ pub async fn run(
     };
 
     // Simulate different outcomes based on goal type
+    let sh = platform::shell();
     let (action, expected_success) = match goal_id {
-        id if id.contains("impossible") => (executor::Action::Cli("false".to_string()), false),
+        id if id.contains("impossible") => (executor::Action::Cli(sh.fail()), false),
         id if id.contains("hard") => (
-            executor::Action::Cli(format!(
-                "sleep 0.1 && echo {}",
-                shell_escape::escape(message.clone().into())
-            )),
-            true,
-        ),
-        _ => (
-            executor::Action::Cli(format!(
-                "echo {}",
-                shell_escape::escape(message.clone().into())
-            )),
+            executor::Action::Cli(sh.and(&sh.sleep_ms(100), &sh.echo(&message))),
             true,
         ),
+        _ => (executor::Action::Cli(sh.echo(&message)), true),
     };
 
-    let res = executor::execute(action, policy).await?;
+    let run_id = format!("r-{}", Uuid::new_v4());
+    let res = executor::execute(action.clone(), policy).await?;
 
     if res.drift {
         bits.d = 1.0;
@@ -941,8 +2462,27 @@ pub async fn run(
         bits.t *= 0.7; // Lower trust when predictions are wrong
     }
 
+    // Evidence gate enforcement: U >= τ requires a verification sub-run before high trust.
+    let verification = if needs_verification {
+        let v = verify::verification_subrun(&run_id, action, policy, &res).await;
+        if !v.passed {
+            bits.t = bits.t.min(verify::UNVERIFIED_TRUST_CAP);
+        }
+        telemetry::emit(
+            "kernel",
+            "verification",
+            Some(&run_id),
+            json!({"goal_id": goal_id, "sub_run_id": v.run_id, "passed": v.passed}),
+        );
+        Some(v)
+    } else {
+        None
+    };
+
     // L3 meta² check: should we propose policy changes?
-    let current_evidence_coverage = bits.t; // Simplified: use trust as proxy
+    // Simplified: use trust as proxy, discounted by how users rate this goal's replies.
+    let satisfaction = crate::integrations::feedback::goal_satisfaction(goal_id).await;
+    let current_evidence_coverage = bits.t * satisfaction.map_or(1.0, |s| 0.5 + 0.5 * s);
     unsafe {
         KPI_HISTORY.push(current_evidence_coverage);
     }
@@ -953,6 +2493,14 @@ pub async fn run(
     } else {
         None
     };
+    if let Some(p) = meta2_proposal.as_ref() {
+        telemetry::emit(
+            "kernel",
+            "meta2_proposal",
+            None,
+            json!({"goal_id": goal_id, "proposal": p}),
+        );
+    }
 
     // STRUCTURAL VALIDATION: Enforce kernel contract
     if let Err(e) = kernel.validate_bits_complete(&bits) {
@@ -963,6 +2511,12 @@ pub async fn run(
     if goal_id.contains("action") || goal_id.contains("execute") {
         if let Err(e) = kernel.enforce_ask_act_gate(&bits) {
             tracing::warn!("Ask-Act gate blocked action: {}", e);
+            telemetry::emit(
+                "kernel",
+                "gate_trip",
+                None,
+                json!({"gate": "ask_act_enforced", "goal_id": goal_id, "error": e}),
+            );
             // Return clarification request instead of proceeding
             let clarification = format!("Ask-Act gate: {}. Need P=1, A=1, Δ=0", e);
             let blocked_manifest = Manifest {
@@ -981,6 +2535,7 @@ pub async fn run(
                     t: bits.t,
                     m: bits.m,
                 },
+                schema_version: MANIFEST_SCHEMA_VERSION,
             };
             return Ok((blocked_manifest, bits, None));
         }
@@ -995,7 +2550,7 @@ pub async fn run(
     }
 
     let manifest = Manifest {
-        run_id: format!("r-{}", Uuid::new_v4()),
+        run_id,
         goal_id: goal_id.to_string(),
         deliverables: vec![],
         evidence: serde_json::json!({
@@ -1003,9 +2558,12 @@ pub async fn run(
             "expected_success": expected_success,
             "actual_success": passed,
             "l2_params": kernel.l2_params,
+            "evidence_gate": {"triggered": needs_verification, "tau": tau},
+            "verification": verification,
             "meta2_triggered": bits.m > 0.0
         }),
         bits: bits.clone().into(), // Convert to legacy Bits for compatibility
+        schema_version: MANIFEST_SCHEMA_VERSION,
     };
 
     Ok((manifest, bits, meta2_proposal))
@@ -0,0 +1,254 @@
+//! Evidence size cap: oversized evidence fields move to files next to the receipt.
+//!
+//! After a run, every evidence string longer than the inline cap keeps only its head inline,
+//! followed by a `[truncated ...]` marker with the artifact URL; the full text is written to
+//! `runs/receipts/<run_id>/evidence/<field>.txt`. Top-level values that are still over the cap
+//! (large arrays or objects) are replaced by a pointer object and written as `.json`. Each
+//! offloaded field is listed under `evidence.offloaded`, keyed by its JSON pointer:
+//!
+//! ```json
+//! "offloaded": {"/stdout": {"path": "runs/receipts/r-1/evidence/stdout.txt", "url": "...", "bytes": 5242880}}
+//! ```
+//!
+//! The cap is `evidence.inline_max_bytes` in the policies file (default 64 KiB), overridden by
+//! `ONE_ENGINE_EVIDENCE_INLINE_MAX`; `0` turns offloading off. Offloaded files are redacted
+//! like the receipt itself. Consumers that need a whole field call [`full_text`].
+
+use once_cell::sync::Lazy;
+use one_engine::redact::{redact, redact_value};
+use one_engine::{atomic, storage};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+
+use super::urls;
+
+const DEFAULT_INLINE_MAX: usize = 64 * 1024;
+
+#[derive(Debug, Default, Deserialize)]
+struct EvidenceSection {
+    #[serde(default)]
+    inline_max_bytes: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesEvidence {
+    #[serde(default)]
+    evidence: Option<EvidenceSection>,
+}
+
+static INLINE_MAX: Lazy<usize> = Lazy::new(|| {
+    if let Some(n) = std::env::var("ONE_ENGINE_EVIDENCE_INLINE_MAX")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        return n;
+    }
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesEvidence>(&raw).ok())
+        .and_then(|p| p.evidence)
+        .and_then(|e| e.inline_max_bytes)
+        .unwrap_or(DEFAULT_INLINE_MAX)
+});
+
+fn meta3_root() -> PathBuf {
+    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()))
+}
+
+/// A JSON pointer as a file name: `/build_report/errors/0` → `build_report.errors.0`.
+fn file_stem(pointer: &str) -> String {
+    let stem: String = pointer
+        .trim_start_matches('/')
+        .chars()
+        .map(|c| match c {
+            '/' => '.',
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' => c,
+            _ => '_',
+        })
+        .collect();
+    if stem.is_empty() {
+        "evidence".to_string()
+    } else {
+        stem
+    }
+}
+
+/// The longest prefix of `s` within `max` bytes that ends on a char boundary.
+fn head(s: &str, max: usize) -> &str {
+    let mut end = max.min(s.len());
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+struct Offloader<'a> {
+    root: &'a Path,
+    run_id: String,
+    max: usize,
+    records: Map<String, Value>,
+}
+
+impl Offloader<'_> {
+    /// Write `content`, redacted, for `pointer`; the record's URL, or `None` if the write failed.
+    fn write(&mut self, pointer: &str, ext: &str, content: &str) -> Option<String> {
+        let content = redact(content);
+        let rel = format!(
+            "runs/receipts/{}/evidence/{}.{}",
+            self.run_id,
+            file_stem(pointer),
+            ext
+        );
+        let path = self.root.join(&rel);
+        let written = path
+            .parent()
+            .map_or(Ok(()), |dir| storage::create_dir_all(dir).map(|_| ()))
+            .and_then(|_| atomic::write(&path, &content));
+        if let Err(e) = written {
+            tracing::warn!(
+                "cannot offload evidence {} of {}: {}",
+                pointer,
+                self.run_id,
+                e
+            );
+            return None;
+        }
+        let url = urls::url_for(&format!("/{}", rel));
+        self.records.insert(
+            pointer.to_string(),
+            json!({"path": rel, "url": url, "bytes": content.len()}),
+        );
+        Some(url)
+    }
+
+    fn strings(&mut self, v: &mut Value, pointer: &str) {
+        match v {
+            Value::String(s) if s.len() > self.max => {
+                if let Some(url) = self.write(pointer, "txt", s) {
+                    let total = s.len();
+                    *s = format!(
+                        "{}\n…[truncated: {} bytes; full text at {}]",
+                        head(s, self.max),
+                        total,
+                        url
+                    );
+                }
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter_mut().enumerate() {
+                    self.strings(item, &format!("{}/{}", pointer, i));
+                }
+            }
+            Value::Object(map) => {
+                for (k, item) in map.iter_mut() {
+                    let key = k.replace('~', "~0").replace('/', "~1");
+                    self.strings(item, &format!("{}/{}", pointer, key));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Apply the inline cap to `evidence` for receipt `run_id`; returns how many fields moved.
+pub fn apply(run_id: &str, evidence: &mut Value) -> usize {
+    apply_with(&meta3_root(), run_id, evidence, *INLINE_MAX)
+}
+
+fn apply_with(root: &Path, run_id: &str, evidence: &mut Value, max: usize) -> usize {
+    if max == 0 || evidence.get("offloaded").is_some() {
+        return 0;
+    }
+    let Some(obj) = evidence.as_object_mut() else {
+        return 0;
+    };
+    let mut off = Offloader {
+        root,
+        run_id: run_id.to_string(),
+        max,
+        records: Map::new(),
+    };
+    for (k, v) in obj.iter_mut() {
+        let pointer = format!("/{}", k.replace('~', "~0").replace('/', "~1"));
+        off.strings(v, &pointer);
+        if v.is_string() {
+            continue;
+        }
+        let raw = serde_json::to_string_pretty(v).unwrap_or_default();
+        if raw.len() > max {
+            // Secret-looking keys are only recognisable before serialisation.
+            let mut clean = v.clone();
+            redact_value(&mut clean);
+            let clean = serde_json::to_string_pretty(&clean).unwrap_or_default();
+            if let Some(url) = off.write(&pointer, "json", &clean) {
+                *v = json!({"truncated": true, "bytes": raw.len(), "url": url});
+            }
+        }
+    }
+    let n = off.records.len();
+    if n > 0 {
+        obj.insert("offloaded".to_string(), Value::Object(off.records));
+    }
+    n
+}
+
+/// The whole of `evidence` at `pointer`: the offloaded file when the field was moved,
+/// otherwise the inline string.
+pub fn full_text(evidence: &Value, pointer: &str) -> Option<String> {
+    if let Some(rel) = evidence
+        .get("offloaded")
+        .and_then(|o| o.get(pointer))
+        .and_then(|r| r.get("path"))
+        .and_then(|p| p.as_str())
+    {
+        if let Ok(s) = storage::read_to_string(&meta3_root().join(rel)) {
+            return Some(s);
+        }
+    }
+    evidence
+        .pointer(pointer)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heads_stay_on_char_boundaries_and_pointers_become_file_names() {
+        assert_eq!(head("héllo", 2), "h");
+        assert_eq!(head("abc", 10), "abc");
+        assert_eq!(file_stem("/build_report/errors/0"), "build_report.errors.0");
+        assert_eq!(file_stem("/a~1b c"), "a_1b_c");
+    }
+
+    #[test]
+    fn small_evidence_is_untouched() {
+        let mut ev = json!({"stdout": "ok", "checks": [1, 2, 3]});
+        let before = ev.clone();
+        assert_eq!(apply_with(Path::new("."), "r-1", &mut ev, 1024), 0);
+        assert_eq!(ev, before);
+        assert_eq!(full_text(&ev, "/stdout").as_deref(), Some("ok"));
+    }
+
+    #[test]
+    fn offloaded_files_are_redacted() {
+        let root = std::env::temp_dir().join(format!("offload-{}", uuid::Uuid::new_v4()));
+        let stdout = format!("{}x-api-key: demo-key-123\n", "a".repeat(64));
+        let mut ev = json!({
+            "stdout": stdout,
+            "request": {"api_token": "t0ps3cret", "user": "alice", "host": "example.org"}
+        });
+        assert_eq!(apply_with(&root, "r-1", &mut ev, 32), 2);
+        let dir = root.join("runs/receipts/r-1/evidence");
+        let txt = std::fs::read_to_string(dir.join("stdout.txt")).unwrap();
+        let obj = std::fs::read_to_string(dir.join("request.json")).unwrap();
+        assert!(txt.contains("x-api-key: [REDACTED]") && !txt.contains("demo-key-123"));
+        assert!(obj.contains("[REDACTED]") && !obj.contains("t0ps3cret"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+//! Gated execution of LM-proposed ops (`write` / `exec`) for the N* loop.
+//!
+//! Each op goes through, in order: shape check (A), write-scope / denylist / risk cap (P),
+//! pre-image drift (Δ), then the kernel's Ask-Act gate, and only then `executor::execute`.
+//! Every op, applied or not, gets a child receipt `runs/receipts/<parent>-op<N>/`.
+
+use super::correlation;
+use super::executor::{self, Action};
+use super::kernel::{ExtendedBits, KernelLoop};
+use super::platform;
+use super::types::Policy;
+use crate::integrations::telemetry;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+
+const WRITE_PREFIXES: &[&str] = &["src/", "ui/", "scripts/", "docs/"];
+
+/// Substrings that always block an `exec` op (extend with `NSTAR_OPS_DENYLIST=a,b`).
+const DEFAULT_DENYLIST: &[&str] = &[
+    "rm -rf /",
+    "sudo ",
+    "mkfs",
+    "dd if=",
+    "shutdown",
+    "reboot",
+    ":(){",
+    "| sh",
+    "| bash",
+    "git push --force",
+    "chmod -r 777 /",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpReceipt {
+    pub op_run_id: String,
+    pub parent_run_id: String,
+    pub index: usize,
+    /// "write" | "exec" | other (rejected)
+    pub op: String,
+    pub target: String,
+    /// "applied" | "dry_run" | "blocked" | "failed"
+    pub status: String,
+    pub reason: Option<String>,
+    pub risk: f32,
+    pub pre_sha256: Option<String>,
+    pub post_sha256: Option<String>,
+    pub bits: ExtendedBits,
+}
+
+impl OpReceipt {
+    pub fn summary(&self) -> String {
+        match &self.reason {
+            Some(r) => format!("{} {} [{}: {}]", self.op, self.target, self.status, r),
+            None => format!("{} {} [{}]", self.op, self.target, self.status),
+        }
+    }
+}
+
+/// Simple Safety: only allow writing to known subdirs, never outside the repo.
+pub fn is_allowed_write(path: &str) -> bool {
+    WRITE_PREFIXES.iter().any(|p| path.starts_with(p))
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn denylist() -> Vec<String> {
+    let mut out: Vec<String> = DEFAULT_DENYLIST.iter().map(|s| s.to_string()).collect();
+    if let Ok(extra) = std::env::var("NSTAR_OPS_DENYLIST") {
+        out.extend(
+            extra
+                .split(',')
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty()),
+        );
+    }
+    out
+}
+
+/// Risk in 0..=1 for comparison against `policy.max_risk`.
+fn exec_risk(cmd: &str) -> f32 {
+    match executor::detect_capability(cmd) {
+        Some("identity") => 0.9,
+        Some("file_write") => 0.6,
+        Some("network") => 0.5,
+        _ => 0.2,
+    }
+}
+
+async fn sha256_file(path: &Path) -> Option<String> {
+    let bytes = tokio::fs::read(path).await.ok()?;
+    let mut h = Sha256::new();
+    h.update(&bytes);
+    Some(format!("{:x}", h.finalize()))
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+async fn write_child_receipt(r: &OpReceipt, op: &Value) {
+    let dir = meta3_root().join("runs/receipts").join(&r.op_run_id);
+    if tokio::fs::create_dir_all(&dir).await.is_err() {
+        return;
+    }
+    let goal_id = format!("nstar.op.{}", r.op);
+    let request = json!({
+        "goal_id": goal_id,
+        "parent_run_id": r.parent_run_id,
+        "index": r.index,
+        "op": op,
+        "correlation_id": correlation::current()
+    });
+    let response = json!({
+        "manifest": {
+            "run_id": r.op_run_id,
+            "goal_id": goal_id,
+            "deliverables": if r.status == "applied" && r.op == "write" { vec![r.target.clone()] } else { vec![] },
+            "evidence": {
+                "status": r.status,
+                "reason": r.reason,
+                "risk": r.risk,
+                "pre_sha256": r.pre_sha256,
+                "post_sha256": r.post_sha256,
+                "parent_run_id": r.parent_run_id,
+                "actual_success": r.status == "applied" || r.status == "dry_run"
+            },
+            "bits": r.bits
+        }
+    });
+    let md = format!(
+        "# Op receipt\n\n- op_run_id: `{}`\n- parent_run_id: `{}`\n- op: `{}` #{}\n- target: `{}`\n- status: **{}**{}\n- risk: {:.2}\n- pre_sha256: `{}`\n- post_sha256: `{}`\n",
+        r.op_run_id,
+        r.parent_run_id,
+        r.op,
+        r.index,
+        r.target,
+        r.status,
+        r.reason.as_deref().map(|x| format!(" ({})", x)).unwrap_or_default(),
+        r.risk,
+        r.pre_sha256.as_deref().unwrap_or("-"),
+        r.post_sha256.as_deref().unwrap_or("-"),
+    );
+    let _ = tokio::fs::write(
+        dir.join("request.json"),
+        serde_json::to_string_pretty(&request).unwrap_or_default(),
+    )
+    .await;
+    let _ = tokio::fs::write(
+        dir.join("response.json"),
+        serde_json::to_string_pretty(&response).unwrap_or_default(),
+    )
+    .await;
+    let _ = tokio::fs::write(dir.join("RECEIPT.md"), md).await;
+
+    let mut link = correlation::Link::new("op", &r.op_run_id);
+    link.parent_run_id = Some(r.parent_run_id.clone());
+    link.goal_id = Some(goal_id);
+    correlation::record(&link).await;
+}
+
+/// Gate inputs for one op: the executor action plus A/P/Δ bits and the first failing reason.
+struct PlannedOp {
+    kind: String,
+    target: String,
+    action: Option<Action>,
+    bits: ExtendedBits,
+    reason: Option<String>,
+    risk: f32,
+    pre_sha256: Option<String>,
+}
+
+async fn plan_op(op: &Value, policy: &Policy, deny: &[String]) -> PlannedOp {
+    let kind = op
+        .get("op")
+        .and_then(|s| s.as_str())
+        .unwrap_or("")
+        .to_string();
+    let mut bits = ExtendedBits::init();
+    let mut reason: Option<String> = None;
+    let mut risk = 0.0f32;
+    let mut pre_sha256 = None;
+
+    let (target, action) = match kind.as_str() {
+        "write" => {
+            let path = platform::slash_path(op.get("path").and_then(|s| s.as_str()).unwrap_or(""));
+            let content = op.get("content").and_then(|s| s.as_str());
+            pre_sha256 = sha256_file(&platform::native_path(&path)).await;
+            risk = if pre_sha256.is_some() { 0.2 } else { 0.1 };
+            if path.is_empty() || content.is_none() {
+                bits.a = 0.0;
+                reason = Some("write needs path and content".to_string());
+            } else if !is_allowed_write(&path) {
+                bits.p = 0.0;
+                reason = Some(format!(
+                    "path outside write scope ({})",
+                    WRITE_PREFIXES.join(", ")
+                ));
+            }
+            // Δ: the LM saw a different version of the file than what's on disk now.
+            if let Some(expected) = op.get("expected_sha256").and_then(|v| v.as_str()) {
+                if pre_sha256.as_deref() != Some(expected) {
+                    bits.d = 1.0;
+                    reason.get_or_insert_with(|| {
+                        "pre-image changed since op was planned".to_string()
+                    });
+                }
+            }
+            let action = Action::WriteFile {
+                path: path.clone(),
+                content: content.unwrap_or("").to_string(),
+            };
+            (path, Some(action))
+        }
+        "exec" => {
+            let cmd = op.get("cmd").and_then(|s| s.as_str()).unwrap_or("");
+            let args: Vec<String> = op
+                .get("args")
+                .and_then(|a| a.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|s| s.as_str())
+                        .map(|s| s.to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let line = platform::shell().argv(cmd, &args);
+            let plain = std::iter::once(cmd.to_string())
+                .chain(args)
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase();
+            risk = exec_risk(&plain);
+            if cmd.is_empty() {
+                bits.a = 0.0;
+                reason = Some("exec needs cmd".to_string());
+            } else if let Some(hit) = deny.iter().find(|d| plain.contains(d.as_str())) {
+                bits.p = 0.0;
+                reason = Some(format!("denylisted: {}", hit));
+            }
+            (line.clone(), Some(Action::Cli(line)))
+        }
+        _ => {
+            bits.a = 0.0;
+            reason = Some(format!("unknown op: {}", kind));
+            (String::new(), None)
+        }
+    };
+
+    if reason.is_none() && risk > policy.max_risk {
+        bits.p = 0.0;
+        reason = Some(format!(
+            "risk {:.2} > max_risk {:.2}",
+            risk, policy.max_risk
+        ));
+    }
+
+    PlannedOp {
+        kind,
+        target,
+        action,
+        bits,
+        reason,
+        risk,
+        pre_sha256,
+    }
+}
+
+/// Count ops the gates would block, without executing or writing receipts.
+pub async fn preview_blocked(ops: &[Value], policy: &Policy) -> usize {
+    let kernel = KernelLoop::new();
+    let deny = denylist();
+    let mut blocked = 0;
+    for op in ops {
+        let p = plan_op(op, policy, &deny).await;
+        if p.action.is_none() || kernel.enforce_ask_act_gate(&p.bits).is_err() {
+            blocked += 1;
+        }
+    }
+    blocked
+}
+
+/// Gate, execute (or dry-run) and receipt each op in order.
+pub async fn run_ops(
+    parent_run_id: &str,
+    ops: &[Value],
+    policy: &Policy,
+    dry_run: bool,
+) -> Vec<OpReceipt> {
+    let kernel = KernelLoop::new();
+    let deny = denylist();
+    let mut out = Vec::new();
+
+    for (index, op) in ops.iter().enumerate() {
+        let PlannedOp {
+            kind,
+            target,
+            action,
+            bits,
+            reason,
+            risk,
+            pre_sha256,
+        } = plan_op(op, policy, &deny).await;
+
+        let op_run_id = format!("{}-op{}", parent_run_id, index);
+        let mut receipt = OpReceipt {
+            op_run_id,
+            parent_run_id: parent_run_id.to_string(),
+            index,
+            op: if kind.is_empty() {
+                "unknown".to_string()
+            } else {
+                kind.clone()
+            },
+            target: target.clone(),
+            status: "blocked".to_string(),
+            reason: None,
+            risk,
+            pre_sha256,
+            post_sha256: None,
+            bits: bits.clone(),
+        };
+
+        match (kernel.enforce_ask_act_gate(&bits), action) {
+            (Ok(()), Some(action)) => {
+                let res = if dry_run {
+                    Ok(executor::dry_run(&action).await)
+                } else {
+                    executor::execute(action, policy).await
+                };
+                match res {
+                    Ok(r) if r.ok => {
+                        receipt.status = if dry_run { "dry_run" } else { "applied" }.to_string();
+                        if !dry_run && kind == "write" {
+                            receipt.post_sha256 = sha256_file(Path::new(&target)).await;
+                        }
+                        receipt.bits.t = 0.9;
+                    }
+                    Ok(r) => {
+                        receipt.status = "failed".to_string();
+                        receipt.reason = Some(r.stderr.chars().take(300).collect());
+                        receipt.bits.e = 1.0;
+                        receipt.bits.t = 0.3;
+                    }
+                    Err(e) => {
+                        receipt.reason = Some(e.to_string());
+                        receipt.bits.e = 1.0;
+                        receipt.bits.t = 0.3;
+                    }
+                }
+            }
+            (gate, _) => {
+                receipt.reason = reason.or_else(|| gate.err());
+                receipt.bits.t = 0.0;
+                telemetry::emit(
+                    "nstar",
+                    "gate_trip",
+                    Some(parent_run_id),
+                    json!({"gate": "ops", "op": kind, "target": target, "reason": receipt.reason}),
+                );
+            }
+        }
+
+        write_child_receipt(&receipt, op).await;
+        out.push(receipt);
+    }
+    out
+}
@@ -0,0 +1,189 @@
+//! Minimal dependency-free PDF writer for text reports.
+//!
+//! Renders markdown-ish text (headings `#`..`###`, bullets, paragraphs) onto A4 pages using the
+//! built-in Helvetica fonts, so no font embedding is needed. Characters outside Latin-1 are
+//! replaced with `?`; long lines are wrapped on an approximate per-size character budget.
+
+const PAGE_W: f32 = 595.0;
+const PAGE_H: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+
+struct Line {
+    text: String,
+    size: f32,
+    bold: bool,
+}
+
+fn pdf_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if (c as u32) < 0x20 => out.push(' '),
+            c if (c as u32) < 0x7f => out.push(c),
+            c if (0xa0..=0xff).contains(&(c as u32)) => {
+                out.push_str(&format!("\\{:03o}", c as u32))
+            }
+            '\u{2013}' | '\u{2014}' => out.push('-'),
+            '\u{2018}' | '\u{2019}' => out.push('\''),
+            '\u{201c}' | '\u{201d}' => out.push('"'),
+            '\u{2026}' => out.push_str("..."),
+            _ => out.push('?'),
+        }
+    }
+    out
+}
+
+fn wrap(text: &str, max_chars: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    for word in text.split_whitespace() {
+        if !cur.is_empty() && cur.chars().count() + 1 + word.chars().count() > max_chars {
+            out.push(std::mem::take(&mut cur));
+        }
+        if !cur.is_empty() {
+            cur.push(' ');
+        }
+        cur.push_str(word);
+        while cur.chars().count() > max_chars {
+            let head: String = cur.chars().take(max_chars).collect();
+            cur = cur.chars().skip(max_chars).collect();
+            out.push(head);
+        }
+    }
+    if !cur.is_empty() || out.is_empty() {
+        out.push(cur);
+    }
+    out
+}
+
+fn layout(markdown: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for raw in markdown.lines() {
+        let (text, size, bold, indent) = if let Some(h) = raw.strip_prefix("### ") {
+            (h, 12.0, true, "")
+        } else if let Some(h) = raw.strip_prefix("## ") {
+            (h, 14.0, true, "")
+        } else if let Some(h) = raw.strip_prefix("# ") {
+            (h, 18.0, true, "")
+        } else if let Some(b) = raw.strip_prefix("- ") {
+            (b, 10.0, false, "  ")
+        } else {
+            (raw, 10.0, false, "")
+        };
+        // Drop inline markdown that reads badly in plain text.
+        let text = text.replace("**", "").replace('`', "");
+        if text.trim().is_empty() {
+            lines.push(Line {
+                text: String::new(),
+                size: 6.0,
+                bold: false,
+            });
+            continue;
+        }
+        // Helvetica averages ~0.5em per char.
+        let max_chars = ((PAGE_W - 2.0 * MARGIN) / (size * 0.5)) as usize - indent.len();
+        for (i, chunk) in wrap(&text, max_chars).into_iter().enumerate() {
+            let prefix = match (indent.is_empty(), i) {
+                (false, 0) => "- ",
+                (false, _) => "  ",
+                _ => "",
+            };
+            lines.push(Line {
+                text: format!("{indent}{prefix}{chunk}"),
+                size,
+                bold,
+            });
+        }
+    }
+    lines
+}
+
+/// Render `markdown` to a complete PDF document.
+pub fn render_markdown(title: &str, markdown: &str) -> Vec<u8> {
+    // Paginate into content streams.
+    let mut pages: Vec<String> = Vec::new();
+    let mut cur = String::new();
+    let mut y = PAGE_H - MARGIN;
+    for line in layout(markdown) {
+        let lead = line.size * 1.35;
+        if y - lead < MARGIN {
+            pages.push(std::mem::take(&mut cur));
+            y = PAGE_H - MARGIN;
+        }
+        y -= lead;
+        if !line.text.is_empty() {
+            cur.push_str(&format!(
+                "BT /{} {} Tf {} {:.1} Td ({}) Tj ET\n",
+                if line.bold { "F2" } else { "F1" },
+                line.size,
+                MARGIN,
+                y,
+                pdf_escape(&line.text)
+            ));
+        }
+    }
+    pages.push(cur);
+
+    // Objects: 1 catalog, 2 pages, 3/4 fonts, 5 info, then (page, content) pairs.
+    let n = pages.len();
+    let page_id = |i: usize| 6 + 2 * i;
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..n)
+                .map(|i| format!("{} 0 R", page_id(i)))
+                .collect::<Vec<_>>()
+                .join(" "),
+            n
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+        format!(
+            "<< /Title ({}) /Producer (one-engine) >>",
+            pdf_escape(title)
+        ),
+    ];
+    for (i, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            PAGE_W,
+            PAGE_H,
+            page_id(i) + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut out = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, body) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, body).as_bytes());
+    }
+    let xref = out.len();
+    out.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for off in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", off).as_bytes());
+    }
+    out.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .as_bytes(),
+    );
+    out
+}
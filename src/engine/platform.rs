@@ -0,0 +1,321 @@
+//! Host platform for the executor: which shell runs `Action::Cli` commands, how goals spell
+//! common commands for that shell, and how file goals turn input paths into host paths.
+//!
+//! The shell is `ONE_ENGINE_SHELL` (`bash`, `sh`, `pwsh`, `powershell` or `cmd`) or, by
+//! default, `bash` (else `sh`) on Unix and PowerShell (`pwsh` when installed) on Windows.
+//! Goals whose commands only make sense in a POSIX shell call [`require_posix`] and fail with
+//! an explanation elsewhere. Inside a test harness the shell is always `bash`, since fakes match
+//! POSIX command strings, and for runs on the container backend it is the image's `sh`.
+
+use anyhow::anyhow;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+use super::{container, harness};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Sh,
+    /// Windows PowerShell 5.
+    Powershell,
+    /// PowerShell 7+.
+    Pwsh,
+    Cmd,
+}
+
+impl Shell {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bash" => Some(Shell::Bash),
+            "sh" => Some(Shell::Sh),
+            "powershell" => Some(Shell::Powershell),
+            "pwsh" => Some(Shell::Pwsh),
+            "cmd" => Some(Shell::Cmd),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Sh => "sh",
+            Shell::Powershell => "powershell",
+            Shell::Pwsh => "pwsh",
+            Shell::Cmd => "cmd",
+        }
+    }
+
+    pub fn is_posix(self) -> bool {
+        matches!(self, Shell::Bash | Shell::Sh)
+    }
+
+    fn is_powershell(self) -> bool {
+        matches!(self, Shell::Powershell | Shell::Pwsh)
+    }
+
+    /// A command that runs `script` in this shell (`bash -lc`, `cmd /C`, ...).
+    pub fn command(self, script: &str) -> Command {
+        let mut c = Command::new(self.name());
+        match self {
+            Shell::Bash => c.arg("-lc"),
+            Shell::Sh => c.arg("-c"),
+            Shell::Powershell | Shell::Pwsh => {
+                c.args(["-NoProfile", "-NonInteractive", "-Command"])
+            }
+            Shell::Cmd => c.arg("/C"),
+        };
+        c.arg(script);
+        c
+    }
+
+    /// A command that parses `script` without running it, for shells that have such a flag.
+    pub fn syntax_check(self, script: &str) -> Option<Command> {
+        if !self.is_posix() {
+            return None;
+        }
+        let mut c = Command::new(self.name());
+        c.arg("-n").arg("-c").arg(script);
+        Some(c)
+    }
+
+    /// One argument, quoted for this shell.
+    pub fn quote(self, arg: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Sh => shell_escape::unix::escape(arg.into()).to_string(),
+            Shell::Powershell | Shell::Pwsh => {
+                // PowerShell also ends a single-quoted string at the typographic quotes
+                // U+2018..U+201B; doubling escapes any of them.
+                let mut out = String::from("'");
+                for c in arg.chars() {
+                    if matches!(c, '\'' | '\u{2018}'..='\u{201B}') {
+                        out.push(c);
+                    }
+                    out.push(c);
+                }
+                out.push('\'');
+                out
+            }
+            Shell::Cmd => {
+                if !arg.is_empty()
+                    && !arg.contains(|c: char| c.is_whitespace() || "&|<>()^\"%!".contains(c))
+                {
+                    arg.to_string()
+                } else {
+                    // cmd expands `%VAR%` even inside quotes. Each `%` goes outside them as
+                    // `^%`, so no variable name can form (it would contain `"^`) and the caret
+                    // pass leaves a plain `%`.
+                    format!("\"{}\"", arg.replace('"', "\"\"").replace('%', "\"^%\""))
+                }
+            }
+        }
+    }
+
+    /// Run `program` with `args`.
+    pub fn argv(self, program: &str, args: &[String]) -> String {
+        let line = std::iter::once(program)
+            .chain(args.iter().map(|a| a.as_str()))
+            .map(|a| self.quote(a))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if self.is_powershell() {
+            // A quoted program name is a string to PowerShell unless invoked with `&`.
+            format!("& {}", line)
+        } else {
+            line
+        }
+    }
+
+    /// Print `msg` and a newline.
+    pub fn echo(self, msg: &str) -> String {
+        match self {
+            Shell::Cmd => {
+                let mut out = String::from("echo ");
+                for c in msg.chars() {
+                    if "&|<>()^".contains(c) {
+                        out.push('^');
+                    }
+                    out.push(c);
+                }
+                out
+            }
+            Shell::Powershell | Shell::Pwsh => format!("Write-Output {}", self.quote(msg)),
+            Shell::Bash | Shell::Sh => format!("echo {}", self.quote(msg)),
+        }
+    }
+
+    /// Exit unsuccessfully.
+    pub fn fail(self) -> String {
+        match self {
+            Shell::Bash | Shell::Sh => "false".to_string(),
+            Shell::Powershell | Shell::Pwsh => "exit 1".to_string(),
+            Shell::Cmd => "exit /b 1".to_string(),
+        }
+    }
+
+    pub fn sleep_ms(self, ms: u64) -> String {
+        match self {
+            Shell::Bash | Shell::Sh => format!("sleep {}", ms as f64 / 1000.0),
+            Shell::Powershell | Shell::Pwsh => format!("Start-Sleep -Milliseconds {}", ms),
+            // cmd has no sub-second sleep; borrow PowerShell's.
+            Shell::Cmd => format!(
+                "powershell -NoProfile -Command Start-Sleep -Milliseconds {}",
+                ms
+            ),
+        }
+    }
+
+    /// Run `b` only if `a` succeeded.
+    pub fn and(self, a: &str, b: &str) -> String {
+        match self {
+            // Windows PowerShell 5 has no `&&`.
+            Shell::Powershell => format!("{}; if ($?) {{ {} }}", a, b),
+            _ => format!("{} && {}", a, b),
+        }
+    }
+
+    /// Run `cmd` with `dir` as the working directory.
+    pub fn in_dir(self, dir: &str, cmd: &str) -> String {
+        let cd = match self {
+            Shell::Bash | Shell::Sh => format!("cd {}", self.quote(dir)),
+            Shell::Powershell | Shell::Pwsh => {
+                format!("Set-Location -LiteralPath {}", self.quote(dir))
+            }
+            Shell::Cmd => format!("cd /d {}", self.quote(dir)),
+        };
+        self.and(&cd, cmd)
+    }
+}
+
+pub(crate) fn on_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| {
+        dir.join(program).is_file()
+            || (cfg!(windows) && dir.join(format!("{}.exe", program)).is_file())
+    })
+}
+
+static HOST_SHELL: Lazy<Shell> = Lazy::new(|| {
+    if let Ok(v) = std::env::var("ONE_ENGINE_SHELL") {
+        match Shell::parse(&v) {
+            Some(s) => return s,
+            None => tracing::warn!("unknown ONE_ENGINE_SHELL {:?}; detecting the shell", v),
+        }
+    }
+    if cfg!(windows) {
+        if on_path("pwsh") {
+            Shell::Pwsh
+        } else {
+            Shell::Powershell
+        }
+    } else if on_path("bash") || !on_path("sh") {
+        Shell::Bash
+    } else {
+        Shell::Sh
+    }
+});
+
+/// Whether `sh`'s program is on `PATH`.
+pub fn is_installed(sh: Shell) -> bool {
+    on_path(sh.name())
+}
+
+/// Shell for `Action::Cli` commands in the current task; `sh` when they run in a container.
+pub fn shell() -> Shell {
+    if harness::current().is_some() {
+        Shell::Bash
+    } else if container::active() {
+        Shell::Sh
+    } else {
+        *HOST_SHELL
+    }
+}
+
+/// `Err` explaining that `what` needs a POSIX shell, when this host doesn't run one.
+pub fn require_posix(what: &str) -> anyhow::Result<()> {
+    let sh = shell();
+    if sh.is_posix() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{} needs a POSIX shell (bash or sh), but commands on this host run in {}; install Git Bash or WSL and set ONE_ENGINE_SHELL=bash",
+        what,
+        sh.name()
+    ))
+}
+
+/// An input path (either separator) as a host path.
+pub fn native_path(p: &str) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(p.replace('/', "\\"))
+    } else {
+        PathBuf::from(p)
+    }
+}
+
+/// An input path with `/` separators, the form prefix checks and receipts use.
+pub fn slash_path(p: &str) -> String {
+    if cfg!(windows) {
+        p.replace('\\', "/")
+    } else {
+        p.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posix_spellings_match_what_goals_always_ran() {
+        let sh = Shell::Bash;
+        assert_eq!(sh.echo("hi there"), "echo 'hi there'");
+        assert_eq!(sh.fail(), "false");
+        assert_eq!(
+            sh.and(&sh.sleep_ms(100), &sh.echo("x")),
+            "sleep 0.1 && echo x"
+        );
+        assert_eq!(
+            sh.in_dir("my repo", "cargo build"),
+            "cd 'my repo' && cargo build"
+        );
+        assert_eq!(
+            sh.argv("git", &["log".into(), "-n 1".into()]),
+            "git log '-n 1'"
+        );
+    }
+
+    #[test]
+    fn windows_shells_quote_their_own_way() {
+        let ps = Shell::Powershell;
+        assert_eq!(ps.echo("it's"), "Write-Output 'it''s'");
+        assert_eq!(
+            ps.in_dir("C:\\repo", "cargo build"),
+            "Set-Location -LiteralPath 'C:\\repo'; if ($?) { cargo build }"
+        );
+        assert_eq!(ps.argv("git", &["status".into()]), "& 'git' 'status'");
+        assert_eq!(
+            ps.quote("a\u{2019}; rm x; \u{2018}b"),
+            "'a\u{2019}\u{2019}; rm x; \u{2018}\u{2018}b'"
+        );
+        assert_eq!(
+            ps.quote("\u{201A}\u{201B}"),
+            "'\u{201A}\u{201A}\u{201B}\u{201B}'"
+        );
+        assert_eq!(Shell::Pwsh.and("a", "b"), "a && b");
+
+        let cmd = Shell::Cmd;
+        assert_eq!(cmd.echo("a & b"), "echo a ^& b");
+        assert_eq!(
+            cmd.in_dir("C:\\my repo", "dir"),
+            "cd /d \"C:\\my repo\" && dir"
+        );
+        assert_eq!(cmd.fail(), "exit /b 1");
+        assert_eq!(cmd.quote("%PATH%"), "\"\"^%\"PATH\"^%\"\"");
+        assert_eq!(cmd.quote("50% off"), "\"50\"^%\" off\"");
+        assert!(cmd.syntax_check("dir").is_none());
+    }
+}
@@ -0,0 +1,517 @@
+//! WASM goal plugins (`--features wasm`): handlers compiled to WebAssembly and run in
+//! wasmtime, for logic that a `config/goals.d` command template cannot express.
+//!
+//! Each `plugins/<name>.wasm` (directory overridable with `ONE_ENGINE_PLUGINS_DIR`) needs a
+//! `plugins/<name>.yaml` next to it:
+//!
+//! ```yaml
+//! goals: [licenses.scan]
+//! description: SPDX license report for the workspace
+//! allow:                    # programs the plugin may run, each with its allowed subcommands
+//!   cargo: [metadata, tree]
+//!   git: [ls-files, log]
+//! max_actions: 16
+//! fuel: 1000000000           # wasmtime fuel, roughly instructions
+//! max_memory_mb: 64
+//! ```
+//!
+//! A plugin gets no WASI: no files, sockets, clock or environment. It exports `memory` and
+//! `run() -> i32` (0 = success) and may import these from the `one_engine` module; every
+//! `(ptr, len)` is a byte range of its own memory:
+//!
+//! - `input_len() -> i32`, `input_read(ptr, len) -> i32`: the run's inputs as JSON.
+//! - `evidence(ptr, len) -> i32`: merge a JSON object into the run's evidence.
+//! - `deliverable(ptr, len) -> i32`: add a deliverable path or URL.
+//! - `log(ptr, len)`: a line for the engine log.
+//! - `exec(ptr, len) -> i32`: run `{"argv": ["cargo", "metadata"]}` through the executor with
+//!   the run's policy. Only `allow`ed programs and subcommands, never with an option that
+//!   reconfigures the program ([`CONFIG_FLAGS`], e.g. `git -c` or `cargo --config`), at most
+//!   `max_actions` times; the arguments are quoted, so no shell syntax reaches the host.
+//!   Returns the byte length of the JSON result
+//!   (`{"ok", "stdout", "stderr"}` or `{"ok": false, "error"}`), read with `result_read`.
+//! - `result_read(ptr, len) -> i32`: copy out the last `exec` result.
+//!
+//! Calls that fail return -1. Running out of fuel or memory, a trap, or going past the
+//! policy's `time_ms` fails the run; the time limit is enforced by epoch interruption, so it
+//! also stops a plugin that loops without calling back into the host.
+//!
+//! A plugin may not claim a built-in goal id. Modules are compiled once and cached.
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use wasmtime::{
+    Caller, Config, Engine, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use crate::engine::catalog;
+use crate::engine::executor::{self, Action};
+use crate::engine::platform;
+use crate::engine::types::Policy;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginManifest {
+    pub goals: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+    /// Program -> the subcommands (first argument) it may run with.
+    #[serde(default)]
+    pub allow: BTreeMap<String, Vec<String>>,
+    #[serde(default = "default_max_actions")]
+    pub max_actions: usize,
+    #[serde(default = "default_fuel")]
+    pub fuel: u64,
+    #[serde(default = "default_max_memory_mb")]
+    pub max_memory_mb: usize,
+    /// The module next to the manifest.
+    #[serde(skip)]
+    pub wasm: PathBuf,
+}
+
+fn default_max_actions() -> usize {
+    16
+}
+
+fn default_fuel() -> u64 {
+    1_000_000_000
+}
+
+fn default_max_memory_mb() -> usize {
+    64
+}
+
+/// What a plugin run produced.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PluginRun {
+    pub plugin: String,
+    pub exit_code: i32,
+    pub evidence: Map<String, Value>,
+    pub deliverables: Vec<String>,
+    /// Each `exec` request and whether it ran and succeeded.
+    pub actions: Vec<Value>,
+    pub fuel_used: u64,
+}
+
+struct HostState {
+    inputs: Vec<u8>,
+    policy: Policy,
+    allow: BTreeMap<String, Vec<String>>,
+    max_actions: usize,
+    last_result: Vec<u8>,
+    limits: StoreLimits,
+    run: PluginRun,
+}
+
+fn plugins_dir() -> PathBuf {
+    PathBuf::from(std::env::var("ONE_ENGINE_PLUGINS_DIR").unwrap_or_else(|_| "plugins".to_string()))
+}
+
+fn claims_builtin(m: &PluginManifest) -> bool {
+    m.goals
+        .iter()
+        .any(|g| catalog::is_builtin(&catalog::resolve(g).goal_id))
+}
+
+fn load_dir(dir: &Path) -> Vec<PluginManifest> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yaml" | "yml")))
+        .collect();
+    files.sort();
+    let mut out = Vec::new();
+    for path in files {
+        let wasm = path.with_extension("wasm");
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|raw| Ok(serde_yaml::from_str::<PluginManifest>(&raw)?));
+        match parsed {
+            Ok(_) if !wasm.is_file() => {
+                tracing::warn!("skipping plugin {}: no {}", path.display(), wasm.display());
+            }
+            Ok(m) if claims_builtin(&m) => {
+                tracing::warn!("skipping plugin {}: claims a built-in goal", path.display());
+            }
+            Ok(mut m) => {
+                m.wasm = wasm;
+                out.push(m);
+            }
+            Err(e) => tracing::warn!("skipping plugin {}: {:#}", path.display(), e),
+        }
+    }
+    out
+}
+
+static MANIFESTS: Lazy<Vec<PluginManifest>> = Lazy::new(|| load_dir(&plugins_dir()));
+
+/// Epoch length; a run's `time_ms` is rounded up to whole ticks.
+const TICK: Duration = Duration::from_millis(10);
+
+/// Options that let a program run something other than what was asked (`git -c
+/// core.pager=...`, `cargo --config build.rustc-wrapper=...`); refused anywhere in argv.
+pub const CONFIG_FLAGS: &[&str] = &[
+    "-c",
+    "--config",
+    "--exec-path",
+    "--upload-pack",
+    "--receive-pack",
+    "-Z",
+];
+
+static ENGINE: Lazy<Engine> = Lazy::new(|| {
+    let mut config = Config::new();
+    config.async_support(true);
+    config.consume_fuel(true);
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config).expect("wasmtime engine config");
+    let ticker = engine.clone();
+    std::thread::Builder::new()
+        .name("wasm-epoch".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(TICK);
+            ticker.increment_epoch();
+        })
+        .expect("wasm epoch ticker");
+    engine
+});
+
+/// Compiled modules by path; manifests are read once, so modules are too.
+static MODULES: Lazy<Mutex<HashMap<PathBuf, Module>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Read the plugin manifests now (at startup) rather than on the first run; returns the count.
+pub fn load() -> usize {
+    MANIFESTS.len()
+}
+
+pub fn manifests() -> &'static [PluginManifest] {
+    &MANIFESTS
+}
+
+/// The plugin handling `goal_id`, if any (a `user:<id>.` prefix is ignored).
+pub fn find(goal_id: &str) -> Option<&'static PluginManifest> {
+    let bare = goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.'))
+        .map_or(goal_id, |(_, g)| g);
+    MANIFESTS.iter().find(|m| m.goals.iter().any(|g| g == bare))
+}
+
+fn memory(caller: &mut Caller<'_, HostState>) -> Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|e| e.into_memory())
+        .ok_or_else(|| anyhow!("plugin exports no memory"))
+}
+
+fn read(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>> {
+    let mem = memory(caller)?;
+    let mut buf = vec![0u8; usize::try_from(len)?];
+    mem.read(&*caller, usize::try_from(ptr)?, &mut buf)?;
+    Ok(buf)
+}
+
+/// Copy `bytes` (at most `len` of them) to `ptr`; the count copied, or -1.
+fn write(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32, bytes: &[u8]) -> i32 {
+    let (Ok(ptr), Ok(len)) = (usize::try_from(ptr), usize::try_from(len)) else {
+        return -1;
+    };
+    let n = len.min(bytes.len());
+    match memory(caller).and_then(|m| Ok(m.write(&mut *caller, ptr, &bytes[..n])?)) {
+        Ok(()) => n as i32,
+        Err(_) => -1,
+    }
+}
+
+fn is_config_flag(arg: &str) -> bool {
+    CONFIG_FLAGS.iter().any(|f| {
+        arg == *f
+            || arg
+                .strip_prefix(f)
+                .is_some_and(|rest| rest.starts_with('=') || !f.starts_with("--"))
+    })
+}
+
+/// The argv of an `exec` request, if it is well-formed and allowed.
+fn requested_argv(
+    req: &[u8],
+    allow: &BTreeMap<String, Vec<String>>,
+) -> std::result::Result<Vec<String>, String> {
+    #[derive(Deserialize)]
+    struct ExecRequest {
+        argv: Vec<String>,
+    }
+    let req: ExecRequest =
+        serde_json::from_slice(req).map_err(|e| format!("bad exec request: {}", e))?;
+    let program = req.argv.first().ok_or("empty argv")?;
+    let subcommands = allow
+        .get(program)
+        .ok_or_else(|| format!("{} is not in the plugin's allow list", program))?;
+    let sub = req.argv.get(1).map(String::as_str).unwrap_or("");
+    if !subcommands.iter().any(|s| s == sub) {
+        return Err(format!(
+            "{} {} is not in the plugin's allow list",
+            program, sub
+        ));
+    }
+    if let Some(flag) = req.argv[1..].iter().find(|a| is_config_flag(a)) {
+        return Err(format!("{} is not allowed in plugin commands", flag));
+    }
+    Ok(req.argv)
+}
+
+fn linker() -> Result<Linker<HostState>> {
+    let mut l: Linker<HostState> = Linker::new(&ENGINE);
+    l.func_wrap(
+        "one_engine",
+        "input_len",
+        |caller: Caller<'_, HostState>| caller.data().inputs.len() as i32,
+    )?;
+    l.func_wrap(
+        "one_engine",
+        "input_read",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            let inputs = caller.data().inputs.clone();
+            write(&mut caller, ptr, len, &inputs)
+        },
+    )?;
+    l.func_wrap(
+        "one_engine",
+        "evidence",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            let Ok(raw) = read(&mut caller, ptr, len) else {
+                return -1;
+            };
+            match serde_json::from_slice::<Value>(&raw) {
+                Ok(Value::Object(obj)) => {
+                    caller.data_mut().run.evidence.extend(obj);
+                    0
+                }
+                _ => -1,
+            }
+        },
+    )?;
+    l.func_wrap(
+        "one_engine",
+        "deliverable",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| match read(&mut caller, ptr, len)
+            .map(String::from_utf8)
+        {
+            Ok(Ok(d)) => {
+                caller.data_mut().run.deliverables.push(d);
+                0
+            }
+            _ => -1,
+        },
+    )?;
+    l.func_wrap(
+        "one_engine",
+        "log",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            if let Ok(raw) = read(&mut caller, ptr, len) {
+                let plugin = caller.data().run.plugin.clone();
+                tracing::info!("plugin {}: {}", plugin, String::from_utf8_lossy(&raw));
+            }
+        },
+    )?;
+    l.func_wrap(
+        "one_engine",
+        "result_read",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+            let last = caller.data().last_result.clone();
+            write(&mut caller, ptr, len, &last)
+        },
+    )?;
+    l.func_wrap_async(
+        "one_engine",
+        "exec",
+        |mut caller: Caller<'_, HostState>, (ptr, len): (i32, i32)| {
+            Box::new(async move {
+                let Ok(raw) = read(&mut caller, ptr, len) else {
+                    return -1;
+                };
+                let state = caller.data();
+                let argv = if state.run.actions.len() >= state.max_actions {
+                    Err(format!("max_actions ({}) reached", state.max_actions))
+                } else {
+                    requested_argv(&raw, &state.allow)
+                };
+                let policy = state.policy.clone();
+                let result = match argv {
+                    Ok(argv) => {
+                        let cmd = platform::shell().argv(&argv[0], &argv[1..]);
+                        let res = executor::execute(Action::Cli(cmd), &policy).await;
+                        let result = match res {
+                            Ok(r) => json!({"ok": r.ok, "stdout": r.stdout, "stderr": r.stderr}),
+                            Err(e) => json!({"ok": false, "error": e.to_string()}),
+                        };
+                        caller
+                            .data_mut()
+                            .run
+                            .actions
+                            .push(json!({"argv": argv, "ok": result["ok"]}));
+                        result
+                    }
+                    Err(e) => {
+                        tracing::warn!("plugin exec denied: {}", e);
+                        json!({"ok": false, "error": e})
+                    }
+                };
+                let bytes = serde_json::to_vec(&result).unwrap_or_default();
+                let n = bytes.len() as i32;
+                caller.data_mut().last_result = bytes;
+                n
+            })
+        },
+    )?;
+    Ok(l)
+}
+
+async fn run_module(
+    module: &Module,
+    manifest: &PluginManifest,
+    name: &str,
+    inputs: &Value,
+    policy: &Policy,
+) -> Result<PluginRun> {
+    let state = HostState {
+        inputs: serde_json::to_vec(inputs)?,
+        policy: policy.clone(),
+        allow: manifest.allow.clone(),
+        max_actions: manifest.max_actions,
+        last_result: Vec::new(),
+        limits: StoreLimitsBuilder::new()
+            .memory_size(manifest.max_memory_mb * 1024 * 1024)
+            .build(),
+        run: PluginRun {
+            plugin: name.to_string(),
+            ..Default::default()
+        },
+    };
+    let mut store = Store::new(&ENGINE, state);
+    store.limiter(|s| &mut s.limits);
+    store.set_fuel(manifest.fuel)?;
+    // Traps once `time_ms` worth of ticks pass, even in a loop that never yields.
+    store.set_epoch_deadline(policy.time_ms.div_ceil(TICK.as_millis() as u64) + 1);
+    let instance = linker()?
+        .instantiate_async(&mut store, module)
+        .await
+        .with_context(|| format!("instantiating plugin {}", name))?;
+    let entry = instance
+        .get_typed_func::<(), i32>(&mut store, "run")
+        .with_context(|| format!("plugin {} exports no run() -> i32", name))?;
+    let code = tokio::time::timeout(
+        Duration::from_millis(policy.time_ms),
+        entry.call_async(&mut store, ()),
+    )
+    .await
+    .map_err(|_| anyhow!("plugin {} ran past {} ms", name, policy.time_ms))?
+    .with_context(|| format!("plugin {} trapped", name))?;
+    let fuel_left = store.get_fuel().unwrap_or(0);
+    let mut run = std::mem::take(&mut store.data_mut().run);
+    run.exit_code = code;
+    run.fuel_used = manifest.fuel.saturating_sub(fuel_left);
+    Ok(run)
+}
+
+/// Run `manifest`'s module for one goal run.
+pub async fn run(manifest: &PluginManifest, inputs: &Value, policy: &Policy) -> Result<PluginRun> {
+    let name = manifest
+        .wasm
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let module = module_for(&manifest.wasm).await?;
+    run_module(&module, manifest, &name, inputs, policy).await
+}
+
+/// The compiled module at `path`, compiling it (off the async workers) on first use.
+async fn module_for(path: &Path) -> Result<Module> {
+    if let Some(m) = MODULES.lock().unwrap_or_else(|e| e.into_inner()).get(path) {
+        return Ok(m.clone());
+    }
+    let owned = path.to_path_buf();
+    let module = tokio::task::spawn_blocking(move || Module::from_file(&ENGINE, &owned))
+        .await?
+        .with_context(|| format!("loading {}", path.display()))?;
+    MODULES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(path.to_path_buf(), module.clone());
+    Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_requests_need_an_allowed_program_and_subcommand() {
+        let allow = BTreeMap::from([
+            ("cargo".to_string(), vec!["metadata".to_string()]),
+            ("git".to_string(), vec!["log".to_string()]),
+        ]);
+        assert_eq!(
+            requested_argv(br#"{"argv": ["cargo", "metadata"]}"#, &allow).unwrap(),
+            vec!["cargo", "metadata"]
+        );
+        assert!(requested_argv(br#"{"argv": ["sh", "-c", "id"]}"#, &allow).is_err());
+        assert!(requested_argv(br#"{"argv": ["cargo", "run"]}"#, &allow).is_err());
+        assert!(requested_argv(
+            br#"{"argv": ["git", "-c", "core.pager=id", "log"]}"#,
+            &allow
+        )
+        .is_err());
+        assert!(requested_argv(br#"{"argv": ["git", "log", "-ccore.pager=id"]}"#, &allow).is_err());
+        assert!(
+            requested_argv(br#"{"argv": ["cargo", "metadata", "--config=x"]}"#, &allow).is_err()
+        );
+        assert!(requested_argv(br#"{"argv": []}"#, &allow).is_err());
+        assert!(requested_argv(b"cargo metadata", &allow).is_err());
+    }
+
+    #[tokio::test]
+    async fn plugin_reads_inputs_and_emits_evidence() {
+        // Copies its inputs to offset 0 and reports them back as evidence.
+        let wat = r#"
+            (module
+              (import "one_engine" "input_len" (func $input_len (result i32)))
+              (import "one_engine" "input_read" (func $input_read (param i32 i32) (result i32)))
+              (import "one_engine" "evidence" (func $evidence (param i32 i32) (result i32)))
+              (memory (export "memory") 1)
+              (func (export "run") (result i32)
+                (local $n i32)
+                (local.set $n (call $input_read (i32.const 0) (call $input_len)))
+                (call $evidence (i32.const 0) (local.get $n))))
+        "#;
+        let module = Module::new(&ENGINE, wat).unwrap();
+        let manifest = PluginManifest {
+            goals: vec!["echo.inputs".into()],
+            description: String::new(),
+            allow: BTreeMap::new(),
+            max_actions: default_max_actions(),
+            fuel: default_fuel(),
+            max_memory_mb: default_max_memory_mb(),
+            wasm: PathBuf::new(),
+        };
+        let run = run_module(
+            &module,
+            &manifest,
+            "echo",
+            &json!({"answer": 42}),
+            &Policy::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(run.exit_code, 0);
+        assert_eq!(run.evidence["answer"], 42);
+        assert!(run.fuel_used > 0);
+    }
+}
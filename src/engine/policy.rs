@@ -1,4 +1,6 @@
 use super::bits::Bits;
+use super::types::{ExecutorBackend, Policy, ResourceLimits};
+use serde::{Deserialize, Serialize};
 
 pub fn trust_from(passed: bool, b: &Bits) -> f32 {
     if passed && b.e == 0.0 {
@@ -9,3 +11,189 @@ pub fn trust_from(passed: bool, b: &Bits) -> f32 {
         0.3
     }
 }
+
+/// Partial policy: only the fields that are set replace the base.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gamma_gate: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_risk: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiny_diff_loc: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotent: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tool_iterations: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executor: Option<ExecutorBackend>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ResourceLimits>,
+}
+
+impl PolicyOverride {
+    pub fn apply(&self, p: &mut Policy) {
+        if let Some(v) = self.gamma_gate {
+            p.gamma_gate = v;
+        }
+        if let Some(v) = self.time_ms {
+            p.time_ms = v;
+        }
+        if let Some(v) = self.max_risk {
+            p.max_risk = v;
+        }
+        if let Some(v) = self.tiny_diff_loc {
+            p.tiny_diff_loc = v;
+        }
+        if let Some(v) = self.snapshot {
+            p.snapshot = v;
+        }
+        if let Some(v) = self.idempotent {
+            p.idempotent = v;
+        }
+        if let Some(v) = self.max_tool_iterations {
+            p.max_tool_iterations = v;
+        }
+        if let Some(v) = self.executor {
+            p.executor = v;
+        }
+        if let Some(v) = &self.limits {
+            p.limits = v.clone();
+        }
+    }
+}
+
+fn tighter(base: Option<u64>, caller: Option<u64>) -> Option<u64> {
+    match (base, caller) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Lay a user or request policy over `base`, field by field. Callers tune the rest freely but
+/// can only tighten the safety fields: `max_risk` never rises, a container executor stays a
+/// container, and each resource limit keeps the lower of the two (a limit can be added, not
+/// dropped).
+pub fn layer_caller(base: &Policy, caller: Policy) -> Policy {
+    let executor = if base.executor == ExecutorBackend::Container {
+        ExecutorBackend::Container
+    } else {
+        caller.executor
+    };
+    Policy {
+        max_risk: caller.max_risk.min(base.max_risk),
+        executor,
+        limits: ResourceLimits {
+            cpu_s: tighter(base.limits.cpu_s, caller.limits.cpu_s),
+            max_rss_mb: tighter(base.limits.max_rss_mb, caller.limits.max_rss_mb),
+            max_output_bytes: tighter(base.limits.max_output_bytes, caller.limits.max_output_bytes),
+        },
+        ..caller
+    }
+}
+
+/// One `goal_policies:` entry: `goal` is an exact id or a `*` glob (`wiki.*`, `*.build`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoalPolicy {
+    pub goal: String,
+    #[serde(flatten)]
+    pub policy: PolicyOverride,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesGoalPolicies {
+    #[serde(default)]
+    goal_policies: Vec<GoalPolicy>,
+}
+
+pub fn load_goal_policies() -> Vec<GoalPolicy> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesGoalPolicies>(&raw) {
+            Ok(p) => p.goal_policies,
+            Err(e) => {
+                tracing::warn!("invalid {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == s;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !s.starts_with(first) || s.len() < first.len() + last.len() || !s.ends_with(last) {
+        return false;
+    }
+    let mut rest = &s[first.len()..s.len() - last.len()];
+    for mid in &parts[1..parts.len() - 1] {
+        match rest.find(mid) {
+            Some(i) => rest = &rest[i + mid.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Entries matching `goal_id` in file order (later entries win when applied in turn).
+/// User-namespaced ids (`user:<id>.<goal>`) match on the bare goal.
+pub fn goal_policies_for(goal_id: &str, all: &[GoalPolicy]) -> Vec<GoalPolicy> {
+    let bare = goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.').map(|(_, g)| g))
+        .unwrap_or(goal_id);
+    all.iter()
+        .filter(|g| glob_match(g.goal.trim(), bare))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caller_policies_cannot_loosen_safety_fields() {
+        let base = Policy {
+            max_risk: 0.3,
+            executor: ExecutorBackend::Container,
+            limits: ResourceLimits {
+                cpu_s: Some(60),
+                max_rss_mb: None,
+                max_output_bytes: Some(1 << 20),
+            },
+            ..Policy::default()
+        };
+        let caller = Policy {
+            max_risk: 0.9,
+            time_ms: 1234,
+            executor: ExecutorBackend::Host,
+            limits: ResourceLimits {
+                cpu_s: None,
+                max_rss_mb: Some(512),
+                max_output_bytes: Some(1 << 30),
+            },
+            ..Policy::default()
+        };
+        let p = layer_caller(&base, caller);
+        assert_eq!((p.max_risk, p.time_ms), (0.3, 1234));
+        assert_eq!(p.executor, ExecutorBackend::Container);
+        assert_eq!(
+            p.limits,
+            ResourceLimits {
+                cpu_s: Some(60),
+                max_rss_mb: Some(512),
+                max_output_bytes: Some(1 << 20),
+            }
+        );
+    }
+}
@@ -0,0 +1,329 @@
+//! Prompt registry: system prompts as versioned templates.
+//!
+//! Templates live in `prompts/` (`ONE_ENGINE_PROMPTS_DIR`) as `<name>.md` (version 1) or
+//! `<name>.v<N>.md`; names are case-insensitive, so `META_OMNI.md` is `meta_omni`. The highest
+//! version is active unless the policies file pins another:
+//!
+//! ```yaml
+//! prompts:
+//!   pins: {meta_omni: 1}
+//! ```
+//!
+//! `{{name}}` placeholders are filled from the caller's variables (`user`, `thread`, `goal`,
+//! `task`, `context`, `date`); unknown ones render empty and are listed under `missing`. Files
+//! are re-read when their modification time changes, so edits apply from the next turn. With
+//! no template, the caller's built-in text is used and logged. Every render returns a
+//! [`PromptRecord`] (name, version, sha256 of the template) for the run's evidence.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct PromptInfo {
+    pub name: String,
+    pub version: u32,
+    pub path: String,
+    pub sha256: String,
+    pub bytes: u64,
+    /// The version [`render`] uses.
+    pub active: bool,
+    /// Placeholders the template uses.
+    pub variables: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+}
+
+/// Which prompt a run used.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct PromptRecord {
+    pub name: String,
+    /// 0 for the caller's built-in text.
+    pub version: u32,
+    /// sha256 of the template before interpolation.
+    pub sha256: String,
+    /// The template file; absent for the built-in text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Placeholders no variable was given for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+pub struct Rendered {
+    pub text: String,
+    pub record: PromptRecord,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PromptsSection {
+    #[serde(default)]
+    pins: BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesPrompts {
+    #[serde(default)]
+    prompts: Option<PromptsSection>,
+}
+
+fn pins() -> BTreeMap<String, u32> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesPrompts>(&raw).ok())
+        .and_then(|p| p.prompts)
+        .map(|s| {
+            s.pins
+                .into_iter()
+                .map(|(k, v)| (k.to_lowercase(), v))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn prompts_dir() -> PathBuf {
+    PathBuf::from(std::env::var("ONE_ENGINE_PROMPTS_DIR").unwrap_or_else(|_| "prompts".to_string()))
+}
+
+fn placeholder() -> &'static Regex {
+    static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([A-Za-z0-9_]+)\s*\}\}").unwrap());
+    &RE
+}
+
+fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// `("meta_omni", 2)` for `META_OMNI.v2.md`, `("meta_omni", 1)` for `META_OMNI.md`.
+fn parse_file_name(file: &str) -> Option<(String, u32)> {
+    let stem = file.strip_suffix(".md")?;
+    let (name, version) = match stem.rsplit_once(".v") {
+        Some((name, v)) if !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit()) => {
+            (name, v.parse().ok()?)
+        }
+        _ => (stem, 1),
+    };
+    (!name.is_empty() && !name.contains('.')).then(|| (name.to_lowercase(), version))
+}
+
+#[derive(Clone)]
+struct Template {
+    name: String,
+    version: u32,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    text: String,
+}
+
+/// Templates by path, re-read when the file's mtime changes.
+static CACHE: Lazy<Mutex<HashMap<PathBuf, Template>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn scan(dir: &Path) -> Vec<Template> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut out = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some((name, version)) = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(parse_file_name)
+        else {
+            continue;
+        };
+        let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+        let fresh = cache
+            .get(&path)
+            .filter(|t| modified.is_some() && t.modified == modified)
+            .cloned();
+        let template = match fresh {
+            Some(t) => t,
+            None => {
+                let Ok(text) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                let t = Template {
+                    name,
+                    version,
+                    path: path.clone(),
+                    modified,
+                    text,
+                };
+                cache.insert(path, t.clone());
+                t
+            }
+        };
+        out.push(template);
+    }
+    out.sort_by(|a, b| (&a.name, a.version).cmp(&(&b.name, b.version)));
+    out
+}
+
+/// The active version of `name`: the pinned one, else the highest.
+fn active<'a>(
+    templates: &'a [Template],
+    pins: &BTreeMap<String, u32>,
+    name: &str,
+) -> Option<&'a Template> {
+    let name = name.to_lowercase();
+    let mut versions = templates.iter().filter(|t| t.name == name);
+    match pins.get(&name) {
+        Some(v) => versions.find(|t| t.version == *v),
+        None => versions.max_by_key(|t| t.version),
+    }
+}
+
+fn interpolate(template: &str, vars: &[(&str, &str)]) -> (String, Vec<String>) {
+    let mut missing = BTreeSet::new();
+    let text = placeholder().replace_all(template, |c: &regex::Captures| {
+        match vars.iter().find(|(k, _)| *k == &c[1]) {
+            Some((_, v)) => v.to_string(),
+            None => {
+                missing.insert(c[1].to_string());
+                String::new()
+            }
+        }
+    });
+    (text.into_owned(), missing.into_iter().collect())
+}
+
+fn render_from(
+    templates: &[Template],
+    pins: &BTreeMap<String, u32>,
+    name: &str,
+    builtin: &str,
+    vars: &[(&str, &str)],
+) -> Rendered {
+    let (template, version, path) = match active(templates, pins, name) {
+        Some(t) => (
+            t.text.as_str(),
+            t.version,
+            Some(t.path.display().to_string()),
+        ),
+        None => {
+            tracing::warn!(
+                "no prompt template {:?} in {}; using the built-in text",
+                name,
+                prompts_dir().display()
+            );
+            (builtin, 0, None)
+        }
+    };
+    let (text, missing) = interpolate(template, vars);
+    Rendered {
+        text,
+        record: PromptRecord {
+            name: name.to_lowercase(),
+            version,
+            sha256: sha256_hex(template),
+            path,
+            missing,
+        },
+    }
+}
+
+/// Prompt `name` with `vars` filled in, or `builtin` when there is no template.
+pub fn render(name: &str, builtin: &str, vars: &[(&str, &str)]) -> Rendered {
+    render_from(&scan(&prompts_dir()), &pins(), name, builtin, vars)
+}
+
+/// Every template version, by name then version.
+pub fn list() -> Vec<PromptInfo> {
+    let templates = scan(&prompts_dir());
+    let pins = pins();
+    templates
+        .iter()
+        .map(|t| {
+            let variables: BTreeSet<String> = placeholder()
+                .captures_iter(&t.text)
+                .map(|c| c[1].to_string())
+                .collect();
+            PromptInfo {
+                name: t.name.clone(),
+                version: t.version,
+                path: t.path.display().to_string(),
+                sha256: sha256_hex(&t.text),
+                bytes: t.text.len() as u64,
+                active: active(&templates, &pins, &t.name).is_some_and(|a| a.path == t.path),
+                variables: variables.into_iter().collect(),
+                modified: t
+                    .modified
+                    .map(|m| chrono::DateTime::<chrono::Utc>::from(m).to_rfc3339()),
+            }
+        })
+        .collect()
+}
+
+/// Template text of `name` at `version` (the active one when `None`).
+pub fn text(name: &str, version: Option<u32>) -> Option<String> {
+    let templates = scan(&prompts_dir());
+    let pins = match version {
+        Some(v) => BTreeMap::from([(name.to_lowercase(), v)]),
+        None => pins(),
+    };
+    active(&templates, &pins, name).map(|t| t.text.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_or_pinned_version_renders_with_its_hash() {
+        let dir = std::env::temp_dir().join(format!("prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("META_OMNI.md"), "v1 for {{user}}").unwrap();
+        std::fs::write(
+            dir.join("meta_omni.v2.md"),
+            "v2 for {{ user }} in {{thread}}",
+        )
+        .unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        let templates = scan(&dir);
+        assert_eq!(templates.len(), 2);
+
+        let r = render_from(
+            &templates,
+            &BTreeMap::new(),
+            "meta_omni",
+            "builtin",
+            &[("user", "ada")],
+        );
+        assert_eq!(r.text, "v2 for ada in ");
+        assert_eq!(r.record.version, 2);
+        assert_eq!(r.record.missing, vec!["thread".to_string()]);
+        assert_eq!(
+            r.record.sha256,
+            sha256_hex("v2 for {{ user }} in {{thread}}")
+        );
+
+        let pinned = BTreeMap::from([("meta_omni".to_string(), 1)]);
+        let r = render_from(
+            &templates,
+            &pinned,
+            "META_OMNI",
+            "builtin",
+            &[("user", "ada")],
+        );
+        assert_eq!((r.text.as_str(), r.record.version), ("v1 for ada", 1));
+
+        let r = render_from(&templates, &BTreeMap::new(), "nstar", "fallback", &[]);
+        assert_eq!((r.text.as_str(), r.record.version), ("fallback", 0));
+        assert!(r.record.path.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,509 @@
+//! Regression reports for red `meta3.build` runs.
+//!
+//! When a build fails, the failing run is compared with the last green receipt of the same
+//! goal under `runs/receipts/`: what changed in the inputs, in the environment (build command,
+//! repo path, remote host and revision, effective policy, and `evidence.environment` when the
+//! receipt has one) and in the build report (new error lines, newly failing tests, packages
+//! that went from ok to failed). The report is written to `runs/regressions/<receipt_id>.json`
+//! and `.md`, so only those who may read the failing run's receipt may read it, and the failing
+//! run's evidence links it under `regression`.
+//!
+//! With bisecting on (`bisect: true` input, or `meta3_build.regression_bisect` in the
+//! policies file), the red runs since the last green one are searched for the first stored
+//! build log containing the failing run's first error line. The search is binary, so it
+//! assumes an error, once introduced, stays until it is fixed.
+
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use one_engine::{atomic, storage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::build_log::BuildReport;
+use super::urls;
+
+/// One run as the detector sees it.
+#[derive(Debug, Clone, Default)]
+pub struct RunView {
+    /// Receipt directory name (the run being analysed may not have one yet).
+    pub receipt_id: String,
+    pub run_id: String,
+    pub inputs: Value,
+    pub environment: Value,
+    pub success: bool,
+    pub build_report: Option<BuildReport>,
+    pub log_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRef {
+    pub receipt_id: String,
+    pub run_id: String,
+}
+
+impl RunRef {
+    fn of(v: &RunView) -> Self {
+        Self {
+            receipt_id: v.receipt_id.clone(),
+            run_id: v.run_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub path: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BuildDiff {
+    pub errors_before: usize,
+    pub errors_after: usize,
+    pub warnings_before: usize,
+    pub warnings_after: usize,
+    pub new_errors: Vec<String>,
+    pub newly_failing_tests: Vec<String>,
+    pub newly_failing_packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bisect {
+    /// The first red run whose log has `signature`.
+    pub first_bad: RunRef,
+    /// The run just before it (the last green one when the error is new in the earliest red).
+    pub last_good: Option<RunRef>,
+    pub signature: String,
+    pub logs_read: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegressionReport {
+    pub goal_id: String,
+    pub failing: RunRef,
+    pub last_green: Option<RunRef>,
+    /// Red runs stored between the last green one and this one.
+    pub reds_since_green: usize,
+    pub inputs_diff: Vec<Change>,
+    pub environment_diff: Vec<Change>,
+    pub build_diff: BuildDiff,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bisect: Option<Bisect>,
+    /// The likeliest causes, most specific first.
+    pub suspects: Vec<String>,
+}
+
+/// Leaf differences between two JSON values, by JSON pointer. Keys starting with `__`
+/// (engine-internal inputs) are ignored.
+pub fn json_diff(before: &Value, after: &Value) -> Vec<Change> {
+    fn walk(path: &str, a: &Value, b: &Value, out: &mut Vec<Change>) {
+        match (a, b) {
+            (Value::Object(x), Value::Object(y)) => {
+                let keys: BTreeSet<&String> = x.keys().chain(y.keys()).collect();
+                for k in keys.into_iter().filter(|k| !k.starts_with("__")) {
+                    let null = Value::Null;
+                    walk(
+                        &format!("{}/{}", path, k.replace('~', "~0").replace('/', "~1")),
+                        x.get(k).unwrap_or(&null),
+                        y.get(k).unwrap_or(&null),
+                        out,
+                    );
+                }
+            }
+            _ if a != b => out.push(Change {
+                path: if path.is_empty() {
+                    "/".into()
+                } else {
+                    path.into()
+                },
+                before: a.clone(),
+                after: b.clone(),
+            }),
+            _ => {}
+        }
+    }
+    let mut out = Vec::new();
+    walk("", before, after, &mut out);
+    out
+}
+
+pub fn build_diff(green: Option<&BuildReport>, red: Option<&BuildReport>) -> BuildDiff {
+    let empty = BuildReport::default();
+    let (g, r) = (green.unwrap_or(&empty), red.unwrap_or(&empty));
+    let new = |before: &[String], after: &[String]| -> Vec<String> {
+        after
+            .iter()
+            .filter(|x| !before.contains(x))
+            .cloned()
+            .collect()
+    };
+    let failed = |rep: &BuildReport| -> Vec<String> {
+        rep.packages
+            .iter()
+            .filter(|p| p.status == "failed")
+            .map(|p| p.name.clone())
+            .collect()
+    };
+    BuildDiff {
+        errors_before: g.errors,
+        errors_after: r.errors,
+        warnings_before: g.warnings,
+        warnings_after: r.warnings,
+        new_errors: new(&g.error_samples, &r.error_samples),
+        newly_failing_tests: new(&g.failing_tests, &r.failing_tests),
+        newly_failing_packages: new(&failed(g), &failed(r)),
+    }
+}
+
+fn meta3_root() -> PathBuf {
+    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()))
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&storage::read_to_string(path).ok()?).ok()
+}
+
+/// The environment fields of a `meta3.build` run: evidence and effective policy.
+pub fn environment(evidence: &Value, policy: &Value) -> Value {
+    let mut env = evidence
+        .get("environment")
+        .and_then(|v| v.as_object())
+        .cloned()
+        .unwrap_or_default();
+    for k in ["repo_path", "build_cmd"] {
+        if let Some(v) = evidence.get(k) {
+            env.insert(k.to_string(), v.clone());
+        }
+    }
+    if let Some(r) = evidence.get("remote") {
+        env.insert(
+            "remote".to_string(),
+            json!({"host": r.get("host"), "rev": r.get("rev")}),
+        );
+    }
+    if !policy.is_null() {
+        env.insert("policy".to_string(), policy.clone());
+    }
+    Value::Object(env)
+}
+
+/// A run from its (unresolved) inputs, effective policy and manifest; `None` when the
+/// evidence does not say whether it succeeded.
+pub fn view(receipt_id: &str, inputs: &Value, policy: &Value, manifest: &Value) -> Option<RunView> {
+    let evidence = manifest.get("evidence")?;
+    let success = evidence.get("actual_success").and_then(|v| v.as_bool())?;
+    Some(RunView {
+        receipt_id: receipt_id.to_string(),
+        run_id: manifest
+            .get("run_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string(),
+        inputs: inputs.clone(),
+        environment: environment(evidence, policy),
+        success,
+        build_report: evidence
+            .get("build_report")
+            .and_then(|b| serde_json::from_value(b.clone()).ok()),
+        log_path: evidence
+            .get("log_path")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+    })
+}
+
+/// Stored receipts of `goal_id`, oldest first.
+fn stored_runs(goal_id: &str) -> Vec<RunView> {
+    let dir = meta3_root().join("runs/receipts");
+    let mut runs: Vec<(std::time::SystemTime, RunView)> = Vec::new();
+    for name in storage::entries(&dir) {
+        let resp_path = dir.join(&name).join("response.json");
+        let (Some(req), Some(resp)) = (
+            read_json(&dir.join(&name).join("request.json")),
+            read_json(&resp_path),
+        ) else {
+            continue;
+        };
+        if req.get("goal_id").and_then(|v| v.as_str()) != Some(goal_id) {
+            continue;
+        }
+        let Some(run) = view(
+            &name,
+            req.get("inputs").unwrap_or(&Value::Null),
+            req.get("policy_effective").unwrap_or(&Value::Null),
+            resp.get("manifest").unwrap_or(&Value::Null),
+        ) else {
+            continue;
+        };
+        let mtime = storage::modified(&resp_path).unwrap_or(std::time::UNIX_EPOCH);
+        runs.push((mtime, run));
+    }
+    runs.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then_with(|| a.1.receipt_id.cmp(&b.1.receipt_id))
+    });
+    runs.into_iter().map(|(_, r)| r).collect()
+}
+
+fn read_log(path: &str) -> Option<String> {
+    let raw = storage::read(Path::new(path)).ok()?;
+    if path.ends_with(".gz") {
+        let mut s = String::new();
+        GzDecoder::new(raw.as_slice()).read_to_string(&mut s).ok()?;
+        Some(s)
+    } else {
+        Some(String::from_utf8_lossy(&raw).into_owned())
+    }
+}
+
+/// Binary search over `reds` (oldest first) for the first run whose log has `signature`.
+fn bisect(reds: &[RunView], green: Option<&RunView>, signature: &str) -> Option<Bisect> {
+    let has = |v: &RunView| {
+        v.log_path
+            .as_deref()
+            .and_then(read_log)
+            .is_some_and(|log| log.contains(signature))
+    };
+    let (mut lo, mut hi) = (0usize, reds.len().checked_sub(1)?);
+    let mut logs_read = 0;
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        logs_read += 1;
+        if has(&reds[mid]) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    let last_good = match lo {
+        0 => green.map(RunRef::of),
+        i => Some(RunRef::of(&reds[i - 1])),
+    };
+    Some(Bisect {
+        first_bad: RunRef::of(&reds[lo]),
+        last_good,
+        signature: signature.to_string(),
+        logs_read,
+    })
+}
+
+fn suspects(r: &RegressionReport) -> Vec<String> {
+    let mut out = Vec::new();
+    if let Some(b) = &r.bisect {
+        out.push(format!(
+            "first log with {:?}: run {}",
+            b.signature, b.first_bad.run_id
+        ));
+    }
+    for c in r.environment_diff.iter().chain(&r.inputs_diff) {
+        out.push(format!("{} changed: {} -> {}", c.path, c.before, c.after));
+    }
+    for p in &r.build_diff.newly_failing_packages {
+        out.push(format!("package {} started failing", p));
+    }
+    if r.last_green.is_none() {
+        out.push("no green run of this goal on record".to_string());
+    }
+    out
+}
+
+/// Compare `failing` with the stored runs of `goal_id`. Reads every receipt of the goal, so
+/// call it off the async workers.
+pub fn analyze(goal_id: &str, failing: &RunView, with_bisect: bool) -> RegressionReport {
+    analyze_with(goal_id, failing, &stored_runs(goal_id), with_bisect)
+}
+
+fn analyze_with(
+    goal_id: &str,
+    failing: &RunView,
+    history: &[RunView],
+    with_bisect: bool,
+) -> RegressionReport {
+    let green_at = history.iter().rposition(|r| r.success);
+    let green = green_at.map(|i| &history[i]);
+    let mut reds: Vec<RunView> = history[green_at.map_or(0, |i| i + 1)..]
+        .iter()
+        .filter(|r| !r.success && r.run_id != failing.run_id)
+        .cloned()
+        .collect();
+    let reds_since_green = reds.len();
+    reds.push(failing.clone());
+    let signature = failing.build_report.as_ref().and_then(|b| {
+        b.error_samples
+            .first()
+            .or_else(|| b.failing_tests.first())
+            .cloned()
+    });
+    let mut report = RegressionReport {
+        goal_id: goal_id.to_string(),
+        failing: RunRef::of(failing),
+        last_green: green.map(RunRef::of),
+        reds_since_green,
+        inputs_diff: green
+            .map(|g| json_diff(&g.inputs, &failing.inputs))
+            .unwrap_or_default(),
+        environment_diff: green
+            .map(|g| json_diff(&g.environment, &failing.environment))
+            .unwrap_or_default(),
+        build_diff: build_diff(
+            green.and_then(|g| g.build_report.as_ref()),
+            failing.build_report.as_ref(),
+        ),
+        bisect: signature
+            .filter(|_| with_bisect)
+            .and_then(|s| bisect(&reds, green, &s)),
+        suspects: Vec::new(),
+    };
+    report.suspects = suspects(&report);
+    report
+}
+
+fn markdown(r: &RegressionReport) -> String {
+    let mut md = format!("# Regression report: {}\n\n", r.goal_id);
+    md.push_str(&format!("- failing run: `{}`\n", r.failing.run_id));
+    match &r.last_green {
+        Some(g) => md.push_str(&format!(
+            "- last green: `{}` (receipt `{}`), {} red run(s) since\n",
+            g.run_id, g.receipt_id, r.reds_since_green
+        )),
+        None => md.push_str("- last green: none on record\n"),
+    }
+    md.push_str("\n## Suspects\n");
+    for s in &r.suspects {
+        md.push_str(&format!("- {}\n", s));
+    }
+    let d = &r.build_diff;
+    md.push_str(&format!(
+        "\n## Build\n- errors: {} -> {}\n- warnings: {} -> {}\n",
+        d.errors_before, d.errors_after, d.warnings_before, d.warnings_after
+    ));
+    for e in &d.new_errors {
+        md.push_str(&format!("- new error: `{}`\n", e));
+    }
+    for t in &d.newly_failing_tests {
+        md.push_str(&format!("- newly failing test: `{}`\n", t));
+    }
+    md
+}
+
+/// Write `report` under `runs/regressions/`, named by the failing run's receipt (its run id
+/// when it has none); the evidence entry that links it.
+pub fn write(report: &RegressionReport) -> anyhow::Result<(PathBuf, Value)> {
+    let dir = meta3_root().join("runs/regressions");
+    let id = match report.failing.receipt_id.as_str() {
+        "" => &report.failing.run_id,
+        receipt_id => receipt_id,
+    };
+    storage::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let json_path = dir.join(format!("{}.json", id));
+    atomic::write(
+        &json_path,
+        serde_json::to_string_pretty(report).unwrap_or_default(),
+    )
+    .with_context(|| format!("failed to write {}", json_path.display()))?;
+    atomic::write(dir.join(format!("{}.md", id)), markdown(report))
+        .with_context(|| format!("failed to write regression markdown for {}", id))?;
+    let mut link = Map::new();
+    link.insert(
+        "report_url".into(),
+        json!(urls::url_for(&format!("/runs/regressions/{}.json", id))),
+    );
+    link.insert(
+        "summary_url".into(),
+        json!(urls::url_for(&format!("/runs/regressions/{}.md", id))),
+    );
+    link.insert("last_green".into(), json!(report.last_green));
+    link.insert(
+        "first_bad".into(),
+        json!(report.bisect.as_ref().map(|b| &b.first_bad)),
+    );
+    link.insert(
+        "suspects".into(),
+        json!(report.suspects.iter().take(3).collect::<Vec<_>>()),
+    );
+    Ok((json_path, Value::Object(link)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(id: &str, success: bool, cmd: &str, errors: &[&str]) -> RunView {
+        RunView {
+            receipt_id: format!("rc-{}", id),
+            run_id: id.to_string(),
+            inputs: json!({"repo_path": "meta3", "__run_id": id}),
+            environment: json!({"build_cmd": cmd}),
+            success,
+            build_report: Some(BuildReport {
+                error_samples: errors.iter().map(|e| e.to_string()).collect(),
+                errors: errors.len(),
+                ..Default::default()
+            }),
+            log_path: None,
+        }
+    }
+
+    #[test]
+    fn red_run_is_compared_with_the_last_green_one() {
+        let history = vec![
+            run("r-1", true, "make", &[]),
+            run("r-2", true, "make -j8", &[]),
+            run("r-3", false, "make -j8", &["error: x"]),
+        ];
+        let failing = run("r-4", false, "make -j8", &["error: x", "error: y"]);
+        let r = analyze_with("meta3.build", &failing, &history, false);
+        assert_eq!(r.last_green.unwrap().run_id, "r-2");
+        assert_eq!(r.reds_since_green, 1);
+        assert!(r.inputs_diff.is_empty());
+        assert!(r.environment_diff.is_empty());
+        assert_eq!(r.build_diff.new_errors, vec!["error: x", "error: y"]);
+
+        let r = analyze_with("meta3.build", &failing, &history[..1], false);
+        assert_eq!(
+            r.environment_diff,
+            vec![Change {
+                path: "/build_cmd".into(),
+                before: json!("make"),
+                after: json!("make -j8"),
+            }]
+        );
+        assert!(r.suspects[0].starts_with("/build_cmd changed"));
+    }
+
+    #[test]
+    fn bisect_finds_the_first_red_log_with_the_error() {
+        let dir = std::env::temp_dir().join(format!("regression-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let green = run("r-0", true, "make", &[]);
+        let reds: Vec<RunView> = (1..=6)
+            .map(|i| {
+                let log = dir.join(format!("{}.log", i));
+                let text = if i >= 4 {
+                    "error: x\n"
+                } else {
+                    "error: other\n"
+                };
+                std::fs::write(&log, text).unwrap();
+                RunView {
+                    log_path: Some(log.display().to_string()),
+                    ..run(&format!("r-{}", i), false, "make", &["error: x"])
+                }
+            })
+            .collect();
+        let b = bisect(&reds, Some(&green), "error: x").unwrap();
+        assert_eq!(b.first_bad.run_id, "r-4");
+        assert_eq!(b.last_good.unwrap().run_id, "r-3");
+        assert!(b.logs_read <= 3);
+
+        // New in the earliest red run: the last good one is the green run.
+        let b = bisect(&reds[3..], Some(&green), "error: x").unwrap();
+        assert_eq!(b.first_bad.run_id, "r-4");
+        assert_eq!(b.last_good.unwrap().run_id, "r-0");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
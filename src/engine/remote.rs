@@ -0,0 +1,338 @@
+//! Remote execution targets: goals that accept a `remote` input (`meta3.build`) run their
+//! command on another machine over OpenSSH instead of on the engine's host.
+//!
+//! Targets are named in the policies file; a run can only pick one of these names:
+//!
+//! ```yaml
+//! remote_hosts:
+//!   buildbox:
+//!     host: build-01.internal
+//!     user: ci                     # default: ssh's own default
+//!     port: 22
+//!     identity_file: /etc/one-engine/buildbox_ed25519
+//!     workspace: /srv/meta3        # the remote checkout
+//!     sync: checkout               # checkout | upload | git
+//!     artifacts: ["logs/*.log", "reports/junit.xml"]
+//! ```
+//!
+//! `sync` says how the workspace matches the local repo before the command runs: `checkout`
+//! uses the remote checkout as it is, `upload` rsyncs the local repo into it (skipping
+//! `exclude`, by default `.git`, `node_modules` and `target`), and `git` fetches and checks out
+//! the local repo's `HEAD` commit there. After the command, each `artifacts` path (relative to
+//! the workspace; globs expand remotely) is copied back under the run's local log directory.
+//!
+//! `ssh`, `rsync` and `git` run as ordinary executor commands, so they go through the
+//! capability gate, the policy's `time_ms` and test harness fakes. The remote side is assumed
+//! to be POSIX; `BatchMode` keeps ssh from waiting on a password prompt.
+
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::executor::{self, Action};
+use super::platform::{self, Shell};
+use super::types::{ExecutorBackend, Policy};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    #[default]
+    Checkout,
+    Upload,
+    Git,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoteHost {
+    pub host: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    pub workspace: String,
+    #[serde(default)]
+    pub sync: SyncMode,
+    #[serde(default = "default_exclude")]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+fn default_exclude() -> Vec<String> {
+    vec![".git".into(), "node_modules".into(), "target".into()]
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesRemoteHosts {
+    #[serde(default)]
+    remote_hosts: HashMap<String, RemoteHost>,
+}
+
+/// The `remote_hosts:` entry called `name`.
+pub fn lookup(name: &str) -> anyhow::Result<RemoteHost> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let hosts = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesRemoteHosts>(&raw).ok())
+        .map(|p| p.remote_hosts)
+        .unwrap_or_default();
+    hosts.get(name).cloned().ok_or_else(|| {
+        anyhow!(
+            "unknown remote host {:?} (not in remote_hosts of {})",
+            name,
+            path
+        )
+    })
+}
+
+/// What a remote run did besides its command, for the receipt.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RemoteRun {
+    pub name: String,
+    pub host: String,
+    pub workspace: String,
+    pub sync: SyncMode,
+    /// Commit checked out by `sync: git`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Local copies of the fetched artifacts.
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+    /// Artifact patterns that could not be fetched.
+    #[serde(default)]
+    pub missing: Vec<String>,
+}
+
+impl RemoteHost {
+    fn destination(&self) -> String {
+        match &self.user {
+            Some(u) => format!("{}@{}", u, self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    fn ssh_opts(&self) -> Vec<String> {
+        let mut opts = vec!["-o".to_string(), "BatchMode=yes".to_string()];
+        if let Some(p) = self.port {
+            opts.extend(["-p".to_string(), p.to_string()]);
+        }
+        if let Some(id) = &self.identity_file {
+            opts.extend(["-i".to_string(), id.clone()]);
+        }
+        opts
+    }
+
+    /// A command for `sh` (the host shell) running `script` in the remote workspace.
+    pub fn ssh(&self, sh: Shell, script: &str) -> String {
+        let remote = Shell::Sh.in_dir(&self.workspace, script);
+        let mut args = self.ssh_opts();
+        args.push(self.destination());
+        args.push(remote);
+        sh.argv("ssh", &args)
+    }
+
+    /// The `-e` transport for rsync.
+    fn rsync_shell(&self) -> String {
+        Shell::Sh.argv("ssh", &self.ssh_opts())
+    }
+
+    fn remote_path(&self, rel: &str) -> String {
+        format!(
+            "{}:{}/{}",
+            self.destination(),
+            self.workspace.trim_end_matches('/'),
+            rel
+        )
+    }
+
+    fn upload(&self, sh: Shell, repo: &str) -> String {
+        let mut args = vec!["-az".to_string(), "--delete".to_string()];
+        for e in &self.exclude {
+            args.push(format!("--exclude={}", e));
+        }
+        args.extend([
+            "-e".to_string(),
+            self.rsync_shell(),
+            format!("{}/", platform::slash_path(repo).trim_end_matches('/')),
+            self.remote_path(""),
+        ]);
+        sh.argv("rsync", &args)
+    }
+
+    fn fetch(&self, sh: Shell, pattern: &str, dest: &Path) -> String {
+        let args = vec![
+            "-az".to_string(),
+            "-e".to_string(),
+            self.rsync_shell(),
+            self.remote_path(pattern),
+            format!("{}/", dest.display()),
+        ];
+        sh.argv("rsync", &args)
+    }
+}
+
+/// Transport commands run on the host even when the run's commands go to a container.
+fn host_policy(policy: &Policy) -> Policy {
+    Policy {
+        executor: ExecutorBackend::Host,
+        ..policy.clone()
+    }
+}
+
+async fn run(cmd: String, policy: &Policy) -> anyhow::Result<executor::ExecResult> {
+    executor::execute(Action::Cli(cmd), &host_policy(policy)).await
+}
+
+/// Run `script` in `target`'s workspace.
+pub async fn execute(
+    target: &RemoteHost,
+    script: &str,
+    policy: &Policy,
+) -> anyhow::Result<executor::ExecResult> {
+    run(target.ssh(platform::shell(), script), policy).await
+}
+
+/// Bring the remote workspace in line with `repo` per `target.sync`.
+pub async fn sync(
+    name: &str,
+    target: &RemoteHost,
+    repo: &str,
+    policy: &Policy,
+) -> anyhow::Result<RemoteRun> {
+    let mut out = RemoteRun {
+        name: name.to_string(),
+        host: target.host.clone(),
+        workspace: target.workspace.clone(),
+        sync: target.sync,
+        ..Default::default()
+    };
+    match target.sync {
+        SyncMode::Checkout => {}
+        SyncMode::Upload => {
+            let res = run(target.upload(platform::shell(), repo), policy).await?;
+            if !res.ok {
+                return Err(anyhow!("upload to {} failed: {}", name, res.stderr.trim()));
+            }
+        }
+        SyncMode::Git => {
+            let sh = platform::shell();
+            let head = run(
+                sh.argv(
+                    "git",
+                    &["-C".into(), repo.into(), "rev-parse".into(), "HEAD".into()],
+                ),
+                policy,
+            )
+            .await?;
+            let rev = head.stdout.trim().to_string();
+            if !head.ok || rev.is_empty() {
+                return Err(anyhow!(
+                    "cannot read HEAD of {}: {}",
+                    repo,
+                    head.stderr.trim()
+                ));
+            }
+            let checkout = Shell::Sh.and(
+                "git fetch --quiet origin",
+                &Shell::Sh.argv(
+                    "git",
+                    &[
+                        "checkout".into(),
+                        "--quiet".into(),
+                        "--force".into(),
+                        rev.clone(),
+                    ],
+                ),
+            );
+            let res = run(target.ssh(sh, &checkout), policy)
+                .await
+                .with_context(|| format!("checking out {} on {}", rev, name))?;
+            if !res.ok {
+                return Err(anyhow!(
+                    "checkout of {} on {} failed: {}",
+                    rev,
+                    name,
+                    res.stderr.trim()
+                ));
+            }
+            out.rev = Some(rev);
+        }
+    }
+    Ok(out)
+}
+
+/// Copy `target.artifacts` into `dest`; patterns that fail are listed in `run.missing`.
+pub async fn fetch_artifacts(
+    target: &RemoteHost,
+    dest: &Path,
+    policy: &Policy,
+    run: &mut RemoteRun,
+) {
+    if target.artifacts.is_empty() {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(dest) {
+        tracing::warn!("cannot create {}: {}", dest.display(), e);
+        run.missing = target.artifacts.clone();
+        return;
+    }
+    for pattern in &target.artifacts {
+        match self::run(target.fetch(platform::shell(), pattern, dest), policy).await {
+            Ok(res) if res.ok => {}
+            _ => run.missing.push(pattern.clone()),
+        }
+    }
+    run.artifacts = walk(dest);
+}
+
+fn walk(dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return files;
+    };
+    for e in entries.flatten() {
+        let p = e.path();
+        if p.is_dir() {
+            files.extend(walk(&p));
+        } else {
+            files.push(p.display().to_string());
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buildbox() -> RemoteHost {
+        RemoteHost {
+            host: "build-01".into(),
+            user: Some("ci".into()),
+            port: Some(2222),
+            identity_file: None,
+            workspace: "/srv/meta3".into(),
+            sync: SyncMode::Upload,
+            exclude: default_exclude(),
+            artifacts: vec![],
+        }
+    }
+
+    #[test]
+    fn commands_spell_the_destination_and_transport() {
+        let t = buildbox();
+        assert_eq!(
+            t.ssh(Shell::Bash, "make 2>&1"),
+            "ssh -o BatchMode=yes -p 2222 'ci@build-01' 'cd /srv/meta3 && make 2>&1'"
+        );
+        assert_eq!(
+            t.upload(Shell::Bash, "/home/me/meta3/"),
+            "rsync -az --delete --exclude=.git --exclude=node_modules --exclude=target -e 'ssh -o BatchMode=yes -p 2222' /home/me/meta3/ 'ci@build-01:/srv/meta3/'"
+        );
+    }
+}
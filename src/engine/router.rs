@@ -3,6 +3,8 @@ use reqwest::{Client, StatusCode};
 use serde_json::{json, Value};
 use std::time::Duration;
 
+use crate::integrations::http_cassette;
+
 // Defaults are set for OpenRouter; override via ROUTER_URL / OPENROUTER_URL and ROUTER_MODEL / OPENROUTER_MODEL.
 const DEFAULT_URL: &str = "https://openrouter.ai/api/v1/chat/completions";
 const DEFAULT_MODEL: &str = "moonshotai/kimi-k2";
@@ -23,7 +25,8 @@ fn api_url() -> String {
     first_env(&["ROUTER_URL", "OPENROUTER_URL"]).unwrap_or_else(|| DEFAULT_URL.to_string())
 }
 
-fn model_name() -> String {
+/// The model chat turns go to.
+pub fn model_name() -> String {
     first_env(&["ROUTER_MODEL", "OPENROUTER_MODEL"]).unwrap_or_else(|| DEFAULT_MODEL.to_string())
 }
 
@@ -39,15 +42,76 @@ fn timeout_secs() -> u64 {
         .unwrap_or(60)
 }
 
-pub async fn chat(system: &str, user: &str) -> Result<Value> {
+/// Per-call sampling knobs (unset fields use the provider defaults).
+#[derive(Debug, Clone, Default)]
+pub struct ChatOpts {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Model for this call instead of `ROUTER_MODEL` (e.g. a small one for summaries).
+    pub model: Option<String>,
+}
+
+/// Parsed reply plus provider-reported token usage (when available).
+#[derive(Debug, Clone)]
+pub struct ChatOutcome {
+    pub value: Value,
+    pub total_tokens: Option<u64>,
+}
+
+/// Connectivity probe for deep health checks: `GET <base>/models` with a short timeout.
+/// Returns the HTTP status (any response means reachable); `None` when no key is set.
+pub async fn probe(timeout: Duration) -> Option<Result<u16>> {
+    if super::simulation::active() {
+        return Some(Ok(200));
+    }
+    let key = api_key().ok()?;
     let url = api_url();
-    let model = model_name();
-    let key = api_key()?;
+    let models = format!(
+        "{}/models",
+        url.strip_suffix("/chat/completions").unwrap_or(&url)
+    );
+    let res = async {
+        let client = Client::builder().timeout(timeout).build()?;
+        let resp = client.get(&models).bearer_auth(key).send().await?;
+        Ok::<_, anyhow::Error>(resp.status().as_u16())
+    }
+    .await;
+    Some(res)
+}
+
+/// POST `payload` to the router and return the response body. Goes through the HTTP
+/// cassettes (`integrations::http_cassette`), so a replay needs no API key; in simulation
+/// mode a request with no recording gets a synthetic reply (see `engine::simulation`).
+async fn post(payload: &Value) -> Result<Value> {
     let client = Client::builder()
         .timeout(Duration::from_secs(timeout_secs()))
         .build()?;
+    let req = client.post(api_url()).json(payload).build()?;
+    let authorize = |req: reqwest::Request| {
+        let key = api_key()?;
+        let mut req = req;
+        req.headers_mut().insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", key).parse()?,
+        );
+        Ok(req)
+    };
+    let reply = match http_cassette::execute_authorized("router", &client, req, authorize).await {
+        Err(e) if super::simulation::active() && e.is::<http_cassette::Unrecorded>() => {
+            return Ok(super::simulation::synthetic_chat());
+        }
+        reply => reply?,
+    };
+    let body = reply.json()?;
+    if reply.status != StatusCode::OK.as_u16() {
+        return Err(anyhow!("router error {}: {}", reply.status, body));
+    }
+    Ok(body)
+}
 
-    let payload = json!({
+pub async fn chat_opts(system: &str, user: &str, opts: &ChatOpts) -> Result<ChatOutcome> {
+    let model = opts.model.clone().unwrap_or_else(model_name);
+    let mut payload = json!({
       "model": model,
       "messages": [
         {"role": "system", "content": system},
@@ -55,63 +119,49 @@ pub async fn chat(system: &str, user: &str) -> Result<Value> {
       ],
       "response_format": {"type": "json_object"}
     });
-    let resp = client
-        .post(&url)
-        .bearer_auth(key)
-        .json(&payload)
-        .send()
-        .await?;
-    let status = resp.status();
-    let body = resp.json::<Value>().await?;
-    if status != StatusCode::OK {
-        return Err(anyhow!("router error {}: {}", status, body));
+    if let Some(t) = opts.temperature {
+        payload["temperature"] = json!(t);
+    }
+    if let Some(m) = opts.max_tokens {
+        payload["max_tokens"] = json!(m);
     }
+    let body = post(&payload).await?;
     let content = body
         .pointer("/choices/0/message/content")
         .and_then(|v| v.as_str())
         .unwrap_or("{}");
     let parsed =
         serde_json::from_str::<Value>(content).unwrap_or_else(|_| json!({"reply": content}));
-    Ok(parsed)
+    Ok(ChatOutcome {
+        value: parsed,
+        total_tokens: body.pointer("/usage/total_tokens").and_then(|v| v.as_u64()),
+    })
 }
 
-pub async fn chat_messages(mut messages: Vec<Value>) -> Result<Value> {
-    let url = api_url();
-    let model = model_name();
-    let key = api_key()?;
-
-    if messages.is_empty() {
-        messages.push(json!({"role": "user", "content": ""}));
-    }
-
-    let client = Client::builder()
-        .timeout(Duration::from_secs(timeout_secs()))
-        .build()?;
+/// JSON body of an assistant message (plain text becomes `{"reply": ...}`).
+pub fn parse_content(message: &Value) -> Value {
+    let content = message
+        .get("content")
+        .and_then(|v| v.as_str())
+        .unwrap_or("{}");
+    serde_json::from_str::<Value>(content).unwrap_or_else(|_| json!({"reply": content}))
+}
 
-    let payload = json!({
+/// One function-calling turn: `tools` are OpenAI-style function definitions. Returns the
+/// raw assistant message, which carries either `tool_calls` or a final `content`.
+pub async fn chat_turn(messages: &[Value], tools: &[Value]) -> Result<Value> {
+    let model = model_name();
+    let mut payload = json!({
         "model": model,
         "messages": messages,
         "response_format": {"type": "json_object"}
     });
-
-    let resp = client
-        .post(&url)
-        .bearer_auth(key)
-        .json(&payload)
-        .send()
-        .await?;
-
-    let status = resp.status();
-    let body = resp.json::<Value>().await?;
-    if status != StatusCode::OK {
-        return Err(anyhow!("router error {}: {}", status, body));
+    if !tools.is_empty() {
+        payload["tools"] = json!(tools);
+        payload["tool_choice"] = json!("auto");
     }
-
-    let content = body
-        .pointer("/choices/0/message/content")
-        .and_then(|v| v.as_str())
-        .unwrap_or("{}");
-    let parsed =
-        serde_json::from_str::<Value>(content).unwrap_or_else(|_| json!({"reply": content}));
-    Ok(parsed)
+    let body = post(&payload).await?;
+    body.pointer("/choices/0/message")
+        .cloned()
+        .ok_or_else(|| anyhow!("router reply has no message: {}", body))
 }
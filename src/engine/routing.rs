@@ -0,0 +1,370 @@
+//! Bits-aware routing: a goal family runs as one of its variants, picked from the current bits.
+//!
+//! ```yaml
+//! goal_variants:
+//!   - family: meta3.build
+//!     variants:
+//!       - goal: meta3.build.fast
+//!         cost: 1
+//!         risk: 0.1
+//!         dry: true
+//!         inputs: {dry_run: true}   # defaults the variant adds; the caller's inputs win
+//!       - goal: meta3.build.full
+//!         cost: 5
+//!         risk: 0.6
+//!     uncertainty_high: 0.5        # default 0.5
+//! ```
+//!
+//! A run of the family id (or `user:<id>.<family>`) is routed; a run of a variant id is not.
+//! Flags apply to both: a family that is switched off doesn't route, and the chosen variant is
+//! checked again. The current bits are the engine's own, never the caller's: the mean over the
+//! family's last [`HISTORY_RUNS`] receipts of the past week, else the initial bits
+//! (`u` 0, `t` 0.5). The variant is chosen from them and the run's `max_risk`:
+//! - risk budget = `max_risk × 2t × (1 − u)`, capped at 1: trust widens it, uncertainty shrinks it;
+//! - variants whose `risk` exceeds the budget are out, except dry ones;
+//! - when `u ≥ uncertainty_high` the cheapest remaining variant wins, dry ones first;
+//! - otherwise the riskiest remaining one (the fullest path allowed), then the cheapest;
+//! - when none is left, the least risky variant runs.
+//!
+//! The decision and every alternative, with the reason it lost, go to `evidence.routing`.
+
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use super::types::Policy;
+use crate::integrations::{run_index, telemetry};
+
+/// Receipts averaged for a family's current bits.
+pub const HISTORY_RUNS: usize = 5;
+/// How long the recent-bits scan is reused.
+const HISTORY_TTL: Duration = Duration::from_secs(60);
+
+fn default_uncertainty_high() -> f32 {
+    0.5
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalVariant {
+    pub goal: String,
+    /// Relative cost; only compared between the family's variants.
+    #[serde(default)]
+    pub cost: f32,
+    /// 0–1, compared with the run's risk budget.
+    #[serde(default)]
+    pub risk: f32,
+    /// Changes nothing outside its run directory; always eligible.
+    #[serde(default)]
+    pub dry: bool,
+    /// Inputs the variant runs with unless the caller set them.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub inputs: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalFamily {
+    pub family: String,
+    pub variants: Vec<GoalVariant>,
+    #[serde(default = "default_uncertainty_high")]
+    pub uncertainty_high: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BitsSource {
+    History,
+    Default,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Alternative {
+    pub goal: String,
+    pub cost: f32,
+    pub risk: f32,
+    pub dry: bool,
+    pub eligible: bool,
+    /// Why it was not chosen; absent for the chosen variant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<String>,
+}
+
+/// `evidence.routing` of a routed run.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct RouteDecision {
+    pub family: String,
+    /// The goal id that ran (a `user:<id>.` prefix is carried over).
+    pub goal_id: String,
+    pub u: f32,
+    pub t: f32,
+    pub bits_source: BitsSource,
+    pub max_risk: f32,
+    pub risk_budget: f32,
+    pub reason: String,
+    pub alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesGoalVariants {
+    #[serde(default)]
+    goal_variants: Vec<GoalFamily>,
+}
+
+/// The `goal_variants:` families of the policies file.
+pub fn families() -> Vec<GoalFamily> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesGoalVariants>(&raw) {
+            Ok(p) => p
+                .goal_variants
+                .into_iter()
+                .filter(|f| !f.variants.is_empty())
+                .collect(),
+            Err(e) => {
+                tracing::warn!("invalid goal_variants in {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+fn split_user(goal_id: &str) -> (&str, &str) {
+    match goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.'))
+    {
+        Some((user, bare)) => (&goal_id[..5 + user.len() + 1], bare),
+        None => ("", goal_id),
+    }
+}
+
+fn bits_from(v: &Value) -> Option<(f32, f32)> {
+    let u = v.get("u")?.as_f64()?;
+    let t = v.get("t")?.as_f64()?;
+    Some((u.clamp(0.0, 1.0) as f32, t.clamp(0.0, 1.0) as f32))
+}
+
+type History = (Instant, Arc<Vec<run_index::RunRecord>>);
+
+static HISTORY: Lazy<Mutex<Option<History>>> = Lazy::new(|| Mutex::new(None));
+
+/// The last week's receipts, rescanned at most every [`HISTORY_TTL`]. The lock is only held
+/// to read or swap the cache: concurrent runs may scan twice but never wait on each other.
+async fn history() -> Arc<Vec<run_index::RunRecord>> {
+    let cached = HISTORY
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .filter(|(at, _)| at.elapsed() <= HISTORY_TTL)
+        .map(|(_, records)| records.clone());
+    if let Some(records) = cached {
+        return records;
+    }
+    let since = chrono::Utc::now() - chrono::Duration::days(7);
+    let records = Arc::new(run_index::scan(Some(since)).await);
+    *HISTORY.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), records.clone()));
+    records
+}
+
+/// Mean `(u, t)` of the family's latest receipts that carry bits.
+async fn recent_bits(family: &GoalFamily) -> Option<(f32, f32)> {
+    let records = history().await;
+    let in_family = |goal_id: &str| {
+        let bare = split_user(goal_id).1;
+        bare == family.family || family.variants.iter().any(|v| v.goal == bare)
+    };
+    let mut recent: Vec<&run_index::RunRecord> =
+        records.iter().filter(|r| in_family(&r.goal_id)).collect();
+    recent.sort_by_key(|r| std::cmp::Reverse(r.ts));
+    let bits: Vec<(f32, f32)> = recent
+        .iter()
+        .filter_map(|r| r.bits.as_ref().and_then(bits_from))
+        .take(HISTORY_RUNS)
+        .collect();
+    if bits.is_empty() {
+        return None;
+    }
+    let n = bits.len() as f32;
+    let (u, t) = bits.iter().fold((0.0, 0.0), |(u, t), b| (u + b.0, t + b.1));
+    Some((u / n, t / n))
+}
+
+/// Pick the variant of `family` for bits `(u, t)` under `max_risk`.
+fn choose(
+    family: &GoalFamily,
+    u: f32,
+    t: f32,
+    max_risk: f32,
+) -> (usize, f32, String, Vec<Alternative>) {
+    let budget = (max_risk * 2.0 * t * (1.0 - u)).clamp(0.0, 1.0);
+    let cautious = u >= family.uncertainty_high;
+    let eligible: Vec<bool> = family
+        .variants
+        .iter()
+        .map(|v| v.dry || v.risk <= budget)
+        .collect();
+    let by_cost = |a: &GoalVariant, b: &GoalVariant| a.cost.total_cmp(&b.cost);
+    let candidates = || {
+        family
+            .variants
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| eligible[*i])
+    };
+    let (chosen, reason) = if cautious {
+        let i = candidates()
+            .min_by(|(_, a), (_, b)| b.dry.cmp(&a.dry).then(by_cost(a, b)))
+            .map(|(i, _)| i);
+        (
+            i,
+            format!(
+                "u {:.2} ≥ {:.2}: cheapest eligible variant",
+                u, family.uncertainty_high
+            ),
+        )
+    } else {
+        let i = candidates()
+            .max_by(|(_, a), (_, b)| a.risk.total_cmp(&b.risk).then(by_cost(b, a)))
+            .map(|(i, _)| i);
+        (
+            i,
+            format!("t {:.2}: riskiest variant within budget {:.2}", t, budget),
+        )
+    };
+    let (chosen, reason) = match chosen {
+        Some(i) => (i, reason),
+        None => {
+            let i = (0..family.variants.len())
+                .min_by(|a, b| {
+                    family.variants[*a]
+                        .risk
+                        .total_cmp(&family.variants[*b].risk)
+                })
+                .unwrap_or(0);
+            (
+                i,
+                format!("no variant within budget {:.2}: least risky", budget),
+            )
+        }
+    };
+    let alternatives = family
+        .variants
+        .iter()
+        .enumerate()
+        .map(|(i, v)| Alternative {
+            goal: v.goal.clone(),
+            cost: v.cost,
+            risk: v.risk,
+            dry: v.dry,
+            eligible: eligible[i],
+            rejected: (i != chosen).then(|| {
+                if !eligible[i] {
+                    format!("risk {:.2} over budget {:.2}", v.risk, budget)
+                } else if cautious {
+                    "costlier under high uncertainty".to_string()
+                } else {
+                    "less thorough than the chosen variant".to_string()
+                }
+            }),
+        })
+        .collect();
+    (chosen, budget, reason, alternatives)
+}
+
+/// Whether `goal_id` (maybe `user:<id>.`-prefixed) names a `goal_variants:` family.
+pub fn is_family(goal_id: &str) -> bool {
+    let bare = split_user(goal_id).1;
+    families().iter().any(|f| f.family == bare)
+}
+
+/// Route a run of a family id to one of its variants; `inputs` gains the variant's defaults.
+/// `None` (and `inputs` unchanged) when `goal_id` is no family.
+pub async fn route(goal_id: &str, inputs: &mut Value, policy: &Policy) -> Option<RouteDecision> {
+    let (prefix, bare) = split_user(goal_id);
+    let family = families().into_iter().find(|f| f.family == bare)?;
+    let (u, t, bits_source) = match recent_bits(&family).await {
+        Some((u, t)) => (u, t, BitsSource::History),
+        None => (0.0, 0.5, BitsSource::Default),
+    };
+    let (i, risk_budget, reason, alternatives) = choose(&family, u, t, policy.max_risk);
+    let variant = &family.variants[i];
+    if let (Some(obj), Some(defaults)) = (inputs.as_object_mut(), variant.inputs.as_object()) {
+        for (k, v) in defaults {
+            obj.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+    }
+    let decision = RouteDecision {
+        family: family.family.clone(),
+        goal_id: format!("{}{}", prefix, variant.goal),
+        u,
+        t,
+        bits_source,
+        max_risk: policy.max_risk,
+        risk_budget,
+        reason,
+        alternatives,
+    };
+    tracing::info!(
+        "routed {} to {} ({})",
+        goal_id,
+        decision.goal_id,
+        decision.reason
+    );
+    telemetry::emit(
+        "engine",
+        "goal_routed",
+        inputs.get("__run_id").and_then(|v| v.as_str()),
+        json!({"family": decision.family, "goal_id": decision.goal_id, "u": u, "t": t}),
+    );
+    Some(decision)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn family() -> GoalFamily {
+        serde_yaml::from_str(
+            r#"
+family: meta3.build
+variants:
+  - {goal: meta3.build.fast, cost: 1, risk: 0.1, dry: true}
+  - {goal: meta3.build.full, cost: 5, risk: 0.3}
+  - {goal: meta3.build.deploy, cost: 8, risk: 0.9}
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn bits_pick_cheap_variants_when_uncertain_and_riskier_ones_when_trusted() {
+        let f = family();
+        // Initial bits under max_risk 0.2: budget 0.2 leaves only the dry variant.
+        let (i, budget, _, alts) = choose(&f, 0.0, 0.5, 0.2);
+        assert_eq!(
+            (f.variants[i].goal.as_str(), budget),
+            ("meta3.build.fast", 0.2)
+        );
+        assert!(!alts[1].eligible && alts[1].rejected.is_some());
+        assert!(alts[0].rejected.is_none());
+
+        // High trust widens the budget to the full build, but not the deploy.
+        let (i, _, _, _) = choose(&f, 0.0, 0.9, 0.2);
+        assert_eq!(f.variants[i].goal, "meta3.build.full");
+
+        // High uncertainty takes the cheapest even when the budget allows more.
+        let (i, _, reason, _) = choose(&f, 0.6, 1.0, 1.0);
+        assert_eq!(f.variants[i].goal, "meta3.build.fast");
+        assert!(reason.contains("cheapest"));
+
+        assert_eq!(
+            split_user("user:demo.meta3.build"),
+            ("user:demo.", "meta3.build")
+        );
+    }
+}
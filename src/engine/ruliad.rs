@@ -0,0 +1,269 @@
+//! Bounded, resumable multiway exploration for `ruliad.kernel`.
+//!
+//! States are expanded breadth-first, each at most once. Successors are deduplicated by a
+//! canonical key (`dedup`), and exploration stops at the requested depth or when `max_states` /
+//! `max_edges` would be exceeded. The unexpanded frontier is persisted as `frontier.json` so a
+//! later run with the same run_id continues deeper instead of recomputing.
+
+use anyhow::{bail, Context, Result};
+use one_engine::{atomic, storage};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+
+pub const FRONTIER_FILE: &str = "frontier.json";
+
+/// How two strings are considered the same state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Dedup {
+    /// Byte-identical strings only.
+    Exact,
+    /// Equal up to cyclic rotation.
+    Rotation,
+    /// Equal up to reversal.
+    Reflection,
+    /// Equal up to rotation and reversal.
+    Dihedral,
+}
+
+impl Dedup {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "exact" => Some(Self::Exact),
+            "rotation" | "cyclic" => Some(Self::Rotation),
+            "reflection" | "reverse" => Some(Self::Reflection),
+            "dihedral" | "canonical" => Some(Self::Dihedral),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Rotation => "rotation",
+            Self::Reflection => "reflection",
+            Self::Dihedral => "dihedral",
+        }
+    }
+
+    pub fn canonical(&self, s: &str) -> String {
+        match self {
+            Self::Exact => s.to_string(),
+            Self::Rotation => min_rotation(s),
+            Self::Reflection => {
+                let r: String = s.chars().rev().collect();
+                if r.as_str() < s {
+                    r
+                } else {
+                    s.to_string()
+                }
+            }
+            Self::Dihedral => {
+                let a = min_rotation(s);
+                let b = min_rotation(&s.chars().rev().collect::<String>());
+                a.min(b)
+            }
+        }
+    }
+}
+
+fn min_rotation(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    (0..chars.len().max(1))
+        .map(|i| {
+            chars[i..]
+                .iter()
+                .chain(chars[..i].iter())
+                .collect::<String>()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Run ids double as directory names under `runs/ruliad_kernel/`.
+pub fn is_valid_run_id(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 128
+        && !s.contains("..")
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub max_states: usize,
+    pub max_edges: usize,
+}
+
+/// Why `explore` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// Every state shallower than the requested depth has been expanded.
+    Depth,
+    /// No rule applies to any remaining state.
+    Exhausted,
+    MaxStates,
+    MaxEdges,
+}
+
+impl Stop {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Depth => "depth",
+            Self::Exhausted => "exhausted",
+            Self::MaxStates => "max_states",
+            Self::MaxEdges => "max_edges",
+        }
+    }
+
+    pub fn truncated(&self) -> bool {
+        matches!(self, Self::MaxStates | Self::MaxEdges)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Exploration {
+    pub seed: String,
+    pub rules: Vec<(String, String)>,
+    pub dedup: Dedup,
+    /// Representative (first seen) string per state id.
+    pub states: Vec<String>,
+    /// BFS depth at which each state was first reached.
+    pub depth_of: Vec<usize>,
+    /// (src, dst, depth, rule pattern)
+    pub edges: Vec<(usize, usize, usize, String)>,
+    /// State ids not yet expanded, in BFS order.
+    pub pending: VecDeque<usize>,
+    #[serde(skip)]
+    index: HashMap<String, usize>,
+}
+
+impl Exploration {
+    pub fn new(seed: &str, rules: Vec<(String, String)>, dedup: Dedup) -> Self {
+        let mut index = HashMap::new();
+        index.insert(dedup.canonical(seed), 0);
+        Self {
+            seed: seed.to_string(),
+            rules,
+            dedup,
+            states: vec![seed.to_string()],
+            depth_of: vec![0],
+            edges: Vec::new(),
+            pending: VecDeque::from([0]),
+            index,
+        }
+    }
+
+    /// Load a persisted frontier from `dir`, if any. Refuses to resume with different
+    /// seed/rules/dedup since the stored state ids would no longer mean the same thing.
+    pub fn resume(
+        dir: &Path,
+        seed: &str,
+        rules: &[(String, String)],
+        dedup: Dedup,
+    ) -> Result<Option<Self>> {
+        let path = dir.join(FRONTIER_FILE);
+        if !storage::exists(&path) {
+            return Ok(None);
+        }
+        let raw =
+            storage::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+        let mut ex: Exploration =
+            serde_json::from_str(&raw).with_context(|| format!("parse {}", path.display()))?;
+        if ex.seed != seed || ex.rules != rules || ex.dedup != dedup {
+            bail!(
+                "cannot resume {}: seed/rules/dedup differ from the persisted exploration",
+                dir.display()
+            );
+        }
+        ex.index = ex
+            .states
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (ex.dedup.canonical(s), i))
+            .collect();
+        Ok(Some(ex))
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        atomic::write(
+            dir.join(FRONTIER_FILE),
+            serde_json::to_string(self).unwrap_or_default(),
+        )
+        .with_context(|| format!("write {}", FRONTIER_FILE))
+    }
+
+    /// Depth of the shallowest unexpanded state (`None` once exhausted).
+    pub fn frontier_depth(&self) -> Option<usize> {
+        self.pending.front().map(|id| self.depth_of[*id])
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.depth_of.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Expand pending states until every state shallower than `depth` is expanded or a limit
+    /// would be exceeded. A state is expanded atomically: if its successors don't fit, it stays
+    /// pending so a resumed run with larger limits picks it up.
+    pub fn explore(&mut self, depth: usize, limits: Limits) -> Stop {
+        while let Some(&id) = self.pending.front() {
+            let d = self.depth_of[id];
+            if d >= depth {
+                return Stop::Depth;
+            }
+            let s = self.states[id].clone();
+            let mut succ: Vec<(String, String)> = Vec::new();
+            for (pat, rep) in &self.rules {
+                if pat.is_empty() {
+                    continue;
+                }
+                let mut idx = 0usize;
+                while let Some(pos) = s[idx..].find(pat.as_str()) {
+                    let global = idx + pos;
+                    succ.push((
+                        format!("{}{}{}", &s[..global], rep, &s[global + pat.len()..]),
+                        pat.clone(),
+                    ));
+                    // Advance by one char so overlapping matches are found.
+                    idx = global
+                        + s[global..]
+                            .chars()
+                            .next()
+                            .map(|c| c.len_utf8())
+                            .unwrap_or(1);
+                }
+            }
+            let mut new_keys: Vec<String> = succ
+                .iter()
+                .map(|(ns, _)| self.dedup.canonical(ns))
+                .filter(|k| !self.index.contains_key(k))
+                .collect();
+            new_keys.sort();
+            new_keys.dedup();
+            if self.states.len() + new_keys.len() > limits.max_states {
+                return Stop::MaxStates;
+            }
+            if self.edges.len() + succ.len() > limits.max_edges {
+                return Stop::MaxEdges;
+            }
+            self.pending.pop_front();
+            for (ns, pat) in succ {
+                let key = self.dedup.canonical(&ns);
+                let dst = match self.index.get(&key) {
+                    Some(i) => *i,
+                    None => {
+                        let i = self.states.len();
+                        self.states.push(ns);
+                        self.depth_of.push(d + 1);
+                        self.index.insert(key, i);
+                        self.pending.push_back(i);
+                        i
+                    }
+                };
+                self.edges.push((id, dst, d + 1, pat));
+            }
+        }
+        Stop::Exhausted
+    }
+}
@@ -0,0 +1,281 @@
+//! Named secrets for goal inputs.
+//!
+//! Inputs reference a secret as `{"$secret": "github_token"}`; the reference is what lands
+//! in `request.json`, and the value is only substituted right before the goal runs. Anything
+//! the goal echoes back (evidence, deliverables, errors) is scrubbed to `[secret:<name>]`
+//! before it can reach a receipt.
+//!
+//! Sources, in order:
+//! - `secrets:` entries in policies.yaml, each with one of `env`, `file` or `keychain`
+//! - `ONE_ENGINE_SECRET_<NAME>` (upper-cased name) when the entry names no source
+//!
+//! A secret resolves only for the goals its entry lists under `goals` (globs such as
+//! `research.*`); undeclared secrets and entries without `goals` resolve for none, so a caller
+//! can't hand a secret to a goal that echoes its inputs.
+//!
+//! ```yaml
+//! secrets:
+//!   - name: github_token
+//!     env: GITHUB_TOKEN
+//!     goals: ["research.*"]
+//! ```
+
+use anyhow::{anyhow, bail, Result};
+use one_engine::research::glob_match;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+pub const ENV_PREFIX: &str = "ONE_ENGINE_SECRET_";
+
+#[derive(Debug, Clone, Deserialize)]
+struct KeychainRef {
+    service: String,
+    account: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SecretDef {
+    name: String,
+    #[serde(default)]
+    env: Option<String>,
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    keychain: Option<KeychainRef>,
+    /// Goal ids (globs) allowed to receive the value.
+    #[serde(default)]
+    goals: Vec<String>,
+}
+
+impl SecretDef {
+    fn allows(&self, goal_id: &str) -> bool {
+        self.goals.iter().any(|g| glob_match(g, goal_id))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesSecrets {
+    #[serde(default)]
+    secrets: Vec<SecretDef>,
+}
+
+/// Listing entry for `GET /secrets` (never carries the value).
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SecretInfo {
+    pub name: String,
+    /// `env`, `file` or `keychain`.
+    pub source: String,
+    /// Whether the secret currently resolves to a non-empty value.
+    pub available: bool,
+    /// Goals it resolves for; empty for undeclared secrets, which resolve for none.
+    pub goals: Vec<String>,
+}
+
+fn load_defs() -> Vec<SecretDef> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesSecrets>(&raw) {
+            Ok(p) => p.secrets,
+            Err(e) => {
+                tracing::warn!("invalid {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+fn env_var_for(name: &str) -> String {
+    format!(
+        "{}{}",
+        ENV_PREFIX,
+        name.to_ascii_uppercase().replace(['-', '.'], "_")
+    )
+}
+
+/// macOS `security` or the freedesktop `secret-tool`.
+fn read_keychain(k: &KeychainRef) -> Option<String> {
+    let out = if cfg!(target_os = "macos") {
+        std::process::Command::new("security")
+            .args([
+                "find-generic-password",
+                "-s",
+                &k.service,
+                "-a",
+                &k.account,
+                "-w",
+            ])
+            .output()
+    } else {
+        std::process::Command::new("secret-tool")
+            .args(["lookup", "service", &k.service, "account", &k.account])
+            .output()
+    }
+    .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&out.stdout)
+            .trim_end_matches(['\r', '\n'])
+            .to_string(),
+    )
+}
+
+fn read_def(d: &SecretDef) -> Option<String> {
+    let v = if let Some(var) = &d.env {
+        std::env::var(var).ok()
+    } else if let Some(path) = &d.file {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|s| s.trim_end_matches(['\r', '\n']).to_string())
+    } else if let Some(k) = &d.keychain {
+        // The keychain is a host program; simulation never runs one.
+        if crate::engine::simulation::active() {
+            tracing::warn!("secret {}: keychain is disabled in simulation mode", d.name);
+            None
+        } else {
+            read_keychain(k)
+        }
+    } else {
+        std::env::var(env_var_for(&d.name)).ok()
+    };
+    v.filter(|s| !s.is_empty())
+}
+
+fn source_of(d: &SecretDef) -> &'static str {
+    if d.file.is_some() {
+        "file"
+    } else if d.keychain.is_some() {
+        "keychain"
+    } else {
+        "env"
+    }
+}
+
+fn lookup(name: &str, goal_id: &str, defs: &[SecretDef]) -> Result<String> {
+    let Some(d) = defs.iter().find(|d| d.name == name) else {
+        bail!("unknown secret `{}`", name);
+    };
+    if !d.allows(goal_id) {
+        bail!("secret `{}` is not allowed for goal {}", name, goal_id);
+    }
+    read_def(d).ok_or_else(|| anyhow!("secret `{}` is empty", name))
+}
+
+/// Declared secrets plus `ONE_ENGINE_SECRET_*` env secrets, sorted by name.
+pub fn list() -> Vec<SecretInfo> {
+    let mut out: BTreeMap<String, SecretInfo> = BTreeMap::new();
+    for d in load_defs() {
+        out.insert(
+            d.name.clone(),
+            SecretInfo {
+                name: d.name.clone(),
+                source: source_of(&d).to_string(),
+                available: read_def(&d).is_some(),
+                goals: d.goals.clone(),
+            },
+        );
+    }
+    for (k, v) in std::env::vars() {
+        let Some(name) = k.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let name = name.to_ascii_lowercase();
+        out.entry(name.clone()).or_insert(SecretInfo {
+            name,
+            source: "env".to_string(),
+            available: !v.is_empty(),
+            goals: Vec::new(),
+        });
+    }
+    out.into_values().collect()
+}
+
+/// Values substituted into one run, to scrub from its outputs.
+#[derive(Debug, Default)]
+pub struct Resolved {
+    used: Vec<(String, String)>,
+}
+
+impl Resolved {
+    pub fn is_empty(&self) -> bool {
+        self.used.is_empty()
+    }
+
+    /// Replace every substituted value in `s` with `[secret:<name>]`.
+    pub fn scrub_str(&self, s: &str) -> String {
+        let mut out = s.to_string();
+        for (name, value) in &self.used {
+            if value.len() >= 4 {
+                out = out.replace(value.as_str(), &format!("[secret:{}]", name));
+            }
+        }
+        out
+    }
+
+    pub fn scrub(&self, v: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        match v {
+            Value::String(s) => *s = self.scrub_str(s),
+            Value::Array(a) => a.iter_mut().for_each(|x| self.scrub(x)),
+            Value::Object(o) => o.values_mut().for_each(|x| self.scrub(x)),
+            _ => {}
+        }
+    }
+}
+
+fn secret_ref(v: &Value) -> Option<&str> {
+    let obj = v.as_object()?;
+    if obj.len() != 1 {
+        return None;
+    }
+    obj.get("$secret")?.as_str()
+}
+
+fn substitute(
+    v: &mut Value,
+    goal_id: &str,
+    defs: &[SecretDef],
+    resolved: &mut Resolved,
+) -> Result<()> {
+    if let Some(name) = secret_ref(v).map(|s| s.to_string()) {
+        let value = lookup(&name, goal_id, defs)?;
+        if !resolved.used.iter().any(|(n, _)| n == &name) {
+            resolved.used.push((name, value.clone()));
+        }
+        *v = Value::String(value);
+        return Ok(());
+    }
+    match v {
+        Value::Array(a) => a
+            .iter_mut()
+            .try_for_each(|x| substitute(x, goal_id, defs, resolved)),
+        Value::Object(o) => o
+            .values_mut()
+            .try_for_each(|x| substitute(x, goal_id, defs, resolved)),
+        _ => Ok(()),
+    }
+}
+
+/// Substitute `{"$secret": name}` references in the inputs of `goal_id`. Unknown names and
+/// secrets not allowed for the goal fail the run rather than passing the reference through.
+/// Keychain sources shell out, so the lookup runs on the blocking pool.
+pub async fn resolve(goal_id: &str, inputs: Value) -> Result<(Value, Resolved)> {
+    if !inputs.to_string().contains("\"$secret\"") {
+        return Ok((inputs, Resolved::default()));
+    }
+    let goal_id = goal_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut inputs = inputs;
+        let mut resolved = Resolved::default();
+        substitute(&mut inputs, &goal_id, &load_defs(), &mut resolved)?;
+        Ok((inputs, resolved))
+    })
+    .await?
+}
@@ -0,0 +1,287 @@
+//! Simulation mode: the engine runs as usual, but without side effects outside a sandbox.
+//!
+//! ```yaml
+//! simulation:
+//!   enabled: true                             # or ONE_ENGINE_SIMULATION=1
+//!   sandbox_root: /tmp/one-engine-sim         # or ONE_ENGINE_SIMULATION_ROOT
+//!   fixtures: config/simulation/commands.yaml
+//!   cassettes_dir: config/simulation/cassettes
+//!   record: false                             # outside simulation: save LM calls as cassettes
+//! ```
+//!
+//! [`init`] runs at startup, before anything writes, and points `META3_ROOT` at the sandbox
+//! root, so receipts, graphs and the other artifacts goals write with `std::fs` land there.
+//! Inside [`scope`] (every goal run) the [`harness`] stands in for the executor:
+//! - `Action::Cli` gets the first fixture whose `match` is a substring of the command, else a
+//!   synthetic success that echoes the command;
+//! - `Action::WriteFile` goes to an in-memory filesystem shared by all runs.
+//!
+//! LM calls replay the router's HTTP cassette, `<cassettes_dir>/router.json` (see
+//! `integrations::http_cassette`, whose format and matching they share); a request with no
+//! recording gets a synthetic reply ([`synthetic_chat`]). With `record: true` and simulation
+//! off, real exchanges are appended (redacted) to that cassette. Nothing else leaves the process: `research.fetch`, `context.staleness`
+//! probes, notification webhooks, `/meta/run` (python) and keychain secrets refuse to run,
+//! schedules don't fire, the environment snapshot skips its `git`/tool probes and graphs use
+//! the builtin layout instead of `dot`. `/version` carries a `simulation` banner while the
+//! mode is on.
+
+use anyhow::{bail, Result};
+use once_cell::sync::OnceCell;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use super::executor::ExecResult;
+use super::harness::{self, BoxFuture, CommandRunner, Harness, MemFs, SystemClock};
+use super::types::Policy;
+use crate::integrations::http_cassette::{self, Cassette};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SimulationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub sandbox_root: Option<PathBuf>,
+    #[serde(default)]
+    pub fixtures: Option<PathBuf>,
+    #[serde(default)]
+    pub cassettes_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub record: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesSimulation {
+    #[serde(default)]
+    simulation: Option<SimulationConfig>,
+}
+
+pub fn load_config() -> SimulationConfig {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let mut cfg = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesSimulation>(&raw).ok())
+        .and_then(|p| p.simulation)
+        .unwrap_or_default();
+    if let Ok(v) = std::env::var("ONE_ENGINE_SIMULATION") {
+        cfg.enabled = matches!(v.trim(), "1" | "true" | "on");
+    }
+    if let Ok(v) = std::env::var("ONE_ENGINE_SIMULATION_ROOT") {
+        if !v.trim().is_empty() {
+            cfg.sandbox_root = Some(PathBuf::from(v.trim()));
+        }
+    }
+    cfg
+}
+
+fn cassettes_dir(cfg: &SimulationConfig) -> PathBuf {
+    cfg.cassettes_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("config/simulation/cassettes"))
+}
+
+/// A canned command result.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Fixture {
+    /// Substring of the command.
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(default = "default_ok")]
+    pub ok: bool,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+}
+
+fn default_ok() -> bool {
+    true
+}
+
+fn load_fixtures(path: &Path) -> Vec<Fixture> {
+    match std::fs::read_to_string(path) {
+        Ok(raw) => serde_yaml::from_str(&raw).unwrap_or_else(|e| {
+            tracing::warn!("invalid simulation fixtures {}: {}", path.display(), e);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+struct Active {
+    sandbox_root: PathBuf,
+    cassettes_dir: PathBuf,
+    runner: Arc<SimRunner>,
+    fs: Arc<MemFs>,
+}
+
+static ACTIVE: OnceCell<Option<Active>> = OnceCell::new();
+
+fn active_state() -> Option<&'static Active> {
+    ACTIVE.get().and_then(|a| a.as_ref())
+}
+
+/// Turn simulation on if configured: create the sandbox and point `META3_ROOT` at it. Call
+/// once at startup, before anything reads `META3_ROOT` and before the async runtime starts
+/// (it sets an environment variable).
+pub fn init() {
+    ACTIVE.get_or_init(|| {
+        let cfg = load_config();
+        if cfg.record && !cfg.enabled {
+            http_cassette::install("router", Cassette::record(cassettes_dir(&cfg), "router"));
+        }
+        if !cfg.enabled {
+            return None;
+        }
+        http_cassette::install("router", Cassette::replay(cassettes_dir(&cfg), "router"));
+        let sandbox_root = cfg
+            .sandbox_root
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("one-engine-sim"));
+        if let Err(e) = std::fs::create_dir_all(sandbox_root.join("runs")) {
+            tracing::warn!(
+                "simulation sandbox {} not created: {}",
+                sandbox_root.display(),
+                e
+            );
+        }
+        std::env::set_var("META3_ROOT", &sandbox_root);
+        let fixtures = cfg
+            .fixtures
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("config/simulation/commands.yaml"));
+        let runner = SimRunner {
+            fixtures: load_fixtures(&fixtures),
+        };
+        tracing::warn!(
+            "SIMULATION MODE: {} command fixture(s), receipts under {}",
+            runner.fixtures.len(),
+            sandbox_root.display()
+        );
+        Some(Active {
+            sandbox_root,
+            cassettes_dir: cassettes_dir(&cfg),
+            runner: Arc::new(runner),
+            fs: Arc::new(MemFs::default()),
+        })
+    });
+}
+
+pub fn active() -> bool {
+    active_state().is_some()
+}
+
+/// The `/version` banner.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct SimulationStatus {
+    pub banner: String,
+    pub sandbox_root: String,
+    pub fixtures: usize,
+    pub cassettes_dir: String,
+    pub cassettes: usize,
+}
+
+/// `None` unless simulation is on.
+pub fn status() -> Option<SimulationStatus> {
+    let a = active_state()?;
+    let cassettes = http_cassette::recordings(&Cassette::replay(&a.cassettes_dir, "router"));
+    Some(SimulationStatus {
+        banner: "SIMULATION: commands, file writes and LM calls are simulated".to_string(),
+        sandbox_root: a.sandbox_root.display().to_string(),
+        fixtures: a.runner.fixtures.len(),
+        cassettes_dir: a.cassettes_dir.display().to_string(),
+        cassettes,
+    })
+}
+
+/// Fail with `what` when simulation is on (for calls that would reach the network).
+pub fn deny(what: &str) -> Result<()> {
+    if active() {
+        bail!("{} is disabled in simulation mode", what);
+    }
+    Ok(())
+}
+
+/// Answers commands from fixtures, else with a synthetic success.
+pub struct SimRunner {
+    fixtures: Vec<Fixture>,
+}
+
+impl SimRunner {
+    fn answer(&self, cmd: &str) -> ExecResult {
+        match self.fixtures.iter().find(|f| cmd.contains(&f.pattern)) {
+            Some(f) => ExecResult {
+                ok: f.ok,
+                drift: false,
+                stdout: f.stdout.clone(),
+                stderr: f.stderr.clone(),
+            },
+            None => harness::exec_result(true, &format!("[simulated] {}\n", cmd)),
+        }
+    }
+}
+
+impl CommandRunner for SimRunner {
+    fn run<'a>(&'a self, cmd: &'a str, _policy: &'a Policy) -> BoxFuture<'a, Result<ExecResult>> {
+        Box::pin(async move { Ok(self.answer(cmd)) })
+    }
+}
+
+/// The simulation harness; `None` unless simulation is on.
+pub fn harness() -> Option<Harness> {
+    let a = active_state()?;
+    Some(Harness {
+        clock: Arc::new(SystemClock),
+        runner: a.runner.clone(),
+        fs: a.fs.clone(),
+    })
+}
+
+/// Run `fut` under the simulation harness when simulation is on and no harness is set.
+pub async fn scope<F: Future>(fut: F) -> F::Output {
+    match harness().filter(|_| harness::current().is_none()) {
+        Some(h) => harness::scope(h, fut).await,
+        None => fut.await,
+    }
+}
+
+/// The provider response to an LM request that has no recording in simulation.
+pub fn synthetic_chat() -> Value {
+    let reply = json!({"reply": "[simulated] no recorded reply for this request"});
+    json!({
+        "choices": [{"message": {"role": "assistant", "content": reply.to_string()}}],
+        "usage": {"total_tokens": 0}
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fixtures_answer_commands_and_the_rest_succeed_synthetically() {
+        let fixtures: Vec<Fixture> = serde_yaml::from_str(
+            r#"
+- match: cargo build
+  stdout: "Finished dev [unoptimized] target(s)"
+- match: cargo test
+  ok: false
+  stderr: "1 failed"
+"#,
+        )
+        .unwrap();
+        let runner = SimRunner { fixtures };
+        let policy = Policy::default();
+        let build = runner.run("cd repo && cargo build", &policy).await.unwrap();
+        assert!(build.ok && build.stdout.starts_with("Finished"));
+        let test = runner.run("cargo test --all", &policy).await.unwrap();
+        assert!(!test.ok && test.stderr == "1 failed");
+        let other = runner.run("ls -la", &policy).await.unwrap();
+        assert!(other.ok && other.stdout.contains("[simulated] ls -la"));
+    }
+}
@@ -0,0 +1,338 @@
+//! Pre-mutation snapshots under `runs/snapshots/<run_id>/` and rollback.
+//!
+//! A snapshot is a plain copy of each target (file or directory tree) plus `snapshot.json`.
+//! Targets that did not exist are recorded as `missing`, so rollback removes them again;
+//! directory targets are restored exactly (files created after the snapshot are removed).
+//! Snapshots and restored files go through [`storage`], so they work with a read-only
+//! `META3_ROOT` in scratch mode.
+
+use anyhow::{anyhow, bail, Context, Result};
+use one_engine::{atomic, storage};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Heavy directories never copied into a snapshot (nor touched on restore).
+const SKIP_DIRS: &[&str] = &[".git", "target", "node_modules"];
+
+/// The engine's own state under `META3_ROOT`: receipts, snapshots and threads. A rollback
+/// must not take them back in time (and a snapshot must not copy itself).
+const ENGINE_DIRS: &[&str] = &["runs", "users"];
+
+/// Whether `dir` is skipped by snapshots and restores: a [`SKIP_DIRS`] name, or one of the
+/// [`ENGINE_DIRS`] (or the snapshot store), whichever target it was reached from.
+fn is_skipped_dir(name: &str, dir: &Path) -> bool {
+    if SKIP_DIRS.contains(&name) {
+        return true;
+    }
+    let Ok(dir) = fs::canonicalize(dir) else {
+        return false;
+    };
+    let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+    ENGINE_DIRS
+        .iter()
+        .map(|d| root.join(d))
+        .chain([snapshots_dir()])
+        .any(|d| fs::canonicalize(d).is_ok_and(|d| d == dir))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotTarget {
+    pub path: String,
+    /// "file" | "dir" | "missing"
+    pub kind: String,
+    pub files: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub run_id: String,
+    pub goal_id: String,
+    pub created_at: String,
+    pub targets: Vec<SnapshotTarget>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreReport {
+    pub run_id: String,
+    pub restored: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+fn is_safe_segment(s: &str) -> bool {
+    !s.is_empty()
+        && s.len() <= 128
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && !s.contains("..")
+        && s != "."
+}
+
+pub fn snapshots_dir() -> PathBuf {
+    PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()))
+        .join("runs/snapshots")
+}
+
+pub fn exists(run_id: &str) -> bool {
+    is_safe_segment(run_id) && storage::exists(&snapshots_dir().join(run_id).join("snapshot.json"))
+}
+
+/// Goals that write to the filesystem and so get a snapshot when `policy.snapshot` is set.
+pub fn is_mutating(goal_id: &str) -> bool {
+    goal_id.contains("file.write")
+        || goal_id.contains("file.patch")
+        || goal_id.contains("shell.exec")
+}
+
+/// Paths a goal is about to mutate: `inputs.snapshot_paths` wins, otherwise derived from
+/// `file.write` (`path`) and `file.patch` (`edits[].path` / diff headers under `root`);
+/// `shell.exec` can touch anything under its working directory, so that is the target.
+pub fn targets_for(goal_id: &str, inputs: &Value) -> Vec<PathBuf> {
+    if let Some(arr) = inputs.get("snapshot_paths").and_then(|v| v.as_array()) {
+        return arr
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(PathBuf::from)
+            .collect();
+    }
+    if goal_id.contains("file.write") {
+        return inputs
+            .get("path")
+            .and_then(|v| v.as_str())
+            .map(|p| vec![PathBuf::from(p)])
+            .unwrap_or_default();
+    }
+    if goal_id.contains("shell.exec") {
+        return vec![PathBuf::from(".")];
+    }
+    if goal_id.contains("file.patch") {
+        let root = PathBuf::from(inputs.get("root").and_then(|v| v.as_str()).unwrap_or("."));
+        let mut rels: Vec<String> = Vec::new();
+        if let Some(edits) = inputs.get("edits").and_then(|v| v.as_array()) {
+            rels.extend(
+                edits
+                    .iter()
+                    .filter_map(|e| e.get("path").and_then(|v| v.as_str()))
+                    .map(|s| s.to_string()),
+            );
+        }
+        if let Some(diff) = inputs.get("diff").and_then(|v| v.as_str()) {
+            for line in diff.lines() {
+                let Some(raw) = line
+                    .strip_prefix("--- ")
+                    .or_else(|| line.strip_prefix("+++ "))
+                else {
+                    continue;
+                };
+                let p = raw.split('\t').next().unwrap_or("").trim();
+                if p != "/dev/null" {
+                    let p = p
+                        .strip_prefix("a/")
+                        .or_else(|| p.strip_prefix("b/"))
+                        .unwrap_or(p);
+                    rels.push(p.to_string());
+                }
+            }
+        }
+        rels.sort();
+        rels.dedup();
+        return rels.into_iter().map(|r| root.join(r)).collect();
+    }
+    Vec::new()
+}
+
+/// File type of `path` wherever it lives (see [`storage::resolve`]); symlinks are not followed.
+fn file_type(path: &Path) -> Option<fs::FileType> {
+    fs::symlink_metadata(storage::resolve(path))
+        .ok()
+        .map(|m| m.file_type())
+}
+
+fn copy_tree(src: &Path, dst: &Path) -> Result<usize> {
+    storage::create_dir_all(dst).with_context(|| format!("failed to create {}", dst.display()))?;
+    let mut n = 0;
+    for name in storage::entries(src) {
+        let from = src.join(&name);
+        let Some(ft) = file_type(&from) else {
+            continue;
+        };
+        if ft.is_dir() {
+            if is_skipped_dir(&name, &from) {
+                continue;
+            }
+            n += copy_tree(&from, &dst.join(&name))?;
+        } else if ft.is_file() {
+            storage::copy(&from, &dst.join(&name))
+                .with_context(|| format!("failed to copy {}", from.display()))?;
+            n += 1;
+        }
+    }
+    Ok(n)
+}
+
+/// Remove files/dirs under `live` that are absent from `saved` (skipping [`is_skipped_dir`]).
+fn prune_extra(live: &Path, saved: &Path, removed: &mut Vec<String>) -> Result<()> {
+    for name in storage::entries(live) {
+        let path = live.join(&name);
+        let Some(ft) = file_type(&path) else {
+            continue;
+        };
+        let counterpart = file_type(&saved.join(&name));
+        if ft.is_dir() {
+            if is_skipped_dir(&name, &path) {
+                continue;
+            }
+            if counterpart.is_some_and(|t| t.is_dir()) {
+                prune_extra(&path, &saved.join(&name), removed)?;
+            } else {
+                fs::remove_dir_all(storage::resolve(&path))?;
+                removed.push(path.display().to_string());
+            }
+        } else if !counterpart.is_some_and(|t| t.is_file()) {
+            fs::remove_file(storage::resolve(&path))?;
+            removed.push(path.display().to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Copy `targets` into `runs/snapshots/<run_id>/` before a mutating goal runs.
+pub fn take(run_id: &str, goal_id: &str, targets: &[PathBuf]) -> Result<Snapshot> {
+    if !is_safe_segment(run_id) {
+        bail!("invalid run_id for snapshot: {}", run_id);
+    }
+    let dir = snapshots_dir().join(run_id);
+    if storage::exists(&dir) {
+        fs::remove_dir_all(storage::resolve(&dir))
+            .with_context(|| format!("failed to reset {}", dir.display()))?;
+    }
+    let data = dir.join("data");
+    storage::create_dir_all(&data)
+        .with_context(|| format!("failed to create {}", data.display()))?;
+
+    let mut out = Vec::new();
+    for (i, target) in targets.iter().enumerate() {
+        let slot = data.join(i.to_string());
+        let ft = file_type(target);
+        if ft.is_some_and(|t| t.is_dir()) && is_skipped_dir("", target) {
+            bail!(
+                "{} holds the engine's own state and can't be snapshotted",
+                target.display()
+            );
+        }
+        let (kind, files) = if ft.is_some_and(|t| t.is_dir()) {
+            ("dir", copy_tree(target, &slot)?)
+        } else if ft.is_some_and(|t| t.is_file()) {
+            storage::copy(target, &slot)
+                .with_context(|| format!("failed to snapshot {}", target.display()))?;
+            ("file", 1)
+        } else {
+            ("missing", 0)
+        };
+        out.push(SnapshotTarget {
+            path: target.display().to_string(),
+            kind: kind.to_string(),
+            files,
+        });
+    }
+
+    let snap = Snapshot {
+        run_id: run_id.to_string(),
+        goal_id: goal_id.to_string(),
+        created_at: chrono::Utc::now().to_rfc3339(),
+        targets: out,
+    };
+    atomic::write(
+        dir.join("snapshot.json"),
+        serde_json::to_string_pretty(&snap).unwrap_or_default(),
+    )
+    .with_context(|| format!("failed to write {}", dir.join("snapshot.json").display()))?;
+    Ok(snap)
+}
+
+/// Move a snapshot taken under a provisional id to the run's final id.
+pub fn rename(from: &str, to: &str) -> Result<()> {
+    if !is_safe_segment(from) || !is_safe_segment(to) {
+        bail!("invalid snapshot id");
+    }
+    let dir = snapshots_dir();
+    let target = dir.join(to);
+    if storage::exists(&target) {
+        fs::remove_dir_all(storage::resolve(&target))?;
+    }
+    // The snapshot stays in whichever store it was taken in (the root or the scratch dir).
+    let source = storage::resolve(&dir.join(from));
+    fs::rename(&source, source.with_file_name(to))?;
+    let meta = target.join("snapshot.json");
+    if let Ok(raw) = storage::read_to_string(&meta) {
+        if let Ok(mut snap) = serde_json::from_str::<Snapshot>(&raw) {
+            snap.run_id = to.to_string();
+            atomic::write(
+                &meta,
+                serde_json::to_string_pretty(&snap).unwrap_or_default(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+pub fn load(run_id: &str) -> Result<Snapshot> {
+    if !is_safe_segment(run_id) {
+        bail!("invalid run_id: {}", run_id);
+    }
+    let meta = snapshots_dir().join(run_id).join("snapshot.json");
+    let raw =
+        storage::read_to_string(&meta).map_err(|_| anyhow!("no snapshot for run {}", run_id))?;
+    serde_json::from_str(&raw).with_context(|| format!("corrupt {}", meta.display()))
+}
+
+/// Restore every target of a snapshot to its pre-run state.
+pub fn restore(run_id: &str) -> Result<RestoreReport> {
+    let snap = load(run_id)?;
+    let data = snapshots_dir().join(run_id).join("data");
+    let mut report = RestoreReport {
+        run_id: run_id.to_string(),
+        restored: Vec::new(),
+        removed: Vec::new(),
+    };
+    for (i, t) in snap.targets.iter().enumerate() {
+        let live = PathBuf::from(&t.path);
+        let slot = data.join(i.to_string());
+        match t.kind.as_str() {
+            "file" => {
+                if file_type(&live).is_some_and(|t| t.is_dir()) {
+                    fs::remove_dir_all(storage::resolve(&live))?;
+                }
+                if let Some(parent) = live.parent() {
+                    storage::create_dir_all(parent)?;
+                }
+                storage::copy(&slot, &live)
+                    .with_context(|| format!("failed to restore {}", live.display()))?;
+                report.restored.push(t.path.clone());
+            }
+            "dir" => {
+                let ft = file_type(&live);
+                if ft.is_some_and(|t| !t.is_dir()) {
+                    fs::remove_file(storage::resolve(&live))?;
+                }
+                if ft.is_some_and(|t| t.is_dir()) {
+                    prune_extra(&live, &slot, &mut report.removed)?;
+                }
+                copy_tree(&slot, &live)?;
+                report.restored.push(t.path.clone());
+            }
+            _ => {
+                let ft = file_type(&live);
+                if ft.is_some_and(|t| t.is_dir()) {
+                    fs::remove_dir_all(storage::resolve(&live))?;
+                    report.removed.push(t.path.clone());
+                } else if ft.is_some() {
+                    fs::remove_file(storage::resolve(&live))?;
+                    report.removed.push(t.path.clone());
+                }
+            }
+        }
+    }
+    Ok(report)
+}
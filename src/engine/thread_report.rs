@@ -1,16 +1,24 @@
 use anyhow::{anyhow, Context, Result};
+use one_engine::{atomic, jsonl};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
+use super::pdf;
+use super::urls::url_for;
+
+/// Markdown report sections, in output order.
+pub const SECTIONS: &[&str] = &["summary", "decisions", "runs_by_goal", "failures", "open_questions"];
+
 #[derive(Debug, Clone)]
 pub struct ThreadReportResult {
     pub out_dir: PathBuf,
     pub nodes: usize,
     pub thread: String,
+    pub sections: Vec<String>,
+    pub pdf: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -19,6 +27,15 @@ pub struct ThreadReportOpts {
     pub thread: String, // explicit thread id, or "auto"
     pub max_events: usize,
     pub content_chars: usize,
+    /// RFC3339 timestamp, `YYYY-MM-DD`, or a relative window like `24h` / `7d` / `2w`.
+    pub since: Option<String>,
+    /// Comma-separated goal_id substrings; events whose run matches none are dropped.
+    pub goal_filter: Option<String>,
+    /// Keep only these roles (empty = all).
+    pub roles: Vec<String>,
+    /// Markdown sections to include (empty = all of `SECTIONS`).
+    pub sections: Vec<String>,
+    pub pdf: bool,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -47,6 +64,7 @@ fn is_safe_segment(seg: &str) -> bool {
         && !seg.contains('/')
         && !seg.contains('\\')
         && !seg.contains("..")
+        && seg != "."
         && seg
             .chars()
             .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
@@ -91,31 +109,9 @@ fn truncate_chars(s: &str, max_chars: usize) -> String {
 }
 
 fn tail_lines(path: &Path, limit: usize, max_bytes: u64) -> Result<Vec<String>> {
-    let meta = fs::metadata(path).with_context(|| format!("metadata {}", path.display()))?;
-    let len = meta.len();
-    let start = if len > max_bytes { len - max_bytes } else { 0 };
-
-    let mut f = fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
-    if start > 0 {
-        f.seek(SeekFrom::Start(start))
-            .with_context(|| format!("seek {}", path.display()))?;
-    }
-    let mut buf = Vec::new();
-    f.read_to_end(&mut buf)
-        .with_context(|| format!("read {}", path.display()))?;
-
-    let mut s = String::from_utf8_lossy(&buf).to_string();
-    if start > 0 {
-        if let Some(idx) = s.find('\n') {
-            s = s[idx + 1..].to_string();
-        }
-    }
-
-    let mut lines: Vec<&str> = s.lines().filter(|l| !l.trim().is_empty()).collect();
-    if lines.len() > limit {
-        lines = lines[lines.len() - limit..].to_vec();
-    }
-    Ok(lines.into_iter().map(|l| l.to_string()).collect())
+    // Thread files rotate into segments; read across them.
+    jsonl::tail_segments(path, limit, max_bytes)
+        .with_context(|| format!("tail {}", path.display()))
 }
 
 fn receipt_response_json(run_id: &str) -> Option<Value> {
@@ -129,7 +125,9 @@ fn receipt_response_json(run_id: &str) -> Option<Value> {
         .join(run_id)
         .join("response.json");
     let txt = fs::read_to_string(p).ok()?;
-    serde_json::from_str::<Value>(&txt).ok()
+    let mut resp = serde_json::from_str::<Value>(&txt).ok()?;
+    super::migrate::upgrade_response(&mut resp);
+    Some(resp)
 }
 
 fn get_goal_id(resp: &Value) -> Option<String> {
@@ -182,7 +180,7 @@ fn select_thread(threads_dir: &Path, thread: &str) -> Result<String> {
                 .with_context(|| format!("read_dir {}", threads_dir.display()))?;
             for entry in rd.flatten() {
                 let p = entry.path();
-                if p.extension().and_then(|x| x.to_str()) != Some("jsonl") {
+                if p.extension().and_then(|x| x.to_str()) != Some("jsonl") || jsonl::is_rotated_segment(&p) {
                     continue;
                 }
                 let name = p
@@ -244,6 +242,43 @@ fn keywords(user_texts: &[String]) -> Vec<(String, u32)> {
     v
 }
 
+fn get_error(resp: &Value) -> Option<String> {
+    let ev = resp.get("manifest")?.get("evidence")?;
+    ev.get("error")
+        .or_else(|| ev.get("stderr"))
+        .and_then(|v| v.as_str())
+        .map(one_line)
+        .filter(|s| !s.is_empty())
+}
+
+fn parse_since(s: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let s = s.trim();
+    let exact = if s.len() == 10 { format!("{s}T00:00:00Z") } else { s.to_string() };
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(&exact) {
+        return Some(t.with_timezone(&chrono::Utc));
+    }
+    crate::integrations::run_index::parse_window(s).map(|w| chrono::Utc::now() - w)
+}
+
+/// Phrases that mark a message as recording a decision (heuristic).
+const DECISION_MARKERS: &[&str] = &[
+    "decided",
+    "decision",
+    "we will",
+    "we'll",
+    "let's go with",
+    "going with",
+    "agreed",
+    "ship it",
+    "plan:",
+    "next step",
+];
+
+fn is_decision(text: &str) -> bool {
+    let t = text.to_lowercase();
+    DECISION_MARKERS.iter().any(|m| t.contains(m))
+}
+
 pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<ThreadReportResult> {
     if !is_safe_segment(external_run_id) {
         return Err(anyhow!("invalid __run_id"));
@@ -254,6 +289,28 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
 
     opts.max_events = opts.max_events.max(20).min(2000);
     opts.content_chars = opts.content_chars.max(60).min(600);
+    let since = match opts.since.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(raw) => Some(parse_since(raw).ok_or_else(|| anyhow!("invalid since: {raw}"))?),
+        None => None,
+    };
+    let roles: Vec<String> = opts.roles.iter().map(|r| r.trim().to_lowercase()).filter(|r| !r.is_empty()).collect();
+    let goal_terms: Vec<String> = opts
+        .goal_filter
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    let sections: Vec<String> = if opts.sections.is_empty() {
+        SECTIONS.iter().map(|s| s.to_string()).collect()
+    } else {
+        let wanted: Vec<String> = opts.sections.iter().map(|s| s.trim().to_lowercase()).collect();
+        if let Some(bad) = wanted.iter().find(|w| !SECTIONS.contains(&w.as_str())) {
+            return Err(anyhow!("unknown section {bad:?} (expected one of {})", SECTIONS.join(", ")));
+        }
+        SECTIONS.iter().filter(|s| wanted.iter().any(|w| w == *s)).map(|s| s.to_string()).collect()
+    };
 
     let root = meta3_root();
     let threads_dir = root.join("users").join(&opts.user_id).join("threads");
@@ -265,7 +322,6 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
 
     let lines = tail_lines(&thread_path, opts.max_events, 4_000_000)?;
     let mut events: Vec<ThreadEvent> = Vec::new();
-    let mut counts_by_role: BTreeMap<String, u64> = BTreeMap::new();
     for line in lines {
         let v: Value = match serde_json::from_str(&line) {
             Ok(v) => v,
@@ -278,7 +334,15 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
         if role.is_empty() || run_id.is_empty() || !is_safe_segment(&run_id) {
             continue;
         }
-        *counts_by_role.entry(role.clone()).or_insert(0) += 1;
+        if !roles.is_empty() && !roles.contains(&role.to_lowercase()) {
+            continue;
+        }
+        if let Some(cut) = since {
+            let at = chrono::DateTime::parse_from_rfc3339(&ts).ok();
+            if at.map(|t| t < cut).unwrap_or(false) {
+                continue;
+            }
+        }
         events.push(ThreadEvent {
             ts,
             role,
@@ -287,7 +351,7 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
         });
     }
     if events.is_empty() {
-        return Err(anyhow!("thread has no parseable events"));
+        return Err(anyhow!("thread has no parseable events (after since/roles filters)"));
     }
 
     // Build run index info from receipts.
@@ -303,6 +367,8 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
         view_url: Option<String>,
         text: String,
         receipt_url: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
     }
     let mut run_index: Vec<RunInfo> = Vec::new();
     for (i, ev) in events.iter().enumerate() {
@@ -311,6 +377,13 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
         let view_url = resp.as_ref().and_then(get_view_url);
         let actual_success = resp.as_ref().and_then(get_actual_success);
         let bits = resp.as_ref().map(get_bits).unwrap_or_default();
+        let error = resp.as_ref().and_then(get_error).map(|e| truncate_chars(&e, 300));
+        if !goal_terms.is_empty() {
+            let g = goal_id.as_deref().unwrap_or("").to_lowercase();
+            if !goal_terms.iter().any(|t| g.contains(t)) {
+                continue;
+            }
+        }
         run_index.push(RunInfo {
             i: i + 1,
             ts: ev.ts.clone(),
@@ -321,9 +394,17 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
             bits,
             view_url,
             text: truncate_chars(&ev.content, opts.content_chars),
-            receipt_url: format!("/runs/receipts/{}/RECEIPT.md", ev.run_id),
+            receipt_url: url_for(&format!("/runs/receipts/{}/RECEIPT.md", ev.run_id)),
+            error,
         });
     }
+    if run_index.is_empty() {
+        return Err(anyhow!("no thread events match the filters"));
+    }
+    let mut counts_by_role: BTreeMap<String, u64> = BTreeMap::new();
+    for r in &run_index {
+        *counts_by_role.entry(r.role.clone()).or_insert(0) += 1;
+    }
 
     let user_msgs: Vec<String> = run_index
         .iter()
@@ -341,14 +422,134 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
         "nodes": run_index.len(),
         "counts_by_role": counts_by_role,
         "top_keywords": topk,
+        "filters": {
+            "since": since.map(|t| t.to_rfc3339()),
+            "goal_filter": goal_terms,
+            "roles": roles,
+        },
+        "sections": sections,
         "runs": run_index,
     });
-    fs::write(
+    atomic::write(
         out_dir.join("report.json"),
         serde_json::to_string_pretty(&report_json).unwrap_or_default(),
     )
     .with_context(|| "write report.json".to_string())?;
 
+    // Markdown (+ optional PDF): one entry per run_id for the run-centric sections.
+    let mut seen_runs = HashSet::new();
+    let runs: Vec<&RunInfo> = run_index.iter().filter(|r| seen_runs.insert(r.run_id.clone())).collect();
+    let ok = runs.iter().filter(|r| r.actual_success == Some(true)).count();
+    let failed: Vec<&&RunInfo> = runs.iter().filter(|r| r.actual_success == Some(false)).collect();
+    let mut md = format!("# Thread report: {}\n\n", thread);
+    for section in &sections {
+        match section.as_str() {
+            "summary" => {
+                md.push_str("## Summary\n\n");
+                md.push_str(&format!("- user: `{}` · thread: `{}`\n", opts.user_id, thread));
+                let first = run_index.first().map(|r| r.ts.as_str()).unwrap_or("");
+                let last = run_index.last().map(|r| r.ts.as_str()).unwrap_or("");
+                md.push_str(&format!("- window: {} → {}\n", first, last));
+                md.push_str(&format!(
+                    "- events: {} ({})\n",
+                    run_index.len(),
+                    counts_by_role.iter().map(|(r, c)| format!("{r}: {c}")).collect::<Vec<_>>().join(", ")
+                ));
+                md.push_str(&format!(
+                    "- runs: {} · ok: {} · failed: {} · unknown: {}\n",
+                    runs.len(),
+                    ok,
+                    failed.len(),
+                    runs.len() - ok - failed.len()
+                ));
+                if !topk.is_empty() {
+                    md.push_str(&format!(
+                        "- keywords: {}\n",
+                        topk.iter().take(12).map(|(w, _)| w.as_str()).collect::<Vec<_>>().join(", ")
+                    ));
+                }
+                md.push('\n');
+            }
+            "decisions" => {
+                md.push_str("## Decisions\n\n");
+                let decisions: Vec<&RunInfo> = run_index.iter().filter(|r| is_decision(&r.text)).collect();
+                if decisions.is_empty() {
+                    md.push_str("_None recorded._\n");
+                }
+                for r in decisions {
+                    md.push_str(&format!("- {} **{}**: {}\n", r.ts, r.role, r.text));
+                }
+                md.push('\n');
+            }
+            "runs_by_goal" => {
+                md.push_str("## Runs by goal\n\n| goal | runs | ok | failed | last run |\n|---|---|---|---|---|\n");
+                let mut by_goal: BTreeMap<&str, (usize, usize, usize, &RunInfo)> = BTreeMap::new();
+                for r in runs.iter().copied() {
+                    let g = r.goal_id.as_deref().unwrap_or("(no receipt)");
+                    let e = by_goal.entry(g).or_insert((0, 0, 0, r));
+                    e.0 += 1;
+                    e.1 += usize::from(r.actual_success == Some(true));
+                    e.2 += usize::from(r.actual_success == Some(false));
+                    e.3 = r;
+                }
+                for (g, (n, ok, fail, last)) in by_goal {
+                    md.push_str(&format!(
+                        "| `{}` | {} | {} | {} | [{}]({}) |\n",
+                        g, n, ok, fail, last.run_id, last.receipt_url
+                    ));
+                }
+                md.push('\n');
+            }
+            "failures" => {
+                md.push_str("## Failures\n\n");
+                if failed.is_empty() {
+                    md.push_str("_No failed runs._\n");
+                }
+                for r in &failed {
+                    md.push_str(&format!(
+                        "- `{}` [{}]({}): {}\n",
+                        r.goal_id.as_deref().unwrap_or("?"),
+                        r.run_id,
+                        r.receipt_url,
+                        r.error.as_deref().unwrap_or(&r.text)
+                    ));
+                }
+                md.push('\n');
+            }
+            "open_questions" => {
+                // A user question is open when no later assistant turn on the same run succeeded.
+                md.push_str("## Open questions\n\n");
+                let open: Vec<&RunInfo> = run_index
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, r)| r.role == "user" && r.text.contains('?'))
+                    .filter(|(i, r)| {
+                        !run_index[i + 1..].iter().any(|a| {
+                            a.role == "assistant" && a.run_id == r.run_id && a.actual_success != Some(false)
+                        })
+                    })
+                    .map(|(_, r)| r)
+                    .collect();
+                if open.is_empty() {
+                    md.push_str("_None._\n");
+                }
+                for r in open.iter().rev().take(20) {
+                    md.push_str(&format!("- {} {}\n", r.ts, r.text));
+                }
+                md.push('\n');
+            }
+            _ => {}
+        }
+    }
+    atomic::write(out_dir.join("report.md"), md.as_bytes()).with_context(|| "write report.md".to_string())?;
+    if opts.pdf {
+        atomic::write(
+            out_dir.join("report.pdf"),
+            pdf::render_markdown(&format!("Thread report: {thread}"), &md),
+        )
+        .with_context(|| "write report.pdf".to_string())?;
+    }
+
     // HTML
     let mut rows_html = String::new();
     for r in report_json
@@ -435,7 +636,7 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
 <body>
   <h1>Thread Report</h1>
   <div class="muted">user: <code>{user}</code> · thread: <code>{thread}</code> · nodes: <code>{nodes}</code></div>
-  <div class="muted" style="margin-top:8px">Links: <a href="report.json">report.json</a></div>
+  <div class="muted" style="margin-top:8px">Links: <a href="report.json">report.json</a> · <a href="report.md">report.md</a>{pdf_link}</div>
 
   <h2 style="margin-top:18px">Keywords</h2>
   <div class="muted">Extracted from user messages (heuristic).</div>
@@ -467,17 +668,20 @@ pub fn generate(external_run_id: &str, mut opts: ThreadReportOpts) -> Result<Thr
         rid = html_escape(external_run_id),
         user = html_escape(&opts.user_id),
         thread = html_escape(&thread),
-        nodes = events.len(),
+        nodes = run_index.len(),
         rows = rows_html,
-        kw = kw_html
+        kw = kw_html,
+        pdf_link = if opts.pdf { " · <a href=\"report.pdf\">report.pdf</a>" } else { "" }
     );
-    fs::write(out_dir.join("index.html"), html.as_bytes())
+    atomic::write(out_dir.join("index.html"), html.as_bytes())
         .with_context(|| "write index.html".to_string())?;
 
     Ok(ThreadReportResult {
         out_dir,
-        nodes: events.len(),
+        nodes: run_index.len(),
         thread,
+        sections,
+        pdf: opts.pdf,
     })
 }
 
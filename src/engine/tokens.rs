@@ -0,0 +1,398 @@
+//! Token counting and context-window budgeting for chat turns.
+//!
+//! Counts use the model's BPE from `tiktoken-rs`: `cl100k_base` for the models tiktoken maps
+//! to it, `o200k_base` for every other model, including non-OpenAI ones, where it is the
+//! closest general-purpose match. The context length comes from `context_budget.context_lengths`
+//! (exact model id or id without the `provider/` prefix), else a built-in table, else
+//! [`DEFAULT_CONTEXT_TOKENS`].
+//!
+//! [`fit`] packs a chat into `context length - reserve_output_tokens`:
+//! 1. [`Keep::Always`] messages (system prompt, the latest user turn, the current tool round)
+//!    and [`Keep::Round`] messages (earlier tool rounds) are always sent. If they overflow, the
+//!    tool outputs of earlier rounds are elided, oldest first, then the latest user turn is cut.
+//! 2. [`Keep::Pinned`] messages (run summaries and attached receipts) go next, newest first,
+//!    skipping any that don't fit.
+//! 3. [`Keep::History`] turns fill what is left, newest first, stopping at the first that
+//!    doesn't fit so the kept history has no gaps.
+//!
+//! ```yaml
+//! context_budget:
+//!   reserve_output_tokens: 4096
+//!   tool_output_tokens: 2000     # cap per tool result fed back to the model
+//!   history_messages: 200        # thread events loaded before budgeting
+//!   context_lengths: {moonshotai/kimi-k2: 131072}
+//! ```
+
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+use utoipa::ToSchema;
+
+pub const DEFAULT_CONTEXT_TOKENS: usize = 32_768;
+/// Framing tokens per chat message (role, separators).
+const MESSAGE_OVERHEAD: usize = 4;
+/// Tokens that prime the assistant's reply.
+const REPLY_PRIMING: usize = 3;
+/// A cut user turn keeps at least this many tokens.
+const MIN_TURN_TOKENS: usize = 256;
+const TRUNCATED: &str = "\n…[truncated]";
+/// What an elided tool output of an earlier round is replaced with.
+const ELIDED: &str = "[earlier tool output elided to fit the context window]";
+
+/// Context lengths of common router models, matched on a substring of the id.
+const KNOWN_CONTEXTS: &[(&str, usize)] = &[
+    ("kimi-k2", 131_072),
+    ("claude", 200_000),
+    ("gemini", 1_048_576),
+    ("deepseek", 65_536),
+    ("llama-3", 131_072),
+    ("mistral", 32_768),
+    ("qwen", 32_768),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default = "default_reserve_output_tokens")]
+    pub reserve_output_tokens: usize,
+    #[serde(default = "default_tool_output_tokens")]
+    pub tool_output_tokens: usize,
+    #[serde(default = "default_history_messages")]
+    pub history_messages: usize,
+    #[serde(default)]
+    pub context_lengths: BTreeMap<String, usize>,
+}
+
+fn default_reserve_output_tokens() -> usize {
+    4096
+}
+
+fn default_tool_output_tokens() -> usize {
+    2000
+}
+
+fn default_history_messages() -> usize {
+    200
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            reserve_output_tokens: default_reserve_output_tokens(),
+            tool_output_tokens: default_tool_output_tokens(),
+            history_messages: default_history_messages(),
+            context_lengths: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesBudget {
+    #[serde(default)]
+    context_budget: Option<BudgetConfig>,
+}
+
+pub fn load_config() -> BudgetConfig {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesBudget>(&raw).ok())
+        .and_then(|p| p.context_budget)
+        .unwrap_or_default()
+}
+
+static O200K: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::o200k_base().ok());
+static CL100K: Lazy<Option<CoreBPE>> = Lazy::new(|| tiktoken_rs::cl100k_base().ok());
+
+fn bare(model: &str) -> &str {
+    model.rsplit('/').next().unwrap_or(model)
+}
+
+/// The encoding used for `model` and its name (`chars/4` if the BPE failed to load).
+fn encoding(model: &str) -> (&'static str, Option<&'static CoreBPE>) {
+    let (name, bpe) = match get_tokenizer(bare(model)) {
+        Some(Tokenizer::Cl100kBase) => ("cl100k_base", CL100K.as_ref()),
+        _ => ("o200k_base", O200K.as_ref()),
+    };
+    match bpe {
+        Some(bpe) => (name, Some(bpe)),
+        None => ("chars/4", None),
+    }
+}
+
+fn count_with(bpe: Option<&CoreBPE>, text: &str) -> usize {
+    match bpe {
+        Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+        None => text.chars().count().div_ceil(4),
+    }
+}
+
+/// Tokens of `text` for `model`.
+pub fn count(model: &str, text: &str) -> usize {
+    count_with(encoding(model).1, text)
+}
+
+/// `text` cut to at most `max` tokens for `model` (marked as truncated when cut).
+pub fn truncate(model: &str, text: &str, max: usize) -> String {
+    truncate_with(encoding(model).1, text, max)
+}
+
+fn truncate_with(bpe: Option<&CoreBPE>, text: &str, max: usize) -> String {
+    let total = count_with(bpe, text);
+    if total <= max {
+        return text.to_string();
+    }
+    let room = max.saturating_sub(count_with(bpe, TRUNCATED));
+    // Start from the proportional cut and back off until the head fits.
+    let mut chars = text.chars().count() * room / total.max(1);
+    loop {
+        let head: String = text.chars().take(chars).collect();
+        if chars == 0 || count_with(bpe, &head) <= room {
+            return head + TRUNCATED;
+        }
+        chars = chars * 9 / 10;
+    }
+}
+
+fn context_length_with(cfg: &BudgetConfig, model: &str) -> usize {
+    if let Some(n) = cfg
+        .context_lengths
+        .get(model)
+        .or_else(|| cfg.context_lengths.get(bare(model)))
+    {
+        return *n;
+    }
+    let id = bare(model).to_ascii_lowercase();
+    if let Some((_, n)) = KNOWN_CONTEXTS.iter().find(|(k, _)| id.contains(k)) {
+        return *n;
+    }
+    if ["gpt-", "o1", "o3", "o4"].iter().any(|p| id.starts_with(p)) {
+        return tiktoken_rs::model::get_context_size(&id);
+    }
+    DEFAULT_CONTEXT_TOKENS
+}
+
+/// How a message is treated when the chat does not fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    Always,
+    /// A message of an earlier tool round: always sent, since a tool call needs its result,
+    /// but its tool output is the first thing elided when the chat doesn't fit.
+    Round,
+    Pinned,
+    History,
+}
+
+/// What [`fit`] sent, for the run's evidence.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct BudgetReport {
+    pub model: String,
+    pub tokenizer: String,
+    pub context_tokens: usize,
+    /// Context length minus the tokens reserved for the reply.
+    pub budget_tokens: usize,
+    pub used_tokens: usize,
+    pub kept: usize,
+    pub dropped: usize,
+    /// The latest user turn was cut to fit.
+    pub truncated: bool,
+    /// Tool outputs of earlier rounds replaced by a placeholder to fit.
+    #[serde(default)]
+    pub elided_tool_outputs: usize,
+}
+
+fn message_tokens(bpe: Option<&CoreBPE>, m: &Value) -> usize {
+    let body = match m.get("content").and_then(|v| v.as_str()) {
+        Some(text) => count_with(bpe, text),
+        None => 0,
+    };
+    let calls = m
+        .get("tool_calls")
+        .map_or(0, |c| count_with(bpe, &c.to_string()));
+    body + calls + MESSAGE_OVERHEAD
+}
+
+/// The messages of `items` that fit `model`'s context, in their original order.
+pub fn fit(model: &str, items: Vec<(Keep, Value)>) -> (Vec<Value>, BudgetReport) {
+    let cfg = load_config();
+    fit_with(&cfg, model, items)
+}
+
+fn fit_with(
+    cfg: &BudgetConfig,
+    model: &str,
+    mut items: Vec<(Keep, Value)>,
+) -> (Vec<Value>, BudgetReport) {
+    let (tokenizer, bpe) = encoding(model);
+    let context_tokens = context_length_with(cfg, model);
+    let budget = context_tokens
+        .saturating_sub(cfg.reserve_output_tokens)
+        .max(MIN_TURN_TOKENS * 2);
+    let mut costs: Vec<usize> = items.iter().map(|(_, m)| message_tokens(bpe, m)).collect();
+    let mut keep: Vec<bool> = items
+        .iter()
+        .map(|(k, _)| matches!(k, Keep::Always | Keep::Round))
+        .collect();
+    let mut used = REPLY_PRIMING
+        + costs
+            .iter()
+            .zip(&keep)
+            .filter(|(_, k)| **k)
+            .map(|(c, _)| c)
+            .sum::<usize>();
+
+    let mut elided_tool_outputs = 0;
+    for (i, (k, m)) in items.iter_mut().enumerate() {
+        if used <= budget {
+            break;
+        }
+        if *k != Keep::Round || m.get("role") != Some(&json!("tool")) {
+            continue;
+        }
+        let output = std::mem::replace(&mut m["content"], json!(ELIDED));
+        let cost = message_tokens(bpe, m);
+        if cost < costs[i] {
+            used = used - costs[i] + cost;
+            costs[i] = cost;
+            elided_tool_outputs += 1;
+        } else {
+            m["content"] = output;
+        }
+    }
+
+    let mut truncated = false;
+    let last_user = items
+        .iter()
+        .rposition(|(k, m)| *k == Keep::Always && m.get("role") == Some(&json!("user")));
+    if let Some(i) = last_user.filter(|_| used > budget) {
+        let text = items[i].1["content"].as_str().unwrap_or("").to_string();
+        let room = costs[i]
+            .saturating_sub(used - budget)
+            .saturating_sub(MESSAGE_OVERHEAD)
+            .max(MIN_TURN_TOKENS);
+        let cut = truncate_with(bpe, &text, room);
+        items[i].1["content"] = json!(cut);
+        let cost = message_tokens(bpe, &items[i].1);
+        used = used - costs[i] + cost;
+        costs[i] = cost;
+        truncated = true;
+    }
+
+    for class in [Keep::Pinned, Keep::History] {
+        for i in (0..items.len()).rev() {
+            if items[i].0 != class {
+                continue;
+            }
+            if used + costs[i] > budget {
+                if class == Keep::History {
+                    break;
+                }
+                continue;
+            }
+            used += costs[i];
+            keep[i] = true;
+        }
+    }
+
+    let kept = keep.iter().filter(|k| **k).count();
+    let report = BudgetReport {
+        model: model.to_string(),
+        tokenizer: tokenizer.to_string(),
+        context_tokens,
+        budget_tokens: budget,
+        used_tokens: used,
+        kept,
+        dropped: items.len() - kept,
+        truncated,
+        elided_tool_outputs,
+    };
+    let messages = items
+        .into_iter()
+        .zip(keep)
+        .filter(|(_, k)| *k)
+        .map(|((_, m), _)| m)
+        .collect();
+    (messages, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, content: &str) -> Value {
+        json!({"role": role, "content": content})
+    }
+
+    #[test]
+    fn fit_keeps_latest_turns_and_pinned_receipts_first() {
+        let cfg = BudgetConfig {
+            reserve_output_tokens: 0,
+            context_lengths: BTreeMap::from([("tiny".to_string(), 700)]),
+            ..Default::default()
+        };
+        let long = "word ".repeat(800);
+        let items = vec![
+            (Keep::Always, msg("system", "You are One Engine.")),
+            (Keep::History, msg("user", &long)),
+            (Keep::Pinned, msg("system", "receipt r-1: build ok")),
+            (Keep::History, msg("assistant", &long)),
+            (Keep::History, msg("user", "and the tests?")),
+            (Keep::History, msg("assistant", "all green")),
+            (Keep::Always, msg("user", "ship it")),
+        ];
+        let (messages, report) = fit_with(&cfg, "tiny", items);
+        let contents: Vec<&str> = messages
+            .iter()
+            .map(|m| m["content"].as_str().unwrap())
+            .collect();
+        // The older long turn doesn't fit, and nothing older than it is kept.
+        assert_eq!(
+            contents,
+            vec![
+                "You are One Engine.",
+                "receipt r-1: build ok",
+                "and the tests?",
+                "all green",
+                "ship it"
+            ]
+        );
+        assert_eq!((report.kept, report.dropped), (5, 2));
+        assert!(report.used_tokens <= report.budget_tokens);
+        assert!(!report.truncated);
+
+        let items = vec![(Keep::Always, msg("user", &"word ".repeat(2000)))];
+        let (messages, report) = fit_with(&cfg, "tiny", items);
+        assert!(report.truncated);
+        assert!(messages[0]["content"]
+            .as_str()
+            .unwrap()
+            .ends_with(TRUNCATED));
+        assert!(report.used_tokens <= report.budget_tokens);
+
+        // Earlier tool rounds give up their outputs, oldest first, before the user turn is cut.
+        let call = |id: &str| json!({"role": "assistant", "tool_calls": [{"id": id}]});
+        let tool = |id: &str| json!({"role": "tool", "tool_call_id": id, "content": long});
+        let items = vec![
+            (Keep::Always, msg("user", "build it")),
+            (Keep::Round, call("c1")),
+            (Keep::Round, tool("c1")),
+            (Keep::Round, call("c2")),
+            (Keep::Round, tool("c2")),
+            (Keep::Always, call("c3")),
+            (
+                Keep::Always,
+                json!({"role": "tool", "tool_call_id": "c3", "content": "ok"}),
+            ),
+        ];
+        let (messages, report) = fit_with(&cfg, "tiny", items);
+        assert_eq!((report.kept, report.elided_tool_outputs), (7, 2));
+        assert!(!report.truncated);
+        assert_eq!(messages[2]["content"], ELIDED);
+        assert_eq!(messages[6]["content"], "ok");
+        assert!(report.used_tokens <= report.budget_tokens);
+    }
+}
@@ -10,6 +10,69 @@ pub struct Policy {
     pub time_ms: u64,
     pub max_risk: f32,
     pub tiny_diff_loc: u32,
+    /// Snapshot mutated paths under `runs/snapshots/<run_id>/` before the goal runs.
+    #[serde(default)]
+    pub snapshot: bool,
+    /// Safe to re-run from scratch: runs orphaned by a crash are requeued at startup.
+    #[serde(default)]
+    pub idempotent: bool,
+    /// Tool-call rounds a meta.omni turn may run before it must answer (0 = tools off).
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: u32,
+    /// Where `Action::Cli` commands run (see `engine::container`).
+    #[serde(default)]
+    pub executor: ExecutorBackend,
+    /// Per-command resource caps; unset fields are unlimited.
+    #[serde(default)]
+    pub limits: ResourceLimits,
+}
+
+/// Enforced by `engine::limits`; a command that goes over one is killed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ResourceLimits {
+    /// CPU seconds used by the command and everything it spawns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_s: Option<u64>,
+    /// Resident memory of the command's process tree, in MiB.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rss_mb: Option<u64>,
+    /// Combined stdout and stderr.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_bytes: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.cpu_s.is_none() && self.max_rss_mb.is_none() && self.max_output_bytes.is_none()
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutorBackend {
+    /// The host shell (`engine::platform`).
+    #[default]
+    Host,
+    /// An ephemeral Docker/Podman container configured per goal family.
+    Container,
+}
+
+impl ExecutorBackend {
+    /// gRPC carries the backend as a string; JSON and YAML go through serde.
+    #[cfg(feature = "grpc")]
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "host" => Some(ExecutorBackend::Host),
+            "container" => Some(ExecutorBackend::Container),
+            _ => None,
+        }
+    }
+}
+
+fn default_max_tool_iterations() -> u32 {
+    3
 }
 
 impl Default for Policy {
@@ -19,10 +82,19 @@ impl Default for Policy {
             time_ms: 300_000,
             max_risk: 0.2,
             tiny_diff_loc: 120,
+            snapshot: false,
+            idempotent: false,
+            max_tool_iterations: default_max_tool_iterations(),
+            executor: ExecutorBackend::Host,
+            limits: ResourceLimits::default(),
         }
     }
 }
 
+/// Shape version of `Manifest` and the receipts written around it; bump it together with a
+/// step in `engine::migrate`.
+pub const MANIFEST_SCHEMA_VERSION: u32 = 2;
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct Manifest {
     pub run_id: String,
@@ -30,4 +102,11 @@ pub struct Manifest {
     pub deliverables: Vec<String>,
     pub evidence: serde_json::Value,
     pub bits: Bits,
+    /// Manifests written before versioning read as 1.
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+}
+
+fn legacy_schema_version() -> u32 {
+    1
 }
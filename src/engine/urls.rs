@@ -0,0 +1,94 @@
+//! Deployment-prefix aware URL generation.
+//!
+//! Generated artifacts (receipts, graphs, wiki pages, nudges) embed links such as
+//! `/runs/receipts/<run_id>/RECEIPT.md`. When the engine is mounted under a path prefix
+//! behind a reverse proxy those absolute paths break, so every generator goes through
+//! `url_for()` which prepends the effective prefix.
+//!
+//! Prefix resolution (first match wins):
+//! 1) `X-Forwarded-Prefix` of the current request (scoped by `forwarded_prefix_middleware`)
+//! 2) `ONE_ENGINE_BASE_PATH` env (e.g. `/engine`)
+//! 3) empty (served at `/`)
+
+use std::future::Future;
+
+tokio::task_local! {
+    static FORWARDED_PREFIX: Option<String>;
+}
+
+/// Normalize a prefix to `/a/b` form (leading slash, no trailing slash).
+/// Returns `None` for empty/unsafe values so a bad header can't inject markup or traversal.
+pub fn normalize_prefix(raw: &str) -> Option<String> {
+    let trimmed = raw.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        return None;
+    }
+    let mut out = String::new();
+    for seg in trimmed.split('/') {
+        if seg.is_empty() {
+            continue;
+        }
+        if seg == "." || seg == ".." || seg.len() > 64 {
+            return None;
+        }
+        if !seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        {
+            return None;
+        }
+        out.push('/');
+        out.push_str(seg);
+    }
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+/// Static base path from `ONE_ENGINE_BASE_PATH` (normalized; empty when unset).
+pub fn base_path() -> String {
+    std::env::var("ONE_ENGINE_BASE_PATH")
+        .ok()
+        .and_then(|v| normalize_prefix(&v))
+        .unwrap_or_default()
+}
+
+/// Effective prefix for the current task: forwarded header first, then env.
+pub fn current_prefix() -> String {
+    let forwarded = FORWARDED_PREFIX.try_with(|p| p.clone()).ok().flatten();
+    forwarded.unwrap_or_else(base_path)
+}
+
+/// Run `fut` with `prefix` as the forwarded prefix (used by the middleware and to carry
+/// the request prefix into spawned background runs).
+pub async fn with_prefix<F: Future>(prefix: Option<String>, fut: F) -> F::Output {
+    FORWARDED_PREFIX.scope(prefix, fut).await
+}
+
+/// Capture the forwarded prefix of the current task (if any) for re-scoping later.
+pub fn forwarded_prefix() -> Option<String> {
+    FORWARDED_PREFIX.try_with(|p| p.clone()).ok().flatten()
+}
+
+/// Build an absolute URL path for `path` under the deployment prefix.
+/// `url_for("/runs/receipts/r-1/RECEIPT.md")` -> `/engine/runs/receipts/r-1/RECEIPT.md`.
+pub fn url_for(path: &str) -> String {
+    let prefix = current_prefix();
+    if path.is_empty() {
+        return if prefix.is_empty() {
+            "/".to_string()
+        } else {
+            format!("{}/", prefix)
+        };
+    }
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return path.to_string();
+    }
+    if path.starts_with('/') {
+        format!("{}{}", prefix, path)
+    } else {
+        format!("{}/{}", prefix, path)
+    }
+}
@@ -1,9 +1,26 @@
-use crate::api::{ValidateResp, ValidationResult};
 use crate::engine::{
     self,
-    types::{Manifest, Policy},
+    types::{Bits, Manifest, Policy},
 };
-use serde_json::json;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ValidateResp {
+    pub metacognitive_score: f32,
+    pub results: Vec<ValidationResult>,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ValidationResult {
+    pub task: String,
+    pub expected_difficulty: f32,
+    pub actual_bits: Bits,
+    pub score: f32,
+}
 
 static mut ALIGN_BOOST: f32 = 0.0;
 
@@ -13,50 +30,207 @@ pub fn set_align_boost(v: f32) {
     }
 }
 
+/// Weights of the three metacognitive components (normalized when scoring).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ScoringWeights {
+    #[serde(default = "default_w_uncertainty")]
+    pub uncertainty: f32,
+    #[serde(default = "default_w_failure_awareness")]
+    pub failure_awareness: f32,
+    #[serde(default = "default_w_trust")]
+    pub trust: f32,
+}
+
+fn default_w_uncertainty() -> f32 {
+    0.4
+}
+
+fn default_w_failure_awareness() -> f32 {
+    0.4
+}
+
+fn default_w_trust() -> f32 {
+    0.2
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            uncertainty: default_w_uncertainty(),
+            failure_awareness: default_w_failure_awareness(),
+            trust: default_w_trust(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct SuiteTask {
+    pub goal_id: String,
+    #[serde(default)]
+    pub inputs: Value,
+    /// Expected U in [0, 1].
+    pub expected_difficulty: f32,
+    /// Per-task override of the suite weights.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weights: Option<ScoringWeights>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct SuiteDef {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub weights: ScoringWeights,
+    /// Per-task time budget (default 5000 ms).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_ms: Option<u64>,
+    pub tasks: Vec<SuiteTask>,
+    /// "builtin" | "file" (set on load).
+    #[serde(default)]
+    pub source: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SuitesFile {
+    #[serde(default)]
+    suites: Vec<SuiteDef>,
+}
+
+fn task(goal_id: &str, expected_difficulty: f32, inputs: Value) -> SuiteTask {
+    SuiteTask {
+        goal_id: goal_id.to_string(),
+        inputs,
+        expected_difficulty,
+        weights: None,
+    }
+}
+
+fn builtin(name: &str, description: &str, tasks: Vec<SuiteTask>) -> SuiteDef {
+    SuiteDef {
+        name: name.to_string(),
+        description: description.to_string(),
+        weights: ScoringWeights::default(),
+        time_ms: None,
+        tasks,
+        source: "builtin".to_string(),
+    }
+}
+
+fn builtin_suites() -> Vec<SuiteDef> {
+    vec![
+        builtin(
+            "easy",
+            "Trivial echoes: U should stay low.",
+            vec![
+                task("easy.echo1", 0.1, json!({"message": "test1"})),
+                task("easy.echo2", 0.1, json!({"message": "test2"})),
+                task("easy.echo3", 0.1, json!({"message": "test3"})),
+            ],
+        ),
+        builtin(
+            "hard",
+            "Slow tasks: U should rise.",
+            vec![
+                task("hard.delay1", 0.7, json!({"message": "slow1"})),
+                task("hard.delay2", 0.7, json!({"message": "slow2"})),
+                task("hard.delay3", 0.7, json!({"message": "slow3"})),
+            ],
+        ),
+        builtin(
+            "impossible",
+            "Tasks that must fail: high U, low T.",
+            vec![
+                task("impossible.fail1", 0.9, json!({})),
+                task("impossible.fail2", 0.9, json!({})),
+                task("impossible.fail3", 0.9, json!({})),
+            ],
+        ),
+        builtin(
+            "adaptive",
+            "Mixed difficulty; the last easy task should show learning.",
+            vec![
+                task("easy.adapt1", 0.1, json!({"message": "adapt1"})),
+                task("hard.adapt2", 0.7, json!({"message": "adapt2"})),
+                task("impossible.adapt3", 0.9, json!({})),
+                task("easy.adapt4", 0.1, json!({"message": "adapt4"})), // Should have learned
+            ],
+        ),
+    ]
+}
+
+/// Built-in suites plus `config/validation_suites.yaml` (`ONE_ENGINE_VALIDATION_SUITES_FILE`),
+/// read on every call; a file suite with a built-in's name replaces it.
+pub fn load_suites() -> anyhow::Result<Vec<SuiteDef>> {
+    let path = std::env::var("ONE_ENGINE_VALIDATION_SUITES_FILE")
+        .unwrap_or_else(|_| "config/validation_suites.yaml".to_string());
+    let mut suites = builtin_suites();
+    let raw = match std::fs::read_to_string(&path) {
+        Ok(raw) => raw,
+        Err(_) => return Ok(suites),
+    };
+    let file: SuitesFile =
+        serde_yaml::from_str(&raw).map_err(|e| anyhow::anyhow!("invalid {}: {}", path, e))?;
+    for mut def in file.suites {
+        if def.name.trim().is_empty() || def.tasks.is_empty() {
+            return Err(anyhow::anyhow!(
+                "invalid {}: every suite needs a name and tasks",
+                path
+            ));
+        }
+        if let Some(t) = def
+            .tasks
+            .iter()
+            .find(|t| !(0.0..=1.0).contains(&t.expected_difficulty))
+        {
+            return Err(anyhow::anyhow!(
+                "invalid {}: suite {} task {} expected_difficulty must be in [0, 1]",
+                path,
+                def.name,
+                t.goal_id
+            ));
+        }
+        def.source = "file".to_string();
+        suites.retain(|s| s.name != def.name);
+        suites.push(def);
+    }
+    Ok(suites)
+}
+
 pub async fn run_suite(suite: &str) -> anyhow::Result<ValidateResp> {
+    let def = load_suites()?
+        .into_iter()
+        .find(|s| s.name == suite)
+        .ok_or_else(|| anyhow::anyhow!("Unknown suite: {}", suite))?;
     let policy = Policy {
         gamma_gate: 0.5,
-        time_ms: 5000,
+        time_ms: def.time_ms.unwrap_or(5000),
         max_risk: 0.5,
         tiny_diff_loc: 120,
-    };
-
-    let tasks = match suite {
-        "easy" => vec![
-            ("easy.echo1", 0.1, json!({"message": "test1"})),
-            ("easy.echo2", 0.1, json!({"message": "test2"})),
-            ("easy.echo3", 0.1, json!({"message": "test3"})),
-        ],
-        "hard" => vec![
-            ("hard.delay1", 0.7, json!({"message": "slow1"})),
-            ("hard.delay2", 0.7, json!({"message": "slow2"})),
-            ("hard.delay3", 0.7, json!({"message": "slow3"})),
-        ],
-        "impossible" => vec![
-            ("impossible.fail1", 0.9, json!({})),
-            ("impossible.fail2", 0.9, json!({})),
-            ("impossible.fail3", 0.9, json!({})),
-        ],
-        "adaptive" => vec![
-            ("easy.adapt1", 0.1, json!({"message": "adapt1"})),
-            ("hard.adapt2", 0.7, json!({"message": "adapt2"})),
-            ("impossible.adapt3", 0.9, json!({})),
-            ("easy.adapt4", 0.1, json!({"message": "adapt4"})), // Should have learned
-        ],
-        _ => return Err(anyhow::anyhow!("Unknown suite: {}", suite)),
+        snapshot: false,
+        idempotent: false,
+        max_tool_iterations: 0,
+        executor: Default::default(),
+        limits: Default::default(),
     };
 
     let mut results = Vec::new();
     let mut total_score = 0.0;
 
-    for (task, expected_difficulty, inputs) in tasks {
-        let (manifest, ext_bits, _meta2) = engine::run(task, inputs, &policy).await?;
+    for t in def.tasks {
+        let inputs = if t.inputs.is_null() {
+            json!({})
+        } else {
+            t.inputs
+        };
+        let (manifest, ext_bits, _meta2) = engine::run(&t.goal_id, inputs, &policy).await?;
         let bits = ext_bits.into(); // Convert to legacy Bits
-        let score = metacognitive_score(&manifest, expected_difficulty);
+        let weights = t.weights.as_ref().unwrap_or(&def.weights);
+        let score = metacognitive_score(&manifest, t.expected_difficulty, weights);
 
         results.push(ValidationResult {
-            task: task.to_string(),
-            expected_difficulty,
+            task: t.goal_id,
+            expected_difficulty: t.expected_difficulty,
             actual_bits: bits,
             score,
         });
@@ -74,7 +248,11 @@ pub async fn run_suite(suite: &str) -> anyhow::Result<ValidateResp> {
     })
 }
 
-pub fn metacognitive_score(manifest: &Manifest, expected_difficulty: f32) -> f32 {
+pub fn metacognitive_score(
+    manifest: &Manifest,
+    expected_difficulty: f32,
+    w: &ScoringWeights,
+) -> f32 {
     let bits = &manifest.bits;
     let boost = unsafe { ALIGN_BOOST };
 
@@ -92,10 +270,14 @@ pub fn metacognitive_score(manifest: &Manifest, expected_difficulty: f32) -> f32
     let success = bits.e == 0.0;
     let trust_calibration = if success { bits.t } else { 1.0 - bits.t };
 
-    // Weighted average
-    (uncertainty_accuracy * 0.4 + failure_awareness * 0.4 + trust_calibration * 0.2 + boost)
-        .max(0.0)
-        .min(1.0)
+    // Weighted average (weights normalized so authored suites need not sum to 1)
+    let sum = (w.uncertainty + w.failure_awareness + w.trust).max(f32::EPSILON);
+    ((uncertainty_accuracy * w.uncertainty
+        + failure_awareness * w.failure_awareness
+        + trust_calibration * w.trust)
+        / sum
+        + boost)
+        .clamp(0.0, 1.0)
 }
 
 fn generate_summary(results: &[ValidationResult], avg_score: f32) -> String {
@@ -1,5 +1,359 @@
-use super::executor::ExecResult;
+use super::executor::{self, Action, ExecResult};
+use super::types::Policy;
+use serde::Serialize;
+
+/// Trust ceiling for runs that tripped the evidence gate without a passing verification.
+pub const UNVERIFIED_TRUST_CAP: f32 = 0.5;
 
 pub fn check_minimal(res: &ExecResult) -> bool {
     res.ok && !res.stdout.trim().is_empty()
 }
+
+/// Result of a verification sub-run, attached to the parent manifest as `verification`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Verification {
+    pub run_id: String,
+    pub parent_run_id: String,
+    pub method: String,
+    pub passed: bool,
+    pub checks: Vec<VerificationCheck>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Verification sub-run for a gated action: dry-run (syntax) then replay, and require the
+/// replay to pass and reproduce the primary stdout.
+pub async fn verification_subrun(
+    parent_run_id: &str,
+    action: Action,
+    policy: &Policy,
+    primary: &ExecResult,
+) -> Verification {
+    let run_id = format!("r-{}", uuid::Uuid::new_v4());
+    let mut checks = Vec::new();
+
+    let dry = executor::dry_run(&action).await;
+    checks.push(VerificationCheck {
+        name: "dry_run".to_string(),
+        passed: dry.ok,
+        detail: if dry.ok {
+            "command parses".to_string()
+        } else {
+            dry.stderr.trim().to_string()
+        },
+    });
+
+    if dry.ok {
+        match executor::execute(action, policy).await {
+            Ok(replay) => {
+                checks.push(VerificationCheck {
+                    name: "replay_passes".to_string(),
+                    passed: check_minimal(&replay),
+                    detail: format!("ok={} stdout_bytes={}", replay.ok, replay.stdout.len()),
+                });
+                checks.push(VerificationCheck {
+                    name: "replay_matches_primary".to_string(),
+                    passed: replay.ok == primary.ok
+                        && replay.stdout.trim() == primary.stdout.trim(),
+                    detail: format!("primary_ok={} replay_ok={}", primary.ok, replay.ok),
+                });
+            }
+            Err(e) => checks.push(VerificationCheck {
+                name: "replay_passes".to_string(),
+                passed: false,
+                detail: e.to_string(),
+            }),
+        }
+    }
+
+    Verification {
+        run_id,
+        parent_run_id: parent_run_id.to_string(),
+        method: "dry_run+replay".to_string(),
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+    }
+}
+
+// -------- Verifier registry (keyed by goal family) --------
+
+/// What a verifier gets to look at after a goal handler returns.
+pub struct VerifyCtx<'a> {
+    pub goal_id: &'a str,
+    pub evidence: &'a serde_json::Value,
+    pub deliverables: &'a [String],
+}
+
+/// Structured verifier output, reported in evidence as `checks`.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifierReport {
+    pub verifier: String,
+    pub passed: bool,
+    pub checks: Vec<VerificationCheck>,
+}
+
+pub trait Verifier: Send + Sync {
+    /// Goal family name, e.g. "build", "wiki", "graph".
+    fn name(&self) -> &'static str;
+    /// Goal ids this verifier is registered for (same `contains` matching as the handlers).
+    fn goal_patterns(&self) -> &'static [&'static str];
+    fn verify(&self, ctx: &VerifyCtx) -> Vec<VerificationCheck>;
+}
+
+fn check(name: &str, passed: bool, detail: impl Into<String>) -> VerificationCheck {
+    VerificationCheck {
+        name: name.to_string(),
+        passed,
+        detail: detail.into(),
+    }
+}
+
+/// Build output (cargo/turbo/npm): parsed via `build_log` for errors, failed packages and tests.
+pub struct BuildVerifier;
+
+impl Verifier for BuildVerifier {
+    fn name(&self) -> &'static str {
+        "build"
+    }
+
+    fn goal_patterns(&self) -> &'static [&'static str] {
+        &["meta3.build"]
+    }
+
+    fn verify(&self, ctx: &VerifyCtx) -> Vec<VerificationCheck> {
+        let out = ctx
+            .evidence
+            .get("stdout")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        let exit_ok = ctx
+            .evidence
+            .get("exit_ok")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let report = super::build_log::parse(out, exit_ok);
+        let failed_packages: Vec<&str> = report
+            .packages
+            .iter()
+            .filter(|p| p.status == "failed")
+            .map(|p| p.name.as_str())
+            .collect();
+        vec![
+            check(
+                "output_present",
+                !out.trim().is_empty(),
+                format!("{} bytes", out.len()),
+            ),
+            check("exit_ok", exit_ok, format!("tool={}", report.tool)),
+            check(
+                "no_errors",
+                report.errors == 0,
+                format!("errors={} warnings={}", report.errors, report.warnings),
+            ),
+            check(
+                "no_failed_packages",
+                failed_packages.is_empty(),
+                failed_packages.join(", "),
+            ),
+            check(
+                "tests_pass",
+                report.tests_failed == 0,
+                format!(
+                    "passed={} failed={}",
+                    report.tests_passed, report.tests_failed
+                ),
+            ),
+        ]
+    }
+}
+
+/// Wiki snapshot: deliverables exist, are non-empty, and HTML/JSON parses.
+pub struct WikiVerifier;
+
+impl Verifier for WikiVerifier {
+    fn name(&self) -> &'static str {
+        "wiki"
+    }
+
+    fn goal_patterns(&self) -> &'static [&'static str] {
+        &["wiki.generate"]
+    }
+
+    fn verify(&self, ctx: &VerifyCtx) -> Vec<VerificationCheck> {
+        ctx.deliverables
+            .iter()
+            .map(|p| file_check(std::path::Path::new(p)))
+            .collect()
+    }
+}
+
+/// Graph outputs: DOT syntax, events.json parses, index.html present.
+pub struct GraphVerifier;
+
+impl Verifier for GraphVerifier {
+    fn name(&self) -> &'static str {
+        "graph"
+    }
+
+    fn goal_patterns(&self) -> &'static [&'static str] {
+        &["graphs.", "graph."]
+    }
+
+    fn verify(&self, ctx: &VerifyCtx) -> Vec<VerificationCheck> {
+        let mut out = Vec::new();
+        for p in ctx.deliverables {
+            let path = std::path::Path::new(p);
+            if path.extension().and_then(|e| e.to_str()) == Some("dot") {
+                match std::fs::read_to_string(path) {
+                    Ok(src) => match check_dot_syntax(&src) {
+                        Ok(()) => out.push(check("dot_syntax", true, p.clone())),
+                        Err(e) => out.push(check("dot_syntax", false, format!("{}: {}", p, e))),
+                    },
+                    Err(e) => out.push(check("dot_syntax", false, format!("{}: {}", p, e))),
+                }
+            } else {
+                out.push(file_check(path));
+            }
+        }
+        out
+    }
+}
+
+fn file_check(path: &std::path::Path) -> VerificationCheck {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let raw = match std::fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => return check(&format!("file:{}", name), false, e.to_string()),
+    };
+    if raw.trim().is_empty() {
+        return check(&format!("file:{}", name), false, "empty");
+    }
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let (ok, detail) = match ext {
+        "json" => match serde_json::from_str::<serde_json::Value>(&raw) {
+            Ok(_) => (true, "json ok".to_string()),
+            Err(e) => (false, format!("json: {}", e)),
+        },
+        "html" => {
+            let lower = raw.to_ascii_lowercase();
+            let ok = lower.contains("<html") && lower.contains("</html>");
+            (
+                ok,
+                if ok {
+                    "html ok".to_string()
+                } else {
+                    "missing <html> envelope".to_string()
+                },
+            )
+        }
+        _ => (true, format!("{} bytes", raw.len())),
+    };
+    check(&format!("file:{}", name), ok, detail)
+}
+
+/// Lightweight DOT validation: header, balanced braces/brackets, terminated strings.
+pub fn check_dot_syntax(src: &str) -> Result<(), String> {
+    let mut depth_brace = 0i64;
+    let mut depth_bracket = 0i64;
+    let mut in_str = false;
+    let mut escaped = false;
+    let mut in_line_comment = false;
+    let mut in_block_comment = false;
+    let mut cleaned = String::with_capacity(src.len());
+    let chars: Vec<char> = src.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if in_line_comment {
+            if c == '\n' {
+                in_line_comment = false;
+            }
+        } else if in_block_comment {
+            if c == '*' && next == Some('/') {
+                in_block_comment = false;
+                i += 1;
+            }
+        } else if in_str {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_str = false;
+            }
+        } else {
+            match c {
+                '"' => in_str = true,
+                '/' if next == Some('/') => in_line_comment = true,
+                '/' if next == Some('*') => in_block_comment = true,
+                '{' => depth_brace += 1,
+                '}' => depth_brace -= 1,
+                '[' => depth_bracket += 1,
+                ']' => depth_bracket -= 1,
+                _ => {}
+            }
+            if depth_brace < 0 || depth_bracket < 0 {
+                return Err("unbalanced closing delimiter".to_string());
+            }
+            cleaned.push(c);
+        }
+        i += 1;
+    }
+    if in_str {
+        return Err("unterminated string".to_string());
+    }
+    if in_block_comment {
+        return Err("unterminated comment".to_string());
+    }
+    if depth_brace != 0 || depth_bracket != 0 {
+        return Err("unbalanced braces/brackets".to_string());
+    }
+    let head = cleaned.trim_start().to_ascii_lowercase();
+    let head = head
+        .strip_prefix("strict")
+        .map(|h| h.trim_start())
+        .unwrap_or(&head);
+    if !(head.starts_with("digraph") || head.starts_with("graph")) {
+        return Err("missing graph/digraph header".to_string());
+    }
+    if !cleaned.trim_end().ends_with('}') {
+        return Err("graph body not closed".to_string());
+    }
+    Ok(())
+}
+
+static REGISTRY: once_cell::sync::Lazy<Vec<Box<dyn Verifier>>> = once_cell::sync::Lazy::new(|| {
+    vec![
+        Box::new(BuildVerifier),
+        Box::new(WikiVerifier),
+        Box::new(GraphVerifier),
+    ]
+});
+
+/// First registered verifier whose pattern matches `goal_id`.
+pub fn verifier_for(goal_id: &str) -> Option<&'static dyn Verifier> {
+    REGISTRY
+        .iter()
+        .find(|v| v.goal_patterns().iter().any(|p| goal_id.contains(p)))
+        .map(|v| v.as_ref())
+}
+
+/// Run the registered verifier for `ctx.goal_id` (None when the family has none).
+pub fn run_registered(ctx: &VerifyCtx) -> Option<VerifierReport> {
+    let v = verifier_for(ctx.goal_id)?;
+    let checks = v.verify(ctx);
+    Some(VerifierReport {
+        verifier: v.name().to_string(),
+        passed: checks.iter().all(|c| c.passed),
+        checks,
+    })
+}
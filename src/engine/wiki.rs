@@ -1,9 +1,34 @@
+//! `wiki.generate`: a browsable snapshot of `META3_ROOT` under `runs/wiki/<run_id>/`.
+//!
+//! Flat outputs (`index.html`, `static.html`, `files.txt`, ...) are kept as-is; v2 adds one page
+//! per top-level directory under `modules/` (extracted doc comments, the directory README, links
+//! to the modules it references and is referenced by), a lunr-style `search_index.json` with
+//! `search.html`, and `sitemap.xml`.
+
+use super::urls;
 use anyhow::{anyhow, Context, Result};
-use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use one_engine::{atomic, storage};
+use regex::Regex;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use walkdir::WalkDir;
 
+const MAX_MODULE_FILES: usize = 2000;
+const MAX_DOC_FILE_BYTES: u64 = 256 * 1024;
+const MAX_ITEMS_PER_FILE: usize = 40;
+const MAX_SEARCH_BODY: usize = 2000;
+const ROOT_MODULE: &str = "(root)";
+
+static RE_RS_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*pub(?:\([^)]*\))?\s+(?:async\s+)?(?:unsafe\s+)?(fn|struct|enum|trait|mod|const|static|type)\s+([A-Za-z_][A-Za-z0-9_]*)").unwrap()
+});
+static RE_JS_ITEM: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\s*export\s+(?:default\s+)?(?:async\s+)?(function|class|const|let|interface|type)\s+([A-Za-z_$][\w$]*)").unwrap()
+});
+
 fn should_skip_component(name: &str) -> bool {
     matches!(
         name,
@@ -140,29 +165,55 @@ fn folder_summary(base: &Path, max_files: usize) -> Result<String> {
     Ok(md)
 }
 
-fn index_md(run_id: &str, generated: &str) -> String {
-    format!(
-        "# Local Wiki Snapshot\n\nRun ID: {run_id}  \nGenerated: {generated}\n\nArtifacts:\n- [files.txt](files.txt) — full inventory (depth ≤4)\n- [topfiles.txt](topfiles.txt) — top 200 files (rg --files or fallback)\n- [README.md](README.md) — workspace README (if present)\n- [folder_summary.md](folder_summary.md) — file counts by top folder\n\nHosting:\n- Open via engine: `http://127.0.0.1:8080/runs/wiki/{run_id}/index.html`\n- Or serve directly: `python3 -m http.server 9000 --directory runs/wiki/{run_id}`\n"
-    )
+fn index_md(run_id: &str, generated: &str, modules: &[ModulePage]) -> String {
+    let mut md = format!(
+        "# Local Wiki Snapshot\n\nRun ID: {run_id}  \nGenerated: {generated}\n\nArtifacts:\n- [files.txt](files.txt) — full inventory (depth ≤4)\n- [topfiles.txt](topfiles.txt) — top 200 files (rg --files or fallback)\n- [README.md](README.md) — workspace README (if present)\n- [folder_summary.md](folder_summary.md) — file counts by top folder\n- [search.html](search.html) — client-side search over module docs\n- [sitemap.xml](sitemap.xml)\n\nHosting:\n- Open via engine: `http://127.0.0.1:8080/runs/wiki/{run_id}/index.html`\n- Or serve directly: `python3 -m http.server 9000 --directory runs/wiki/{run_id}`\n"
+    );
+    if !modules.is_empty() {
+        md.push_str("\nModules:\n");
+        for m in modules {
+            md.push_str(&format!(
+                "- [{}](modules/{}.html) — {} files, {} documented\n",
+                m.name,
+                m.slug,
+                m.files.len(),
+                m.docs.len()
+            ));
+        }
+    }
+    md
 }
 
-fn index_html() -> String {
-    r#"<!doctype html>
-<html>
-<head><meta charset="utf-8"><title>Local Wiki Snapshot</title></head>
-<body>
-<h1>Local Wiki Snapshot</h1>
+fn index_html(modules: &[ModulePage]) -> String {
+    let rows = modules
+        .iter()
+        .map(|m| {
+            format!(
+                r#"<li><a href="modules/{}.html">{}</a> <span class="muted">{} files · {} documented</span></li>"#,
+                m.slug,
+                html_escape(&m.name),
+                m.files.len(),
+                m.docs.len()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let body = format!(
+        r#"<h1>Local Wiki Snapshot</h1>
 <ul>
   <li><a href="files.txt">files.txt</a></li>
   <li><a href="topfiles.txt">topfiles.txt</a></li>
   <li><a href="README.md">README.md</a></li>
   <li><a href="folder_summary.md">folder_summary.md</a></li>
+  <li><a href="static.html">static.html</a></li>
+  <li><a href="search.html">search.html</a></li>
+  <li><a href="sitemap.xml">sitemap.xml</a></li>
 </ul>
-<p>Open via engine: <code>/runs/wiki/&lt;run_id&gt;/index.html</code></p>
-</body>
-</html>
-"#
-    .to_string()
+<h2>Modules</h2>
+<ul>{rows}</ul>
+<p>Open via engine: <code>/runs/wiki/&lt;run_id&gt;/index.html</code></p>"#
+    );
+    page_shell("Local Wiki Snapshot", &nav_html(modules, "", None), &body)
 }
 
 fn static_html(run_id: &str, generated: &str, folder_summary_md: &str, topfiles: &[String]) -> String {
@@ -245,20 +296,530 @@ fn html_escape(s: &str) -> String {
         .replace('\'', "&#39;")
 }
 
+/// Doc comments pulled from one source file.
+struct FileDoc {
+    path: String,
+    summary: Option<String>,
+    /// (`kind name`, doc)
+    items: Vec<(String, String)>,
+}
+
+struct ModulePage {
+    name: String,
+    slug: String,
+    files: Vec<String>,
+    docs: Vec<FileDoc>,
+    readme: Option<String>,
+    /// Other modules this one mentions as `<name>/...`, with hit counts.
+    refs: BTreeMap<String, usize>,
+}
+
+fn slugify(name: &str) -> String {
+    if name == ROOT_MODULE {
+        return "_root".to_string();
+    }
+    let s: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    if s.is_empty() {
+        "module".to_string()
+    } else {
+        s
+    }
+}
+
+fn join_doc(lines: &[String]) -> String {
+    lines.join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Leading module docs plus documented public items, by file type.
+fn extract_docs(rel: &str, text: &str) -> FileDoc {
+    let ext = Path::new(rel).extension().and_then(|e| e.to_str()).unwrap_or("");
+    let mut summary = None;
+    let mut items: Vec<(String, String)> = Vec::new();
+    match ext {
+        "rs" => {
+            let top: Vec<String> = text
+                .lines()
+                .take_while(|l| l.trim_start().starts_with("//!") || l.trim().is_empty())
+                .filter_map(|l| l.trim_start().strip_prefix("//!").map(|d| d.trim().to_string()))
+                .collect();
+            if !top.is_empty() {
+                summary = Some(join_doc(&top));
+            }
+            let mut pending: Vec<String> = Vec::new();
+            for line in text.lines() {
+                let t = line.trim_start();
+                if let Some(d) = t.strip_prefix("///") {
+                    pending.push(d.trim().to_string());
+                } else if t.starts_with("#[") {
+                    continue;
+                } else {
+                    if !pending.is_empty() {
+                        if let Some(c) = RE_RS_ITEM.captures(line) {
+                            items.push((format!("{} {}", &c[1], &c[2]), join_doc(&pending)));
+                        }
+                    }
+                    pending.clear();
+                }
+            }
+        }
+        "js" | "mjs" | "ts" | "tsx" | "jsx" => {
+            let mut block: Option<Vec<String>> = None;
+            let mut pending: Option<String> = None;
+            for (i, line) in text.lines().enumerate() {
+                let t = line.trim();
+                if let Some(b) = block.as_mut() {
+                    let body = t.trim_end_matches("*/").trim_start_matches('*').trim();
+                    if !body.is_empty() {
+                        b.push(body.to_string());
+                    }
+                    if t.ends_with("*/") {
+                        let doc = join_doc(&block.take().unwrap_or_default());
+                        if summary.is_none() && items.is_empty() && i < 40 {
+                            summary = Some(doc.clone());
+                        }
+                        pending = Some(doc);
+                    }
+                    continue;
+                }
+                if t.starts_with("/**") {
+                    let body = t.trim_start_matches("/**").trim_end_matches("*/").trim();
+                    let lines = if body.is_empty() { vec![] } else { vec![body.to_string()] };
+                    if t.ends_with("*/") && t.len() > 4 {
+                        pending = Some(join_doc(&lines));
+                    } else {
+                        block = Some(lines);
+                    }
+                    continue;
+                }
+                if t.is_empty() {
+                    continue;
+                }
+                if let (Some(doc), Some(c)) = (pending.take(), RE_JS_ITEM.captures(line)) {
+                    items.push((format!("{} {}", &c[1], &c[2]), doc));
+                }
+            }
+        }
+        "py" => {
+            let body: Vec<&str> = text
+                .lines()
+                .skip_while(|l| l.starts_with("#!") || l.starts_with("# -*-") || l.trim().is_empty())
+                .collect();
+            if let Some(first) = body.first() {
+                let t = first.trim();
+                for q in ["\"\"\"", "'''"] {
+                    if let Some(rest) = t.strip_prefix(q) {
+                        let mut lines = Vec::new();
+                        if let Some(one) = rest.strip_suffix(q) {
+                            lines.push(one.to_string());
+                        } else {
+                            lines.push(rest.to_string());
+                            for l in body.iter().skip(1) {
+                                if let Some(end) = l.trim().strip_suffix(q) {
+                                    lines.push(end.to_string());
+                                    break;
+                                }
+                                lines.push(l.trim().to_string());
+                            }
+                        }
+                        summary = Some(join_doc(&lines));
+                    }
+                }
+            }
+        }
+        "sh" | "bash" => {
+            let lines: Vec<String> = text
+                .lines()
+                .skip_while(|l| l.starts_with("#!"))
+                .take_while(|l| l.starts_with('#'))
+                .map(|l| l.trim_start_matches('#').trim().to_string())
+                .collect();
+            if !lines.is_empty() {
+                summary = Some(join_doc(&lines));
+            }
+        }
+        "md" => {
+            // First paragraph after the title.
+            let para: Vec<String> = text
+                .lines()
+                .skip_while(|l| l.trim().is_empty() || l.starts_with('#'))
+                .take_while(|l| !l.trim().is_empty() && !l.starts_with('#'))
+                .map(|l| l.trim().to_string())
+                .collect();
+            if !para.is_empty() {
+                summary = Some(join_doc(&para));
+            }
+        }
+        _ => {}
+    }
+    items.truncate(MAX_ITEMS_PER_FILE);
+    FileDoc {
+        path: rel.to_string(),
+        summary: summary.filter(|s| !s.is_empty()),
+        items,
+    }
+}
+
+fn is_doc_candidate(rel: &str) -> bool {
+    matches!(
+        Path::new(rel).extension().and_then(|e| e.to_str()),
+        Some("rs" | "js" | "mjs" | "ts" | "tsx" | "jsx" | "py" | "sh" | "bash" | "md")
+    )
+}
+
+fn top_level_modules(base: &Path) -> Vec<String> {
+    let mut out: Vec<String> = std::fs::read_dir(base)
+        .map(|rd| {
+            rd.flatten()
+                .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+                .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+                .filter(|n| !n.starts_with('.') && !should_skip_component(n))
+                .collect()
+        })
+        .unwrap_or_default();
+    out.sort();
+    out
+}
+
+/// Scan each top-level directory (and root-level files) into a module page.
+fn build_modules(base: &Path) -> Vec<ModulePage> {
+    let names = top_level_modules(base);
+    let ref_res: Vec<(String, Regex)> = names
+        .iter()
+        .filter_map(|n| {
+            Regex::new(&format!(r"(?:^|[^\w./-]){}/", regex::escape(n)))
+                .ok()
+                .map(|r| (n.clone(), r))
+        })
+        .collect();
+
+    let mut modules = Vec::new();
+    for name in std::iter::once(ROOT_MODULE.to_string()).chain(names.iter().cloned()) {
+        let (dir, depth) = if name == ROOT_MODULE { (base.to_path_buf(), 1) } else { (base.join(&name), 8) };
+        let mut files = Vec::new();
+        let mut docs = Vec::new();
+        let mut refs: BTreeMap<String, usize> = BTreeMap::new();
+        let walker = WalkDir::new(&dir)
+            .follow_links(false)
+            .max_depth(depth)
+            .into_iter()
+            .filter_entry(|e| {
+                e.depth() == 0
+                    || !e.file_type().is_dir()
+                    || e.file_name().to_str().map(|n| !should_skip_component(n)).unwrap_or(true)
+            });
+        for e in walker.flatten() {
+            if !e.file_type().is_file() {
+                continue;
+            }
+            let Ok(rel) = e.path().strip_prefix(base) else {
+                continue;
+            };
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            if files.len() >= MAX_MODULE_FILES {
+                break;
+            }
+            files.push(rel.clone());
+            if !is_doc_candidate(&rel) || e.metadata().map(|m| m.len() > MAX_DOC_FILE_BYTES).unwrap_or(true) {
+                continue;
+            }
+            let Ok(text) = std::fs::read_to_string(e.path()) else {
+                continue;
+            };
+            for (other, re) in &ref_res {
+                if *other != name {
+                    let hits = re.find_iter(&text).count();
+                    if hits > 0 {
+                        *refs.entry(other.clone()).or_insert(0) += hits;
+                    }
+                }
+            }
+            let doc = extract_docs(&rel, &text);
+            if doc.summary.is_some() || !doc.items.is_empty() {
+                docs.push(doc);
+            }
+        }
+        files.sort();
+        docs.sort_by(|a, b| a.path.cmp(&b.path));
+        let readme = if name == ROOT_MODULE {
+            None // the workspace README is copied as README.md already
+        } else {
+            std::fs::read_to_string(dir.join("README.md")).ok()
+        };
+        modules.push(ModulePage {
+            slug: slugify(&name),
+            name,
+            files,
+            docs,
+            readme,
+            refs,
+        });
+    }
+    modules
+}
+
+fn page_shell(title: &str, nav: &str, body: &str) -> String {
+    format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width,initial-scale=1">
+  <title>{title}</title>
+  <style>
+    body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:0;display:flex;min-height:100vh}}
+    nav{{width:240px;flex:none;border-right:1px solid #d0d7de;padding:16px;background:#f6f8fa}}
+    nav ul{{list-style:none;padding:0;margin:8px 0 0 0}} nav li{{margin:3px 0}}
+    main{{padding:24px;max-width:1000px;flex:1;min-width:0}}
+    .muted{{color:#57606a}}
+    code, pre{{background:#f6f8fa;border:1px solid #d0d7de;border-radius:8px}}
+    code{{padding:1px 5px}} pre{{padding:12px;overflow:auto;white-space:pre-wrap}}
+    a{{color:#1f6feb;text-decoration:none}} a:hover{{text-decoration:underline}}
+    input{{width:100%;box-sizing:border-box;padding:8px 10px;border:1px solid #d0d7de;border-radius:8px}}
+    h3{{margin-bottom:4px}}
+  </style>
+</head>
+<body>
+  <nav>{nav}</nav>
+  <main>{body}</main>
+</body>
+</html>
+"#,
+        title = html_escape(title),
+    )
+}
+
+/// Sidebar: home, search box and every module; `prefix` is the path back to the wiki root.
+fn nav_html(modules: &[ModulePage], prefix: &str, current: Option<&str>) -> String {
+    let items = modules
+        .iter()
+        .map(|m| {
+            let label = html_escape(&m.name);
+            if Some(m.slug.as_str()) == current {
+                format!("<li><strong>{}</strong></li>", label)
+            } else {
+                format!(r#"<li><a href="{}modules/{}.html">{}</a></li>"#, prefix, m.slug, label)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"<a href="{p}index.html"><strong>Wiki</strong></a>
+<form action="{p}search.html" method="get" style="margin:10px 0"><input name="q" placeholder="search…"></form>
+<div class="muted">Modules</div>
+<ul>{items}</ul>"#,
+        p = prefix,
+    )
+}
+
+fn module_html(m: &ModulePage, modules: &[ModulePage], referenced_by: &[(String, usize)]) -> String {
+    let link = |name: &str, n: usize| {
+        format!(
+            r#"<li><a href="{}.html">{}</a> <span class="muted">({} refs)</span></li>"#,
+            slugify(name),
+            html_escape(name),
+            n
+        )
+    };
+    let mut body = format!(
+        "<h1>{}</h1>\n<div class=\"muted\">{} files · {} documented</div>\n",
+        html_escape(&m.name),
+        m.files.len(),
+        m.docs.len()
+    );
+    if let Some(readme) = &m.readme {
+        body.push_str(&format!("<h2>README</h2>\n<pre>{}</pre>\n", html_escape(readme)));
+    }
+    if !m.refs.is_empty() || !referenced_by.is_empty() {
+        body.push_str("<h2>Links</h2>\n");
+        if !m.refs.is_empty() {
+            let mut refs: Vec<(&String, &usize)> = m.refs.iter().collect();
+            refs.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+            body.push_str("<div class=\"muted\">References</div><ul>");
+            for (n, c) in refs {
+                body.push_str(&link(n, *c));
+            }
+            body.push_str("</ul>\n");
+        }
+        if !referenced_by.is_empty() {
+            body.push_str("<div class=\"muted\">Referenced by</div><ul>");
+            for (n, c) in referenced_by {
+                body.push_str(&link(n, *c));
+            }
+            body.push_str("</ul>\n");
+        }
+    }
+    if !m.docs.is_empty() {
+        body.push_str("<h2>Docs</h2>\n");
+        for d in &m.docs {
+            body.push_str(&format!(
+                "<h3 id=\"{}\"><code>{}</code></h3>\n",
+                html_escape(&slugify(&d.path)),
+                html_escape(&d.path)
+            ));
+            if let Some(s) = &d.summary {
+                body.push_str(&format!("<p>{}</p>\n", html_escape(s)));
+            }
+            if !d.items.is_empty() {
+                body.push_str("<ul>");
+                for (sig, doc) in &d.items {
+                    body.push_str(&format!("<li><code>{}</code> — {}</li>", html_escape(sig), html_escape(doc)));
+                }
+                body.push_str("</ul>\n");
+            }
+        }
+    }
+    body.push_str("<h2>Files</h2>\n<ul>");
+    for f in m.files.iter().take(500) {
+        body.push_str(&format!("<li><code>{}</code></li>", html_escape(f)));
+    }
+    if m.files.len() > 500 {
+        body.push_str(&format!("<li class=\"muted\">… {} more in files.txt</li>", m.files.len() - 500));
+    }
+    body.push_str("</ul>\n");
+    page_shell(&format!("{} · wiki", m.name), &nav_html(modules, "../", Some(&m.slug)), &body)
+}
+
+fn tokenize(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| t.len() >= 2 && t.len() <= 40)
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// Lunr-style index: documents plus an inverted `term -> [[doc, weight]]` map
+/// (title terms weighted ×3). `search.html` scores with tf·idf client-side.
+fn search_index(modules: &[ModulePage]) -> serde_json::Value {
+    let mut docs = Vec::new();
+    for m in modules {
+        let body = m
+            .readme
+            .as_deref()
+            .unwrap_or("")
+            .chars()
+            .take(MAX_SEARCH_BODY)
+            .collect::<String>();
+        docs.push((m.name.clone(), format!("modules/{}.html", m.slug), m.name.clone(), body));
+        for d in &m.docs {
+            let mut body = d.summary.clone().unwrap_or_default();
+            for (sig, doc) in &d.items {
+                body.push_str(&format!("\n{}: {}", sig, doc));
+            }
+            docs.push((
+                d.path.clone(),
+                format!("modules/{}.html#{}", m.slug, slugify(&d.path)),
+                m.name.clone(),
+                body.chars().take(MAX_SEARCH_BODY).collect(),
+            ));
+        }
+    }
+    let mut inverted: BTreeMap<String, Vec<(usize, u32)>> = BTreeMap::new();
+    for (i, (title, _, _, body)) in docs.iter().enumerate() {
+        let mut tf: HashMap<String, u32> = HashMap::new();
+        for t in tokenize(title) {
+            *tf.entry(t).or_insert(0) += 3;
+        }
+        for t in tokenize(body) {
+            *tf.entry(t).or_insert(0) += 1;
+        }
+        for (t, w) in tf {
+            inverted.entry(t).or_default().push((i, w));
+        }
+    }
+    json!({
+        "version": 1,
+        "fields": [{"name": "title", "boost": 3}, {"name": "body", "boost": 1}],
+        "docs": docs.iter().enumerate().map(|(i, (title, url, module, body))| json!({
+            "id": i,
+            "title": title,
+            "url": url,
+            "module": module,
+            "body": body.chars().take(300).collect::<String>(),
+        })).collect::<Vec<_>>(),
+        "index": inverted,
+    })
+}
+
+fn search_html(modules: &[ModulePage]) -> String {
+    let body = r#"<h1>Search</h1>
+<input id="q" placeholder="search docs, files, modules…" autofocus>
+<p class="muted" id="status">loading index…</p>
+<ol id="results"></ol>
+<script>
+  const q = document.getElementById('q');
+  const out = document.getElementById('results');
+  const status = document.getElementById('status');
+  const esc = s => s.replace(/[&<>"']/g, c => ({'&':'&amp;','<':'&lt;','>':'&gt;','"':'&quot;',"'":'&#39;'}[c]));
+  let idx = null;
+  function run() {
+    if (!idx) return;
+    const terms = q.value.toLowerCase().split(/[^\p{L}\p{N}_]+/u).filter(t => t.length >= 2);
+    const scores = new Map();
+    for (const t of terms) {
+      for (const [term, postings] of Object.entries(idx.index)) {
+        if (!term.startsWith(t)) continue;
+        const idf = Math.log(1 + idx.docs.length / postings.length) * (term === t ? 1 : 0.5);
+        for (const [doc, w] of postings) scores.set(doc, (scores.get(doc) || 0) + w * idf);
+      }
+    }
+    const ranked = [...scores.entries()].sort((a, b) => b[1] - a[1]).slice(0, 50);
+    status.textContent = terms.length ? `${ranked.length} result(s)` : `${idx.docs.length} documents indexed`;
+    out.innerHTML = ranked.map(([i]) => {
+      const d = idx.docs[i];
+      return `<li><a href="${esc(d.url)}">${esc(d.title)}</a> <span class="muted">${esc(d.module)}</span><div>${esc(d.body)}</div></li>`;
+    }).join('');
+  }
+  fetch('search_index.json').then(r => r.json()).then(j => {
+    idx = j;
+    q.value = new URLSearchParams(location.search).get('q') || '';
+    run();
+  });
+  q.addEventListener('input', run);
+</script>"#;
+    page_shell("Search · wiki", &nav_html(modules, "", None), body)
+}
+
+fn sitemap_xml(run_id: &str, base_url: &str, generated: &str, pages: &[String]) -> String {
+    let base = base_url.trim_end_matches('/');
+    let urls = pages
+        .iter()
+        .map(|p| {
+            format!(
+                "  <url><loc>{}{}</loc><lastmod>{}</lastmod></url>",
+                html_escape(base),
+                html_escape(&urls::url_for(&format!("/runs/wiki/{}/{}", run_id, p))),
+                generated
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n{}\n</urlset>\n",
+        urls
+    )
+}
+
 pub struct WikiResult {
     pub out_dir: PathBuf,
     pub files_count: usize,
     pub topfiles_count: usize,
     pub readme_copied: bool,
+    pub modules_count: usize,
+    pub search_docs: usize,
+    /// HTML pages written, relative to `out_dir`: index, static, search, then one per module.
+    pub pages: Vec<String>,
 }
 
-pub async fn generate(run_id: &str) -> Result<WikiResult> {
+/// `base_url` prefixes sitemap locations (default `ONE_ENGINE_WIKI_BASE_URL`, else
+/// `http://127.0.0.1:8080`).
+pub async fn generate(run_id: &str, base_url: Option<&str>) -> Result<WikiResult> {
     let meta_root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
     let base = meta_root.clone();
     let out_dir = meta_root.join("runs/wiki").join(run_id);
 
-    tokio::fs::create_dir_all(&out_dir)
-        .await
+    storage::create_dir_all(&out_dir)
         .with_context(|| format!("create out_dir {}", out_dir.display()))?;
 
     let generated = chrono::Utc::now().to_rfc3339();
@@ -269,7 +830,7 @@ pub async fn generate(run_id: &str) -> Result<WikiResult> {
         .await
         .context("join inventory task")??;
 
-    tokio::fs::write(out_dir.join("files.txt"), files.join("\n") + "\n")
+    atomic::write_async(out_dir.join("files.txt"), files.join("\n") + "\n")
         .await
         .context("write files.txt")?;
 
@@ -284,15 +845,16 @@ pub async fn generate(run_id: &str) -> Result<WikiResult> {
         Err(_) => files.iter().take(200).cloned().collect(),
     };
 
-    tokio::fs::write(out_dir.join("topfiles.txt"), topfiles.join("\n") + "\n")
+    atomic::write_async(out_dir.join("topfiles.txt"), topfiles.join("\n") + "\n")
         .await
         .context("write topfiles.txt")?;
 
     let mut readme_copied = false;
     let readme_src = base.join("README.md");
-    if tokio::fs::metadata(&readme_src).await.is_ok() {
-        let _ = tokio::fs::copy(&readme_src, out_dir.join("README.md")).await;
-        readme_copied = true;
+    if let Ok(readme) = tokio::fs::read(&readme_src).await {
+        readme_copied = atomic::write_async(out_dir.join("README.md"), readme)
+            .await
+            .is_ok();
     }
 
     let summary_md = tokio::task::spawn_blocking({
@@ -302,22 +864,70 @@ pub async fn generate(run_id: &str) -> Result<WikiResult> {
     .await
     .context("join folder_summary task")??;
 
-    tokio::fs::write(out_dir.join("folder_summary.md"), summary_md)
+    atomic::write_async(out_dir.join("folder_summary.md"), summary_md)
         .await
         .context("write folder_summary.md")?;
 
-    tokio::fs::write(out_dir.join("index.md"), index_md(run_id, &generated))
+    let modules = tokio::task::spawn_blocking({
+        let base = base.clone();
+        move || build_modules(&base)
+    })
+    .await
+    .context("join modules task")?;
+
+    atomic::write_async(out_dir.join("index.md"), index_md(run_id, &generated, &modules))
         .await
         .context("write index.md")?;
-    tokio::fs::write(out_dir.join("index.html"), index_html())
+    atomic::write_async(out_dir.join("index.html"), index_html(&modules))
         .await
         .context("write index.html")?;
 
+    let modules_dir = out_dir.join("modules");
+    storage::create_dir_all(&modules_dir)
+        .with_context(|| format!("create {}", modules_dir.display()))?;
+    let mut pages = vec![
+        "index.html".to_string(),
+        "static.html".to_string(),
+        "search.html".to_string(),
+    ];
+    for m in &modules {
+        let mut referenced_by: Vec<(String, usize)> = modules
+            .iter()
+            .filter_map(|o| o.refs.get(&m.name).map(|n| (o.name.clone(), *n)))
+            .collect();
+        referenced_by.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let page = format!("modules/{}.html", m.slug);
+        atomic::write_async(out_dir.join(&page), module_html(m, &modules, &referenced_by))
+            .await
+            .with_context(|| format!("write {}", page))?;
+        pages.push(page);
+    }
+
+    let index = search_index(&modules);
+    let search_docs = index.get("docs").and_then(|d| d.as_array()).map(|d| d.len()).unwrap_or(0);
+    atomic::write_async(out_dir.join("search_index.json"), serde_json::to_string(&index)?)
+        .await
+        .context("write search_index.json")?;
+    atomic::write_async(out_dir.join("search.html"), search_html(&modules))
+        .await
+        .context("write search.html")?;
+
+    let base_url = base_url
+        .map(|s| s.to_string())
+        .or_else(|| std::env::var("ONE_ENGINE_WIKI_BASE_URL").ok())
+        .unwrap_or_else(|| "http://127.0.0.1:8080".to_string());
+    atomic::write_async(
+        out_dir.join("sitemap.xml"),
+        sitemap_xml(run_id, &base_url, &generated, &pages),
+    )
+    .await
+    .context("write sitemap.xml")?;
+
     // Single-file “show it now” page (embeds summaries; still links to artifacts).
-    let summary_embed = tokio::fs::read_to_string(out_dir.join("folder_summary.md"))
+    let summary_embed = storage::read_to_string_async(out_dir.join("folder_summary.md"))
         .await
         .unwrap_or_default();
-    tokio::fs::write(
+    atomic::write_async(
         out_dir.join("static.html"),
         static_html(run_id, &generated, &summary_embed, &topfiles),
     )
@@ -329,5 +939,8 @@ pub async fn generate(run_id: &str) -> Result<WikiResult> {
         files_count: files.len(),
         topfiles_count: topfiles.len(),
         readme_copied,
+        modules_count: modules.len(),
+        search_docs,
+        pages,
     })
 }
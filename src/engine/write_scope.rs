@@ -0,0 +1,133 @@
+//! Write scope for `file.write`: targets must resolve under one of the allowed roots.
+//!
+//! Roots come from `write_scope.roots` in policies.yaml (`ONE_ENGINE_WRITE_ROOTS`, comma
+//! separated, overrides it) and default to META3_ROOT. Targets are canonicalized through
+//! their deepest existing ancestor, so `..` tricks and symlinked directories or files that
+//! point outside every root are rejected.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct WriteScopeSection {
+    #[serde(default)]
+    roots: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesWriteScope {
+    #[serde(default)]
+    write_scope: Option<WriteScopeSection>,
+}
+
+/// Why a target was refused; recorded as receipt evidence.
+#[derive(Debug, Clone, Serialize)]
+pub struct Violation {
+    pub path: String,
+    pub reason: String,
+    pub allowed_roots: Vec<String>,
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn configured_roots() -> Vec<PathBuf> {
+    if let Ok(v) = std::env::var("ONE_ENGINE_WRITE_ROOTS") {
+        return v
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+            .collect();
+    }
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let section = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(
+            |raw| match serde_yaml::from_str::<PoliciesWriteScope>(&raw) {
+                Ok(p) => p.write_scope,
+                Err(e) => {
+                    tracing::warn!("invalid {}: {}", path, e);
+                    None
+                }
+            },
+        )
+        .unwrap_or_default();
+    if section.roots.is_empty() {
+        vec![meta3_root()]
+    } else {
+        section.roots.into_iter().map(PathBuf::from).collect()
+    }
+}
+
+/// Allowed roots, canonicalized; roots that don't exist are dropped.
+pub fn allowed_roots() -> Vec<PathBuf> {
+    configured_roots()
+        .into_iter()
+        .filter_map(|r| std::fs::canonicalize(r).ok())
+        .collect()
+}
+
+/// Resolve `target` (relative to the working directory) to the real path it would write,
+/// without requiring it to exist.
+fn resolve(target: &Path) -> Result<PathBuf, String> {
+    let abs = if target.is_absolute() {
+        target.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map_err(|e| format!("cwd: {}", e))?
+            .join(target)
+    };
+    if abs.components().any(|c| matches!(c, Component::ParentDir)) {
+        return Err("path contains `..`".to_string());
+    }
+    // Dangling symlink as the final component: canonicalize would fail, and writing would
+    // follow it wherever it points.
+    if let Ok(meta) = std::fs::symlink_metadata(&abs) {
+        if meta.file_type().is_symlink() && std::fs::metadata(&abs).is_err() {
+            return Err("target is a dangling symlink".to_string());
+        }
+    }
+    let mut existing = abs.as_path();
+    let mut rest: Vec<&std::ffi::OsStr> = Vec::new();
+    loop {
+        if existing.exists() {
+            break;
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return Err("no existing ancestor".to_string()),
+        }
+    }
+    let mut real = std::fs::canonicalize(existing).map_err(|e| e.to_string())?;
+    for name in rest.into_iter().rev() {
+        real.push(name);
+    }
+    Ok(real)
+}
+
+/// Real path to write when `target` is inside the write scope.
+pub fn check(target: &Path) -> Result<PathBuf, Violation> {
+    let roots = allowed_roots();
+    let violation = |reason: String| Violation {
+        path: target.display().to_string(),
+        reason,
+        allowed_roots: roots.iter().map(|r| r.display().to_string()).collect(),
+    };
+    let real = resolve(target).map_err(&violation)?;
+    if roots.iter().any(|r| real.starts_with(r)) {
+        Ok(real)
+    } else {
+        Err(violation(format!(
+            "{} is outside the write scope",
+            real.display()
+        )))
+    }
+}
@@ -0,0 +1,504 @@
+//! Read-only GraphQL at `/graphql` (GraphiQL on `GET`): runs from the run index joined with
+//! users and thread metadata, for dashboards that need filters REST doesn't offer.
+//!
+//! ```graphql
+//! { runs(filter: { goal: "wiki.*", since: "24h", success: false }, first: 20) {
+//!     totalCount pageInfo { hasNextPage endCursor }
+//!     nodes { runId goalId userId bits { t e } tags } } }
+//! ```
+//!
+//! Pagination is cursor-based over newest-first runs; the cursor is the last run id seen.
+//!
+//! Queries need an `x-api-key`. A user's key sees only that user's runs and threads (and a
+//! run's `manifest` only where the receipt ACL lets it); `users` and other users' data need
+//! the admin key.
+
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, InputObject, Json, Object, Schema,
+    SimpleObject,
+};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::http::HeaderMap;
+use axum::response::{Html, IntoResponse, Response};
+use one_engine::jsonl;
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::api::{self, AppState};
+use crate::engine::policy::glob_match;
+use crate::engine::types::Policy;
+use crate::engine::urls::url_for;
+use crate::integrations::receipt_acl::{self, Caller};
+use crate::integrations::run_index::{self, RunRecord};
+
+pub type EngineSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+const DEFAULT_PAGE: usize = 50;
+const MAX_PAGE: usize = 500;
+
+pub fn schema(state: AppState) -> EngineSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(state)
+        .limit_depth(8)
+        .finish()
+}
+
+pub async fn graphiql() -> impl IntoResponse {
+    Html(
+        GraphiQLSource::build()
+            .endpoint(&url_for("/graphql"))
+            .finish(),
+    )
+}
+
+/// `POST /graphql`: authenticate, then run the query with the caller in its context.
+pub async fn execute(
+    schema: EngineSchema,
+    state: AppState,
+    headers: HeaderMap,
+    req: GraphQLRequest,
+) -> Response {
+    match api::require_caller(&state, &headers) {
+        Ok(caller) => GraphQLResponse::from(schema.execute(req.into_inner().data(caller)).await)
+            .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+fn caller(ctx: &Context<'_>) -> Caller {
+    ctx.data::<Caller>().cloned().unwrap_or(Caller::Anonymous)
+}
+
+/// Narrow `filter` to what `caller` may see; `None` when it asks for someone else's runs.
+fn scoped(caller: &Caller, mut filter: RunFilter) -> Option<RunFilter> {
+    match caller {
+        Caller::Admin => Some(filter),
+        Caller::User(u) => match &filter.user_id {
+            Some(other) if other != u => None,
+            _ => {
+                filter.user_id = Some(u.clone());
+                Some(filter)
+            }
+        },
+        Caller::Anonymous => None,
+    }
+}
+
+fn may_see_user(caller: &Caller, user_id: &str) -> bool {
+    match caller {
+        Caller::Admin => true,
+        Caller::User(u) => u == user_id,
+        Caller::Anonymous => false,
+    }
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RunBits {
+    pub a: Option<f64>,
+    pub u: Option<f64>,
+    pub p: Option<f64>,
+    pub e: Option<f64>,
+    pub d: Option<f64>,
+    pub i: Option<f64>,
+    pub r: Option<f64>,
+    pub t: Option<f64>,
+    pub m: Option<f64>,
+}
+
+impl RunBits {
+    fn from_value(v: &Value) -> Self {
+        let f = |k: &str| v.get(k).and_then(|x| x.as_f64());
+        Self {
+            a: f("a"),
+            u: f("u"),
+            p: f("p"),
+            e: f("e"),
+            d: f("d"),
+            i: f("i"),
+            r: f("r"),
+            t: f("t"),
+            m: f("m"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
+pub struct Run {
+    pub run_id: String,
+    pub goal_id: String,
+    /// RFC 3339 time of the receipt.
+    pub ts: String,
+    pub success: Option<bool>,
+    pub latency_ms: Option<u64>,
+    pub user_id: Option<String>,
+    pub bits: Option<RunBits>,
+}
+
+impl From<&RunRecord> for Run {
+    fn from(r: &RunRecord) -> Self {
+        Self {
+            run_id: r.run_id.clone(),
+            goal_id: r.goal_id.clone(),
+            ts: r.ts.to_rfc3339(),
+            success: r.success,
+            latency_ms: r.latency_ms,
+            user_id: r.user_id.clone(),
+            bits: r.bits.as_ref().map(RunBits::from_value),
+        }
+    }
+}
+
+/// `kind:<ctx.kind>`, `experiment:<id>/<arm>` and any `evidence.tags` strings.
+async fn run_tags(run_id: &str) -> Vec<String> {
+    let Some(d) = run_index::get(run_id).await else {
+        return Vec::new();
+    };
+    let mut tags = Vec::new();
+    let request = tokio::fs::read_to_string(
+        meta3_root()
+            .join("runs/receipts")
+            .join(run_id)
+            .join("request.json"),
+    )
+    .await
+    .ok()
+    .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+    if let Some(kind) = request
+        .as_ref()
+        .and_then(|r| r.pointer("/ctx/kind"))
+        .and_then(|v| v.as_str())
+    {
+        tags.push(format!("kind:{}", kind));
+    }
+    let evidence = d.response.pointer("/manifest/evidence");
+    if let Some(x) = evidence.and_then(|e| e.get("experiment")) {
+        if let (Some(id), Some(arm)) = (
+            x.get("id").and_then(|v| v.as_str()),
+            x.get("arm").and_then(|v| v.as_str()),
+        ) {
+            tags.push(format!("experiment:{}/{}", id, arm));
+        }
+    }
+    if let Some(arr) = evidence
+        .and_then(|e| e.get("tags"))
+        .and_then(|v| v.as_array())
+    {
+        tags.extend(arr.iter().filter_map(|t| t.as_str()).map(|s| s.to_string()));
+    }
+    tags
+}
+
+#[ComplexObject]
+impl Run {
+    async fn tags(&self) -> Vec<String> {
+        run_tags(&self.run_id).await
+    }
+
+    /// Full manifest from the receipt (absent for queued/running stubs, and for receipts the
+    /// key may not read).
+    async fn manifest(&self, ctx: &Context<'_>) -> Option<Json<Value>> {
+        if !receipt_acl::caller_can_read(&self.run_id, &caller(ctx)).await {
+            return None;
+        }
+        let d = run_index::get(&self.run_id).await?;
+        d.response
+            .get("manifest")
+            .filter(|m| m.is_object() && d.status.is_none())
+            .cloned()
+            .map(Json)
+    }
+
+    async fn receipt_url(&self) -> String {
+        url_for(&format!("/runs/receipts/{}/RECEIPT.md", self.run_id))
+    }
+}
+
+#[derive(Debug, Default, Clone, InputObject)]
+pub struct RunFilter {
+    pub user_id: Option<String>,
+    /// Exact goal id or `*` glob.
+    pub goal: Option<String>,
+    pub success: Option<bool>,
+    /// Window (`24h`, `7d`) or RFC 3339 time.
+    pub since: Option<String>,
+    /// Only runs carrying this tag (see `Run.tags`).
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct PageInfo {
+    pub has_next_page: bool,
+    pub end_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RunConnection {
+    pub total_count: usize,
+    pub nodes: Vec<Run>,
+    pub page_info: PageInfo,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+pub struct RunStats {
+    pub count: usize,
+    pub finished: usize,
+    pub succeeded: usize,
+    pub success_rate: Option<f64>,
+    pub mean_trust: Option<f64>,
+    pub mean_latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
+pub struct Thread {
+    pub user_id: String,
+    pub thread: String,
+    pub messages_total: usize,
+    pub messages_user: usize,
+    pub messages_assistant: usize,
+    pub messages_tool: usize,
+    pub approx_tokens: u64,
+    pub last_updated: Option<String>,
+    /// Newest first, at most 8.
+    pub last_run_ids: Vec<String>,
+}
+
+#[ComplexObject]
+impl Thread {
+    /// Runs referenced by the thread's recent messages.
+    async fn runs(&self) -> Vec<Run> {
+        let mut out = Vec::new();
+        for id in &self.last_run_ids {
+            if let Some(d) = run_index::get(id).await {
+                out.push(Run::from(&d.record));
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, SimpleObject)]
+#[graphql(complex)]
+pub struct User {
+    pub user_id: String,
+    pub quota_remaining: u32,
+    pub permissions: Vec<String>,
+    /// Effective policy override (stored `PUT /users/{id}/policy` or built-in).
+    pub policy: Option<Json<Policy>>,
+}
+
+#[ComplexObject]
+impl User {
+    async fn runs(
+        &self,
+        filter: Option<RunFilter>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> RunConnection {
+        let mut filter = filter.unwrap_or_default();
+        filter.user_id = Some(self.user_id.clone());
+        runs_page(&filter, first, after).await
+    }
+
+    async fn threads(&self) -> Vec<Thread> {
+        list_threads(&self.user_id).await
+    }
+}
+
+async fn filtered_runs(filter: &RunFilter) -> Vec<RunRecord> {
+    let since = filter.since.as_deref().and_then(|s| {
+        run_index::parse_window(s)
+            .map(|w| chrono::Utc::now() - w)
+            .or_else(|| {
+                chrono::DateTime::parse_from_rfc3339(s)
+                    .ok()
+                    .map(|t| t.with_timezone(&chrono::Utc))
+            })
+    });
+    let mut runs: Vec<RunRecord> = run_index::scan(since)
+        .await
+        .into_iter()
+        .filter(|r| {
+            filter
+                .user_id
+                .as_ref()
+                .is_none_or(|u| r.user_id.as_ref() == Some(u))
+        })
+        .filter(|r| {
+            filter
+                .goal
+                .as_deref()
+                .is_none_or(|g| glob_match(g.trim(), &r.goal_id))
+        })
+        .filter(|r| filter.success.is_none_or(|s| r.success == Some(s)))
+        .collect();
+    if let Some(tag) = &filter.tag {
+        let mut tagged = Vec::new();
+        for r in runs {
+            if run_tags(&r.run_id).await.contains(tag) {
+                tagged.push(r);
+            }
+        }
+        runs = tagged;
+    }
+    runs
+}
+
+async fn runs_page(filter: &RunFilter, first: Option<i32>, after: Option<String>) -> RunConnection {
+    let runs = filtered_runs(filter).await;
+    let limit = first
+        .map(|n| n.max(0) as usize)
+        .unwrap_or(DEFAULT_PAGE)
+        .min(MAX_PAGE);
+    let start = after
+        .as_ref()
+        .and_then(|c| runs.iter().position(|r| &r.run_id == c))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let nodes: Vec<Run> = runs.iter().skip(start).take(limit).map(Run::from).collect();
+    RunConnection {
+        total_count: runs.len(),
+        page_info: PageInfo {
+            has_next_page: start + nodes.len() < runs.len(),
+            end_cursor: nodes.last().map(|r| r.run_id.clone()),
+        },
+        nodes,
+    }
+}
+
+async fn list_threads(user_id: &str) -> Vec<Thread> {
+    let dir = meta3_root().join("users").join(user_id).join("threads");
+    let mut names = Vec::new();
+    if let Ok(mut rd) = tokio::fs::read_dir(&dir).await {
+        while let Ok(Some(ent)) = rd.next_entry().await {
+            let name = ent.file_name().to_string_lossy().to_string();
+            if jsonl::is_rotated_segment(&ent.path()) {
+                continue;
+            }
+            if let Some(thread) = name.strip_suffix(".jsonl") {
+                names.push(thread.to_string());
+            }
+        }
+    }
+    names.sort();
+    let mut out = Vec::new();
+    for thread in names {
+        // `thread_path` rejects unsafe segments.
+        let Some(path) = api::thread_path(user_id, &thread) else {
+            continue;
+        };
+        let s = api::thread_summary(&path, user_id, &thread).await;
+        out.push(Thread {
+            user_id: s.user_id,
+            thread: s.thread,
+            messages_total: s.messages_total,
+            messages_user: s.messages_user,
+            messages_assistant: s.messages_assistant,
+            messages_tool: s.messages_tool,
+            approx_tokens: s.approx_tokens,
+            last_updated: s.last_updated,
+            last_run_ids: s.last_run_ids,
+        });
+    }
+    out
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Newest-first runs matching `filter`; `after` is the `endCursor` of the previous page.
+    /// A user's key only gets that user's runs.
+    async fn runs(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<RunFilter>,
+        first: Option<i32>,
+        after: Option<String>,
+    ) -> async_graphql::Result<RunConnection> {
+        let filter = scoped(&caller(ctx), filter.unwrap_or_default())
+            .ok_or("not allowed to list these runs")?;
+        Ok(runs_page(&filter, first, after).await)
+    }
+
+    async fn run(&self, ctx: &Context<'_>, run_id: String) -> Option<Run> {
+        if !receipt_acl::caller_can_read(&run_id, &caller(ctx)).await {
+            return None;
+        }
+        run_index::get(&run_id).await.map(|d| Run::from(&d.record))
+    }
+
+    /// Success rate, mean trust (`bits.t`) and latency over the filtered runs.
+    async fn run_stats(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<RunFilter>,
+    ) -> async_graphql::Result<RunStats> {
+        let filter = scoped(&caller(ctx), filter.unwrap_or_default())
+            .ok_or("not allowed to read these runs")?;
+        let runs = filtered_runs(&filter).await;
+        let finished: Vec<bool> = runs.iter().filter_map(|r| r.success).collect();
+        let succeeded = finished.iter().filter(|ok| **ok).count();
+        let trusts: Vec<f64> = runs
+            .iter()
+            .filter_map(|r| r.bits.as_ref()?.get("t")?.as_f64())
+            .collect();
+        let latencies: Vec<u64> = runs.iter().filter_map(|r| r.latency_ms).collect();
+        Ok(RunStats {
+            count: runs.len(),
+            finished: finished.len(),
+            succeeded,
+            success_rate: (!finished.is_empty()).then(|| succeeded as f64 / finished.len() as f64),
+            mean_trust: (!trusts.is_empty())
+                .then(|| trusts.iter().sum::<f64>() / trusts.len() as f64),
+            mean_latency_ms: (!latencies.is_empty())
+                .then(|| latencies.iter().sum::<u64>() / latencies.len() as u64),
+        })
+    }
+
+    /// Known users (API keys are never exposed). Admin key only.
+    async fn users(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<User>> {
+        if caller(ctx) != Caller::Admin {
+            return Err("admin key required".into());
+        }
+        let Ok(state) = ctx.data::<AppState>() else {
+            return Ok(Vec::new());
+        };
+        let mut users: Vec<User> = state
+            .users
+            .values()
+            .map(|u| {
+                // Same lookup as requests, so the stored policy override shows up.
+                let u = api::authenticate_user(state, &u.api_key).unwrap_or_else(|| u.clone());
+                User {
+                    user_id: u.user_id,
+                    quota_remaining: u.quota_remaining,
+                    permissions: u.permissions,
+                    policy: u.policy_overrides.map(Json),
+                }
+            })
+            .collect();
+        users.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+        Ok(users)
+    }
+
+    /// The caller's own threads, or any user's with the admin key.
+    async fn threads(
+        &self,
+        ctx: &Context<'_>,
+        user_id: String,
+    ) -> async_graphql::Result<Vec<Thread>> {
+        if !may_see_user(&caller(ctx), &user_id) {
+            return Err("not allowed to read this user's threads".into());
+        }
+        Ok(list_threads(&user_id).await)
+    }
+}
@@ -0,0 +1,297 @@
+//! gRPC surface (`--features grpc`): `Run`, `RunAsync`, `StreamProgress` and `GetReceipt`
+//! from `proto/engine.proto`, on the same pipeline as the HTTP handlers
+//! ([`api::execute_run`], [`api::enqueue_run`], the progress bus, [`api::run_status`]).
+//!
+//! Auth is the `x-api-key` metadata entry: a known key adds that user's policy layer and
+//! quota (each `Run`/`RunAsync` spends one), an unknown one is rejected, and no key runs with
+//! the anonymous `/run` policy. `StreamProgress` and `GetReceipt` only show runs the caller
+//! may read (`receipt_acl`); streaming every run at once needs the admin key.
+//! Listens on `ONE_ENGINE_GRPC_ADDR` (default `127.0.0.1:50051`).
+//!
+//! The feature needs these manifest entries (`build.rs` compiles the proto with them):
+//! `grpc = ["dep:tonic", "dep:prost", "dep:tonic-build"]` under `[features]`, `tonic` and
+//! `prost` as optional dependencies, and `tonic-build` as an optional build dependency.
+
+use serde_json::{json, Value};
+use std::pin::Pin;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::api::{self, AppState, RunAsyncResp, RunReq, RunResp, UserContext};
+use crate::engine::types::{Bits, ExecutorBackend, Manifest, Policy, ResourceLimits};
+use crate::integrations::progress;
+use crate::integrations::receipt_acl::{self, Caller};
+use crate::integrations::run_queue::Priority;
+
+pub mod pb {
+    tonic::include_proto!("one_engine.v1");
+}
+
+use pb::engine_server::{Engine, EngineServer};
+
+pub struct EngineService {
+    state: AppState,
+}
+
+impl From<Bits> for pb::Bits {
+    fn from(b: Bits) -> Self {
+        Self {
+            a: b.a,
+            u: b.u,
+            p: b.p,
+            e: b.e,
+            d: b.d,
+            i: b.i,
+            r: b.r,
+            t: b.t,
+            m: b.m,
+        }
+    }
+}
+
+impl From<pb::Policy> for Policy {
+    fn from(p: pb::Policy) -> Self {
+        Self {
+            gamma_gate: p.gamma_gate,
+            time_ms: p.time_ms,
+            max_risk: p.max_risk,
+            tiny_diff_loc: p.tiny_diff_loc,
+            snapshot: p.snapshot,
+            idempotent: p.idempotent,
+            max_tool_iterations: p.max_tool_iterations,
+            // Unset or unknown backends run on the host.
+            executor: ExecutorBackend::parse(&p.executor).unwrap_or_default(),
+            limits: p
+                .limits
+                .map(|l| ResourceLimits {
+                    cpu_s: l.cpu_s,
+                    max_rss_mb: l.max_rss_mb,
+                    max_output_bytes: l.max_output_bytes,
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Manifest> for pb::Manifest {
+    fn from(m: Manifest) -> Self {
+        Self {
+            run_id: m.run_id,
+            goal_id: m.goal_id,
+            deliverables: m.deliverables,
+            evidence_json: m.evidence.to_string(),
+            bits: Some(m.bits.into()),
+            schema_version: m.schema_version,
+        }
+    }
+}
+
+impl From<RunResp> for pb::RunResponse {
+    fn from(r: RunResp) -> Self {
+        Self {
+            manifest: Some(r.manifest.into()),
+            bits: Some(r.bits.into()),
+            pr_created: r.pr_created,
+            meta2_proposal: r.meta2_proposal,
+        }
+    }
+}
+
+impl From<RunAsyncResp> for pb::RunAsyncResponse {
+    fn from(r: RunAsyncResp) -> Self {
+        Self {
+            run_id: r.run_id,
+            goal_id: r.goal_id,
+            status: r.status,
+            receipt_url: r.receipt_url,
+            sse_url: r.sse_url,
+        }
+    }
+}
+
+fn run_req(r: pb::RunRequest) -> Result<RunReq, Status> {
+    let inputs = if r.inputs_json.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str::<Value>(&r.inputs_json)
+            .map_err(|e| Status::invalid_argument(format!("inputs_json: {}", e)))?
+    };
+    let priority = match r.priority.as_deref() {
+        None | Some("") => None,
+        Some(p) => Some(Priority::parse(p).ok_or_else(|| {
+            Status::invalid_argument(format!("priority: expected high|normal|low, got {:?}", p))
+        })?),
+    };
+    Ok(RunReq {
+        goal_id: r.goal_id,
+        inputs,
+        policy: r.policy.map(Policy::from),
+        run_id: r.run_id,
+        priority,
+        depends_on: Vec::new(),
+        on_parent_failure: None,
+    })
+}
+
+fn progress_event(raw: String) -> pb::ProgressEvent {
+    let v = serde_json::from_str::<Value>(&raw).unwrap_or(Value::Null);
+    let field = |k: &str| v.get(k).and_then(|x| x.as_str()).unwrap_or("").to_string();
+    pb::ProgressEvent {
+        run_id: field("run_id"),
+        goal_id: field("goal_id"),
+        phase: field("phase"),
+        ts: field("ts"),
+        event_json: raw,
+        missed: 0,
+    }
+}
+
+impl EngineService {
+    /// Caller from `x-api-key` metadata, and the user behind it if it isn't the admin.
+    fn caller<T>(&self, request: &Request<T>) -> Result<(Caller, Option<UserContext>), Status> {
+        let Some(key) = request
+            .metadata()
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .filter(|k| !k.is_empty())
+        else {
+            return Ok((Caller::Anonymous, None));
+        };
+        if api::is_admin_key(key) {
+            return Ok((Caller::Admin, None));
+        }
+        let user = api::authenticate_user(&self.state, key)
+            .ok_or_else(|| Status::unauthenticated("Invalid API key"))?;
+        Ok((Caller::User(user.user_id.clone()), Some(user)))
+    }
+
+    /// The user a run is charged to, gated like the HTTP user endpoints.
+    fn user<T>(&self, request: &Request<T>) -> Result<Option<UserContext>, Status> {
+        let (_, user) = self.caller(request)?;
+        if user.as_ref().is_some_and(|u| u.quota_remaining == 0) {
+            return Err(Status::resource_exhausted("Quota exceeded"));
+        }
+        Ok(user)
+    }
+
+    /// `run_id` if `caller` may read it; other runs look unknown.
+    async fn readable(&self, run_id: &str, caller: &Caller) -> Result<(), Status> {
+        if receipt_acl::caller_can_read(run_id, caller).await {
+            Ok(())
+        } else {
+            Err(Status::not_found(format!("unknown run: {}", run_id)))
+        }
+    }
+}
+
+type ProgressStream = Pin<Box<dyn Stream<Item = Result<pb::ProgressEvent, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Engine for EngineService {
+    async fn run(
+        &self,
+        request: Request<pb::RunRequest>,
+    ) -> Result<Response<pb::RunResponse>, Status> {
+        let mut user = self.user(&request)?;
+        let req = run_req(request.into_inner())?;
+        let resp = api::execute_run(req, user.as_ref(), "grpc")
+            .await
+            .map_err(Status::invalid_argument)?;
+        if let Some(user) = user.as_mut() {
+            api::spend_quota(user);
+        }
+        Ok(Response::new(resp.into()))
+    }
+
+    async fn run_async(
+        &self,
+        request: Request<pb::RunRequest>,
+    ) -> Result<Response<pb::RunAsyncResponse>, Status> {
+        let mut user = self.user(&request)?;
+        let req = run_req(request.into_inner())?;
+        let resp = api::enqueue_run(req, user.as_ref(), "grpc").await;
+        if let Some(user) = user.as_mut() {
+            api::spend_quota(user);
+        }
+        Ok(Response::new(resp.into()))
+    }
+
+    type StreamProgressStream = ProgressStream;
+
+    async fn stream_progress(
+        &self,
+        request: Request<pb::ProgressRequest>,
+    ) -> Result<Response<Self::StreamProgressStream>, Status> {
+        let (caller, _) = self.caller(&request)?;
+        let run_id = request.into_inner().run_id;
+        let rx = if run_id.is_empty() {
+            // Every run's progress, whoever owns it.
+            if caller != Caller::Admin {
+                return Err(Status::permission_denied(
+                    "streaming all runs needs the admin key; pass a run_id",
+                ));
+            }
+            progress::subscribe_all()
+        } else {
+            self.readable(&run_id, &caller).await?;
+            progress::subscribe(&run_id)
+        };
+        // A run's stream ends when its channel closes, as with `/progress.sse?run_id=`.
+        let stream = BroadcastStream::new(rx).map(move |evt| {
+            Ok(match evt {
+                Ok(raw) => progress_event(raw),
+                Err(BroadcastStreamRecvError::Lagged(missed)) => pb::ProgressEvent {
+                    run_id: run_id.clone(),
+                    missed,
+                    ..Default::default()
+                },
+            })
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_receipt(
+        &self,
+        request: Request<pb::ReceiptRequest>,
+    ) -> Result<Response<pb::Receipt>, Status> {
+        let (caller, _) = self.caller(&request)?;
+        let run_id = request.into_inner().run_id;
+        self.readable(&run_id, &caller).await?;
+        let status = api::run_status(&run_id)
+            .await
+            .ok_or_else(|| Status::not_found(format!("unknown run: {}", run_id)))?;
+        let response = api::read_receipt_response_json(&run_id)
+            .await
+            .unwrap_or(Value::Null);
+        Ok(Response::new(pb::Receipt {
+            run_id: status.run_id,
+            goal_id: status.goal_id,
+            status: status.status,
+            success: status.success,
+            response_json: response.to_string(),
+        }))
+    }
+}
+
+/// Serve until the process exits; errors are logged (HTTP keeps running).
+pub async fn serve(state: AppState) {
+    let addr =
+        std::env::var("ONE_ENGINE_GRPC_ADDR").unwrap_or_else(|_| "127.0.0.1:50051".to_string());
+    let addr = match addr.parse::<std::net::SocketAddr>() {
+        Ok(a) => a,
+        Err(e) => {
+            tracing::warn!("invalid ONE_ENGINE_GRPC_ADDR {}: {}", addr, e);
+            return;
+        }
+    };
+    tracing::info!("gRPC listening on {addr}");
+    if let Err(e) = tonic::transport::Server::builder()
+        .add_service(EngineServer::new(EngineService { state }))
+        .serve(addr)
+        .await
+    {
+        tracing::warn!("gRPC server stopped: {}", e);
+    }
+}
@@ -0,0 +1,389 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+use super::run_index;
+
+// -------- META3_ROOT/runs/api_trace.jsonl (+ runs/api_trace/*.jsonl.gz archives) --------
+//
+// The live file rotates when it reaches ONE_ENGINE_API_TRACE_MAX_BYTES (default 16 MiB) or
+// its first event is older than ONE_ENGINE_API_TRACE_MAX_AGE (window, default 24h). Rotated
+// segments are gzipped to runs/api_trace/api_trace.<rotated_at>.jsonl.gz; archives older
+// than ONE_ENGINE_API_TRACE_RETENTION (default 30d) or beyond
+// ONE_ENGINE_API_TRACE_MAX_ARCHIVES (default 100) are deleted at rotation time.
+
+const ARCHIVE_PREFIX: &str = "api_trace.";
+const ARCHIVE_SUFFIX: &str = ".jsonl.gz";
+const ARCHIVE_TS: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApiTraceEvent {
+    pub ts: String,
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    pub status: u16,
+    pub ms: u64,
+    pub mutation: bool,
+    pub run_id: Option<String>,
+    pub user_id: Option<String>,
+    pub thread: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    /// Redacted, size-capped request body (mutations only, when capture is enabled).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Original body size in bytes when a body was captured or skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_bytes: Option<u64>,
+}
+
+// Request body capture for mutations: off unless ONE_ENGINE_API_TRACE_BODIES=1. Bodies are
+// redacted and capped at ONE_ENGINE_API_TRACE_BODY_MAX_BYTES (default 2048); routes whose path
+// starts with an entry of ONE_ENGINE_API_TRACE_BODY_OPTOUT (comma-separated, default
+// `/telemetry`) are never captured.
+
+/// Bodies larger than this are not buffered for capture at all.
+pub const BODY_BUFFER_LIMIT: u64 = 1024 * 1024;
+
+pub fn body_capture_enabled() -> bool {
+    matches!(
+        std::env::var("ONE_ENGINE_API_TRACE_BODIES").ok().as_deref(),
+        Some("1") | Some("true")
+    )
+}
+
+pub fn body_max_bytes() -> usize {
+    env_u64("ONE_ENGINE_API_TRACE_BODY_MAX_BYTES", 2048) as usize
+}
+
+pub fn body_opted_out(path: &str) -> bool {
+    let raw = std::env::var("ONE_ENGINE_API_TRACE_BODY_OPTOUT")
+        .unwrap_or_else(|_| "/telemetry".to_string());
+    raw.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .any(|p| path.starts_with(p))
+}
+
+/// Cap `body` at `max` bytes on a char boundary.
+pub fn cap_body(body: &str, max: usize) -> String {
+    if body.len() <= max {
+        return body.to_string();
+    }
+    let mut end = max;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…[truncated]", &body[..end])
+}
+
+/// Serializes writers with rotation; caches when the live segment started.
+static SINK: Lazy<Mutex<Option<DateTime<Utc>>>> = Lazy::new(|| Mutex::new(None));
+
+fn runs_dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs")
+}
+
+pub fn live_path() -> PathBuf {
+    runs_dir().join("api_trace.jsonl")
+}
+
+fn archive_dir() -> PathBuf {
+    runs_dir().join("api_trace")
+}
+
+fn env_u64(key: &str, default: u64) -> u64 {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(default)
+}
+
+fn env_window(key: &str, default: chrono::Duration) -> chrono::Duration {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| run_index::parse_window(&v))
+        .unwrap_or(default)
+}
+
+fn parse_ts(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+fn first_event_ts(path: &Path) -> Option<DateTime<Utc>> {
+    let f = std::fs::File::open(path).ok()?;
+    let mut line = String::new();
+    std::io::BufReader::new(f).read_line(&mut line).ok()?;
+    let v: serde_json::Value = serde_json::from_str(&line).ok()?;
+    parse_ts(v.get("ts")?.as_str()?)
+}
+
+fn archive_time(name: &str) -> Option<DateTime<Utc>> {
+    let stem = name
+        .strip_prefix(ARCHIVE_PREFIX)?
+        .strip_suffix(ARCHIVE_SUFFIX)?;
+    // Collisions get a `-<n>` suffix.
+    let ts = stem.split('-').next()?;
+    NaiveDateTime::parse_from_str(ts, ARCHIVE_TS)
+        .ok()
+        .map(|t| Utc.from_utc_datetime(&t))
+}
+
+/// Archive names, newest first.
+fn list_archives(dir: &Path) -> Vec<(String, DateTime<Utc>)> {
+    let mut out: Vec<(String, DateTime<Utc>)> = std::fs::read_dir(dir)
+        .map(|rd| {
+            rd.flatten()
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    archive_time(&name).map(|t| (name, t))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    out.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+    out
+}
+
+/// Gzip `src` into the archive dir, then apply retention. Blocking.
+fn archive_segment(src: &Path, now: DateTime<Utc>) -> std::io::Result<PathBuf> {
+    let dir = archive_dir();
+    std::fs::create_dir_all(&dir)?;
+    let stamp = now.format(ARCHIVE_TS).to_string();
+    let mut dst = dir.join(format!("{ARCHIVE_PREFIX}{stamp}{ARCHIVE_SUFFIX}"));
+    let mut n = 1;
+    while dst.exists() {
+        dst = dir.join(format!("{ARCHIVE_PREFIX}{stamp}-{n}{ARCHIVE_SUFFIX}"));
+        n += 1;
+    }
+    let raw = std::fs::read(src)?;
+    let mut enc =
+        flate2::write::GzEncoder::new(std::fs::File::create(&dst)?, flate2::Compression::default());
+    enc.write_all(&raw)?;
+    enc.finish()?;
+    std::fs::remove_file(src)?;
+
+    let retention = env_window("ONE_ENGINE_API_TRACE_RETENTION", chrono::Duration::days(30));
+    let max_archives = env_u64("ONE_ENGINE_API_TRACE_MAX_ARCHIVES", 100) as usize;
+    for (i, (name, t)) in list_archives(&dir).into_iter().enumerate() {
+        if i >= max_archives || now - t > retention {
+            let _ = std::fs::remove_file(dir.join(name));
+        }
+    }
+    Ok(dst)
+}
+
+async fn rotate_if_needed(started: &mut Option<DateTime<Utc>>) {
+    let live = live_path();
+    let Ok(meta) = tokio::fs::metadata(&live).await else {
+        *started = None;
+        return;
+    };
+    if started.is_none() {
+        *started = first_event_ts(&live).or_else(|| Some(Utc::now()));
+    }
+    let now = Utc::now();
+    let max_age = env_window("ONE_ENGINE_API_TRACE_MAX_AGE", chrono::Duration::hours(24));
+    let too_big = meta.len() >= env_u64("ONE_ENGINE_API_TRACE_MAX_BYTES", 16 * 1024 * 1024);
+    let too_old = started.map(|s| now - s >= max_age).unwrap_or(false);
+    if !too_big && !too_old {
+        return;
+    }
+    // Move the segment aside under the lock; compress off the async runtime.
+    let pending = runs_dir().join(format!("api_trace.{}.rotating", now.format(ARCHIVE_TS)));
+    if tokio::fs::rename(&live, &pending).await.is_err() {
+        return;
+    }
+    *started = None;
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = archive_segment(&pending, now) {
+            tracing::warn!("api_trace rotation failed for {}: {}", pending.display(), e);
+        }
+    });
+}
+
+/// Append one event to the live trace, rotating first when due.
+pub async fn append(ev: &ApiTraceEvent) {
+    let mut started = SINK.lock().await;
+    let _ = tokio::fs::create_dir_all(runs_dir()).await;
+    rotate_if_needed(&mut started).await;
+    let line = serde_json::to_string(ev).unwrap_or_default();
+    let Ok(mut f) = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(live_path())
+        .await
+    else {
+        return;
+    };
+    let _ = f.write_all(format!("{line}\n").as_bytes()).await;
+    if started.is_none() {
+        *started = parse_ts(&ev.ts);
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ApiTraceFilter {
+    /// Path prefix (e.g. `/users/`).
+    pub path: Option<String>,
+    /// Exact code (`404`) or class (`5xx`).
+    pub status: Option<String>,
+    pub method: Option<String>,
+    pub run_id: Option<String>,
+    pub correlation_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl ApiTraceFilter {
+    fn status_matches(&self, status: u16) -> bool {
+        match self.status.as_deref().map(str::trim) {
+            None | Some("") => true,
+            Some(s) if s.len() == 3 && s.ends_with("xx") => s[..1]
+                .parse::<u16>()
+                .map(|c| status / 100 == c)
+                .unwrap_or(false),
+            Some(s) => s.parse::<u16>().map(|c| c == status).unwrap_or(false),
+        }
+    }
+
+    fn matches(&self, ev: &ApiTraceEvent) -> bool {
+        if self
+            .path
+            .as_deref()
+            .map(|p| !ev.path.starts_with(p))
+            .unwrap_or(false)
+        {
+            return false;
+        }
+        if self
+            .method
+            .as_deref()
+            .map(|m| !ev.method.eq_ignore_ascii_case(m))
+            .unwrap_or(false)
+        {
+            return false;
+        }
+        if self.run_id.is_some() && self.run_id != ev.run_id {
+            return false;
+        }
+        if self.correlation_id.is_some() && self.correlation_id != ev.correlation_id {
+            return false;
+        }
+        if let Some(since) = self.since {
+            if parse_ts(&ev.ts).map(|t| t < since).unwrap_or(true) {
+                return false;
+            }
+        }
+        self.status_matches(ev.status)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiTracePage {
+    /// Newest first.
+    pub events: Vec<ApiTraceEvent>,
+    pub offset: usize,
+    /// Pass as `offset` to fetch the next (older) page; `None` when exhausted.
+    pub next_offset: Option<usize>,
+    /// Segments read (live file + archives).
+    pub segments_scanned: usize,
+}
+
+/// The events of one segment (gzipped archives are decompressed as they stream), in file
+/// order; `f` sees each one, and the segment is never held in memory whole.
+fn each_event(path: &Path, mut f: impl FnMut(ApiTraceEvent)) {
+    let Ok(file) = std::fs::File::open(path) else {
+        return;
+    };
+    let reader: Box<dyn BufRead> = if path.extension().and_then(|e| e.to_str()) == Some("gz") {
+        Box::new(std::io::BufReader::new(flate2::read::GzDecoder::new(file)))
+    } else {
+        Box::new(std::io::BufReader::new(file))
+    };
+    for line in reader.lines().map_while(Result::ok) {
+        if let Ok(ev) = serde_json::from_str::<ApiTraceEvent>(&line) {
+            f(ev);
+        }
+    }
+}
+
+fn query_blocking(filter: ApiTraceFilter) -> ApiTracePage {
+    let limit = if filter.limit == 0 { 200 } else { filter.limit };
+    let mut segments: Vec<PathBuf> = vec![live_path()];
+    let dir = archive_dir();
+    for (name, rotated_at) in list_archives(&dir) {
+        // An archive only holds events from before its rotation time.
+        if filter.since.map(|s| rotated_at < s).unwrap_or(false) {
+            break;
+        }
+        segments.push(dir.join(name));
+    }
+
+    // Segments are read oldest line first but paged newest first, so each one is streamed
+    // twice: once to count its matches, once to keep just the ones on this page.
+    let mut to_skip = filter.offset;
+    let mut events = Vec::new();
+    let mut scanned = 0usize;
+    let mut more = false;
+    for seg in &segments {
+        scanned += 1;
+        let mut matching = 0usize;
+        each_event(seg, |ev| matching += filter.matches(&ev) as usize);
+        let skip = to_skip.min(matching);
+        to_skip -= skip;
+        // One more than fits, to know whether there is a next page.
+        let take = (matching - skip).min(limit + 1 - events.len());
+        if take > 0 {
+            let first = matching - skip - take;
+            let mut page = Vec::with_capacity(take);
+            let mut n = 0usize;
+            each_event(seg, |ev| {
+                if filter.matches(&ev) {
+                    if (first..first + take).contains(&n) {
+                        page.push(ev);
+                    }
+                    n += 1;
+                }
+            });
+            events.extend(page.into_iter().rev());
+        }
+        if events.len() > limit {
+            events.truncate(limit);
+            more = true;
+            break;
+        }
+    }
+    ApiTracePage {
+        next_offset: more.then_some(filter.offset + events.len()),
+        offset: filter.offset,
+        events,
+        segments_scanned: scanned,
+    }
+}
+
+/// Page through the live trace and its archives, newest first.
+pub async fn query(filter: ApiTraceFilter) -> ApiTracePage {
+    let offset = filter.offset;
+    tokio::task::spawn_blocking(move || query_blocking(filter))
+        .await
+        .unwrap_or(ApiTracePage {
+            events: Vec::new(),
+            offset,
+            next_offset: None,
+            segments_scanned: 0,
+        })
+}
@@ -0,0 +1,302 @@
+//! Append-only audit log of privileged actions (`runs/audit/audit.jsonl`).
+//!
+//! Each entry carries the sha256 of the previous one (`prev_hash`) and its own `hash` over
+//! its content, so editing, dropping or reordering lines breaks the chain and shows up in
+//! [`verify`]. Requests are stored as a digest only; the receipt (if any) holds the body.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub ts: String,
+    /// User id, `admin`, `key:<digest prefix>` or `anonymous`.
+    pub actor: String,
+    /// e.g. `goal.run`, `meta.reset`, `user.policy.set`, `runs.rollback`.
+    pub action: String,
+    /// Goal id, user id or run id the action applied to.
+    pub target: String,
+    /// sha256 of the request body (JSON-serialized).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub detail: Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ChainStatus {
+    pub ok: bool,
+    pub entries: u64,
+    /// First entry whose hash or link doesn't match.
+    pub first_bad_seq: Option<u64>,
+    pub reason: Option<String>,
+}
+
+/// `(last seq, last hash)`; loaded from the file on first use and guarding appends.
+static TIP: Lazy<Mutex<Option<(u64, String)>>> = Lazy::new(|| Mutex::new(None));
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn audit_path() -> PathBuf {
+    meta3_root().join("runs").join("audit").join("audit.jsonl")
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut h = Sha256::new();
+    h.update(bytes);
+    h.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// sha256 of `v` serialized as JSON.
+pub fn digest<T: Serialize>(v: &T) -> String {
+    sha256_hex(&serde_json::to_vec(v).unwrap_or_default())
+}
+
+/// Hash over everything but `hash` itself (which is blanked while hashing).
+fn entry_hash(e: &AuditEntry) -> String {
+    let mut copy = e.clone();
+    copy.hash = String::new();
+    digest(&copy)
+}
+
+/// Actor label for an `x-api-key`: never the key itself.
+pub fn actor_for_key(api_key: Option<&str>) -> String {
+    match api_key.filter(|k| !k.is_empty()) {
+        None => "anonymous".to_string(),
+        Some(k) => {
+            let admin = std::env::var("ONE_ENGINE_ADMIN_KEY").unwrap_or_default();
+            if !admin.is_empty() && admin == k {
+                "admin".to_string()
+            } else {
+                format!("key:{}", &sha256_hex(k.as_bytes())[..8])
+            }
+        }
+    }
+}
+
+/// The log's lines, read one at a time; `None` when there is no log yet.
+async fn lines(path: &Path) -> Option<Lines<BufReader<tokio::fs::File>>> {
+    let f = tokio::fs::File::open(path).await.ok()?;
+    Some(BufReader::new(f).lines())
+}
+
+async fn last_entry() -> Option<AuditEntry> {
+    let mut lines = lines(&audit_path()).await?;
+    let mut last = None;
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Ok(e) = serde_json::from_str::<AuditEntry>(&line) {
+            last = Some(e);
+        }
+    }
+    last
+}
+
+/// Append one entry. Failures are logged, never surfaced: auditing must not block the action.
+pub async fn record(
+    actor: &str,
+    action: &str,
+    target: &str,
+    request_digest: Option<String>,
+    detail: Value,
+) {
+    let mut tip = TIP.lock().await;
+    if tip.is_none() {
+        *tip = Some(
+            last_entry()
+                .await
+                .map(|e| (e.seq, e.hash))
+                .unwrap_or((0, GENESIS.to_string())),
+        );
+    }
+    let (last_seq, last_hash) = tip.clone().unwrap_or((0, GENESIS.to_string()));
+    let mut entry = AuditEntry {
+        seq: last_seq + 1,
+        ts: chrono::Utc::now().to_rfc3339(),
+        actor: actor.to_string(),
+        action: action.to_string(),
+        target: target.to_string(),
+        request_digest,
+        detail,
+        prev_hash: last_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry_hash(&entry);
+
+    let path = audit_path();
+    if let Some(dir) = path.parent() {
+        let _ = tokio::fs::create_dir_all(dir).await;
+    }
+    let line = match serde_json::to_string(&entry) {
+        Ok(l) => l,
+        Err(e) => {
+            tracing::warn!("audit serialize failed: {}", e);
+            return;
+        }
+    };
+    let written = async {
+        let mut f = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        f.write_all(format!("{line}\n").as_bytes()).await
+    }
+    .await;
+    match written {
+        Ok(()) => *tip = Some((entry.seq, entry.hash)),
+        Err(e) => tracing::warn!("audit append to {} failed: {}", path.display(), e),
+    }
+}
+
+/// Entries with `ts >= since` (RFC 3339; all when `None`), newest last, at most `limit`.
+pub async fn query(since: Option<chrono::DateTime<chrono::Utc>>, limit: usize) -> Vec<AuditEntry> {
+    let mut out = VecDeque::new();
+    let Some(mut lines) = lines(&audit_path()).await else {
+        return Vec::new();
+    };
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(e) = serde_json::from_str::<AuditEntry>(&line) else {
+            continue;
+        };
+        let keep = match since {
+            Some(s) => chrono::DateTime::parse_from_rfc3339(&e.ts)
+                .map(|t| t.with_timezone(&chrono::Utc) >= s)
+                .unwrap_or(false),
+            None => true,
+        };
+        if keep {
+            if out.len() == limit {
+                out.pop_front();
+            }
+            out.push_back(e);
+        }
+    }
+    out.into()
+}
+
+/// Walk the whole chain: sequence numbers, `prev_hash` links and entry hashes.
+pub async fn verify() -> ChainStatus {
+    verify_file(&audit_path()).await
+}
+
+async fn verify_file(path: &Path) -> ChainStatus {
+    let mut prev = GENESIS.to_string();
+    let mut n = 0u64;
+    let intact = |n: u64| ChainStatus {
+        ok: true,
+        entries: n,
+        first_bad_seq: None,
+        reason: None,
+    };
+    let Some(mut lines) = lines(path).await else {
+        return intact(0);
+    };
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let bad = |seq: u64, reason: String| ChainStatus {
+            ok: false,
+            entries: n,
+            first_bad_seq: Some(seq),
+            reason: Some(reason),
+        };
+        let e = match serde_json::from_str::<AuditEntry>(&line) {
+            Ok(e) => e,
+            Err(err) => return bad(n + 1, format!("unparseable entry: {}", err)),
+        };
+        if e.seq != n + 1 {
+            return bad(e.seq, format!("expected seq {}, found {}", n + 1, e.seq));
+        }
+        if e.prev_hash != prev {
+            return bad(
+                e.seq,
+                "prev_hash does not match the previous entry".to_string(),
+            );
+        }
+        if entry_hash(&e) != e.hash {
+            return bad(e.seq, "entry content does not match its hash".to_string());
+        }
+        prev = e.hash;
+        n += 1;
+    }
+    intact(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chained(n: u64) -> Vec<AuditEntry> {
+        let mut prev = GENESIS.to_string();
+        (1..=n)
+            .map(|seq| {
+                let mut e = AuditEntry {
+                    seq,
+                    ts: "2026-01-01T00:00:00Z".to_string(),
+                    actor: "admin".to_string(),
+                    action: "goal.run".to_string(),
+                    target: format!("g{seq}"),
+                    request_digest: None,
+                    detail: Value::Null,
+                    prev_hash: prev.clone(),
+                    hash: String::new(),
+                };
+                e.hash = entry_hash(&e);
+                prev = e.hash.clone();
+                e
+            })
+            .collect()
+    }
+
+    async fn verify_entries(entries: &[AuditEntry]) -> ChainStatus {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let body: String = entries
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap() + "\n")
+            .collect();
+        tokio::fs::write(&path, body).await.unwrap();
+        let status = verify_file(&path).await;
+        tokio::fs::remove_file(&path).await.unwrap();
+        status
+    }
+
+    #[tokio::test]
+    async fn verify_accepts_an_intact_chain_and_pins_the_first_broken_entry() {
+        let entries = chained(4);
+        let status = verify_entries(&entries).await;
+        assert!(status.ok);
+        assert_eq!(status.entries, 4);
+
+        let mut edited = entries.clone();
+        edited[2].target = "other".to_string();
+        let status = verify_entries(&edited).await;
+        assert!(!status.ok);
+        assert_eq!((status.entries, status.first_bad_seq), (2, Some(3)));
+
+        let mut dropped = entries.clone();
+        dropped.remove(1);
+        let status = verify_entries(&dropped).await;
+        assert_eq!(status.first_bad_seq, Some(3));
+        assert!(status.reason.unwrap().contains("expected seq 2"));
+
+        let missing = verify_file(Path::new("/nonexistent/audit.jsonl")).await;
+        assert!(missing.ok && missing.entries == 0);
+    }
+}
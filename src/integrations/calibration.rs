@@ -0,0 +1,285 @@
+//! Bits calibration: does trust (`bits.t`) predict `actual_success`?
+//!
+//! Joins every finished receipt's bits with its outcome (run index), buckets predictions into
+//! a reliability curve, and scores them with Brier and expected calibration error (ECE) overall
+//! and per goal family (`meta3.build` → `meta3`). Also compares mean uncertainty (`bits.u`) of
+//! successes vs failures, since U is still set heuristically. [`write_report`] saves a report
+//! to `runs/calibration/index.html` + `calibration.json`.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use utoipa::ToSchema;
+
+use super::run_index;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CalibrationBin {
+    pub lo: f32,
+    pub hi: f32,
+    pub count: usize,
+    pub mean_predicted: Option<f32>,
+    pub observed_rate: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FamilyCalibration {
+    pub family: String,
+    pub n: usize,
+    pub success_rate: f32,
+    pub mean_predicted: f32,
+    /// Mean squared error of `t` vs outcome (0 = perfect, 0.25 = coin flip at 0.5).
+    pub brier: f32,
+    /// Count-weighted |predicted - observed| across bins.
+    pub ece: f32,
+    pub mean_u_success: Option<f32>,
+    pub mean_u_failure: Option<f32>,
+    pub bins: Vec<CalibrationBin>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CalibrationReport {
+    pub generated_at: String,
+    pub window: Option<String>,
+    /// Finished runs with a trust value.
+    pub n: usize,
+    /// Finished runs skipped for lack of bits.
+    pub skipped: usize,
+    pub overall: Option<FamilyCalibration>,
+    /// Worst-calibrated (highest Brier) first.
+    pub families: Vec<FamilyCalibration>,
+    /// Set once the report has been written (`POST /bits/calibration`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub json_url: Option<String>,
+}
+
+struct Sample {
+    t: f32,
+    u: Option<f32>,
+    ok: bool,
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// `user:<id>.meta3.build` → `meta3`.
+pub(crate) fn family(goal_id: &str) -> String {
+    let bare = goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.').map(|(_, g)| g))
+        .unwrap_or(goal_id);
+    bare.split('.').next().unwrap_or(bare).to_string()
+}
+
+fn bit(bits: &Value, key: &str) -> Option<f32> {
+    bits.get(key).and_then(|v| v.as_f64()).map(|v| v as f32)
+}
+
+fn mean(xs: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, n) = xs.fold((0.0f32, 0usize), |(s, n), x| (s + x, n + 1));
+    (n > 0).then(|| sum / n as f32)
+}
+
+fn calibrate(name: &str, samples: &[&Sample], bins: usize) -> FamilyCalibration {
+    fn outcome(s: &Sample) -> f32 {
+        if s.ok {
+            1.0
+        } else {
+            0.0
+        }
+    }
+    let n = samples.len();
+    let mut out_bins = Vec::with_capacity(bins);
+    let mut ece = 0.0f32;
+    for i in 0..bins {
+        let lo = i as f32 / bins as f32;
+        let hi = (i + 1) as f32 / bins as f32;
+        let members: Vec<&&Sample> = samples
+            .iter()
+            .filter(|s| {
+                let t = s.t.clamp(0.0, 1.0);
+                t >= lo && (t < hi || (i + 1 == bins && t <= hi))
+            })
+            .collect();
+        let mean_predicted = mean(members.iter().map(|s| s.t));
+        let observed_rate = mean(members.iter().map(|s| outcome(s)));
+        if let (Some(p), Some(o)) = (mean_predicted, observed_rate) {
+            ece += members.len() as f32 / n.max(1) as f32 * (p - o).abs();
+        }
+        out_bins.push(CalibrationBin {
+            lo,
+            hi,
+            count: members.len(),
+            mean_predicted,
+            observed_rate,
+        });
+    }
+    FamilyCalibration {
+        family: name.to_string(),
+        n,
+        success_rate: mean(samples.iter().map(|s| outcome(s))).unwrap_or(0.0),
+        mean_predicted: mean(samples.iter().map(|s| s.t)).unwrap_or(0.0),
+        brier: mean(samples.iter().map(|s| (s.t - outcome(s)).powi(2))).unwrap_or(0.0),
+        ece,
+        mean_u_success: mean(samples.iter().filter(|s| s.ok).filter_map(|s| s.u)),
+        mean_u_failure: mean(samples.iter().filter(|s| !s.ok).filter_map(|s| s.u)),
+        bins: out_bins,
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn fmt_opt(v: Option<f32>) -> String {
+    v.map(|x| format!("{:.2}", x))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Inline SVG reliability diagram: diagonal = perfect calibration, dots sized by count.
+fn reliability_svg(f: &FamilyCalibration) -> String {
+    let (w, h, pad) = (220.0f32, 220.0f32, 20.0f32);
+    let x = |p: f32| pad + p * (w - 2.0 * pad);
+    let y = |p: f32| h - pad - p * (h - 2.0 * pad);
+    let max_count = f.bins.iter().map(|b| b.count).max().unwrap_or(1).max(1) as f32;
+    let mut dots = String::new();
+    let mut path = Vec::new();
+    for b in &f.bins {
+        if let (Some(p), Some(o)) = (b.mean_predicted, b.observed_rate) {
+            let r = 2.0 + 6.0 * (b.count as f32 / max_count).sqrt();
+            dots.push_str(&format!(
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"#1f6feb\" fill-opacity=\"0.7\"><title>t {:.2}–{:.2}: n={} predicted {:.2} observed {:.2}</title></circle>",
+                x(p), y(o), r, b.lo, b.hi, b.count, p, o
+            ));
+            path.push(format!("{:.1},{:.1}", x(p), y(o)));
+        }
+    }
+    format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\"><rect x=\"{pad}\" y=\"{pad}\" width=\"{iw}\" height=\"{iw}\" fill=\"#f6f8fa\"/><line x1=\"{x0}\" y1=\"{y0}\" x2=\"{x1}\" y2=\"{y1}\" stroke=\"#999\" stroke-dasharray=\"4 3\"/><polyline points=\"{pts}\" fill=\"none\" stroke=\"#1f6feb\"/>{dots}</svg>",
+        w = w,
+        h = h,
+        pad = pad,
+        iw = w - 2.0 * pad,
+        x0 = x(0.0),
+        y0 = y(0.0),
+        x1 = x(1.0),
+        y1 = y(1.0),
+        pts = path.join(" "),
+        dots = dots
+    )
+}
+
+fn render_html(r: &CalibrationReport) -> String {
+    let mut cards = String::new();
+    for f in r.overall.iter().chain(r.families.iter()) {
+        cards.push_str(&format!(
+            "<div class=\"card\"><h3>{}</h3>{}<p class=\"muted\">n={} · success {:.0}% · predicted {:.2} · Brier {:.3} · ECE {:.3}<br>mean U: success {} / failure {}</p></div>",
+            html_escape(&f.family),
+            reliability_svg(f),
+            f.n,
+            f.success_rate * 100.0,
+            f.mean_predicted,
+            f.brier,
+            f.ece,
+            fmt_opt(f.mean_u_success),
+            fmt_opt(f.mean_u_failure)
+        ));
+    }
+    if cards.is_empty() {
+        cards.push_str("<p class=\"muted\">No finished runs with bits yet.</p>");
+    }
+    format!(
+        r#"<!doctype html><html><head><meta charset="utf-8"><title>Bits calibration</title>
+<meta name="viewport" content="width=device-width,initial-scale=1">
+<style>body{{font-family:system-ui,-apple-system,Segoe UI,Roboto,Arial;margin:24px}} a{{color:#1f6feb;text-decoration:none}} .muted{{color:#57606a;font-size:12px}} .grid{{display:flex;flex-wrap:wrap;gap:16px}} .card{{border:1px solid #eee;border-radius:8px;padding:12px;width:240px}} h3{{margin:0 0 8px 0;font-size:14px}}</style>
+</head><body><h1>Bits calibration</h1>
+<p class="muted">Generated {generated} · window {window} · {n} runs ({skipped} without bits skipped) · trust <code>t</code> vs <code>actual_success</code>; dashed line = perfectly calibrated · <a href="calibration.json">calibration.json</a></p>
+<div class="grid">{cards}</div>
+</body></html>"#,
+        generated = html_escape(&r.generated_at),
+        window = html_escape(r.window.as_deref().unwrap_or("all")),
+        n = r.n,
+        skipped = r.skipped,
+        cards = cards
+    )
+}
+
+/// Compute calibration over receipts since `since` (all when `None`). Writes nothing.
+pub async fn compute(
+    window: Option<String>,
+    since: Option<DateTime<Utc>>,
+    bins: usize,
+) -> CalibrationReport {
+    let bins = bins.clamp(2, 50);
+    let mut samples: Vec<(String, Sample)> = Vec::new();
+    let mut skipped = 0;
+    for r in run_index::scan(since).await {
+        let Some(ok) = r.success else {
+            continue;
+        };
+        let bits = r.bits.unwrap_or(Value::Null);
+        match bit(&bits, "t") {
+            Some(t) => samples.push((
+                family(&r.goal_id),
+                Sample {
+                    t,
+                    u: bit(&bits, "u"),
+                    ok,
+                },
+            )),
+            None => skipped += 1,
+        }
+    }
+
+    let mut by_family: BTreeMap<&str, Vec<&Sample>> = BTreeMap::new();
+    for (fam, s) in &samples {
+        by_family.entry(fam.as_str()).or_default().push(s);
+    }
+    let mut families: Vec<FamilyCalibration> = by_family
+        .into_iter()
+        .map(|(fam, ss)| calibrate(fam, &ss, bins))
+        .collect();
+    families.sort_by(|a, b| {
+        b.brier
+            .partial_cmp(&a.brier)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let all: Vec<&Sample> = samples.iter().map(|(_, s)| s).collect();
+
+    CalibrationReport {
+        generated_at: Utc::now().to_rfc3339(),
+        window,
+        n: all.len(),
+        skipped,
+        overall: (!all.is_empty()).then(|| calibrate("all", &all, bins)),
+        families,
+        report_url: None,
+        json_url: None,
+    }
+}
+
+/// Write `report` to `runs/calibration/` and point its urls at the written files.
+pub async fn write_report(report: &mut CalibrationReport) -> std::io::Result<()> {
+    let dir = meta3_root().join("runs").join("calibration");
+    tokio::fs::create_dir_all(&dir).await?;
+    report.report_url = Some(crate::engine::urls::url_for("/runs/calibration/index.html"));
+    report.json_url = Some(crate::engine::urls::url_for(
+        "/runs/calibration/calibration.json",
+    ));
+    tokio::fs::write(
+        dir.join("calibration.json"),
+        serde_json::to_string_pretty(report).unwrap_or_default(),
+    )
+    .await?;
+    tokio::fs::write(dir.join("index.html"), render_html(report)).await
+}
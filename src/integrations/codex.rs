@@ -0,0 +1,784 @@
+//! Codex history sources and import: raw archives/rollouts → deduplicated UTIR events under `runs/utir/`.
+//!
+//! Where the raw history lives is configurable ([`sources`]); the built-in defaults match the
+//! `agents/NIX.codecli` layout.
+//!
+//! `import` reads each raw JSONL file from the byte offset it stopped at last time
+//! (`codex_import_state.json`), normalizes events to [`UtirEvent`], drops ones whose content
+//! id was already imported, appends the rest to `codex_events.jsonl` and rebuilds the
+//! per-session index `codex_sessions.json`. `/codex/search` reads the normalized events
+//! through [`search`] instead of tailing raw files.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use one_engine::jsonl;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+const MAX_TEXT_CHARS: usize = 4000;
+const MAX_SESSION_ITEMS: usize = 50;
+
+static IMPORT_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+/// Parsed `codex_events.jsonl` and the mtime + length it was read at.
+type CachedEvents = ((Option<SystemTime>, u64), std::sync::Arc<Vec<UtirEvent>>);
+static EVENTS_CACHE: Lazy<std::sync::Mutex<Option<CachedEvents>>> =
+    Lazy::new(|| std::sync::Mutex::new(None));
+static RE_FILE_PATH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?:^|[\s'"`(=])((?:\.{0,2}/)?(?:[\w.-]+/)+[\w.-]+\.[A-Za-z0-9]{1,8})\b"#).unwrap()
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct UtirEvent {
+    /// sha256 over (session, ts, role, kind, text, command): the dedupe key.
+    pub id: String,
+    pub ts: Option<String>,
+    pub session: String,
+    pub source: String,
+    pub file: String,
+    pub line: u64,
+    pub role: Option<String>,
+    pub kind: Option<String>,
+    pub text: String,
+    pub command: Option<String>,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct SessionSummary {
+    pub session: String,
+    pub source: String,
+    pub first_ts: Option<String>,
+    pub last_ts: Option<String>,
+    pub events: u64,
+    pub commands: Vec<String>,
+    pub files_touched: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FileCursor {
+    offset: u64,
+    len: u64,
+    mtime: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ImportState {
+    files: BTreeMap<String, FileCursor>,
+    updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ImportReport {
+    pub files_scanned: usize,
+    pub files_changed: usize,
+    pub lines_read: u64,
+    pub events_added: u64,
+    pub duplicates: u64,
+    pub unparsed: u64,
+    pub sessions: usize,
+    pub total_events: u64,
+    pub duration_ms: u64,
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+pub fn enabled() -> bool {
+    match std::env::var("ONE_ENGINE_ENABLE_CODEX_HISTORY") {
+        Ok(v) => {
+            let v = v.to_ascii_lowercase();
+            v == "1" || v == "true" || v == "yes" || v == "y"
+        }
+        Err(_) => false,
+    }
+}
+
+/// Source kinds: `archive` (era exports, newest file wins for tails), `rollouts` (session logs,
+/// newest by mtime), `utir` (already-normalized JSONL; searched but not imported).
+pub const SOURCE_KINDS: &[&str] = &["archive", "rollouts", "utir"];
+
+/// One configured Codex history location.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct CodexSource {
+    pub id: String,
+    pub kind: String,
+    /// Directory or single file; relative paths resolve against `META3_ROOT`.
+    pub path: String,
+    /// Glob over paths relative to `path` (`**` recurses). Default `*.jsonl`.
+    #[serde(default = "default_glob")]
+    pub glob: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodexSourcesFile {
+    #[serde(default)]
+    sources: Vec<CodexSource>,
+}
+
+/// A file matched by a [`CodexSource`].
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    /// Path relative to the source root (the file name for flat layouts).
+    pub name: String,
+    pub path: PathBuf,
+    pub len: u64,
+    pub modified: SystemTime,
+}
+
+fn default_glob() -> String {
+    "*.jsonl".to_string()
+}
+
+impl CodexSource {
+    fn new(id: &str, kind: &str, path: &str, glob: &str, description: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            kind: kind.to_string(),
+            path: path.to_string(),
+            glob: glob.to_string(),
+            description: description.to_string(),
+        }
+    }
+
+    pub fn root(&self) -> PathBuf {
+        let p = PathBuf::from(&self.path);
+        if p.is_absolute() {
+            p
+        } else {
+            meta3_root().join(p)
+        }
+    }
+
+    /// Matching `.jsonl` files, sorted by name.
+    pub fn files(&self) -> Vec<SourceFile> {
+        let root = self.root();
+        let stat = |path: PathBuf, name: String| {
+            let m = std::fs::metadata(&path).ok().filter(|m| m.is_file())?;
+            Some(SourceFile {
+                name,
+                len: m.len(),
+                modified: m.modified().unwrap_or(std::time::UNIX_EPOCH),
+                path,
+            })
+        };
+        if root.is_file() {
+            let name = root
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_string();
+            return stat(root.clone(), name).into_iter().collect();
+        }
+        let mut out: Vec<SourceFile> = walkdir::WalkDir::new(&root)
+            .max_depth(if self.glob.contains("**") || self.glob.contains('/') {
+                8
+            } else {
+                1
+            })
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file())
+            .filter_map(|e| {
+                let rel = e
+                    .path()
+                    .strip_prefix(&root)
+                    .ok()?
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                (rel.ends_with(".jsonl") && one_engine::research::glob_match(&self.glob, &rel))
+                    .then(|| stat(e.path().to_path_buf(), rel))
+                    .flatten()
+            })
+            .collect();
+        out.sort_by(|a, b| a.name.cmp(&b.name));
+        out
+    }
+
+    /// Newest file by name (archives are named by export date).
+    pub fn latest_by_name(&self) -> Option<SourceFile> {
+        self.files().into_iter().max_by(|a, b| a.name.cmp(&b.name))
+    }
+}
+
+/// The layout this engine grew up with; used when nothing is configured.
+fn default_sources() -> Vec<CodexSource> {
+    vec![
+        CodexSource::new(
+            "orchestrator_archives",
+            "archive",
+            "agents/NIX.codecli/orchestrator/runs/archives",
+            "codex_history_*.jsonl",
+            "Codex Time Machine era archives (codex_history_*.jsonl)",
+        ),
+        CodexSource::new(
+            "meta3_rollouts",
+            "rollouts",
+            "agents/NIX.codecli/meta3/logs",
+            "*.jsonl",
+            "Meta3 rollout logs (*.jsonl)",
+        ),
+        CodexSource::new(
+            "utir_normalized_codex",
+            "utir",
+            "runs/utir/normalized_codex.jsonl",
+            "*.jsonl",
+            "UTIR normalized Codex JSONL",
+        ),
+        CodexSource::new(
+            "utir_normalized_history",
+            "utir",
+            "runs/utir/normalized_history.jsonl",
+            "*.jsonl",
+            "UTIR normalized history/event ledger JSONL",
+        ),
+    ]
+}
+
+/// `ONE_ENGINE_CODEX_SOURCES=id:kind:path[:glob],...`
+fn sources_from_env(raw: &str) -> Vec<CodexSource> {
+    raw.split(',')
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+        .filter_map(|e| {
+            let mut parts = e.splitn(4, ':');
+            let id = parts.next()?.trim();
+            let kind = parts.next()?.trim();
+            let path = parts.next()?.trim();
+            let glob = parts.next().map(|g| g.trim()).filter(|g| !g.is_empty());
+            Some(CodexSource::new(
+                id,
+                kind,
+                path,
+                glob.unwrap_or("*.jsonl"),
+                "",
+            ))
+        })
+        .collect()
+}
+
+/// Configured sources: `ONE_ENGINE_CODEX_SOURCES`, else `ONE_ENGINE_CODEX_SOURCES_FILE`
+/// (default `config/codex_sources.yaml`), else [`default_sources`]. Entries with an unknown
+/// kind, an unsafe id or a duplicate id are dropped.
+pub fn sources() -> Vec<CodexSource> {
+    let configured = match std::env::var("ONE_ENGINE_CODEX_SOURCES") {
+        Ok(raw) if !raw.trim().is_empty() => sources_from_env(&raw),
+        _ => {
+            let path = std::env::var("ONE_ENGINE_CODEX_SOURCES_FILE")
+                .unwrap_or_else(|_| "config/codex_sources.yaml".to_string());
+            match std::fs::read_to_string(&path) {
+                Ok(raw) => match serde_yaml::from_str::<CodexSourcesFile>(&raw) {
+                    Ok(f) => f.sources,
+                    Err(e) => {
+                        tracing::warn!("invalid {}: {}", path, e);
+                        Vec::new()
+                    }
+                },
+                Err(_) => return default_sources(),
+            }
+        }
+    };
+    let mut seen = HashSet::new();
+    configured
+        .into_iter()
+        .filter(|s| {
+            let ok = SOURCE_KINDS.contains(&s.kind.as_str())
+                && !s.id.is_empty()
+                && s.id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                && !s.path.is_empty();
+            if !ok {
+                tracing::warn!("ignoring codex source {:?} ({}: {})", s.id, s.kind, s.path);
+            }
+            ok && seen.insert(s.id.clone())
+        })
+        .collect()
+}
+
+pub fn sources_of(kind: &str) -> Vec<CodexSource> {
+    sources().into_iter().filter(|s| s.kind == kind).collect()
+}
+
+fn utir_dir() -> PathBuf {
+    meta3_root().join("runs").join("utir")
+}
+
+pub fn events_path() -> PathBuf {
+    utir_dir().join("codex_events.jsonl")
+}
+
+pub fn sessions_path() -> PathBuf {
+    utir_dir().join("codex_sessions.json")
+}
+
+fn state_path() -> PathBuf {
+    utir_dir().join("codex_import_state.json")
+}
+
+fn str_at<'a>(v: &'a Value, ptrs: &[&str]) -> Option<&'a str> {
+    ptrs.iter()
+        .find_map(|p| v.pointer(p).and_then(|x| x.as_str()))
+        .filter(|s| !s.is_empty())
+}
+
+/// Text from a string or a `[{text}]` / `[{type, text}]` content array.
+fn content_text(v: &Value) -> Option<String> {
+    match v {
+        Value::String(s) if !s.trim().is_empty() => Some(s.clone()),
+        Value::Array(parts) => {
+            let joined = parts
+                .iter()
+                .filter_map(|p| {
+                    p.get("text")
+                        .and_then(|t| t.as_str())
+                        .or_else(|| p.as_str())
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            (!joined.trim().is_empty()).then_some(joined)
+        }
+        _ => None,
+    }
+}
+
+/// Shell command from `{command: [..] | ".."}`, possibly JSON-encoded in `arguments`.
+fn command_of(v: &Value) -> Option<String> {
+    let from = |c: &Value| match c {
+        Value::String(s) => Some(s.clone()),
+        Value::Array(a) => {
+            let parts: Vec<&str> = a.iter().filter_map(|x| x.as_str()).collect();
+            // ["bash", "-lc", "<script>"] → the script
+            match parts.as_slice() {
+                [sh, flag, script] if sh.ends_with("sh") && flag.starts_with('-') => {
+                    Some(script.to_string())
+                }
+                _ if !parts.is_empty() => Some(parts.join(" ")),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    for base in [v, v.get("payload").unwrap_or(&Value::Null)] {
+        if let Some(c) = base
+            .get("command")
+            .or_else(|| base.get("cmd"))
+            .and_then(from)
+        {
+            return Some(c);
+        }
+        if let Some(args) = base.get("arguments").and_then(|a| a.as_str()) {
+            if let Ok(parsed) = serde_json::from_str::<Value>(args) {
+                if let Some(c) = parsed
+                    .get("command")
+                    .or_else(|| parsed.get("cmd"))
+                    .and_then(from)
+                {
+                    return Some(c);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn event_id(
+    session: &str,
+    ts: Option<&str>,
+    role: Option<&str>,
+    kind: Option<&str>,
+    text: &str,
+    cmd: Option<&str>,
+) -> String {
+    let mut h = Sha256::new();
+    for part in [
+        session,
+        ts.unwrap_or(""),
+        role.unwrap_or(""),
+        kind.unwrap_or(""),
+        text,
+        cmd.unwrap_or(""),
+    ] {
+        h.update(part.as_bytes());
+        h.update([0u8]);
+    }
+    format!("{:x}", h.finalize())[..32].to_string()
+}
+
+/// Normalize one raw line; `None` when it carries no text or command.
+fn normalize(
+    raw: &Value,
+    source: &str,
+    file: &str,
+    line: u64,
+    session_hint: &str,
+) -> Option<UtirEvent> {
+    let session = str_at(
+        raw,
+        &[
+            "/session_id",
+            "/session",
+            "/payload/session_id",
+            "/conversation_id",
+        ],
+    )
+    .unwrap_or(session_hint)
+    .to_string();
+    let ts = str_at(
+        raw,
+        &["/ts", "/timestamp", "/payload/timestamp", "/created_at"],
+    )
+    .map(|s| s.to_string());
+    let role = str_at(raw, &["/role", "/payload/role", "/message/role"]).map(|s| s.to_string());
+    let kind = str_at(raw, &["/payload/type", "/type", "/event", "/kind"]).map(|s| s.to_string());
+    let command = command_of(raw).map(|c| one_engine::redact::redact(&c));
+    let text = [
+        "/content",
+        "/payload/content",
+        "/message/content",
+        "/text",
+        "/payload/text",
+        "/msg/message",
+        "/payload/output",
+    ]
+    .iter()
+    .find_map(|p| raw.pointer(p).and_then(content_text))
+    .or_else(|| raw.get("payload").and_then(content_text))
+    .unwrap_or_default();
+    if text.trim().is_empty() && command.is_none() {
+        return None;
+    }
+    let text: String = one_engine::redact::redact(&text)
+        .chars()
+        .take(MAX_TEXT_CHARS)
+        .collect();
+    let mut files: Vec<String> = RE_FILE_PATH
+        .captures_iter(&format!("{} {}", text, command.as_deref().unwrap_or("")))
+        .filter_map(|c| c.get(1).map(|m| m.as_str().to_string()))
+        .filter(|p| !p.starts_with("//") && !p.contains("://"))
+        .collect();
+    files.sort();
+    files.dedup();
+    files.truncate(MAX_SESSION_ITEMS);
+    Some(UtirEvent {
+        id: event_id(
+            &session,
+            ts.as_deref(),
+            role.as_deref(),
+            kind.as_deref(),
+            &text,
+            command.as_deref(),
+        ),
+        ts,
+        session,
+        source: source.to_string(),
+        file: file.to_string(),
+        line,
+        role,
+        kind,
+        text,
+        command,
+        files,
+    })
+}
+
+fn mtime_secs(m: &std::fs::Metadata) -> Option<i64> {
+    m.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+}
+
+pub fn load_events() -> Vec<UtirEvent> {
+    std::fs::read_to_string(events_path())
+        .map(|s| {
+            s.lines()
+                .filter_map(|l| serde_json::from_str(l).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn build_sessions(events: &[UtirEvent]) -> Vec<SessionSummary> {
+    let mut by: HashMap<&str, SessionSummary> = HashMap::new();
+    let mut cmd_counts: HashMap<&str, HashMap<&str, u64>> = HashMap::new();
+    let mut file_counts: HashMap<&str, HashMap<&str, u64>> = HashMap::new();
+    for e in events {
+        let s = by
+            .entry(e.session.as_str())
+            .or_insert_with(|| SessionSummary {
+                session: e.session.clone(),
+                source: e.source.clone(),
+                ..Default::default()
+            });
+        s.events += 1;
+        if let Some(ts) = e.ts.as_deref() {
+            if s.first_ts.as_deref().is_none_or(|f| ts < f) {
+                s.first_ts = Some(ts.to_string());
+            }
+            if s.last_ts.as_deref().is_none_or(|l| ts > l) {
+                s.last_ts = Some(ts.to_string());
+            }
+        }
+        if let Some(c) = e.command.as_deref() {
+            *cmd_counts
+                .entry(e.session.as_str())
+                .or_default()
+                .entry(c)
+                .or_insert(0) += 1;
+        }
+        for f in &e.files {
+            *file_counts
+                .entry(e.session.as_str())
+                .or_default()
+                .entry(f.as_str())
+                .or_insert(0) += 1;
+        }
+    }
+    let top = |m: Option<&HashMap<&str, u64>>| -> Vec<String> {
+        let mut v: Vec<(&str, u64)> = m
+            .map(|m| m.iter().map(|(k, c)| (*k, *c)).collect())
+            .unwrap_or_default();
+        v.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        v.into_iter()
+            .take(MAX_SESSION_ITEMS)
+            .map(|(k, _)| k.to_string())
+            .collect()
+    };
+    let mut out: Vec<SessionSummary> = by
+        .into_iter()
+        .map(|(id, mut s)| {
+            s.commands = top(cmd_counts.get(id));
+            s.files_touched = top(file_counts.get(id));
+            s
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        b.last_ts
+            .cmp(&a.last_ts)
+            .then_with(|| a.session.cmp(&b.session))
+    });
+    out
+}
+
+fn import_blocking(full: bool) -> Result<ImportReport> {
+    let started = std::time::Instant::now();
+    let dir = utir_dir();
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let mut state: ImportState = if full {
+        ImportState::default()
+    } else {
+        std::fs::read_to_string(state_path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    };
+    if full {
+        let _ = std::fs::remove_file(events_path());
+    }
+    let mut events = load_events();
+    let mut seen: HashSet<String> = events.iter().map(|e| e.id.clone()).collect();
+    let mut report = ImportReport::default();
+    let mut added: Vec<UtirEvent> = Vec::new();
+
+    for src in sources().into_iter().filter(|s| s.kind != "utir") {
+        for sf in src.files() {
+            let path = sf.path;
+            report.files_scanned += 1;
+            let key = path.display().to_string();
+            let Ok(meta) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let cursor = state.files.get(&key).cloned().unwrap_or_default();
+            let mtime = mtime_secs(&meta);
+            if cursor.len == meta.len() && cursor.mtime == mtime {
+                continue;
+            }
+            // Truncated/rewritten files are re-read from the start; dedupe drops repeats.
+            let start = if meta.len() < cursor.offset {
+                0
+            } else {
+                cursor.offset
+            };
+            let mut f = std::fs::File::open(&path)?;
+            f.seek(SeekFrom::Start(start))?;
+            // Only consume complete lines; a partial last line is picked up next time.
+            let mut lines = jsonl::Lines::complete(BufReader::new(f));
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("")
+                .to_string();
+            let stem = name.trim_end_matches(".jsonl").to_string();
+            // Line numbers are relative to the whole file, so count lines before `start`.
+            let base_line = if start == 0 {
+                0
+            } else {
+                count_lines(&path, start)
+            };
+            report.files_changed += 1;
+            for (i, line) in lines.by_ref().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                report.lines_read += 1;
+                let Ok(v) = serde_json::from_str::<Value>(&line) else {
+                    report.unparsed += 1;
+                    continue;
+                };
+                let Some(ev) = normalize(&v, &src.id, &name, base_line + i as u64 + 1, &stem)
+                else {
+                    continue;
+                };
+                if seen.insert(ev.id.clone()) {
+                    added.push(ev);
+                } else {
+                    report.duplicates += 1;
+                }
+            }
+            state.files.insert(
+                key,
+                FileCursor {
+                    offset: start + lines.consumed(),
+                    len: meta.len(),
+                    mtime,
+                },
+            );
+        }
+    }
+
+    if !added.is_empty() {
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(events_path())
+            .with_context(|| format!("failed to open {}", events_path().display()))?;
+        for e in &added {
+            writeln!(f, "{}", serde_json::to_string(e)?)?;
+        }
+    }
+    report.events_added = added.len() as u64;
+    events.extend(added);
+    let sessions = build_sessions(&events);
+    report.sessions = sessions.len();
+    report.total_events = events.len() as u64;
+    std::fs::write(sessions_path(), serde_json::to_string_pretty(&sessions)?)
+        .with_context(|| format!("failed to write {}", sessions_path().display()))?;
+    state.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    std::fs::write(state_path(), serde_json::to_string_pretty(&state)?)
+        .with_context(|| format!("failed to write {}", state_path().display()))?;
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    Ok(report)
+}
+
+fn count_lines(path: &Path, upto: u64) -> u64 {
+    let Ok(f) = std::fs::File::open(path) else {
+        return 0;
+    };
+    let mut buf = Vec::new();
+    if f.take(upto).read_to_end(&mut buf).is_err() {
+        return 0;
+    }
+    buf.iter().filter(|b| **b == b'\n').count() as u64
+}
+
+/// Import new raw events (incremental unless `full`) and index them for search; serialized so
+/// the background job and the `codex.import` goal never interleave writes.
+pub async fn import(full: bool) -> Result<ImportReport> {
+    let _guard = IMPORT_LOCK.lock().await;
+    one_engine::atomic::spawn_blocking(move || {
+        let report = import_blocking(full)?;
+        if let Err(e) = super::codex_index::refresh() {
+            tracing::warn!("codex index refresh failed: {}", e);
+        }
+        Ok(report)
+    })
+    .await
+    .context("codex import task failed")?
+}
+
+/// Background importer: every `ONE_ENGINE_CODEX_IMPORT_INTERVAL_SECS` (default 300, 0 disables)
+/// while codex history is enabled.
+pub fn spawn_background_import() {
+    let interval = std::env::var("ONE_ENGINE_CODEX_IMPORT_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(300);
+    if interval == 0 || !enabled() {
+        return;
+    }
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(std::time::Duration::from_secs(interval));
+        loop {
+            tick.tick().await;
+            match import(false).await {
+                Ok(r) if r.events_added > 0 => {
+                    tracing::info!(
+                        "codex import: +{} events ({} sessions)",
+                        r.events_added,
+                        r.sessions
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("codex import failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Normalized events, re-read only when `codex_events.jsonl` changes.
+pub fn cached_events() -> std::sync::Arc<Vec<UtirEvent>> {
+    let path = events_path();
+    let key = std::fs::metadata(&path)
+        .map(|m| (m.modified().ok(), m.len()))
+        .unwrap_or((None, 0));
+    let mut cache = EVENTS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((k, evs)) = cache.as_ref() {
+        if *k == key {
+            return evs.clone();
+        }
+    }
+    let evs = std::sync::Arc::new(load_events());
+    *cache = Some((key, evs.clone()));
+    evs
+}
+
+pub fn has_index() -> bool {
+    events_path().is_file()
+}
+
+pub fn load_sessions() -> Vec<SessionSummary> {
+    std::fs::read_to_string(sessions_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Newest-first matches over the normalized events (text and command).
+pub fn search<F: Fn(&str) -> bool>(matches: F, limit: usize) -> (Vec<UtirEvent>, usize) {
+    let events = cached_events();
+    let mut out = Vec::new();
+    for e in events.iter().rev() {
+        if out.len() >= limit {
+            break;
+        }
+        if matches(&e.text) || e.command.as_deref().is_some_and(&matches) {
+            out.push(e.clone());
+        }
+    }
+    (out, events.len())
+}
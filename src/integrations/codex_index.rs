@@ -0,0 +1,482 @@
+//! Token index over the imported Codex events, so `/codex/search` doesn't scan them all.
+//!
+//! `runs/utir/codex_index.json` maps each lowercase alphanumeric token of an event's text and
+//! command to the events that contain it; every event is kept as its byte offset in
+//! `codex_events.jsonl` plus the raw `file` and `line` it came from. [`refresh`] reads the
+//! events file from the offset it stopped at and only indexes the new lines, appending them to
+//! `codex_index.delta.jsonl`; the index file itself is only rewritten when the deltas outgrow
+//! it, and a shorter or rewritten events file (a `full` import) rebuilds it. The background
+//! importer refreshes it after every import, and a search that finds it behind kicks off a
+//! refresh.
+//!
+//! A query is split the same way; the events whose tokens contain every query token are
+//! candidates, read newest first at their offsets and checked against the exact query. Tokens
+//! containing a query token are found through their two-character substrings, not by a scan of
+//! the vocabulary.
+//! Regex queries and queries without a token of two or more characters fall back to the
+//! full scan. [`status`] says how far behind the events file the index is.
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use one_engine::{atomic, jsonl};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, BTreeSet, HashMap};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use utoipa::ToSchema;
+
+use super::codex::{self, UtirEvent};
+
+const INDEX_VERSION: u32 = 1;
+/// Tokens longer than this (encoded blobs, hashes) are not indexed.
+const MAX_TOKEN_CHARS: usize = 128;
+/// Bytes at the start of the events file that identify it across refreshes.
+const HEAD_BYTES: u64 = 512;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DocRef {
+    /// Byte offset of the event's line in `codex_events.jsonl`.
+    offset: u64,
+    file: String,
+    line: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    /// sha256 of the events file's first bytes when the index was built.
+    head: String,
+    /// Bytes of the events file indexed so far.
+    offset: u64,
+    docs: Vec<DocRef>,
+    postings: HashMap<String, Vec<u32>>,
+    updated_at: Option<String>,
+    /// Every token in `postings`, so `grams` can refer to them by position.
+    #[serde(skip)]
+    vocab: Vec<String>,
+    /// Two-character substring → positions in `vocab` of the tokens that contain it.
+    #[serde(skip)]
+    grams: HashMap<String, Vec<u32>>,
+}
+
+impl Index {
+    fn add(&mut self, d: DeltaDoc) {
+        let doc = self.docs.len() as u32;
+        self.docs.push(d.doc);
+        for t in d.tokens {
+            match self.postings.entry(t) {
+                Entry::Occupied(mut e) => e.get_mut().push(doc),
+                Entry::Vacant(e) => {
+                    add_grams(&mut self.grams, self.vocab.len() as u32, e.key());
+                    self.vocab.push(e.key().clone());
+                    e.insert(vec![doc]);
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, delta: Delta) {
+        for d in delta.docs {
+            self.add(d);
+        }
+        self.offset = delta.to;
+        self.updated_at = delta.updated_at;
+    }
+
+    /// `vocab` and `grams` of an index read from disk.
+    fn with_grams(mut self) -> Self {
+        self.vocab = self.postings.keys().cloned().collect();
+        self.grams.clear();
+        for (i, t) in self.vocab.iter().enumerate() {
+            add_grams(&mut self.grams, i as u32, t);
+        }
+        self
+    }
+}
+
+/// The events one [`refresh`] indexed: a line of `codex_index.delta.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Delta {
+    head: String,
+    /// `offset` of the index this applies to; 0 after a rebuild.
+    from: u64,
+    /// `offset` once applied.
+    to: u64,
+    docs: Vec<DeltaDoc>,
+    updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeltaDoc {
+    #[serde(flatten)]
+    doc: DocRef,
+    tokens: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct IndexStatus {
+    /// An index exists and searches use it.
+    pub ready: bool,
+    pub events: usize,
+    pub tokens: usize,
+    pub indexed_bytes: u64,
+    pub events_bytes: u64,
+    /// Bytes of imported events not indexed yet; results may miss them while this is > 0.
+    pub lag_bytes: u64,
+    pub updated_at: Option<String>,
+    pub age_s: Option<i64>,
+    pub refreshing: bool,
+}
+
+static INDEX: Lazy<RwLock<Option<Arc<Index>>>> = Lazy::new(|| RwLock::new(None));
+static BUILD_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+static REFRESHING: AtomicBool = AtomicBool::new(false);
+
+pub fn index_path() -> PathBuf {
+    codex::events_path().with_file_name("codex_index.json")
+}
+
+fn delta_path() -> PathBuf {
+    codex::events_path().with_file_name("codex_index.delta.jsonl")
+}
+
+fn bigrams(token: &str) -> BTreeSet<String> {
+    let chars: Vec<char> = token.chars().collect();
+    chars.windows(2).map(|w| w.iter().collect()).collect()
+}
+
+fn add_grams(grams: &mut HashMap<String, Vec<u32>>, id: u32, token: &str) {
+    for g in bigrams(token) {
+        grams.entry(g).or_default().push(id);
+    }
+}
+
+fn tokens(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| {
+            let n = t.chars().count();
+            (2..=MAX_TOKEN_CHARS).contains(&n)
+        })
+        .map(|t| t.to_lowercase())
+}
+
+fn head_hash(path: &Path) -> String {
+    let mut buf = Vec::new();
+    if let Ok(f) = std::fs::File::open(path) {
+        let _ = f.take(HEAD_BYTES).read_to_end(&mut buf);
+    }
+    use sha2::{Digest, Sha256};
+    Sha256::digest(&buf)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Index the lines of `events` past `index.offset`. Returns what was added, `None` when the
+/// index didn't change.
+fn update(index: &mut Index, events: &Path) -> Result<Option<Delta>> {
+    let len = std::fs::metadata(events).map(|m| m.len()).unwrap_or(0);
+    let mut changed = false;
+    // The head only changes when the file is rewritten; until it reaches HEAD_BYTES it also
+    // grows with appends, which re-indexing a tiny file absorbs.
+    let head = head_hash(events);
+    if index.version != INDEX_VERSION || index.head != head || len < index.offset {
+        *index = Index {
+            version: INDEX_VERSION,
+            head: head.clone(),
+            ..Default::default()
+        };
+        changed = true;
+    }
+    let mut delta = Delta {
+        head,
+        from: index.offset,
+        to: index.offset,
+        docs: Vec::new(),
+        updated_at: index.updated_at.clone(),
+    };
+    if len == index.offset {
+        return Ok(changed.then_some(delta));
+    }
+    let mut f = std::fs::File::open(events)
+        .with_context(|| format!("failed to open {}", events.display()))?;
+    f.seek(SeekFrom::Start(index.offset))?;
+    let start = index.offset;
+    let mut lines = jsonl::Lines::complete(BufReader::new(f));
+    loop {
+        let at = start + lines.consumed();
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let Ok(ev) = serde_json::from_str::<UtirEvent>(&line) else {
+            continue;
+        };
+        let toks: BTreeSet<String> = tokens(&ev.text)
+            .chain(ev.command.as_deref().into_iter().flat_map(tokens))
+            .collect();
+        delta.docs.push(DeltaDoc {
+            doc: DocRef {
+                offset: at,
+                file: ev.file.clone(),
+                line: ev.line,
+            },
+            tokens: toks.into_iter().collect(),
+        });
+    }
+    delta.to = start + lines.consumed();
+    delta.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    index.apply(delta.clone());
+    Ok(Some(delta))
+}
+
+/// The index file at `base` with the deltas at `deltas` that follow on from it applied.
+fn load(base: &Path, deltas: &Path) -> Option<Index> {
+    let raw = std::fs::read_to_string(base).ok()?;
+    let mut idx = serde_json::from_str::<Index>(&raw).ok()?.with_grams();
+    if let Ok(f) = std::fs::File::open(deltas) {
+        for line in jsonl::Lines::complete(BufReader::new(f)).map_while(|l| l.ok()) {
+            match serde_json::from_str::<Delta>(&line) {
+                Ok(d) if d.head == idx.head && d.from == idx.offset => idx.apply(d),
+                // Left over from before the last rewrite of the index file.
+                Ok(d) if d.to <= idx.offset => continue,
+                _ => break,
+            }
+        }
+    }
+    Some(idx)
+}
+
+/// Persist `delta`, which brought `idx` up to date: appended to `deltas`, or, after a rebuild
+/// or once the deltas are larger than the index file, by rewriting `base`.
+fn save(idx: &Index, delta: &Delta, base: &Path, deltas: &Path) -> Result<()> {
+    let base_bytes = std::fs::metadata(base).map(|m| m.len()).unwrap_or(0);
+    let delta_bytes = std::fs::metadata(deltas).map(|m| m.len()).unwrap_or(0);
+    if delta.from > 0 && base_bytes > 0 && delta_bytes < base_bytes {
+        let line = serde_json::to_string(delta)?;
+        return atomic::append(deltas, &line)
+            .with_context(|| format!("failed to append to {}", deltas.display()));
+    }
+    atomic::write(base, serde_json::to_vec(idx)?)
+        .with_context(|| format!("failed to write {}", base.display()))?;
+    match std::fs::remove_file(deltas) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("failed to remove {}", deltas.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The index in memory, else the one on disk.
+fn current() -> Option<Arc<Index>> {
+    if let Some(idx) = INDEX.read().unwrap_or_else(|e| e.into_inner()).clone() {
+        return Some(idx);
+    }
+    let idx = Arc::new(load(&index_path(), &delta_path())?);
+    *INDEX.write().unwrap_or_else(|e| e.into_inner()) = Some(idx.clone());
+    Some(idx)
+}
+
+/// Index newly imported events and save them (blocking).
+pub fn refresh() -> Result<IndexStatus> {
+    let _guard = BUILD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    REFRESHING.store(true, Ordering::SeqCst);
+    let res = (|| {
+        let mut idx = current().map(|i| (*i).clone()).unwrap_or_default();
+        if let Some(delta) = update(&mut idx, &codex::events_path())? {
+            save(&idx, &delta, &index_path(), &delta_path())?;
+            *INDEX.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(idx));
+        }
+        Ok(())
+    })();
+    REFRESHING.store(false, Ordering::SeqCst);
+    res.map(|_| status())
+}
+
+/// Start a [`refresh`] unless one is running.
+pub fn refresh_in_background() {
+    if REFRESHING.load(Ordering::SeqCst) {
+        return;
+    }
+    tokio::task::spawn_blocking(|| {
+        if let Err(e) = refresh() {
+            tracing::warn!("codex index refresh failed: {}", e);
+        }
+    });
+}
+
+pub fn status() -> IndexStatus {
+    let events_bytes = std::fs::metadata(codex::events_path())
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let refreshing = REFRESHING.load(Ordering::SeqCst);
+    let Some(idx) = current() else {
+        return IndexStatus {
+            events_bytes,
+            lag_bytes: events_bytes,
+            refreshing,
+            ..Default::default()
+        };
+    };
+    let age_s = idx
+        .updated_at
+        .as_deref()
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| (chrono::Utc::now() - t.with_timezone(&chrono::Utc)).num_seconds());
+    IndexStatus {
+        ready: true,
+        events: idx.docs.len(),
+        tokens: idx.postings.len(),
+        indexed_bytes: idx.offset,
+        events_bytes,
+        lag_bytes: events_bytes.saturating_sub(idx.offset),
+        updated_at: idx.updated_at.clone(),
+        age_s,
+        refreshing,
+    }
+}
+
+/// Events that may contain `query`, newest first: those with, for every query token, a token
+/// containing it. `None` when the query has no usable token.
+fn candidates(idx: &Index, query: &str) -> Option<Vec<u32>> {
+    let wanted: BTreeSet<String> = tokens(query).collect();
+    if wanted.is_empty() {
+        return None;
+    }
+    let mut acc: Option<BTreeSet<u32>> = None;
+    for q in &wanted {
+        // Only tokens sharing q's rarest two-character substring can contain it.
+        let rarest = bigrams(q)
+            .iter()
+            .map(|g| idx.grams.get(g).map_or(&[][..], |v| v.as_slice()))
+            .min_by_key(|ids| ids.len())
+            .unwrap_or(&[]);
+        let docs: BTreeSet<u32> = rarest
+            .iter()
+            .filter_map(|&id| idx.vocab.get(id as usize))
+            .filter(|t| t.contains(q.as_str()))
+            .filter_map(|t| idx.postings.get(t))
+            .flat_map(|d| d.iter().copied())
+            .collect();
+        acc = Some(match acc {
+            Some(a) => a.intersection(&docs).copied().collect(),
+            None => docs,
+        });
+    }
+    Some(acc.unwrap_or_default().into_iter().rev().collect())
+}
+
+fn read_event(reader: &mut BufReader<std::fs::File>, offset: u64) -> Option<UtirEvent> {
+    reader.seek(SeekFrom::Start(offset)).ok()?;
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    serde_json::from_str(&line).ok()
+}
+
+/// Up to `limit` newest events whose text or command `matches`, found through the index for
+/// `query`. `None` when there is no index or `query` has no token to look up.
+pub fn search<F: Fn(&str) -> bool>(
+    query: &str,
+    matches: F,
+    limit: usize,
+) -> Option<(Vec<UtirEvent>, IndexStatus)> {
+    let idx = current()?;
+    let docs = candidates(&idx, query)?;
+    let mut reader = BufReader::new(std::fs::File::open(codex::events_path()).ok()?);
+    let mut out = Vec::new();
+    for doc in docs {
+        if out.len() >= limit {
+            break;
+        }
+        let Some(d) = idx.docs.get(doc as usize) else {
+            continue;
+        };
+        let Some(ev) = read_event(&mut reader, d.offset) else {
+            continue;
+        };
+        if matches(&ev.text) || ev.command.as_deref().is_some_and(&matches) {
+            out.push(ev);
+        }
+    }
+    Some((out, status()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn event(n: u32, text: &str) -> String {
+        serde_json::json!({
+            "id": format!("e{n}"), "ts": null, "session": "s1", "source": "archive",
+            "file": "history.jsonl", "line": n, "role": "user", "kind": "message",
+            "text": text, "command": null, "files": []
+        })
+        .to_string()
+            + "\n"
+    }
+
+    #[test]
+    fn index_grows_with_appends_and_narrows_candidates() {
+        let dir = std::env::temp_dir().join(format!("codex-index-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let events = dir.join("codex_events.jsonl");
+        // Past HEAD_BYTES, so appends leave the head alone (the blob is too long to index).
+        let first_text = format!(
+            "run cargo test --workspace {}",
+            "x".repeat(HEAD_BYTES as usize)
+        );
+        std::fs::write(&events, event(1, &first_text) + &event(2, "open README.md")).unwrap();
+
+        let mut idx = Index::default();
+        let first = update(&mut idx, &events).unwrap().unwrap();
+        assert_eq!(idx.docs.len(), 2);
+        assert!(update(&mut idx, &events).unwrap().is_none());
+        let (base, deltas) = (dir.join("index.json"), dir.join("index.delta.jsonl"));
+        save(&idx, &first, &base, &deltas).unwrap();
+
+        let mut f = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&events)
+            .unwrap();
+        f.write_all(event(3, "Cargo build failed").as_bytes())
+            .unwrap();
+        // A partial line is left for the next refresh.
+        f.write_all(b"{\"id\":").unwrap();
+        let appended = update(&mut idx, &events).unwrap().unwrap();
+        assert_eq!(idx.docs.len(), 3);
+        // Only the new event is written, and reading back gives the same index.
+        save(&idx, &appended, &base, &deltas).unwrap();
+        assert_eq!(appended.docs.len(), 1);
+        assert!(deltas.exists());
+        let loaded = load(&base, &deltas).unwrap();
+        assert_eq!((loaded.docs.len(), loaded.offset), (3, idx.offset));
+        assert_eq!(candidates(&loaded, "carg").unwrap(), vec![2, 0]);
+        assert_eq!(
+            (idx.docs[2].file.as_str(), idx.docs[2].line),
+            ("history.jsonl", 3)
+        );
+
+        // Substrings of tokens match; every query token must be present.
+        assert_eq!(candidates(&idx, "carg").unwrap(), vec![2, 0]);
+        assert_eq!(candidates(&idx, "cargo test").unwrap(), vec![0]);
+        assert!(candidates(&idx, "readme cargo").unwrap().is_empty());
+        assert!(candidates(&idx, "-").is_none());
+
+        let mut reader = BufReader::new(std::fs::File::open(&events).unwrap());
+        let ev = read_event(&mut reader, idx.docs[1].offset).unwrap();
+        assert_eq!(ev.text, "open README.md");
+
+        // A rewritten file is re-indexed from the start.
+        std::fs::write(&events, event(9, "fresh start")).unwrap();
+        let rebuilt = update(&mut idx, &events).unwrap().unwrap();
+        assert_eq!(idx.docs.len(), 1);
+        save(&idx, &rebuilt, &base, &deltas).unwrap();
+        assert!(!deltas.exists());
+        assert_eq!(load(&base, &deltas).unwrap().docs.len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
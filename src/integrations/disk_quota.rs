@@ -0,0 +1,460 @@
+//! Disk quotas per user and per goal family.
+//!
+//! Runs execute inside [`atomic::metered`] with a [`RunMeter`]: every artifact a run writes
+//! through `one_engine::atomic` is charged to its user and its goal family (`wiki.build` →
+//! `wiki`) before it reaches the disk. A write that would go over a quota is refused and the
+//! run fails with a policy error; a run whose quota is already used up does not start.
+//!
+//! Finished runs append what they wrote to `runs/usage/disk.jsonl`. Usage is the sum over the
+//! last `window_days`, plus runs still in flight.
+//!
+//! ```yaml
+//! disk_quotas:
+//!   window_days: 30   # default
+//!   users:
+//!     default: 2GiB   # users without their own entry
+//!     demo: 512MB
+//!   goal_families:
+//!     wiki: 10GiB
+//! ```
+//!
+//! No entry (and no `default`) means no limit. Runs without a user only count toward their
+//! family.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use one_engine::{atomic, jsonl};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use super::calibration::family;
+
+/// The ledger is re-read at most this often (finished runs are added as they finish).
+const REFRESH: Duration = Duration::from_secs(600);
+
+/// `1024`, `"512MB"`, `"2GiB"`: decimal (kB/MB/GB/TB) or binary (KiB/MiB/GiB/TiB) units.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let n: f64 = num.parse().ok()?;
+    let mult: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        _ => return None,
+    };
+    Some((n * mult as f64) as u64)
+}
+
+fn human(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+    let mut v = bytes as f64;
+    let mut i = 0;
+    while v >= 1000.0 && i < UNITS.len() - 1 {
+        v /= 1000.0;
+        i += 1;
+    }
+    if i == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", v, UNITS[i])
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "SizeRepr")]
+struct Size(u64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeRepr {
+    Bytes(u64),
+    Text(String),
+}
+
+impl TryFrom<SizeRepr> for Size {
+    type Error = String;
+
+    fn try_from(r: SizeRepr) -> Result<Self, Self::Error> {
+        match r {
+            SizeRepr::Bytes(n) => Ok(Size(n)),
+            SizeRepr::Text(s) => parse_size(&s)
+                .map(Size)
+                .ok_or_else(|| format!("invalid size {:?}", s)),
+        }
+    }
+}
+
+fn default_window_days() -> u32 {
+    30
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct QuotaSection {
+    #[serde(default = "default_window_days")]
+    window_days: u32,
+    #[serde(default)]
+    users: HashMap<String, Size>,
+    #[serde(default)]
+    goal_families: HashMap<String, Size>,
+}
+
+impl Default for QuotaSection {
+    fn default() -> Self {
+        Self {
+            window_days: default_window_days(),
+            users: HashMap::new(),
+            goal_families: HashMap::new(),
+        }
+    }
+}
+
+impl QuotaSection {
+    fn user_quota(&self, user_id: &str) -> Option<u64> {
+        self.users
+            .get(user_id)
+            .or_else(|| self.users.get("default"))
+            .map(|s| s.0)
+    }
+
+    fn family_quota(&self, family: &str) -> Option<u64> {
+        self.goal_families
+            .get(family)
+            .or_else(|| self.goal_families.get("default"))
+            .map(|s| s.0)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesQuota {
+    #[serde(default)]
+    disk_quotas: Option<QuotaSection>,
+}
+
+static CONFIG: Lazy<QuotaSection> = Lazy::new(|| {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return QuotaSection::default();
+    };
+    match serde_yaml::from_str::<PoliciesQuota>(&raw) {
+        Ok(p) => p.disk_quotas.unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!("disk_quotas in {}: {}; no quotas enforced", path, e);
+            QuotaSection::default()
+        }
+    }
+});
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn ledger_path() -> PathBuf {
+    meta3_root().join("runs").join("usage").join("disk.jsonl")
+}
+
+type Key = (Option<String>, String);
+
+/// Bytes per (user, family): finished runs in the window, and runs in flight.
+#[derive(Default)]
+struct Ledger {
+    loaded_at: Option<Instant>,
+    done: HashMap<Key, u64>,
+    inflight: HashMap<String, (Key, u64)>,
+}
+
+impl Ledger {
+    fn refresh(&mut self, window_days: u32) {
+        if self.loaded_at.is_some_and(|t| t.elapsed() < REFRESH) {
+            return;
+        }
+        let since = Utc::now() - chrono::Duration::days(window_days as i64);
+        let mut done: HashMap<Key, u64> = HashMap::new();
+        for line in jsonl::lines(&ledger_path()).into_iter().flatten().flatten() {
+            let Ok(v) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            let ts = v
+                .get("ts")
+                .and_then(|x| x.as_str())
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|d| d.with_timezone(&Utc));
+            if ts.is_none_or(|t| t < since) {
+                continue;
+            }
+            let user = v.get("user_id").and_then(|x| x.as_str()).map(String::from);
+            let fam = v.get("family").and_then(|x| x.as_str()).unwrap_or("");
+            let bytes = v.get("bytes").and_then(|x| x.as_u64()).unwrap_or(0);
+            *done.entry((user, fam.to_string())).or_insert(0) += bytes;
+        }
+        self.done = done;
+        self.loaded_at = Some(Instant::now());
+    }
+
+    fn entries(&self) -> impl Iterator<Item = (&Key, u64)> {
+        self.done
+            .iter()
+            .map(|(k, b)| (k, *b))
+            .chain(self.inflight.values().map(|(k, b)| (k, *b)))
+    }
+
+    fn user_bytes(&self, user_id: &str) -> u64 {
+        self.entries()
+            .filter(|(k, _)| k.0.as_deref() == Some(user_id))
+            .map(|(_, b)| b)
+            .sum()
+    }
+
+    fn family_bytes(&self, family: &str) -> u64 {
+        self.entries()
+            .filter(|(k, _)| k.1 == family)
+            .map(|(_, b)| b)
+            .sum()
+    }
+
+    /// Why writing `bytes` more for (`user_id`, `family`) is refused, if it is.
+    fn over(
+        &self,
+        cfg: &QuotaSection,
+        user_id: Option<&str>,
+        family: &str,
+        bytes: u64,
+    ) -> Option<String> {
+        let refuse = |what: String, used: u64, quota: u64| {
+            format!(
+                "disk quota exceeded for {}: {} written in the last {} days, quota {} (policy disk_quotas)",
+                what,
+                human(used),
+                cfg.window_days,
+                human(quota)
+            )
+        };
+        if let Some(user_id) = user_id {
+            if let Some(quota) = cfg.user_quota(user_id) {
+                let used = self.user_bytes(user_id);
+                if used + bytes > quota {
+                    return Some(refuse(format!("user {}", user_id), used, quota));
+                }
+            }
+        }
+        if let Some(quota) = cfg.family_quota(family) {
+            let used = self.family_bytes(family);
+            if used + bytes > quota {
+                return Some(refuse(format!("goal family {}", family), used, quota));
+            }
+        }
+        None
+    }
+}
+
+static LEDGER: Lazy<Mutex<Ledger>> = Lazy::new(|| Mutex::new(Ledger::default()));
+
+fn ledger() -> std::sync::MutexGuard<'static, Ledger> {
+    let mut l = LEDGER.lock().unwrap_or_else(|e| e.into_inner());
+    l.refresh(CONFIG.window_days);
+    l
+}
+
+/// `Err` with a policy message when `user_id` or the goal's family has no quota left.
+pub fn check(user_id: Option<&str>, goal_id: &str) -> Result<(), String> {
+    match ledger().over(&CONFIG, user_id, &family(goal_id), 1) {
+        Some(msg) => Err(msg),
+        None => Ok(()),
+    }
+}
+
+/// Charges one run's writes; see [`finish`].
+pub struct RunMeter {
+    run_id: String,
+    key: Key,
+    refused: Mutex<Option<String>>,
+}
+
+impl RunMeter {
+    pub fn new(run_id: &str, user_id: Option<&str>, goal_id: &str) -> Arc<Self> {
+        Arc::new(Self {
+            run_id: run_id.to_string(),
+            key: (user_id.map(String::from), family(goal_id)),
+            refused: Mutex::new(None),
+        })
+    }
+
+    /// The first refused write's reason, if any was refused.
+    pub fn refused(&self) -> Option<String> {
+        self.refused
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl atomic::Meter for RunMeter {
+    fn charge(&self, path: &Path, bytes: u64) -> io::Result<()> {
+        let mut l = ledger();
+        if let Some(msg) = l.over(&CONFIG, self.key.0.as_deref(), &self.key.1, bytes) {
+            let mut refused = self.refused.lock().unwrap_or_else(|e| e.into_inner());
+            refused.get_or_insert_with(|| msg.clone());
+            return Err(io::Error::other(format!(
+                "{} ({} not written)",
+                msg,
+                path.display()
+            )));
+        }
+        l.inflight
+            .entry(self.run_id.clone())
+            .or_insert_with(|| (self.key.clone(), 0))
+            .1 += bytes;
+        Ok(())
+    }
+}
+
+/// Move the run's bytes from in flight to the ledger. Call after the metered scope ended.
+pub fn finish(meter: &RunMeter) -> u64 {
+    let mut l = ledger();
+    let Some((key, bytes)) = l.inflight.remove(&meter.run_id) else {
+        return 0;
+    };
+    *l.done.entry(key.clone()).or_insert(0) += bytes;
+    drop(l);
+    let line = json!({
+        "ts": Utc::now().to_rfc3339(),
+        "run_id": meter.run_id,
+        "user_id": key.0,
+        "family": key.1,
+        "bytes": bytes,
+    });
+    let path = ledger_path();
+    let res = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| atomic::append(&path, &line.to_string()));
+    if let Err(e) = res {
+        tracing::warn!("disk usage ledger {}: {}", path.display(), e);
+    }
+    bytes
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct UsageRow {
+    /// User id or goal family.
+    pub name: String,
+    pub bytes: u64,
+    pub quota_bytes: Option<u64>,
+}
+
+/// Disk usage by user and goal family over the quota window (dashboard).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct DiskUsage {
+    pub window_days: u32,
+    /// Largest first.
+    pub users: Vec<UsageRow>,
+    pub goal_families: Vec<UsageRow>,
+}
+
+/// `GET /users/{user_id}/usage`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct UserUsage {
+    pub user_id: String,
+    pub window_days: u32,
+    pub disk_bytes: u64,
+    pub disk_quota_bytes: Option<u64>,
+    /// This user's bytes per goal family.
+    pub by_goal_family: BTreeMap<String, u64>,
+}
+
+pub fn user_usage(user_id: &str) -> UserUsage {
+    let l = ledger();
+    let mut by_goal_family = BTreeMap::new();
+    for (k, b) in l.entries().filter(|(k, _)| k.0.as_deref() == Some(user_id)) {
+        *by_goal_family.entry(k.1.clone()).or_insert(0) += b;
+    }
+    UserUsage {
+        user_id: user_id.to_string(),
+        window_days: CONFIG.window_days,
+        disk_bytes: by_goal_family.values().sum(),
+        disk_quota_bytes: CONFIG.user_quota(user_id),
+        by_goal_family,
+    }
+}
+
+pub fn summary() -> DiskUsage {
+    let l = ledger();
+    let mut users: HashMap<&str, u64> = HashMap::new();
+    let mut families: HashMap<&str, u64> = HashMap::new();
+    for ((user, fam), b) in l.entries() {
+        if let Some(u) = user {
+            *users.entry(u).or_insert(0) += b;
+        }
+        *families.entry(fam).or_insert(0) += b;
+    }
+    fn rows(m: HashMap<&str, u64>, quota: impl Fn(&str) -> Option<u64>) -> Vec<UsageRow> {
+        let mut rows: Vec<UsageRow> = m
+            .into_iter()
+            .map(|(name, bytes)| UsageRow {
+                name: name.to_string(),
+                bytes,
+                quota_bytes: quota(name),
+            })
+            .collect();
+        rows.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+        rows
+    }
+    DiskUsage {
+        window_days: CONFIG.window_days,
+        users: rows(users, |u| CONFIG.user_quota(u)),
+        goal_families: rows(families, |f| CONFIG.family_quota(f)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_parse_with_units() {
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("512MB"), Some(512_000_000));
+        assert_eq!(parse_size("2 GiB"), Some(2 << 30));
+        assert_eq!(parse_size("1.5kb"), Some(1500));
+        assert_eq!(parse_size("12 parsecs"), None);
+    }
+
+    #[test]
+    fn quotas_apply_per_user_and_per_family() {
+        let cfg: QuotaSection =
+            serde_yaml::from_str("users: {default: 100, demo: 10}\ngoal_families: {wiki: 50}")
+                .unwrap();
+        let mut l = Ledger::default();
+        l.done.insert((Some("demo".into()), "wiki".into()), 8);
+        l.inflight
+            .insert("r-1".into(), ((Some("other".into()), "wiki".into()), 40));
+        // demo: 8 of 10 used.
+        assert!(l.over(&cfg, Some("demo"), "research", 2).is_none());
+        let msg = l.over(&cfg, Some("demo"), "research", 3).unwrap();
+        assert!(msg.contains("user demo"), "{}", msg);
+        // wiki: 48 of 50 across both users; `other` falls back to the default user quota.
+        let msg = l.over(&cfg, Some("other"), "wiki", 3).unwrap();
+        assert!(msg.contains("goal family wiki"), "{}", msg);
+        assert!(l.over(&cfg, Some("other"), "research", 60).is_none());
+        assert!(l.over(&cfg, None, "research", 1_000).is_none());
+    }
+}
@@ -0,0 +1,325 @@
+//! A/B policy experiments.
+//!
+//! An `experiments:` entry in policies.yaml sends a fraction of a goal family's runs through
+//! an alternative policy (and optionally kernel τ). Assignment is a hash of experiment id and
+//! run id, so a retried run lands in the same arm. Each assignment is appended to
+//! `runs/experiments/ab/<id>/assignments.jsonl`; receipts carry `evidence.experiment`, and
+//! `GET /experiments/{id}/report` joins the two through the run index.
+//!
+//! ```yaml
+//! experiments:
+//!   - id: gamma-low
+//!     goal: "wiki.*"
+//!     fraction: 0.2
+//!     policy: { gamma_gate: 0.3 }
+//!     kernel: { confidence_gate_tau: 0.6 }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use utoipa::ToSchema;
+
+use crate::engine::policy::{glob_match, PolicyOverride};
+use crate::engine::types::Policy;
+
+use super::run_index;
+
+pub const CONTROL: &str = "control";
+pub const TREATMENT: &str = "treatment";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KernelOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence_gate_tau: Option<f32>,
+}
+
+fn default_fraction() -> f64 {
+    0.5
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExperimentDef {
+    pub id: String,
+    /// Exact goal id or `*` glob, as in `goal_policies`.
+    pub goal: String,
+    /// Share of matching runs sent to the treatment arm.
+    #[serde(default = "default_fraction")]
+    pub fraction: f64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub policy: PolicyOverride,
+    #[serde(default)]
+    pub kernel: KernelOverride,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesExperiments {
+    #[serde(default)]
+    experiments: Vec<ExperimentDef>,
+}
+
+/// The arm one run was placed in.
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub experiment: ExperimentDef,
+    pub arm: &'static str,
+}
+
+impl Assignment {
+    pub fn is_treatment(&self) -> bool {
+        self.arm == TREATMENT
+    }
+
+    /// Effective policy for this arm.
+    pub fn policy(&self, base: &Policy) -> Policy {
+        let mut p = base.clone();
+        if self.is_treatment() {
+            self.experiment.policy.apply(&mut p);
+        }
+        p
+    }
+
+    /// Kernel overrides to pass to the engine (treatment only).
+    pub fn kernel(&self) -> Option<Value> {
+        (self.is_treatment() && self.experiment.kernel.confidence_gate_tau.is_some())
+            .then(|| json!(self.experiment.kernel))
+    }
+
+    /// Tag recorded in `evidence.experiment`.
+    pub fn tag(&self) -> Value {
+        json!({ "id": self.experiment.id, "arm": self.arm })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExperimentArm {
+    pub arm: String,
+    /// Policy overrides applied in this arm (empty for control).
+    #[schema(value_type = Object)]
+    pub overrides: Value,
+    pub runs: usize,
+    pub finished: usize,
+    pub succeeded: usize,
+    pub success_rate: Option<f32>,
+    pub mean_trust: Option<f32>,
+    pub mean_latency_ms: Option<u64>,
+    /// Sum of `evidence.cost` where goals report it.
+    pub total_cost: f64,
+}
+
+/// Treatment minus control.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ArmDelta {
+    pub success_rate: Option<f32>,
+    pub mean_trust: Option<f32>,
+    pub mean_latency_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ExperimentReport {
+    pub id: String,
+    pub goal: String,
+    pub fraction: f64,
+    pub enabled: bool,
+    pub arms: Vec<ExperimentArm>,
+    pub delta: ArmDelta,
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn assignments_path(id: &str) -> PathBuf {
+    meta3_root()
+        .join("runs/experiments/ab")
+        .join(id)
+        .join("assignments.jsonl")
+}
+
+pub fn load() -> Vec<ExperimentDef> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesExperiments>(&raw) {
+            Ok(p) => p
+                .experiments
+                .into_iter()
+                .filter(|e| is_safe_segment(&e.id))
+                .collect(),
+            Err(e) => {
+                tracing::warn!("invalid {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Uniform in [0, 1) from the experiment id and run id.
+fn bucket(id: &str, run_id: &str) -> f64 {
+    let digest = Sha256::digest(format!("{}:{}", id, run_id).as_bytes());
+    let mut b = [0u8; 8];
+    b.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(b) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Place `run_id` in the first enabled experiment matching `goal_id`, recording the
+/// assignment. `None` when no experiment applies.
+pub async fn assign(goal_id: &str, run_id: &str) -> Option<Assignment> {
+    let experiment = load()
+        .into_iter()
+        .find(|e| e.enabled && glob_match(e.goal.trim(), goal_id))?;
+    let arm = if bucket(&experiment.id, run_id) < experiment.fraction.clamp(0.0, 1.0) {
+        TREATMENT
+    } else {
+        CONTROL
+    };
+    let path = assignments_path(&experiment.id);
+    if let Some(dir) = path.parent() {
+        let _ = tokio::fs::create_dir_all(dir).await;
+    }
+    let line = json!({
+        "ts": chrono::Utc::now().to_rfc3339(),
+        "run_id": run_id,
+        "goal_id": goal_id,
+        "arm": arm
+    });
+    let written = async {
+        let mut f = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        f.write_all(format!("{}\n", line).as_bytes()).await
+    }
+    .await;
+    if let Err(e) = written {
+        tracing::warn!("experiment assignment to {} failed: {}", path.display(), e);
+    }
+    Some(Assignment { experiment, arm })
+}
+
+/// `(run_id, arm)` pairs, last assignment per run winning.
+async fn read_assignments(id: &str) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    let Ok(f) = tokio::fs::File::open(assignments_path(id)).await else {
+        return out;
+    };
+    let mut lines = tokio::io::BufReader::new(f).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(v) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let (Some(run_id), Some(arm)) = (
+            v.get("run_id").and_then(|x| x.as_str()),
+            v.get("arm").and_then(|x| x.as_str()),
+        ) else {
+            continue;
+        };
+        out.retain(|(r, _)| r != run_id);
+        out.push((run_id.to_string(), arm.to_string()));
+    }
+    out
+}
+
+fn mean(xs: &[f32]) -> Option<f32> {
+    (!xs.is_empty()).then(|| xs.iter().sum::<f32>() / xs.len() as f32)
+}
+
+async fn arm_stats(arm: &str, overrides: Value, run_ids: &[String]) -> ExperimentArm {
+    let (mut finished, mut succeeded, mut total_cost) = (0usize, 0usize, 0.0f64);
+    let (mut trusts, mut latencies): (Vec<f32>, Vec<u64>) = (Vec::new(), Vec::new());
+    for run_id in run_ids {
+        let Some(d) = run_index::get(run_id).await else {
+            continue;
+        };
+        let Some(ok) = d.record.success else {
+            continue;
+        };
+        finished += 1;
+        if ok {
+            succeeded += 1;
+        }
+        if let Some(t) = d
+            .record
+            .bits
+            .as_ref()
+            .and_then(|b| b.get("t"))
+            .and_then(|x| x.as_f64())
+        {
+            trusts.push(t as f32);
+        }
+        if let Some(ms) = d.record.latency_ms {
+            latencies.push(ms);
+        }
+        total_cost += d
+            .response
+            .pointer("/manifest/evidence/cost")
+            .and_then(|x| x.as_f64())
+            .unwrap_or(0.0);
+    }
+    ExperimentArm {
+        arm: arm.to_string(),
+        overrides,
+        runs: run_ids.len(),
+        finished,
+        succeeded,
+        success_rate: (finished > 0).then(|| succeeded as f32 / finished as f32),
+        mean_trust: mean(&trusts),
+        mean_latency_ms: (!latencies.is_empty())
+            .then(|| latencies.iter().sum::<u64>() / latencies.len() as u64),
+        total_cost,
+    }
+}
+
+/// Arm-level comparison for a configured experiment; `None` when `id` isn't configured.
+pub async fn report(id: &str) -> Option<ExperimentReport> {
+    let def = load().into_iter().find(|e| e.id == id)?;
+    let assignments = read_assignments(id).await;
+    let runs_in = |arm: &str| -> Vec<String> {
+        assignments
+            .iter()
+            .filter(|(_, a)| a == arm)
+            .map(|(r, _)| r.clone())
+            .collect()
+    };
+    let treatment_overrides = json!({ "policy": def.policy, "kernel": def.kernel });
+    let control = arm_stats(CONTROL, json!({}), &runs_in(CONTROL)).await;
+    let treatment = arm_stats(TREATMENT, treatment_overrides, &runs_in(TREATMENT)).await;
+    let diff = |a: Option<f32>, b: Option<f32>| Some(a? - b?);
+    let delta = ArmDelta {
+        success_rate: diff(treatment.success_rate, control.success_rate),
+        mean_trust: diff(treatment.mean_trust, control.mean_trust),
+        mean_latency_ms: match (treatment.mean_latency_ms, control.mean_latency_ms) {
+            (Some(t), Some(c)) => Some(t as i64 - c as i64),
+            _ => None,
+        },
+    };
+    Some(ExperimentReport {
+        id: def.id,
+        goal: def.goal,
+        fraction: def.fraction,
+        enabled: def.enabled,
+        arms: vec![control, treatment],
+        delta,
+    })
+}
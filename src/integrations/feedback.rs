@@ -0,0 +1,307 @@
+//! Reply feedback: a thumbs up or down (and an optional comment) on a run, from the user whose
+//! thread it belongs to.
+//!
+//! `POST /users/{user_id}/threads/{thread}/feedback` stores the rating next to the receipt, in
+//! `runs/receipts/<run_id>/feedback.json`; rating the same run again replaces it. The run index
+//! reads it back as [`RunRecord::feedback`], so the receipts scan that already feeds the
+//! dashboard and KPIs also yields:
+//! - per-goal satisfaction on `/dashboard` ([`by_goal`], over the dashboard window),
+//! - `satisfaction` in the weekly KPI snapshots, with a planning goal when it drops,
+//! - the flywheel ranking, which weights a goal's context by [`goal_satisfaction`].
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use one_engine::storage;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+use super::run_index::{self, RunRecord};
+
+/// Comments are cut to this many characters.
+pub const MAX_COMMENT_CHARS: usize = 2000;
+/// How long [`goal_satisfaction`] reuses its scan; new ratings are applied to it as they come.
+const CACHE_TTL: Duration = Duration::from_secs(300);
+/// Ratings older than this don't count towards [`goal_satisfaction`].
+const RANKING_WINDOW_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Feedback {
+    pub ts: String,
+    pub run_id: String,
+    pub user_id: String,
+    pub thread: String,
+    pub goal_id: String,
+    pub rating: Rating,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalSatisfaction {
+    /// Goal id without a `user:<id>.` prefix.
+    pub goal_id: String,
+    pub up: u32,
+    pub down: u32,
+    /// `up / (up + down)`.
+    pub satisfaction: f32,
+}
+
+fn receipts_dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs")
+        .join("receipts")
+}
+
+/// Who a receipt's run belongs to, from its `request.json`.
+#[derive(Debug, Clone, Default)]
+pub struct RunOwner {
+    pub goal_id: String,
+    pub user_id: Option<String>,
+    pub thread: Option<String>,
+}
+
+/// `None` when run `run_id` has no finished receipt.
+pub async fn owner(run_id: &str) -> Option<RunOwner> {
+    let dir = receipts_dir().join(run_id);
+    if !storage::exists(&dir.join("response.json")) {
+        return None;
+    }
+    let raw = storage::read_to_string_async(dir.join("request.json"))
+        .await
+        .unwrap_or_default();
+    let req: Value = serde_json::from_str(&raw).unwrap_or(Value::Null);
+    let ctx = |k: &str| {
+        req.pointer(&format!("/ctx/{}", k))
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+    };
+    Some(RunOwner {
+        goal_id: req
+            .get("goal_id")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        user_id: ctx("user_id"),
+        thread: ctx("thread"),
+    })
+}
+
+/// Write `feedback` next to its receipt, replacing an earlier rating of the run.
+pub async fn record(mut feedback: Feedback) -> Result<Feedback> {
+    let dir = receipts_dir().join(&feedback.run_id);
+    if !storage::exists(&dir) {
+        bail!("no receipt for run {}", feedback.run_id);
+    }
+    feedback.comment = feedback
+        .comment
+        .map(|c| c.trim().chars().take(MAX_COMMENT_CHARS).collect::<String>())
+        .filter(|c| !c.is_empty())
+        .map(|c| one_engine::redact::redact(&c));
+    let path = dir.join("feedback.json");
+    one_engine::atomic::write(&path, serde_json::to_string_pretty(&feedback)?)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    if let Some(cache) = CACHE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        cache.rate(&feedback.run_id, bare(&feedback.goal_id), feedback.rating);
+    }
+    super::telemetry::emit(
+        "feedback",
+        "rated",
+        Some(&feedback.run_id),
+        serde_json::json!({
+            "goal_id": feedback.goal_id,
+            "rating": feedback.rating,
+            "has_comment": feedback.comment.is_some(),
+        }),
+    );
+    Ok(feedback)
+}
+
+/// The rating stored in receipt dir `rdir`, if any.
+pub async fn read(rdir: &Path) -> Option<Rating> {
+    let raw = storage::read_to_string_async(rdir.join("feedback.json"))
+        .await
+        .ok()?;
+    serde_json::from_str::<Feedback>(&raw)
+        .ok()
+        .map(|f| f.rating)
+}
+
+fn bare(goal_id: &str) -> &str {
+    goal_id
+        .strip_prefix("user:")
+        .and_then(|rest| rest.split_once('.'))
+        .map_or(goal_id, |(_, g)| g)
+}
+
+/// Share of thumbs up among the rated `records`; `None` when none is rated.
+pub fn rate(records: &[&RunRecord]) -> Option<f32> {
+    let (up, down) = records
+        .iter()
+        .fold((0u32, 0u32), |(u, d), r| match r.feedback {
+            Some(Rating::Up) => (u + 1, d),
+            Some(Rating::Down) => (u, d + 1),
+            None => (u, d),
+        });
+    (up + down > 0).then(|| up as f32 / (up + down) as f32)
+}
+
+/// Satisfaction per goal over the rated `records`, most rated first.
+pub fn by_goal(records: &[RunRecord]) -> Vec<GoalSatisfaction> {
+    let mut goals: BTreeMap<&str, GoalSatisfaction> = BTreeMap::new();
+    for r in records {
+        let Some(rating) = r.feedback else {
+            continue;
+        };
+        let g = goals
+            .entry(bare(&r.goal_id))
+            .or_insert_with(|| GoalSatisfaction {
+                goal_id: bare(&r.goal_id).to_string(),
+                ..Default::default()
+            });
+        match rating {
+            Rating::Up => g.up += 1,
+            Rating::Down => g.down += 1,
+        }
+    }
+    let mut out: Vec<GoalSatisfaction> = goals
+        .into_values()
+        .map(|mut g| {
+            g.satisfaction = g.up as f32 / (g.up + g.down) as f32;
+            g
+        })
+        .collect();
+    out.sort_by_key(|g| std::cmp::Reverse(g.up + g.down));
+    out
+}
+
+/// The ratings of the last scan, kept current by [`record`].
+struct Ratings {
+    at: Instant,
+    /// Run id → (bare goal id, rating), so a run rated again replaces its earlier rating.
+    runs: HashMap<String, (String, Rating)>,
+    /// Bare goal id → (up, down).
+    goals: HashMap<String, (u32, u32)>,
+}
+
+impl Ratings {
+    fn rate(&mut self, run_id: &str, goal_id: &str, rating: Rating) {
+        let earlier = self
+            .runs
+            .insert(run_id.to_string(), (goal_id.to_string(), rating));
+        if let Some((goal, old)) = earlier {
+            if let Some((up, down)) = self.goals.get_mut(&goal) {
+                match old {
+                    Rating::Up => *up = up.saturating_sub(1),
+                    Rating::Down => *down = down.saturating_sub(1),
+                }
+            }
+        }
+        let (up, down) = self.goals.entry(goal_id.to_string()).or_default();
+        match rating {
+            Rating::Up => *up += 1,
+            Rating::Down => *down += 1,
+        }
+    }
+
+    fn satisfaction(&self, goal_id: &str) -> Option<f32> {
+        let (up, down) = *self.goals.get(goal_id)?;
+        (up + down > 0).then(|| up as f32 / (up + down) as f32)
+    }
+}
+
+static CACHE: Lazy<Mutex<Option<Ratings>>> = Lazy::new(|| Mutex::new(None));
+
+/// Satisfaction of `goal_id` over the last 30 days of ratings; `None` when it has none. The
+/// lock is only held to read or swap the cache, never across the rescan.
+pub async fn goal_satisfaction(goal_id: &str) -> Option<f32> {
+    {
+        let cache = CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(c) = cache.as_ref().filter(|c| c.at.elapsed() <= CACHE_TTL) {
+            return c.satisfaction(bare(goal_id));
+        }
+    }
+    let since = Utc::now() - chrono::Duration::days(RANKING_WINDOW_DAYS);
+    let records = run_index::scan(Some(since)).await;
+    let mut fresh = Ratings {
+        at: Instant::now(),
+        runs: HashMap::new(),
+        goals: HashMap::new(),
+    };
+    for r in &records {
+        if let Some(rating) = r.feedback {
+            fresh.rate(&r.run_id, bare(&r.goal_id), rating);
+        }
+    }
+    let satisfaction = fresh.satisfaction(bare(goal_id));
+    *CACHE.lock().unwrap_or_else(|e| e.into_inner()) = Some(fresh);
+    satisfaction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rec(goal_id: &str, feedback: Option<Rating>) -> RunRecord {
+        RunRecord {
+            run_id: "r".into(),
+            goal_id: goal_id.into(),
+            ts: Utc::now(),
+            success: Some(true),
+            latency_ms: None,
+            user_id: None,
+            meta2_proposal: None,
+            bits: None,
+            feedback,
+        }
+    }
+
+    #[test]
+    fn satisfaction_groups_user_goals_and_skips_unrated_runs() {
+        let records = vec![
+            rec("user:demo.meta.omni", Some(Rating::Up)),
+            rec("meta.omni", Some(Rating::Down)),
+            rec("meta.omni", Some(Rating::Up)),
+            rec("wiki.generate", Some(Rating::Down)),
+            rec("wiki.generate", None),
+        ];
+        let goals = by_goal(&records);
+        assert_eq!(goals.len(), 2);
+        assert_eq!(goals[0].goal_id, "meta.omni");
+        assert_eq!((goals[0].up, goals[0].down), (2, 1));
+        assert!((goals[0].satisfaction - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(goals[1].satisfaction, 0.0);
+
+        let all: Vec<&RunRecord> = records.iter().collect();
+        assert_eq!(rate(&all), Some(0.5));
+        assert_eq!(rate(&all[4..]), None);
+
+        let mut ratings = Ratings {
+            at: Instant::now(),
+            runs: HashMap::new(),
+            goals: HashMap::new(),
+        };
+        ratings.rate("r-1", "meta.omni", Rating::Down);
+        ratings.rate("r-2", "meta.omni", Rating::Up);
+        assert_eq!(ratings.satisfaction("meta.omni"), Some(0.5));
+        // Rating a run again replaces its earlier rating.
+        ratings.rate("r-1", "meta.omni", Rating::Up);
+        assert_eq!(ratings.satisfaction("meta.omni"), Some(1.0));
+        assert_eq!(ratings.satisfaction("wiki.generate"), None);
+    }
+}
@@ -11,12 +11,13 @@ pub struct SearchResult {
 }
 
 pub async fn search(query: &str) -> anyhow::Result<Vec<SearchResult>> {
-    // Simple mock search for now
+    // Simple mock search for now; context from goals users rate poorly ranks lower.
+    let satisfaction = super::feedback::goal_satisfaction(query).await;
     let results = vec![SearchResult {
         id: format!("search-{}", Uuid::new_v4()),
         content: format!("Context for: {}", query),
-        relevance: 0.85,
-        metadata: json!({"source": "flywheel"}),
+        relevance: 0.85 * satisfaction.map_or(1.0, |s| 0.5 + 0.5 * s),
+        metadata: json!({"source": "flywheel", "satisfaction": satisfaction}),
     }];
 
     Ok(results)
@@ -0,0 +1,297 @@
+//! Deep health (`/healthz?deep=1`): probes the pieces a run depends on and folds them into
+//! one level for load balancers.
+//!
+//! - `disk`: create + remove a probe file under `META3_ROOT/runs` (failure = `down`); with a
+//!   read-only root (see `one_engine::storage`) the storage mode instead: `degraded` while
+//!   writes go to the scratch dir or memory, `down` when they fail
+//! - `router`: `GET <router>/models` with a short timeout (unreachable = `degraded`;
+//!   no key configured = `ok` with `configured: false`)
+//! - `queue`: queued/running runs older than `ONE_ENGINE_HEALTH_STUCK_SECS` (default 3600)
+//! - `shell`: the shell commands run in and whether it is POSIX (Unix-only goals refuse to
+//!   run otherwise); `degraded` when its program is not on `PATH`
+//! - `errors`: failure rate of receipts in `ONE_ENGINE_HEALTH_ERROR_WINDOW` (default `1h`),
+//!   `degraded` at or above `ONE_ENGINE_HEALTH_ERROR_RATE` (default 0.5) with at least 5 runs
+//!
+//! Reports are cached for `ONE_ENGINE_HEALTH_CACHE_SECS` (default 5) so frequent probes
+//! don't rescan receipts.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+use super::run_index;
+use crate::engine::platform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Ok,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Component {
+    pub name: String,
+    pub status: Level,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    #[schema(value_type = Object)]
+    pub detail: Value,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct HealthReport {
+    /// Worst component level.
+    pub status: Level,
+    pub checked_at: String,
+    pub components: Vec<Component>,
+}
+
+/// Start time of a queued/running run, as the API tracks it.
+pub struct ActiveRunAge {
+    pub run_id: String,
+    pub status: String,
+    pub since: DateTime<Utc>,
+}
+
+static CACHE: Lazy<Mutex<Option<(Instant, HealthReport)>>> = Lazy::new(|| Mutex::new(None));
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn env_num<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<T>().ok())
+        .unwrap_or(default)
+}
+
+fn component(
+    name: &str,
+    status: Level,
+    started: Instant,
+    message: Option<String>,
+    detail: Value,
+) -> Component {
+    Component {
+        name: name.to_string(),
+        status,
+        latency_ms: started.elapsed().as_millis() as u64,
+        message,
+        detail,
+    }
+}
+
+async fn check_disk() -> Component {
+    let t0 = Instant::now();
+    let storage = one_engine::storage::status();
+    if storage.mode != one_engine::storage::Mode::Writable {
+        let level = match storage.mode {
+            one_engine::storage::Mode::ReadOnly => Level::Down,
+            _ => Level::Degraded,
+        };
+        return component(
+            "disk",
+            level,
+            t0,
+            Some(format!(
+                "storage mode {}: {}",
+                storage.mode.as_str(),
+                storage.reason.as_deref().unwrap_or_default()
+            )),
+            json!({ "storage": storage }),
+        );
+    }
+    let dir = meta3_root().join("runs");
+    let probe = dir.join(format!(".healthz-{}", uuid::Uuid::new_v4()));
+    let res = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&probe, b"ok").await?;
+        tokio::fs::remove_file(&probe).await
+    }
+    .await;
+    match res {
+        Ok(()) => component(
+            "disk",
+            Level::Ok,
+            t0,
+            None,
+            json!({ "dir": dir.display().to_string(), "storage": storage }),
+        ),
+        Err(e) => component(
+            "disk",
+            Level::Down,
+            t0,
+            Some(format!("{} not writable: {}", dir.display(), e)),
+            Value::Null,
+        ),
+    }
+}
+
+async fn check_router() -> Component {
+    let t0 = Instant::now();
+    match crate::engine::router::probe(Duration::from_secs(3)).await {
+        None => component(
+            "router",
+            Level::Ok,
+            t0,
+            None,
+            json!({ "configured": false }),
+        ),
+        Some(Ok(code)) if code < 500 => component(
+            "router",
+            Level::Ok,
+            t0,
+            None,
+            json!({ "configured": true, "http_status": code }),
+        ),
+        Some(Ok(code)) => component(
+            "router",
+            Level::Degraded,
+            t0,
+            Some(format!("router returned {}", code)),
+            json!({ "configured": true, "http_status": code }),
+        ),
+        Some(Err(e)) => component(
+            "router",
+            Level::Degraded,
+            t0,
+            Some(format!("router unreachable: {}", e)),
+            json!({ "configured": true }),
+        ),
+    }
+}
+
+fn check_queue(active: &[ActiveRunAge]) -> Component {
+    let t0 = Instant::now();
+    let stuck_secs = env_num("ONE_ENGINE_HEALTH_STUCK_SECS", 3600i64);
+    let now = Utc::now();
+    let stuck: Vec<&str> = active
+        .iter()
+        .filter(|r| (now - r.since).num_seconds() > stuck_secs)
+        .map(|r| r.run_id.as_str())
+        .collect();
+    let count = |s: &str| active.iter().filter(|r| r.status == s).count();
+    let detail = json!({
+        "queued": count("queued"),
+        "running": count("running"),
+        "stuck": stuck,
+        "stuck_after_secs": stuck_secs
+    });
+    if stuck.is_empty() {
+        component("queue", Level::Ok, t0, None, detail)
+    } else {
+        component(
+            "queue",
+            Level::Degraded,
+            t0,
+            Some(format!(
+                "{} run(s) active for over {}s",
+                stuck.len(),
+                stuck_secs
+            )),
+            detail,
+        )
+    }
+}
+
+fn check_shell() -> Component {
+    let t0 = Instant::now();
+    let sh = platform::shell();
+    let installed = platform::is_installed(sh);
+    let detail = json!({
+        "shell": sh.name(),
+        "posix": sh.is_posix(),
+        "os": std::env::consts::OS
+    });
+    if installed {
+        component("shell", Level::Ok, t0, None, detail)
+    } else {
+        component(
+            "shell",
+            Level::Degraded,
+            t0,
+            Some(format!(
+                "{} not found on PATH; goals that run commands will fail",
+                sh.name()
+            )),
+            detail,
+        )
+    }
+}
+
+async fn check_errors() -> Component {
+    let t0 = Instant::now();
+    let window =
+        std::env::var("ONE_ENGINE_HEALTH_ERROR_WINDOW").unwrap_or_else(|_| "1h".to_string());
+    let span = run_index::parse_window(&window).unwrap_or_else(|| chrono::Duration::hours(1));
+    let threshold = env_num("ONE_ENGINE_HEALTH_ERROR_RATE", 0.5f64);
+    let finished: Vec<bool> = run_index::scan(Some(Utc::now() - span))
+        .await
+        .into_iter()
+        .filter_map(|r| r.success)
+        .collect();
+    let failed = finished.iter().filter(|ok| !**ok).count();
+    let rate = if finished.is_empty() {
+        0.0
+    } else {
+        failed as f64 / finished.len() as f64
+    };
+    let detail = json!({
+        "window": window,
+        "runs": finished.len(),
+        "failed": failed,
+        "error_rate": rate,
+        "threshold": threshold
+    });
+    if finished.len() >= 5 && rate >= threshold {
+        component(
+            "errors",
+            Level::Degraded,
+            t0,
+            Some(format!(
+                "{:.0}% of runs failed in the last {}",
+                rate * 100.0,
+                window
+            )),
+            detail,
+        )
+    } else {
+        component("errors", Level::Ok, t0, None, detail)
+    }
+}
+
+/// Run all probes (or return the cached report).
+pub async fn deep(active: Vec<ActiveRunAge>) -> HealthReport {
+    let ttl = Duration::from_secs(env_num("ONE_ENGINE_HEALTH_CACHE_SECS", 5u64));
+    let mut cache = CACHE.lock().await;
+    if let Some((at, report)) = cache.as_ref() {
+        if at.elapsed() < ttl {
+            return report.clone();
+        }
+    }
+    let (disk, router, errors) = tokio::join!(check_disk(), check_router(), check_errors());
+    let components = vec![disk, router, check_queue(&active), check_shell(), errors];
+    let report = HealthReport {
+        status: components
+            .iter()
+            .map(|c| c.status)
+            .max()
+            .unwrap_or(Level::Ok),
+        checked_at: Utc::now().to_rfc3339(),
+        components,
+    };
+    *cache = Some((Instant::now(), report.clone()));
+    report
+}
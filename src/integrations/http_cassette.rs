@@ -0,0 +1,437 @@
+//! Record/replay cassettes for outbound HTTP (the LM router and notification webhooks). Any
+//! integration that talks HTTP goes through here; there is no GitHub client in the engine
+//! yet, and one would send through [`execute`] like the others.
+//!
+//! Integrations send through [`execute`] instead of `Client::execute`. Outside cassette mode
+//! that is a plain request. In cassette mode each exchange is matched against
+//! `<dir>/<cassette>.json` by method, URL and a hash of the body:
+//! - `replay`: the recorded response is returned and nothing is sent. A request with no
+//!   recording fails with [`Unrecorded`], naming the request and the cassette;
+//! - `record`: the request is sent and the exchange appended to the cassette;
+//! - `auto`: replay what is recorded, record the rest (a cassette fills up on its first run).
+//!
+//! The mode comes from [`scope`] (tests), then from a cassette [`install`]ed for the
+//! integration (simulation mode installs the router's), then from
+//! `ONE_ENGINE_HTTP_CASSETTES=off|record|replay|auto` with `ONE_ENGINE_HTTP_CASSETTE_DIR`
+//! (default `tests/cassettes`); without a scope the cassette is named after the integration
+//! (`router`, `notify`). Credentials are added by [`execute_authorized`] only when a request
+//! is really sent, so a replay needs no API key. Stored exchanges keep no
+//! request headers; bodies go through the redactor, URLs lose token-like path segments
+//! (webhook URLs carry their secret there) and secret-looking query values, and matching
+//! uses the redacted request so replays line up with what was stored.
+//!
+//! ```ignore
+//! let cassette = Cassette::replay("tests/cassettes", "chat_turn_tool_call");
+//! let reply = http_cassette::scope(cassette, router::chat_turn(&messages, &tools)).await?;
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use one_engine::redact;
+use reqwest::{Client, Request};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Off,
+    Record,
+    Replay,
+    Auto,
+}
+
+impl Mode {
+    fn parse(s: &str) -> Option<Mode> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "" | "off" => Some(Mode::Off),
+            "record" => Some(Mode::Record),
+            "replay" => Some(Mode::Replay),
+            "auto" => Some(Mode::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// One cassette file and how to use it. Clones share which recordings were already replayed,
+/// so repeated identical requests get the recorded responses in order.
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    pub mode: Mode,
+    pub path: PathBuf,
+    used: Arc<Mutex<HashSet<usize>>>,
+}
+
+impl Cassette {
+    pub fn new(mode: Mode, dir: impl AsRef<Path>, name: &str) -> Self {
+        Self {
+            mode,
+            path: dir.as_ref().join(format!("{}.json", name)),
+            used: Arc::default(),
+        }
+    }
+
+    pub fn replay(dir: impl AsRef<Path>, name: &str) -> Self {
+        Self::new(Mode::Replay, dir, name)
+    }
+
+    pub fn record(dir: impl AsRef<Path>, name: &str) -> Self {
+        Self::new(Mode::Record, dir, name)
+    }
+}
+
+/// The request had no recording in a replaying cassette.
+#[derive(Debug, Clone)]
+pub struct Unrecorded {
+    pub method: String,
+    pub url: String,
+    pub cassette: PathBuf,
+}
+
+impl std::fmt::Display for Unrecorded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unrecorded HTTP request {} {} (cassette {}); record it with ONE_ENGINE_HTTP_CASSETTES=record",
+            self.method,
+            self.url,
+            self.cassette.display()
+        )
+    }
+}
+
+impl std::error::Error for Unrecorded {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    body_sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+/// Status and body of a response, live or replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reply {
+    pub status: u16,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    pub body: String,
+}
+
+impl Reply {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn json(&self) -> Result<Value> {
+        serde_json::from_str(&self.body)
+            .with_context(|| format!("HTTP {} body is not JSON", self.status))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: Reply,
+    recorded_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    #[serde(default)]
+    interactions: Vec<Interaction>,
+}
+
+tokio::task_local! {
+    static CASSETTE: Cassette;
+}
+
+/// Run `fut` with its outbound HTTP going through `cassette`. Tests only; the server
+/// [`install`]s its cassettes.
+#[cfg(test)]
+pub async fn scope<F: std::future::Future>(cassette: Cassette, fut: F) -> F::Output {
+    CASSETTE.scope(cassette, fut).await
+}
+
+static INSTALLED: Lazy<Mutex<Vec<(String, Cassette)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static ENV_CASSETTES: Lazy<Mutex<Vec<(String, Cassette)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+/// Held while a cassette file is rewritten, so concurrent recordings don't drop each other.
+static APPEND: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// Send `integration`'s requests through `cassette` for the rest of the process, unless a
+/// [`scope`] says otherwise.
+pub fn install(integration: &str, cassette: Cassette) {
+    let mut all = INSTALLED.lock().unwrap_or_else(|e| e.into_inner());
+    all.retain(|(n, _)| n != integration);
+    all.push((integration.to_string(), cassette));
+}
+
+/// The scoped cassette, else the installed one, else the env-configured one named after
+/// `integration`.
+fn current(integration: &str) -> Option<Cassette> {
+    if let Ok(c) = CASSETTE.try_with(|c| c.clone()) {
+        return Some(c);
+    }
+    let installed = INSTALLED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, c)) = installed.iter().find(|(n, _)| n == integration) {
+        return Some(c.clone());
+    }
+    drop(installed);
+    let mode = std::env::var("ONE_ENGINE_HTTP_CASSETTES")
+        .ok()
+        .and_then(|m| Mode::parse(&m))
+        .unwrap_or(Mode::Off);
+    if mode == Mode::Off {
+        return None;
+    }
+    let dir = std::env::var("ONE_ENGINE_HTTP_CASSETTE_DIR")
+        .unwrap_or_else(|_| "tests/cassettes".to_string());
+    let mut all = ENV_CASSETTES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, c)) = all.iter().find(|(n, c)| n == integration && c.mode == mode) {
+        return Some(c.clone());
+    }
+    let c = Cassette::new(mode, dir, integration);
+    all.push((integration.to_string(), c.clone()));
+    Some(c)
+}
+
+/// A path segment or query value that is probably a credential.
+fn looks_like_token(s: &str) -> bool {
+    s.len() >= 16
+        && s.chars().any(|c| c.is_ascii_digit())
+        && s.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+fn redact_url(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    let path: Vec<String> = url
+        .path_segments()
+        .map(|segs| {
+            segs.map(|s| if looks_like_token(s) { "[REDACTED]" } else { s }.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    if !path.is_empty() {
+        url.set_path(&path.join("/"));
+    }
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| {
+            let secret = ["key", "token", "secret", "sig", "password"]
+                .iter()
+                .any(|n| k.to_ascii_lowercase().contains(n));
+            let v = if secret || looks_like_token(&v) {
+                "[REDACTED]".to_string()
+            } else {
+                v.into_owned()
+            };
+            (k.into_owned(), v)
+        })
+        .collect();
+    if !query.is_empty() {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+    url.to_string()
+}
+
+fn recorded_request(req: &Request) -> RecordedRequest {
+    let body = req
+        .body()
+        .and_then(|b| b.as_bytes())
+        .map(|b| redact::redact(&String::from_utf8_lossy(b)));
+    let body_sha256 = format!(
+        "{:x}",
+        Sha256::digest(body.as_deref().unwrap_or_default().as_bytes())
+    );
+    RecordedRequest {
+        method: req.method().to_string(),
+        url: redact_url(req.url()),
+        body_sha256,
+        body,
+    }
+}
+
+fn load(path: &Path) -> CassetteFile {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Exchanges recorded in `cassette`.
+pub fn recordings(cassette: &Cassette) -> usize {
+    load(&cassette.path).interactions.len()
+}
+
+/// The first recording of `req` in `cassette` that hasn't been replayed yet.
+fn find(cassette: &Cassette, req: &RecordedRequest) -> Option<Reply> {
+    let file = load(&cassette.path);
+    let mut used = cassette.used.lock().unwrap_or_else(|e| e.into_inner());
+    let (i, hit) = file.interactions.iter().enumerate().find(|(i, x)| {
+        !used.contains(i)
+            && x.request.method == req.method
+            && x.request.url == req.url
+            && x.request.body_sha256 == req.body_sha256
+    })?;
+    used.insert(i);
+    Some(hit.response.clone())
+}
+
+fn append(cassette: &Cassette, request: RecordedRequest, reply: &Reply) -> Result<()> {
+    let _guard = APPEND.lock().unwrap_or_else(|e| e.into_inner());
+    let mut file = load(&cassette.path);
+    file.interactions.push(Interaction {
+        request,
+        response: Reply {
+            body: redact::redact(&reply.body),
+            ..reply.clone()
+        },
+        recorded_at: chrono::Utc::now().to_rfc3339(),
+    });
+    // The new recording was just used by this request.
+    cassette
+        .used
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(file.interactions.len() - 1);
+    if let Some(dir) = cassette.path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    one_engine::atomic::write(&cassette.path, serde_json::to_string_pretty(&file)?)?;
+    Ok(())
+}
+
+async fn send(client: &Client, req: Request) -> Result<Reply> {
+    let resp = client.execute(req).await?;
+    let status = resp.status().as_u16();
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string());
+    let body = resp.text().await?;
+    Ok(Reply {
+        status,
+        content_type,
+        body,
+    })
+}
+
+/// Send `req` for `integration`, through the current cassette if there is one.
+pub async fn execute(integration: &str, client: &Client, req: Request) -> Result<Reply> {
+    execute_authorized(integration, client, req, Ok).await
+}
+
+/// [`execute`], with `authorize` adding credentials to `req` only if it is really sent: a
+/// replayed request never asks for them.
+pub async fn execute_authorized(
+    integration: &str,
+    client: &Client,
+    req: Request,
+    authorize: impl FnOnce(Request) -> Result<Request>,
+) -> Result<Reply> {
+    let Some(cassette) = current(integration) else {
+        return send(client, authorize(req)?).await;
+    };
+    let recorded = recorded_request(&req);
+    if matches!(cassette.mode, Mode::Replay | Mode::Auto) {
+        if let Some(reply) = find(&cassette, &recorded) {
+            return Ok(reply);
+        }
+        if cassette.mode == Mode::Replay {
+            return Err(anyhow!(Unrecorded {
+                method: recorded.method,
+                url: recorded.url,
+                cassette: cassette.path.clone(),
+            }));
+        }
+    }
+    let reply = send(client, authorize(req)?).await?;
+    if let Err(e) = append(&cassette, recorded, &reply) {
+        tracing::warn!("cassette {} not written: {:#}", cassette.path.display(), e);
+    }
+    Ok(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replays_in_order_redacts_and_rejects_unrecorded_requests() {
+        let dir = std::env::temp_dir().join(format!("cassettes-{}", uuid::Uuid::new_v4()));
+        let client = Client::new();
+        let post = |body: &str| {
+            client
+                .post("https://router.invalid/v1/chat/completions")
+                .bearer_auth("sk-live-not-stored")
+                .body(body.to_string())
+                .build()
+                .unwrap()
+        };
+
+        // Two recordings of the same request, as `record` would leave them.
+        let recorder = Cassette::record(&dir, "chat");
+        for n in 1..=2 {
+            let reply = Reply {
+                status: 200,
+                content_type: Some("application/json".into()),
+                body: format!(r#"{{"n":{}}}"#, n),
+            };
+            append(&recorder, recorded_request(&post(r#"{"q":"hi"}"#)), &reply).unwrap();
+        }
+        let stored = std::fs::read_to_string(dir.join("chat.json")).unwrap();
+        assert!(!stored.contains("sk-live-not-stored"));
+        let hook = reqwest::Url::parse(
+            "https://hooks.example.com/services/T0AB12CD3/B0XY98ZW7/a1B2c3D4e5F6g7H8i9J0?sig=s1&v=2",
+        )
+        .unwrap();
+        assert_eq!(
+            redact_url(&hook),
+            "https://hooks.example.com/services/T0AB12CD3/B0XY98ZW7/[REDACTED]?sig=%5BREDACTED%5D&v=2"
+        );
+
+        let cassette = Cassette::replay(&dir, "chat");
+        let replies = scope(cassette, async {
+            // A replay never asks for credentials.
+            let no_key = |_: Request| Err(anyhow!("router API key not set"));
+            let a = execute_authorized("router", &client, post(r#"{"q":"hi"}"#), no_key)
+                .await
+                .unwrap();
+            let b = execute("router", &client, post(r#"{"q":"hi"}"#))
+                .await
+                .unwrap();
+            let c = execute("router", &client, post(r#"{"q":"other"}"#)).await;
+            (a, b, c)
+        })
+        .await;
+        assert_eq!(replies.0.json().unwrap()["n"], 1);
+        assert_eq!(replies.1.json().unwrap()["n"], 2);
+        let err = replies.2.unwrap_err();
+        assert!(err.downcast_ref::<Unrecorded>().is_some());
+        assert!(err.to_string().contains("POST https://router.invalid"));
+
+        // Concurrent recordings all land in the file.
+        std::thread::scope(|s| {
+            for n in 0..8 {
+                let recorder = &recorder;
+                let req = recorded_request(&post(&format!(r#"{{"q":{}}}"#, n)));
+                s.spawn(move || {
+                    let reply = Reply {
+                        status: 200,
+                        content_type: None,
+                        body: "{}".into(),
+                    };
+                    append(recorder, req, &reply).unwrap();
+                });
+            }
+        });
+        assert_eq!(recordings(&recorder), 10);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,40 +1,251 @@
+use super::run_index::{self, RunRecord};
+use super::telemetry::{self, TelemetryFilter};
 use super::{AgentGoal, KPIDashboard, TelemetryEvent};
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+// KPI definitions (all scores in 0..=1, higher is better), computed per 7-day window
+// from the receipts run index and the telemetry sink (`runs/telemetry/`):
+// - signal_density:  succeeded runs / all runs
+// - flow_minutes:    minutes of successful run time / FLOW_TARGET_MINUTES
+// - knowledge_yield: successful research/wiki/graph/report runs / KNOWLEDGE_TARGET_RUNS
+// - noise_ratio:     1 - (noisy runs / finished runs)  (1.0 = no noise); a run is noisy when
+//                    it failed or telemetry recorded a NOISE_EVENTS event for it
+// - satisfaction:    thumbs up / rated runs (reply feedback); absent in weeks without ratings
+const FLOW_TARGET_MINUTES: f32 = 120.0;
+const KNOWLEDGE_TARGET_RUNS: f32 = 20.0;
+const TREND_WEEKS: i64 = 5;
+const KNOWLEDGE_GOALS: &[&str] = &["research.", "wiki.", "graphs.", "threads.report"];
+/// Telemetry event types that mark a run as noisy even when it ended up succeeding.
+const NOISE_EVENTS: &[&str] = &["gate_trip", "exec_failure"];
+/// Most recent telemetry events read per KPI computation.
+const TELEMETRY_SCAN_LIMIT: usize = 50_000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WeeklySnapshot {
+    pub week: String,
+    pub since: String,
+    pub until: String,
+    pub runs: u32,
+    pub signal_density: f32,
+    pub flow_minutes: f32,
+    pub knowledge_yield: f32,
+    pub noise_ratio: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub satisfaction: Option<f32>,
+    #[serde(default)]
+    pub rated: u32,
+}
+
+impl WeeklySnapshot {
+    fn composite(&self) -> f32 {
+        (self.signal_density + self.flow_minutes + self.knowledge_yield + self.noise_ratio) / 4.0
+    }
+
+    fn get(&self, kpi: &str) -> f32 {
+        match kpi {
+            "signal_density" => self.signal_density,
+            "flow_minutes" => self.flow_minutes,
+            "knowledge_yield" => self.knowledge_yield,
+            "noise_ratio" => self.noise_ratio,
+            // No ratings is no evidence of dissatisfaction.
+            "satisfaction" => self.satisfaction.unwrap_or(1.0),
+            _ => 0.0,
+        }
+    }
+}
+
+fn kpi_dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs")
+        .join("kpi")
+        .join("weekly")
+}
+
+/// `<ISO year>-W<ww>`: the ISO week-numbering year, so 2024-12-30 is `2025-W01`.
+fn iso_week_label(t: DateTime<Utc>) -> String {
+    use chrono::Datelike;
+    let w = t.iso_week();
+    format!("{}-W{:02}", w.year(), w.week())
+}
+
+/// `(ts, run_id)` of the telemetry noise events since `since`.
+async fn noise_events(since: DateTime<Utc>) -> Vec<(DateTime<Utc>, String)> {
+    let mut out = Vec::new();
+    for event_type in NOISE_EVENTS {
+        let filter = TelemetryFilter {
+            event_type: Some(event_type.to_string()),
+            since: Some(since),
+            limit: TELEMETRY_SCAN_LIMIT,
+            ..Default::default()
+        };
+        out.extend(telemetry::query(&filter).await.into_iter().filter_map(|e| {
+            let ts = DateTime::parse_from_rfc3339(&e.ts)
+                .ok()?
+                .with_timezone(&Utc);
+            Some((ts, e.run_id?))
+        }));
+    }
+    out
+}
+
+pub fn compute_window(
+    records: &[RunRecord],
+    noise: &[(DateTime<Utc>, String)],
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> WeeklySnapshot {
+    let in_window: Vec<&RunRecord> = records
+        .iter()
+        .filter(|r| r.ts >= since && r.ts < until)
+        .collect();
+    let runs = in_window.len() as u32;
+    let succeeded = in_window.iter().filter(|r| r.success == Some(true)).count() as f32;
+    let failed = in_window
+        .iter()
+        .filter(|r| r.success == Some(false))
+        .count() as f32;
+    let finished = succeeded + failed;
+    // Events can land slightly after the run's receipt timestamp; match on run id, not time.
+    let noisy_ids: HashSet<&str> = noise.iter().map(|(_, id)| id.as_str()).collect();
+    let noisy = in_window
+        .iter()
+        .filter(|r| match r.success {
+            Some(false) => true,
+            Some(true) => noisy_ids.contains(r.run_id.as_str()),
+            None => false,
+        })
+        .count() as f32;
+
+    let flow_ms: u64 = in_window
+        .iter()
+        .filter(|r| r.success == Some(true))
+        .filter_map(|r| r.latency_ms)
+        .sum();
+    let knowledge = in_window
+        .iter()
+        .filter(|r| r.success == Some(true))
+        .filter(|r| KNOWLEDGE_GOALS.iter().any(|g| r.goal_id.contains(g)))
+        .count() as f32;
+    let rated = in_window.iter().filter(|r| r.feedback.is_some()).count() as u32;
+
+    WeeklySnapshot {
+        week: iso_week_label(since),
+        since: since.to_rfc3339(),
+        until: until.to_rfc3339(),
+        runs,
+        signal_density: if runs == 0 {
+            0.0
+        } else {
+            succeeded / runs as f32
+        },
+        flow_minutes: ((flow_ms as f32 / 60_000.0) / FLOW_TARGET_MINUTES).min(1.0),
+        knowledge_yield: (knowledge / KNOWLEDGE_TARGET_RUNS).min(1.0),
+        noise_ratio: if finished == 0.0 {
+            1.0
+        } else {
+            1.0 - noisy / finished
+        },
+        satisfaction: super::feedback::rate(&in_window),
+        rated,
+    }
+}
+
+/// Rolling 7-day snapshots, oldest first (last = current week).
+async fn weekly_snapshots() -> Vec<WeeklySnapshot> {
+    let now = Utc::now();
+    let since = now - Duration::weeks(TREND_WEEKS);
+    let records = run_index::scan(Some(since)).await;
+    let noise = noise_events(since).await;
+    (0..TREND_WEEKS)
+        .rev()
+        .map(|i| {
+            let until = now - Duration::weeks(i);
+            compute_window(&records, &noise, until - Duration::weeks(1), until)
+        })
+        .collect()
+}
+
+/// Persist the current week's snapshot to `runs/kpi/weekly/<YYYY>-W<ww>.json` (overwrites).
+async fn persist_snapshot(s: &WeeklySnapshot) {
+    let dir = kpi_dir();
+    if tokio::fs::create_dir_all(&dir).await.is_err() {
+        return;
+    }
+    let label = iso_week_label(Utc::now());
+    let _ = tokio::fs::write(
+        dir.join(format!("{}.json", label)),
+        serde_json::to_string_pretty(&WeeklySnapshot {
+            week: label.clone(),
+            ..s.clone()
+        })
+        .unwrap_or_default(),
+    )
+    .await;
+}
 
 pub async fn current_scores() -> KPIDashboard {
-    // Simulate current KPI state
+    let snaps = weekly_snapshots().await;
+    let current = snaps.last().cloned().unwrap_or_default();
+    persist_snapshot(&current).await;
+
     KPIDashboard {
-        signal_density: 0.75,
-        flow_minutes: 0.60, // Lowest - needs focus
-        knowledge_yield: 0.85,
-        noise_ratio: 0.70,
-        weekly_trend: vec![0.65, 0.68, 0.72, 0.75, 0.73],
+        signal_density: current.signal_density,
+        flow_minutes: current.flow_minutes,
+        knowledge_yield: current.knowledge_yield,
+        noise_ratio: current.noise_ratio,
+        satisfaction: current.satisfaction,
+        weekly_trend: snaps.iter().map(|s| s.composite()).collect(),
     }
 }
 
 pub async fn weekly_planning() -> anyhow::Result<Vec<AgentGoal>> {
-    let kpis = current_scores().await;
-    let mut goals = Vec::new();
+    let snaps = weekly_snapshots().await;
+    let current = snaps.last().cloned().unwrap_or_default();
+    let previous = snaps
+        .len()
+        .checked_sub(2)
+        .and_then(|i| snaps.get(i))
+        .cloned()
+        .unwrap_or_default();
+    persist_snapshot(&current).await;
 
-    // Focus on lowest performing KPIs
-    if kpis.flow_minutes < 0.7 {
-        goals.push(AgentGoal {
-            id: "improve-flow-minutes".to_string(),
-            kpi_target: "flow_minutes".to_string(),
-            priority: 1.0 - kpis.flow_minutes, // Higher priority for lower scores
-            estimated_impact: 0.15,
-        });
-    }
+    // (kpi, goal id, threshold): propose work for KPIs under threshold.
+    let targets = [
+        ("flow_minutes", "improve-flow-minutes", 0.7),
+        ("noise_ratio", "reduce-noise-ratio", 0.8),
+        ("knowledge_yield", "raise-knowledge-yield", 0.6),
+        ("signal_density", "raise-signal-density", 0.7),
+        ("satisfaction", "raise-reply-satisfaction", 0.7),
+    ];
 
-    if kpis.noise_ratio < 0.8 {
+    let mut goals = Vec::new();
+    for (kpi, id, threshold) in targets {
+        let score = current.get(kpi);
+        if score >= threshold {
+            continue;
+        }
+        // Impact = remaining gap to threshold, plus the lost ground if the KPI regressed.
+        let delta = score - previous.get(kpi);
+        let gap = threshold - score;
+        let estimated_impact = (gap + (-delta).max(0.0)).clamp(0.0, 1.0);
         goals.push(AgentGoal {
-            id: "reduce-noise-ratio".to_string(),
-            kpi_target: "noise_ratio".to_string(),
-            priority: 1.0 - kpis.noise_ratio,
-            estimated_impact: 0.10,
+            id: id.to_string(),
+            kpi_target: kpi.to_string(),
+            priority: 1.0 - score, // Higher priority for lower scores
+            estimated_impact,
         });
     }
+    goals.sort_by(|a, b| {
+        b.priority
+            .partial_cmp(&a.priority)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
 
     emit_telemetry(
         "kpi",
@@ -42,7 +253,9 @@ pub async fn weekly_planning() -> anyhow::Result<Vec<AgentGoal>> {
         None,
         json!({
             "goals_generated": goals.len(),
-            "focus_areas": goals.iter().map(|g| &g.kpi_target).collect::<Vec<_>>()
+            "focus_areas": goals.iter().map(|g| &g.kpi_target).collect::<Vec<_>>(),
+            "week": current.week,
+            "runs": current.runs
         }),
     )
     .await;
@@ -94,4 +307,7 @@ async fn emit_telemetry(
     };
 
     tracing::debug!("Telemetry: {:?}", event);
+    if let Err(e) = super::telemetry::append_events(&[event]).await {
+        tracing::debug!("telemetry sink write failed: {}", e);
+    }
 }
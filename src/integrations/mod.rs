@@ -1,8 +1,29 @@
+pub mod api_trace;
+pub mod audit;
+pub mod calibration;
+pub mod codex;
+pub mod codex_index;
+pub mod disk_quota;
+pub mod experiments;
+pub mod feedback;
 pub mod flywheel;
+pub mod health;
+pub mod http_cassette;
 pub mod kpi;
 pub mod monorepo;
+pub mod notify;
+pub mod nudges;
+pub mod progress;
+pub mod receipt_acl;
+pub mod run_deps;
+pub mod run_index;
+pub mod run_queue;
+pub mod run_summary;
+pub mod schedule;
 pub mod telemetry;
 pub mod ui;
+pub mod user_policy;
+pub mod workspace_files;
 
 use crate::engine::types::{Bits, Manifest};
 use schemars::JsonSchema;
@@ -36,6 +57,52 @@ pub struct UIState {
     pub eval_scores: Vec<EvalResult>,
     pub cost_tracking: CostSummary,
     pub kpi_dashboard: KPIDashboard,
+    pub timeline: RunTimeline,
+    /// Bytes written per user and goal family against their disk quotas.
+    #[serde(default)]
+    pub disk_usage: disk_quota::DiskUsage,
+    /// Reply ratings per goal over the window, most rated first.
+    #[serde(default)]
+    pub satisfaction: Vec<feedback::GoalSatisfaction>,
+}
+
+/// Aggregated run activity over a `window=` (computed from the receipts run index).
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct RunTimeline {
+    pub window: String,
+    /// "hour" for windows up to 48h, otherwise "day".
+    pub bucket: String,
+    pub total_runs: u32,
+    pub success_rate: Option<f32>,
+    pub latency_p50_ms: Option<u64>,
+    pub latency_p95_ms: Option<u64>,
+    pub buckets: Vec<TimelineBucket>,
+    pub top_failing_goals: Vec<GoalFailures>,
+    pub meta2_proposals: Vec<Meta2ProposalRef>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct TimelineBucket {
+    pub start: String, // ISO 8601 timestamp
+    pub total: u32,
+    pub succeeded: u32,
+    pub success_rate: Option<f32>,
+    pub by_goal: std::collections::BTreeMap<String, u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct GoalFailures {
+    pub goal_id: String,
+    pub failures: u32,
+    pub total: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Meta2ProposalRef {
+    pub run_id: String,
+    pub goal_id: String,
+    pub ts: String,
+    pub proposal: serde_json::Value,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -67,5 +134,8 @@ pub struct KPIDashboard {
     pub flow_minutes: f32,
     pub knowledge_yield: f32,
     pub noise_ratio: f32,
+    /// Share of thumbs up among this week's rated replies.
+    #[serde(default)]
+    pub satisfaction: Option<f32>,
     pub weekly_trend: Vec<f32>,
 }
@@ -0,0 +1,90 @@
+//! Outbound notifications: a single webhook (Slack-compatible or plain JSON).
+//!
+//! Configured by the `notify:` section of policies.yaml or `ONE_ENGINE_NOTIFY_WEBHOOK`
+//! (+ `ONE_ENGINE_NOTIFY_FORMAT`); the env vars win so secrets can stay out of the file.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NotifyConfig {
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// "slack" (`{"text": ...}`, default) or "json" (the whole notification).
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesNotify {
+    #[serde(default)]
+    notify: Option<NotifyConfig>,
+}
+
+pub fn load_config() -> NotifyConfig {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let mut cfg = match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesNotify>(&raw) {
+            Ok(p) => p.notify.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("invalid {}: {}", path, e);
+                NotifyConfig::default()
+            }
+        },
+        Err(_) => NotifyConfig::default(),
+    };
+    if let Ok(url) = std::env::var("ONE_ENGINE_NOTIFY_WEBHOOK") {
+        cfg.webhook_url = Some(url);
+    }
+    if let Ok(format) = std::env::var("ONE_ENGINE_NOTIFY_FORMAT") {
+        cfg.format = Some(format);
+    }
+    cfg.webhook_url = cfg.webhook_url.filter(|u| !u.trim().is_empty());
+    cfg
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub subject: String,
+    /// Plain-text / markdown body.
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Deliver `n` to the configured webhook; returns the HTTP status on success.
+pub async fn send(n: &Notification) -> Result<u16> {
+    crate::engine::simulation::deny("notification delivery")?;
+    let cfg = load_config();
+    let url = cfg.webhook_url.ok_or_else(|| {
+        anyhow!(
+            "no notification webhook configured (notify.webhook_url / ONE_ENGINE_NOTIFY_WEBHOOK)"
+        )
+    })?;
+    let body = match cfg.format.as_deref().unwrap_or("slack") {
+        "json" => serde_json::to_value(n)?,
+        _ => {
+            let link = n
+                .url
+                .as_deref()
+                .map(|u| format!("\n<{}>", u))
+                .unwrap_or_default();
+            json!({ "text": format!("*{}*\n{}{}", n.subject, n.text, link) })
+        }
+    };
+    let client = reqwest::Client::new();
+    let req = client
+        .post(&url)
+        .json(&body)
+        .timeout(std::time::Duration::from_secs(15))
+        .build()?;
+    let reply = super::http_cassette::execute("notify", &client, req)
+        .await
+        .map_err(|e| anyhow!("notify webhook: {:#}", e))?;
+    if !reply.is_success() {
+        return Err(anyhow!("notify webhook returned HTTP {}", reply.status));
+    }
+    Ok(reply.status)
+}
@@ -0,0 +1,485 @@
+//! Nudges: actionable next steps derived from the receipts run index.
+//!
+//! Each tracked feature goal is checked against its runs: a failing latest run, no successful
+//! run at all, or a last success older than the configured age thresholds produces a nudge with
+//! a ready-to-post `run_payload`. Thresholds and features come from the `nudges:` section of
+//! `config/policies.yaml` (`ONE_ENGINE_POLICIES_FILE`); paths in payloads are relative to
+//! META3_ROOT. Dismissals are stored per user in `users/<user_id>/nudges_dismissed.json`.
+
+use super::run_index::{self, RunRecord};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use tokio::fs;
+use utoipa::ToSchema;
+
+use crate::engine::urls::url_for;
+
+const DISMISSALS_FILE: &str = "nudges_dismissed.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Nudge {
+    pub id: String,
+    pub title: String,
+    pub severity: String, // info | warn | error
+    pub action: String,
+    pub link: Option<String>,
+    pub command: Option<String>,
+    pub run_payload: Option<Value>,
+    /// Latest finished run of the goal; a newer run re-surfaces a dismissed nudge.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<String>,
+}
+
+/// One tracked goal (`nudges.features[]`). Thresholds fall back to the section defaults.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeatureRule {
+    pub goal: String,
+    #[serde(default)]
+    pub warn_after: Option<String>,
+    #[serde(default)]
+    pub error_after: Option<String>,
+    #[serde(default)]
+    pub inputs: Value,
+    #[serde(default)]
+    pub time_ms: Option<u64>,
+}
+
+impl FeatureRule {
+    fn new(goal: &str, warn_after: &str, inputs: Value, time_ms: u64) -> Self {
+        Self {
+            goal: goal.to_string(),
+            warn_after: Some(warn_after.to_string()),
+            error_after: None,
+            inputs,
+            time_ms: Some(time_ms),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NudgeConfig {
+    /// Age of the last success after which a feature is stale (window syntax: `36h`, `3d`, `2w`).
+    #[serde(default = "default_warn_after")]
+    pub warn_after: String,
+    #[serde(default = "default_error_after")]
+    pub error_after: String,
+    #[serde(default = "default_features")]
+    pub features: Vec<FeatureRule>,
+}
+
+fn default_warn_after() -> String {
+    "3d".to_string()
+}
+
+fn default_error_after() -> String {
+    "14d".to_string()
+}
+
+fn default_features() -> Vec<FeatureRule> {
+    vec![
+        FeatureRule::new(
+            "demo.ping",
+            "1d",
+            json!({"message": "staleness nudge ping"}),
+            8000,
+        ),
+        FeatureRule::new(
+            "research.read",
+            "3d",
+            json!({"path": "research/sources/history_miner_folder/memory/policy_ucb.json"}),
+            12000,
+        ),
+        FeatureRule::new(
+            "meta3.build",
+            "2d",
+            json!({"repo_path": "meta3-monorepo"}),
+            300000,
+        ),
+        FeatureRule::new("wiki.generate", "7d", json!({}), 300000),
+    ]
+}
+
+impl Default for NudgeConfig {
+    fn default() -> Self {
+        Self {
+            warn_after: default_warn_after(),
+            error_after: default_error_after(),
+            features: default_features(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PoliciesNudges {
+    #[serde(default)]
+    nudges: Option<NudgeConfig>,
+}
+
+pub fn load_config() -> NudgeConfig {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesNudges>(&raw) {
+            Ok(p) => p.nudges.unwrap_or_default(),
+            Err(e) => {
+                tracing::warn!("invalid {}: {}", path, e);
+                NudgeConfig::default()
+            }
+        },
+        Err(_) => NudgeConfig::default(),
+    }
+}
+
+/// Per-feature staleness as derived from the run index.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FeatureStaleness {
+    pub goal: String,
+    /// "fresh" | "stale" | "failing" | "never"
+    pub status: String,
+    pub runs: usize,
+    pub last_success: Option<String>,
+    pub last_run: Option<String>,
+    pub age_hours: Option<f64>,
+    pub warn_after: String,
+    pub error_after: String,
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn run_payload(rule: &FeatureRule) -> Value {
+    let inputs = if rule.inputs.is_object() {
+        rule.inputs.clone()
+    } else {
+        json!({})
+    };
+    json!({
+        "goal_id": rule.goal,
+        "inputs": inputs,
+        "policy": {"gamma_gate": 0.5, "time_ms": rule.time_ms.unwrap_or(120000), "max_risk": 0.3, "tiny_diff_loc": 120}
+    })
+}
+
+fn fmt_age(age: Duration) -> String {
+    if age.num_hours() < 48 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}d", age.num_days())
+    }
+}
+
+fn receipt_link(run_id: &str) -> String {
+    url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id))
+}
+
+/// Staleness of one feature plus its nudge (if any). `records` are newest first.
+fn assess(
+    rule: &FeatureRule,
+    cfg: &NudgeConfig,
+    records: &[RunRecord],
+    now: DateTime<Utc>,
+) -> (FeatureStaleness, Option<Nudge>) {
+    let warn_raw = rule
+        .warn_after
+        .clone()
+        .unwrap_or_else(|| cfg.warn_after.clone());
+    let error_raw = rule
+        .error_after
+        .clone()
+        .unwrap_or_else(|| cfg.error_after.clone());
+    let warn_after = run_index::parse_window(&warn_raw).unwrap_or_else(|| Duration::days(3));
+    let error_after = run_index::parse_window(&error_raw).unwrap_or_else(|| Duration::days(14));
+
+    let finished: Vec<&RunRecord> = records
+        .iter()
+        .filter(|r| r.goal_id.contains(&rule.goal) && r.success.is_some())
+        .collect();
+    let last_run = finished.first().copied();
+    let last_success = finished.iter().find(|r| r.success == Some(true)).copied();
+    let age = last_success.map(|r| now - r.ts);
+
+    let goal = rule.goal.as_str();
+    let (status, nudge) = match (last_run, last_success, age) {
+        (Some(run), _, _) if run.success == Some(false) => (
+            "failing",
+            Some((
+                "error",
+                format!("{goal} failing"),
+                format!(
+                    "Last run {} failed; inspect the receipt and re-run {goal}",
+                    run.run_id
+                ),
+                receipt_link(&run.run_id),
+            )),
+        ),
+        (_, None, _) => (
+            "never",
+            Some((
+                "warn",
+                format!("{goal} has never succeeded"),
+                format!("Run {goal} to produce a first green receipt"),
+                url_for("/browse"),
+            )),
+        ),
+        (_, Some(ok), Some(age)) if age > warn_after => (
+            "stale",
+            Some((
+                if age > error_after { "error" } else { "warn" },
+                format!("{goal} is stale ({} since last success)", fmt_age(age)),
+                format!("Re-run {goal}; last success was {}", ok.run_id),
+                receipt_link(&ok.run_id),
+            )),
+        ),
+        _ => ("fresh", None),
+    };
+
+    let staleness = FeatureStaleness {
+        goal: rule.goal.clone(),
+        status: status.to_string(),
+        runs: finished.len(),
+        last_success: last_success.map(|r| r.ts.to_rfc3339()),
+        last_run: last_run.map(|r| r.ts.to_rfc3339()),
+        age_hours: age.map(|a| a.num_minutes() as f64 / 60.0),
+        warn_after: warn_raw,
+        error_after: error_raw,
+    };
+    let nudge = nudge.map(|(severity, title, action, link)| Nudge {
+        id: format!("stale:{}", rule.goal),
+        title,
+        severity: severity.to_string(),
+        action,
+        link: Some(link),
+        command: None,
+        run_payload: Some(run_payload(rule)),
+        last_run: staleness.last_run.clone(),
+    });
+    (staleness, nudge)
+}
+
+fn evergreen_nudges() -> Vec<Nudge> {
+    let evergreen = |id: &str, title: &str, action: &str, link: &str, payload: Value| Nudge {
+        id: format!("evergreen:{id}"),
+        title: title.to_string(),
+        severity: "info".to_string(),
+        action: action.to_string(),
+        link: Some(url_for(link)),
+        command: None,
+        run_payload: Some(payload),
+        last_run: None,
+    };
+    vec![
+        evergreen(
+            "wiki_local",
+            "Generate a local DeepWiki snapshot",
+            "Generate wiki under /runs/wiki/<run_id>/index.html",
+            "/browse",
+            json!({
+                "goal_id": "wiki.generate",
+                "inputs": {},
+                "policy": {"gamma_gate": 0.5, "time_ms": 300000, "max_risk": 0.2, "tiny_diff_loc": 120}
+            }),
+        ),
+        evergreen(
+            "green_build",
+            "Produce a fresh green receipt (fast)",
+            "Run a real build of the engine repo and write a receipt",
+            "/browse",
+            json!({
+                "goal_id": "meta3.build",
+                "inputs": {"repo_path": ".", "build_cmd": "cargo build --profile release-fast --bin one-engine"},
+                "policy": {"gamma_gate": 0.5, "time_ms": 300000, "max_risk": 0.3, "tiny_diff_loc": 120}
+            }),
+        ),
+        evergreen(
+            "staleness_check",
+            "Refresh the staleness matrix",
+            "Probe health, version, demo.ping, research.read, meta3.build and progress.sse",
+            "/docs/staleness_matrix.html",
+            json!({
+                "goal_id": "staleness.check",
+                "inputs": {},
+                "policy": {"gamma_gate": 0.5, "time_ms": 300000, "max_risk": 0.2, "tiny_diff_loc": 120}
+            }),
+        ),
+        evergreen(
+            "threads_report",
+            "Summarize this chat thread (auto)",
+            "Generate an HTML report from recent chat turns + receipts",
+            "/terminal",
+            json!({
+                "goal_id": "threads.report",
+                "inputs": {"user_id": "demo", "thread": "auto", "max_events": 600, "content_chars": 240},
+                "policy": {"gamma_gate": 0.5, "time_ms": 120000, "max_risk": 0.2, "tiny_diff_loc": 120}
+            }),
+        ),
+        evergreen(
+            "graphs_thread",
+            "Generate a thread graph (auto)",
+            "Generate a recursive, bits-native graph from recent chat turns",
+            "/terminal",
+            json!({
+                "goal_id": "graphs.thread",
+                "inputs": {"user_id": "demo", "thread": "auto", "recursive": true, "depth": 2, "max_nodes": 400, "include_bits": true},
+                "policy": {"gamma_gate": 0.5, "time_ms": 120000, "max_risk": 0.2, "tiny_diff_loc": 120}
+            }),
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Dismissal {
+    pub dismissed_at: String,
+    /// Snooze end; `None` keeps the nudge hidden until the goal runs again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+}
+
+/// Nudge ids are `<kind>:<name>` (e.g. `stale:meta3.build`).
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && !id.contains("..")
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn dismissals_path(user_id: &str) -> PathBuf {
+    meta3_root()
+        .join("users")
+        .join(user_id)
+        .join(DISMISSALS_FILE)
+}
+
+pub async fn load_dismissals(user_id: &str) -> BTreeMap<String, Dismissal> {
+    if !is_safe_segment(user_id) {
+        return BTreeMap::new();
+    }
+    match fs::read_to_string(dismissals_path(user_id)).await {
+        Ok(raw) => serde_json::from_str(&raw).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// Record a dismissal for `user_id`; `hours` turns it into a snooze.
+pub async fn dismiss(user_id: &str, id: &str, hours: Option<u64>) -> Result<Dismissal> {
+    if !is_safe_segment(user_id) {
+        return Err(anyhow!("invalid user_id"));
+    }
+    if !is_valid_id(id) {
+        return Err(anyhow!("invalid nudge id"));
+    }
+    let now = Utc::now();
+    let dismissal = Dismissal {
+        dismissed_at: now.to_rfc3339(),
+        until: hours.map(|h| (now + Duration::hours(h as i64)).to_rfc3339()),
+    };
+    let mut all = load_dismissals(user_id).await;
+    all.insert(id.to_string(), dismissal.clone());
+
+    let path = dismissals_path(user_id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("mkdir {}", dir.display()))?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(&all).unwrap_or_default())
+        .await
+        .with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, &path)
+        .await
+        .with_context(|| format!("rename {}", path.display()))?;
+    Ok(dismissal)
+}
+
+fn parse_ts(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn is_dismissed(n: &Nudge, dismissals: &BTreeMap<String, Dismissal>, now: DateTime<Utc>) -> bool {
+    let Some(d) = dismissals.get(&n.id) else {
+        return false;
+    };
+    let Some(at) = parse_ts(&d.dismissed_at) else {
+        return false;
+    };
+    if d.until
+        .as_deref()
+        .and_then(parse_ts)
+        .map(|u| now >= u)
+        .unwrap_or(false)
+    {
+        return false;
+    }
+    // A run finished after the dismissal means the situation changed.
+    !n.last_run
+        .as_deref()
+        .and_then(parse_ts)
+        .map(|t| t > at)
+        .unwrap_or(false)
+}
+
+pub struct NudgesReport {
+    pub features: Vec<FeatureStaleness>,
+    pub nudges: Vec<Nudge>,
+    pub dismissed: usize,
+}
+
+/// Nudges for `user_id` (dismissed ones are dropped unless `include_dismissed`).
+pub async fn compute(user_id: &str, include_dismissed: bool) -> NudgesReport {
+    let cfg = load_config();
+    let records = run_index::scan(None).await;
+    let now = Utc::now();
+
+    let mut features = Vec::new();
+    let mut nudges = Vec::new();
+    for rule in cfg.features.iter().filter(|r| !r.goal.trim().is_empty()) {
+        let (staleness, nudge) = assess(rule, &cfg, &records, now);
+        features.push(staleness);
+        nudges.extend(nudge);
+    }
+    // Failures first, then stale, then never-run.
+    nudges.sort_by_key(|n| match n.severity.as_str() {
+        "error" => 0,
+        "warn" => 1,
+        _ => 2,
+    });
+
+    // Evergreen nudges (dedup by id) so the UI always has "Run this" actions.
+    let mut seen: HashSet<String> = nudges.iter().map(|n| n.id.clone()).collect();
+    for n in evergreen_nudges() {
+        if seen.insert(n.id.clone()) {
+            nudges.push(n);
+        }
+    }
+
+    let dismissals = load_dismissals(user_id).await;
+    let before = nudges.len();
+    if !include_dismissed {
+        nudges.retain(|n| !is_dismissed(n, &dismissals, now));
+    }
+    NudgesReport {
+        features,
+        dismissed: before - nudges.len(),
+        nudges,
+    }
+}
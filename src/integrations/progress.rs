@@ -0,0 +1,330 @@
+//! Progress bus behind `/progress.sse`.
+//!
+//! Every event goes to a global channel (for unfiltered subscribers) and, when someone is
+//! watching that run, to the run's own channel. Run channels are created on subscribe and
+//! dropped by [`close`] once the run has finished: subscribers drain what is buffered and
+//! then see the end of the stream. Both channels are bounded; a subscriber that falls
+//! behind loses the oldest events and is told how many (see `progress_sse_handler`).
+//!
+//! - `ONE_ENGINE_PROGRESS_CAPACITY`: global buffer (default 256)
+//! - `ONE_ENGINE_PROGRESS_RUN_CAPACITY`: per-run buffer (default 64)
+//!
+//! Runs with a duration history get an ETA: [`track_eta`] when the run is accepted, then
+//! every event carries `eta_s` ([`eta_s`]) until [`close`].
+//!
+//! Run events name a [`Phase`]. [`publish_phase`] lets exactly one terminal phase (`done`,
+//! `error`, `interrupted`) through per run; a run id that starts over (`queued`, `init`)
+//! may finish again.
+
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use super::run_index::DurationStats;
+
+fn capacity(key: &str, default: usize) -> usize {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(default)
+}
+
+static GLOBAL: Lazy<broadcast::Sender<String>> = Lazy::new(|| {
+    let (tx, _rx) = broadcast::channel(capacity("ONE_ENGINE_PROGRESS_CAPACITY", 256));
+    tx
+});
+
+static RUNS: Lazy<Mutex<HashMap<String, broadcast::Sender<String>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Publish one serialized event for `run_id`.
+pub fn publish(run_id: &str, payload: String) {
+    if let Ok(runs) = RUNS.lock() {
+        if let Some(tx) = runs.get(run_id) {
+            let _ = tx.send(payload.clone());
+        }
+    }
+    let _ = GLOBAL.send(payload);
+}
+
+/// Where a run is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Queued,
+    Init,
+    Preempted,
+    Start,
+    Plan,
+    Act,
+    Tick,
+    Verify,
+    Done,
+    Error,
+    Interrupted,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 11] = [
+        Phase::Queued,
+        Phase::Init,
+        Phase::Preempted,
+        Phase::Start,
+        Phase::Plan,
+        Phase::Act,
+        Phase::Tick,
+        Phase::Verify,
+        Phase::Done,
+        Phase::Error,
+        Phase::Interrupted,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Queued => "queued",
+            Phase::Init => "init",
+            Phase::Preempted => "preempted",
+            Phase::Start => "start",
+            Phase::Plan => "plan",
+            Phase::Act => "act",
+            Phase::Tick => "tick",
+            Phase::Verify => "verify",
+            Phase::Done => "done",
+            Phase::Error => "error",
+            Phase::Interrupted => "interrupted",
+        }
+    }
+
+    /// Rank in a run's lifecycle: a run's events never go down in order, except that
+    /// `preempted` sends it back to the queue.
+    pub fn order(self) -> u8 {
+        match self {
+            Phase::Queued | Phase::Init | Phase::Preempted => 0,
+            Phase::Start => 1,
+            Phase::Plan => 2,
+            Phase::Act | Phase::Tick => 3,
+            Phase::Verify => 4,
+            Phase::Done | Phase::Error | Phase::Interrupted => 5,
+        }
+    }
+
+    pub fn is_terminal(self) -> bool {
+        matches!(self, Phase::Done | Phase::Error | Phase::Interrupted)
+    }
+}
+
+/// One row of `GET /progress/phases`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct PhaseInfo {
+    pub phase: Phase,
+    pub order: u8,
+    pub terminal: bool,
+}
+
+pub fn phases() -> Vec<PhaseInfo> {
+    Phase::ALL
+        .iter()
+        .map(|p| PhaseInfo {
+            phase: *p,
+            order: p.order(),
+            terminal: p.is_terminal(),
+        })
+        .collect()
+}
+
+/// Runs that already published a terminal phase (the most recent few thousand).
+static TERMINATED: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+const TERMINATED_KEEP: usize = 4096;
+
+/// Publish `run_id`'s `phase` event; a second terminal phase for the run is dropped (and
+/// logged), so `false` means nothing was sent.
+pub fn publish_phase(run_id: &str, phase: Phase, payload: String) -> bool {
+    {
+        let mut done = TERMINATED.lock().unwrap_or_else(|e| e.into_inner());
+        if matches!(phase, Phase::Queued | Phase::Init) {
+            done.retain(|r| r != run_id);
+        } else if phase.is_terminal() {
+            if done.iter().any(|r| r == run_id) {
+                tracing::warn!(
+                    "dropping a second terminal phase ({}) for run {}",
+                    phase.as_str(),
+                    run_id
+                );
+                return false;
+            }
+            if done.len() >= TERMINATED_KEEP {
+                done.pop_front();
+            }
+            done.push_back(run_id.to_string());
+        }
+    }
+    publish(run_id, payload);
+    true
+}
+
+/// Receive every run's events.
+pub fn subscribe_all() -> broadcast::Receiver<String> {
+    GLOBAL.subscribe()
+}
+
+/// Receive one run's events. Channels nobody listens to any more are pruned here, so
+/// subscribers to runs that never finish (or never existed) don't accumulate.
+pub fn subscribe(run_id: &str) -> broadcast::Receiver<String> {
+    let mut runs = RUNS.lock().unwrap_or_else(|e| e.into_inner());
+    runs.retain(|_, tx| tx.receiver_count() > 0);
+    runs.entry(run_id.to_string())
+        .or_insert_with(|| broadcast::channel(capacity("ONE_ENGINE_PROGRESS_RUN_CAPACITY", 64)).0)
+        .subscribe()
+}
+
+/// The run is over: drop its channel so subscribers finish after the buffered events.
+pub fn close(run_id: &str) {
+    if let Ok(mut runs) = RUNS.lock() {
+        runs.remove(run_id);
+    }
+    if let Ok(mut etas) = ETAS.lock() {
+        etas.remove(run_id);
+    }
+}
+
+/// When a run was accepted, and its goal's duration history.
+type EtaClock = (Instant, Option<DurationStats>);
+
+/// Clocks of the runs tracked for an ETA.
+static ETAS: Lazy<Mutex<HashMap<String, EtaClock>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Start the clock for `run_id`; without history (`stats: None`) there is no ETA.
+pub fn track_eta(run_id: &str, stats: Option<DurationStats>) {
+    if let Ok(mut etas) = ETAS.lock() {
+        etas.insert(run_id.to_string(), (Instant::now(), stats));
+    }
+}
+
+/// Seconds left, refined as the run goes: the p50 until the run is slower than that, then
+/// the p90. `None` for untracked runs and once a run has outlived its p90.
+pub fn eta_s(run_id: &str) -> Option<u64> {
+    let etas = ETAS.lock().ok()?;
+    let (started, stats) = etas.get(run_id)?;
+    remaining_ms(stats.as_ref()?, started.elapsed().as_millis() as u64).map(|ms| ms.div_ceil(1000))
+}
+
+fn remaining_ms(stats: &DurationStats, elapsed_ms: u64) -> Option<u64> {
+    [stats.p50_ms, stats.p90_ms]
+        .into_iter()
+        .find(|p| *p > elapsed_ms)
+        .map(|p| p - elapsed_ms)
+}
+
+/// Milliseconds since [`track_eta`], for recording the run's duration when it finishes.
+pub fn elapsed_ms(run_id: &str) -> Option<u64> {
+    let etas = ETAS.lock().ok()?;
+    etas.get(run_id)
+        .map(|(t, _)| t.elapsed().as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    async fn drain(
+        rx: &mut broadcast::Receiver<String>,
+        tag: &str,
+        want: usize,
+    ) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        while seen.len() < want {
+            match tokio::time::timeout(Duration::from_secs(5), rx.recv()).await {
+                Ok(Ok(s)) if s.starts_with(tag) => {
+                    seen.insert(s);
+                }
+                Ok(Ok(_)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => {}
+                _ => break,
+            }
+        }
+        seen
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_emitters_and_subscribers_share_one_bus() {
+        let tag = format!("bus-{}", uuid::Uuid::new_v4());
+        // First use races across OS threads: every subscriber must land on the same sender.
+        let subs: Vec<_> = (0..8).map(|_| std::thread::spawn(subscribe_all)).collect();
+        let mut rxs: Vec<_> = subs
+            .into_iter()
+            .map(|h| h.join().expect("subscribe"))
+            .collect();
+
+        let emitters: Vec<_> = (0..8)
+            .map(|i| {
+                let payload = format!("{}-{}", tag, i);
+                tokio::spawn(async move { publish("r-bus-test", payload) })
+            })
+            .collect();
+        for e in emitters {
+            e.await.expect("emit");
+        }
+        for rx in rxs.iter_mut() {
+            assert_eq!(drain(rx, &tag, 8).await.len(), 8);
+        }
+    }
+
+    #[test]
+    fn eta_moves_from_p50_to_p90_then_gives_up() {
+        let stats = DurationStats {
+            samples: 10,
+            p50_ms: 10_000,
+            p90_ms: 30_000,
+        };
+        assert_eq!(remaining_ms(&stats, 0), Some(10_000));
+        assert_eq!(remaining_ms(&stats, 4_000), Some(6_000));
+        assert_eq!(remaining_ms(&stats, 12_000), Some(18_000));
+        assert_eq!(remaining_ms(&stats, 30_000), None);
+
+        let run = format!("r-{}", uuid::Uuid::new_v4());
+        track_eta(&run, Some(stats));
+        assert_eq!(eta_s(&run), Some(10));
+        close(&run);
+        assert_eq!(eta_s(&run), None);
+    }
+
+    #[test]
+    fn terminal_phases_go_out_once_per_run() {
+        let run = format!("r-{}", uuid::Uuid::new_v4());
+        assert!(publish_phase(&run, Phase::Act, String::new()));
+        assert!(publish_phase(&run, Phase::Done, String::new()));
+        assert!(!publish_phase(&run, Phase::Error, String::new()));
+        // Same id, new run.
+        assert!(publish_phase(&run, Phase::Queued, String::new()));
+        assert!(publish_phase(&run, Phase::Done, String::new()));
+
+        assert_eq!(serde_json::to_value(Phase::Tick).unwrap(), "tick");
+        assert!(phases().windows(2).all(|w| w[0].order <= w[1].order));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn run_channels_are_isolated_and_end_on_close() {
+        let run_a = format!("r-{}", uuid::Uuid::new_v4());
+        let run_b = format!("r-{}", uuid::Uuid::new_v4());
+        let mut rx_a = subscribe(&run_a);
+        let mut rx_b = subscribe(&run_b);
+        publish(&run_a, format!("{}-a", run_a));
+        publish(&run_b, format!("{}-b", run_b));
+        close(&run_a);
+
+        assert_eq!(rx_a.recv().await.ok(), Some(format!("{}-a", run_a)));
+        assert!(matches!(
+            rx_a.recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        ));
+        assert_eq!(rx_b.recv().await.ok(), Some(format!("{}-b", run_b)));
+        close(&run_b);
+    }
+}
@@ -0,0 +1,277 @@
+//! Who may read a run's receipt and the artifacts it produced under `/runs`.
+//!
+//! Each receipt gets an `acl.json` next to `request.json` when it is written: the `owner` is the
+//! user the run was made for (`ctx.user_id` of the request) and `is_public` starts out false.
+//! Receipts written before this have no `acl.json`; their owner is read from `request.json`.
+//!
+//! A path under `runs/` belongs to a run when one of its first three segments (or that segment
+//! up to its first `.`) names a receipt: `receipts/<run_id>/…`, `graphs/<run_id>/…`,
+//! `patches/<run_id>.reverse.patch`, `/runs/<run_id>/bundle.zip`. Such a path is readable by:
+//! - anyone, if the run is public or has no owner (system and anonymous runs, as before);
+//! - the owner's API key, or the admin key, otherwise.
+//!
+//! Anything else under `runs/` needs the admin key (default deny), except the shared reports
+//! in [`PUBLIC_PATHS`]. [`PRIVATE_PATHS`] are admin-only even where a segment happens to name a
+//! receipt.
+
+use anyhow::{bail, Context, Result};
+use one_engine::storage;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+/// Top-level entries of `runs/` that mix every user's data.
+pub const PRIVATE_PATHS: &[&str] = &[
+    "audit",
+    "usage",
+    "utir",
+    "api_trace",
+    "api_trace.jsonl",
+    "telemetry",
+    "threads",
+    "flags",
+    "correlations",
+];
+
+/// Top-level entries of `runs/` anyone may read when no run owns the path: rendered reports
+/// that carry no per-user content.
+pub const PUBLIC_PATHS: &[&str] = &[
+    "wiki",
+    "graphs",
+    "calibration",
+    "kpi",
+    "evals",
+    "ruliad_kernel",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Acl {
+    /// The user the run belongs to; `None` for system and anonymous runs.
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// Readable without a key.
+    #[serde(default)]
+    pub is_public: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<String>,
+}
+
+/// The key a request came with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    Admin,
+    User(String),
+    /// No key, or a key no user has.
+    Anonymous,
+}
+
+/// What a path under `runs/` is protected by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Run(String),
+    Private,
+    Open,
+}
+
+fn runs_dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs")
+}
+
+fn is_receipt(runs: &Path, id: &str) -> bool {
+    !id.is_empty() && id != "receipts" && storage::exists(&runs.join("receipts").join(id))
+}
+
+fn scope_in(runs: &Path, tail: &str) -> Scope {
+    let segs: Vec<&str> = tail
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect();
+    let Some(first) = segs.first() else {
+        return Scope::Private;
+    };
+    if PRIVATE_PATHS.contains(first) {
+        return Scope::Private;
+    }
+    for seg in segs.iter().take(3) {
+        let stem = seg.split('.').next().unwrap_or(seg);
+        for id in [*seg, stem] {
+            if is_receipt(runs, id) {
+                return Scope::Run(id.to_string());
+            }
+        }
+    }
+    if PUBLIC_PATHS.contains(first) {
+        Scope::Open
+    } else {
+        Scope::Private
+    }
+}
+
+/// The run (or private log) path `tail` under `runs/` belongs to.
+pub fn scope(tail: &str) -> Scope {
+    scope_in(&runs_dir(), tail)
+}
+
+fn read_acl(rdir: &Path) -> Acl {
+    if let Some(acl) = storage::read_to_string(&rdir.join("acl.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Acl>(&raw).ok())
+    {
+        return acl;
+    }
+    let req: Value = storage::read_to_string(&rdir.join("request.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or(Value::Null);
+    Acl {
+        owner: owner_of(&req),
+        ..Default::default()
+    }
+}
+
+/// The access list of run `run_id`.
+pub async fn load(run_id: &str) -> Acl {
+    let rdir = runs_dir().join("receipts").join(run_id);
+    tokio::task::spawn_blocking(move || read_acl(&rdir))
+        .await
+        .unwrap_or_default()
+}
+
+/// The user a (redacted) request was made for.
+pub fn owner_of(request: &Value) -> Option<String> {
+    request
+        .pointer("/ctx/user_id")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+}
+
+/// Record `owner` on the receipt in `rdir` unless it already has one; keeps `is_public`.
+pub async fn record_owner(rdir: &Path, owner: Option<String>) {
+    let path = rdir.join("acl.json");
+    let existing = storage::read_to_string_async(&path).await.ok();
+    let mut acl = existing
+        .as_deref()
+        .and_then(|raw| serde_json::from_str::<Acl>(raw).ok())
+        .unwrap_or_default();
+    if acl.owner.is_some() || (owner.is_none() && existing.is_some()) {
+        return;
+    }
+    acl.owner = owner;
+    acl.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    let body = serde_json::to_string_pretty(&acl).unwrap_or_default();
+    if let Err(e) = one_engine::atomic::write_async(&path, body).await {
+        tracing::warn!("failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// Whether `caller` may read the receipt in `rdir`, for callers already off the async workers.
+pub fn readable_in(rdir: &Path, caller: &Caller) -> bool {
+    *caller == Caller::Admin || can_read(&read_acl(rdir), caller)
+}
+
+/// Whether `caller` may read run `run_id`'s receipt and artifacts.
+pub async fn caller_can_read(run_id: &str, caller: &Caller) -> bool {
+    *caller == Caller::Admin || can_read(&load(run_id).await, caller)
+}
+
+pub fn can_read(acl: &Acl, caller: &Caller) -> bool {
+    match (&acl.owner, caller) {
+        _ if acl.is_public => true,
+        (None, _) => true,
+        (_, Caller::Admin) => true,
+        (Some(owner), Caller::User(u)) => owner == u,
+        (Some(_), Caller::Anonymous) => false,
+    }
+}
+
+/// Share (or unshare) run `run_id`; only its owner or the admin may.
+pub async fn set_public(run_id: &str, is_public: bool, caller: &Caller) -> Result<Acl> {
+    let rdir = runs_dir().join("receipts").join(run_id);
+    if !storage::exists(&rdir) {
+        bail!("no receipt for run {}", run_id);
+    }
+    let mut acl = load(run_id).await;
+    let allowed = match (caller, &acl.owner) {
+        (Caller::Admin, _) => true,
+        (Caller::User(u), Some(owner)) => u == owner,
+        _ => false,
+    };
+    if !allowed {
+        bail!("only the run's owner or the admin can share it");
+    }
+    acl.is_public = is_public;
+    acl.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    let path = rdir.join("acl.json");
+    one_engine::atomic::write_async(&path, serde_json::to_string_pretty(&acl)?)
+        .await
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    let actor = match caller {
+        Caller::User(u) => u.as_str(),
+        _ => "admin",
+    };
+    super::audit::record(
+        actor,
+        "receipt.share",
+        run_id,
+        None,
+        serde_json::json!({ "is_public": is_public }),
+    )
+    .await;
+    Ok(acl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_map_to_their_run_and_owners_gate_reads() {
+        let runs = std::env::temp_dir().join(format!("receipt-acl-{}", uuid::Uuid::new_v4()));
+        let rdir = runs.join("receipts").join("r-1");
+        std::fs::create_dir_all(&rdir).unwrap();
+        std::fs::write(
+            rdir.join("request.json"),
+            r#"{"goal_id":"meta.omni","ctx":{"user_id":"demo","thread":"t-default"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            scope_in(&runs, "receipts/r-1/RECEIPT.md"),
+            Scope::Run("r-1".into())
+        );
+        assert_eq!(scope_in(&runs, "r-1/bundle.zip"), Scope::Run("r-1".into()));
+        assert_eq!(
+            scope_in(&runs, "patches/r-1.reverse.patch"),
+            Scope::Run("r-1".into())
+        );
+        assert_eq!(scope_in(&runs, "calibration/index.html"), Scope::Open);
+        assert_eq!(scope_in(&runs, "audit/audit.jsonl"), Scope::Private);
+        // Default deny: listings and anything not known to be shared.
+        assert_eq!(scope_in(&runs, "receipts"), Scope::Private);
+        assert_eq!(scope_in(&runs, ""), Scope::Private);
+        assert_eq!(scope_in(&runs, "staleness/ctx.json"), Scope::Private);
+        assert_eq!(
+            scope_in(&runs, "api_trace/2024-01-01.jsonl"),
+            Scope::Private
+        );
+        assert_eq!(scope_in(&runs, "threads/r-1/t.jsonl"), Scope::Private);
+
+        // Legacy receipt: the owner comes from request.json.
+        let mut acl = read_acl(&rdir);
+        assert_eq!(acl.owner.as_deref(), Some("demo"));
+        assert!(can_read(&acl, &Caller::User("demo".into())));
+        assert!(can_read(&acl, &Caller::Admin));
+        assert!(!can_read(&acl, &Caller::User("other".into())));
+        assert!(!can_read(&acl, &Caller::Anonymous));
+        acl.is_public = true;
+        assert!(can_read(&acl, &Caller::Anonymous));
+        assert!(can_read(&Acl::default(), &Caller::Anonymous));
+        std::fs::remove_dir_all(&runs).unwrap();
+    }
+}
@@ -0,0 +1,340 @@
+//! Run dependencies: a queued run that starts only after other runs have finished.
+//!
+//! `POST /run.async` takes `depends_on: [run_id]` (receipts that must exist and that the
+//! caller may read) and `on_parent_failure: skip|run_anyway`. The run is queued at once, then
+//! waits, before it takes a slot, until every parent's receipt is terminal: finished
+//! (successfully or not), interrupted or skipped. With `skip` (the default) a run whose parent
+//! did not succeed is not started; its receipt says `skipped`, which in turn skips its own
+//! dependents. `run_anyway` starts it regardless. Either way the receipt's evidence lists each
+//! parent's outcome under `depends_on`, and `request.json` keeps the dependencies, which
+//! `graphs.receipts` draws as edges.
+//!
+//! ```yaml
+//! run_dependencies:
+//!   max_wait_s: 86400   # a parent still pending after this counts as failed
+//! ```
+
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use one_engine::storage;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use utoipa::ToSchema;
+
+use super::receipt_acl::{self, Caller};
+
+/// Parents one run may wait on.
+pub const MAX_PARENTS: usize = 32;
+/// How often pending parents are re-read when no receipt in this process finished.
+const POLL: Duration = Duration::from_secs(2);
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum OnParentFailure {
+    #[default]
+    Skip,
+    RunAnyway,
+}
+
+/// What `request.json` records under `ctx.dependencies`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct Dependencies {
+    pub depends_on: Vec<String>,
+    #[serde(default)]
+    pub on_parent_failure: OnParentFailure,
+}
+
+/// A parent run's state, from its receipt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParentState {
+    /// No receipt.
+    Unknown,
+    /// Queued or running.
+    Pending,
+    /// Terminal; `status` is `succeeded`, `failed`, `interrupted`, `skipped`, ...
+    Done { status: String, success: bool },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ParentOutcome {
+    pub run_id: String,
+    pub status: String,
+    pub success: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DepsSection {
+    #[serde(default)]
+    max_wait_s: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesDeps {
+    #[serde(default)]
+    run_dependencies: Option<DepsSection>,
+}
+
+fn max_wait() -> Duration {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let secs = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesDeps>(&raw).ok())
+        .and_then(|p| p.run_dependencies?.max_wait_s)
+        .unwrap_or(86_400);
+    Duration::from_secs(secs)
+}
+
+fn receipts_dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs")
+        .join("receipts")
+}
+
+/// State of a receipt's `response.json`: a queued stub carries `status`, a finished run a
+/// `manifest`.
+pub fn state_of(response: Option<&Value>) -> ParentState {
+    let Some(resp) = response else {
+        return ParentState::Pending;
+    };
+    if let Some(evidence) = resp.pointer("/manifest/evidence") {
+        let success = evidence
+            .get("actual_success")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let status = evidence
+            .get("status")
+            .and_then(|v| v.as_str())
+            .filter(|s| *s == "skipped")
+            .unwrap_or(if success { "succeeded" } else { "failed" });
+        return ParentState::Done {
+            status: status.to_string(),
+            success,
+        };
+    }
+    match resp.get("status").and_then(|v| v.as_str()) {
+        Some("queued") | Some("running") | None => ParentState::Pending,
+        Some(other) => ParentState::Done {
+            status: other.to_string(),
+            success: false,
+        },
+    }
+}
+
+pub fn state(run_id: &str) -> ParentState {
+    let dir = receipts_dir().join(run_id);
+    if !storage::exists(&dir) {
+        return ParentState::Unknown;
+    }
+    let resp = storage::read_to_string(&dir.join("response.json"))
+        .ok()
+        .and_then(|raw| serde_json::from_str::<Value>(&raw).ok());
+    state_of(resp.as_ref())
+}
+
+/// `read` (normally [`state`]) of each of `run_ids`, on the blocking pool.
+async fn states(run_ids: &[String], read: fn(&str) -> ParentState) -> Vec<ParentState> {
+    let ids = run_ids.to_vec();
+    tokio::task::spawn_blocking(move || ids.iter().map(|id| read(id)).collect())
+        .await
+        .unwrap_or_else(|_| vec![ParentState::Unknown; run_ids.len()])
+}
+
+/// Reject dependencies a run can't wait on: itself, unsafe ids, runs without a receipt or
+/// that `caller` may not read. A run with dependencies needs a new id: parents must already
+/// exist, so a new run can never close a cycle, but reusing an existing id could.
+pub async fn validate(run_id: &str, parents: &[String], caller: &Caller) -> Result<()> {
+    if parents.is_empty() {
+        return Ok(());
+    }
+    if parents.len() > MAX_PARENTS {
+        bail!("depends_on takes at most {} runs", MAX_PARENTS);
+    }
+    for p in parents {
+        if p == run_id {
+            bail!("a run cannot depend on itself");
+        }
+        if p.is_empty() || p.contains(['/', '\\']) || p.contains("..") {
+            bail!("invalid run id in depends_on: {}", p);
+        }
+    }
+    let mut ids = parents.to_vec();
+    ids.push(run_id.to_string());
+    let found = states(&ids, state).await;
+    if !run_id.is_empty() && found[parents.len()] != ParentState::Unknown {
+        bail!(
+            "run {} already exists; a run with depends_on needs a new run_id",
+            run_id
+        );
+    }
+    for (p, s) in parents.iter().zip(&found) {
+        // Runs the caller can't read are reported like missing ones.
+        if *s == ParentState::Unknown || !receipt_acl::caller_can_read(p, caller).await {
+            bail!("depends_on: no run {}", p);
+        }
+    }
+    Ok(())
+}
+
+static FINISHED: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// Wake runs waiting on parents; call when a receipt is written.
+pub fn notify_finished() {
+    FINISHED.notify_waiters();
+}
+
+/// Wait until every parent is terminal (or [`max_wait`] passes: those count as `timed_out`).
+pub async fn await_parents(parents: &[String]) -> Vec<ParentOutcome> {
+    wait_for(parents, max_wait(), state).await
+}
+
+async fn wait_for(
+    parents: &[String],
+    max_wait: Duration,
+    read: fn(&str) -> ParentState,
+) -> Vec<ParentOutcome> {
+    let deadline = Instant::now() + max_wait;
+    loop {
+        let notified = FINISHED.notified();
+        let states = states(parents, read).await;
+        let pending = states.contains(&ParentState::Pending);
+        if !pending || Instant::now() >= deadline {
+            return parents
+                .iter()
+                .zip(states)
+                .map(|(p, s)| {
+                    let (status, success) = match s {
+                        ParentState::Done { status, success } => (status, success),
+                        ParentState::Pending => ("timed_out".to_string(), false),
+                        ParentState::Unknown => ("missing".to_string(), false),
+                    };
+                    ParentOutcome {
+                        run_id: p.clone(),
+                        status,
+                        success,
+                    }
+                })
+                .collect();
+        }
+        let _ = tokio::time::timeout(POLL, notified).await;
+    }
+}
+
+/// Why the run must be skipped, if it must.
+pub fn skip_reason(outcomes: &[ParentOutcome], policy: OnParentFailure) -> Option<String> {
+    if policy == OnParentFailure::RunAnyway {
+        return None;
+    }
+    let failed: Vec<String> = outcomes
+        .iter()
+        .filter(|o| !o.success)
+        .map(|o| format!("{} ({})", o.run_id, o.status))
+        .collect();
+    (!failed.is_empty()).then(|| format!("parent run did not succeed: {}", failed.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    static PARENT_DONE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+    fn parent(_: &str) -> ParentState {
+        if PARENT_DONE.load(std::sync::atomic::Ordering::SeqCst) {
+            ParentState::Done {
+                status: "succeeded".into(),
+                success: true,
+            }
+        } else {
+            ParentState::Pending
+        }
+    }
+
+    #[tokio::test]
+    async fn waiters_wake_when_a_receipt_finishes_and_time_out_otherwise() {
+        let parents = vec!["r-parent".to_string()];
+        let timed_out = wait_for(&parents, Duration::ZERO, parent).await;
+        assert_eq!(
+            (timed_out[0].status.as_str(), timed_out[0].success),
+            ("timed_out", false)
+        );
+
+        let started = Instant::now();
+        let waiter = tokio::spawn({
+            let parents = parents.clone();
+            async move { wait_for(&parents, Duration::from_secs(60), parent).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        PARENT_DONE.store(true, std::sync::atomic::Ordering::SeqCst);
+        notify_finished();
+        let outcomes = waiter.await.unwrap();
+        assert_eq!(
+            (outcomes[0].status.as_str(), outcomes[0].success),
+            ("succeeded", true)
+        );
+        // Woken by the notification, not by the next poll.
+        assert!(started.elapsed() < POLL);
+    }
+
+    #[test]
+    fn receipt_states_and_failure_policies() {
+        assert_eq!(state_of(None), ParentState::Pending);
+        assert_eq!(
+            state_of(Some(&json!({"run_id": "r-1", "status": "queued"}))),
+            ParentState::Pending
+        );
+        let interrupted = state_of(Some(&json!({"status": "interrupted"})));
+        assert_eq!(
+            interrupted,
+            ParentState::Done {
+                status: "interrupted".into(),
+                success: false
+            }
+        );
+        let ok = json!({"manifest": {"evidence": {"actual_success": true}}});
+        let skipped =
+            json!({"manifest": {"evidence": {"actual_success": false, "status": "skipped"}}});
+        assert_eq!(
+            state_of(Some(&ok)),
+            ParentState::Done {
+                status: "succeeded".into(),
+                success: true
+            }
+        );
+        assert_eq!(
+            state_of(Some(&skipped)),
+            ParentState::Done {
+                status: "skipped".into(),
+                success: false
+            }
+        );
+
+        let outcomes = vec![
+            ParentOutcome {
+                run_id: "r-a".into(),
+                status: "succeeded".into(),
+                success: true,
+            },
+            ParentOutcome {
+                run_id: "r-b".into(),
+                status: "failed".into(),
+                success: false,
+            },
+        ];
+        assert_eq!(
+            skip_reason(&outcomes, OnParentFailure::Skip).as_deref(),
+            Some("parent run did not succeed: r-b (failed)")
+        );
+        assert_eq!(skip_reason(&outcomes, OnParentFailure::RunAnyway), None);
+        assert_eq!(skip_reason(&outcomes[..1], OnParentFailure::Skip), None);
+    }
+}
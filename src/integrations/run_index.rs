@@ -0,0 +1,413 @@
+//! Run index: a scan over `META3_ROOT/runs/receipts/<run_id>/` (one dir per run).
+//!
+//! Each record joins `request.json`, `response.json`, `timing.json`, the reply rating in
+//! `feedback.json` and the per-run request latency from `runs/api_trace.jsonl`. Successful-run
+//! latencies also feed the per-goal duration history behind run ETAs ([`duration_stats`]).
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Instant;
+use tokio::fs;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    pub goal_id: String,
+    pub ts: DateTime<Utc>,
+    pub success: Option<bool>,
+    pub latency_ms: Option<u64>,
+    pub user_id: Option<String>,
+    pub meta2_proposal: Option<Value>,
+    pub bits: Option<Value>,
+    /// The user's rating of the reply, from `feedback.json`.
+    #[serde(default)]
+    pub feedback: Option<super::feedback::Rating>,
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+async fn read_json(p: PathBuf) -> Option<Value> {
+    let raw = one_engine::storage::read_to_string_async(p).await.ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Max request latency per run_id from the API trace (sync runs block for the whole run).
+async fn api_trace_latencies(since: Option<DateTime<Utc>>) -> HashMap<String, u64> {
+    let mut out: HashMap<String, u64> = HashMap::new();
+    let p = meta3_root().join("runs").join("api_trace.jsonl");
+    let Ok(f) = fs::File::open(&p).await else {
+        return out;
+    };
+    let mut lines = tokio::io::BufReader::new(f).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(v) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let Some(run_id) = v.get("run_id").and_then(|x| x.as_str()) else {
+            continue;
+        };
+        if let Some(since) = since {
+            let ts = v
+                .get("ts")
+                .and_then(|x| x.as_str())
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|d| d.with_timezone(&Utc));
+            if ts.map(|t| t < since).unwrap_or(false) {
+                continue;
+            }
+        }
+        let ms = v.get("ms").and_then(|x| x.as_u64()).unwrap_or(0);
+        let e = out.entry(run_id.to_string()).or_insert(0);
+        *e = (*e).max(ms);
+    }
+    out
+}
+
+fn span_ms(timing: &Value) -> Option<u64> {
+    let parse = |k: &str| {
+        timing
+            .get(k)
+            .and_then(|x| x.as_str())
+            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+    };
+    let (a, b) = (parse("started_at")?, parse("finished_at")?);
+    let ms = (b - a).num_milliseconds();
+    if ms > 0 {
+        Some(ms as u64)
+    } else {
+        None
+    }
+}
+
+/// Scan receipts (newest first). `since` filters on the receipt's response mtime.
+pub async fn scan(since: Option<DateTime<Utc>>) -> Vec<RunRecord> {
+    let dir = meta3_root().join("runs").join("receipts");
+    let latencies = api_trace_latencies(since).await;
+    let mut out = Vec::new();
+
+    // With a read-only META3_ROOT new receipts are in the scratch dir or in memory.
+    let mut run_ids: Vec<String> = one_engine::storage::memory_entries(&dir);
+    for d in one_engine::storage::overlays(&dir) {
+        let Ok(mut rd) = fs::read_dir(&d).await else {
+            continue;
+        };
+        while let Ok(Some(ent)) = rd.next_entry().await {
+            if ent.file_type().await.map(|ft| ft.is_dir()).unwrap_or(false) {
+                run_ids.push(ent.file_name().to_string_lossy().to_string());
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    for run_id in run_ids {
+        if !is_safe_segment(&run_id) || !seen.insert(run_id.clone()) {
+            continue;
+        }
+        let rdir = dir.join(&run_id);
+        let Some(modified) = one_engine::storage::modified(&rdir.join("response.json")) else {
+            continue;
+        };
+        let ts: DateTime<Utc> = modified.into();
+        if since.map(|s| ts < s).unwrap_or(false) {
+            continue;
+        }
+
+        let latency = latencies.get(&run_id).copied();
+        out.push(read_detail(run_id, &rdir, ts, latency).await.record);
+    }
+    out.sort_by_key(|r| std::cmp::Reverse(r.ts));
+    out
+}
+
+/// Everything a single receipt dir says about its run.
+pub struct RunDetail {
+    pub record: RunRecord,
+    /// `status` from a stub receipt (`queued`, `running`, `interrupted`, ...); `None` once the
+    /// run has a full response.
+    pub status: Option<String>,
+    pub response: Value,
+    pub timing: Value,
+}
+
+async fn read_detail(
+    run_id: String,
+    rdir: &std::path::Path,
+    ts: DateTime<Utc>,
+    trace_latency: Option<u64>,
+) -> RunDetail {
+    let mut resp = read_json(rdir.join("response.json"))
+        .await
+        .unwrap_or(Value::Null);
+    crate::engine::migrate::upgrade_response(&mut resp);
+    let req = read_json(rdir.join("request.json"))
+        .await
+        .unwrap_or(Value::Null);
+    let timing = read_json(rdir.join("timing.json"))
+        .await
+        .unwrap_or(Value::Null);
+
+    let manifest = resp.get("manifest");
+    let goal_id = manifest
+        .and_then(|m| m.get("goal_id"))
+        .or_else(|| resp.get("goal_id"))
+        .or_else(|| req.get("goal_id"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    let status = resp.get("status").and_then(|v| v.as_str());
+    let success = if matches!(status, Some("queued") | Some("running")) {
+        None
+    } else {
+        manifest
+            .and_then(|m| m.get("evidence"))
+            .and_then(|e| e.get("actual_success"))
+            .and_then(|v| v.as_bool())
+    };
+    let meta2_proposal = resp.get("meta2_proposal").filter(|v| !v.is_null()).cloned();
+    let bits = resp
+        .get("bits")
+        .or_else(|| manifest.and_then(|m| m.get("bits")))
+        .cloned();
+    let user_id = req
+        .get("ctx")
+        .and_then(|c| c.get("user_id"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
+
+    let latency_ms = match (trace_latency, span_ms(&timing)) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    };
+
+    let feedback = super::feedback::read(rdir).await;
+
+    let status = status.map(|s| s.to_string());
+    let record = RunRecord {
+        run_id,
+        goal_id,
+        ts,
+        success,
+        latency_ms,
+        user_id,
+        meta2_proposal,
+        bits,
+        feedback,
+    };
+    RunDetail {
+        record,
+        status,
+        response: resp,
+        timing,
+    }
+}
+
+/// One run by id (receipt mtime as `ts`; latency from `timing.json` only).
+pub async fn get(run_id: &str) -> Option<RunDetail> {
+    if !is_safe_segment(run_id) {
+        return None;
+    }
+    let rdir = meta3_root().join("runs").join("receipts").join(run_id);
+    let ts: DateTime<Utc> = one_engine::storage::modified(&rdir.join("response.json"))?.into();
+    Some(read_detail(run_id.to_string(), &rdir, ts, None).await)
+}
+
+/// Finished-run durations for one goal, from the receipts of the last [`DURATION_WINDOW_DAYS`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DurationStats {
+    pub samples: usize,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+}
+
+const DURATION_WINDOW_DAYS: i64 = 30;
+/// Most recent durations kept per goal.
+const DURATION_SAMPLES: usize = 200;
+const DURATION_REFRESH: std::time::Duration = std::time::Duration::from_secs(600);
+
+/// Successful-run durations per goal_id (newest last), rebuilt from a receipt scan every
+/// [`DURATION_REFRESH`] and topped up by [`record_duration`] in between.
+static DURATIONS: Lazy<Mutex<Option<(Instant, DurationsByGoal)>>> = Lazy::new(|| Mutex::new(None));
+
+/// Durations in ms per goal_id, oldest first.
+type DurationsByGoal = HashMap<String, Vec<u64>>;
+
+/// Nearest-rank p50/p90; `None` without samples.
+pub fn duration_stats_of(samples: &[u64]) -> Option<DurationStats> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let rank =
+        |q: f64| sorted[((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+    Some(DurationStats {
+        samples: sorted.len(),
+        p50_ms: rank(0.5),
+        p90_ms: rank(0.9),
+    })
+}
+
+async fn durations_by_goal() -> DurationsByGoal {
+    let since = Utc::now() - chrono::Duration::days(DURATION_WINDOW_DAYS);
+    let mut by_goal = DurationsByGoal::new();
+    // `scan` is newest first; keep the newest samples, stored oldest first.
+    for r in scan(Some(since)).await.into_iter().rev() {
+        if let (Some(true), Some(ms)) = (r.success, r.latency_ms) {
+            by_goal.entry(r.goal_id).or_default().push(ms);
+        }
+    }
+    for v in by_goal.values_mut() {
+        let excess = v.len().saturating_sub(DURATION_SAMPLES);
+        v.drain(..excess);
+    }
+    by_goal
+}
+
+/// p50/p90 duration of successful `goal_id` runs; `None` for goals with no history.
+pub async fn duration_stats(goal_id: &str) -> Option<DurationStats> {
+    let mut cache = DURATIONS.lock().await;
+    if cache
+        .as_ref()
+        .is_none_or(|(at, _)| at.elapsed() > DURATION_REFRESH)
+    {
+        *cache = Some((Instant::now(), durations_by_goal().await));
+    }
+    let (_, by_goal) = cache.as_ref()?;
+    duration_stats_of(by_goal.get(goal_id)?)
+}
+
+/// Add a finished run to the duration history without waiting for the next rescan.
+pub async fn record_duration(goal_id: &str, ms: u64) {
+    if let Some((_, by_goal)) = DURATIONS.lock().await.as_mut() {
+        let v = by_goal.entry(goal_id.to_string()).or_default();
+        v.push(ms);
+        if v.len() > DURATION_SAMPLES {
+            v.remove(0);
+        }
+    }
+}
+
+/// Longest span `parse_window` returns; larger windows are clamped to it.
+const MAX_WINDOW_DAYS: i64 = 366;
+
+/// Parse `window=` values like `24h`, `7d`, `90m`, `2w` (default unit: hours), clamped to
+/// `MAX_WINDOW_DAYS`.
+pub fn parse_window(raw: &str) -> Option<chrono::Duration> {
+    let s = raw.trim().to_ascii_lowercase();
+    if s.is_empty() {
+        return None;
+    }
+    let (num, unit) = match s.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => (&s[..i], &s[i..]),
+        None => (s.as_str(), "h"),
+    };
+    let n: i64 = num.parse().ok().filter(|n| *n > 0)?;
+    let minutes_per = match unit {
+        "m" => 1,
+        "h" => 60,
+        "d" => 24 * 60,
+        "w" => 7 * 24 * 60,
+        _ => return None,
+    };
+    let minutes = n.saturating_mul(minutes_per).min(MAX_WINDOW_DAYS * 24 * 60);
+    Some(chrono::Duration::minutes(minutes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_window_reads_units_and_clamps() {
+        assert_eq!(parse_window("24"), Some(chrono::Duration::hours(24)));
+        assert_eq!(parse_window(" 90m "), Some(chrono::Duration::minutes(90)));
+        assert_eq!(parse_window("7D"), Some(chrono::Duration::days(7)));
+        assert_eq!(parse_window("2w"), Some(chrono::Duration::weeks(2)));
+        assert_eq!(
+            parse_window("1000w"),
+            Some(chrono::Duration::days(MAX_WINDOW_DAYS))
+        );
+        for bad in ["", "0h", "-1d", "3y", "h"] {
+            assert_eq!(parse_window(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn duration_stats_use_nearest_rank() {
+        assert_eq!(duration_stats_of(&[]), None);
+        let samples: Vec<u64> = (1..=10).rev().map(|n| n * 100).collect();
+        assert_eq!(
+            duration_stats_of(&samples),
+            Some(DurationStats {
+                samples: 10,
+                p50_ms: 500,
+                p90_ms: 900,
+            })
+        );
+        assert_eq!(
+            duration_stats_of(&[42]).map(|s| (s.p50_ms, s.p90_ms)),
+            Some((42, 42))
+        );
+    }
+
+    #[tokio::test]
+    async fn read_detail_joins_request_response_and_timing() {
+        let rdir = std::env::temp_dir()
+            .join("one-engine-run-index")
+            .join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&rdir).unwrap();
+        let write = |name: &str, v: Value| std::fs::write(rdir.join(name), v.to_string()).unwrap();
+        write(
+            "request.json",
+            json!({"goal_id": "wiki.build", "ctx": {"user_id": "u1"}}),
+        );
+        write(
+            "response.json",
+            json!({"manifest": {"goal_id": "wiki.build", "evidence": {"actual_success": true}}}),
+        );
+        write(
+            "timing.json",
+            json!({"started_at": "2026-01-01T00:00:00Z", "finished_at": "2026-01-01T00:00:02Z"}),
+        );
+        let ts = Utc::now();
+
+        let d = read_detail("r-1".into(), &rdir, ts, Some(1_500)).await;
+        let r = &d.record;
+        assert_eq!(
+            (r.goal_id.as_str(), r.user_id.as_deref(), r.success),
+            ("wiki.build", Some("u1"), Some(true))
+        );
+        // The longer of the traced request and the receipt's own span.
+        assert_eq!(r.latency_ms, Some(2_000));
+        assert_eq!(d.status, None);
+
+        write(
+            "response.json",
+            json!({"status": "queued", "goal_id": "wiki.build"}),
+        );
+        let d = read_detail("r-1".into(), &rdir, ts, None).await;
+        assert_eq!(d.status.as_deref(), Some("queued"));
+        assert_eq!(d.record.success, None);
+        let _ = std::fs::remove_dir_all(&rdir);
+    }
+}
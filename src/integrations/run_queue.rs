@@ -0,0 +1,378 @@
+//! Run slots and priorities.
+//!
+//! At most `queue.slots` goal runs execute at once. The rest wait and start highest priority
+//! first, oldest first within a priority. With `queue.preempt: true`, a `high` run that has to
+//! wait stops the newest preemptible `low` run; that run keeps its place at the head of the
+//! `low` queue and starts over when a slot frees up.
+//!
+//! ```yaml
+//! queue:
+//!   slots: 4        # default: the machine's available parallelism
+//!   preempt: false
+//! ```
+//!
+//! `ONE_ENGINE_RUN_SLOTS` and `ONE_ENGINE_RUN_PREEMPT` (`1`/`0`) override the policy.
+
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, Notify};
+use utoipa::ToSchema;
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// Lower runs first.
+    fn rank(self) -> u8 {
+        match self {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    slots: usize,
+    preempt: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct QueueSection {
+    #[serde(default)]
+    slots: Option<usize>,
+    #[serde(default)]
+    preempt: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesQueue {
+    #[serde(default)]
+    queue: Option<QueueSection>,
+}
+
+static CONFIG: Lazy<Config> = Lazy::new(|| {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let section = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesQueue>(&raw).ok())
+        .and_then(|p| p.queue)
+        .unwrap_or_default();
+    let slots = std::env::var("ONE_ENGINE_RUN_SLOTS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .or(section.slots)
+        .filter(|n| *n > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+        });
+    let preempt = match std::env::var("ONE_ENGINE_RUN_PREEMPT").ok().as_deref() {
+        Some("1") | Some("true") => true,
+        Some("0") | Some("false") => false,
+        _ => section.preempt,
+    };
+    Config { slots, preempt }
+});
+
+struct Running {
+    priority: Priority,
+    preemptible: bool,
+    preempt: Arc<Notify>,
+    preempted: bool,
+}
+
+struct Waiting {
+    seq: u64,
+    priority: Priority,
+    preemptible: bool,
+    tx: oneshot::Sender<Arc<Notify>>,
+}
+
+/// Running runs and waiters, both keyed by ticket sequence number.
+#[derive(Default)]
+struct Queue {
+    running: HashMap<u64, Running>,
+    waiting: Vec<Waiting>,
+}
+
+enum Entry {
+    Started(Arc<Notify>),
+    Waiting(oneshot::Receiver<Arc<Notify>>),
+}
+
+impl Queue {
+    fn start(&mut self, seq: u64, priority: Priority, preemptible: bool) -> Arc<Notify> {
+        let preempt = Arc::new(Notify::new());
+        self.running.insert(
+            seq,
+            Running {
+                priority,
+                preemptible,
+                preempt: preempt.clone(),
+                preempted: false,
+            },
+        );
+        preempt
+    }
+
+    fn enter(&mut self, cfg: Config, ticket: &Ticket) -> Entry {
+        if self.running.len() < cfg.slots && self.waiting.is_empty() {
+            return Entry::Started(self.start(ticket.seq, ticket.priority, ticket.preemptible));
+        }
+        let (tx, rx) = oneshot::channel();
+        self.waiting.push(Waiting {
+            seq: ticket.seq,
+            priority: ticket.priority,
+            preemptible: ticket.preemptible,
+            tx,
+        });
+        if cfg.preempt && ticket.priority == Priority::High {
+            // Newest first: it has the least work to lose.
+            let victim = self
+                .running
+                .iter_mut()
+                .filter(|(_, r)| r.preemptible && r.priority == Priority::Low && !r.preempted)
+                .max_by_key(|(seq, _)| **seq);
+            if let Some((_, r)) = victim {
+                r.preempted = true;
+                r.preempt.notify_one();
+            }
+        }
+        Entry::Waiting(rx)
+    }
+
+    /// A waiter went away: drop its place, and give back the slot if one was already sent.
+    fn abandon(&mut self, cfg: Config, seq: u64) {
+        self.waiting.retain(|w| w.seq != seq);
+        if self.running.contains_key(&seq) {
+            self.leave(cfg, seq);
+        }
+    }
+
+    fn leave(&mut self, cfg: Config, seq: u64) {
+        self.running.remove(&seq);
+        while self.running.len() < cfg.slots {
+            let Some(i) = self
+                .waiting
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, w)| (w.priority.rank(), w.seq))
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            let w = self.waiting.remove(i);
+            let preempt = self.start(w.seq, w.priority, w.preemptible);
+            // A waiter that went away (its task was dropped) gives the slot straight back.
+            if w.tx.send(preempt).is_err() {
+                self.running.remove(&w.seq);
+            }
+        }
+    }
+}
+
+static QUEUE: Lazy<Mutex<Queue>> = Lazy::new(|| Mutex::new(Queue::default()));
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A run's place in the queue. Re-acquiring after a preemption keeps the original place.
+pub struct Ticket {
+    seq: u64,
+    priority: Priority,
+    preemptible: bool,
+}
+
+impl Ticket {
+    /// `preemptible` runs may be stopped for a `high` one (only `low` runs ever are).
+    pub fn new(priority: Priority, preemptible: bool) -> Self {
+        Self {
+            seq: SEQ.fetch_add(1, Ordering::Relaxed),
+            priority,
+            preemptible,
+        }
+    }
+
+    /// Wait for a free slot; the slot is given back when the returned [`Slot`] is dropped.
+    /// Dropping the future while it waits gives up the place, or the slot if it was already
+    /// handed over.
+    pub async fn acquire(&self) -> Slot {
+        loop {
+            let entry = QUEUE
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .enter(*CONFIG, self);
+            let rx = match entry {
+                Entry::Started(preempt) => {
+                    return Slot {
+                        seq: self.seq,
+                        preempt,
+                    }
+                }
+                Entry::Waiting(rx) => rx,
+            };
+            let mut waiter = Waiter {
+                seq: self.seq,
+                armed: true,
+            };
+            let handed = rx.await;
+            if let Ok(preempt) = handed {
+                waiter.armed = false;
+                return Slot {
+                    seq: self.seq,
+                    preempt,
+                };
+            }
+            // The place was lost without a slot; `waiter` cleans up and we queue again.
+        }
+    }
+}
+
+/// Cleans up after an [`Ticket::acquire`] that stopped waiting before it got its slot.
+struct Waiter {
+    seq: u64,
+    armed: bool,
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        if self.armed {
+            QUEUE
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .abandon(*CONFIG, self.seq);
+        }
+    }
+}
+
+pub struct Slot {
+    seq: u64,
+    preempt: Arc<Notify>,
+}
+
+impl Slot {
+    /// Resolves when a `high` run wants this slot; the holder should stop and re-queue.
+    pub async fn preempted(&self) {
+        self.preempt.notified().await
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        QUEUE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .leave(*CONFIG, self.seq);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(seq: u64, priority: Priority, preemptible: bool) -> Ticket {
+        Ticket {
+            seq,
+            priority,
+            preemptible,
+        }
+    }
+
+    fn waiting(e: Entry) -> oneshot::Receiver<Arc<Notify>> {
+        match e {
+            Entry::Waiting(rx) => rx,
+            Entry::Started(_) => panic!("expected to wait"),
+        }
+    }
+
+    #[tokio::test]
+    async fn waiters_start_by_priority_and_high_preempts_low() {
+        let cfg = Config {
+            slots: 1,
+            preempt: true,
+        };
+        let mut q = Queue::default();
+        let Entry::Started(low) = q.enter(cfg, &ticket(0, Priority::Low, true)) else {
+            panic!("first run should start");
+        };
+        let mut normal = waiting(q.enter(cfg, &ticket(1, Priority::Normal, false)));
+        let mut high = waiting(q.enter(cfg, &ticket(2, Priority::High, false)));
+        // The high run asked the low one to stop.
+        tokio::time::timeout(std::time::Duration::from_secs(1), low.notified())
+            .await
+            .expect("low run preempted");
+
+        // The preempted run leaves and re-queues with its old place; high goes first.
+        q.leave(cfg, 0);
+        let mut low_again = waiting(q.enter(cfg, &ticket(0, Priority::Low, true)));
+        assert!(high.try_recv().is_ok());
+        assert!(normal.try_recv().is_err());
+        q.leave(cfg, 2);
+        assert!(normal.try_recv().is_ok());
+        assert!(low_again.try_recv().is_err());
+        q.leave(cfg, 1);
+        assert!(low_again.try_recv().is_ok());
+        assert_eq!(q.running.len(), 1);
+        assert!(q.waiting.is_empty());
+    }
+
+    #[test]
+    fn abandoned_waiters_give_back_their_place_and_slot() {
+        let cfg = Config {
+            slots: 1,
+            preempt: false,
+        };
+        let mut q = Queue::default();
+        let _ = q.enter(cfg, &ticket(0, Priority::Normal, false));
+        let gone = waiting(q.enter(cfg, &ticket(1, Priority::High, false)));
+        let mut next = waiting(q.enter(cfg, &ticket(2, Priority::Normal, false)));
+        // The slot was sent to 1, whose future is dropped before it turns into a Slot.
+        q.leave(cfg, 0);
+        assert!(q.running.contains_key(&1));
+        drop(gone);
+        q.abandon(cfg, 1);
+        assert!(next.try_recv().is_ok());
+        assert_eq!(q.running.keys().collect::<Vec<_>>(), [&2]);
+
+        // Still waiting: only the place goes.
+        let _ = q.enter(cfg, &ticket(3, Priority::Normal, false));
+        q.abandon(cfg, 3);
+        assert!(q.waiting.is_empty());
+        assert_eq!(q.running.len(), 1);
+    }
+
+    #[test]
+    fn no_preemption_unless_enabled_and_preemptible() {
+        let mut q = Queue::default();
+        let off = Config {
+            slots: 1,
+            preempt: false,
+        };
+        let _ = q.enter(off, &ticket(0, Priority::Low, true));
+        let _ = q.enter(off, &ticket(1, Priority::High, false));
+        assert!(!q.running[&0].preempted);
+
+        let mut q = Queue::default();
+        let on = Config {
+            slots: 1,
+            preempt: true,
+        };
+        let _ = q.enter(on, &ticket(0, Priority::Low, false));
+        let _ = q.enter(on, &ticket(1, Priority::High, false));
+        assert!(!q.running[&0].preempted);
+    }
+}
@@ -0,0 +1,226 @@
+//! LM-written summaries for runs attached to a thread.
+//!
+//! With `attach_summary.mode: lm`, attaching a run asks the router (optionally a smaller
+//! `model`) for 3-5 bullets on what the run did and what it produced, instead of the
+//! heuristic evidence snippets. The prompt is a redacted digest of the manifest capped at
+//! `input_chars`, and the reply at `max_tokens`. Without a router key, on any router error or
+//! on an unusable reply, [`bullets`] returns `None` and the caller keeps the heuristic.
+//!
+//! ```yaml
+//! attach_summary:
+//!   mode: lm            # lm | heuristic (default)
+//!   model: openai/gpt-4o-mini
+//!   max_tokens: 200
+//!   input_chars: 6000
+//! ```
+//!
+//! The section is read once per process. `ONE_ENGINE_ATTACH_SUMMARY` (`lm`/`heuristic`) and
+//! `ONE_ENGINE_ATTACH_SUMMARY_MODEL` override it. A reply with fewer than 3 bullets counts as
+//! unusable.
+
+use once_cell::sync::Lazy;
+use one_engine::redact::redact;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::engine::router;
+use crate::integrations::telemetry;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SummaryConfig {
+    #[serde(default)]
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    #[serde(default = "default_input_chars")]
+    pub input_chars: usize,
+}
+
+fn default_max_tokens() -> u32 {
+    200
+}
+
+fn default_input_chars() -> usize {
+    6000
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        Self {
+            mode: None,
+            model: None,
+            max_tokens: default_max_tokens(),
+            input_chars: default_input_chars(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesSummary {
+    #[serde(default)]
+    attach_summary: Option<SummaryConfig>,
+}
+
+/// `attach_summary` from policies.yaml, read once rather than on every attach.
+static FILE_CONFIG: Lazy<SummaryConfig> = Lazy::new(|| {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesSummary>(&raw).ok())
+        .and_then(|p| p.attach_summary)
+        .unwrap_or_default()
+});
+
+pub fn load_config() -> SummaryConfig {
+    let mut cfg = FILE_CONFIG.clone();
+    if let Ok(v) = std::env::var("ONE_ENGINE_ATTACH_SUMMARY") {
+        cfg.mode = Some(v);
+    }
+    if let Ok(v) = std::env::var("ONE_ENGINE_ATTACH_SUMMARY_MODEL") {
+        cfg.model = Some(v).filter(|m| !m.trim().is_empty());
+    }
+    cfg
+}
+
+const SYSTEM: &str = "You summarize one finished goal run for a chat thread. Reply as JSON \
+{\"bullets\": [\"...\"]} with 3 to 5 short bullets: what the run did, whether it succeeded, \
+and its key outputs (files, URLs, counts, errors). Use only facts from the run; no advice.";
+
+const FIELD_CHARS: usize = 400;
+
+/// Compact, redacted text of `manifest` within `budget` chars: strings cut to a few hundred
+/// chars, nesting below four levels and array items past eight dropped.
+fn digest(manifest: &Value, budget: usize) -> String {
+    fn walk(v: &Value, depth: usize, out: &mut String, key: &str, budget: usize) {
+        if out.len() >= budget {
+            return;
+        }
+        let pad = "  ".repeat(depth);
+        match v {
+            Value::Object(map) if depth < 4 => {
+                out.push_str(&format!("{}{}:\n", pad, key));
+                for (k, item) in map {
+                    walk(item, depth + 1, out, k, budget);
+                }
+            }
+            Value::Array(items) if depth < 4 => {
+                out.push_str(&format!("{}{}: ({} items)\n", pad, key, items.len()));
+                for (i, item) in items.iter().take(8).enumerate() {
+                    walk(item, depth + 1, out, &i.to_string(), budget);
+                }
+            }
+            Value::String(s) => {
+                let s: String = s.chars().take(FIELD_CHARS).collect();
+                out.push_str(&format!("{}{}: {}\n", pad, key, redact(&s)));
+            }
+            Value::Object(_) | Value::Array(_) => {}
+            other => out.push_str(&format!("{}{}: {}\n", pad, key, other)),
+        }
+    }
+    // Headline fields first so a tight budget still names the run.
+    let mut out = String::new();
+    for k in ["goal_id", "run_id"] {
+        if let Some(v) = manifest.get(k).and_then(|v| v.as_str()) {
+            out.push_str(&format!("{}: {}\n", k, v));
+        }
+    }
+    for k in ["deliverables", "evidence"] {
+        if let Some(v) = manifest.get(k) {
+            walk(v, 0, &mut out, k, budget);
+        }
+    }
+    out.chars().take(budget).collect()
+}
+
+/// 1-5 non-empty bullets from the model's reply, or `None`.
+/// The reply's bullets, cleaned; `None` unless there are at least 3 (more than 5 are cut).
+fn parse_bullets(reply: &Value) -> Option<Vec<String>> {
+    let bullets: Vec<String> = reply
+        .get("bullets")?
+        .as_array()?
+        .iter()
+        .filter_map(|b| b.as_str())
+        .map(|b| b.trim().trim_start_matches(['-', '*', '•']).trim())
+        .filter(|b| !b.is_empty())
+        .map(|b| redact(&b.chars().take(240).collect::<String>()))
+        .take(5)
+        .collect();
+    (bullets.len() >= 3).then_some(bullets)
+}
+
+/// LM bullets for the receipt response `resp`, when enabled and the router answers.
+pub async fn bullets(resp: &Value) -> Option<Vec<String>> {
+    let cfg = load_config();
+    if !cfg
+        .mode
+        .as_deref()
+        .is_some_and(|m| m.trim().eq_ignore_ascii_case("lm"))
+    {
+        return None;
+    }
+    let manifest = resp.get("manifest")?;
+    let opts = router::ChatOpts {
+        temperature: Some(0.0),
+        max_tokens: Some(cfg.max_tokens),
+        model: cfg.model.clone(),
+    };
+    let user = digest(manifest, cfg.input_chars);
+    match router::chat_opts(SYSTEM, &user, &opts).await {
+        Ok(out) => {
+            let parsed = parse_bullets(&out.value);
+            telemetry::emit(
+                "attach_summary",
+                if parsed.is_some() {
+                    "lm"
+                } else {
+                    "lm_unusable"
+                },
+                None,
+                json!({"tokens": out.total_tokens, "model": cfg.model}),
+            );
+            parsed
+        }
+        Err(e) => {
+            tracing::debug!("attach summary falls back to heuristic: {}", e);
+            telemetry::emit(
+                "attach_summary",
+                "fallback",
+                None,
+                json!({"error": e.to_string()}),
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digest_stays_in_budget_and_bullets_are_cleaned() {
+        let manifest = json!({
+            "goal_id": "meta3.build",
+            "evidence": {"stdout": "x".repeat(10_000), "actual_success": true}
+        });
+        let d = digest(&manifest, 300);
+        assert!(d.chars().count() <= 300);
+        assert!(d.contains("goal_id: meta3.build"));
+
+        let reply = json!({"bullets": ["- Built the repo", "  ", "* 3 warnings", "• 2 files"]});
+        assert_eq!(
+            parse_bullets(&reply).unwrap(),
+            vec![
+                "Built the repo".to_string(),
+                "3 warnings".to_string(),
+                "2 files".to_string()
+            ]
+        );
+        // Fewer than 3 usable bullets falls back to the heuristic summary.
+        assert!(parse_bullets(&json!({"bullets": ["- Built", " "]})).is_none());
+        assert!(parse_bullets(&json!({"reply": "sure"})).is_none());
+    }
+}
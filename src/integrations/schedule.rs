@@ -0,0 +1,139 @@
+//! In-process goal scheduler (cron-style) driven by the `schedules:` section of policies.yaml.
+//!
+//! ```yaml
+//! schedules:
+//!   - goal: reports.daily
+//!     at: "08:00"        # daily, UTC
+//!     inputs: { notify: true }
+//!   - goal: staleness.check
+//!     every: 6h          # window syntax (m/h/d/w)
+//! ```
+//!
+//! Each firing goes through `POST /run` on the server itself (`ONE_ENGINE_BASE_URL`, default
+//! `http://127.0.0.1:8080`) so scheduled runs leave the same receipts as manual ones.
+//! `ONE_ENGINE_SCHEDULES=0` disables the scheduler.
+
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::run_index;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Schedule {
+    pub goal: String,
+    /// Daily at `HH:MM` UTC.
+    #[serde(default)]
+    pub at: Option<String>,
+    /// Fixed interval (`30m`, `6h`, `1d`).
+    #[serde(default)]
+    pub every: Option<String>,
+    #[serde(default)]
+    pub inputs: Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesSchedules {
+    #[serde(default)]
+    schedules: Vec<Schedule>,
+}
+
+pub fn load_config() -> Vec<Schedule> {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    match std::fs::read_to_string(&path) {
+        Ok(raw) => match serde_yaml::from_str::<PoliciesSchedules>(&raw) {
+            Ok(p) => p.schedules,
+            Err(e) => {
+                tracing::warn!("invalid {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+impl Schedule {
+    /// Next firing strictly after `now`; `None` when neither `at` nor `every` parses.
+    pub fn next_after(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Some(at) = self.at.as_deref() {
+            let t = NaiveTime::parse_from_str(at.trim(), "%H:%M").ok()?;
+            let today = Utc.from_utc_datetime(&now.date_naive().and_time(t));
+            return Some(if today > now {
+                today
+            } else {
+                today + chrono::Duration::days(1)
+            });
+        }
+        let every = run_index::parse_window(self.every.as_deref()?)?;
+        Some(now + every)
+    }
+}
+
+fn base_url() -> String {
+    std::env::var("ONE_ENGINE_BASE_URL")
+        .unwrap_or_else(|_| "http://127.0.0.1:8080".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+async fn fire(client: &reqwest::Client, s: &Schedule) {
+    let inputs = if s.inputs.is_object() {
+        s.inputs.clone()
+    } else {
+        json!({})
+    };
+    let body = json!({ "goal_id": s.goal, "inputs": inputs });
+    match client
+        .post(format!("{}/run", base_url()))
+        .json(&body)
+        .send()
+        .await
+    {
+        Ok(resp) => {
+            let status = resp.status();
+            let v: Value = resp.json().await.unwrap_or(Value::Null);
+            let run_id = v
+                .get("manifest")
+                .and_then(|m| m.get("run_id"))
+                .and_then(|x| x.as_str())
+                .unwrap_or("-");
+            tracing::info!(
+                "schedule {}: HTTP {} run_id={}",
+                s.goal,
+                status.as_u16(),
+                run_id
+            );
+        }
+        Err(e) => tracing::warn!("schedule {} failed: {}", s.goal, e),
+    }
+}
+
+/// Spawn one timer task per valid schedule entry.
+pub fn spawn_all() {
+    if std::env::var("ONE_ENGINE_SCHEDULES").ok().as_deref() == Some("0") {
+        return;
+    }
+    if crate::engine::simulation::active() {
+        tracing::info!("schedules don't fire in simulation mode");
+        return;
+    }
+    for s in load_config() {
+        if s.next_after(Utc::now()).is_none() {
+            tracing::warn!(
+                "schedule {}: needs `at: HH:MM` or `every: <window>`",
+                s.goal
+            );
+            continue;
+        }
+        tracing::info!("scheduled {} (at={:?} every={:?})", s.goal, s.at, s.every);
+        tokio::spawn(async move {
+            let client = reqwest::Client::new();
+            while let Some(next) = s.next_after(Utc::now()) {
+                let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                fire(&client, &s).await;
+            }
+        });
+    }
+}
@@ -1,6 +1,184 @@
 use super::TelemetryEvent;
 use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+// -------- Durable sink: META3_ROOT/runs/telemetry/<YYYY-MM-DD>.jsonl --------
+//
+// Files rotate by size (ONE_ENGINE_TELEMETRY_MAX_BYTES, default 16 MiB): the full
+// day file is renamed to <date>.<n>.jsonl and a fresh <date>.jsonl is started.
+
+static SINK_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+fn telemetry_dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs")
+        .join("telemetry")
+}
+
+fn max_file_bytes() -> u64 {
+    std::env::var("ONE_ENGINE_TELEMETRY_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(16 * 1024 * 1024)
+}
+
+async fn rotate_if_needed(dir: &Path, date: &str) {
+    let cur = dir.join(format!("{}.jsonl", date));
+    let Ok(meta) = tokio::fs::metadata(&cur).await else {
+        return;
+    };
+    if meta.len() < max_file_bytes() {
+        return;
+    }
+    let mut n = 1u32;
+    loop {
+        let rotated = dir.join(format!("{}.{}.jsonl", date, n));
+        if tokio::fs::metadata(&rotated).await.is_err() {
+            let _ = tokio::fs::rename(&cur, &rotated).await;
+            return;
+        }
+        n += 1;
+    }
+}
+
+/// Append events to today's telemetry file. Returns how many were written.
+pub async fn append_events(events: &[TelemetryEvent]) -> std::io::Result<usize> {
+    if events.is_empty() {
+        return Ok(0);
+    }
+    let dir = telemetry_dir();
+    let date = Utc::now().format("%Y-%m-%d").to_string();
+    let _guard = SINK_LOCK.lock().await;
+    tokio::fs::create_dir_all(&dir).await?;
+    rotate_if_needed(&dir, &date).await;
+
+    let mut buf = String::new();
+    for ev in events {
+        if let Ok(line) = serde_json::to_string(ev) {
+            buf.push_str(&line);
+            buf.push('\n');
+        }
+    }
+    let mut f = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(format!("{}.jsonl", date)))
+        .await?;
+    f.write_all(buf.as_bytes()).await?;
+    Ok(events.len())
+}
+
+/// Fire-and-forget engine-internal event (gate trips, meta2 proposals, executor failures).
+pub fn emit(component: &str, event_type: &str, run_id: Option<&str>, metadata: serde_json::Value) {
+    let event = TelemetryEvent {
+        ts: Utc::now().to_rfc3339(),
+        component: component.to_string(),
+        event_type: event_type.to_string(),
+        run_id: run_id.map(|s| s.to_string()),
+        bits: None,
+        cost: None,
+        kpi_impact: None,
+        metadata,
+    };
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn(async move {
+            if let Err(e) = append_events(std::slice::from_ref(&event)).await {
+                tracing::debug!("telemetry sink write failed: {}", e);
+            }
+        });
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TelemetryFilter {
+    pub component: Option<String>,
+    pub event_type: Option<String>,
+    pub run_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+/// Read events back (newest files first), filtered; returns up to `limit` newest matches.
+pub async fn query(filter: &TelemetryFilter) -> Vec<TelemetryEvent> {
+    let dir = telemetry_dir();
+    let since_day = filter.since.map(|s| s.format("%Y-%m-%d").to_string());
+    let mut files: Vec<String> = Vec::new();
+    if let Ok(mut rd) = tokio::fs::read_dir(&dir).await {
+        while let Ok(Some(ent)) = rd.next_entry().await {
+            let name = ent.file_name().to_string_lossy().to_string();
+            // `get` also rejects a non-ASCII name whose 10th byte isn't a char boundary.
+            let Some(file_day) = name.get(..10).filter(|_| name.ends_with(".jsonl")) else {
+                continue;
+            };
+            if since_day.as_deref().is_some_and(|day| file_day < day) {
+                continue;
+            }
+            files.push(name);
+        }
+    }
+    // Day order, rotated parts (<date>.<n>) before the live <date>.jsonl.
+    files.sort_by_key(|n| {
+        let day = n[..10].to_string();
+        let part = n[10..]
+            .trim_start_matches('.')
+            .trim_end_matches("jsonl")
+            .trim_end_matches('.')
+            .parse::<u32>()
+            .unwrap_or(u32::MAX);
+        (day, part)
+    });
+
+    let limit = if filter.limit == 0 { 500 } else { filter.limit };
+    let mut out: std::collections::VecDeque<TelemetryEvent> = std::collections::VecDeque::new();
+    for name in files {
+        let Ok(f) = tokio::fs::File::open(dir.join(&name)).await else {
+            continue;
+        };
+        let mut lines = tokio::io::BufReader::new(f).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let Ok(ev) = serde_json::from_str::<TelemetryEvent>(&line) else {
+                continue;
+            };
+            if filter
+                .component
+                .as_deref()
+                .map(|c| c != ev.component)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            if filter
+                .event_type
+                .as_deref()
+                .map(|t| t != ev.event_type)
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            if filter.run_id.is_some() && filter.run_id != ev.run_id {
+                continue;
+            }
+            if let Some(since) = filter.since {
+                let ts = DateTime::parse_from_rfc3339(&ev.ts).map(|d| d.with_timezone(&Utc));
+                if ts.map(|t| t < since).unwrap_or(true) {
+                    continue;
+                }
+            }
+            out.push_back(ev);
+            if out.len() > limit {
+                out.pop_front();
+            }
+        }
+    }
+    out.into_iter().collect()
+}
 
 pub struct TelemetryStore {
     events: Vec<TelemetryEvent>,
@@ -12,9 +190,10 @@ impl TelemetryStore {
     }
 
     pub async fn append(&mut self, event: TelemetryEvent) {
+        if let Err(e) = append_events(std::slice::from_ref(&event)).await {
+            tracing::debug!("telemetry sink write failed: {}", e);
+        }
         self.events.push(event);
-
-        // In production: write to persistent store (JSONL, DB, etc.)
         tracing::debug!("Telemetry event stored");
     }
 
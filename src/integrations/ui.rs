@@ -1,19 +1,158 @@
-use super::{CostSummary, EvalResult, KPIDashboard, SearchResult, UIState};
+use super::run_index::{self, RunRecord};
+use super::{
+    CostSummary, EvalResult, GoalFailures, Meta2ProposalRef, RunTimeline, SearchResult,
+    TimelineBucket, UIState,
+};
 use crate::engine::types::Manifest;
-use chrono::Utc;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+const DEFAULT_WINDOW: &str = "24h";
+
+pub async fn render_unified_state(window: Option<&str>) -> anyhow::Result<UIState> {
+    let window = window.unwrap_or(DEFAULT_WINDOW);
+    let span = run_index::parse_window(window)
+        .ok_or_else(|| anyhow::anyhow!("invalid window: {} (use e.g. 24h, 7d, 2w)", window))?;
+    let since = Utc::now() - span;
+    let records = run_index::scan(Some(since)).await;
 
-pub async fn render_unified_state() -> anyhow::Result<UIState> {
     let state = UIState {
         search_hits: get_recent_searches().await,
-        agent_runs: get_recent_runs().await,
+        agent_runs: get_recent_runs(&records).await,
         eval_scores: get_recent_evals().await,
         cost_tracking: get_cost_summary().await,
         kpi_dashboard: super::kpi::current_scores().await,
+        timeline: run_timeline(window, span, since, &records),
+        disk_usage: tokio::task::spawn_blocking(super::disk_quota::summary)
+            .await
+            .unwrap_or_default(),
+        satisfaction: super::feedback::by_goal(&records),
     };
 
     Ok(state)
 }
 
+fn percentile(sorted: &[u64], p: f32) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let idx = ((sorted.len() as f32 - 1.0) * p).round() as usize;
+    sorted.get(idx.min(sorted.len() - 1)).copied()
+}
+
+fn rate(ok: u32, total: u32) -> Option<f32> {
+    if total == 0 {
+        None
+    } else {
+        Some(ok as f32 / total as f32)
+    }
+}
+
+pub fn run_timeline(
+    window: &str,
+    span: Duration,
+    since: DateTime<Utc>,
+    records: &[RunRecord],
+) -> RunTimeline {
+    let (bucket_name, bucket) = if span <= Duration::hours(48) {
+        ("hour", Duration::hours(1))
+    } else {
+        ("day", Duration::days(1))
+    };
+
+    // Pre-seed empty buckets so the UI gets a continuous series.
+    let mut buckets: BTreeMap<DateTime<Utc>, TimelineBucket> = BTreeMap::new();
+    let mut cursor = since.duration_trunc(bucket).unwrap_or(since);
+    let now = Utc::now();
+    while cursor <= now {
+        buckets.insert(
+            cursor,
+            TimelineBucket {
+                start: cursor.to_rfc3339(),
+                total: 0,
+                succeeded: 0,
+                success_rate: None,
+                by_goal: BTreeMap::new(),
+            },
+        );
+        cursor += bucket;
+    }
+
+    let mut latencies: Vec<u64> = Vec::new();
+    let mut per_goal: HashMap<String, (u32, u32)> = HashMap::new();
+    let mut finished = 0u32;
+    let mut succeeded = 0u32;
+    let mut meta2 = Vec::new();
+
+    for r in records {
+        let key = r.ts.duration_trunc(bucket).unwrap_or(r.ts);
+        if let Some(b) = buckets.get_mut(&key) {
+            b.total += 1;
+            if r.success == Some(true) {
+                b.succeeded += 1;
+            }
+            *b.by_goal.entry(r.goal_id.clone()).or_insert(0) += 1;
+        }
+        if let Some(ok) = r.success {
+            finished += 1;
+            let g = per_goal.entry(r.goal_id.clone()).or_insert((0, 0));
+            g.1 += 1;
+            if ok {
+                succeeded += 1;
+            } else {
+                g.0 += 1;
+            }
+        }
+        if let Some(ms) = r.latency_ms {
+            latencies.push(ms);
+        }
+        if let Some(p) = r.meta2_proposal.as_ref() {
+            if meta2.len() < 20 {
+                meta2.push(Meta2ProposalRef {
+                    run_id: r.run_id.clone(),
+                    goal_id: r.goal_id.clone(),
+                    ts: r.ts.to_rfc3339(),
+                    proposal: p.clone(),
+                });
+            }
+        }
+    }
+
+    let mut buckets: Vec<TimelineBucket> = buckets.into_values().collect();
+    for b in &mut buckets {
+        b.success_rate = rate(b.succeeded, b.total);
+    }
+
+    latencies.sort_unstable();
+    let mut top_failing: Vec<GoalFailures> = per_goal
+        .into_iter()
+        .filter(|(_, (fail, _))| *fail > 0)
+        .map(|(goal_id, (failures, total))| GoalFailures {
+            goal_id,
+            failures,
+            total,
+        })
+        .collect();
+    top_failing.sort_by(|a, b| {
+        b.failures
+            .cmp(&a.failures)
+            .then_with(|| a.goal_id.cmp(&b.goal_id))
+    });
+    top_failing.truncate(10);
+
+    RunTimeline {
+        window: window.to_string(),
+        bucket: bucket_name.to_string(),
+        total_runs: records.len() as u32,
+        success_rate: rate(succeeded, finished),
+        latency_p50_ms: percentile(&latencies, 0.50),
+        latency_p95_ms: percentile(&latencies, 0.95),
+        buckets,
+        top_failing_goals: top_failing,
+        meta2_proposals: meta2,
+    }
+}
+
 async fn get_recent_searches() -> Vec<SearchResult> {
     // Simulate recent flywheel searches
     vec![SearchResult {
@@ -24,9 +163,30 @@ async fn get_recent_searches() -> Vec<SearchResult> {
     }]
 }
 
-async fn get_recent_runs() -> Vec<Manifest> {
-    // Simulate recent agent runs
-    vec![]
+async fn get_recent_runs(records: &[RunRecord]) -> Vec<Manifest> {
+    // Latest manifests from receipts (records are newest first).
+    let root = std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string());
+    let mut out = Vec::new();
+    for r in records.iter().take(20) {
+        let p = std::path::Path::new(&root)
+            .join("runs/receipts")
+            .join(&r.run_id)
+            .join("response.json");
+        let Ok(raw) = tokio::fs::read_to_string(&p).await else {
+            continue;
+        };
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(&raw) else {
+            continue;
+        };
+        if let Some(m) = v
+            .get("manifest")
+            .cloned()
+            .and_then(|m| serde_json::from_value::<Manifest>(m).ok())
+        {
+            out.push(m);
+        }
+    }
+    out
 }
 
 async fn get_recent_evals() -> Vec<EvalResult> {
@@ -0,0 +1,276 @@
+//! Stored per-user policy overrides (`users/<id>/policy.json`) and their audit trail
+//! (`runs/audit/policy_changes.jsonl`).
+//!
+//! A stored override replaces the built-in `UserContext::policy_overrides`; clearing it falls
+//! back to the built-in one. Every change appends an audit entry whose id is echoed in the
+//! receipt's `policy_chain`, so a run can be traced back to the change that shaped it.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use utoipa::ToSchema;
+
+use crate::engine::types::Policy;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StoredPolicy {
+    pub policy: Policy,
+    pub updated_at: String,
+    pub updated_by: String,
+    /// Audit entry that produced this override.
+    pub audit_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PolicyAuditEntry {
+    pub id: String,
+    pub ts: String,
+    pub user_id: String,
+    /// `admin` or the user id acting on itself.
+    pub actor: String,
+    pub before: Option<Policy>,
+    /// `None` when the override was cleared.
+    pub after: Option<Policy>,
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn policy_path(root: &Path, user_id: &str) -> PathBuf {
+    root.join("users").join(user_id).join("policy.json")
+}
+
+fn audit_path(root: &Path) -> PathBuf {
+    root.join("runs").join("audit").join("policy_changes.jsonl")
+}
+
+/// Stored override, if any. Blocking read: called while authenticating.
+pub fn load(user_id: &str) -> Option<StoredPolicy> {
+    load_in(&meta3_root(), user_id)
+}
+
+fn load_in(root: &Path, user_id: &str) -> Option<StoredPolicy> {
+    if !is_safe_segment(user_id) {
+        return None;
+    }
+    let raw = std::fs::read_to_string(policy_path(root, user_id)).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// The override in effect and where it came from: the stored one wins over the built-in one
+/// (`"stored"`, `"builtin"` or `"none"`).
+pub fn effective(
+    stored: Option<&StoredPolicy>,
+    builtin: Option<Policy>,
+) -> (Option<Policy>, &'static str) {
+    match (stored, builtin) {
+        (Some(s), _) => (Some(s.policy.clone()), "stored"),
+        (None, Some(p)) => (Some(p), "builtin"),
+        (None, None) => (None, "none"),
+    }
+}
+
+pub fn validate(p: &Policy) -> Result<()> {
+    if !(0.0..=1.0).contains(&p.gamma_gate) {
+        return Err(anyhow!("gamma_gate must be in [0, 1]"));
+    }
+    if !(0.0..=1.0).contains(&p.max_risk) {
+        return Err(anyhow!("max_risk must be in [0, 1]"));
+    }
+    if !(1_000..=3_600_000).contains(&p.time_ms) {
+        return Err(anyhow!("time_ms must be between 1000 and 3600000"));
+    }
+    if p.tiny_diff_loc == 0 || p.tiny_diff_loc > 100_000 {
+        return Err(anyhow!("tiny_diff_loc must be between 1 and 100000"));
+    }
+    Ok(())
+}
+
+async fn append_audit(root: &Path, entry: &PolicyAuditEntry) -> Result<()> {
+    let path = audit_path(root);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("mkdir {}", dir.display()))?;
+    }
+    let mut f = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await
+        .with_context(|| format!("open {}", path.display()))?;
+    let line = serde_json::to_string(entry)?;
+    f.write_all(format!("{line}\n").as_bytes())
+        .await
+        .with_context(|| format!("append {}", path.display()))?;
+    Ok(())
+}
+
+/// Store (or clear, with `None`) the override for `user_id`; `before` is the override in effect
+/// until now. The audit entry is written first so a stored override always has one.
+pub async fn set(
+    user_id: &str,
+    policy: Option<Policy>,
+    before: Option<Policy>,
+    actor: &str,
+) -> Result<PolicyAuditEntry> {
+    set_in(&meta3_root(), user_id, policy, before, actor).await
+}
+
+async fn set_in(
+    root: &Path,
+    user_id: &str,
+    policy: Option<Policy>,
+    before: Option<Policy>,
+    actor: &str,
+) -> Result<PolicyAuditEntry> {
+    if !is_safe_segment(user_id) {
+        return Err(anyhow!("invalid user_id"));
+    }
+    if let Some(p) = &policy {
+        validate(p)?;
+    }
+    let now = chrono::Utc::now().to_rfc3339();
+    let entry = PolicyAuditEntry {
+        id: format!("pa-{}", uuid::Uuid::new_v4()),
+        ts: now.clone(),
+        user_id: user_id.to_string(),
+        actor: actor.to_string(),
+        before,
+        after: policy.clone(),
+    };
+    append_audit(root, &entry).await?;
+
+    let path = policy_path(root, user_id);
+    match policy {
+        Some(p) => {
+            let stored = StoredPolicy {
+                policy: p,
+                updated_at: now,
+                updated_by: actor.to_string(),
+                audit_id: entry.id.clone(),
+            };
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)
+                    .await
+                    .with_context(|| format!("mkdir {}", dir.display()))?;
+            }
+            let tmp = path.with_extension("json.tmp");
+            fs::write(
+                &tmp,
+                serde_json::to_string_pretty(&stored).unwrap_or_default(),
+            )
+            .await
+            .with_context(|| format!("write {}", tmp.display()))?;
+            fs::rename(&tmp, &path)
+                .await
+                .with_context(|| format!("rename {}", path.display()))?;
+        }
+        None => {
+            let _ = fs::remove_file(&path).await;
+        }
+    }
+    Ok(entry)
+}
+
+/// Audit entries for `user_id`, newest first.
+pub async fn history(user_id: &str, limit: usize) -> Vec<PolicyAuditEntry> {
+    history_in(&meta3_root(), user_id, limit).await
+}
+
+async fn history_in(root: &Path, user_id: &str, limit: usize) -> Vec<PolicyAuditEntry> {
+    let Ok(raw) = fs::read_to_string(audit_path(root)).await else {
+        return Vec::new();
+    };
+    raw.lines()
+        .rev()
+        .filter_map(|l| serde_json::from_str::<PolicyAuditEntry>(l).ok())
+        .filter(|e| e.user_id == user_id)
+        .take(limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(time_ms: u64) -> Policy {
+        Policy {
+            time_ms,
+            ..Policy::default()
+        }
+    }
+
+    #[test]
+    fn stored_override_wins_over_the_builtin_one() {
+        let stored = StoredPolicy {
+            policy: policy(5_000),
+            updated_at: String::new(),
+            updated_by: "admin".into(),
+            audit_id: "pa-1".into(),
+        };
+        let (p, source) = effective(Some(&stored), Some(policy(9_000)));
+        assert_eq!((p.map(|p| p.time_ms), source), (Some(5_000), "stored"));
+        let (p, source) = effective(None, Some(policy(9_000)));
+        assert_eq!((p.map(|p| p.time_ms), source), (Some(9_000), "builtin"));
+        assert_eq!(effective(None, None).1, "none");
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_fields() {
+        assert!(validate(&policy(5_000)).is_ok());
+        assert!(validate(&policy(10)).is_err());
+        assert!(validate(&Policy {
+            gamma_gate: 1.5,
+            ..policy(5_000)
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn set_stores_clears_and_audits_each_change() {
+        let root = std::env::temp_dir()
+            .join("one-engine-user-policy")
+            .join(uuid::Uuid::new_v4().to_string());
+
+        let first = set_in(&root, "u1", Some(policy(5_000)), None, "admin")
+            .await
+            .unwrap();
+        let stored = load_in(&root, "u1").unwrap();
+        assert_eq!(stored.policy.time_ms, 5_000);
+        assert_eq!(stored.audit_id, first.id);
+
+        set_in(&root, "u1", None, Some(stored.policy), "u1")
+            .await
+            .unwrap();
+        assert!(load_in(&root, "u1").is_none());
+        set_in(&root, "u2", Some(policy(7_000)), None, "admin")
+            .await
+            .unwrap();
+
+        let history = history_in(&root, "u1", 10).await;
+        assert_eq!(history.len(), 2);
+        assert!(history[0].after.is_none());
+        assert_eq!(history[0].actor, "u1");
+        assert_eq!(history[1].id, first.id);
+        assert!(set_in(&root, "../u1", None, None, "admin").await.is_err());
+        assert!(set_in(&root, "u1", Some(policy(10)), None, "admin")
+            .await
+            .is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,479 @@
+//! Browse the files under `META3_ROOT/workspaces` without shell access (`GET /files`,
+//! `GET /files/preview`), so the UI can pick inputs for `research.read`.
+//!
+//! Paths are relative to META3_ROOT and lie under `workspaces/`, so an entry's `path` goes
+//! straight to `research.read`; `..` and symlinks that lead out of the workspaces root are
+//! refused. Entries whose name matches a deny pattern ([`DEFAULT_DENY`] plus
+//! `workspace_files.deny`) are left out of listings and can't be previewed; a denied directory
+//! hides everything under it, and a symlink to a denied file is refused like the file itself.
+//! Previews are capped at `preview_max_bytes` and redacted. Both endpoints need an API key.
+//!
+//! ```yaml
+//! workspace_files:
+//!   deny: ["*.sqlite", "dumps"]   # added to the defaults
+//!   preview_max_bytes: 65536
+//! ```
+
+use one_engine::redact::redact_counted;
+use one_engine::research::glob_match;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+use walkdir::WalkDir;
+
+/// File names never listed or previewed (globs on a single path segment).
+pub const DEFAULT_DENY: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "*.kdbx",
+    "id_rsa*",
+    "id_dsa*",
+    "id_ecdsa*",
+    "id_ed25519*",
+    ".netrc",
+    ".npmrc",
+    ".pypirc",
+    ".ssh",
+    ".aws",
+    ".gnupg",
+    ".git",
+    "credentials*",
+];
+/// Entries a `glob` listing looks at before it gives up (`truncated`).
+const MAX_SCAN: usize = 20_000;
+/// The workspaces root, relative to META3_ROOT; every path in and out starts with it.
+pub const WORKSPACES: &str = "workspaces";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WorkspaceFilesConfig {
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default = "default_preview_max_bytes")]
+    pub preview_max_bytes: usize,
+}
+
+fn default_preview_max_bytes() -> usize {
+    64 * 1024
+}
+
+impl Default for WorkspaceFilesConfig {
+    fn default() -> Self {
+        Self {
+            deny: Vec::new(),
+            preview_max_bytes: default_preview_max_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesWorkspaceFiles {
+    #[serde(default)]
+    workspace_files: Option<WorkspaceFilesConfig>,
+}
+
+pub fn load_config() -> WorkspaceFilesConfig {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesWorkspaceFiles>(&raw).ok())
+        .and_then(|p| p.workspace_files)
+        .unwrap_or_default()
+}
+
+/// `META3_ROOT/workspaces`.
+pub fn workspaces_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join(WORKSPACES)
+}
+
+#[derive(Debug)]
+pub enum FilesError {
+    /// Not a path under the workspaces root.
+    Invalid(String),
+    /// Matches a deny pattern.
+    Denied(String),
+    NotFound(String),
+    Io(String),
+}
+
+impl std::fmt::Display for FilesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilesError::Invalid(p) => write!(f, "invalid path: {}", p),
+            FilesError::Denied(p) => write!(f, "path is not browsable: {}", p),
+            FilesError::NotFound(p) => write!(f, "no such file or directory: {}", p),
+            FilesError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct FileEntry {
+    /// Relative to META3_ROOT (`workspaces/...`); pass it back as `path`, or to `research.read`.
+    pub path: String,
+    pub name: String,
+    pub kind: EntryKind,
+    pub bytes: u64,
+    /// RFC3339.
+    pub mtime: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct FilesPage {
+    pub path: String,
+    pub glob: Option<String>,
+    /// Directories first, then by name; with `glob`, by path.
+    pub entries: Vec<FileEntry>,
+    /// Matches across all pages.
+    pub total: usize,
+    pub offset: usize,
+    /// Pass as `offset` for the next page; `None` when exhausted.
+    pub next_offset: Option<usize>,
+    /// The `glob` walk stopped early; narrow `path`.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct FilePreview {
+    pub path: String,
+    /// Size of the whole file.
+    pub bytes: u64,
+    pub mtime: Option<String>,
+    pub content_type: String,
+    /// Only the first `preview_max_bytes` are shown.
+    pub truncated: bool,
+    /// Looks binary; `text` is empty.
+    pub binary: bool,
+    pub redactions: usize,
+    pub text: String,
+}
+
+/// Whether a single path segment matches [`DEFAULT_DENY`] or `deny`.
+pub(crate) fn is_denied(name: &str, deny: &[String]) -> bool {
+    DEFAULT_DENY.iter().any(|p| glob_match(p, name)) || deny.iter().any(|p| glob_match(p, name))
+}
+
+/// `path` (META3_ROOT-relative, empty for the workspaces root) as a normalized path relative to
+/// the workspaces root (`a/b`), or why it isn't one.
+fn normalize(path: &str, deny: &[String]) -> Result<String, FilesError> {
+    let mut segs = Vec::new();
+    for seg in path.split('/') {
+        if seg.is_empty() || seg == "." {
+            continue;
+        }
+        if seg == ".." || seg.contains('\\') || seg.contains('\0') {
+            return Err(FilesError::Invalid(path.to_string()));
+        }
+        if is_denied(seg, deny) {
+            return Err(FilesError::Denied(path.to_string()));
+        }
+        segs.push(seg);
+    }
+    match segs.first() {
+        None => Ok(String::new()),
+        Some(&WORKSPACES) => Ok(segs[1..].join("/")),
+        Some(_) => Err(FilesError::Invalid(path.to_string())),
+    }
+}
+
+/// `rel` (relative to the workspaces root) as the META3_ROOT-relative path callers see.
+fn shown(rel: &str) -> String {
+    if rel.is_empty() {
+        WORKSPACES.to_string()
+    } else {
+        format!("{}/{}", WORKSPACES, rel)
+    }
+}
+
+/// `rel` under `root`, after symlinks, refusing anything that ends up outside it or at a
+/// denied name.
+fn resolve(root: &Path, rel: &str, deny: &[String]) -> Result<PathBuf, FilesError> {
+    let joined = root.join(rel);
+    let real = joined
+        .canonicalize()
+        .map_err(|_| FilesError::NotFound(shown(rel)))?;
+    let real_root = root
+        .canonicalize()
+        .map_err(|_| FilesError::NotFound(shown(rel)))?;
+    let Ok(inside) = real.strip_prefix(&real_root) else {
+        return Err(FilesError::Invalid(shown(rel)));
+    };
+    if inside
+        .components()
+        .any(|c| c.as_os_str().to_str().is_none_or(|n| is_denied(n, deny)))
+    {
+        return Err(FilesError::Denied(shown(rel)));
+    }
+    Ok(real)
+}
+
+fn mtime_of(meta: &std::fs::Metadata) -> Option<String> {
+    meta.modified()
+        .ok()
+        .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+}
+
+fn entry_for(rel: String, path: &Path) -> Option<FileEntry> {
+    let link = std::fs::symlink_metadata(path).ok()?;
+    let kind = if link.file_type().is_symlink() {
+        EntryKind::Symlink
+    } else if link.is_dir() {
+        EntryKind::Dir
+    } else {
+        EntryKind::File
+    };
+    let name = rel.rsplit('/').next().unwrap_or(&rel).to_string();
+    Some(FileEntry {
+        path: shown(&rel),
+        name,
+        kind,
+        bytes: if kind == EntryKind::File {
+            link.len()
+        } else {
+            0
+        },
+        mtime: mtime_of(&link),
+    })
+}
+
+/// List `path` (under `workspaces/`, whose directory is `root`): its children, or with `glob`
+/// every entry below it whose path (relative to `path`) matches.
+pub fn list_in(
+    root: &Path,
+    path: &str,
+    glob: Option<&str>,
+    offset: usize,
+    limit: usize,
+    deny: &[String],
+) -> Result<FilesPage, FilesError> {
+    let rel = normalize(path, deny)?;
+    if rel.is_empty() && !root.is_dir() {
+        return Ok(FilesPage {
+            path: shown(&rel),
+            glob: glob.map(|g| g.to_string()),
+            entries: Vec::new(),
+            total: 0,
+            offset,
+            next_offset: None,
+            truncated: false,
+        });
+    }
+    let dir = resolve(root, &rel, deny)?;
+    if !dir.is_dir() {
+        return Err(FilesError::Invalid(format!(
+            "{} is not a directory",
+            shown(&rel)
+        )));
+    }
+    let prefix = |name: &str| {
+        if rel.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", rel, name)
+        }
+    };
+    let mut truncated = false;
+    let mut entries: Vec<FileEntry> = match glob {
+        None => std::fs::read_dir(&dir)
+            .map_err(|e| FilesError::Io(e.to_string()))?
+            .flatten()
+            .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+            .filter(|name| !is_denied(name, deny))
+            .filter_map(|name| entry_for(prefix(&name), &dir.join(&name)))
+            .collect(),
+        Some(pattern) => {
+            let mut out = Vec::new();
+            let walk = WalkDir::new(&dir)
+                .min_depth(1)
+                .into_iter()
+                .filter_entry(|e| !e.file_name().to_str().is_some_and(|n| is_denied(n, deny)));
+            for (seen, e) in walk.flatten().enumerate() {
+                if seen >= MAX_SCAN {
+                    truncated = true;
+                    break;
+                }
+                let Ok(sub) = e.path().strip_prefix(&dir) else {
+                    continue;
+                };
+                let sub = sub.to_string_lossy().replace('\\', "/");
+                if glob_match(pattern, &sub) {
+                    out.extend(entry_for(prefix(&sub), e.path()));
+                }
+            }
+            out
+        }
+    };
+    if glob.is_some() {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+    } else {
+        entries.sort_by(|a, b| {
+            (b.kind == EntryKind::Dir)
+                .cmp(&(a.kind == EntryKind::Dir))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+    let total = entries.len();
+    let page: Vec<FileEntry> = entries.into_iter().skip(offset).take(limit).collect();
+    let next = offset + page.len();
+    Ok(FilesPage {
+        path: shown(&rel),
+        glob: glob.map(|g| g.to_string()),
+        entries: page,
+        total,
+        offset,
+        next_offset: (next < total).then_some(next),
+        truncated,
+    })
+}
+
+/// The first `max_bytes` of file `path` (under `workspaces/`, whose directory is `root`),
+/// redacted.
+pub fn preview_in(
+    root: &Path,
+    path: &str,
+    max_bytes: usize,
+    deny: &[String],
+) -> Result<FilePreview, FilesError> {
+    let rel = normalize(path, deny)?;
+    let path = resolve(root, &rel, deny)?;
+    let meta = std::fs::metadata(&path).map_err(|e| FilesError::Io(e.to_string()))?;
+    if !meta.is_file() {
+        return Err(FilesError::Invalid(format!(
+            "{} is not a file",
+            shown(&rel)
+        )));
+    }
+    let mut buf = Vec::with_capacity(max_bytes.min(meta.len() as usize));
+    std::fs::File::open(&path)
+        .and_then(|f| f.take(max_bytes as u64).read_to_end(&mut buf))
+        .map_err(|e| FilesError::Io(e.to_string()))?;
+    let binary = buf.contains(&0);
+    let (text, redactions) = if binary {
+        (String::new(), 0)
+    } else {
+        redact_counted(&String::from_utf8_lossy(&buf))
+    };
+    Ok(FilePreview {
+        content_type: crate::artifacts::content_type_for(&path).to_string(),
+        path: shown(&rel),
+        bytes: meta.len(),
+        mtime: mtime_of(&meta),
+        truncated: meta.len() > buf.len() as u64,
+        binary,
+        redactions,
+        text,
+    })
+}
+
+pub fn list(
+    path: &str,
+    glob: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> Result<FilesPage, FilesError> {
+    let cfg = load_config();
+    list_in(&workspaces_root(), path, glob, offset, limit, &cfg.deny)
+}
+
+pub fn preview(path: &str) -> Result<FilePreview, FilesError> {
+    let cfg = load_config();
+    preview_in(&workspaces_root(), path, cfg.preview_max_bytes, &cfg.deny)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn listings_hide_denied_files_and_previews_are_capped_and_redacted() {
+        let root = std::env::temp_dir().join(format!("workspace-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("repo/docs")).unwrap();
+        std::fs::create_dir_all(root.join("repo/.git")).unwrap();
+        std::fs::write(root.join("repo/.env"), "OPENAI_API_KEY=sk-abcdefghijklmnop").unwrap();
+        std::fs::write(root.join("repo/server.key"), "-----BEGIN").unwrap();
+        std::fs::write(root.join("repo/.git/config"), "[core]").unwrap();
+        std::fs::write(
+            root.join("repo/README.md"),
+            "# Repo\nkey sk-abcdefghijklmnop\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("repo/docs/a.md"), "a").unwrap();
+        std::fs::write(root.join("repo/docs/b.txt"), "b").unwrap();
+        let deny = vec!["*.txt".to_string()];
+
+        let page = list_in(&root, "workspaces/repo", None, 0, 10, &deny).unwrap();
+        let names: Vec<&str> = page.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["docs", "README.md"]);
+        assert_eq!(page.entries[1].path, "workspaces/repo/README.md");
+
+        let md = list_in(&root, "/workspaces/repo/", Some("**/*.md"), 0, 1, &deny).unwrap();
+        assert_eq!((md.total, md.next_offset), (2, Some(1)));
+        assert_eq!(md.entries[0].path, "workspaces/repo/README.md");
+        let rest = list_in(&root, "workspaces/repo", Some("**/*.md"), 1, 1, &deny).unwrap();
+        assert_eq!(rest.entries[0].path, "workspaces/repo/docs/a.md");
+        assert_eq!(rest.next_offset, None);
+
+        assert!(matches!(
+            list_in(&root, "workspaces/repo/../..", None, 0, 10, &deny),
+            Err(FilesError::Invalid(_))
+        ));
+        assert!(matches!(
+            preview_in(&root, "workspaces/repo/.env", 1024, &deny),
+            Err(FilesError::Denied(_))
+        ));
+        assert!(matches!(
+            preview_in(&root, "workspaces/repo/.git/config", 1024, &deny),
+            Err(FilesError::Denied(_))
+        ));
+
+        let p = preview_in(&root, "workspaces/repo/README.md", 1024, &deny).unwrap();
+        assert!(!p.truncated && p.redactions == 1 && !p.text.contains("sk-abc"));
+        let short = preview_in(&root, "workspaces/repo/README.md", 6, &deny).unwrap();
+        assert!(short.truncated && short.text == "# Repo");
+        assert!(matches!(
+            list_in(&root, "repo", None, 0, 10, &deny),
+            Err(FilesError::Invalid(_))
+        ));
+
+        // A symlink with a harmless name is checked where it leads.
+        #[cfg(unix)]
+        {
+            let outside = root.with_extension("outside");
+            std::fs::write(&outside, "secret").unwrap();
+            std::os::unix::fs::symlink(root.join("repo/.env"), root.join("repo/notes.md")).unwrap();
+            std::os::unix::fs::symlink(root.join("repo/.git"), root.join("repo/vcs")).unwrap();
+            std::os::unix::fs::symlink(&outside, root.join("repo/out.md")).unwrap();
+            for (path, denied) in [
+                ("workspaces/repo/notes.md", true),
+                ("workspaces/repo/vcs/config", true),
+                ("workspaces/repo/out.md", false),
+            ] {
+                let res = preview_in(&root, path, 1024, &deny);
+                assert!(match res {
+                    Err(FilesError::Denied(_)) => denied,
+                    Err(FilesError::Invalid(_)) => !denied,
+                    _ => false,
+                });
+            }
+            std::fs::remove_file(&outside).unwrap();
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
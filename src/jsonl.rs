@@ -0,0 +1,428 @@
+//! Streaming line access for append-only JSONL files (threads, api_trace, telemetry, logs).
+//!
+//! [`RevLines`] walks a file backwards from EOF in [`CHUNK`]-sized seeks, so tailing the last
+//! few hundred events of a multi-hundred-MB log holds one chunk plus the lines kept, not the
+//! whole window. [`Lines`] is the forward counterpart. Both decode each line lossily: bad
+//! UTF-8 is replaced, never an error.
+//!
+//! The line rules are those of [`crate::tail::split_tail`]: with a `max_bytes` window, a line
+//! that starts before the window is partial and dropped, and blank lines are skipped.
+//!
+//! Thread files rotate by size into `<thread>.jsonl.<n>` segments ([`rotate_if_larger`]);
+//! [`tail_segments`] reads across them as if they were still one file. Live files always end
+//! in `.jsonl`, so no thread id can be mistaken for a segment of another.
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::tail::window_start;
+
+/// Bytes read per backwards seek.
+pub const CHUNK: usize = 64 * 1024;
+
+/// Lines of a reader from the end backwards (newest first), limited to the last `max_bytes`.
+pub struct RevLines<R> {
+    reader: R,
+    /// Lowest offset to read; one byte before the window when `truncated`.
+    start: u64,
+    truncated: bool,
+    /// Next (backwards) read ends here.
+    pos: u64,
+    /// Bytes read but not yet returned, in file order; never holds more than one `\n`-free
+    /// prefix plus the last chunk.
+    pending: Vec<u8>,
+    /// No line returned yet: the next one ends at EOF rather than at a `\n`.
+    at_eof: bool,
+    chunk: usize,
+    done: bool,
+}
+
+impl<R: Read + Seek> RevLines<R> {
+    pub fn new(mut reader: R, max_bytes: u64) -> io::Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        let (start, truncated) = window_start(len, max_bytes);
+        Ok(RevLines {
+            reader,
+            start,
+            truncated,
+            pos: len,
+            pending: Vec::new(),
+            at_eof: true,
+            chunk: CHUNK,
+            done: false,
+        })
+    }
+
+    #[cfg(test)]
+    fn with_chunk(mut self, chunk: usize) -> Self {
+        self.chunk = chunk.max(1);
+        self
+    }
+
+    fn emit(&mut self, line: &[u8]) -> Option<String> {
+        let terminated = !std::mem::replace(&mut self.at_eof, false);
+        let s = if terminated {
+            strip_eol(line)
+        } else {
+            String::from_utf8_lossy(line).into_owned()
+        };
+        (!s.trim().is_empty()).then_some(s)
+    }
+
+    fn read_back(&mut self) -> io::Result<()> {
+        let n = (self.pos - self.start).min(self.chunk as u64) as usize;
+        self.pos -= n as u64;
+        self.reader.seek(SeekFrom::Start(self.pos))?;
+        let mut chunk = vec![0u8; n];
+        self.reader.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&self.pending);
+        self.pending = chunk;
+        Ok(())
+    }
+}
+
+impl RevLines<File> {
+    pub fn open(path: &Path, max_bytes: u64) -> io::Result<Self> {
+        RevLines::new(File::open(path)?, max_bytes)
+    }
+}
+
+impl<R: Read + Seek> Iterator for RevLines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+            if let Some(i) = self.pending.iter().rposition(|b| *b == b'\n') {
+                let line = self.pending.split_off(i + 1);
+                self.pending.truncate(i);
+                match self.emit(&line) {
+                    Some(s) => return Some(Ok(s)),
+                    None => continue,
+                }
+            }
+            if self.pos == self.start {
+                // What is left starts at `start`: a whole line only if nothing was cut.
+                self.done = true;
+                let line = std::mem::take(&mut self.pending);
+                return if self.truncated {
+                    None
+                } else {
+                    self.emit(&line).map(Ok)
+                };
+            }
+            if let Err(e) = self.read_back() {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Lines of a buffered reader in file order, blank lines included so line numbers stay right.
+pub struct Lines<R> {
+    reader: R,
+    buf: Vec<u8>,
+    consumed: u64,
+    complete_only: bool,
+}
+
+impl<R: BufRead> Lines<R> {
+    pub fn new(reader: R) -> Self {
+        Lines {
+            reader,
+            buf: Vec::new(),
+            consumed: 0,
+            complete_only: false,
+        }
+    }
+
+    /// Only `\n`-terminated lines: a partial last line (a writer mid-append) is left for the
+    /// next read, which resumes at [`Lines::consumed`].
+    pub fn complete(reader: R) -> Self {
+        Lines {
+            complete_only: true,
+            ..Lines::new(reader)
+        }
+    }
+
+    /// Bytes of the lines returned so far, terminators included.
+    pub fn consumed(&self) -> u64 {
+        self.consumed
+    }
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buf.clear();
+        let n = match self.reader.read_until(b'\n', &mut self.buf) {
+            Ok(0) => return None,
+            Ok(n) => n as u64,
+            Err(e) => return Some(Err(e)),
+        };
+        if self.buf.ends_with(b"\n") {
+            self.consumed += n;
+            Some(Ok(strip_eol(&self.buf)))
+        } else if self.complete_only {
+            None
+        } else {
+            self.consumed += n;
+            Some(Ok(String::from_utf8_lossy(&self.buf).into_owned()))
+        }
+    }
+}
+
+/// Every line of `path`, streamed.
+pub fn lines(path: &Path) -> io::Result<Lines<BufReader<File>>> {
+    Ok(Lines::new(BufReader::new(File::open(path)?)))
+}
+
+/// The last `limit` non-blank lines within the last `max_bytes` of `path`, oldest first.
+pub fn tail(path: &Path, limit: usize, max_bytes: u64) -> io::Result<Vec<String>> {
+    let mut out = RevLines::open(path, max_bytes)?
+        .take(limit)
+        .collect::<io::Result<Vec<_>>>()?;
+    out.reverse();
+    Ok(out)
+}
+
+/// `<dir>/<name>.jsonl.<n>`: the `n`th rotated segment of `<dir>/<name>.jsonl` (1 is the oldest).
+pub fn segment_path(path: &Path, n: u32) -> PathBuf {
+    let name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+    path.with_file_name(format!("{name}.{n}"))
+}
+
+/// Numbers of the rotated segments of `path` that exist, ascending.
+fn segment_numbers(path: &Path) -> Vec<u32> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name().and_then(|s| s.to_str())) else {
+        return Vec::new();
+    };
+    let prefix = format!("{name}.");
+    let mut out: Vec<u32> = fs::read_dir(dir)
+        .map(|rd| {
+            rd.flatten()
+                .filter_map(|e| {
+                    let name = e.file_name().into_string().ok()?;
+                    let n = name.strip_prefix(&prefix)?;
+                    if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+                        return None;
+                    }
+                    n.parse().ok()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    out.sort_unstable();
+    out
+}
+
+/// `path` followed by its rotated segments, newest first.
+pub fn segments(path: &Path) -> Vec<PathBuf> {
+    let mut out = vec![path.to_path_buf()];
+    out.extend(
+        segment_numbers(path)
+            .into_iter()
+            .rev()
+            .map(|n| segment_path(path, n)),
+    );
+    out
+}
+
+/// Whether `path` is a rotated segment (`<name>.jsonl.<n>`) rather than a file of its own.
+/// Directory listings of threads skip these.
+pub fn is_rotated_segment(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+        return false;
+    };
+    match name.rsplit_once('.') {
+        Some((live, n)) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => {
+            live.len() > ".jsonl".len() && live.ends_with(".jsonl")
+        }
+        _ => false,
+    }
+}
+
+/// Move `path` to its next segment once it has reached `max_bytes`, leaving an empty live
+/// file behind. Callers hold the file's append lock. Returns the new segment, if any.
+pub fn rotate_if_larger(path: &Path, max_bytes: u64) -> io::Result<Option<PathBuf>> {
+    match fs::metadata(path) {
+        Ok(m) if m.len() >= max_bytes && m.len() > 0 => {}
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let next = segment_numbers(path).last().map_or(1, |n| n + 1);
+    let seg = segment_path(path, next);
+    fs::rename(path, &seg)?;
+    File::create(path)?;
+    Ok(Some(seg))
+}
+
+/// [`tail`] across `path` and its rotated segments: the last `limit` lines within the last
+/// `max_bytes` of the thread as a whole. Segments end on line boundaries, so only the oldest
+/// segment read can have a partial line cut off.
+pub fn tail_segments(path: &Path, limit: usize, max_bytes: u64) -> io::Result<Vec<String>> {
+    let segs = segments(path);
+    let mut out = Vec::new();
+    let mut budget = max_bytes;
+    for seg in &segs {
+        if out.len() >= limit || budget == 0 {
+            break;
+        }
+        let len = match fs::metadata(seg) {
+            Ok(m) => m.len(),
+            // The live file may be missing while older segments exist.
+            Err(e) if e.kind() == io::ErrorKind::NotFound && segs.len() > 1 => continue,
+            Err(e) => return Err(e),
+        };
+        for line in RevLines::open(seg, budget)?.take(limit - out.len()) {
+            out.push(line?);
+        }
+        budget = budget.saturating_sub(len);
+    }
+    out.reverse();
+    Ok(out)
+}
+
+/// A `\n`-terminated line without its `\n` or `\r\n`, as `str::lines` splits them.
+fn strip_eol(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tail::split_tail;
+    use proptest::prelude::*;
+    use std::io::Cursor;
+
+    fn rev_tail(file: &[u8], limit: usize, max_bytes: u64, chunk: usize) -> Vec<String> {
+        let mut out: Vec<String> = RevLines::new(Cursor::new(file), max_bytes)
+            .unwrap()
+            .with_chunk(chunk)
+            .take(limit)
+            .map(|l| l.unwrap())
+            .collect();
+        out.reverse();
+        out
+    }
+
+    proptest! {
+        /// Streaming and in-memory tails agree on every window and chunk size, including
+        /// windows that start inside a line or a multi-byte character.
+        #[test]
+        fn matches_split_tail(
+            file in proptest::collection::vec(
+                prop_oneof![Just(b'\n'), Just(b'\r'), Just(b' '), any::<u8>()], 0..256),
+            max_bytes in 0u64..300,
+            limit in 0usize..20,
+            chunk in 1usize..40,
+        ) {
+            let (start, truncated) = window_start(file.len() as u64, max_bytes);
+            let expected = split_tail(&file[start as usize..], truncated, limit);
+            prop_assert_eq!(rev_tail(&file, limit, max_bytes, chunk), expected);
+        }
+    }
+
+    #[test]
+    fn lines_longer_than_a_chunk_survive() {
+        let long = "x".repeat(CHUNK * 2 + 17);
+        let file = format!("{{\"a\":1}}\n{long}\n{{\"b\":2}}\n");
+        assert_eq!(
+            rev_tail(file.as_bytes(), 10, u64::MAX, CHUNK),
+            vec!["{\"a\":1}".to_string(), long, "{\"b\":2}".to_string()]
+        );
+    }
+
+    #[test]
+    fn forward_lines_keep_blanks_and_strip_crlf() {
+        let got: Vec<String> = Lines::new(Cursor::new(&b"a\r\n\nb\xff"[..]))
+            .map(|l| l.unwrap())
+            .collect();
+        assert_eq!(got, vec!["a", "", "b\u{fffd}"]);
+    }
+
+    #[test]
+    fn complete_lines_stop_before_a_partial_append() {
+        let mut it = Lines::complete(Cursor::new(&b"{\"a\":1}\r\n\n{\"b\":"[..]));
+        let got: Vec<String> = it.by_ref().map(|l| l.unwrap()).collect();
+        assert_eq!(got, vec!["{\"a\":1}", ""]);
+        assert_eq!(it.consumed(), 10);
+    }
+
+    #[test]
+    fn tail_reads_across_rotated_segments() {
+        let dir = std::env::temp_dir()
+            .join("one-engine-jsonl")
+            .join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("t.jsonl");
+        for i in 0..30 {
+            if rotate_if_larger(&path, 40).unwrap().is_some() {
+                assert_eq!(fs::metadata(&path).unwrap().len(), 0);
+            }
+            crate::atomic::append(&path, &format!("{{\"n\":{i}}}")).unwrap();
+        }
+        // A thread whose id looks like an old-style segment number is a thread of its own.
+        let other = dir.join("t.1.jsonl");
+        crate::atomic::append(&other, "{\"other\":true}").unwrap();
+        let segs = segments(&path);
+        assert!(segs.len() > 3);
+        assert!(!segs.contains(&other));
+        assert!(segs[1..].iter().all(|p| is_rotated_segment(p)));
+        assert!(!is_rotated_segment(&path) && !is_rotated_segment(&other));
+
+        let want: Vec<String> = (0..30).map(|i| format!("{{\"n\":{i}}}")).collect();
+        assert_eq!(tail_segments(&path, 100, u64::MAX).unwrap(), want);
+        assert_eq!(tail_segments(&path, 7, u64::MAX).unwrap(), want[23..]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Budgets are for `--release` on a laptop; slower CI boxes set `ONE_ENGINE_BUDGET_SCALE`.
+    fn budget(ms: u64) -> std::time::Duration {
+        let scale: f64 = std::env::var("ONE_ENGINE_BUDGET_SCALE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1.0);
+        std::time::Duration::from_millis((ms as f64 * scale) as u64)
+    }
+
+    /// A 2 GiB thread (sparse, so free on disk) costs the same to tail as a small one.
+    #[test]
+    #[ignore = "performance budget; run with `make bench`"]
+    fn budget_tail_2gb() {
+        use std::io::Write;
+        let dir =
+            std::env::temp_dir().join(format!("one-engine-budget-jsonl-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("big.jsonl");
+        let lines: Vec<String> = (0..10_000)
+            .map(|i| serde_json::json!({"n": i, "content": "x".repeat(80)}).to_string())
+            .collect();
+        let mut f = File::create(&path).unwrap();
+        f.set_len(2 << 30).unwrap();
+        f.seek(SeekFrom::End(0)).unwrap();
+        f.write_all(b"\n").unwrap();
+        for l in &lines {
+            writeln!(f, "{l}").unwrap();
+        }
+        drop(f);
+
+        let t = std::time::Instant::now();
+        for _ in 0..10 {
+            let got = tail(&path, 800, 1_200_000).unwrap();
+            assert_eq!(got.len(), 800);
+            assert_eq!(got.last(), lines.last());
+        }
+        let took = t.elapsed();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(took < budget(250), "10 tails took {:?}", took);
+    }
+}
@@ -1 +1,6 @@
+pub mod atomic;
+pub mod jsonl;
+pub mod redact;
 pub mod research;
+pub mod storage;
+pub mod tail;
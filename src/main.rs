@@ -1,8 +1,17 @@
 mod api;
+mod artifacts;
+mod bundle;
+mod context;
+mod cors;
 mod engine;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod graphql;
 mod integrations;
+mod mcp;
 mod meta;
 mod nstar;
+mod nstar_policy;
 
 use axum::http::StatusCode;
 use axum::{
@@ -13,6 +22,10 @@ use axum::{
 };
 use std::path::PathBuf;
 use tokio::net::TcpListener;
+use tower_http::compression::{
+    predicate::{DefaultPredicate, NotForContentType, Predicate},
+    CompressionLayer,
+};
 use tower_http::services::ServeDir;
 use tracing_subscriber::{fmt, EnvFilter};
 use utoipa::OpenApi;
@@ -52,40 +65,107 @@ fn load_dotenv_if_present() {
     }
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+/// Server pieces the engine calls back into (see `engine::hooks`).
+fn register_engine_hooks() {
+    engine::hooks::set_receipt_sink(std::sync::Arc::new(api::ToolCallReceipts));
+    engine::hooks::set_world_renderer(std::sync::Arc::new(nstar::Worlds));
+    engine::hooks::set_child_runner(std::sync::Arc::new(api::ChildRuns));
+}
+
+/// gzip/deflate for JSON, HTML and logs. The default predicate already skips tiny bodies,
+/// images, gRPC and `text/event-stream` (SSE must stay unbuffered); archives are skipped too.
+/// Responses that already carry `Content-Encoding` (pre-compressed `.gz` artifacts) pass through.
+fn compression_layer() -> CompressionLayer<impl Predicate> {
+    let predicate = DefaultPredicate::new()
+        .and(NotForContentType::const_new("application/gzip"))
+        .and(NotForContentType::const_new("application/zip"));
+    CompressionLayer::new()
+        .gzip(true)
+        .deflate(true)
+        .compress_when(predicate)
+}
+
+fn main() -> anyhow::Result<()> {
+    // Everything that sets environment variables (.env, simulation's META3_ROOT) runs here,
+    // before the runtime starts worker threads that read them.
     load_dotenv_if_present();
 
     let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    fmt().with_env_filter(env_filter).init();
+    if std::env::args().nth(1).as_deref() == Some("migrate-receipts") {
+        // Maintenance command: upgrade stored receipts, print a JSON report and exit.
+        fmt().with_env_filter(env_filter).with_writer(std::io::stderr).init();
+        let dry_run = std::env::args().any(|a| a == "--dry-run");
+        let root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
+        let report = engine::migrate::migrate_receipts(&root, dry_run)?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        if !report.failed.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let mcp_stdio = std::env::args().any(|a| a == "--mcp-stdio");
+    if mcp_stdio {
+        // stdout carries the protocol; logs go to stderr.
+        fmt().with_env_filter(env_filter).with_writer(std::io::stderr).init();
+    } else {
+        fmt().with_env_filter(env_filter).init();
+    }
+    // Simulation moves META3_ROOT to its sandbox, so it goes first.
+    engine::simulation::init();
+    // Before anything writes: a read-only META3_ROOT switches writes to the fallback store.
+    one_engine::storage::init();
+    register_engine_hooks();
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    if mcp_stdio {
+        return runtime.block_on(async { mcp::serve_stdio(api::AppState::default()).await });
+    }
+    runtime.block_on(serve())
+}
 
+async fn serve() -> anyhow::Result<()> {
     let state = api::AppState::default();
+    #[cfg(feature = "grpc")]
+    let grpc_state = state.clone();
+    integrations::codex::spawn_background_import();
+    let declared = engine::goals::declared::load();
+    if declared > 0 {
+        tracing::info!("loaded {} declared goal(s) from config/goals.d", declared);
+    }
+    #[cfg(feature = "wasm")]
+    {
+        let plugins = engine::plugins::load();
+        if plugins > 0 {
+            tracing::info!("loaded {} WASM goal plugin(s)", plugins);
+        }
+    }
     let openapi = api::ApiDoc::openapi();
     let enable_swagger = std::env::var("ENABLE_SWAGGER").ok().as_deref() == Some("1");
 
     let meta_root = PathBuf::from(std::env::var("META3_ROOT").unwrap_or_else(|_| ".".to_string()));
     let docs_root = meta_root.join("docs");
-    let runs_root = meta_root.join("runs");
 
     let docs_service = get_service(ServeDir::new(docs_root))
         .handle_error(|_| async move { (StatusCode::INTERNAL_SERVER_ERROR, "static file error") });
 
-    let runs_service = get_service(ServeDir::new(runs_root))
-        .handle_error(|_| async move { (StatusCode::INTERNAL_SERVER_ERROR, "static file error") });
-
     let ui_service = get_service(ServeDir::new("ui").append_index_html_on_directories(true))
         .handle_error(|_| async move { (StatusCode::INTERNAL_SERVER_ERROR, "static file error") });
 
-    let mut app = Router::new()
-
-        .route("/", get(|| async { Redirect::temporary("/ui/") }))
+    let mut core_routes = Router::new()
+        .route(
+            "/",
+            get(|| async { Redirect::temporary(&engine::urls::url_for("/ui/")) }),
+        )
         .route(
             "/terminal",
-            get(|| async { Redirect::temporary("/ui/") }),
+            get(|| async { Redirect::temporary(&engine::urls::url_for("/ui/")) }),
         )
         .route("/health", get(|| async { "ok" }))
         .route("/healthz", get(api::healthz_handler))
         .route("/version", get(api::version_handler))
+        .route("/goals", get(api::goals_catalog_handler))
         .route("/metrics", get(api::metrics_handler))
         .route("/tau", post(api::tau_handler))
         .route("/execute", post(api::execute_handler))
@@ -95,17 +175,33 @@ async fn main() -> anyhow::Result<()> {
         .route("/patterns/:pattern_id", get(api::pattern_detail_handler))
         .route("/seed", get(api::seed_handler))
         .route("/config", get(api::config_handler))
+        .route("/secrets", get(api::secrets_handler))
         .route("/run", post(api::run_handler))
         .route("/run.async", post(api::run_async_handler))
         .route("/runs.active.json", get(api::runs_active_json_handler))
         .route("/ruliad/:run_id", get(api::ruliad_list_handler))
         .route("/ruliad/:run_id/:file", get(api::ruliad_file_handler))
         .route("/validate", post(api::validate_handler))
+        .route("/validate/suites", get(api::validate_suites_handler))
         .route("/validate_golden", post(api::validate_golden_handler))
         .route("/golden/:name", get(api::golden_handler))
         .route("/dashboard", get(api::dashboard_handler))
+        .route(
+            "/bits/calibration",
+            get(api::bits_calibration_handler).post(api::bits_calibration_write_handler),
+        )
         .route("/planning", get(api::planning_handler))
+        .route("/telemetry", post(api::telemetry_ingest_handler))
+        .route("/telemetry/query", get(api::telemetry_query_handler))
+        .route("/api_trace/query", get(api::api_trace_query_handler))
+        .route("/correlations/:id", get(api::correlations_handler))
+        .route("/audit", get(api::audit_handler))
+        .route("/admin/flags", get(api::flags_handler).post(api::set_flag_handler))
+        .route("/prompts", get(api::prompts_handler))
+        .route("/experiments/:id/report", get(api::experiment_report_handler))
         .route("/research/index", get(api::research_index_handler))
+        .route("/files", get(api::files_handler))
+        .route("/files/preview", get(api::files_preview_handler))
         .route("/codex/sources", get(api::codex_sources_handler))
         .route("/codex/archive", get(api::codex_archive_handler))
         .route("/codex/rollouts", get(api::codex_rollouts_list_handler))
@@ -115,33 +211,83 @@ async fn main() -> anyhow::Result<()> {
         )
         .route("/codex/capabilities", get(api::codex_capabilities_handler))
         .route("/codex/search", get(api::codex_search_handler))
+        .route("/codex/sessions", get(api::codex_sessions_handler))
+        .route("/codex/index", get(api::codex_index_handler))
         .route("/browse", get(api::browse_handler))
         .route("/browse.json", get(api::browse_json_handler))
         .route("/nudges", get(api::nudges_handler))
         .route("/nudges.json", get(api::nudges_json_handler))
+        .route("/nudges/:id/dismiss", post(api::nudge_dismiss_handler))
         .nest_service("/ui", ui_service)
         .nest_service("/docs", docs_service)
-        .nest_service("/runs", runs_service)
-        // Multi-tenant user endpoints
+        // Artifacts under META3_ROOT/runs (ETag / Last-Modified / Range aware)
+        .route(
+            "/runs/*path",
+            get(api::runs_artifact_handler).post(api::runs_action_handler),
+        )
+        .route("/nstar/run", post(nstar::nstar_run_handler))
+        .route("/nstar/hud", get(nstar::nstar_hud_handler))
+        .route("/nstar/policy", get(nstar_policy::nstar_policy_handler))
+        .route("/meta/run", post(meta::meta_run_handler))
+        .route("/meta/state", get(meta::meta_state_handler))
+        .route("/meta/reset", post(meta::meta_reset_handler))
+        .route("/meta/history", get(meta::meta_history_handler))
+        .route("/v1/context/resolve", post(nstar::resolve_context_handler))
+        .route("/context/staleness", get(context::staleness_handler))
+        .route("/graphql", {
+            let schema = graphql::schema(state.clone());
+            let gql_state = state.clone();
+            get(graphql::graphiql).post(
+                move |headers: axum::http::HeaderMap, req: async_graphql_axum::GraphQLRequest| {
+                    graphql::execute(schema.clone(), gql_state.clone(), headers, req)
+                },
+            )
+        });
+    if let Some(l) = cors::layer_for(cors::CorsScope::Default) {
+        core_routes = core_routes.layer(l);
+    }
+
+    // Multi-tenant user endpoints (own CORS scope: SPA dashboards send x-api-key)
+    let mut user_routes = Router::new()
         .route("/users/:user_id/run", post(api::user_run_handler))
         .route("/users/:user_id/chat", post(api::user_chat_handler))
         .route(
             "/users/:user_id/threads/:thread/attach_run",
             post(api::user_thread_attach_run_handler),
         )
+        .route(
+            "/users/:user_id/threads/:thread/feedback",
+            post(api::user_thread_feedback_handler),
+        )
         .route(
             "/users/:user_id/threads/:thread/summary",
             get(api::user_thread_summary_handler),
         )
-        .route("/progress.sse", get(api::progress_sse_handler))
         .route("/users/:user_id/status", get(api::user_status_handler))
-        .route("/nstar/run", post(nstar::nstar_run_handler))
-        .route("/nstar/hud", get(nstar::nstar_hud_handler))
-        .route("/meta/run", post(meta::meta_run_handler))
-        .route("/meta/state", get(meta::meta_state_handler))
-        .route("/meta/reset", post(meta::meta_reset_handler))
-        .route("/v1/context/resolve", post(nstar::resolve_context_handler))
+        .route("/users/:user_id/usage", get(api::user_usage_handler))
+        .route(
+            "/users/:user_id/policy",
+            get(api::user_policy_get_handler).put(api::user_policy_put_handler),
+        );
+    if let Some(l) = cors::layer_for(cors::CorsScope::Users) {
+        user_routes = user_routes.layer(l);
+    }
+
+    let mut sse_routes = Router::new()
+        .route("/progress.sse", get(api::progress_sse_handler))
+        .route("/progress/phases", get(api::progress_phases_handler))
+        .route("/mcp/sse", get(mcp::sse_handler))
+        .route("/mcp/messages", post(mcp::messages_handler));
+    if let Some(l) = cors::layer_for(cors::CorsScope::Sse) {
+        sse_routes = sse_routes.layer(l);
+    }
+
+    let mut app = core_routes
+        .merge(user_routes)
+        .merge(sse_routes)
         .layer(middleware::from_fn(api::api_trace_middleware))
+        .layer(compression_layer())
+        .layer(middleware::from_fn(api::forwarded_prefix_middleware))
         .with_state(state);
 
     if enable_swagger {
@@ -157,6 +303,10 @@ async fn main() -> anyhow::Result<()> {
     }
 
     let listener = TcpListener::bind(&addr).await?;
+    tokio::spawn(api::recover_orphaned_runs());
+    #[cfg(feature = "grpc")]
+    tokio::spawn(grpc::serve(grpc_state));
+    integrations::schedule::spawn_all();
     axum::serve(listener, app).await?;
     Ok(())
 }
@@ -0,0 +1,446 @@
+//! Model Context Protocol server: the engine as a tool server for IDE agents.
+//!
+//! - tools: the meta.omni goal catalog (`chat_tools:`), run through [`api::execute_run`] so
+//!   policy resolution, receipts and auditing match `POST /run`
+//! - resources: recent receipts (`receipt://<run_id>`, only runs the key may read, see
+//!   `receipt_acl`) and indexed research artifacts (`research://<path>`)
+//! - notifications: a tool call's progress events, as `notifications/progress` when the
+//!   client sent a `progressToken`, else as `notifications/message`
+//!
+//! Transports: stdio (`one-engine --mcp-stdio`, key from `ONE_ENGINE_MCP_API_KEY`) and
+//! HTTP+SSE (`GET /mcp/sse`, then `POST /mcp/messages?session_id=...`, key from
+//! `x-api-key`). A key selects that user's policy layer and quota, which each tool call
+//! spends one run of; without one, calls run with the anonymous `/run` policy.
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Json,
+};
+use once_cell::sync::Lazy;
+use one_engine::research;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_stream::StreamExt;
+
+use crate::api::{self, AppState, RunReq, UserContext};
+use crate::engine::goals::meta_omni::tool_catalog;
+use crate::engine::urls::url_for;
+use crate::integrations::receipt_acl::{self, Caller};
+use crate::integrations::{progress, run_index};
+
+const PROTOCOL_VERSION: &str = "2024-11-05";
+const MAX_RESOURCES: usize = 50;
+const MAX_RESOURCE_BYTES: usize = 1 << 20;
+
+/// One connected client: where responses and notifications go, and whose policy applies.
+struct Session {
+    out: mpsc::UnboundedSender<Value>,
+    state: AppState,
+    user: Option<UserContext>,
+}
+
+impl Session {
+    /// The session's user as of now: its quota and stored policy may have changed since
+    /// the session opened.
+    fn current_user(&self) -> Option<UserContext> {
+        let user = self.user.as_ref()?;
+        api::authenticate_user(&self.state, &user.api_key).or_else(|| Some(user.clone()))
+    }
+
+    fn caller(&self) -> Caller {
+        self.user
+            .as_ref()
+            .map_or(Caller::Anonymous, |u| Caller::User(u.user_id.clone()))
+    }
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<String, Arc<Session>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Drops the SSE session when its stream goes away (client disconnected).
+struct SessionGuard(String);
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        if let Ok(mut sessions) = SESSIONS.lock() {
+            sessions.remove(&self.0);
+        }
+    }
+}
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+fn is_safe_segment(seg: &str) -> bool {
+    !seg.is_empty()
+        && !seg.contains("..")
+        && seg != "."
+        && seg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn ok(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn err(id: Value, code: i64, message: impl Into<String>) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message.into() } })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn initialize(params: &Value) -> Value {
+    json!({
+        "protocolVersion": params
+            .get("protocolVersion")
+            .and_then(|v| v.as_str())
+            .unwrap_or(PROTOCOL_VERSION),
+        "capabilities": {
+            "tools": { "listChanged": false },
+            "resources": { "subscribe": false, "listChanged": false },
+            "logging": {}
+        },
+        "serverInfo": { "name": "one-engine", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+fn list_tools() -> Value {
+    let tools: Vec<Value> = tool_catalog()
+        .iter()
+        .map(|t| {
+            json!({
+                "name": t.name(),
+                "description": format!("{} (goal `{}`)", t.description, t.goal_id),
+                "inputSchema": t.input_schema()
+            })
+        })
+        .collect();
+    json!({ "tools": tools })
+}
+
+/// Forward `run_id`'s progress events to the session until the run's channel closes.
+fn forward_progress(
+    session: Arc<Session>,
+    run_id: &str,
+    token: Option<Value>,
+) -> tokio::task::JoinHandle<()> {
+    let mut rx = progress::subscribe(run_id);
+    tokio::spawn(async move {
+        let mut n = 0u64;
+        loop {
+            let raw = match rx.recv().await {
+                Ok(raw) => raw,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+            n += 1;
+            let event =
+                serde_json::from_str::<Value>(&raw).unwrap_or_else(|_| json!({ "raw": raw }));
+            let note = match &token {
+                Some(t) => notification(
+                    "notifications/progress",
+                    json!({
+                        "progressToken": t,
+                        "progress": n,
+                        "message": event.get("phase").and_then(|v| v.as_str()).unwrap_or("")
+                    }),
+                ),
+                None => notification(
+                    "notifications/message",
+                    json!({ "level": "info", "logger": "progress", "data": event }),
+                ),
+            };
+            if session.out.send(note).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+async fn call_tool(session: &Arc<Session>, params: &Value) -> Result<Value, String> {
+    let name = params.get("name").and_then(|v| v.as_str()).unwrap_or("");
+    let tool = tool_catalog()
+        .into_iter()
+        .find(|t| t.name() == name)
+        .ok_or_else(|| format!("unknown tool: {}", name))?;
+    let mut user = session.current_user();
+    if user.as_ref().is_some_and(|u| u.quota_remaining == 0) {
+        return Err("Quota exceeded".to_string());
+    }
+    let inputs = params
+        .get("arguments")
+        .filter(|v| v.is_object())
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    let token = params.pointer("/_meta/progressToken").cloned();
+    let run_id = format!("r-{}", uuid::Uuid::new_v4());
+    let forwarder = forward_progress(session.clone(), &run_id, token);
+    let req = RunReq {
+        goal_id: tool.goal_id.clone(),
+        inputs,
+        policy: None,
+        run_id: Some(run_id.clone()),
+        priority: None,
+        depends_on: Vec::new(),
+        on_parent_failure: None,
+    };
+    let result = api::execute_run(req, user.as_ref(), "mcp").await;
+    if let (Ok(_), Some(user)) = (&result, user.as_mut()) {
+        api::spend_quota(user);
+    }
+    // Both paths close the run's channel, so the forwarder drains and stops.
+    let _ = forwarder.await;
+    let receipt = url_for(&format!("/runs/receipts/{}/RECEIPT.md", run_id));
+    Ok(match result {
+        Ok(resp) => {
+            let success = resp
+                .manifest
+                .evidence
+                .get("actual_success")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let mut text = format!(
+                "{} run {}: {}\nreceipt: {}",
+                tool.goal_id,
+                run_id,
+                if success { "ok" } else { "failed" },
+                receipt
+            );
+            for d in &resp.manifest.deliverables {
+                text.push_str(&format!("\n- {}", d));
+            }
+            json!({
+                "content": [{ "type": "text", "text": text }],
+                "structuredContent": resp,
+                "isError": !success
+            })
+        }
+        Err(e) => json!({
+            "content": [{ "type": "text", "text": format!("{} run {} failed: {}", tool.goal_id, run_id, e) }],
+            "isError": true
+        }),
+    })
+}
+
+async fn list_resources(session: &Session) -> Value {
+    let mut runs = run_index::scan(None).await;
+    runs.sort_by_key(|r| std::cmp::Reverse(r.ts));
+    let caller = session.caller();
+    let mut resources: Vec<Value> = Vec::new();
+    for r in &runs {
+        if resources.len() == MAX_RESOURCES {
+            break;
+        }
+        if !receipt_acl::caller_can_read(&r.run_id, &caller).await {
+            continue;
+        }
+        resources.push(json!({
+            "uri": format!("receipt://{}", r.run_id),
+            "name": format!("{} ({})", r.run_id, r.goal_id),
+            "description": format!("Receipt of {} at {}", r.goal_id, r.ts.to_rfc3339()),
+            "mimeType": "text/markdown"
+        }));
+    }
+    let index = research::read_index(Path::new("research/index.jsonl"));
+    resources.extend(index.iter().take(MAX_RESOURCES).map(|a| {
+        json!({
+            "uri": format!("research://{}", a.path),
+            "name": a.id,
+            "description": format!("{} artifact ({})", a.kind, a.tags.join(", ")),
+            "mimeType": "text/plain"
+        })
+    }));
+    json!({ "resources": resources })
+}
+
+async fn read_resource(session: &Session, params: &Value) -> Result<Value, String> {
+    let uri = params.get("uri").and_then(|v| v.as_str()).unwrap_or("");
+    let (path, mime) = if let Some(run_id) = uri.strip_prefix("receipt://") {
+        if !is_safe_segment(run_id) {
+            return Err(format!("invalid run id: {}", run_id));
+        }
+        // Receipts the key may not read look like missing ones, as over HTTP.
+        if !receipt_acl::caller_can_read(run_id, &session.caller()).await {
+            return Err(format!("{}: no such receipt", uri));
+        }
+        (
+            meta3_root()
+                .join("runs/receipts")
+                .join(run_id)
+                .join("RECEIPT.md"),
+            "text/markdown",
+        )
+    } else if let Some(rel) = uri.strip_prefix("research://") {
+        // Only paths the research index lists; never arbitrary files.
+        let index = research::read_index(Path::new("research/index.jsonl"));
+        if !index.iter().any(|a| a.path == rel) {
+            return Err(format!("not in the research index: {}", rel));
+        }
+        (PathBuf::from(rel), "text/plain")
+    } else {
+        return Err(format!("unsupported resource uri: {}", uri));
+    };
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("{}: {}", uri, e))?;
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_RESOURCE_BYTES)]).to_string();
+    Ok(json!({ "contents": [{ "uri": uri, "mimeType": mime, "text": text }] }))
+}
+
+/// Handle one JSON-RPC message; `None` for notifications.
+async fn dispatch(session: &Arc<Session>, msg: Value) -> Option<Value> {
+    let method = msg.get("method").and_then(|v| v.as_str()).unwrap_or("");
+    let params = msg.get("params").cloned().unwrap_or(Value::Null);
+    // Notifications (`notifications/initialized`, `notifications/cancelled`) need no reply.
+    let id = msg.get("id").cloned()?;
+    Some(match method {
+        "initialize" => ok(id, initialize(&params)),
+        "ping" => ok(id, json!({})),
+        "tools/list" => ok(id, list_tools()),
+        "tools/call" => match call_tool(session, &params).await {
+            Ok(result) => ok(id, result),
+            Err(e) => err(id, -32602, e),
+        },
+        "resources/list" => ok(id, list_resources(session).await),
+        "resources/read" => match read_resource(session, &params).await {
+            Ok(result) => ok(id, result),
+            Err(e) => err(id, -32002, e),
+        },
+        "logging/setLevel" => ok(id, json!({})),
+        _ => err(id, -32601, format!("method not found: {}", method)),
+    })
+}
+
+/// Run a message in the background, replying through the session.
+fn spawn_dispatch(session: Arc<Session>, msg: Value) {
+    tokio::spawn(async move {
+        if let Some(reply) = dispatch(&session, msg).await {
+            let _ = session.out.send(reply);
+        }
+    });
+}
+
+fn user_for_key(state: &AppState, key: Option<&str>) -> Result<Option<UserContext>, String> {
+    match key.filter(|k| !k.is_empty()) {
+        None => Ok(None),
+        Some(k) => api::authenticate_user(state, k)
+            .map(Some)
+            .ok_or_else(|| "Invalid API key".to_string()),
+    }
+}
+
+/// Serve MCP over stdin/stdout (one JSON-RPC message per line) until stdin closes.
+pub async fn serve_stdio(state: AppState) -> anyhow::Result<()> {
+    let key = std::env::var("ONE_ENGINE_MCP_API_KEY").ok();
+    let user = user_for_key(&state, key.as_deref())
+        .map_err(|e| anyhow::anyhow!("ONE_ENGINE_MCP_API_KEY: {}", e))?;
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    let session = Arc::new(Session {
+        out: tx,
+        state,
+        user,
+    });
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(v) = rx.recv().await {
+            if stdout
+                .write_all(format!("{}\n", v).as_bytes())
+                .await
+                .is_err()
+            {
+                break;
+            }
+            let _ = stdout.flush().await;
+        }
+    });
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Value>(&line) {
+            Ok(msg) => spawn_dispatch(session.clone(), msg),
+            Err(e) => {
+                let _ = session
+                    .out
+                    .send(err(Value::Null, -32700, format!("parse error: {}", e)));
+            }
+        }
+    }
+    // Let in-flight calls finish: the writer stops once every session handle is gone.
+    drop(session);
+    let _ = writer.await;
+    Ok(())
+}
+
+/// `GET /mcp/sse`: open a session. The first event (`endpoint`) names the URL to POST
+/// messages to; responses and notifications arrive as `message` events.
+pub async fn sse_handler(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+    let user = match user_for_key(&state, key) {
+        Ok(u) => u,
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    let session_id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = mpsc::unbounded_channel::<Value>();
+    if let Ok(mut sessions) = SESSIONS.lock() {
+        let session = Session {
+            out: tx,
+            state,
+            user,
+        };
+        sessions.insert(session_id.clone(), Arc::new(session));
+    }
+    let endpoint = url_for(&format!("/mcp/messages?session_id={}", session_id));
+    let guard = SessionGuard(session_id);
+    let messages = UnboundedReceiverStream::new(rx).map(move |v| {
+        let _session = &guard;
+        Ok::<_, Infallible>(Event::default().event("message").data(v.to_string()))
+    });
+    let stream =
+        tokio_stream::once(Ok(Event::default().event("endpoint").data(endpoint))).chain(messages);
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MessagesQuery {
+    pub session_id: String,
+}
+
+/// `POST /mcp/messages?session_id=...`: accept one JSON-RPC message for an SSE session.
+pub async fn messages_handler(
+    Query(q): Query<MessagesQuery>,
+    Json(msg): Json<Value>,
+) -> impl IntoResponse {
+    let session = SESSIONS
+        .lock()
+        .ok()
+        .and_then(|s| s.get(&q.session_id).cloned());
+    match session {
+        Some(session) => {
+            spawn_dispatch(session, msg);
+            StatusCode::ACCEPTED.into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "unknown session".to_string()).into_response(),
+    }
+}
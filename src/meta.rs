@@ -1,8 +1,26 @@
-use axum::{response::IntoResponse, Json};
+//! Meta selection loop (`/meta/*`) and its event-sourced state.
+//!
+//! Every step appends events to `runs/meta/events.jsonl`: a `decision` (the plan and config the
+//! selector chose), a `strategy_switch` when the plan differs from the previous step's, a
+//! `score_update`, and a `ucb_snapshot` of the selector's state; `POST /meta/reset` appends a
+//! `reset`. [`PersistedMetaState`] is the fold of those events. `runs/meta/state.json` is only a
+//! snapshot of it with the log offset it covers: loading replays whatever was appended after
+//! that offset, so a crash between the append and the snapshot write loses nothing, and a
+//! missing or corrupt snapshot is rebuilt from the log. `GET /meta/state?at=<ts>` replays the
+//! log up to `ts` instead. A `state.json` from before the log existed is carried over as an
+//! `imported` event on the next step.
+
+use axum::{extract::Query, response::IntoResponse, Json};
+use once_cell::sync::Lazy;
+use one_engine::{atomic, jsonl, storage};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::process::Command as TokioCommand;
+use tokio::sync::Mutex;
 use utoipa::ToSchema;
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -22,13 +40,302 @@ pub struct MetaRunResp {
     pub latency_s: f32,
 }
 
+fn api_key_actor(headers: &axum::http::HeaderMap) -> String {
+    crate::integrations::audit::actor_for_key(
+        headers.get("x-api-key").and_then(|v| v.to_str().ok()),
+    )
+}
+
+/// Serializes event appends and the `runs/meta/state.json` snapshot.
+static META_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct StrategyStats {
+    pub count: u32,
+    pub score_sum: f32,
+    pub best_score: f32,
+}
+
+/// Meta state projected from `runs/meta/events.jsonl` (snapshotted to `runs/meta/state.json`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct PersistedMetaState {
+    pub iterations: u64,
+    pub last_run_id: Option<String>,
+    pub last_score: Option<f32>,
+    pub best_score: Option<f32>,
+    /// plan → selection count / scores.
+    pub strategies: BTreeMap<String, StrategyStats>,
+    /// Last copy of the selector's UCB state (`META_STATE`), restored if that file is lost.
+    pub ucb: Option<serde_json::Value>,
+    pub updated_at: Option<String>,
+    /// Plan of the latest decision.
+    #[serde(default)]
+    pub current_strategy: Option<String>,
+    #[serde(default)]
+    pub switches: u32,
+    /// Last event folded in (0: none).
+    #[serde(default)]
+    pub seq: u64,
+    /// Bytes of `events.jsonl` folded in.
+    #[serde(default)]
+    pub offset: u64,
+}
+
+/// One line of `runs/meta/events.jsonl`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct MetaEvent {
+    pub seq: u64,
+    pub ts: String,
+    #[serde(flatten)]
+    pub kind: MetaEventKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MetaEventKind {
+    /// State from a `state.json` written before the event log existed.
+    Imported {
+        state: Box<PersistedMetaState>,
+    },
+    Decision {
+        run_id: String,
+        task: String,
+        plan: String,
+        config: serde_json::Value,
+        latency_s: f32,
+    },
+    StrategySwitch {
+        run_id: String,
+        from: String,
+        to: String,
+    },
+    ScoreUpdate {
+        run_id: String,
+        plan: String,
+        score: f32,
+    },
+    UcbSnapshot {
+        ucb: serde_json::Value,
+    },
+    Reset {
+        archived_to: String,
+    },
+}
+
+/// Fold `ev` into `state`.
+fn apply(state: &mut PersistedMetaState, ev: &MetaEvent) {
+    match &ev.kind {
+        MetaEventKind::Imported { state: imported } => *state = (**imported).clone(),
+        MetaEventKind::Decision { run_id, plan, .. } => {
+            state.iterations += 1;
+            state.last_run_id = Some(run_id.clone());
+            state.current_strategy = Some(plan.clone());
+            state.strategies.entry(plan.clone()).or_default().count += 1;
+        }
+        MetaEventKind::StrategySwitch { .. } => state.switches += 1,
+        MetaEventKind::ScoreUpdate { plan, score, .. } => {
+            state.last_score = Some(*score);
+            state.best_score = Some(state.best_score.map_or(*score, |b| b.max(*score)));
+            let s = state.strategies.entry(plan.clone()).or_default();
+            s.score_sum += score;
+            s.best_score = s.best_score.max(*score);
+        }
+        MetaEventKind::UcbSnapshot { ucb } => state.ucb = Some(ucb.clone()),
+        MetaEventKind::Reset { .. } => *state = PersistedMetaState::default(),
+    }
+    state.seq = ev.seq;
+    state.updated_at = Some(ev.ts.clone());
+}
+
+/// Fold the events of `path` from `state.offset` on, stopping before the first one after
+/// `until`. A log shorter than the offset (replaced or truncated) is replayed from the start.
+fn replay(
+    path: &Path,
+    state: &mut PersistedMetaState,
+    until: Option<chrono::DateTime<chrono::Utc>>,
+) {
+    let Ok(mut f) = std::fs::File::open(storage::resolve(path)) else {
+        return;
+    };
+    let len = f.metadata().map(|m| m.len()).unwrap_or(0);
+    if len < state.offset {
+        *state = PersistedMetaState::default();
+    }
+    if f.seek(SeekFrom::Start(state.offset)).is_err() {
+        return;
+    }
+    let start = state.offset;
+    let mut lines = jsonl::Lines::complete(BufReader::new(f));
+    while let Some(Ok(line)) = lines.next() {
+        let end = start + lines.consumed();
+        if let Ok(ev) = serde_json::from_str::<MetaEvent>(&line) {
+            let after = chrono::DateTime::parse_from_rfc3339(&ev.ts)
+                .is_ok_and(|t| until.is_some_and(|u| t > u));
+            if after {
+                break;
+            }
+            apply(state, &ev);
+        }
+        state.offset = end;
+    }
+}
+
+fn ucb_state_path() -> String {
+    std::env::var("META_STATE").unwrap_or_else(|_| "trace/meta_ucb_state.json".to_string())
+}
+
+fn meta_dir() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("runs/meta")
+}
+
+fn events_path() -> PathBuf {
+    meta_dir().join("events.jsonl")
+}
+
+/// The snapshot plus every event appended after it.
+async fn load_persisted() -> PersistedMetaState {
+    tokio::task::spawn_blocking(|| {
+        let mut state: PersistedMetaState = storage::read_to_string(&meta_dir().join("state.json"))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        replay(&events_path(), &mut state, None);
+        state
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// The state as of `at` (or now), replayed from the start of the log.
+async fn load_at(at: Option<chrono::DateTime<chrono::Utc>>) -> PersistedMetaState {
+    tokio::task::spawn_blocking(move || {
+        let mut state = PersistedMetaState::default();
+        replay(&events_path(), &mut state, at);
+        state
+    })
+    .await
+    .unwrap_or_default()
+}
+
+async fn save_persisted(state: &PersistedMetaState) {
+    let dir = meta_dir();
+    let _ = fs::create_dir_all(&dir).await;
+    let _ = atomic::write_async(
+        dir.join("state.json"),
+        serde_json::to_string_pretty(state).unwrap_or_default(),
+    )
+    .await;
+}
+
+/// Append `kinds` as the next events (one write, so a step is logged whole or not at all),
+/// fold them into `state` and snapshot it. Call with [`META_LOCK`] held and `state` loaded.
+async fn commit(state: &mut PersistedMetaState, kinds: Vec<MetaEventKind>) {
+    let ts = chrono::Utc::now().to_rfc3339();
+    let events: Vec<MetaEvent> = kinds
+        .into_iter()
+        .enumerate()
+        .map(|(i, kind)| MetaEvent {
+            seq: state.seq + 1 + i as u64,
+            ts: ts.clone(),
+            kind,
+        })
+        .collect();
+    let lines: Vec<String> = events
+        .iter()
+        .filter_map(|e| serde_json::to_string(e).ok())
+        .collect();
+    let block = lines.join("\n");
+    let _ = fs::create_dir_all(meta_dir()).await;
+    if let Err(e) = atomic::append_async(events_path(), &block).await {
+        tracing::warn!("meta events append failed: {}", e);
+        return;
+    }
+    for ev in &events {
+        apply(state, ev);
+    }
+    state.offset += block.len() as u64 + 1;
+    save_persisted(state).await;
+}
+
+/// If the selector's state file is gone (fresh checkout, wiped trace/), restore the persisted copy.
+async fn restore_ucb_state_if_missing() {
+    let path = ucb_state_path();
+    if fs::metadata(&path).await.is_ok() {
+        return;
+    }
+    if let Some(ucb) = load_persisted().await.ucb {
+        if let Some(parent) = std::path::Path::new(&path).parent() {
+            let _ = fs::create_dir_all(parent).await;
+        }
+        let _ = fs::write(
+            &path,
+            serde_json::to_string_pretty(&ucb).unwrap_or_default(),
+        )
+        .await;
+    }
+}
+
+async fn record_run(resp: &MetaRunResp) {
+    let _guard = META_LOCK.lock().await;
+    let mut state = load_persisted().await;
+    let mut kinds = Vec::new();
+    if state.seq == 0 && state.iterations > 0 {
+        kinds.push(MetaEventKind::Imported {
+            state: Box::new(state.clone()),
+        });
+    }
+    if let Some(prev) = state.current_strategy.as_ref().filter(|p| **p != resp.plan) {
+        kinds.push(MetaEventKind::StrategySwitch {
+            run_id: resp.run_id.clone(),
+            from: prev.clone(),
+            to: resp.plan.clone(),
+        });
+    }
+    kinds.push(MetaEventKind::Decision {
+        run_id: resp.run_id.clone(),
+        task: resp.task.clone(),
+        plan: resp.plan.clone(),
+        config: resp.config.clone(),
+        latency_s: resp.latency_s,
+    });
+    kinds.push(MetaEventKind::ScoreUpdate {
+        run_id: resp.run_id.clone(),
+        plan: resp.plan.clone(),
+        score: resp.score,
+    });
+    if let Ok(raw) = fs::read_to_string(ucb_state_path()).await {
+        if let Ok(ucb) = serde_json::from_str::<serde_json::Value>(&raw) {
+            kinds.push(MetaEventKind::UcbSnapshot { ucb });
+        }
+    }
+    commit(&mut state, kinds).await;
+}
+
 #[utoipa::path(
     post,
     path = "/meta/run",
     request_body = MetaRunReq,
     responses((status=200, description="Run one meta selection step", body=MetaRunResp))
 )]
-pub async fn meta_run_handler(Json(req): Json<MetaRunReq>) -> impl IntoResponse {
+pub async fn meta_run_handler(
+    headers: axum::http::HeaderMap,
+    Json(req): Json<MetaRunReq>,
+) -> impl IntoResponse {
+    crate::integrations::audit::record(
+        &api_key_actor(&headers),
+        "meta.run",
+        &req.task,
+        Some(crate::integrations::audit::digest(&req)),
+        serde_json::Value::Null,
+    )
+    .await;
+    if let Err(e) = crate::engine::simulation::deny("meta.run") {
+        return (axum::http::StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response();
+    }
+    restore_ucb_state_if_missing().await;
     let script =
         std::env::var("META_SCRIPT").unwrap_or_else(|_| "scripts/meta_loop.py".to_string());
     let out = TokioCommand::new("python3")
@@ -68,6 +375,7 @@ pub async fn meta_run_handler(Json(req): Json<MetaRunReq>) -> impl IntoResponse
                         latency_s: v.get("latency_s").and_then(|x| x.as_f64()).unwrap_or(0.0)
                             as f32,
                     };
+                    record_run(&resp).await;
                     Json(resp).into_response()
                 }
                 Err(e) => (
@@ -93,48 +401,267 @@ pub async fn meta_run_handler(Json(req): Json<MetaRunReq>) -> impl IntoResponse
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct MetaState {
+    #[serde(default)]
     pub beta: serde_json::Value,
+    #[serde(default)]
     pub gamma: serde_json::Value,
+    #[serde(default)]
     pub beta_ids: Vec<String>,
+    #[serde(default)]
     pub gamma_ids: Vec<String>,
     pub rubric: Option<String>,
     pub ts: Option<String>,
+    /// Projection of the event log (as of `at` when asked for).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projection: Option<PersistedMetaState>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetaStateQuery {
+    /// RFC 3339 time to replay the event log up to.
+    pub at: Option<String>,
 }
 
 #[utoipa::path(
     get,
     path = "/meta/state",
-    responses((status=200, description="Current meta UCB state", body=MetaState))
+    params(("at" = Option<String>, Query, description = "RFC 3339 timestamp: the state as of then, replayed from runs/meta/events.jsonl")),
+    responses(
+        (status=200, description="Meta UCB state with the event-log projection", body=MetaState),
+        (status=400, description="Invalid `at`"),
+        (status=404, description="No meta state (yet, or at `at`)")
+    )
 )]
-pub async fn meta_state_handler() -> impl IntoResponse {
-    let path =
-        std::env::var("META_STATE").unwrap_or_else(|_| "trace/meta_ucb_state.json".to_string());
-    match fs::read_to_string(&path).await {
-        Ok(s) => match serde_json::from_str::<MetaState>(&s) {
-            Ok(v) => Json(v).into_response(),
-            Err(e) => (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("invalid meta state: {}", e),
+pub async fn meta_state_handler(Query(q): Query<MetaStateQuery>) -> impl IntoResponse {
+    let not_found = |msg: String| (axum::http::StatusCode::NOT_FOUND, msg).into_response();
+    let ucb_state = |ucb: Option<serde_json::Value>| {
+        ucb.and_then(|v| serde_json::from_value::<MetaState>(v).ok())
+            .unwrap_or_default()
+    };
+    if let Some(at) = q.at.as_deref() {
+        let Ok(at) = chrono::DateTime::parse_from_rfc3339(at) else {
+            return (
+                axum::http::StatusCode::BAD_REQUEST,
+                "at must be an RFC 3339 timestamp".to_string(),
             )
-                .into_response(),
-        },
-        Err(_) => (
-            axum::http::StatusCode::NOT_FOUND,
-            "no meta state".to_string(),
-        )
-            .into_response(),
+                .into_response();
+        };
+        let projection = load_at(Some(at.with_timezone(&chrono::Utc))).await;
+        if projection.seq == 0 {
+            return not_found(format!("no meta events at or before {}", at.to_rfc3339()));
+        }
+        let mut state = ucb_state(projection.ucb.clone());
+        state.projection = Some(projection);
+        return Json(state).into_response();
     }
+
+    restore_ucb_state_if_missing().await;
+    let projection = load_persisted().await;
+    let mut state = match fs::read_to_string(ucb_state_path()).await {
+        Ok(s) => match serde_json::from_str::<MetaState>(&s) {
+            Ok(v) => v,
+            Err(e) => {
+                return (
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("invalid meta state: {}", e),
+                )
+                    .into_response()
+            }
+        },
+        Err(_) if projection.seq > 0 || projection.iterations > 0 => {
+            ucb_state(projection.ucb.clone())
+        }
+        Err(_) => return not_found("no meta state".to_string()),
+    };
+    state.projection = Some(projection);
+    Json(state).into_response()
 }
 #[utoipa::path(
     post,
     path = "/meta/reset",
-    responses((status=200, description="Reset meta state"))
+    responses((status=200, description="Archive the current meta state under runs/meta/archive/<ts>-<suffix>/, log a reset event and start fresh"))
+)]
+pub async fn meta_reset_handler(headers: axum::http::HeaderMap) -> impl IntoResponse {
+    let _guard = META_LOCK.lock().await;
+    // The suffix keeps two resets in the same second apart; the timestamp keeps names sortable.
+    let name = format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ"),
+        &uuid::Uuid::new_v4().simple().to_string()[..8]
+    );
+    let archive = meta_dir().join("archive").join(name);
+    let created = match fs::create_dir_all(meta_dir().join("archive")).await {
+        Ok(()) => fs::create_dir(&archive).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = created {
+        return (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            format!("archive failed: {}", e),
+        )
+            .into_response();
+    }
+    let mut archived = Vec::new();
+    for (src, name) in [
+        (PathBuf::from(ucb_state_path()), "meta_ucb_state.json"),
+        (meta_dir().join("state.json"), "state.json"),
+    ] {
+        if fs::rename(&src, archive.join(name)).await.is_ok() {
+            archived.push(name);
+        }
+    }
+    // The snapshot was archived; load from the log so the reset gets the next seq.
+    let mut state = load_at(None).await;
+    commit(
+        &mut state,
+        vec![MetaEventKind::Reset {
+            archived_to: archive.display().to_string(),
+        }],
+    )
+    .await;
+    crate::integrations::audit::record(
+        &api_key_actor(&headers),
+        "meta.reset",
+        "runs/meta",
+        None,
+        serde_json::json!({"archived_to": archive.display().to_string(), "files": archived}),
+    )
+    .await;
+    Json(serde_json::json!({
+        "reset": true,
+        "archived_to": archive.display().to_string(),
+        "files": archived
+    }))
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetaHistoryQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MetaHistoryResp {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    /// Newest first.
+    pub items: Vec<serde_json::Value>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/meta/history",
+    params(
+        ("offset" = Option<usize>, Query, description = "Entries to skip (newest first)"),
+        ("limit" = Option<usize>, Query, description = "Page size (default 50, max 500)")
+    ),
+    responses((status=200, description="The meta event log (runs/meta/events.jsonl)", body=MetaHistoryResp))
 )]
-pub async fn meta_reset_handler() -> impl IntoResponse {
-    let path =
-        std::env::var("META_STATE").unwrap_or_else(|_| "trace/meta_ucb_state.json".to_string());
-    let _ = fs::remove_file(&path).await;
-    (axum::http::StatusCode::OK, "reset").into_response()
+pub async fn meta_history_handler(Query(q): Query<MetaHistoryQuery>) -> impl IntoResponse {
+    let offset = q.offset.unwrap_or(0);
+    let limit = q.limit.unwrap_or(50).clamp(1, 500);
+    // history.jsonl is the pre-event-log format; served until the first new step.
+    let raw = match storage::read_to_string_async(events_path()).await {
+        Ok(raw) => raw,
+        Err(_) => fs::read_to_string(meta_dir().join("history.jsonl"))
+            .await
+            .unwrap_or_default(),
+    };
+    let all: Vec<serde_json::Value> = raw
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .collect();
+    let items = all.iter().rev().skip(offset).take(limit).cloned().collect();
+    Json(MetaHistoryResp {
+        total: all.len(),
+        offset,
+        limit,
+        items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn line(seq: u64, ts: &str, kind: MetaEventKind) -> String {
+        let ev = MetaEvent {
+            seq,
+            ts: ts.to_string(),
+            kind,
+        };
+        serde_json::to_string(&ev).unwrap() + "\n"
+    }
+
+    fn step(seq: u64, ts: &str, plan: &str, score: f32) -> String {
+        line(
+            seq,
+            ts,
+            MetaEventKind::Decision {
+                run_id: format!("r-{}", seq),
+                task: "compress_chatlog".into(),
+                plan: plan.into(),
+                config: serde_json::json!({}),
+                latency_s: 0.1,
+            },
+        ) + &line(
+            seq + 1,
+            ts,
+            MetaEventKind::ScoreUpdate {
+                run_id: format!("r-{}", seq),
+                plan: plan.into(),
+                score,
+            },
+        )
+    }
+
+    #[test]
+    fn replay_folds_events_and_resumes_from_a_snapshot() {
+        let path = std::env::temp_dir().join(format!("meta-events-{}.jsonl", uuid::Uuid::new_v4()));
+        let first = step(1, "2026-01-01T00:00:00Z", "greedy", 0.4);
+        let log = first.clone()
+            + &line(
+                3,
+                "2026-01-02T00:00:00Z",
+                MetaEventKind::StrategySwitch {
+                    run_id: "r-3".into(),
+                    from: "greedy".into(),
+                    to: "ucb".into(),
+                },
+            )
+            + &step(4, "2026-01-02T00:00:00Z", "ucb", 0.9);
+        std::fs::write(&path, &log).unwrap();
+
+        let mut state = PersistedMetaState::default();
+        replay(&path, &mut state, None);
+        assert_eq!((state.iterations, state.seq, state.switches), (2, 5, 1));
+        assert_eq!(state.offset, log.len() as u64);
+        assert_eq!(state.current_strategy.as_deref(), Some("ucb"));
+        assert_eq!((state.last_score, state.best_score), (Some(0.9), Some(0.9)));
+        assert_eq!(state.strategies["greedy"].count, 1);
+
+        // Time travel stops before the first later event.
+        let at = chrono::DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z").unwrap();
+        let mut past = PersistedMetaState::default();
+        replay(&path, &mut past, Some(at.with_timezone(&chrono::Utc)));
+        assert_eq!((past.iterations, past.seq), (1, 2));
+        assert_eq!(past.offset, first.len() as u64);
+
+        // A stale snapshot catches up; a half-written last line waits for its newline.
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"seq\":6,\"ts\":")
+            .unwrap();
+        replay(&path, &mut past, None);
+        assert_eq!((past.iterations, past.seq), (state.iterations, state.seq));
+        assert_eq!(past.offset, log.len() as u64);
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -8,12 +8,22 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tokio::{fs, process::Command as TokioCommand};
 use utoipa::ToSchema;
-use crate::engine::router;
+use crate::engine::{correlation, ops, prompts, router, snapshot, types::Policy};
+use crate::{context, nstar_policy};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
 pub struct NStarRunReq {
     pub task: String,
+    /// Policy gating LM-proposed ops (defaults to `Policy::default()`).
+    #[serde(default)]
+    pub policy: Option<Policy>,
+    /// Gate and receipt ops without applying them.
+    #[serde(default)]
+    pub ops_dry_run: bool,
+    /// When set, prepend a resolved context bundle of at most this many tokens to the prompt.
+    #[serde(default)]
+    pub context_budget: Option<usize>,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, ToSchema)]
@@ -22,6 +32,148 @@ pub struct NStarRunResp {
     pub result: String,
     pub policy: serde_json::Value,
     pub adapt: serde_json::Value,
+    /// The system prompt version this run used.
+    pub prompt: prompts::PromptRecord,
+}
+
+/// Prompt suffix + temperature per branch; branch 0 is the plain exploit prompt.
+const BRANCH_VARIANTS: &[(&str, f32)] = &[
+    ("", 0.2),
+    ("\n\nPrefer the smallest, safest change that satisfies the task.", 0.7),
+    ("\n\nConsider an alternative to the obvious approach before answering.", 1.0),
+];
+/// Exploration branches below this token cap are dropped rather than truncated into noise.
+const MIN_BRANCH_TOKENS: u32 = 256;
+
+#[derive(Debug, Clone, Serialize)]
+struct BranchRun {
+    index: usize,
+    temperature: f32,
+    max_tokens: Option<u32>,
+    tokens: Option<u64>,
+    error: Option<String>,
+    blocked_ops: usize,
+    score: f32,
+    reply_preview: String,
+    #[serde(skip)]
+    value: Option<serde_json::Value>,
+}
+
+/// Token caps per branch: branch 0 is uncapped; exploration branches share
+/// `explore_budget * NSTAR_TOKEN_BUDGET` (default 16000) tokens.
+fn branch_caps(branches: u32, explore_budget: f32) -> Vec<Option<u32>> {
+    let budget = std::env::var("NSTAR_TOKEN_BUDGET")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(16_000);
+    let explore_tokens = (budget as f32 * explore_budget.clamp(0.0, 1.0)) as u32;
+    let extra = branches
+        .saturating_sub(1)
+        .min(explore_tokens / MIN_BRANCH_TOKENS) as usize;
+    let mut caps = vec![None];
+    if extra > 0 {
+        caps.extend(std::iter::repeat_n(Some(explore_tokens / extra as u32), extra));
+    }
+    caps
+}
+
+/// Verifier score in 0..=1: LM self-reported bits (low U, no E), minus ops the gates would block.
+fn score_branch(val: &serde_json::Value, blocked_ops: usize, total_ops: usize) -> f32 {
+    let mut score = 0.5;
+    let reply = val.get("reply").and_then(|s| s.as_str()).unwrap_or("");
+    if !reply.trim().is_empty() {
+        score += 0.1;
+    }
+    if val.get("intent").is_some() {
+        score += 0.05;
+    }
+    if let Some(bits) = val.get("bits").or_else(|| val.pointer("/manifest/bits")) {
+        let get = |k: &str, alt: &str| {
+            bits.get(k)
+                .or_else(|| bits.get(alt))
+                .and_then(|v| v.as_f64())
+                .map(|v| v as f32)
+        };
+        score += 0.3 * (1.0 - get("U", "u").unwrap_or(0.5)) - 0.3 * get("E", "e").unwrap_or(0.0);
+    }
+    if total_ops > 0 {
+        score -= 0.4 * blocked_ops as f32 / total_ops as f32;
+    }
+    score.clamp(0.0, 1.0)
+}
+
+/// Run the branches in parallel and score each one.
+async fn run_branches(
+    system_prompt: &str,
+    task: &str,
+    branches: u32,
+    explore_budget: f32,
+    op_policy: &Policy,
+) -> Vec<BranchRun> {
+    let mut set = tokio::task::JoinSet::new();
+    for (index, cap) in branch_caps(branches, explore_budget).into_iter().enumerate() {
+        let (suffix, temperature) = BRANCH_VARIANTS[index % BRANCH_VARIANTS.len()];
+        let system = format!("{}{}", system_prompt, suffix);
+        let task = task.to_string();
+        set.spawn(async move {
+            let opts = router::ChatOpts {
+                temperature: Some(temperature),
+                max_tokens: cap,
+                ..Default::default()
+            };
+            (index, temperature, cap, router::chat_opts(&system, &task, &opts).await)
+        });
+    }
+
+    let mut runs = Vec::new();
+    while let Some(joined) = set.join_next().await {
+        let Ok((index, temperature, max_tokens, res)) = joined else {
+            continue;
+        };
+        let run = match res {
+            Ok(out) => {
+                let proposed = out
+                    .value
+                    .get("ops")
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                let blocked_ops = ops::preview_blocked(&proposed, op_policy).await;
+                BranchRun {
+                    index,
+                    temperature,
+                    max_tokens,
+                    tokens: out.total_tokens,
+                    error: None,
+                    blocked_ops,
+                    score: score_branch(&out.value, blocked_ops, proposed.len()),
+                    reply_preview: out
+                        .value
+                        .get("reply")
+                        .and_then(|s| s.as_str())
+                        .unwrap_or("")
+                        .chars()
+                        .take(200)
+                        .collect(),
+                    value: Some(out.value),
+                }
+            }
+            Err(e) => BranchRun {
+                index,
+                temperature,
+                max_tokens,
+                tokens: None,
+                error: Some(e.to_string()),
+                blocked_ops: 0,
+                score: 0.0,
+                reply_preview: String::new(),
+                value: None,
+            },
+        };
+        runs.push(run);
+    }
+    runs.sort_by_key(|r| r.index);
+    runs
 }
 
 #[utoipa::path(
@@ -35,24 +187,71 @@ pub async fn nstar_run_handler(Json(req): Json<NStarRunReq>) -> impl IntoRespons
     let run_id = uuid::Uuid::new_v4().to_string().chars().take(8).collect::<String>();
     let t0 = SystemTime::now();
 
-    // Policy (simplified/hardcoded for now, mimicking nstar.py defaults)
+    // Policy: branch count / explore budget come from the per-family bandit (GET /nstar/policy).
+    let choice = nstar_policy::choose(&task).await;
+    let op_policy = req.policy.clone().unwrap_or_default();
+    let ops_dry_run = req.ops_dry_run || std::env::var("NSTAR_OPS_DRY_RUN").ok().as_deref() == Some("1");
     let policy = serde_json::json!({
-        "branches": 1,
-        "explore_budget": 0.15
+        "branches": choice.branches,
+        "explore_budget": choice.explore_budget,
+        "family": choice.family,
+        "arm": choice.arm,
+        "strategy": choice.strategy,
+        "ops_dry_run": ops_dry_run,
+        "max_risk": op_policy.max_risk,
+        "snapshot": std::env::var("NSTAR_SNAPSHOT").ok().as_deref() == Some("1")
     });
 
     // 1. Cognition: Load System Prompt & Call LLM
-    let system_prompt = fs::read_to_string("prompts/META_OMNI.md")
-        .await
-        .unwrap_or_else(|_| "You are the Meta3 Engine. Respond in JSON.".to_string());
-
-    // 1. Cognition: Load System Prompt & Call LLM
-    let system_prompt = fs::read_to_string("prompts/META_OMNI.md")
-        .await
-        .unwrap_or_else(|_| "You are the Meta3 Engine. Respond in JSON with optional 'ops' array.".to_string());
+    let date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let prompt = prompts::render(
+        "meta_omni",
+        "You are the Meta3 Engine. Respond in JSON with optional 'ops' array.",
+        &[("task", task.as_str()), ("goal", "nstar"), ("date", date.as_str())],
+    );
+    let system_prompt = prompt.text;
+    let context_bundle = match req.context_budget.filter(|b| *b > 0) {
+        Some(budget) => Some(
+            context::resolve(context::ContextQuery {
+                query: task.clone(),
+                budget_tokens: budget,
+                ..Default::default()
+            })
+            .await,
+        ),
+        None => None,
+    };
+    let system_prompt = match &context_bundle {
+        Some(b) if !b.items.is_empty() => format!("{}\n\n## Context\n\n{}", system_prompt, b.render()),
+        _ => system_prompt,
+    };
 
-    let res = router::chat(&system_prompt, &task).await;
+    // 2. Branches: best-of-N by verifier score (ties keep the lower, exploit-first index).
+    let branch_runs = run_branches(
+        &system_prompt,
+        &task,
+        choice.branches,
+        choice.explore_budget,
+        &op_policy,
+    )
+    .await;
+    let best_branch = branch_runs
+        .iter()
+        .filter(|b| b.value.is_some())
+        .fold(None::<&BranchRun>, |best, b| match best {
+            Some(cur) if cur.score >= b.score => Some(cur),
+            _ => Some(b),
+        });
+    let res: Result<serde_json::Value, String> = match best_branch {
+        Some(b) => Ok(b.value.clone().unwrap_or_default()),
+        None => Err(branch_runs
+            .iter()
+            .filter_map(|b| b.error.clone())
+            .next()
+            .unwrap_or_else(|| "no branches ran".to_string())),
+    };
     
+    let mut op_receipts: Vec<ops::OpReceipt> = Vec::new();
     let (best_out, intent, mut impact_url, ops_report) = match res {
         Ok(val) => {
              // Standard OMNI Response
@@ -69,44 +268,25 @@ pub async fn nstar_run_handler(Json(req): Json<NStarRunReq>) -> impl IntoRespons
 
              // META5: The Universal Actuator (Op Execution Loop)
              let mut ops_log = Vec::new();
-             if let Some(ops) = val.get("ops").and_then(|v| v.as_array()) {
-                 for op in ops {
-                     if let Some(kind) = op.get("op").and_then(|s| s.as_str()) {
-                         let res_str = match kind {
-                             "write" => {
-                                 let path = op.get("path").and_then(|s| s.as_str()).unwrap_or("");
-                                 let content = op.get("content").and_then(|s| s.as_str()).unwrap_or("");
-                                 // Simple Safety: Only allow writing to known subdirs
-                                 if path.starts_with("src/") || path.starts_with("ui/") || path.starts_with("scripts/") || path.starts_with("docs/") {
-                                     if let Some(parent) = std::path::Path::new(path).parent() {
-                                         let _ = fs::create_dir_all(parent).await;
-                                     }
-                                     if let Ok(_) = fs::write(path, content).await {
-                                         format!("Wrote {} bytes to {}", content.len(), path)
-                                     } else {
-                                         format!("Failed to write {}", path)
-                                     }
-                                 } else {
-                                     format!("Blocked unsafe write to {}", path)
-                                 }
-                             },
-                             "exec" => {
-                                 let cmd = op.get("cmd").and_then(|s| s.as_str()).unwrap_or("");
-                                 let args = op.get("args").and_then(|a| a.as_array())
-                                     .map(|arr| arr.iter().map(|s| s.as_str().unwrap_or("")).collect::<Vec<_>>())
-                                     .unwrap_or_default();
-                                 if !cmd.is_empty() {
-                                     match tokio::process::Command::new(cmd).args(args).output().await {
-                                         Ok(o) => format!("Exec OK (len: {})", o.stdout.len()),
-                                         Err(e) => format!("Exec Failed: {}", e)
-                                     }
-                                 } else { "Empty cmd".to_string() }
-                             },
-                             _ => format!("Unknown op: {}", kind)
-                         };
-                         ops_log.push(res_str);
+             if let Some(proposed) = val.get("ops").and_then(|v| v.as_array()) {
+                 // Snapshot write targets first so `/runs/{run_id}/rollback` can undo the ops.
+                 if policy["snapshot"].as_bool() == Some(true) && !ops_dry_run {
+                     let targets: Vec<std::path::PathBuf> = proposed
+                         .iter()
+                         .filter(|op| op.get("op").and_then(|s| s.as_str()) == Some("write"))
+                         .filter_map(|op| op.get("path").and_then(|s| s.as_str()))
+                         .filter(|p| ops::is_allowed_write(p))
+                         .map(std::path::PathBuf::from)
+                         .collect();
+                     if !targets.is_empty() {
+                         match snapshot::take(&run_id, "nstar.ops", &targets) {
+                             Ok(_) => ops_log.push(format!("Snapshot {} ({} paths)", run_id, targets.len())),
+                             Err(e) => ops_log.push(format!("Snapshot failed: {}", e)),
+                         }
                      }
                  }
+                 op_receipts = ops::run_ops(&run_id, proposed, &op_policy, ops_dry_run).await;
+                 ops_log.extend(op_receipts.iter().map(|r| r.summary()));
              }
              let ops_summary = if ops_log.is_empty() { "No ops".to_string() } else { ops_log.join("; ") };
 
@@ -123,11 +303,28 @@ pub async fn nstar_run_handler(Json(req): Json<NStarRunReq>) -> impl IntoRespons
 
     // ... (Rest of existing verification logic) ...
     // 3. Verification & Metrics
-    let ok = true; // Assume success for now
+    let failed_ops = op_receipts
+        .iter()
+        .filter(|r| r.status == "failed" || r.status == "blocked")
+        .count();
+    let ok = intent != "meta6_graph" && failed_ops == 0;
     let note = format!("Intent: {}", intent);
     let dt = t0.elapsed().unwrap().as_secs_f64();
     let cost = 0.001; 
 
+    // 4. Adapt: reward = success, minus failed/blocked op share and a latency penalty.
+    let reward = if intent == "meta6_graph" {
+        0.0
+    } else {
+        let op_penalty = if op_receipts.is_empty() {
+            0.0
+        } else {
+            0.5 * failed_ops as f32 / op_receipts.len() as f32
+        };
+        (1.0 - op_penalty - 0.2 * (dt as f32 / 30.0).min(1.0)).clamp(0.0, 1.0)
+    };
+    let changed = nstar_policy::update(&choice, reward).await;
+
     // Write Receipt logic ... (Use existing code)
     let receipts_path = std::env::var("NSTAR_RECEIPTS").unwrap_or_else(|_| "trace/receipts.jsonl".to_string());
     if let Some(parent) = std::path::Path::new(&receipts_path).parent() {
@@ -144,20 +341,41 @@ pub async fn nstar_run_handler(Json(req): Json<NStarRunReq>) -> impl IntoRespons
         "best": best_out,
         "cost": cost,
         "latency_s": dt,
+        "reward": reward,
+        "branches": branch_runs,
+        "best_branch": best_branch.map(|b| b.index),
+        "tokens": branch_runs.iter().filter_map(|b| b.tokens).sum::<u64>(),
+        "context": context_bundle.as_ref().map(|b| serde_json::json!({
+            "used_tokens": b.used_tokens,
+            "items": b.items.iter().map(|it| serde_json::json!({"source": it.source, "id": it.id, "provenance": it.provenance})).collect::<Vec<_>>()
+        })),
         "mode": "hybrid_omni_v1",
-        "impact_url": impact_url
+        "prompt": prompt.record,
+        "impact_url": impact_url,
+        "correlation_id": correlation::current(),
+        "ops": op_receipts
+            .iter()
+            .map(|r| serde_json::json!({"op_run_id": r.op_run_id, "op": r.op, "status": r.status}))
+            .collect::<Vec<_>>()
     });
 
     use tokio::io::AsyncWriteExt;
     if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(&receipts_path).await {
          let _ = file.write_all(format!("{}\n", rec).as_bytes()).await;
     }
+    correlation::record(&correlation::Link::new("nstar", &run_id)).await;
 
     let resp = NStarRunResp {
         ok,
         result: best_out,
         policy,
-        adapt: serde_json::json!({"changed": false, "impact_url": impact_url}),
+        adapt: serde_json::json!({
+            "changed": changed,
+            "family": choice.family,
+            "reward": reward,
+            "impact_url": impact_url
+        }),
+        prompt: prompt.record,
     };
     Json(resp).into_response()
 }
@@ -216,6 +434,24 @@ static FLUID_GRAPH: Lazy<FluidGraph> = Lazy::new(|| {
     FluidGraph { nodes: m }
 });
 
+/// Registered with [`crate::engine::hooks`] for `meta.omni` CodeAct turns.
+pub struct Worlds;
+
+impl crate::engine::hooks::WorldRenderer for Worlds {
+    fn system_matrix(&self) -> crate::engine::harness::BoxFuture<'_, Result<String, String>> {
+        Box::pin(execute_system_matrix())
+    }
+
+    fn ruliad<'a>(
+        &'a self,
+        seed: &'a str,
+        rules: Vec<(String, String)>,
+        depth: usize,
+    ) -> crate::engine::harness::BoxFuture<'a, Result<String, String>> {
+        Box::pin(execute_divine_ruliad(seed, rules, depth))
+    }
+}
+
 // Ruliad Logic (The Executor)
 pub async fn execute_divine_ruliad(seed: &str, rules: Vec<(String, String)>, depth: usize) -> Result<String, String> {
     use std::collections::{HashSet, HashMap};
@@ -513,6 +749,20 @@ pub async fn nstar_hud_handler(Query(q): Query<HudQuery>) -> impl IntoResponse {
 #[derive(Deserialize, JsonSchema, ToSchema)]
 pub struct ResolveReq {
     pub query: String,
+    /// Subset of `threads`, `receipts`, `research`, `codex`, `fs` (default: all).
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub budget_tokens: Option<usize>,
+    #[serde(default)]
+    pub weights: Option<context::ContextWeights>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub thread: Option<String>,
+    /// Globs for the `fs` source, e.g. `src/**/*.rs`.
+    #[serde(default)]
+    pub globs: Vec<String>,
 }
 
 #[derive(Serialize, JsonSchema, ToSchema)]
@@ -523,45 +773,85 @@ pub struct ContextMatch {
 
 #[derive(Serialize, JsonSchema, ToSchema)]
 pub struct ResolveResp {
+    /// Receipt items only, in the original `{task, run_id}` shape.
     pub matches: Vec<ContextMatch>,
     pub suggestion: String,
+    pub bundle: context::ContextBundle,
 }
 
 #[utoipa::path(
     post,
     path = "/v1/context/resolve",
     request_body = ResolveReq,
-    responses((status=200, description="Resolve Context", body=ResolveResp))
+    responses(
+        (status=200, description="Ranked, budgeted context bundle with provenance", body=ResolveResp),
+        (status=401, description="Missing or unknown x-api-key"),
+        (status=403, description="user_id names another user")
+    )
 )]
-pub async fn resolve_context_handler(Json(req): Json<ResolveReq>) -> impl IntoResponse {
-    use tokio::io::AsyncBufReadExt;
-    let receipts_path = std::env::var("NSTAR_RECEIPTS").unwrap_or_else(|_| "trace/receipts.jsonl".to_string());
-    
-    let mut matches = Vec::new();
-    if let Ok(file) = fs::File::open(&receipts_path).await {
-        let mut reader = tokio::io::BufReader::new(file).lines();
-        while let Ok(Some(line)) = reader.next_line().await {
-            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&line) {
-                let task = val.get("task").or(val.get("text")).and_then(|s| s.as_str()).unwrap_or("");
-                if !task.is_empty() && task.to_lowercase().contains(&req.query.to_lowercase()) {
-                    let run_id = val.get("run_id").or(val.get("ts")).and_then(|s| s.as_str()).unwrap_or("?").to_string();
-                    matches.push(ContextMatch {
-                        task: task.chars().take(200).collect(), // Reasonable truncation
-                        run_id
-                    });
-                }
-            }
+pub async fn resolve_context_handler(
+    axum::extract::State(state): axum::extract::State<crate::api::AppState>,
+    headers: axum::http::HeaderMap,
+    Json(req): Json<ResolveReq>,
+) -> impl IntoResponse {
+    use crate::integrations::receipt_acl::Caller;
+    let caller = match crate::api::require_caller(&state, &headers) {
+        Ok(c) => c,
+        Err(e) => return e.into_response(),
+    };
+    // A user's key resolves that user's threads only.
+    let user_id = match &caller {
+        Caller::User(u) if req.user_id.as_ref().is_some_and(|r| r != u) => {
+            return (
+                axum::http::StatusCode::FORBIDDEN,
+                "user_id must be the caller's".to_string(),
+            )
+                .into_response();
         }
+        Caller::User(u) => Some(u.clone()),
+        _ => req.user_id,
+    };
+    if let Some(bad) = req.sources.iter().find(|s| !context::ALL_SOURCES.contains(&s.as_str())) {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            format!("unknown source: {} (expected one of {})", bad, context::ALL_SOURCES.join(", ")),
+        )
+            .into_response();
     }
-    
-    // Top 5 recent matches (reverse chronological)
-    matches.reverse();
-    matches.truncate(5);
+    let bundle = context::resolve(context::ContextQuery {
+        query: req.query.clone(),
+        sources: req.sources,
+        budget_tokens: req.budget_tokens.unwrap_or(context::DEFAULT_BUDGET_TOKENS),
+        weights: req.weights.unwrap_or_default(),
+        user_id,
+        thread: req.thread,
+        globs: req.globs,
+        reader: Some(caller),
+    })
+    .await;
+
+    let matches: Vec<ContextMatch> = bundle
+        .items
+        .iter()
+        .filter(|it| it.source == "receipts")
+        .take(5)
+        .map(|it| ContextMatch {
+            task: it.content.chars().take(200).collect(),
+            run_id: it.provenance.run_id.clone().unwrap_or_else(|| it.id.clone()),
+        })
+        .collect();
 
-    let count = matches.len();
     Json(ResolveResp {
-        suggestion: format!("Found {} historical nodes relevant to '{}'.", count, req.query),
-        matches
-    }).into_response()
+        suggestion: format!(
+            "Found {} context items relevant to '{}' ({} of {} tokens).",
+            bundle.items.len(),
+            req.query,
+            bundle.used_tokens,
+            bundle.budget_tokens
+        ),
+        matches,
+        bundle,
+    })
+    .into_response()
 }
 
@@ -0,0 +1,265 @@
+//! N* policy learning: a per-task-family bandit over (branches, explore_budget) arms.
+//!
+//! Arm choice is UCB1 with an epsilon-greedy escape hatch (`NSTAR_EPSILON`, default 0.1).
+//! Outcomes are persisted to `NSTAR_POLICY_STATE` (default `trace/nstar_policy.json`)
+//! after each receipt, so the learned policy survives restarts.
+
+use axum::{response::IntoResponse, Json};
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+const BRANCHES: &[u32] = &[1, 2, 3];
+const EXPLORE_BUDGETS: &[f32] = &[0.05, 0.15, 0.3];
+const UCB_C: f32 = 1.0;
+
+/// Keyword → family; first hit wins, otherwise "general".
+const FAMILIES: &[(&str, &str)] = &[
+    ("test", "test"),
+    ("fix", "fix"),
+    ("bug", "fix"),
+    ("refactor", "refactor"),
+    ("doc", "docs"),
+    ("readme", "docs"),
+    ("build", "build"),
+    ("research", "research"),
+    ("graph", "graph"),
+];
+
+static STATE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct ArmStats {
+    pub branches: u32,
+    pub explore_budget: f32,
+    pub pulls: u32,
+    pub reward_sum: f32,
+}
+
+impl ArmStats {
+    fn mean(&self) -> f32 {
+        if self.pulls == 0 {
+            0.0
+        } else {
+            self.reward_sum / self.pulls as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct FamilyPolicy {
+    pub arms: Vec<ArmStats>,
+    pub total_pulls: u32,
+    /// Index of the arm with the best mean reward (the exploit choice).
+    pub best_arm: usize,
+}
+
+impl Default for FamilyPolicy {
+    fn default() -> Self {
+        let arms = BRANCHES
+            .iter()
+            .flat_map(|&b| {
+                EXPLORE_BUDGETS.iter().map(move |&e| ArmStats {
+                    branches: b,
+                    explore_budget: e,
+                    pulls: 0,
+                    reward_sum: 0.0,
+                })
+            })
+            .collect();
+        // Seed the exploit choice with the old hardcoded policy (branches 1, explore 0.15).
+        Self {
+            arms,
+            total_pulls: 0,
+            best_arm: 1,
+        }
+    }
+}
+
+impl FamilyPolicy {
+    fn greedy(&self) -> usize {
+        self.arms
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.pulls > 0)
+            .max_by(|(_, a), (_, b)| {
+                a.mean()
+                    .partial_cmp(&b.mean())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(self.best_arm)
+    }
+
+    fn ucb(&self) -> usize {
+        if let Some(i) = self.arms.iter().position(|a| a.pulls == 0) {
+            return i;
+        }
+        let ln_n = (self.total_pulls.max(1) as f32).ln();
+        self.arms
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (i, a.mean() + UCB_C * (2.0 * ln_n / a.pulls as f32).sqrt()))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+            .unwrap_or(self.best_arm)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, ToSchema)]
+pub struct NStarPolicyState {
+    pub families: BTreeMap<String, FamilyPolicy>,
+    pub updated_at: Option<String>,
+}
+
+/// The arm picked for one run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Choice {
+    pub family: String,
+    pub arm: usize,
+    pub branches: u32,
+    pub explore_budget: f32,
+    /// "ucb" | "epsilon"
+    pub strategy: String,
+}
+
+fn state_path() -> String {
+    std::env::var("NSTAR_POLICY_STATE").unwrap_or_else(|_| "trace/nstar_policy.json".to_string())
+}
+
+fn epsilon() -> f32 {
+    std::env::var("NSTAR_EPSILON")
+        .ok()
+        .and_then(|v| v.parse::<f32>().ok())
+        .unwrap_or(0.1)
+        .clamp(0.0, 1.0)
+}
+
+/// Uniform draw in [0, 1) (uuid v4 bytes; no rand dependency needed for this).
+fn unit_random() -> f32 {
+    (uuid::Uuid::new_v4().as_u128() % 1_000_000) as f32 / 1_000_000.0
+}
+
+pub fn task_family(task: &str) -> String {
+    let t = task.to_lowercase();
+    FAMILIES
+        .iter()
+        .find(|(kw, _)| t.contains(kw))
+        .map(|(_, fam)| fam.to_string())
+        .unwrap_or_else(|| "general".to_string())
+}
+
+async fn load() -> NStarPolicyState {
+    match tokio::fs::read_to_string(state_path()).await {
+        Ok(s) => serde_json::from_str(&s).unwrap_or_default(),
+        Err(_) => NStarPolicyState::default(),
+    }
+}
+
+async fn save(state: &NStarPolicyState) {
+    let path = state_path();
+    if let Some(parent) = std::path::Path::new(&path).parent() {
+        let _ = tokio::fs::create_dir_all(parent).await;
+    }
+    let tmp = format!("{}.tmp", path);
+    if tokio::fs::write(
+        &tmp,
+        serde_json::to_string_pretty(state).unwrap_or_default(),
+    )
+    .await
+    .is_ok()
+    {
+        let _ = tokio::fs::rename(&tmp, &path).await;
+    }
+}
+
+/// Pick the arm for a task: UCB1, or a uniform random arm with probability epsilon.
+pub async fn choose(task: &str) -> Choice {
+    let family = task_family(task);
+    let state = load().await;
+    // A hand-edited state file can leave a family without arms; start it over.
+    let fam = state
+        .families
+        .get(&family)
+        .filter(|f| !f.arms.is_empty())
+        .cloned()
+        .unwrap_or_default();
+    let (arm, strategy) = if unit_random() < epsilon() {
+        (
+            (unit_random() * fam.arms.len() as f32) as usize % fam.arms.len(),
+            "epsilon",
+        )
+    } else {
+        (fam.ucb(), "ucb")
+    };
+    let a = &fam.arms[arm];
+    Choice {
+        family,
+        arm,
+        branches: a.branches,
+        explore_budget: a.explore_budget,
+        strategy: strategy.to_string(),
+    }
+}
+
+/// Record a run's reward (0..=1). Returns whether the family's exploit arm changed.
+pub async fn update(choice: &Choice, reward: f32) -> bool {
+    let _guard = STATE_LOCK.lock().await;
+    let mut state = load().await;
+    let fam = state.families.entry(choice.family.clone()).or_default();
+    if fam.arms.is_empty() {
+        *fam = FamilyPolicy::default();
+    }
+    let before = fam.best_arm;
+    if let Some(a) = fam.arms.get_mut(choice.arm) {
+        a.pulls += 1;
+        a.reward_sum += reward.clamp(0.0, 1.0);
+        fam.total_pulls += 1;
+    }
+    fam.best_arm = fam.greedy();
+    let changed = fam.best_arm != before;
+    state.updated_at = Some(chrono::Utc::now().to_rfc3339());
+    save(&state).await;
+    changed
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NStarPolicyResp {
+    pub epsilon: f32,
+    pub state: NStarPolicyState,
+    /// family → exploit policy `{branches, explore_budget, mean_reward, pulls}`.
+    pub learned: BTreeMap<String, serde_json::Value>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/nstar/policy",
+    responses((status = 200, description = "Learned N* policy per task family", body = NStarPolicyResp))
+)]
+pub async fn nstar_policy_handler() -> impl IntoResponse {
+    let state = load().await;
+    let learned = state
+        .families
+        .iter()
+        .filter_map(|(name, fam)| {
+            let a = fam.arms.get(fam.best_arm).or(fam.arms.last())?;
+            Some((
+                name.clone(),
+                serde_json::json!({
+                    "branches": a.branches,
+                    "explore_budget": a.explore_budget,
+                    "mean_reward": a.mean(),
+                    "pulls": a.pulls
+                }),
+            ))
+        })
+        .collect();
+    Json(NStarPolicyResp {
+        epsilon: epsilon(),
+        state,
+        learned,
+    })
+}
@@ -0,0 +1,207 @@
+//! Secret redaction for everything persisted in receipts, traces and imported transcripts.
+//!
+//! Built-in patterns cover `x-api-key`, `Authorization: Bearer`, `sk-` tokens and JSON values
+//! under secret-looking keys; `redaction.patterns` in policies.yaml adds more.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+static RE_X_API_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(x-api-key\s*[:=]\s*["']?)([^\s"'\\]+)"#).unwrap());
+static RE_AUTH_BEARER: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?i)(authorization\s*:\s*bearer\s+["']?)([^\s"'\\]+)"#).unwrap());
+static RE_SK: Lazy<Regex> = Lazy::new(|| Regex::new(r"sk-[A-Za-z0-9_-]{10,}").unwrap());
+static RE_JSON_SECRET: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)("[a-z0-9_-]*(?:password|secret|token|api_key|apikey|api-key)"\s*:\s*")((?:[^"\\]|\\.)*)(")"#)
+        .unwrap()
+});
+
+/// Object keys whose string values are always redacted in receipts.
+static RE_SECRET_KEY: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(?:password|secret|token|api_key|apikey|api-key)$").unwrap());
+
+#[derive(Debug, Default, Deserialize)]
+struct RedactionSection {
+    #[serde(default)]
+    patterns: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesRedaction {
+    #[serde(default)]
+    redaction: Option<RedactionSection>,
+}
+
+/// Extra `redaction.patterns` from policies.yaml (read once); the whole match is replaced.
+static RE_EXTRA: Lazy<Vec<Regex>> = Lazy::new(|| {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let section = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesRedaction>(&raw).ok())
+        .and_then(|p| p.redaction)
+        .unwrap_or_default();
+    section
+        .patterns
+        .iter()
+        .filter_map(|p| match Regex::new(p) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                tracing::warn!("invalid redaction pattern {:?}: {}", p, e);
+                None
+            }
+        })
+        .collect()
+});
+
+/// Redact `s`, returning the number of replacements made.
+pub fn redact_counted(s: &str) -> (String, usize) {
+    let builtin: [(&Regex, &str); 4] = [
+        (&*RE_X_API_KEY, "${1}[REDACTED]"),
+        (&*RE_AUTH_BEARER, "${1}[REDACTED]"),
+        (&*RE_SK, "sk-[REDACTED]"),
+        (&*RE_JSON_SECRET, "${1}[REDACTED]${3}"),
+    ];
+    let mut out = s.to_string();
+    let mut n = 0;
+    let extra = RE_EXTRA.iter().map(|re| (re, "[REDACTED]"));
+    for (re, rep) in builtin.into_iter().chain(extra) {
+        let hits = re.find_iter(&out).count();
+        if hits > 0 {
+            n += hits;
+            out = re.replace_all(&out, rep).to_string();
+        }
+    }
+    (out, n)
+}
+
+pub fn redact(s: &str) -> String {
+    redact_counted(s).0
+}
+
+/// Redact every string in `v` (and values under secret-looking keys); returns the count.
+pub fn redact_value(v: &mut Value) -> usize {
+    match v {
+        Value::String(s) => {
+            let (out, n) = redact_counted(s);
+            if n > 0 {
+                *s = out;
+            }
+            n
+        }
+        Value::Array(a) => a.iter_mut().map(redact_value).sum(),
+        Value::Object(o) => o
+            .iter_mut()
+            .map(|(k, x)| match x {
+                // `$secret` holds a secret's name, not its value.
+                Value::String(s)
+                    if k != "$secret"
+                        && RE_SECRET_KEY.is_match(k)
+                        && !s.is_empty()
+                        && s != "[REDACTED]" =>
+                {
+                    *s = "[REDACTED]".to_string();
+                    1
+                }
+                _ => redact_value(x),
+            })
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Whether any string in `v` carries a redaction marker, i.e. `v` can't be replayed as is.
+pub fn is_redacted(v: &Value) -> bool {
+    match v {
+        Value::String(s) => s.contains("[REDACTED]"),
+        Value::Array(a) => a.iter().any(is_redacted),
+        Value::Object(o) => o.values().any(is_redacted),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    proptest! {
+        #[test]
+        fn redaction_is_idempotent(s in "\\PC{0,200}|(x-api-key: |sk-|\"token\":\"|authorization: bearer |[a-z\"'\\\\ :=]){0,20}") {
+            let once = redact(&s);
+            prop_assert_eq!(redact(&once), once);
+        }
+
+        #[test]
+        fn sk_tokens_never_survive(pre in "\\PC{0,40}", tok in "[A-Za-z0-9_-]{10,400}", post in "\\PC{0,40}") {
+            let out = redact(&format!("{} sk-{} {}", pre, tok, post));
+            let leaked = format!("sk-{}", tok);
+            prop_assert!(!out.contains(&leaked));
+        }
+
+        /// Quoted and unquoted header values are both redacted.
+        #[test]
+        fn header_values_never_survive(
+            tok in "[A-Za-z0-9._~+/=-]{1,200}",
+            quote in prop_oneof![Just(""), Just("\""), Just("'")],
+            header in prop_oneof![Just("X-Api-Key: "), Just("x-api-key="), Just("Authorization: Bearer ")],
+        ) {
+            let out = redact(&format!("{}{}{}{}\n", header, quote, tok, quote));
+            prop_assert_eq!(out, format!("{}{}[REDACTED]{}\n", header, quote, quote));
+        }
+
+        /// Secrets under secret-looking JSON keys are replaced whole, escaped quotes included,
+        /// and the document stays valid JSON.
+        #[test]
+        fn json_secret_values_are_replaced_whole(secret in "\\PC{1,64}", key in "(db_)?(password|secret|token|api_key)") {
+            let mut obj = serde_json::Map::new();
+            obj.insert(key.clone(), json!(secret));
+            obj.insert("note".to_string(), json!("ok"));
+            let doc = Value::Object(obj).to_string();
+            let out: Value = serde_json::from_str(&redact(&doc)).expect("valid JSON after redaction");
+            prop_assert_eq!(&out[key.as_str()], "[REDACTED]");
+            prop_assert_eq!(&out["note"], "ok");
+        }
+    }
+
+    #[test]
+    fn redacted_values_are_detected() {
+        let mut v = json!({"inputs": {"q": "hi", "headers": ["x-api-key: abc123"]}});
+        assert!(!is_redacted(&v));
+        assert_eq!(redact_value(&mut v), 1);
+        assert!(is_redacted(&v));
+    }
+
+    /// Budgets are for `--release` on a laptop; slower CI boxes set `ONE_ENGINE_BUDGET_SCALE`.
+    fn budget(ms: u64) -> std::time::Duration {
+        let scale: f64 = std::env::var("ONE_ENGINE_BUDGET_SCALE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1.0);
+        std::time::Duration::from_millis((ms as f64 * scale) as u64)
+    }
+
+    /// About 1 MB of thread text with a secret on every line.
+    #[test]
+    #[ignore = "performance budget; run with `make bench`"]
+    fn budget_redact_1mb() {
+        let text: String = (0..8_000)
+            .map(|i| format!("{{\"role\":\"user\",\"content\":\"step {i} x-api-key: k{i}abcdef sk-{i:0>24}\"}}\n"))
+            .collect();
+        assert!(text.len() > 800_000);
+        let t = std::time::Instant::now();
+        let (out, n) = redact_counted(&text);
+        let took = t.elapsed();
+        assert!(n >= 16_000);
+        assert!(!out.contains("sk-0"));
+        assert!(
+            took < budget(300),
+            "redacting {} bytes took {:?}",
+            text.len(),
+            took
+        );
+    }
+}
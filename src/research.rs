@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::{fs, io::Read, path::Path, time::SystemTime};
 use walkdir::WalkDir;
 
+/// Excluded by `update_index` unless the caller passes its own `exclude` list.
+pub const DEFAULT_EXCLUDES: &[&str] = &[".git/**", "target/**", "node_modules/**", "runs/**"];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ResearchArtifact {
     pub id: String,
@@ -17,6 +21,9 @@ pub struct ResearchArtifact {
 
 fn kind_for(path: &Path) -> String {
     let p = path.to_string_lossy().to_lowercase();
+    if p.contains("research/sources/web/") {
+        return "web".into();
+    }
     if p.contains("/prompts/") {
         return "prompt".into();
     }
@@ -61,6 +68,50 @@ fn ts_from(path: &Path) -> String {
     }
 }
 
+fn is_indexed_ext(path: &Path) -> bool {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    matches!(ext, "md" | "json" | "jsonl" | "yaml" | "yml")
+}
+
+fn artifact_for(
+    root: &Path,
+    path: &Path,
+    buf: &[u8],
+    checksum: String,
+    branch: Option<String>,
+) -> ResearchArtifact {
+    let ts = ts_from(path);
+    let ttl = if path.to_string_lossy().contains("trace/golden/") {
+        0
+    } else {
+        14 * 24 * 3600
+    };
+    let rel = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string();
+    let kind = kind_for(path);
+    // tags from simple front-matter if present
+    let mut tags = front_matter_tags(buf);
+    if tags.is_empty() && kind == "policy" {
+        tags.push("policy".into());
+    }
+    let id = format!("{}#{}", rel, checksum);
+    let git_commit = git_last_commit(path).ok();
+    ResearchArtifact {
+        id,
+        kind,
+        path: rel,
+        ts,
+        ttl,
+        tags,
+        checksum,
+        git_commit,
+        git_branch: branch,
+    }
+}
+
 pub fn build_index(root: &Path) -> anyhow::Result<Vec<ResearchArtifact>> {
     let mut out = Vec::new();
     let branch = git_branch().ok();
@@ -69,8 +120,7 @@ pub fn build_index(root: &Path) -> anyhow::Result<Vec<ResearchArtifact>> {
             continue;
         }
         let path = entry.path();
-        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-        if !matches!(ext, "md" | "json" | "jsonl" | "yaml" | "yml") {
+        if !is_indexed_ext(path) {
             continue;
         }
         // read file
@@ -78,38 +128,159 @@ pub fn build_index(root: &Path) -> anyhow::Result<Vec<ResearchArtifact>> {
         let mut buf = Vec::new();
         f.read_to_end(&mut buf)?;
         let checksum = format!("{:08x}", adler32(&buf));
-        let ts = ts_from(path);
-        let ttl = if path.to_string_lossy().contains("trace/golden/") {
-            0
-        } else {
-            14 * 24 * 3600
-        };
-        let rel = path
-            .strip_prefix(root)
+        out.push(artifact_for(root, path, &buf, checksum, branch.clone()));
+    }
+    Ok(out)
+}
+
+/// `*` within a path segment, `**` across segments, `?` for one char.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    glob_regex(pattern).is_some_and(|r| r.is_match(path))
+}
+
+/// The anchored regex behind [`glob_match`], for callers that test many paths.
+pub fn glob_regex(pattern: &str) -> Option<regex::Regex> {
+    let mut re = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    re.push_str("(?:.*/)?");
+                } else {
+                    re.push_str(".*");
+                }
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    regex::Regex::new(&re).ok()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IndexOptions {
+    /// Relative-path globs to index (empty: every md/json/jsonl/yaml/yml file).
+    pub include: Vec<String>,
+    /// Relative-path globs to skip (`None`: [`DEFAULT_EXCLUDES`]).
+    pub exclude: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexStats {
+    pub scanned: usize,
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub hashed: usize,
+}
+
+/// Rebuild the index, reusing `prev` entries whose file is unchanged: same mtime skips the
+/// read entirely, same checksum skips the `git log` lookup.
+pub fn update_index(
+    root: &Path,
+    prev: &[ResearchArtifact],
+    opts: &IndexOptions,
+) -> anyhow::Result<(Vec<ResearchArtifact>, IndexStats)> {
+    let excludes: Vec<regex::Regex> = match &opts.exclude {
+        Some(globs) => globs.iter().filter_map(|g| glob_regex(g)).collect(),
+        None => DEFAULT_EXCLUDES
+            .iter()
+            .filter_map(|g| glob_regex(g))
+            .collect(),
+    };
+    let includes: Vec<regex::Regex> = opts.include.iter().filter_map(|g| glob_regex(g)).collect();
+    let rel_of = |path: &Path| {
+        path.strip_prefix(root)
             .unwrap_or(path)
             .to_string_lossy()
-            .to_string();
-        let kind = kind_for(path);
-        // tags from simple front-matter if present
-        let mut tags = front_matter_tags(&buf);
-        if tags.is_empty() && kind == "policy" {
-            tags.push("policy".into());
+            .replace('\\', "/")
+    };
+    let mut by_path: HashMap<&str, &ResearchArtifact> =
+        prev.iter().map(|a| (a.path.as_str(), a)).collect();
+    let branch = git_branch().ok();
+    let mut stats = IndexStats::default();
+    let mut out = Vec::new();
+
+    // Excluded directories are pruned, not descended: `target/**` also matches `target/`.
+    let walk = WalkDir::new(root).into_iter().filter_entry(|e| {
+        e.depth() == 0 || !e.file_type().is_dir() || {
+            let dir = format!("{}/", rel_of(e.path()));
+            !excludes.iter().any(|r| r.is_match(&dir))
+        }
+    });
+    for entry in walk.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() || !is_indexed_ext(entry.path()) {
+            continue;
+        }
+        let path = entry.path();
+        let rel = rel_of(path);
+        if excludes.iter().any(|r| r.is_match(&rel)) {
+            continue;
+        }
+        if !includes.is_empty() && !includes.iter().any(|r| r.is_match(&rel)) {
+            continue;
+        }
+        stats.scanned += 1;
+        let old = by_path.remove(rel.as_str());
+        let ts = ts_from(path);
+        if let Some(old) = old.filter(|o| o.ts == ts) {
+            stats.unchanged += 1;
+            out.push(old.clone());
+            continue;
+        }
+        let mut buf = Vec::new();
+        fs::File::open(path)?.read_to_end(&mut buf)?;
+        stats.hashed += 1;
+        let checksum = format!("{:08x}", adler32(&buf));
+        match old {
+            Some(o) if o.checksum == checksum => {
+                stats.unchanged += 1;
+                out.push(ResearchArtifact { ts, ..o.clone() });
+            }
+            Some(_) => {
+                stats.updated += 1;
+                out.push(artifact_for(root, path, &buf, checksum, branch.clone()));
+            }
+            None => {
+                stats.added += 1;
+                out.push(artifact_for(root, path, &buf, checksum, branch.clone()));
+            }
         }
-        let id = format!("{}#{}", rel, checksum);
-        let git_commit = git_last_commit(path).ok();
-        out.push(ResearchArtifact {
-            id,
-            kind,
-            path: rel,
-            ts,
-            ttl,
-            tags,
-            checksum,
-            git_commit,
-            git_branch: branch.clone(),
-        });
     }
-    Ok(out)
+    stats.removed = by_path.len();
+    out.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((out, stats))
+}
+
+pub fn read_index(path: &Path) -> Vec<ResearchArtifact> {
+    fs::read_to_string(path)
+        .map(|s| {
+            s.lines()
+                .filter(|l| !l.trim().is_empty())
+                .filter_map(|l| serde_json::from_str(l).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Write `items` as JSONL via a temp file + rename, so readers never see a partial index.
+pub fn write_index(path: &Path, items: &[ResearchArtifact]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        crate::storage::create_dir_all(parent)?;
+    }
+    let mut body = String::new();
+    for a in items {
+        body.push_str(&serde_json::to_string(a)?);
+        body.push('\n');
+    }
+    crate::atomic::write(path, body)?;
+    Ok(())
 }
 
 pub fn build_index_multi(roots: &[std::path::PathBuf]) -> anyhow::Result<Vec<ResearchArtifact>> {
@@ -191,3 +362,20 @@ fn git_last_commit(path: &Path) -> anyhow::Result<String> {
         anyhow::bail!("git log failed")
     }
 }
+
+/// Add or replace the artifact for one file (relative to `root`) in the index at `index_path`.
+pub fn upsert_index(
+    index_path: &Path,
+    root: &Path,
+    file: &Path,
+) -> anyhow::Result<ResearchArtifact> {
+    let buf = crate::storage::read(file)?;
+    let checksum = format!("{:08x}", adler32(&buf));
+    let artifact = artifact_for(root, file, &buf, checksum, git_branch().ok());
+    let mut items = read_index(index_path);
+    items.retain(|a| a.path != artifact.path);
+    items.push(artifact.clone());
+    items.sort_by(|a, b| a.path.cmp(&b.path));
+    write_index(index_path, &items)?;
+    Ok(artifact)
+}
@@ -0,0 +1,512 @@
+//! Where artifact writes go when `META3_ROOT` is mounted read-only.
+//!
+//! [`init`] runs at startup: it writes and removes a probe file under `META3_ROOT/runs`. If that
+//! fails, writes under the root are redirected according to the `storage` policy:
+//!
+//! ```yaml
+//! storage:
+//!   fallback: scratch                  # scratch (default) | memory | none
+//!   scratch_dir: /var/tmp/one-engine   # default <tmp>/one-engine-scratch
+//!   memory_max_bytes: 67108864         # memory mode: oldest files are dropped past this
+//! ```
+//!
+//! `ONE_ENGINE_STORAGE_FALLBACK` and `ONE_ENGINE_SCRATCH_DIR` override the policy.
+//!
+//! - [`Mode::Scratch`]: `<root>/<rel>` is written to `<scratch_dir>/<rel>`. Appending to a file
+//!   that only exists under the root copies it over first, so threads keep their history.
+//! - [`Mode::Memory`]: receipts (`runs/receipts/…`) are kept in memory for the life of the
+//!   process; every other write under the root fails with a "read-only" error.
+//! - [`Mode::ReadOnly`]: every write under the root fails with that error (`fallback: none`,
+//!   or a scratch dir that isn't writable either).
+//!
+//! [`crate::atomic`] applies the redirect to every write and append; directories are made with
+//! [`create_dir_all`] and files copied with [`copy`]. Readers that must see redirected files go
+//! through [`read`], [`resolve`], [`exists`] or [`entries`]; files under the root that were
+//! never rewritten are still read from it.
+
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Writes go to `META3_ROOT` as usual.
+    #[default]
+    Writable,
+    Scratch,
+    Memory,
+    #[serde(rename = "read-only")]
+    ReadOnly,
+}
+
+impl Mode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Writable => "writable",
+            Mode::Scratch => "scratch",
+            Mode::Memory => "memory",
+            Mode::ReadOnly => "read-only",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Fallback {
+    #[default]
+    Scratch,
+    Memory,
+    None,
+}
+
+impl Fallback {
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "scratch" => Some(Fallback::Scratch),
+            "memory" => Some(Fallback::Memory),
+            "none" | "off" => Some(Fallback::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StorageSection {
+    #[serde(default)]
+    fallback: Fallback,
+    #[serde(default)]
+    scratch_dir: Option<PathBuf>,
+    #[serde(default)]
+    memory_max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoliciesStorage {
+    #[serde(default)]
+    storage: Option<StorageSection>,
+}
+
+const DEFAULT_MEMORY_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+fn load_config() -> StorageSection {
+    let path = std::env::var("ONE_ENGINE_POLICIES_FILE")
+        .unwrap_or_else(|_| "config/policies.yaml".to_string());
+    let mut cfg = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_yaml::from_str::<PoliciesStorage>(&raw).ok())
+        .and_then(|p| p.storage)
+        .unwrap_or_default();
+    if let Some(f) = std::env::var("ONE_ENGINE_STORAGE_FALLBACK")
+        .ok()
+        .and_then(|s| Fallback::parse(&s))
+    {
+        cfg.fallback = f;
+    }
+    if let Ok(dir) = std::env::var("ONE_ENGINE_SCRATCH_DIR") {
+        if !dir.trim().is_empty() {
+            cfg.scratch_dir = Some(PathBuf::from(dir));
+        }
+    }
+    cfg
+}
+
+/// Storage mode and why, for `/healthz` and `/version`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StorageStatus {
+    pub mode: Mode,
+    pub root: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scratch_dir: Option<String>,
+    /// Why the root is not written to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Memory mode: receipt files held and their size.
+    pub memory_files: usize,
+    pub memory_bytes: u64,
+}
+
+struct State {
+    mode: Mode,
+    root: PathBuf,
+    scratch: Option<PathBuf>,
+    reason: Option<String>,
+    memory_max_bytes: u64,
+}
+
+static STATE: OnceCell<State> = OnceCell::new();
+
+#[derive(Default)]
+struct Memory {
+    files: BTreeMap<PathBuf, (SystemTime, Vec<u8>)>,
+    bytes: u64,
+}
+
+static MEMORY: Lazy<Mutex<Memory>> = Lazy::new(|| Mutex::new(Memory::default()));
+
+fn meta3_root() -> PathBuf {
+    std::env::var("META3_ROOT")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("."))
+}
+
+/// Create `dir`, then write and remove a probe file in it.
+fn probe(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let file = dir.join(format!(".write-probe-{}", std::process::id()));
+    fs::write(&file, b"ok")?;
+    fs::remove_file(&file)
+}
+
+fn decide(root: &Path, cfg: &StorageSection) -> State {
+    let mut state = State {
+        mode: Mode::Writable,
+        root: root.to_path_buf(),
+        scratch: None,
+        reason: None,
+        memory_max_bytes: cfg.memory_max_bytes.unwrap_or(DEFAULT_MEMORY_MAX_BYTES),
+    };
+    let Err(e) = probe(&root.join("runs")) else {
+        return state;
+    };
+    state.reason = Some(format!(
+        "{} is not writable: {}",
+        root.join("runs").display(),
+        e
+    ));
+    state.mode = match cfg.fallback {
+        Fallback::Scratch => {
+            let dir = cfg
+                .scratch_dir
+                .clone()
+                .unwrap_or_else(|| std::env::temp_dir().join("one-engine-scratch"));
+            match probe(&dir.join("runs")) {
+                Ok(()) => {
+                    state.scratch = Some(dir);
+                    Mode::Scratch
+                }
+                Err(e) => {
+                    state.reason = Some(format!(
+                        "{}; scratch dir {} is not writable either: {}",
+                        state.reason.take().unwrap_or_default(),
+                        dir.display(),
+                        e
+                    ));
+                    Mode::ReadOnly
+                }
+            }
+        }
+        Fallback::Memory => Mode::Memory,
+        Fallback::None => Mode::ReadOnly,
+    };
+    state
+}
+
+/// Probe `META3_ROOT` and pick the storage mode (once; later calls return the same status).
+pub fn init() -> StorageStatus {
+    let state = STATE.get_or_init(|| decide(&meta3_root(), &load_config()));
+    if state.mode != Mode::Writable {
+        tracing::warn!(
+            "storage mode {}: {}",
+            state.mode.as_str(),
+            state.reason.as_deref().unwrap_or("")
+        );
+    }
+    status()
+}
+
+pub fn mode() -> Mode {
+    STATE.get().map_or(Mode::Writable, |s| s.mode)
+}
+
+pub fn status() -> StorageStatus {
+    let Some(state) = STATE.get() else {
+        return StorageStatus {
+            root: meta3_root().display().to_string(),
+            ..Default::default()
+        };
+    };
+    let mem = MEMORY.lock().unwrap_or_else(|e| e.into_inner());
+    StorageStatus {
+        mode: state.mode,
+        root: state.root.display().to_string(),
+        scratch_dir: state.scratch.as_ref().map(|d| d.display().to_string()),
+        reason: state.reason.clone(),
+        memory_files: mem.files.len(),
+        memory_bytes: mem.bytes,
+    }
+}
+
+/// `path` relative to `root`, when it is under it. A relative root (`.`) matches relative paths.
+fn relative_to(root: &Path, path: &Path) -> Option<PathBuf> {
+    let plain = |p: &Path| -> PathBuf {
+        p.components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .collect()
+    };
+    let (root, path) = (plain(root), plain(path));
+    if root.as_os_str().is_empty() {
+        return path.is_relative().then_some(path);
+    }
+    path.strip_prefix(&root).ok().map(|p| p.to_path_buf())
+}
+
+fn is_receipt_path(rel: &Path) -> bool {
+    rel.starts_with(Path::new("runs").join("receipts"))
+}
+
+fn read_only(state: &State) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!(
+            "{} is read-only (storage mode {})",
+            state.root.display(),
+            state.mode.as_str()
+        ),
+    )
+}
+
+/// Where a write to `path` goes.
+pub(crate) enum Target {
+    Disk(PathBuf),
+    Memory(PathBuf),
+}
+
+/// The destination of a write to `path`; `append` copies a file that only exists under the
+/// root into the scratch dir first.
+pub(crate) fn target(path: &Path, append: bool) -> io::Result<Target> {
+    let Some(state) = STATE.get().filter(|s| s.mode != Mode::Writable) else {
+        return Ok(Target::Disk(path.to_path_buf()));
+    };
+    let Some(rel) = relative_to(&state.root, path) else {
+        return Ok(Target::Disk(path.to_path_buf()));
+    };
+    match (state.mode, &state.scratch) {
+        (Mode::Scratch, Some(scratch)) => {
+            let dest = scratch.join(&rel);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if append && !dest.exists() && path.is_file() {
+                fs::copy(path, &dest)?;
+            }
+            Ok(Target::Disk(dest))
+        }
+        (Mode::Memory, _) if is_receipt_path(&rel) => Ok(Target::Memory(rel)),
+        _ => Err(read_only(state)),
+    }
+}
+
+/// A path readers and writers of `path` can share: its scratch copy (made now if missing) in
+/// scratch mode, else `path` itself.
+pub fn writable_path(path: &Path) -> PathBuf {
+    match target(path, true) {
+        Ok(Target::Disk(p)) => p,
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Make the directory `path` where writes under it will land: its scratch counterpart in
+/// scratch mode, nothing for receipts in memory mode. Returns the directory made.
+pub fn create_dir_all(path: &Path) -> io::Result<PathBuf> {
+    match target(path, false)? {
+        Target::Disk(dir) => {
+            fs::create_dir_all(&dir)?;
+            Ok(dir)
+        }
+        Target::Memory(_) => Ok(path.to_path_buf()),
+    }
+}
+
+/// Copy the file at `from` (read wherever it lives) to `to` through [`crate::atomic::write`].
+pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
+    let data = read(from)?;
+    crate::atomic::write(to, &data)?;
+    Ok(data.len() as u64)
+}
+
+pub(crate) fn memory_write(rel: PathBuf, contents: &[u8], append: bool) {
+    let max = STATE
+        .get()
+        .map_or(DEFAULT_MEMORY_MAX_BYTES, |s| s.memory_max_bytes);
+    let mut mem = MEMORY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut data = match mem.files.remove(&rel) {
+        Some((_, old)) => {
+            mem.bytes -= old.len() as u64;
+            if append {
+                old
+            } else {
+                Vec::new()
+            }
+        }
+        None => Vec::new(),
+    };
+    data.extend_from_slice(contents);
+    mem.bytes += data.len() as u64;
+    mem.files.insert(rel, (SystemTime::now(), data));
+    while mem.bytes > max && mem.files.len() > 1 {
+        let Some(oldest) = mem
+            .files
+            .iter()
+            .min_by_key(|(_, (t, _))| *t)
+            .map(|(p, _)| p.clone())
+        else {
+            break;
+        };
+        if let Some((_, d)) = mem.files.remove(&oldest) {
+            mem.bytes -= d.len() as u64;
+        }
+    }
+}
+
+fn memory_key(path: &Path) -> Option<PathBuf> {
+    let state = STATE.get().filter(|s| s.mode == Mode::Memory)?;
+    relative_to(&state.root, path)
+}
+
+/// The scratch copy of `path` when there is one, else `path`.
+pub fn resolve(path: &Path) -> PathBuf {
+    if let Some(state) = STATE.get().filter(|s| s.mode == Mode::Scratch) {
+        if let (Some(rel), Some(scratch)) = (relative_to(&state.root, path), &state.scratch) {
+            let copy = scratch.join(rel);
+            if copy.exists() {
+                return copy;
+            }
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Contents of `path`: from memory, the scratch copy, or the root.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    if let Some(key) = memory_key(path) {
+        let mem = MEMORY.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, data)) = mem.files.get(&key) {
+            return Ok(data.clone());
+        }
+    }
+    fs::read(resolve(path))
+}
+
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// [`read_to_string`] on the blocking pool.
+pub async fn read_to_string_async(path: impl AsRef<Path>) -> io::Result<String> {
+    let path = path.as_ref().to_path_buf();
+    tokio::task::spawn_blocking(move || read_to_string(&path))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Whether `path` exists as a file or directory in memory, in the scratch dir, or under the root.
+pub fn exists(path: &Path) -> bool {
+    if let Some(key) = memory_key(path) {
+        let mem = MEMORY.lock().unwrap_or_else(|e| e.into_inner());
+        if mem.files.keys().any(|k| k.starts_with(&key)) {
+            return true;
+        }
+    }
+    resolve(path).exists()
+}
+
+/// Modification time of the file at `path`, wherever it lives.
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    if let Some(key) = memory_key(path) {
+        let mem = MEMORY.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((t, _)) = mem.files.get(&key) {
+            return Some(*t);
+        }
+    }
+    fs::metadata(resolve(path)).ok()?.modified().ok()
+}
+
+/// Directories to list for the contents of `dir`: its scratch counterpart (newer files), then
+/// `dir` itself.
+pub fn overlays(dir: &Path) -> Vec<PathBuf> {
+    let copy = resolve(dir);
+    if copy != dir {
+        vec![copy, dir.to_path_buf()]
+    } else {
+        vec![dir.to_path_buf()]
+    }
+}
+
+/// Names of the entries directly under `dir`, wherever they live, without duplicates.
+pub fn entries(dir: &Path) -> Vec<String> {
+    let mut names = memory_entries(dir);
+    for d in overlays(dir) {
+        if let Ok(rd) = fs::read_dir(&d) {
+            names.extend(
+                rd.flatten()
+                    .map(|e| e.file_name().to_string_lossy().into_owned()),
+            );
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    names.retain(|n| seen.insert(n.clone()));
+    names
+}
+
+/// Names of the entries directly under `dir` that only exist in memory.
+pub fn memory_entries(dir: &Path) -> Vec<String> {
+    let Some(key) = memory_key(dir) else {
+        return Vec::new();
+    };
+    let mem = MEMORY.lock().unwrap_or_else(|e| e.into_inner());
+    let mut names: Vec<String> = mem
+        .files
+        .keys()
+        .filter_map(|k| k.strip_prefix(&key).ok()?.components().next())
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_root_falls_back_to_scratch_or_nothing() {
+        let base = std::env::temp_dir()
+            .join("one-engine-storage")
+            .join(uuid::Uuid::new_v4().to_string());
+        // A file where `runs/` should be makes the root unwritable without needing permissions.
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("runs"), b"").unwrap();
+        let cfg = StorageSection {
+            scratch_dir: Some(base.join("scratch")),
+            ..Default::default()
+        };
+        let state = decide(&base, &cfg);
+        assert_eq!(state.mode, Mode::Scratch);
+        assert!(state.reason.unwrap().contains("not writable"));
+
+        let cfg = StorageSection {
+            fallback: Fallback::None,
+            ..Default::default()
+        };
+        assert_eq!(decide(&base, &cfg).mode, Mode::ReadOnly);
+        assert_eq!(decide(&base.join("scratch"), &cfg).mode, Mode::Writable);
+
+        assert_eq!(
+            relative_to(Path::new("."), Path::new("./runs/receipts/r-1")),
+            Some(PathBuf::from("runs/receipts/r-1"))
+        );
+        assert_eq!(
+            relative_to(&base, &base.join("users/u/threads/t.jsonl")),
+            Some(PathBuf::from("users/u/threads/t.jsonl"))
+        );
+        assert_eq!(relative_to(&base, Path::new("/elsewhere/x")), None);
+        assert!(is_receipt_path(Path::new("runs/receipts/r-1/request.json")));
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,120 @@
+//! Tail parsing for append-only JSONL files (threads, api_trace, telemetry).
+//!
+//! Readers look at the last `max_bytes` of a file. The window usually starts in the middle
+//! of a line, and possibly in the middle of a UTF-8 sequence, so [`split_tail`] drops that
+//! fragment before decoding. Callers read from one byte *before* the window so a window that
+//! starts exactly on a line boundary keeps its first line.
+//!
+//! Files are tailed with the streaming [`crate::jsonl`] reader; [`split_tail`] is the
+//! in-memory form of the same rules, for buffers already in hand (and the fuzz target).
+
+/// Non-blank lines of `buf`, at most the last `limit`.
+///
+/// With `truncated`, everything up to and including the first `\n` is a partial line and is
+/// dropped; a buffer with no `\n` at all yields nothing. Invalid UTF-8 is replaced, never an
+/// error.
+pub fn split_tail(buf: &[u8], truncated: bool, limit: usize) -> Vec<String> {
+    let buf = if truncated {
+        match buf.iter().position(|b| *b == b'\n') {
+            Some(i) => &buf[i + 1..],
+            None => return Vec::new(),
+        }
+    } else {
+        buf
+    };
+    let text = String::from_utf8_lossy(buf);
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let skip = lines.len().saturating_sub(limit);
+    lines[skip..].iter().map(|l| l.to_string()).collect()
+}
+
+/// Offset to read from: one byte before the last `max_bytes`, so [`split_tail`] can tell
+/// whether the window starts on a line boundary.
+pub fn window_start(len: u64, max_bytes: u64) -> (u64, bool) {
+    if len > max_bytes {
+        (len - max_bytes - 1, true)
+    } else {
+        (0, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn jsonl(lines: &[String]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for l in lines {
+            out.extend_from_slice(l.as_bytes());
+            out.push(b'\n');
+        }
+        out
+    }
+
+    fn json_line() -> impl Strategy<Value = String> {
+        (any::<u32>(), "\\PC{0,40}")
+            .prop_map(|(n, s)| serde_json::json!({"n": n, "content": s}).to_string())
+    }
+
+    proptest! {
+        #[test]
+        fn never_panics_on_arbitrary_bytes(
+            buf in proptest::collection::vec(any::<u8>(), 0..512),
+            truncated in any::<bool>(),
+            limit in 0usize..64,
+        ) {
+            let lines = split_tail(&buf, truncated, limit);
+            prop_assert!(lines.len() <= limit);
+            prop_assert!(lines.iter().all(|l| !l.contains('\n') && !l.trim().is_empty()));
+        }
+
+        /// Wherever the window starts, every returned line is a whole line of the file.
+        #[test]
+        fn cut_windows_only_yield_whole_lines(
+            lines in proptest::collection::vec(json_line(), 1..40),
+            max_bytes in 0u64..2048,
+            limit in 1usize..50,
+        ) {
+            let file = jsonl(&lines);
+            let (start, truncated) = window_start(file.len() as u64, max_bytes);
+            let got = split_tail(&file[start as usize..], truncated, limit);
+            for l in &got {
+                prop_assert!(lines.contains(l), "partial line {:?}", l);
+                prop_assert!(serde_json::from_str::<serde_json::Value>(l).is_ok());
+            }
+            // The newest line survives whenever it fits in the window.
+            let last = lines.last().unwrap();
+            if (last.len() as u64) < max_bytes {
+                prop_assert_eq!(got.last(), Some(last));
+            }
+        }
+
+        #[test]
+        fn untruncated_tail_is_the_last_lines(
+            lines in proptest::collection::vec(json_line(), 0..40),
+            limit in 0usize..50,
+        ) {
+            let got = split_tail(&jsonl(&lines), false, limit);
+            let skip = lines.len().saturating_sub(limit);
+            prop_assert_eq!(got, lines[skip..].to_vec());
+        }
+    }
+
+    #[test]
+    fn window_on_a_line_boundary_keeps_the_first_line() {
+        let file = b"{\"a\":1}\n{\"b\":2}\n";
+        let (start, truncated) = window_start(file.len() as u64, 8);
+        assert_eq!(
+            split_tail(&file[start as usize..], truncated, 10),
+            vec!["{\"b\":2}"]
+        );
+    }
+
+    #[test]
+    fn window_inside_a_multibyte_char_is_not_an_error() {
+        let file = "{\"m\":\"héllo\"}\n{\"m\":\"ok\"}\n".as_bytes();
+        // Byte 8 is the second byte of `é`.
+        assert_eq!(split_tail(&file[8..], true, 10), vec!["{\"m\":\"ok\"}"]);
+    }
+}